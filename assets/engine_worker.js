@@ -0,0 +1,10 @@
+// The engine's web worker, which loads the app's module and runs the searches it is sent.
+import init, { engine_worker_search } from "../chess_computer.js";
+
+// Requests sent while the module loads wait for it
+const ready = init();
+
+self.onmessage = async (event) => {
+  await ready;
+  engine_worker_search(event.data, (response) => self.postMessage(response));
+};
@@ -0,0 +1,2214 @@
+//! The board and the rules of chess and its variants: the [ChessBoard] with its pieces, the
+//! squares and sizes of boards, and the moves which can be made on them.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::castling_rights::CastlingRights;
+use crate::fen::Fen;
+
+use self::r#move::Move;
+
+pub mod fairy;
+pub mod game_log;
+pub mod r#move;
+mod piece;
+pub mod snapshot;
+
+/// The number of ranks and files of a standard board, which is also the most ranks a board has.
+pub const BOARD_SIZE: usize = 8;
+
+/// The most files a board has, which are named from a to l.
+pub const MAX_FILES: usize = 12;
+
+/// The number of ranks and files of a board, such as 8 by 10 for Capablanca chess or 6 by 5 for
+/// minichess.
+///
+/// Squares are indexed in a grid of [BOARD_SIZE] ranks and [MAX_FILES] files, which a smaller
+/// board fills from the bottom left. So rank 0 of a [BoardPosition] is always the 8th rank and
+/// the squares keep their names in algebraic notation on every board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardSize {
+    ranks: usize,
+    files: usize,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize {
+            ranks: BOARD_SIZE,
+            files: BOARD_SIZE,
+        }
+    }
+}
+
+impl BoardSize {
+    /// Creates a board size, or returns None if the board would not fit in the grid of squares.
+    pub fn new(ranks: usize, files: usize) -> Option<Self> {
+        ((1..=BOARD_SIZE).contains(&ranks) && (1..=MAX_FILES).contains(&files))
+            .then_some(BoardSize { ranks, files })
+    }
+
+    pub fn ranks(&self) -> usize {
+        self.ranks
+    }
+
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    /// Returns whether this is the size of a standard board.
+    pub fn is_standard(&self) -> bool {
+        *self == BoardSize::default()
+    }
+
+    /// Returns the index of the top rank of the board.
+    pub fn top_rank(&self) -> usize {
+        BOARD_SIZE - self.ranks
+    }
+
+    /// Returns whether the given square is on the board.
+    pub fn contains(&self, position: &BoardPosition) -> bool {
+        position.rank >= self.top_rank() && position.file < self.files
+    }
+
+    /// Returns the squares of the board from the top left, rank by rank.
+    pub fn positions(&self) -> impl Iterator<Item = BoardPosition> {
+        let files = self.files;
+        (self.top_rank()..BOARD_SIZE)
+            .flat_map(move |rank| (0..files).map(move |file| BoardPosition::new(rank, file)))
+    }
+
+    /// Returns the rank the pawns of the given color promote on.
+    pub fn last_rank(&self, color: &PieceColor) -> usize {
+        match color {
+            PieceColor::White => self.top_rank(),
+            PieceColor::Black => BOARD_SIZE - 1,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Component))]
+pub enum PieceColor {
+    White,
+    Black,
+}
+
+impl PieceColor {
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::White => PieceColor::Black,
+            PieceColor::Black => PieceColor::White,
+        }
+    }
+}
+
+impl fmt::Display for PieceColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PieceColor::White => write!(f, "White"),
+            PieceColor::Black => write!(f, "Black"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Component))]
+pub enum PieceType {
+    King,
+    Queen,
+    Bishop,
+    Knight,
+    Rook,
+    Pawn,
+    /// The [fairy piece](fairy) registered with the given index.
+    #[strum(disabled)]
+    Fairy(u8),
+}
+
+impl fmt::Display for PieceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PieceType::King => write!(f, "King"),
+            PieceType::Queen => write!(f, "Queen"),
+            PieceType::Bishop => write!(f, "Bishop"),
+            PieceType::Knight => write!(f, "Knight"),
+            PieceType::Rook => write!(f, "Rook"),
+            PieceType::Pawn => write!(f, "Pawn"),
+            PieceType::Fairy(index) => match fairy::fairy_piece(*index) {
+                Some(piece) => write!(f, "{}", piece.name),
+                None => write!(f, "Fairy {}", index),
+            },
+        }
+    }
+}
+
+impl PieceType {
+    /// Returns the index of a standard piece, from 0 for the king to 5 for the pawn in the order
+    /// of the variants, or None for a fairy piece.
+    pub fn index(&self) -> Option<usize> {
+        match self {
+            PieceType::King => Some(0),
+            PieceType::Queen => Some(1),
+            PieceType::Bishop => Some(2),
+            PieceType::Knight => Some(3),
+            PieceType::Rook => Some(4),
+            PieceType::Pawn => Some(5),
+            PieceType::Fairy(_) => None,
+        }
+    }
+
+    /// Returns the English letter of the piece in FEN and standard algebraic notation, which is P
+    /// for pawns.
+    pub fn letter(&self) -> char {
+        match self {
+            PieceType::King => 'K',
+            PieceType::Queen => 'Q',
+            PieceType::Bishop => 'B',
+            PieceType::Knight => 'N',
+            PieceType::Rook => 'R',
+            PieceType::Pawn => 'P',
+            PieceType::Fairy(index) => fairy::fairy_piece(*index).map_or('?', |piece| piece.letter),
+        }
+    }
+
+    /// Returns the piece with the given uppercase English letter, including registered fairy
+    /// pieces.
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'K' => Some(PieceType::King),
+            'Q' => Some(PieceType::Queen),
+            'B' => Some(PieceType::Bishop),
+            'N' => Some(PieceType::Knight),
+            'R' => Some(PieceType::Rook),
+            'P' => Some(PieceType::Pawn),
+            _ => fairy::fairy_piece_with_letter(letter),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GameEndStatus {
+    Checkmate,
+    Resignation,
+    Stalemate,
+    DeadPosition,
+    FlagFall,
+    Agreement,
+    /// A king was captured, which ends games of [Variant::Duck].
+    KingCapture,
+    /// A player lost all their pieces, which wins games of [Variant::Antichess].
+    AllPiecesLost,
+    /// A draw claimed after fifty moves each without a capture or a pawn move.
+    FiftyMoves,
+    /// A draw claimed in a position which has occurred three times.
+    Repetition,
+}
+
+/// The rules a game is played by.
+#[derive(Clone, Copy, Debug, Default, EnumIter, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Duck chess, where after each move the player moves the duck, a neutral piece which blocks
+    /// its square, to an empty square. There is no check, so a game is won by capturing the king,
+    /// and a player with no legal moves wins. The duck is written as `*` in the [Fen] of a
+    /// position.
+    Duck,
+    /// Antichess, where captures are forced and the king is an ordinary piece, with no check or
+    /// castling. A player wins by losing all their pieces or having no legal moves.
+    Antichess,
+}
+
+/// A square of the board, which is saved as its name in algebraic notation.
+#[derive(PartialEq, Debug, Copy, Clone, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Component))]
+#[serde(into = "String", try_from = "String")]
+pub struct BoardPosition {
+    rank: usize,
+    file: usize,
+}
+
+impl BoardPosition {
+    pub fn new(rank: usize, file: usize) -> Self {
+        if (rank >= BOARD_SIZE) | (file >= MAX_FILES) {
+            panic!("Invalid rank or file value: {}, {}", rank, file)
+        }
+        BoardPosition { rank, file }
+    }
+
+    /// Creates a [BoardPosition] from the name of a square in algebraic notation, e.g. "e4".
+    pub fn from_algebraic(square: &str) -> Option<Self> {
+        let mut chars = square.chars();
+        let file = chars.next().and_then(|char| "abcdefghijkl".find(char))?;
+        let rank = chars
+            .next()
+            .and_then(|char| char.to_digit(10))
+            .filter(|rank| (1..=BOARD_SIZE as u32).contains(rank))?;
+        if chars.next().is_some() {
+            return None;
+        }
+        Some(BoardPosition::new(BOARD_SIZE - rank as usize, file))
+    }
+
+    /// Returns the name of the square in algebraic notation, e.g. "e4".
+    pub fn to_algebraic(self) -> String {
+        format!(
+            "{}{}",
+            char::from(b'a' + self.file as u8),
+            BOARD_SIZE - self.rank
+        )
+    }
+
+    pub fn rank(&self) -> &usize {
+        &self.rank
+    }
+
+    pub fn file(&self) -> &usize {
+        &self.file
+    }
+}
+
+impl From<BoardPosition> for String {
+    fn from(position: BoardPosition) -> Self {
+        position.to_algebraic()
+    }
+}
+
+impl TryFrom<String> for BoardPosition {
+    type Error = String;
+
+    fn try_from(square: String) -> Result<Self, Self::Error> {
+        BoardPosition::from_algebraic(&square).ok_or(format!("Invalid square: {}", square))
+    }
+}
+
+/// A position together with the moves which led to it, which the board can be reset to. With the
+/// `bevy` feature it is also an event, which an app sends whenever its board is reset so whatever
+/// draws the pieces can compare them with the board's [snapshot](snapshot::BoardSnapshot) and
+/// redraw only the squares which changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Event))]
+pub struct ResetBoardEvent {
+    fen: Fen,
+    /// The moves which led to the position, kept as the past moves of the reset board.
+    past_moves: Vec<Move>,
+    /// The variant the position is played in, or None to keep the board's.
+    #[serde(default)]
+    variant: Option<Variant>,
+}
+
+impl ResetBoardEvent {
+    pub fn new(fen: Fen) -> Self {
+        ResetBoardEvent {
+            fen,
+            past_moves: Vec::new(),
+            variant: None,
+        }
+    }
+
+    /// Creates an event which resets the board to a position reached after the given moves.
+    pub fn with_past_moves(fen: Fen, past_moves: Vec<Move>) -> Self {
+        ResetBoardEvent {
+            fen,
+            past_moves,
+            variant: None,
+        }
+    }
+
+    /// Returns the event resetting the board to a game of the given variant.
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = Some(variant);
+        self
+    }
+
+    /// Replays the given moves in standard algebraic notation from the given position, returning
+    /// the event which resets the board to the final position, or None if a move is not legal.
+    pub fn from_algebraic_moves<S: AsRef<str>>(start: &Fen, moves: &[S]) -> Option<Self> {
+        let mut board = ChessBoard::with_position(start);
+        for text in moves {
+            let piece_move = Move::from_algebraic(text.as_ref(), &board)?;
+            board.apply_move(&piece_move);
+        }
+        Some(ResetBoardEvent::with_past_moves(
+            board.to_fen(),
+            board.past_moves().clone(),
+        ))
+    }
+
+    pub fn fen(&self) -> &Fen {
+        &self.fen
+    }
+
+    pub fn past_moves(&self) -> &Vec<Move> {
+        &self.past_moves
+    }
+
+    pub fn variant(&self) -> &Option<Variant> {
+        &self.variant
+    }
+}
+
+/// The pieces on the board and the state of the game they are playing.
+#[derive(Clone)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct ChessBoard {
+    board: [[Option<Box<dyn piece::Piece>>; MAX_FILES]; BOARD_SIZE],
+    size: BoardSize,
+    active_color: Option<PieceColor>,
+    past_moves: Vec<Move>,
+    move_number: i32,
+    castling_rights: CastlingRights,
+    /// The square a pawn has just passed over with a double step, which the player to move can
+    /// capture it on en passant.
+    ep_target_square: Option<BoardPosition>,
+    /// The number of moves since the last capture or pawn move, for the fifty-move rule.
+    halfmove_clock: i32,
+    winner: Option<PieceColor>,
+    game_end_status: Option<GameEndStatus>,
+    draw_offer: Option<PieceColor>,
+    /// The color which has asked to take back its last move. The request stays open until it is
+    /// answered, so it can be answered after the game has ended.
+    takeback_request: Option<PieceColor>,
+    variant: Variant,
+    duck: Option<BoardPosition>,
+    /// Whether the player to move has moved a piece and is yet to place the duck.
+    awaiting_duck: bool,
+}
+
+/// What a move would do if it were made, as found by [ChessBoard::classify_move].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MoveClassification {
+    /// Whether the move would put the opponent in check, which only standard chess has.
+    pub gives_check: bool,
+    /// Whether the move would capture a piece, including en passant.
+    pub is_capture: bool,
+    pub is_promotion: bool,
+    pub is_en_passant: bool,
+    pub is_castle: bool,
+}
+
+/// The condition a castle fails, as found by [ChessBoard::castle_failure].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastleFailure {
+    /// The king or the rook has moved, or the rook has been captured.
+    NoRights,
+    /// There are pieces between the king and the rook.
+    PathOccupied,
+    InCheck,
+    /// The king would pass through a square which is attacked.
+    ThroughCheck,
+}
+
+impl CastleFailure {
+    /// Returns the locale key of the message explaining the failure.
+    pub fn key(&self) -> &'static str {
+        match self {
+            CastleFailure::NoRights => "castle-no-rights",
+            CastleFailure::PathOccupied => "castle-path-occupied",
+            CastleFailure::InCheck => "castle-in-check",
+            CastleFailure::ThroughCheck => "castle-through-check",
+        }
+    }
+}
+
+impl fmt::Display for CastleFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CastleFailure::NoRights => write!(f, "the king or the rook has moved"),
+            CastleFailure::PathOccupied => write!(f, "there are pieces between the king and rook"),
+            CastleFailure::InCheck => write!(f, "the king is in check"),
+            CastleFailure::ThroughCheck => write!(f, "the king would pass through check"),
+        }
+    }
+}
+
+/// Why a move cannot be made, as found by [ChessBoard::check_move].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MoveError {
+    /// The game has ended.
+    GameOver,
+    /// It is the given color's turn.
+    WrongTurn(PieceColor),
+    /// The move would leave or put the mover's king in check.
+    IntoCheck,
+    /// The move is a castle which fails the given condition.
+    Castle(CastleFailure),
+    /// The piece cannot move that way.
+    Illegal,
+    /// The given text is not a legal move in standard algebraic notation.
+    Unrecognised(String),
+    /// Neither the fifty-move rule nor threefold repetition allows a draw to be claimed.
+    NoDrawToClaim,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "the game has ended"),
+            MoveError::WrongTurn(color) => write!(f, "it is {}'s turn", color),
+            MoveError::IntoCheck => write!(f, "the king would be in check"),
+            MoveError::Castle(failure) => write!(f, "cannot castle: {}", failure),
+            MoveError::Illegal => write!(f, "illegal move"),
+            MoveError::Unrecognised(text) => write!(f, "no legal move is written {}", text),
+            MoveError::NoDrawToClaim => write!(f, "no draw can be claimed"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+impl Default for ChessBoard {
+    fn default() -> Self {
+        ChessBoard::empty_board()
+    }
+}
+
+impl ChessBoard {
+    fn empty_board() -> Self {
+        let board: [[Option<Box<dyn piece::Piece>>; MAX_FILES]; BOARD_SIZE] = Default::default();
+        ChessBoard {
+            board,
+            size: BoardSize::default(),
+            active_color: None,
+            past_moves: Vec::new(),
+            move_number: 1,
+            castling_rights: CastlingRights::default(),
+            ep_target_square: None,
+            halfmove_clock: 0,
+            winner: None,
+            game_end_status: None,
+            draw_offer: None,
+            takeback_request: None,
+            variant: Variant::Standard,
+            duck: None,
+            awaiting_duck: false,
+        }
+    }
+
+    /// Creates a board with the position of the given [Fen] and no past moves, without placing
+    /// pieces on screen.
+    pub fn with_position(fen: &Fen) -> Self {
+        // Create an empty board state
+        let mut board_state = ChessBoard::empty_board();
+
+        // Populate it from the given fen
+        board_state.size = *fen.size();
+        for position in fen.size().positions() {
+            if let Some((color, piece_type)) = fen.piece_placement()[position.rank][position.file] {
+                board_state.add_piece(color, piece_type, position);
+            }
+        }
+
+        // Set active color
+        board_state.active_color = Some(*fen.active_color());
+        // Set move number
+        board_state.move_number = *fen.fullmove_number();
+        // Set castling rights
+        board_state.castling_rights = *fen.castling_rights();
+        board_state.ep_target_square = *fen.ep_target_square();
+        board_state.halfmove_clock = *fen.halfmove_clock();
+        board_state.duck = *fen.duck();
+
+        board_state
+    }
+
+    /// Returns the board with the game played by the rules of the given [Variant].
+    pub fn with_variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// Returns the [Fen] of the current position.
+    pub fn to_fen(&self) -> Fen {
+        let mut piece_placement = [[None; MAX_FILES]; BOARD_SIZE];
+        for (placement_rank, board_rank) in piece_placement.iter_mut().zip(&self.board) {
+            for (placement, piece) in placement_rank.iter_mut().zip(board_rank) {
+                *placement = piece
+                    .as_ref()
+                    .map(|piece| (*piece.get_color(), *piece.get_type()));
+            }
+        }
+        // Once the game has ended there is no active color, so use the side that would be next to move
+        let active_color = self
+            .active_color
+            .or_else(|| {
+                self.past_moves
+                    .last()
+                    .map(|piece_move| piece_move.piece_color().opposite())
+            })
+            .unwrap_or(PieceColor::White);
+        Fen::new(
+            piece_placement,
+            active_color,
+            self.castling_rights,
+            self.ep_target_square,
+            self.halfmove_clock,
+            self.move_number,
+        )
+        .with_size(self.size)
+        .with_duck(self.duck)
+    }
+
+    pub fn active_color(&self) -> &Option<PieceColor> {
+        &self.active_color
+    }
+
+    /// Returns the number of moves made since the last capture or pawn move.
+    pub fn halfmove_clock(&self) -> &i32 {
+        &self.halfmove_clock
+    }
+
+    /// Returns the square the player to move can capture a pawn on en passant, if any.
+    pub fn ep_target_square(&self) -> &Option<BoardPosition> {
+        &self.ep_target_square
+    }
+
+    /// Returns the square of the pawn the given move captures en passant, if it is a pawn moving
+    /// diagonally onto the en passant target square past a pawn of the opponent.
+    pub fn en_passant_capture(&self, piece_move: &Move) -> Option<BoardPosition> {
+        let captured = BoardPosition::new(piece_move.from().rank, piece_move.to().file);
+        (*piece_move.piece_type() == PieceType::Pawn
+            && piece_move.from().file != piece_move.to().file
+            && self.ep_target_square == Some(*piece_move.to())
+            && self.get_piece_type(piece_move.to()).is_none()
+            && self.get_piece_type(&captured) == Some(PieceType::Pawn)
+            && self.get_piece_color(&captured) == Some(piece_move.piece_color().opposite()))
+        .then_some(captured)
+    }
+
+    pub fn past_moves(&self) -> &Vec<Move> {
+        &self.past_moves
+    }
+
+    pub fn move_number(&self) -> &i32 {
+        &self.move_number
+    }
+
+    pub fn game_end_status(&self) -> &Option<GameEndStatus> {
+        &self.game_end_status
+    }
+
+    pub fn winner(&self) -> &Option<PieceColor> {
+        &self.winner
+    }
+
+    pub fn size(&self) -> &BoardSize {
+        &self.size
+    }
+
+    pub fn variant(&self) -> &Variant {
+        &self.variant
+    }
+
+    /// Returns the square of the duck, once it has been placed.
+    pub fn duck(&self) -> &Option<BoardPosition> {
+        &self.duck
+    }
+
+    /// Returns whether the player to move has moved a piece and is yet to place the duck.
+    pub fn awaiting_duck(&self) -> bool {
+        self.awaiting_duck
+    }
+
+    /// Ends the game with the given status and winner.
+    pub fn end_game(&mut self, status: GameEndStatus, winner: Option<PieceColor>) {
+        self.game_end_status = Some(status);
+        self.winner = winner;
+        self.active_color = None;
+        self.draw_offer = None;
+        self.awaiting_duck = false;
+    }
+
+    pub fn valid_move(
+        &self,
+        piece_move: &Move,
+        active_color: &Option<PieceColor>,
+        check_for_check: &bool,
+    ) -> bool {
+        // Throw away any move with both the capture and castle tags
+        if piece_move.is_castle() && piece_move.is_capture() {
+            return false;
+        }
+
+        // No piece can move off the board, once it is time to place the duck, or onto the duck
+        if !self.size.contains(piece_move.to())
+            || self.awaiting_duck
+            || self.duck == Some(*piece_move.to())
+        {
+            return false;
+        }
+        // There is no castling in antichess
+        if piece_move.is_castle() && self.variant == Variant::Antichess {
+            return false;
+        }
+        // Only standard chess has check
+        let check_rules = *check_for_check && self.variant == Variant::Standard;
+
+        // A pawn reaching the last rank must promote, and only to one of the allowed pieces
+        let must_promote = piece_move.piece_type() == &PieceType::Pawn
+            && piece_move.to().rank == self.size.last_rank(piece_move.piece_color());
+        if must_promote
+            != piece_move
+                .promotion()
+                .is_some_and(|promotion| self.promotions().contains(&promotion))
+        {
+            return false;
+        }
+
+        // Get piece
+        if self.board[piece_move.from().rank][piece_move.from().file].is_none() {
+            return false;
+        }
+        let piece = self.board[piece_move.from().rank][piece_move.from().file]
+            .as_ref()
+            .unwrap();
+
+        // Check that there is an active colour
+        active_color.is_some()
+        // Check that the piece is the active colour
+        && (*piece.get_color() == active_color.unwrap())
+        // Check whether or not there are any pieces there
+        && match self.get_piece_color(piece_move.to()) {
+            Some(color) => if color == *piece.get_color() {
+                // If a friendly piece is here this move is invalid
+                false
+            } else {
+                // If an enemy piece is here the move must be a valid capture
+                piece.valid_capture(piece_move.to())
+            }
+            // If no piece is here the move must be a valid move, or a capture en passant
+            None => piece.valid_move(piece_move.to())
+                || (self.en_passant_capture(piece_move).is_some() && piece.valid_capture(piece_move.to()))
+        }
+        // No piece in the way for sliding pieces
+        && (!piece.slides_to(piece_move.to()) || self.no_piece_between_squares(piece_move.from(), piece_move.to()))
+        // The move must not put the active color in check
+        && (!check_rules
+        ||{
+                let mut test_board = self.clone();
+                if let Some(captured) = self.en_passant_capture(piece_move) {
+                    test_board.board[captured.rank][captured.file] = None;
+                }
+                test_board.move_piece(piece_move.from(), piece_move.to());
+                !test_board.in_check(&active_color.unwrap())
+            })
+        // Check if a castle is possible
+        && (!check_for_check || !piece_move.is_castle() || self.castle_failure(piece_move).is_none())
+        // Captures are forced in antichess
+        && (!check_for_check
+            || self.variant != Variant::Antichess
+            || piece_move.is_capture()
+            || !self.valid_moves_iter(*active_color, false).any(|other| other.is_capture()))
+    }
+
+    pub fn get_valid_moves(
+        &self,
+        active_color: &Option<PieceColor>,
+        check_for_check: &bool,
+    ) -> Vec<Move> {
+        self.valid_moves_iter(*active_color, *check_for_check)
+            .collect()
+    }
+
+    /// Yields the valid moves for the given color one at a time, in the same order as
+    /// [ChessBoard::get_valid_moves], so callers which only need some of them can stop early.
+    pub fn valid_moves_iter(
+        &self,
+        active_color: Option<PieceColor>,
+        check_for_check: bool,
+    ) -> impl Iterator<Item = Move> + '_ {
+        self.size
+            .positions()
+            .filter_map(move |from| {
+                self.board[from.rank][from.file]
+                    .as_ref()
+                    .filter(|piece| Some(*piece.get_color()) == active_color)
+                    .map(|piece| (from, piece))
+            })
+            .flat_map(move |(from, piece)| {
+                piece
+                    .get_moves(&true)
+                    .into_iter()
+                    .map(move |to| Move::from_board(from, to, self))
+            })
+            .flat_map(move |piece_move| {
+                // A promoting pawn has a move for each piece it can promote to, while other moves
+                // are left unchanged by Move::with_promotion
+                let promotions = match piece_move.promotion() {
+                    Some(_) => self.promotions(),
+                    None => &[PieceType::Pawn],
+                };
+                promotions
+                    .iter()
+                    .map(move |promotion| piece_move.with_promotion(*promotion))
+            })
+            .filter(move |piece_move| self.valid_move(piece_move, &active_color, &check_for_check))
+    }
+
+    /// Returns the pieces a pawn can promote to, most valuable first. The king is one of them in
+    /// [Variant::Antichess], where it is an ordinary piece.
+    pub fn promotions(&self) -> &'static [PieceType] {
+        match self.variant {
+            Variant::Antichess => &[
+                PieceType::Queen,
+                PieceType::Rook,
+                PieceType::Bishop,
+                PieceType::Knight,
+                PieceType::King,
+            ],
+            _ => &[
+                PieceType::Queen,
+                PieceType::Rook,
+                PieceType::Bishop,
+                PieceType::Knight,
+            ],
+        }
+    }
+
+    /// Returns the condition the given castle fails, or None if it can be made as far as castling
+    /// goes. The king must still have the right to castle towards the rook with no pieces between
+    /// them, and in standard chess must neither be in check nor pass through check.
+    pub fn castle_failure(&self, piece_move: &Move) -> Option<CastleFailure> {
+        let color = *piece_move.piece_color();
+        let from = piece_move.from();
+        let direction = *piece_move.to().file() as i32 - *from.file() as i32;
+        // The rook is in the corner the king castles towards, where a FEN may claim a right
+        // without one
+        let rook_file = (*from.file() as i32 + direction * MAX_FILES as i32)
+            .clamp(0, self.size.files as i32 - 1) as usize;
+        let corner = BoardPosition::new(*from.rank(), rook_file);
+        if !self
+            .castling_rights
+            .valid_castle_direction(&color, direction)
+            || self.get_piece_type(&corner) != Some(PieceType::Rook)
+            || self.get_piece_color(&corner) != Some(color)
+        {
+            return Some(CastleFailure::NoRights);
+        }
+        if !self.no_piece_between_squares(from, &corner) {
+            return Some(CastleFailure::PathOccupied);
+        }
+        if self.variant != Variant::Standard {
+            return None;
+        }
+        if self.in_check(&color) {
+            return Some(CastleFailure::InCheck);
+        }
+        let passed = BoardPosition::new(
+            *from.rank(),
+            (*from.file() as i32 + direction.signum()) as usize,
+        );
+        let mut test_board = self.clone();
+        test_board.move_piece(from, &passed);
+        test_board
+            .in_check(&color)
+            .then_some(CastleFailure::ThroughCheck)
+    }
+
+    /// Returns what the given move, which must be valid, would do if it were made, without making
+    /// it.
+    pub fn classify_move(&self, piece_move: &Move) -> MoveClassification {
+        let is_en_passant = self.en_passant_capture(piece_move).is_some();
+        let gives_check = self.variant == Variant::Standard && {
+            let mut test_board = self.clone();
+            test_board.apply_move(piece_move);
+            test_board.in_check(&piece_move.piece_color().opposite())
+        };
+        MoveClassification {
+            gives_check,
+            is_capture: piece_move.is_capture() || is_en_passant,
+            is_promotion: piece_move.promotion().is_some(),
+            is_en_passant,
+            is_castle: piece_move.is_castle(),
+        }
+    }
+
+    /// Returns whether the given move is legal, or why it is not.
+    pub fn check_move(&self, piece_move: &Move) -> Result<(), MoveError> {
+        if self.valid_move(piece_move, &self.active_color, &true) {
+            return Ok(());
+        }
+        let Some(active_color) = self.active_color else {
+            return Err(MoveError::GameOver);
+        };
+        if *piece_move.piece_color() != active_color {
+            return Err(MoveError::WrongTurn(active_color));
+        }
+        if let Some(failure) = piece_move
+            .is_castle()
+            .then(|| self.castle_failure(piece_move))
+            .flatten()
+        {
+            return Err(MoveError::Castle(failure));
+        }
+        if self.valid_move(piece_move, &self.active_color, &false) {
+            return Err(MoveError::IntoCheck);
+        }
+        Err(MoveError::Illegal)
+    }
+
+    /// Yields the legal moves of the player to move one at a time.
+    pub fn legal_moves_iter(&self) -> impl Iterator<Item = Move> + '_ {
+        self.valid_moves_iter(self.active_color, true)
+    }
+
+    /// Makes the given move, which must be valid, and updates the rest of the board state.
+    /// In [Variant::Duck] the turn only ends once the duck has been placed, either with the move
+    /// or afterwards with [ChessBoard::place_duck].
+    /// Returns the start and end of the rook's move if the move was a castle.
+    pub fn apply_move(&mut self, piece_move: &Move) -> Option<(BoardPosition, BoardPosition)> {
+        // A capture or a pawn move starts the fifty-move rule again
+        let en_passant = self.en_passant_capture(piece_move);
+        self.halfmove_clock = match piece_move.piece_type() == &PieceType::Pawn
+            || en_passant.is_some()
+            || (!piece_move.is_castle() && self.get_piece_type(piece_move.to()).is_some())
+        {
+            true => 0,
+            false => self.halfmove_clock + 1,
+        };
+        // Take a pawn captured en passant, which is not on the square the capturing pawn moves to
+        if let Some(captured) = en_passant {
+            self.board[captured.rank][captured.file] = None;
+        }
+        self.ep_target_square = self.passed_square(piece_move);
+
+        // Move the piece, replacing a promoting pawn
+        self.move_piece(piece_move.from(), piece_move.to());
+        if let Some(promotion) = piece_move.promotion() {
+            self.add_piece(*piece_move.piece_color(), promotion, *piece_move.to());
+        }
+
+        // If the move was a castle, also move the rook
+        let rook_move = if piece_move.is_castle() {
+            let file_move_direction =
+                *piece_move.to().file() as i32 - *piece_move.from().file() as i32;
+            let from = BoardPosition::new(
+                *piece_move.from().rank(),
+                (*piece_move.from().file() as i32 + file_move_direction * MAX_FILES as i32)
+                    .clamp(0, self.size.files as i32 - 1) as usize,
+            );
+            let to = BoardPosition::new(
+                *piece_move.to().rank(),
+                (*piece_move.to().file() as i32 - file_move_direction.signum()) as usize,
+            );
+            self.move_piece(&from, &to);
+            Some((from, to))
+        } else {
+            None
+        };
+
+        // Update castling rights
+        self.castling_rights
+            .update_after_move(piece_move, &self.size);
+
+        match piece_move.duck {
+            Some(duck) => self.duck = Some(duck),
+            None => self.awaiting_duck = self.variant == Variant::Duck,
+        }
+        if self.awaiting_duck {
+            self.past_moves.push(*piece_move);
+        } else {
+            self.end_turn(*piece_move);
+        }
+
+        rook_move
+    }
+
+    /// Returns whether the duck can be placed on the given square, which must be empty and not
+    /// already hold the duck.
+    pub fn valid_duck_square(&self, position: &BoardPosition) -> bool {
+        self.awaiting_duck
+            && self.size.contains(position)
+            && self.get_piece_type(position).is_none()
+            && self.duck != Some(*position)
+    }
+
+    /// Places the duck on the given square, which must be valid, ending the turn.
+    pub fn place_duck(&mut self, position: BoardPosition) {
+        self.duck = Some(position);
+        self.awaiting_duck = false;
+        let mut piece_move = self
+            .past_moves
+            .pop()
+            .expect("The duck was placed before a move.");
+        piece_move.duck = Some(position);
+        self.end_turn(piece_move);
+    }
+
+    /// Passes the turn to the opponent once the given move has been made, recording the move.
+    fn end_turn(&mut self, mut piece_move: Move) {
+        // Change the active color
+        self.active_color = Some(self.active_color.unwrap().opposite());
+
+        // Make a record of the move
+        piece_move.is_check =
+            self.variant == Variant::Standard && self.in_check(&self.active_color.unwrap());
+        self.past_moves.push(piece_move);
+
+        // Increment the move number if it is now white's turn
+        if self.active_color == Some(PieceColor::White) {
+            self.move_number += 1;
+        }
+
+        // Moving declines any draw offered by the opponent
+        if self.draw_offer == self.active_color {
+            self.draw_offer = None;
+        }
+    }
+
+    /// Ends the game if it is over.
+    #[cfg(test)]
+    fn check_game_end(&mut self) {
+        if let Some((status, winner)) = self.game_end() {
+            self.apply_event(&game_log::GameEvent::Ended { status, winner });
+        }
+    }
+
+    /// Returns how the game has ended and who won if the player to move has no legal moves or, in
+    /// [Variant::Duck], if a king has been captured. Who wins without legal moves depends on the
+    /// [Variant].
+    pub fn game_end(&self) -> Option<(GameEndStatus, Option<PieceColor>)> {
+        let active_color = self.active_color?;
+        let has_piece = |color: &PieceColor, piece_type: Option<PieceType>| {
+            self.board.iter().flatten().flatten().any(|piece| {
+                piece.get_color() == color
+                    && piece_type.is_none_or(|piece_type| *piece.get_type() == piece_type)
+            })
+        };
+        match self.variant {
+            Variant::Standard => {}
+            Variant::Duck => {
+                if let Some(color) =
+                    PieceColor::iter().find(|color| !has_piece(color, Some(PieceType::King)))
+                {
+                    return Some((GameEndStatus::KingCapture, Some(color.opposite())));
+                }
+                // A player with no legal moves wins
+                return (!self.awaiting_duck && self.legal_moves_iter().next().is_none())
+                    .then_some((GameEndStatus::Stalemate, Some(active_color)));
+            }
+            Variant::Antichess => {
+                if self.legal_moves_iter().next().is_some() {
+                    return None;
+                }
+                // A player with no pieces or no legal moves wins
+                let status = if has_piece(&active_color, None) {
+                    GameEndStatus::Stalemate
+                } else {
+                    GameEndStatus::AllPiecesLost
+                };
+                return Some((status, Some(active_color)));
+            }
+        }
+
+        // Check for checkmate or stalemate
+        if self.legal_moves_iter().next().is_some() {
+            return None;
+        }
+        if self.in_check(&active_color) {
+            Some((GameEndStatus::Checkmate, Some(active_color.opposite())))
+        } else {
+            Some((GameEndStatus::Stalemate, None))
+        }
+    }
+
+    /// Passes the turn to the opponent without moving or recording a move, as the engine does
+    /// when pruning with null moves.
+    pub fn pass_turn(&mut self) {
+        self.active_color = self.active_color.map(|color| color.opposite());
+    }
+
+    /// Returns whether the given color has enough material to possibly checkmate.
+    /// This is the case with any pawn, rook, queen or fairy piece, or with at least two minor
+    /// pieces.
+    pub fn has_mating_material(&self, color: &PieceColor) -> bool {
+        let mut minor_pieces = 0;
+        for piece in self.board.iter().flatten().flatten() {
+            if piece.get_color() == color {
+                match piece.get_type() {
+                    PieceType::Pawn | PieceType::Rook | PieceType::Queen | PieceType::Fairy(_) => {
+                        return true
+                    }
+                    PieceType::Bishop | PieceType::Knight => minor_pieces += 1,
+                    PieceType::King => {}
+                }
+            }
+        }
+        minor_pieces >= 2
+    }
+
+    /// Returns the color which has offered a draw, if the offer is still open.
+    pub fn draw_offer(&self) -> &Option<PieceColor> {
+        &self.draw_offer
+    }
+
+    /// Returns the color which has asked to take back its last move, if the request is still open.
+    pub fn takeback_request(&self) -> &Option<PieceColor> {
+        &self.takeback_request
+    }
+
+    /// Returns the number of moves, from the last, which taking back the last move of the given
+    /// player takes back, or None if they have not moved.
+    pub fn takeback_plies(&self, color: &PieceColor) -> Option<usize> {
+        self.past_moves
+            .iter()
+            .rev()
+            .position(|piece_move| piece_move.piece_color() == color)
+            .map(|index| index + 1)
+    }
+
+    fn add_piece(
+        &mut self,
+        piece_color: PieceColor,
+        piece_type: PieceType,
+        position: BoardPosition,
+    ) {
+        let new_piece = piece::new_piece(piece_color, piece_type, position);
+        self.board[position.rank][position.file] = Some(new_piece);
+    }
+
+    fn move_piece(&mut self, from: &BoardPosition, to: &BoardPosition) {
+        if self.board[*from.rank()][*from.file()].is_none() {
+            panic!("No piece at start location.");
+        }
+        self.board[*from.rank()][*from.file()]
+            .as_mut()
+            .unwrap()
+            .set_position(to);
+        self.board[*to.rank()][*to.file()] = self.board[*from.rank()][*from.file()].clone();
+        self.board[*from.rank()][*from.file()] = None;
+    }
+
+    pub fn get_piece_type(&self, position: &BoardPosition) -> Option<PieceType> {
+        self.board[position.rank][position.file]
+            .as_ref()
+            .map(|piece| *piece.get_type())
+    }
+
+    pub fn get_piece_color(&self, position: &BoardPosition) -> Option<PieceColor> {
+        self.board[position.rank][position.file]
+            .as_ref()
+            .map(|piece| *piece.get_color())
+    }
+
+    pub fn in_check(&self, color: &PieceColor) -> bool {
+        // Get king location
+        let mut king_location = BoardPosition::new(0, 0);
+        'outer: for rank in 0..BOARD_SIZE {
+            for file in 0..MAX_FILES {
+                if self.board[rank][file].is_some()
+                    && *self.board[rank][file].as_ref().unwrap().get_type() == PieceType::King
+                    && self.board[rank][file].as_ref().unwrap().get_color() == color
+                {
+                    king_location = BoardPosition::new(rank, file);
+                    break 'outer;
+                }
+            }
+        }
+        // Check if any valid moves can take the king
+        self.valid_moves_iter(Some(color.opposite()), false)
+            .any(|piece_move| *piece_move.to() == king_location)
+    }
+
+    /// Returns the squares of the pieces of the given color which attack the given square, as
+    /// they could capture an enemy piece there, whatever is on it now.
+    pub fn attackers(&self, position: &BoardPosition, color: &PieceColor) -> Vec<BoardPosition> {
+        let mut board = self.clone();
+        board.awaiting_duck = false;
+        board.add_piece(color.opposite(), PieceType::Pawn, *position);
+        board
+            .valid_moves_iter(Some(*color), false)
+            .filter(|piece_move| piece_move.to() == position)
+            .map(|piece_move| *piece_move.from())
+            .collect()
+    }
+
+    /// Returns the square of the king of the given color, or None if it has none.
+    pub fn king_position(&self, color: &PieceColor) -> Option<BoardPosition> {
+        self.size.positions().find(|position| {
+            self.get_piece_type(position) == Some(PieceType::King)
+                && self.get_piece_color(position) == Some(*color)
+        })
+    }
+
+    /// Returns the squares of the enemy pieces which would attack the mover's king once the given
+    /// move was made, without making it. Any of them makes the move illegal in standard chess.
+    pub fn checkers_after(&self, piece_move: &Move) -> Vec<BoardPosition> {
+        let color = *piece_move.piece_color();
+        let mut test_board = self.clone();
+        test_board.move_piece(piece_move.from(), piece_move.to());
+        match test_board.king_position(&color) {
+            Some(king) => test_board.attackers(&king, &color.opposite()),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the square the given move of a pawn passes over with a double step, if a pawn of
+    /// the opponent stands beside it to capture it en passant. Positions which only differ in a
+    /// capture which could not be made are the same position.
+    fn passed_square(&self, piece_move: &Move) -> Option<BoardPosition> {
+        let (from, to) = (piece_move.from(), piece_move.to());
+        if *piece_move.piece_type() != PieceType::Pawn || from.rank.abs_diff(to.rank) != 2 {
+            return None;
+        }
+        let opponent = piece_move.piece_color().opposite();
+        [to.file.checked_sub(1), Some(to.file + 1)]
+            .into_iter()
+            .flatten()
+            .map(|file| BoardPosition::new(to.rank, file))
+            .any(|beside| {
+                self.size.contains(&beside)
+                    && self.get_piece_type(&beside) == Some(PieceType::Pawn)
+                    && self.get_piece_color(&beside) == Some(opponent)
+            })
+            .then(|| BoardPosition::new((from.rank + to.rank) / 2, to.file))
+    }
+
+    fn no_piece_between_squares(&self, start: &BoardPosition, end: &BoardPosition) -> bool {
+        let mut rank = *start.rank() as i32;
+        let mut file = *start.file() as i32;
+        rank += (*end.rank() as i32 - *start.rank() as i32).signum();
+        file += (*end.file() as i32 - *start.file() as i32).signum();
+        while rank as usize != *end.rank() || file as usize != *end.file() {
+            if self.board[rank as usize][file as usize].is_some()
+                || self.duck == Some(BoardPosition::new(rank as usize, file as usize))
+            {
+                return false;
+            }
+            rank += (*end.rank() as i32 - *start.rank() as i32).signum();
+            file += (*end.file() as i32 - *start.file() as i32).signum();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "Invalid rank or file value: 8, 4")]
+    fn test_board_position_new_rank_too_large() {
+        BoardPosition::new(8, 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid rank or file value: 1, 12")]
+    fn test_board_position_new_file_too_large() {
+        BoardPosition::new(1, 12);
+    }
+
+    #[test]
+    fn test_board_position_from_algebraic() {
+        assert_eq!(
+            BoardPosition::from_algebraic("a8"),
+            Some(BoardPosition::new(0, 0))
+        );
+        assert_eq!(
+            BoardPosition::from_algebraic("e4"),
+            Some(BoardPosition::new(4, 4))
+        );
+        assert_eq!(
+            BoardPosition::from_algebraic("j1"),
+            Some(BoardPosition::new(7, 9))
+        );
+        assert_eq!(BoardPosition::from_algebraic("m4"), None);
+        assert_eq!(BoardPosition::from_algebraic("e9"), None);
+        assert_eq!(BoardPosition::from_algebraic("e44"), None);
+        assert_eq!(BoardPosition::from_algebraic(""), None);
+        assert_eq!(BoardPosition::new(4, 4).to_algebraic(), "e4");
+        assert_eq!(BoardPosition::new(0, 7).to_algebraic(), "h8");
+    }
+
+    #[test]
+    fn test_chess_board_empty_board() {
+        let empty_board = ChessBoard::empty_board();
+
+        assert_eq!(*empty_board.active_color(), None);
+        assert_eq!(empty_board.past_moves.len(), 0);
+        assert_eq!(*empty_board.move_number(), 1);
+        for rank in 0..BOARD_SIZE {
+            for file in 0..BOARD_SIZE {
+                assert!(empty_board.board[rank][file].is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn test_chess_board_end_game() {
+        let mut board = ChessBoard::empty_board();
+        board.active_color = Some(PieceColor::White);
+
+        board.end_game(GameEndStatus::FlagFall, Some(PieceColor::Black));
+
+        assert_eq!(*board.game_end_status(), Some(GameEndStatus::FlagFall));
+        assert_eq!(*board.winner(), Some(PieceColor::Black));
+        assert_eq!(*board.active_color(), None);
+    }
+
+    #[test]
+    fn test_move_as_description() {
+        let mut piece_move = Move {
+            from: BoardPosition::new(2, 5),
+            to: BoardPosition::new(3, 4),
+            piece_type: PieceType::Knight,
+            piece_color: PieceColor::Black,
+            is_capture: true,
+            is_castle: false,
+            is_check: true,
+            is_checkmate: false,
+            duck: None,
+            promotion: None,
+        };
+        assert_eq!(piece_move.as_description(), "Knight takes e5, check");
+
+        piece_move.is_capture = false;
+        piece_move.is_check = false;
+        assert_eq!(piece_move.as_description(), "Knight to e5");
+
+        piece_move.is_checkmate = true;
+        assert_eq!(piece_move.as_description(), "Knight to e5, checkmate");
+
+        let castle = Move {
+            from: BoardPosition::new(7, 4),
+            to: BoardPosition::new(7, 2),
+            piece_type: PieceType::King,
+            piece_color: PieceColor::White,
+            is_capture: false,
+            is_castle: true,
+            is_check: false,
+            is_checkmate: false,
+            duck: None,
+            promotion: None,
+        };
+        assert_eq!(castle.as_description(), "Queenside castle");
+    }
+
+    #[test]
+    fn test_move_from_algebraic() {
+        let fen_string = "rnbqkbnr/ppp1pppp/8/3p4/3PP3/5N2/PPP2PPP/RNBQKB1R w KQkq - 0 1";
+
+        let board = ChessBoard::with_position(&Fen::from_string(fen_string));
+
+        let from =
+            |text: &str| Move::from_algebraic(text, &board).map(|piece_move| piece_move.from);
+        assert_eq!(from("e5"), Some(BoardPosition::new(4, 4)));
+        assert_eq!(from("exd5+"), Some(BoardPosition::new(4, 4)));
+        assert_eq!(from("Nbd2"), Some(BoardPosition::new(7, 1)));
+        assert_eq!(from("Ke2"), Some(BoardPosition::new(7, 4)));
+        // Both knights can move to d2
+        assert_eq!(from("Nd2"), None);
+        // The bishop is in the way of castling
+        assert_eq!(from("O-O"), None);
+        assert_eq!(from("Qh5"), None);
+        assert_eq!(from("hello"), None);
+        assert_eq!(from(""), None);
+    }
+
+    #[test]
+    fn test_chess_board_from_fen() {
+        let fen = Fen::from_string(
+            "rk1r1bb1/ppp1pp1p/3n2n1/1q1p2p1/4P3/1N2Q1PP/PPPP1P2/RK2RBBN b - - 0 1",
+        );
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that the chessboard has been set up correctly
+        let pieces = [
+            vec![
+                Some((PieceType::Rook, PieceColor::Black)),
+                Some((PieceType::King, PieceColor::Black)),
+                None,
+                Some((PieceType::Rook, PieceColor::Black)),
+                None,
+                Some((PieceType::Bishop, PieceColor::Black)),
+                Some((PieceType::Bishop, PieceColor::Black)),
+                None,
+            ],
+            vec![
+                Some((PieceType::Pawn, PieceColor::Black)),
+                Some((PieceType::Pawn, PieceColor::Black)),
+                Some((PieceType::Pawn, PieceColor::Black)),
+                None,
+                Some((PieceType::Pawn, PieceColor::Black)),
+                Some((PieceType::Pawn, PieceColor::Black)),
+                None,
+                Some((PieceType::Pawn, PieceColor::Black)),
+            ],
+            vec![
+                None,
+                None,
+                None,
+                Some((PieceType::Knight, PieceColor::Black)),
+                None,
+                None,
+                Some((PieceType::Knight, PieceColor::Black)),
+                None,
+            ],
+            vec![
+                None,
+                Some((PieceType::Queen, PieceColor::Black)),
+                None,
+                Some((PieceType::Pawn, PieceColor::Black)),
+                None,
+                None,
+                Some((PieceType::Pawn, PieceColor::Black)),
+                None,
+            ],
+            vec![
+                None,
+                None,
+                None,
+                None,
+                Some((PieceType::Pawn, PieceColor::White)),
+                None,
+                None,
+                None,
+            ],
+            vec![
+                None,
+                Some((PieceType::Knight, PieceColor::White)),
+                None,
+                None,
+                Some((PieceType::Queen, PieceColor::White)),
+                None,
+                Some((PieceType::Pawn, PieceColor::White)),
+                Some((PieceType::Pawn, PieceColor::White)),
+            ],
+            vec![
+                Some((PieceType::Pawn, PieceColor::White)),
+                Some((PieceType::Pawn, PieceColor::White)),
+                Some((PieceType::Pawn, PieceColor::White)),
+                Some((PieceType::Pawn, PieceColor::White)),
+                None,
+                Some((PieceType::Pawn, PieceColor::White)),
+                None,
+                None,
+            ],
+            vec![
+                Some((PieceType::Rook, PieceColor::White)),
+                Some((PieceType::King, PieceColor::White)),
+                None,
+                None,
+                Some((PieceType::Rook, PieceColor::White)),
+                Some((PieceType::Bishop, PieceColor::White)),
+                Some((PieceType::Bishop, PieceColor::White)),
+                Some((PieceType::Knight, PieceColor::White)),
+            ],
+        ];
+
+        // Check active color
+        assert_eq!(*board.active_color(), Some(PieceColor::Black));
+
+        // Check past moves
+        assert_eq!(board.past_moves.len(), 0);
+
+        // Check move number
+        assert_eq!(*board.move_number(), 1);
+
+        // Check pieces
+        let board = &board.board;
+        for rank in 0..BOARD_SIZE {
+            for file in 0..BOARD_SIZE {
+                if pieces[rank][file].is_none() {
+                    assert!(board[rank][file].is_none());
+                } else {
+                    assert_eq!(
+                        *board[rank][file].as_ref().unwrap().get_type(),
+                        pieces[rank][file].unwrap().0
+                    );
+                    assert_eq!(
+                        *board[rank][file].as_ref().unwrap().get_color(),
+                        pieces[rank][file].unwrap().1
+                    );
+                    assert_eq!(
+                        *board[rank][file].as_ref().unwrap().get_position(),
+                        BoardPosition::new(rank, file)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chess_board_to_fen() {
+        let fen_string = "rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 7";
+
+        let board = ChessBoard::with_position(&Fen::from_string(fen_string));
+
+        // Confirm that the board converts back to the same fen
+        assert_eq!(board.to_fen().to_string(), fen_string);
+    }
+
+    #[test]
+    fn test_chess_board_capture_rook_castling_rights() {
+        let mut board =
+            ChessBoard::with_position(&Fen::from_string("r3k2r/6B1/8/8/8/8/8/R3K2R w KQkq - 0 1"));
+        let capture = Move::from_algebraic("Bxh8", &board).unwrap();
+        board.apply_move(&capture);
+        assert_eq!(
+            board.to_fen().to_string(),
+            "r3k2B/8/8/8/8/8/8/R3K2R b KQq - 0 1"
+        );
+        // Black can no longer castle kingside with the captured rook
+        assert!(Move::from_algebraic("0-0", &board).is_none());
+        assert!(Move::from_algebraic("0-0-0", &board).is_some());
+    }
+
+    #[test]
+    fn test_chess_board_castle_through_check() {
+        // The bishop attacks d8, which the king would pass through to castle queenside
+        let board =
+            ChessBoard::with_position(&Fen::from_string("r3k2r/2B5/8/8/8/8/8/4K3 b kq - 0 1"));
+        assert!(Move::from_algebraic("0-0-0", &board).is_none());
+        assert!(Move::from_algebraic("0-0", &board).is_some());
+    }
+
+    #[test]
+    fn test_chess_board_castle_queenside() {
+        // The knight is in the way of the rook
+        let board = ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/8/RN2K3 w Q - 0 1"));
+        assert!(Move::from_algebraic("0-0-0", &board).is_none());
+
+        let mut board =
+            ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1"));
+        board.apply_move(&Move::from_algebraic("0-0-0", &board).unwrap());
+        assert_eq!(board.to_fen().to_string(), "4k3/8/8/8/8/8/8/2KR4 b - - 1 1");
+    }
+
+    #[test]
+    fn test_duck_chess() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        board.variant = Variant::Duck;
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        // The turn only ends once the duck is placed on an empty square
+        assert!(board.awaiting_duck());
+        assert_eq!(*board.active_color(), Some(PieceColor::White));
+        assert!(board.legal_moves_iter().next().is_none());
+        assert!(!board.valid_duck_square(&BoardPosition::from_algebraic("e4").unwrap()));
+        board.place_duck(BoardPosition::from_algebraic("e5").unwrap());
+        assert_eq!(*board.active_color(), Some(PieceColor::Black));
+        assert_eq!(board.past_moves().last().unwrap().as_algebraic(), "e4@e5");
+
+        // The duck blocks its square and the squares behind it
+        assert!(Move::from_algebraic("e5", &board).is_none());
+        assert!(Move::from_algebraic("e6", &board).is_some());
+        // The duck must move to a different square
+        assert!(Move::from_algebraic("e6@e5", &board).is_none());
+        let piece_move = Move::from_algebraic("e6@d3", &board).unwrap();
+        board.apply_move(&piece_move);
+        assert!(!board.awaiting_duck());
+        assert_eq!(*board.duck(), BoardPosition::from_algebraic("d3"));
+        assert!(Move::from_algebraic("Bd3", &board).is_none());
+        assert!(Move::from_algebraic("Bc4", &board).is_none());
+        assert!(Move::from_algebraic("Be2", &board).is_some());
+    }
+
+    #[test]
+    fn test_board_size() {
+        assert_eq!(BoardSize::new(9, 8), None);
+        assert_eq!(BoardSize::new(8, 13), None);
+
+        // Capablanca chess is played on ten files
+        let fen = Fen::from_string("1r7k/10/10/10/10/10/10/K8R w - - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        assert_eq!(*board.size(), BoardSize::new(8, 10).unwrap());
+        assert_eq!(board.to_fen().to_string(), fen.to_string());
+        assert!(Move::from_algebraic("Rj7", &board).is_some());
+
+        // Pawns promote on the top rank of a smaller board, and pieces keep to it
+        let fen = Fen::from_string("k4/2P2/5/5/5/K3R w - - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        assert_eq!(*board.size(), BoardSize::new(6, 5).unwrap());
+        assert_eq!(board.to_fen().to_string(), fen.to_string());
+        assert!(board
+            .legal_moves_iter()
+            .all(|piece_move| board.size().contains(piece_move.to())));
+        let promotion = Move::from_algebraic("c6=Q", &board).unwrap();
+        assert_eq!(promotion.to().to_algebraic(), "c6");
+        assert!(Move::from_algebraic("Rf1", &board).is_none());
+    }
+
+    #[test]
+    fn test_classify_move() {
+        let board =
+            ChessBoard::with_position(&Fen::from_string("r3k3/1P6/8/8/8/8/7q/R3K2R w KQq - 0 1"));
+        let classify =
+            |text: &str| board.classify_move(&Move::from_algebraic(text, &board).unwrap());
+        assert_eq!(
+            classify("Rxh2"),
+            MoveClassification {
+                is_capture: true,
+                ..MoveClassification::default()
+            }
+        );
+        assert_eq!(
+            classify("O-O-O"),
+            MoveClassification {
+                is_castle: true,
+                ..MoveClassification::default()
+            }
+        );
+        let promotion = classify("bxa8=Q");
+        assert!(promotion.is_capture && promotion.is_promotion && promotion.gives_check);
+        assert!(!promotion.is_en_passant && !promotion.is_castle);
+        assert!(classify("Rxa8").gives_check);
+        assert!(!classify("Ra7").gives_check);
+    }
+
+    #[test]
+    fn test_antichess() {
+        let mut board = ChessBoard::with_position(&Fen::default()).with_variant(Variant::Antichess);
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        board.apply_move(&Move::from_algebraic("d5", &board).unwrap());
+        // The capture is forced
+        let moves: Vec<String> = board.legal_moves_iter().map(|m| m.as_algebraic()).collect();
+        assert_eq!(moves, vec!["exd5"]);
+
+        // The king can capture and be captured, and losing the last piece wins
+        let mut board =
+            ChessBoard::with_position(&Fen::from_string("8/8/8/8/8/8/1p6/K7 w - - 0 1"))
+                .with_variant(Variant::Antichess);
+        let moves: Vec<Move> = board.legal_moves_iter().collect();
+        assert_eq!(moves.len(), 1);
+        board.apply_move(&moves[0]);
+        board.check_game_end();
+        assert_eq!(*board.game_end_status(), Some(GameEndStatus::AllPiecesLost));
+        assert_eq!(*board.winner(), Some(PieceColor::Black));
+    }
+
+    #[test]
+    fn test_duck_chess_king_capture() {
+        let mut board =
+            ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/8/4QK2 b - - 0 1"));
+        board.variant = Variant::Duck;
+        // There is no check, so the black king may stay in the queen's line
+        board.apply_move(&Move::from_algebraic("Ke7@a1", &board).unwrap());
+        board.check_game_end();
+        assert_eq!(*board.game_end_status(), None);
+
+        // Capturing the king wins without placing the duck
+        board.apply_move(&Move::from_algebraic("Qxe7", &board).unwrap());
+        board.check_game_end();
+        assert_eq!(*board.game_end_status(), Some(GameEndStatus::KingCapture));
+        assert_eq!(*board.winner(), Some(PieceColor::White));
+        assert!(!board.awaiting_duck());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid piece placement")]
+    fn test_chess_board_from_fen_unrecognised_symbol() {
+        let fen = Fen::from_string(
+            "rk1x1bb1/ppp1pp1p/3n2n1/1q1p2p1/4P3/1N2Q1PP/PPPP1P2/RK2RBBN b - - 0 1",
+        );
+
+        ChessBoard::with_position(&fen);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid active color")]
+    fn test_chess_board_from_fen_unrecognised_active_color() {
+        let fen = Fen::from_string(
+            "rk1r1bb1/ppp1pp1p/3n2n1/1q1p2p1/4P3/1N2Q1PP/PPPP1P2/RK2RBBN l - - 0 1",
+        );
+
+        ChessBoard::with_position(&fen);
+    }
+
+    #[test]
+    fn test_chess_board_valid_move_true() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp1ppp1p/5n2/qp4p1/4P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Create move
+        let piece_move =
+            Move::from_board(BoardPosition::new(5, 2), BoardPosition::new(3, 1), &board);
+
+        // Confirm that the move is valid
+        assert!(board.valid_move(&piece_move, board.active_color(), &true));
+    }
+
+    #[test]
+    fn test_chess_board_valid_move_false() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Create move
+        let piece_move =
+            Move::from_board(BoardPosition::new(6, 3), BoardPosition::new(5, 3), &board);
+
+        // Confirm that the move is not valid
+        assert!(!board.valid_move(&piece_move, board.active_color(), &true));
+    }
+
+    #[test]
+    #[should_panic(expected = "No piece found.")]
+    fn test_chess_board_valid_move_no_piece() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Create move
+        let _piece_move =
+            Move::from_board(BoardPosition::new(5, 3), BoardPosition::new(5, 3), &board);
+    }
+
+    #[test]
+    fn test_chess_board_get_valid_moves() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Expected valid moves
+        let expected_valid_moves = vec![
+            Move {
+                from: BoardPosition::new(3, 1),
+                to: BoardPosition::new(1, 0),
+                piece_type: PieceType::Knight,
+                is_capture: true,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(3, 1),
+                to: BoardPosition::new(1, 2),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(3, 1),
+                to: BoardPosition::new(2, 3),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(3, 1),
+                to: BoardPosition::new(4, 3),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(3, 1),
+                to: BoardPosition::new(5, 0),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(3, 1),
+                to: BoardPosition::new(5, 2),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(4, 4),
+                to: BoardPosition::new(3, 4),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(4, 4),
+                to: BoardPosition::new(3, 3),
+                piece_type: PieceType::Pawn,
+                is_capture: true,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(5, 5),
+                to: BoardPosition::new(3, 4),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(5, 5),
+                to: BoardPosition::new(3, 6),
+                piece_type: PieceType::Knight,
+                is_capture: true,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(5, 5),
+                to: BoardPosition::new(4, 3),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(5, 5),
+                to: BoardPosition::new(4, 7),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(5, 5),
+                to: BoardPosition::new(7, 6),
+                piece_type: PieceType::Knight,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 0),
+                to: BoardPosition::new(5, 0),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 0),
+                to: BoardPosition::new(4, 0),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 1),
+                to: BoardPosition::new(5, 1),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 1),
+                to: BoardPosition::new(4, 1),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 2),
+                to: BoardPosition::new(5, 2),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 2),
+                to: BoardPosition::new(4, 2),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 6),
+                to: BoardPosition::new(5, 6),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 6),
+                to: BoardPosition::new(4, 6),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 7),
+                to: BoardPosition::new(5, 7),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(6, 7),
+                to: BoardPosition::new(4, 7),
+                piece_type: PieceType::Pawn,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(7, 0),
+                to: BoardPosition::new(7, 1),
+                piece_type: PieceType::Rook,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(7, 3),
+                to: BoardPosition::new(6, 4),
+                piece_type: PieceType::Queen,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(7, 4),
+                to: BoardPosition::new(6, 4),
+                piece_type: PieceType::King,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(7, 4),
+                to: BoardPosition::new(7, 5),
+                piece_type: PieceType::King,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(7, 4),
+                to: BoardPosition::new(7, 6),
+                piece_type: PieceType::King,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: true,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(7, 7),
+                to: BoardPosition::new(7, 5),
+                piece_type: PieceType::Rook,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+            Move {
+                from: BoardPosition::new(7, 7),
+                to: BoardPosition::new(7, 6),
+                piece_type: PieceType::Rook,
+                is_capture: false,
+                piece_color: PieceColor::White,
+                is_castle: false,
+                is_check: false,
+                is_checkmate: false,
+                duck: None,
+                promotion: None,
+            },
+        ];
+
+        // Get valid moves
+        let valid_moves = board.get_valid_moves(board.active_color(), &true);
+
+        // Confirm that the results match
+        assert_eq!(expected_valid_moves, valid_moves);
+    }
+
+    #[test]
+    fn test_chess_board_legal_moves_iter() {
+        let board = ChessBoard::with_position(&Fen::default());
+        assert_eq!(
+            board.legal_moves_iter().collect::<Vec<Move>>(),
+            board.get_valid_moves(board.active_color(), &true)
+        );
+        assert_eq!(board.legal_moves_iter().count(), 20);
+
+        // Stalemate, so there is no first legal move
+        let board = ChessBoard::with_position(&Fen::from_string("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"));
+        assert!(board.legal_moves_iter().next().is_none());
+        assert!(!board.in_check(&PieceColor::Black));
+    }
+
+    #[test]
+    fn test_chess_board_move_piece() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let mut board = ChessBoard::with_position(&fen);
+
+        // Confirm that the piece starts in the expected location
+        assert!(board.board[2][5].is_some());
+        assert_eq!(
+            *board.board[2][5].as_ref().unwrap().get_color(),
+            PieceColor::Black
+        );
+        assert_eq!(
+            *board.board[2][5].as_ref().unwrap().get_type(),
+            PieceType::Knight
+        );
+
+        // Move the piece
+        board.move_piece(&BoardPosition::new(2, 5), &BoardPosition::new(4, 6));
+
+        // Confirm that the piece has been moved
+        assert!(board.board[2][5].is_none());
+        assert!(board.board[4][6].is_some());
+        assert_eq!(
+            *board.board[4][6].as_ref().unwrap().get_color(),
+            PieceColor::Black
+        );
+        assert_eq!(
+            *board.board[4][6].as_ref().unwrap().get_type(),
+            PieceType::Knight
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "No piece at start location.")]
+    fn test_chess_board_move_piece_no_piece() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let mut board = ChessBoard::with_position(&fen);
+
+        // Attempt to move a non-existent piece
+        board.move_piece(&BoardPosition::new(2, 1), &BoardPosition::new(4, 6));
+    }
+
+    #[test]
+    fn test_chess_board_get_piece_type() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that get_piece_type returns the correct result
+        assert_eq!(
+            board.get_piece_type(&BoardPosition::new(1, 4)),
+            Some(PieceType::Pawn)
+        );
+        assert_eq!(board.get_piece_type(&BoardPosition::new(2, 6)), None);
+        assert_eq!(
+            board.get_piece_type(&BoardPosition::new(7, 2)),
+            Some(PieceType::Bishop)
+        );
+    }
+
+    #[test]
+    fn test_chess_board_get_piece_color() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that get_piece_color returns the correct result
+        assert_eq!(
+            board.get_piece_color(&BoardPosition::new(1, 4)),
+            Some(PieceColor::Black)
+        );
+        assert_eq!(board.get_piece_color(&BoardPosition::new(2, 6)), None);
+        assert_eq!(
+            board.get_piece_color(&BoardPosition::new(7, 2)),
+            Some(PieceColor::White)
+        );
+    }
+
+    #[test]
+    fn test_chess_board_in_check_white() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2pp1p/8/qN1p2N1/4P3/2Pn4/PP1P2PP/1RBQK2R w Kkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that we get the correct result
+        assert!(board.in_check(&PieceColor::White));
+        assert!(!board.in_check(&PieceColor::Black));
+    }
+
+    #[test]
+    fn test_chess_board_in_check_black() {
+        let fen =
+            Fen::from_string("rnb1kb1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R w Kkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that we get the correct result
+        assert!(board.in_check(&PieceColor::Black));
+        assert!(!board.in_check(&PieceColor::White));
+    }
+
+    #[test]
+    fn test_chess_board_in_check_none() {
+        let fen =
+            Fen::from_string("rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that we get the correct result
+        assert!(!board.in_check(&PieceColor::White));
+        assert!(!board.in_check(&PieceColor::Black));
+    }
+
+    #[test]
+    fn test_chess_board_no_piece_between_squares_true() {
+        let fen =
+            Fen::from_string("rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that we get the correct result
+        assert!(
+            board.no_piece_between_squares(&BoardPosition::new(2, 1), &BoardPosition::new(6, 5))
+        );
+    }
+
+    #[test]
+    fn test_chess_board_no_piece_between_squares_false() {
+        let fen =
+            Fen::from_string("rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1");
+
+        let board = ChessBoard::with_position(&fen);
+
+        // Confirm that we get the correct result
+        assert!(
+            !board.no_piece_between_squares(&BoardPosition::new(1, 6), &BoardPosition::new(4, 3))
+        );
+    }
+
+    #[test]
+    fn test_chess_board_has_mating_material() {
+        let board =
+            ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/2NB4/4K1n1 w - - 0 1"));
+
+        assert!(board.has_mating_material(&PieceColor::White));
+        assert!(!board.has_mating_material(&PieceColor::Black));
+    }
+}
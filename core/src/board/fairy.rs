@@ -0,0 +1,364 @@
+//! Fairy pieces, piece types beyond the standard six which can be registered at any time,
+//! such as the archbishop and chancellor of Capablanca chess.
+//!
+//! Each is a [FairyPieceType] which moves by leaping and riding along symmetric steps and is
+//! placed on the board as [PieceType::Fairy] with the index it was registered with in a
+//! [FairyRegistry]. The registry of the running app holds the pieces boards are set up with, which
+//! are registered with [register_piece_type], or from a file with [load_piece_types], as the app
+//! does at startup from the [FAIRY_PIECES_FILE] if there is one, such as
+//!
+//! ```ron
+//! [
+//!     (
+//!         name: "Archbishop",
+//!         letter: 'A',
+//!         value: 800,
+//!         movement: (leaps: [(1, 2)], rides: [(1, 1)]),
+//!         appearance: Bishop,
+//!         sprites: Some(("archbishop_white.svg", "archbishop_black.svg")),
+//!     ),
+//! ]
+//! ```
+//!
+//! after which positions with the piece can be set up from a FEN with its letter, e.g. "A" and
+//! "a". Pawns do not promote to fairy pieces.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use super::{BoardPosition, PieceType, BOARD_SIZE, MAX_FILES};
+
+/// The file the app registers fairy pieces from at startup.
+pub const FAIRY_PIECES_FILE: &str = "fairy_pieces.ron";
+
+/// The most fairy pieces which can be registered.
+pub const MAX_FAIRY_PIECES: usize = 16;
+
+/// Returns the given step in each of its mirror images and rotations, without repeats.
+fn symmetric(step: (i32, i32)) -> Vec<(i32, i32)> {
+    let (a, b) = step;
+    let mut steps = Vec::new();
+    for step in [(a, b), (b, a)] {
+        for (rank_sign, file_sign) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            let step = (step.0 * rank_sign, step.1 * file_sign);
+            if !steps.contains(&step) {
+                steps.push(step);
+            }
+        }
+    }
+    steps
+}
+
+/// How a piece moves, as the steps it leaps by and the directions it rides in, each given once
+/// and counting in all its mirror images and rotations. A leap goes straight to its square, over
+/// any pieces between, and a ride goes any number of squares in its direction until blocked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Movement {
+    /// The steps leapt by as ranks and files, e.g. (1, 2) for a knight.
+    pub leaps: Vec<(i32, i32)>,
+    /// The directions ridden in, which are (1, 0) for a rook and (1, 1) for a bishop.
+    pub rides: Vec<(i32, i32)>,
+}
+
+impl Movement {
+    /// Returns whether the movement can be played, which it can if it has a move, every leap
+    /// moves and every ride is along a rank, file or diagonal.
+    fn is_valid(&self) -> bool {
+        (!self.leaps.is_empty() || !self.rides.is_empty())
+            && self.leaps.iter().all(|leap| *leap != (0, 0))
+            && self
+                .rides
+                .iter()
+                .all(|ride| matches!((ride.0.abs(), ride.1.abs()), (1, 0) | (0, 1) | (1, 1)))
+    }
+
+    /// Returns whether a piece with this movement can leap from the given square to the other.
+    pub(super) fn leaps_to(&self, from: &BoardPosition, to: &BoardPosition) -> bool {
+        let step = (
+            to.rank as i32 - from.rank as i32,
+            to.file as i32 - from.file as i32,
+        );
+        self.leaps
+            .iter()
+            .any(|leap| symmetric(*leap).contains(&step))
+    }
+
+    /// Returns the squares a piece with this movement could move to from the given square on an
+    /// empty grid of squares, which the board leaves out those off it from.
+    pub(super) fn destinations(&self, from: &BoardPosition) -> Vec<BoardPosition> {
+        let square = |rank: i32, file: i32| {
+            ((0..BOARD_SIZE as i32).contains(&rank) && (0..MAX_FILES as i32).contains(&file))
+                .then(|| BoardPosition::new(rank as usize, file as usize))
+        };
+        let (rank, file) = (from.rank as i32, from.file as i32);
+        let mut destinations = Vec::new();
+        for (rank_step, file_step) in self.leaps.iter().flat_map(|leap| symmetric(*leap)) {
+            destinations.extend(square(rank + rank_step, file + file_step));
+        }
+        for (rank_step, file_step) in self.rides.iter().flat_map(|ride| symmetric(*ride)) {
+            let mut distance = 1;
+            while let Some(position) =
+                square(rank + distance * rank_step, file + distance * file_step)
+            {
+                destinations.push(position);
+                distance += 1;
+            }
+        }
+        destinations.sort_by_key(|position| (position.rank, position.file));
+        destinations.dedup();
+        destinations
+    }
+}
+
+/// A registered piece type.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FairyPieceType {
+    /// The English name of the piece, which is shown in every language.
+    pub name: String,
+    /// The uppercase letter of the piece in FEN and standard algebraic notation.
+    pub letter: char,
+    /// The value of the piece in centipawns.
+    pub value: i32,
+    pub movement: Movement,
+    /// The standard piece the piece is drawn as without sprites of its own.
+    pub appearance: PieceType,
+    /// The paths of the SVG documents the white and black pieces are drawn with in the vector
+    /// piece style.
+    #[serde(default)]
+    pub sprites: Option<(String, String)>,
+}
+
+/// Why a piece type cannot be registered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterError {
+    /// The given letter of the piece is not an uppercase letter.
+    NotUppercase(char),
+    /// The given letter is that of a standard piece or of a registered one.
+    LetterUsed(char),
+    /// The piece with the given name has no move, or rides other than along a rank, file or
+    /// diagonal.
+    Movement(String),
+    /// The piece with the given name is drawn as a fairy piece.
+    Appearance(String),
+    /// [MAX_FAIRY_PIECES] are already registered.
+    Full,
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::NotUppercase(letter) => {
+                write!(f, "{} is not an uppercase letter", letter)
+            }
+            RegisterError::LetterUsed(letter) => write!(f, "the letter {} is already used", letter),
+            RegisterError::Movement(name) => write!(f, "{} cannot move like that", name),
+            RegisterError::Appearance(name) => {
+                write!(f, "{} must be drawn as a standard piece", name)
+            }
+            RegisterError::Full => {
+                write!(f, "at most {} pieces can be registered", MAX_FAIRY_PIECES)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+/// A set of registered piece types, in the order of their indices.
+#[derive(Debug, Clone, Default)]
+pub struct FairyRegistry {
+    pieces: Vec<Arc<FairyPieceType>>,
+}
+
+impl FairyRegistry {
+    const fn new() -> Self {
+        FairyRegistry { pieces: Vec::new() }
+    }
+
+    /// Registers the given piece type, returning the piece type it is placed on the board as, or
+    /// why it cannot be registered.
+    pub fn register(&mut self, piece: FairyPieceType) -> Result<PieceType, RegisterError> {
+        if !piece.letter.is_ascii_uppercase() {
+            return Err(RegisterError::NotUppercase(piece.letter));
+        }
+        // The letters of the standard pieces are checked without the registry of the app, which
+        // may be this one and locked
+        if "KQBNRP".contains(piece.letter) || self.with_letter(piece.letter).is_some() {
+            return Err(RegisterError::LetterUsed(piece.letter));
+        }
+        if !piece.movement.is_valid() {
+            return Err(RegisterError::Movement(piece.name));
+        }
+        if matches!(piece.appearance, PieceType::Fairy(_)) {
+            return Err(RegisterError::Appearance(piece.name));
+        }
+        if self.pieces.len() >= MAX_FAIRY_PIECES {
+            return Err(RegisterError::Full);
+        }
+        self.pieces.push(Arc::new(piece));
+        Ok(PieceType::Fairy((self.pieces.len() - 1) as u8))
+    }
+
+    /// Returns the piece type with the given index.
+    pub fn get(&self, index: u8) -> Option<Arc<FairyPieceType>> {
+        self.pieces.get(index as usize).cloned()
+    }
+
+    /// Returns the piece type with the given uppercase letter.
+    pub fn with_letter(&self, letter: char) -> Option<PieceType> {
+        self.pieces
+            .iter()
+            .position(|piece| piece.letter == letter)
+            .map(|index| PieceType::Fairy(index as u8))
+    }
+
+    /// Returns the piece types with their indices.
+    pub fn pieces(&self) -> Vec<(u8, Arc<FairyPieceType>)> {
+        self.pieces
+            .iter()
+            .enumerate()
+            .map(|(index, piece)| (index as u8, piece.clone()))
+            .collect()
+    }
+}
+
+/// The registry of the running app, which boards are set up with.
+static PIECE_TYPES: RwLock<FairyRegistry> = RwLock::new(FairyRegistry::new());
+
+/// Registers the given piece type with the app, returning the piece type it is placed on the board
+/// as, or why it cannot be registered.
+pub fn register_piece_type(piece: FairyPieceType) -> Result<PieceType, RegisterError> {
+    PIECE_TYPES.write().unwrap().register(piece)
+}
+
+/// Returns the piece type registered with the app with the given index.
+pub fn fairy_piece(index: u8) -> Option<Arc<FairyPieceType>> {
+    PIECE_TYPES.read().unwrap().get(index)
+}
+
+/// Returns the piece type registered with the app with the given uppercase letter.
+pub(super) fn fairy_piece_with_letter(letter: char) -> Option<PieceType> {
+    PIECE_TYPES.read().unwrap().with_letter(letter)
+}
+
+/// Returns the piece types registered with the app with their indices.
+pub fn fairy_pieces() -> Vec<(u8, Arc<FairyPieceType>)> {
+    PIECE_TYPES.read().unwrap().pieces()
+}
+
+/// Clears the piece types registered with the app, so a test starts from none.
+#[cfg(test)]
+pub(crate) fn reset_piece_types() {
+    *PIECE_TYPES.write().unwrap() = FairyRegistry::new();
+}
+
+/// Registers the piece types listed in the given file, if it exists, skipping any which cannot be
+/// registered.
+pub fn load_piece_types(path: &Path) {
+    let Ok(text) = fs::read_to_string(path) else {
+        return;
+    };
+    let pieces: Vec<FairyPieceType> = match ron::from_str(&text) {
+        Ok(pieces) => pieces,
+        Err(error) => {
+            warn!(file = %path.display(), %error, "Could not load fairy pieces");
+            return;
+        }
+    };
+    for piece in pieces {
+        let name = piece.name.clone();
+        match register_piece_type(piece) {
+            Ok(_) => info!(name, "Registered fairy piece"),
+            Err(error) => warn!(name, %error, "Could not register fairy piece"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::r#move::Move;
+    use crate::board::ChessBoard;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_register_piece_type() {
+        let archbishop: FairyPieceType = ron::from_str(
+            "(name: \"Archbishop\", letter: 'A', value: 800, \
+             movement: (leaps: [(1, 2)], rides: [(1, 1)]), appearance: Bishop)",
+        )
+        .unwrap();
+        let mut registry = FairyRegistry::default();
+        let piece_type = registry.register(archbishop.clone()).unwrap();
+        assert_eq!(piece_type, PieceType::Fairy(0));
+        assert_eq!(registry.with_letter('A'), Some(piece_type));
+        assert_eq!(
+            registry.register(archbishop.clone()),
+            Err(RegisterError::LetterUsed('A'))
+        );
+        let king = FairyPieceType {
+            letter: 'K',
+            ..archbishop.clone()
+        };
+        assert_eq!(registry.register(king), Err(RegisterError::LetterUsed('K')));
+        let lowercase = FairyPieceType {
+            letter: 'x',
+            ..archbishop.clone()
+        };
+        assert_eq!(
+            registry.register(lowercase),
+            Err(RegisterError::NotUppercase('x'))
+        );
+        let nightrider = FairyPieceType {
+            name: "Nightrider".to_string(),
+            letter: 'X',
+            movement: Movement {
+                leaps: Vec::new(),
+                rides: vec![(1, 2)],
+            },
+            ..archbishop.clone()
+        };
+        let error = registry.register(nightrider).unwrap_err();
+        assert_eq!(error, RegisterError::Movement("Nightrider".to_string()));
+        assert_eq!(error.to_string(), "Nightrider cannot move like that");
+        for letter in ('B'..='Z').filter(|letter| !"KQBNRP".contains(*letter)) {
+            let piece = FairyPieceType {
+                letter,
+                ..archbishop.clone()
+            };
+            if registry.pieces().len() < MAX_FAIRY_PIECES {
+                assert!(registry.register(piece).is_ok());
+            } else {
+                assert_eq!(registry.register(piece), Err(RegisterError::Full));
+            }
+        }
+        assert_eq!(registry.pieces().len(), MAX_FAIRY_PIECES);
+
+        // Boards are set up with the registry of the app
+        reset_piece_types();
+        let piece_type = register_piece_type(archbishop).unwrap();
+        assert_eq!(PieceType::from_letter('A'), Some(piece_type));
+        assert_eq!(piece_type.to_string(), "Archbishop");
+        assert_eq!(fairy_pieces().len(), 1);
+
+        // The archbishop leaps like a knight and slides like a bishop, up to the pawn on d4
+        let fen = Fen::from_string("4k3/8/8/8/3p4/8/8/A3K3 w - - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        assert_eq!(board.to_fen().to_string(), fen.to_string());
+        let mut moves: Vec<String> = board
+            .legal_moves_iter()
+            .filter(|piece_move| piece_move.piece_type() == &piece_type)
+            .map(|piece_move| piece_move.as_algebraic())
+            .collect();
+        moves.sort();
+        assert_eq!(moves, ["Ab2", "Ab3", "Ac2", "Ac3", "Axd4"]);
+        let piece_move = Move::from_algebraic("Axd4", &board).unwrap();
+        assert!(piece_move.is_capture());
+    }
+}
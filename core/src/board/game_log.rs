@@ -0,0 +1,119 @@
+//! The game log, the ordered record of every [GameEvent] which has changed a [ChessBoard]. A board
+//! kept with a log is only ever changed by applying an event and logging it, so the board is the
+//! events of the log folded in order, and folding them again from an empty board always reaches
+//! the same board.
+//!
+//! Events are facts which have already been checked against the board: a move is only logged once
+//! it is known to be allowed, and taking back moves logs the position gone back to, so folding
+//! needs neither the rules the moves were checked by nor the positions before. The log is only
+//! appended to, so it is also an audit trail of the games played on the board.
+
+use serde::{Deserialize, Serialize};
+
+use super::r#move::Move;
+use super::{BoardPosition, ChessBoard, GameEndStatus, PieceColor, ResetBoardEvent};
+
+/// A change to the game on the board.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// The board was reset to the given position, as a new game or a loaded one.
+    Reset(ResetBoardEvent),
+    /// The given move was made.
+    Moved(Move),
+    /// The duck was placed on the given square.
+    DuckPlaced(BoardPosition),
+    /// Moves were taken back, resetting the board to the given position before them.
+    TakenBack(ResetBoardEvent),
+    /// The given player offered a draw.
+    DrawOffered(PieceColor),
+    /// The given player accepted or declined the draw offered by their opponent.
+    DrawAnswered { color: PieceColor, accept: bool },
+    /// The given player asked to take back their last move.
+    TakebackRequested(PieceColor),
+    /// The given player answered the take back asked for by their opponent, which is followed by
+    /// a [GameEvent::TakenBack] if they accepted.
+    TakebackAnswered { color: PieceColor, accept: bool },
+    /// The game ended other than by agreement, with the given winner.
+    Ended {
+        status: GameEndStatus,
+        winner: Option<PieceColor>,
+    },
+}
+
+impl ChessBoard {
+    /// Applies the given event, which must have been checked against the board.
+    /// Returns the start and end of the rook's move if the event was a castle.
+    pub(crate) fn apply_event(
+        &mut self,
+        event: &GameEvent,
+    ) -> Option<(BoardPosition, BoardPosition)> {
+        match event {
+            GameEvent::Reset(reset_event) | GameEvent::TakenBack(reset_event) => {
+                let variant = reset_event.variant.unwrap_or(self.variant);
+                *self = ChessBoard::with_position(reset_event.fen()).with_variant(variant);
+                self.past_moves = reset_event.past_moves().clone();
+                // Positions saved before the duck was part of the FEN restore it from the last move
+                if self.duck.is_none() {
+                    self.duck = self
+                        .past_moves
+                        .last()
+                        .and_then(|piece_move| piece_move.duck);
+                }
+            }
+            GameEvent::Moved(piece_move) => return self.apply_move(piece_move),
+            GameEvent::DuckPlaced(position) => self.place_duck(*position),
+            GameEvent::DrawOffered(color) => self.draw_offer = Some(*color),
+            GameEvent::DrawAnswered { accept: true, .. } => {
+                self.end_game(GameEndStatus::Agreement, None)
+            }
+            GameEvent::DrawAnswered { accept: false, .. } => self.draw_offer = None,
+            GameEvent::TakebackRequested(color) => self.takeback_request = Some(*color),
+            GameEvent::TakebackAnswered { .. } => self.takeback_request = None,
+            GameEvent::Ended { status, winner } => {
+                if *status == GameEndStatus::Checkmate {
+                    if let Some(last_move) = self.past_moves.last_mut() {
+                        last_move.is_checkmate = true;
+                    }
+                }
+                self.end_game(*status, *winner);
+            }
+        }
+        None
+    }
+}
+
+/// The events applied to a board, in the order they were applied.
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::prelude::Resource))]
+pub struct GameLog {
+    events: Vec<GameEvent>,
+}
+
+impl GameLog {
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    /// Applies the given event to the board and logs it.
+    /// Returns the start and end of the rook's move if the event was a castle.
+    pub fn record(
+        &mut self,
+        board: &mut ChessBoard,
+        event: GameEvent,
+    ) -> Option<(BoardPosition, BoardPosition)> {
+        let rook_move = board.apply_event(&event);
+        self.events.push(event);
+        rook_move
+    }
+}
+
+/// Returns the board reached by folding the given events from an empty board, which for the
+/// events of a [GameLog] is the board they were applied to.
+pub fn fold(events: &[GameEvent]) -> ChessBoard {
+    events
+        .iter()
+        .fold(ChessBoard::default(), |mut board, event| {
+            board.apply_event(event);
+            board
+        })
+}
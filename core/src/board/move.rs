@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+
+use super::{BoardPosition, ChessBoard, PieceColor, PieceType, MAX_FILES};
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Move {
+    pub(super) from: BoardPosition,
+    pub(super) to: BoardPosition,
+    pub(super) piece_type: PieceType,
+    pub(super) piece_color: PieceColor,
+    pub(super) is_capture: bool,
+    pub(super) is_castle: bool,
+    /// Whether the move gave check, which is only known once the move has been made.
+    pub(super) is_check: bool,
+    /// Whether the move gave checkmate, which is only known once the move has been made.
+    pub(super) is_checkmate: bool,
+    /// Where the duck was placed after the move in duck chess, written after an "@".
+    pub(super) duck: Option<BoardPosition>,
+    /// The piece a pawn promotes to on reaching the last rank, written after an "=".
+    pub(super) promotion: Option<PieceType>,
+}
+
+impl Move {
+    /// Creates the move of the piece on the start square to the end square. A pawn reaching the
+    /// last rank promotes to a queen, which can be changed with [Move::with_promotion].
+    pub fn from_board(from: BoardPosition, to: BoardPosition, board: &ChessBoard) -> Self {
+        let piece_type = board.get_piece_type(&from).expect("No piece found.");
+        let piece_color = board.get_piece_color(&from).unwrap();
+        Move {
+            from,
+            to,
+            piece_type,
+            piece_color,
+            is_capture: board.get_piece_type(&to).is_some()
+                || (piece_type == PieceType::Pawn
+                    && from.file != to.file
+                    && *board.ep_target_square() == Some(to)),
+            is_castle: board.get_piece_type(&from).unwrap() == PieceType::King
+                && from.file.abs_diff(to.file) == 2,
+            is_check: false,
+            is_checkmate: false,
+            duck: None,
+            promotion: (piece_type == PieceType::Pawn
+                && to.rank == board.size().last_rank(&piece_color))
+            .then_some(PieceType::Queen),
+        }
+    }
+
+    /// Returns the move with the pawn promoting to the given piece instead, if it promotes.
+    pub fn with_promotion(mut self, piece_type: PieceType) -> Self {
+        if self.promotion.is_some() {
+            self.promotion = Some(piece_type);
+        }
+        self
+    }
+
+    /// Creates a move from long algebraic notation, e.g. "e2e4" or "e7e8n" for a promotion, if
+    /// there is a piece on the start square.
+    pub fn from_long_algebraic(text: &str, board: &ChessBoard) -> Option<Self> {
+        let from = BoardPosition::from_algebraic(text.get(0..2)?)?;
+        let to = BoardPosition::from_algebraic(text.get(2..4)?)?;
+        board.get_piece_type(&from)?;
+        let piece_move = Move::from_board(from, to, board);
+        match text.get(4..)? {
+            "" => Some(piece_move),
+            letter => Some(piece_move.with_promotion(Self::promotion_from_letter(
+                letter.to_uppercase().chars().next()?,
+            )?)),
+        }
+    }
+
+    /// Returns the piece a pawn can promote to with the given English letter.
+    fn promotion_from_letter(letter: char) -> Option<PieceType> {
+        match letter {
+            'Q' => Some(PieceType::Queen),
+            'R' => Some(PieceType::Rook),
+            'B' => Some(PieceType::Bishop),
+            'N' => Some(PieceType::Knight),
+            'K' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+
+    /// Creates the legal move given in standard algebraic notation, e.g. "Nf3", "exd5" or "e8=Q", on the given
+    /// board. Check and annotation symbols are ignored and castling may be written with either "O" or "0".
+    /// In duck chess the move may be followed by the square the duck is placed on, e.g. "e4@e5".
+    /// Returns None if the notation does not describe exactly one legal move.
+    pub fn from_algebraic(text: &str, board: &ChessBoard) -> Option<Self> {
+        let text = text.trim().trim_end_matches(['+', '#', '!', '?']);
+        if let Some((text, duck)) = text.split_once('@') {
+            let mut piece_move = Self::from_algebraic(text, board)?;
+            let duck = BoardPosition::from_algebraic(duck)?;
+            let mut after = board.clone();
+            after.apply_move(&piece_move);
+            if !after.valid_duck_square(&duck) {
+                return None;
+            }
+            piece_move.duck = Some(duck);
+            return Some(piece_move);
+        }
+        let (text, promotion) = match text.split_once('=') {
+            Some((text, piece)) => (
+                text,
+                Some(Self::promotion_from_letter(piece.chars().next()?)?),
+            ),
+            None => (text, None),
+        };
+        let text = text.replace('O', "0");
+        let legal_moves = board.legal_moves_iter();
+        let mut candidates: Vec<Move> = if text == "0-0" || text == "0-0-0" {
+            legal_moves
+                .filter(|piece_move| piece_move.is_castle && piece_move.as_algebraic() == text)
+                .collect()
+        } else {
+            let (piece_type, rest) = match PieceType::from_letter(text.chars().next()?) {
+                Some(piece_type) if piece_type != PieceType::Pawn => (piece_type, &text[1..]),
+                _ => (PieceType::Pawn, text.as_str()),
+            };
+            let is_capture = rest.contains('x');
+            let rest = rest.replace('x', "");
+            let to = BoardPosition::from_algebraic(rest.get(rest.len().checked_sub(2)?..)?)?;
+            // Any remaining characters give the file and/or rank the piece moves from
+            let disambiguation = rest.get(..rest.len() - 2)?;
+            legal_moves
+                .filter(|piece_move| {
+                    piece_move.piece_type == piece_type
+                        && piece_move.to == to
+                        && piece_move.promotion == promotion
+                        && !piece_move.is_castle
+                        && (piece_move.is_capture || !is_capture)
+                        && disambiguation.chars().all(|symbol| {
+                            symbol.to_string() == Self::file_to_string(piece_move.from.file)
+                                || symbol.to_string() == (8 - piece_move.from.rank).to_string()
+                        })
+                })
+                .collect()
+        };
+        if candidates.len() == 1 {
+            candidates.pop()
+        } else {
+            None
+        }
+    }
+
+    pub fn from(&self) -> &BoardPosition {
+        &self.from
+    }
+
+    pub fn to(&self) -> &BoardPosition {
+        &self.to
+    }
+
+    pub fn piece_type(&self) -> &PieceType {
+        &self.piece_type
+    }
+
+    pub fn piece_color(&self) -> &PieceColor {
+        &self.piece_color
+    }
+
+    pub fn is_castle(&self) -> bool {
+        self.is_castle
+    }
+
+    pub fn is_capture(&self) -> bool {
+        self.is_capture
+    }
+
+    pub fn is_check(&self) -> bool {
+        self.is_check
+    }
+
+    pub fn is_checkmate(&self) -> bool {
+        self.is_checkmate
+    }
+
+    pub fn promotion(&self) -> Option<PieceType> {
+        self.promotion
+    }
+
+    /// Returns a description of the move in words, e.g. "Knight takes e5, check".
+    pub fn as_description(&self) -> String {
+        let mut description = if self.is_castle {
+            match (self.to.file as i32 - self.from.file as i32).signum() {
+                1 => "Kingside castle".to_string(),
+                -1 => "Queenside castle".to_string(),
+                _ => panic!("Invalid castle from {:?} to {:?}.", self.from, self.to),
+            }
+        } else {
+            format!(
+                "{} {} {}{}",
+                self.piece_type,
+                if self.is_capture { "takes" } else { "to" },
+                Self::file_to_string(self.to.file),
+                8 - self.to.rank
+            )
+        };
+        if let Some(promotion) = self.promotion {
+            description += &format!(", promotes to {}", promotion);
+        }
+        if self.is_checkmate {
+            description.push_str(", checkmate");
+        } else if self.is_check {
+            description.push_str(", check");
+        }
+        if let Some(duck) = self.duck {
+            description += &format!(", duck to {}", Self::square_to_string(&duck));
+        }
+        description
+    }
+
+    /// Returns the move in long algebraic notation as used by UCI, e.g. "e2e4" or "e7e8n".
+    pub fn as_long_algebraic(&self) -> String {
+        let mut text = Self::square_to_string(&self.from) + &Self::square_to_string(&self.to);
+        if let Some(promotion) = self.promotion {
+            text.push(promotion.letter().to_ascii_lowercase());
+        }
+        text
+    }
+
+    pub fn as_algebraic(&self) -> String {
+        let mut algebraic = self.piece_algebraic();
+        if let Some(duck) = self.duck {
+            algebraic.push('@');
+            algebraic += &Self::square_to_string(&duck);
+        }
+        algebraic
+    }
+
+    /// Returns the move of the piece in standard algebraic notation, without the duck.
+    fn piece_algebraic(&self) -> String {
+        if self.is_castle {
+            match (self.to.file as i32 - self.from.file as i32).signum() {
+                1 => "0-0".to_string(),
+                -1 => "0-0-0".to_string(),
+                _ => panic!("Invalid castle from {:?} to {:?}.", self.from, self.to),
+            }
+        } else {
+            let mut algebraic = String::new();
+            if self.piece_type != PieceType::Pawn {
+                algebraic.push(self.piece_type.letter());
+            }
+            if self.is_capture {
+                if self.piece_type == PieceType::Pawn {
+                    algebraic.push_str(&Self::file_to_string(self.from.file));
+                }
+                algebraic.push('x');
+            }
+            algebraic += &Self::square_to_string(&self.to);
+            if let Some(promotion) = self.promotion {
+                algebraic.push('=');
+                algebraic.push(promotion.letter());
+            }
+            algebraic
+        }
+    }
+
+    fn square_to_string(position: &BoardPosition) -> String {
+        format!(
+            "{}{}",
+            Self::file_to_string(position.file),
+            8 - position.rank
+        )
+    }
+
+    fn file_to_string(file: usize) -> String {
+        match file {
+            0..MAX_FILES => char::from(b'a' + file as u8).to_string(),
+            _ => panic!("Unexpected file for moved piece: {}.", file),
+        }
+    }
+}
@@ -1,9 +1,9 @@
-use bevy::{ecs::component::TableStorage, prelude::Component};
 use dyn_clone::DynClone;
 
 use super::{BoardPosition, PieceColor, PieceType};
 
 mod bishop;
+mod fairy;
 mod king;
 mod knight;
 mod pawn;
@@ -12,18 +12,21 @@ mod rook;
 
 dyn_clone::clone_trait_object!(Piece);
 
-pub(super) trait Piece:
-    Send + Sync + DynClone + 'static + Component<Storage = TableStorage>
-{
+pub(super) trait Piece: Send + Sync + DynClone + 'static {
     fn get_type(&self) -> &PieceType;
     fn get_color(&self) -> &PieceColor;
+    #[allow(dead_code)]
     fn get_position(&self) -> &BoardPosition;
-    fn get_starting_position(&self) -> &BoardPosition;
     fn set_position(&mut self, new_position: &BoardPosition);
     fn get_moves(&self, include_captures: &bool) -> Vec<BoardPosition>;
     fn valid_move(&self, end_position: &BoardPosition) -> bool;
     fn valid_capture(&self, end_position: &BoardPosition) -> bool;
     fn is_sliding(&self) -> bool;
+    /// Returns whether the piece slides to the given square it can move to, so it is blocked by
+    /// any piece between, rather than leaping there.
+    fn slides_to(&self, _end_position: &BoardPosition) -> bool {
+        self.is_sliding()
+    }
 }
 
 pub(super) fn new_piece(
@@ -38,6 +41,7 @@ pub(super) fn new_piece(
         PieceType::Bishop => bishop::Bishop::new(position, piece_color),
         PieceType::Knight => knight::Knight::new(position, piece_color),
         PieceType::Rook => rook::Rook::new(position, piece_color),
+        PieceType::Fairy(index) => fairy::Fairy::new(index, position, piece_color),
     };
     piece
 }
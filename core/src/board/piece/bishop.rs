@@ -1,23 +1,16 @@
-use bevy::prelude::Component;
-
-use crate::chess_board::BoardPosition;
+use crate::board::{BoardPosition, BOARD_SIZE, MAX_FILES};
 
 use super::{Piece, PieceColor, PieceType};
 
-#[derive(Component, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(super) struct Bishop {
     color: PieceColor,
-    starting_position: BoardPosition,
     position: BoardPosition,
 }
 
 impl Bishop {
     pub(super) fn new(position: BoardPosition, color: PieceColor) -> Box<Self> {
-        Box::new(Bishop {
-            color,
-            starting_position: position,
-            position,
-        })
+        Box::new(Bishop { color, position })
     }
 }
 
@@ -40,8 +33,8 @@ impl Piece for Bishop {
 
     fn get_moves(&self, _include_captures: &bool) -> Vec<BoardPosition> {
         let mut moves = Vec::new();
-        for rank in 0..8 {
-            for file in 0..8 {
+        for rank in 0..BOARD_SIZE {
+            for file in 0..MAX_FILES {
                 let rank_diff = rank as i32 - self.position.rank as i32;
                 let file_diff = file as i32 - self.position.file as i32;
                 if (rank_diff == file_diff || rank_diff == -file_diff) && rank != self.position.rank
@@ -57,10 +50,6 @@ impl Piece for Bishop {
         true
     }
 
-    fn get_starting_position(&self) -> &BoardPosition {
-        &self.starting_position
-    }
-
     fn valid_move(&self, end_position: &BoardPosition) -> bool {
         let valid_moves = self.get_moves(&false);
         valid_moves.contains(end_position)
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use crate::board::fairy::{fairy_piece, FairyPieceType};
+use crate::board::BoardPosition;
+
+use super::{Piece, PieceColor, PieceType};
+
+#[derive(Clone, Debug)]
+pub(super) struct Fairy {
+    piece_type: PieceType,
+    piece: Arc<FairyPieceType>,
+    color: PieceColor,
+    position: BoardPosition,
+}
+
+impl Fairy {
+    pub(super) fn new(index: u8, position: BoardPosition, color: PieceColor) -> Box<Self> {
+        Box::new(Fairy {
+            piece_type: PieceType::Fairy(index),
+            piece: fairy_piece(index).expect("Fairy piece is not registered."),
+            color,
+            position,
+        })
+    }
+}
+
+impl Piece for Fairy {
+    fn get_type(&self) -> &PieceType {
+        &self.piece_type
+    }
+
+    fn get_color(&self) -> &PieceColor {
+        &self.color
+    }
+
+    fn get_position(&self) -> &BoardPosition {
+        &self.position
+    }
+
+    fn set_position(&mut self, new_position: &BoardPosition) {
+        self.position = *new_position;
+    }
+
+    fn get_moves(&self, _include_captures: &bool) -> Vec<BoardPosition> {
+        self.piece.movement.destinations(&self.position)
+    }
+
+    fn is_sliding(&self) -> bool {
+        !self.piece.movement.rides.is_empty()
+    }
+
+    fn slides_to(&self, end_position: &BoardPosition) -> bool {
+        self.is_sliding() && !self.piece.movement.leaps_to(&self.position, end_position)
+    }
+
+    fn valid_move(&self, end_position: &BoardPosition) -> bool {
+        let valid_moves = self.get_moves(&false);
+        valid_moves.contains(end_position)
+    }
+
+    fn valid_capture(&self, end_position: &BoardPosition) -> bool {
+        self.valid_move(end_position)
+    }
+}
@@ -1,10 +1,8 @@
-use bevy::prelude::Component;
-
-use crate::chess_board::{BoardPosition, BOARD_SIZE};
+use crate::board::{BoardPosition, BOARD_SIZE, MAX_FILES};
 
 use super::{Piece, PieceColor, PieceType};
 
-#[derive(Component, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(super) struct King {
     color: PieceColor,
     starting_position: BoardPosition,
@@ -40,8 +38,8 @@ impl Piece for King {
 
     fn get_moves(&self, _include_captures: &bool) -> Vec<BoardPosition> {
         let mut moves = Vec::new();
-        for rank in 0..8 {
-            for file in 0..8 {
+        for rank in 0..BOARD_SIZE {
+            for file in 0..MAX_FILES {
                 if (rank == self.position.rank && self.position.file.abs_diff(file) == 1)
                     || (file == self.position.file && self.position.rank.abs_diff(rank) == 1)
                     || (self.position.file.abs_diff(file) == 1
@@ -53,7 +51,7 @@ impl Piece for King {
         }
         // The king may also castle
         if self.position == self.starting_position {
-            if self.position.file < BOARD_SIZE - 2 {
+            if self.position.file < MAX_FILES - 2 {
                 moves.push(BoardPosition::new(
                     self.position.rank,
                     self.position.file + 2,
@@ -73,10 +71,6 @@ impl Piece for King {
         true
     }
 
-    fn get_starting_position(&self) -> &BoardPosition {
-        &self.starting_position
-    }
-
     fn valid_move(&self, end_position: &BoardPosition) -> bool {
         let valid_moves = self.get_moves(&false);
         valid_moves.contains(end_position)
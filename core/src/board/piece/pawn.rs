@@ -1,23 +1,16 @@
-use bevy::prelude::Component;
-
-use crate::chess_board::{BoardPosition, BOARD_SIZE};
+use crate::board::{BoardPosition, BOARD_SIZE, MAX_FILES};
 
 use super::{Piece, PieceColor, PieceType};
 
-#[derive(Component, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(super) struct Pawn {
     color: PieceColor,
-    starting_position: BoardPosition,
     position: BoardPosition,
 }
 
 impl Pawn {
     pub(super) fn new(position: BoardPosition, color: PieceColor) -> Box<Self> {
-        Box::new(Pawn {
-            color,
-            starting_position: position,
-            position,
-        })
+        Box::new(Pawn { color, position })
     }
 
     fn move_direction(&self) -> i32 {
@@ -54,7 +47,7 @@ impl Piece for Pawn {
                 self.position.file,
             ));
             if *include_captures {
-                if self.position.file != BOARD_SIZE - 1 {
+                if self.position.file != MAX_FILES - 1 {
                     moves.push(BoardPosition::new(
                         (self.position.rank as i32 + self.move_direction()) as usize,
                         (self.position.file as i32 + 1) as usize,
@@ -84,10 +77,6 @@ impl Piece for Pawn {
         true
     }
 
-    fn get_starting_position(&self) -> &BoardPosition {
-        &self.starting_position
-    }
-
     fn valid_move(&self, end_position: &BoardPosition) -> bool {
         let valid_moves = self.get_moves(&false);
         valid_moves.contains(end_position)
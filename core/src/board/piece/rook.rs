@@ -1,23 +1,16 @@
-use bevy::prelude::Component;
-
-use crate::chess_board::BoardPosition;
+use crate::board::{BoardPosition, BOARD_SIZE, MAX_FILES};
 
 use super::{Piece, PieceColor, PieceType};
 
-#[derive(Component, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub(super) struct Rook {
     color: PieceColor,
-    starting_position: BoardPosition,
     position: BoardPosition,
 }
 
 impl Rook {
     pub(super) fn new(position: BoardPosition, color: PieceColor) -> Box<Self> {
-        Box::new(Rook {
-            color,
-            starting_position: position,
-            position,
-        })
+        Box::new(Rook { color, position })
     }
 }
 
@@ -40,8 +33,8 @@ impl Piece for Rook {
 
     fn get_moves(&self, _include_captures: &bool) -> Vec<BoardPosition> {
         let mut moves = Vec::new();
-        for rank in 0..8 {
-            for file in 0..8 {
+        for rank in 0..BOARD_SIZE {
+            for file in 0..MAX_FILES {
                 if (rank == self.position.rank || file == self.position.file)
                     && (rank != self.position.rank || file != self.position.file)
                 {
@@ -56,10 +49,6 @@ impl Piece for Rook {
         true
     }
 
-    fn get_starting_position(&self) -> &BoardPosition {
-        &self.starting_position
-    }
-
     fn valid_move(&self, end_position: &BoardPosition) -> bool {
         let valid_moves = self.get_moves(&false);
         valid_moves.contains(end_position)
@@ -0,0 +1,115 @@
+//! Snapshots of the pieces on a board, which can be compared to find the squares whose pieces
+//! differ between any two positions.
+//!
+//! Whatever draws the pieces can bring them up to date with a reset board from the changes between
+//! the two, and a client which lost track of a game can do the same from the position it last
+//! saw.
+
+use super::{BoardPosition, ChessBoard, PieceColor, PieceType, BOARD_SIZE, MAX_FILES};
+
+/// The piece on a square, or None if it is empty.
+pub type SquareContent = Option<(PieceColor, PieceType)>;
+
+/// The pieces on every square of the grid, which includes the squares off a smaller board.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoardSnapshot {
+    squares: [[SquareContent; MAX_FILES]; BOARD_SIZE],
+}
+
+/// A square whose piece differs between two snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SquareChange {
+    pub position: BoardPosition,
+    pub before: SquareContent,
+    pub after: SquareContent,
+}
+
+impl BoardSnapshot {
+    /// Takes a snapshot of the pieces on the given board.
+    pub fn of(board: &ChessBoard) -> Self {
+        BoardSnapshot::from_pieces(board.size().positions().filter_map(|position| {
+            let color = board.get_piece_color(&position)?;
+            Some((position, color, board.get_piece_type(&position)?))
+        }))
+    }
+
+    /// Takes a snapshot of the given pieces, where a piece replaces any given before it on the
+    /// same square.
+    pub fn from_pieces(
+        pieces: impl IntoIterator<Item = (BoardPosition, PieceColor, PieceType)>,
+    ) -> Self {
+        let mut squares = [[None; MAX_FILES]; BOARD_SIZE];
+        for (position, color, piece_type) in pieces {
+            squares[position.rank][position.file] = Some((color, piece_type));
+        }
+        BoardSnapshot { squares }
+    }
+
+    pub fn get(&self, position: &BoardPosition) -> SquareContent {
+        self.squares[position.rank][position.file]
+    }
+
+    /// Returns the changes which turn this snapshot into the other, from the 8th rank down and
+    /// from the a-file across.
+    pub fn diff(&self, other: &BoardSnapshot) -> Vec<SquareChange> {
+        (0..BOARD_SIZE)
+            .flat_map(|rank| (0..MAX_FILES).map(move |file| BoardPosition::new(rank, file)))
+            .filter_map(|position| {
+                let (before, after) = (self.get(&position), other.get(&position));
+                (before != after).then_some(SquareChange {
+                    position,
+                    before,
+                    after,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let start = BoardSnapshot::of(&board);
+        assert!(start.diff(&start).is_empty());
+
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        board.apply_move(&Move::from_algebraic("d5", &board).unwrap());
+        board.apply_move(&Move::from_algebraic("exd5", &board).unwrap());
+        let after = BoardSnapshot::of(&board);
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let pawn = |color| Some((color, PieceType::Pawn));
+        assert_eq!(
+            start.diff(&after),
+            vec![
+                SquareChange {
+                    position: square("d7"),
+                    before: pawn(PieceColor::Black),
+                    after: None,
+                },
+                SquareChange {
+                    position: square("d5"),
+                    before: None,
+                    after: pawn(PieceColor::White),
+                },
+                SquareChange {
+                    position: square("e2"),
+                    before: pawn(PieceColor::White),
+                    after: None,
+                },
+            ]
+        );
+        // Going back is the same changes the other way round
+        assert!(after
+            .diff(&start)
+            .iter()
+            .zip(start.diff(&after))
+            .all(|(back, forward)| back.before == forward.after && back.after == forward.before));
+    }
+}
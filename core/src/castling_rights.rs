@@ -0,0 +1,109 @@
+use crate::board::{r#move::Move, BoardSize, PieceColor, PieceType, BOARD_SIZE};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Copy)]
+pub struct CastlingRights {
+    pub white: [bool; 2],
+    pub black: [bool; 2],
+}
+
+impl CastlingRights {
+    pub fn from_fen_string(fen: &str) -> Self {
+        Self {
+            white: [fen.contains('K'), fen.contains('Q')],
+            black: [fen.contains('k'), fen.contains('q')],
+        }
+    }
+
+    /// Returns the castling rights as they are written in a FEN string.
+    pub fn to_fen_string(self) -> String {
+        let mut fen = String::new();
+        for (right, symbol) in self
+            .white
+            .iter()
+            .zip(['K', 'Q'])
+            .chain(self.black.iter().zip(['k', 'q']))
+        {
+            if *right {
+                fen.push(symbol);
+            }
+        }
+        if fen.is_empty() {
+            fen.push('-');
+        }
+        fen
+    }
+
+    pub fn valid_castle_direction(&self, color: &PieceColor, direction: i32) -> bool {
+        let rights = match *color {
+            PieceColor::White => &self.white,
+            PieceColor::Black => &self.black,
+        };
+        match direction.signum() {
+            1 => rights[0],
+            -1 => rights[1],
+            0 => false,
+            _ => panic!("Unexpected castle direction: {}", direction),
+        }
+    }
+
+    /// Removes the rights the given move on a board of the given size loses, as the rooks start
+    /// in its corners.
+    pub fn update_after_move(&mut self, piece_move: &Move, size: &BoardSize) {
+        let last_file = size.files() - 1;
+        let (rights, own_back_rank) = match piece_move.piece_color() {
+            PieceColor::White => (&mut self.white, BOARD_SIZE - 1),
+            PieceColor::Black => (&mut self.black, size.top_rank()),
+        };
+        if *piece_move.piece_type() == PieceType::King {
+            *rights = [false; 2]
+        } else if *piece_move.piece_type() == PieceType::Rook
+            // Only a rook moving from its corner loses the right to castle with it
+            && *piece_move.from().rank() == own_back_rank
+        {
+            if *piece_move.from().file() == 0 {
+                rights[1] = false
+            } else if *piece_move.from().file() == last_file {
+                rights[0] = false
+            }
+        }
+
+        // Capturing a rook on its starting square removes the right to castle with it
+        if piece_move.is_capture() {
+            let (rights, back_rank) = match piece_move.piece_color() {
+                PieceColor::White => (&mut self.black, size.top_rank()),
+                PieceColor::Black => (&mut self.white, BOARD_SIZE - 1),
+            };
+            if *piece_move.to().rank() == back_rank {
+                if *piece_move.to().file() == 0 {
+                    rights[1] = false
+                } else if *piece_move.to().file() == last_file {
+                    rights[0] = false
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::{BoardPosition, ChessBoard};
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_update_after_move() {
+        let fen = Fen::from_string("4k2r/8/7r/8/8/8/8/R3K3 b Qk - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let mut rights = *fen.castling_rights();
+        // A rook on the edge of the board away from its corner leaves the right to castle with
+        // the rook in the corner
+        let rook_move = Move::from_board(square("h6"), square("d6"), &board);
+        rights.update_after_move(&rook_move, board.size());
+        assert_eq!(rights.to_fen_string(), "Qk");
+        let rook_move = Move::from_board(square("h8"), square("h7"), &board);
+        rights.update_after_move(&rook_move, board.size());
+        assert_eq!(rights.to_fen_string(), "Q");
+    }
+}
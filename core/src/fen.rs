@@ -0,0 +1,664 @@
+//! Contains the [Fen] struct which interprets a [Forsyth–Edwards Notation (FEN)](https://en.wikipedia.org/wiki/Forsyth%E2%80%93Edwards_Notation) string.
+//!
+//! The FEN is extended for the variants: the duck of [Variant::Duck] is written as `*` in the
+//! piece placement, and positions of [Variant::Antichess] need not have a king of each color.
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::board::{
+    BoardPosition, BoardSize, PieceColor, PieceType, Variant, BOARD_SIZE, MAX_FILES,
+};
+use crate::castling_rights::CastlingRights;
+
+/// The FEN which represents the default starting position.
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// The pieces on each square of the grid of squares, indexed by rank and file as in a
+/// [BoardPosition].
+pub type PiecePlacement = [[Option<(PieceColor, PieceType)>; MAX_FILES]; BOARD_SIZE];
+
+/// Why a string is not a valid FEN, as found when parsing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FenError {
+    /// The FEN does not have six fields separated by whitespace, but the given number.
+    Fields(usize),
+    /// The piece placement has a symbol which is not a piece, or ranks of different lengths.
+    PiecePlacement,
+    /// The board is larger than the grid of squares.
+    Size,
+    /// There is not one king of each color, outside [Variant::Antichess].
+    Kings,
+    /// There is a duck outside [Variant::Duck], or more than one.
+    Duck,
+    /// The active color is neither `w` nor `b`.
+    ActiveColor,
+    CastlingRights,
+    EnPassantTarget,
+    /// The halfmove clock or fullmove number is not a number.
+    MoveCounters,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::Fields(fields) => write!(f, "a FEN has 6 fields, not {}", fields),
+            FenError::PiecePlacement => write!(f, "invalid piece placement"),
+            FenError::Size => write!(f, "unsupported board size"),
+            FenError::Kings => write!(f, "there must be one king of each color"),
+            FenError::Duck => write!(f, "unexpected duck"),
+            FenError::ActiveColor => write!(f, "invalid active color"),
+            FenError::CastlingRights => write!(f, "invalid castling rights"),
+            FenError::EnPassantTarget => write!(f, "invalid en passant target square"),
+            FenError::MoveCounters => write!(f, "invalid halfmove clock or fullmove number"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// A representation of a board state based on FEN notation, which is saved as its FEN string.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Fen {
+    /// The piece placement.
+    piece_placement: PiecePlacement,
+    /// The size of the board, which is given by the number of ranks and of squares in each.
+    size: BoardSize,
+    /// The active color.
+    active_color: PieceColor,
+    /// The castling rights.
+    castling_rights: CastlingRights,
+    /// A square over which a pawn has just passed after moving two squares, if available.
+    ep_target_square: Option<BoardPosition>,
+    /// The number of halfmoves since the last capture or pawn advance.
+    halfmove_clock: i32,
+    /// The number of the full moves. It starts at 1 and is incremented after Black's move.
+    fullmove_number: i32,
+    /// The square of the duck in [Variant::Duck], once it has been placed.
+    duck: Option<BoardPosition>,
+}
+
+impl Fen {
+    /// Creates a new [Fen] from its parts.
+    pub fn new(
+        piece_placement: PiecePlacement,
+        active_color: PieceColor,
+        castling_rights: CastlingRights,
+        ep_target_square: Option<BoardPosition>,
+        halfmove_clock: i32,
+        fullmove_number: i32,
+    ) -> Self {
+        Fen {
+            piece_placement,
+            size: BoardSize::default(),
+            active_color,
+            castling_rights,
+            ep_target_square,
+            halfmove_clock,
+            fullmove_number,
+            duck: None,
+        }
+    }
+
+    /// Creates the position with the given pieces and player to move, with no castling rights and
+    /// at the first move.
+    pub fn with_pieces(
+        pieces: &[(PieceColor, PieceType, BoardPosition)],
+        active_color: PieceColor,
+    ) -> Self {
+        let mut piece_placement = [[None; MAX_FILES]; BOARD_SIZE];
+        for (color, piece_type, position) in pieces {
+            piece_placement[*position.rank()][*position.file()] = Some((*color, *piece_type));
+        }
+        Fen::new(
+            piece_placement,
+            active_color,
+            CastlingRights::from_fen_string("-"),
+            None,
+            0,
+            1,
+        )
+    }
+
+    /// Returns the position on a board of the given size, which the pieces must be on.
+    pub fn with_size(mut self, size: BoardSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Returns the position with the duck on the given square, which must be empty.
+    pub fn with_duck(mut self, duck: Option<BoardPosition>) -> Self {
+        self.duck = duck;
+        self
+    }
+
+    /// Returns the number of squares in the given rank of a piece placement, or None if it has any
+    /// other symbols than pieces and numbers of empty squares, or more squares than any board.
+    fn rank_files(rank: &str) -> Option<usize> {
+        let mut files = 0;
+        let mut empty_squares = 0;
+        for symbol in rank.chars() {
+            match symbol.to_digit(10) {
+                Some(digit) => empty_squares = empty_squares * 10 + digit as usize,
+                None => {
+                    if symbol != '*' {
+                        PieceType::from_letter(symbol.to_ascii_uppercase())?;
+                    }
+                    files += empty_squares + 1;
+                    empty_squares = 0;
+                }
+            }
+            if files + empty_squares > MAX_FILES {
+                return None;
+            }
+        }
+        Some(files + empty_squares)
+    }
+
+    /// Creates a new [Fen] from the given string, which must be a valid FEN of one of the
+    /// variants, as the positions written in the code are. Anything else, such as what a player
+    /// typed, is read with [Fen::read] or parsed with [str::parse] instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string is not a valid FEN.
+    pub fn from_string(fen_string: &str) -> Self {
+        fen_string
+            .parse()
+            .unwrap_or_else(|error| panic!("Invalid FEN {}: {}", fen_string, error))
+    }
+
+    /// Creates a new [Fen] from the given string, which has been checked to be valid.
+    fn build(fen_string: &str) -> Self {
+        // First split fen into sections separated by spaces
+        let split_fen = fen_string.split_whitespace().collect::<Vec<&str>>();
+
+        // Get piece placement data
+        let piece_placement_string = split_fen[0];
+        // Create an empty board state
+        let mut piece_placement = [[None; MAX_FILES]; BOARD_SIZE];
+        let mut duck = None;
+        let ranks = piece_placement_string.split('/').count();
+        let files = Self::rank_files(piece_placement_string.split('/').next().unwrap())
+            .expect("Unrecognised symbol in FEN.");
+        let size = BoardSize::new(ranks, files)
+            .unwrap_or_else(|| panic!("Unsupported board size in FEN: {}x{}", files, ranks));
+        // Populate it from the given fen string, filling the bottom ranks of a smaller board
+        for (rank, rank_str) in (size.top_rank()..).zip(piece_placement_string.split('/')) {
+            let mut file = 0;
+            let mut empty_squares = 0;
+            for symbol in rank_str.chars().collect::<Vec<char>>() {
+                if let Some(digit) = symbol.to_digit(10) {
+                    empty_squares = empty_squares * 10 + digit as usize;
+                } else if symbol == '*' {
+                    file += empty_squares;
+                    empty_squares = 0;
+                    duck = Some(BoardPosition::new(rank, file));
+                    file += 1;
+                } else {
+                    file += empty_squares;
+                    empty_squares = 0;
+                    let piece_color = if symbol.is_uppercase() {
+                        PieceColor::White
+                    } else {
+                        PieceColor::Black
+                    };
+                    let piece_type = PieceType::from_letter(symbol.to_uppercase().next().unwrap())
+                        .unwrap_or_else(|| panic!("Unrecognised symbol in FEN: {}", symbol));
+                    piece_placement[rank][file] = Some((piece_color, piece_type));
+                    file += 1;
+                }
+            }
+        }
+
+        // Get active color
+        let active_color = match split_fen[1] {
+            "w" => PieceColor::White,
+            "b" => PieceColor::Black,
+            _ => panic!("Unrecognised active color in FEN: {}", split_fen[1]),
+        };
+
+        // Get castling rights
+        let castling_rights = CastlingRights::from_fen_string(split_fen[2]);
+
+        // Get en passant target square
+        let ep_target_square = match split_fen[3] {
+            "-" => None,
+            square => BoardPosition::from_algebraic(square),
+        };
+
+        // Create Fen object
+        Fen {
+            piece_placement,
+            size,
+            active_color,
+            castling_rights,
+            ep_target_square,
+            halfmove_clock: split_fen[4].parse::<i32>().unwrap(),
+            fullmove_number: split_fen[5].parse::<i32>().unwrap(),
+            duck,
+        }
+    }
+
+    /// Creates a new [Fen] from the given string, or returns None if it is not a valid FEN of a
+    /// position with one king of each color.
+    pub fn parse(fen_string: &str) -> Option<Self> {
+        Self::parse_variant(fen_string, Variant::Standard)
+    }
+
+    /// Creates a new [Fen] of a position of the given variant from the given string, or returns
+    /// None if it is not a valid FEN of one. Only duck chess positions can have the duck, and
+    /// antichess positions can have any number of kings.
+    pub fn parse_variant(fen_string: &str, variant: Variant) -> Option<Self> {
+        Self::read(fen_string, variant).ok()
+    }
+
+    /// Creates a new [Fen] of a position of the given variant from the given string, or returns
+    /// why it is not a valid FEN of one.
+    pub fn read(fen_string: &str, variant: Variant) -> Result<Self, FenError> {
+        let split_fen = fen_string.split_whitespace().collect::<Vec<&str>>();
+        if split_fen.len() != 6 {
+            return Err(FenError::Fields(split_fen.len()));
+        }
+
+        // Every rank must have the same number of squares
+        let ranks = split_fen[0].split('/').collect::<Vec<&str>>();
+        let files = Self::rank_files(ranks[0]).ok_or(FenError::PiecePlacement)?;
+        for rank_str in &ranks {
+            if Self::rank_files(rank_str) != Some(files) {
+                return Err(FenError::PiecePlacement);
+            }
+        }
+        BoardSize::new(ranks.len(), files).ok_or(FenError::Size)?;
+        for king in ['K', 'k'] {
+            if split_fen[0].matches(king).count() != 1 && variant != Variant::Antichess {
+                return Err(FenError::Kings);
+            }
+        }
+        let ducks = split_fen[0].matches('*').count();
+        if ducks > usize::from(variant == Variant::Duck) {
+            return Err(FenError::Duck);
+        }
+
+        if !matches!(split_fen[1], "w" | "b") {
+            return Err(FenError::ActiveColor);
+        }
+        if split_fen[2] != "-" && !split_fen[2].chars().all(|right| "KQkq".contains(right)) {
+            return Err(FenError::CastlingRights);
+        }
+        // The square must be on one of the files of the board, which may have fewer than the grid
+        let valid_ep_target_square = split_fen[3] == "-"
+            || (split_fen[3].len() == 2
+                && split_fen[3]
+                    .chars()
+                    .next()
+                    .is_some_and(|file| ('a'..='l').take(files).any(|valid| valid == file))
+                && matches!(split_fen[3].chars().nth(1), Some('3' | '6')));
+        if !valid_ep_target_square {
+            return Err(FenError::EnPassantTarget);
+        }
+        // The counters are kept as non-negative i32s
+        let valid_counter = |field: &str| field.parse::<u32>().is_ok_and(|n| n <= i32::MAX as u32);
+        if !valid_counter(split_fen[4]) || !valid_counter(split_fen[5]) {
+            return Err(FenError::MoveCounters);
+        }
+        Ok(Self::build(fen_string))
+    }
+
+    /// Returns the piece placement.
+    pub fn piece_placement(&self) -> &PiecePlacement {
+        &self.piece_placement
+    }
+
+    /// Returns the size of the board.
+    pub fn size(&self) -> &BoardSize {
+        &self.size
+    }
+
+    /// Returns the active color.
+    pub fn active_color(&self) -> &PieceColor {
+        &self.active_color
+    }
+
+    /// Returns the castling rights.
+    pub fn castling_rights(&self) -> &CastlingRights {
+        &self.castling_rights
+    }
+
+    /// Returns the en passant target square.
+    #[allow(dead_code)]
+    pub fn ep_target_square(&self) -> &Option<BoardPosition> {
+        &self.ep_target_square
+    }
+
+    /// Returns the number of halfmoves since the last capture or pawn advance.
+    #[allow(dead_code)]
+    pub fn halfmove_clock(&self) -> &i32 {
+        &self.halfmove_clock
+    }
+
+    /// Returns the number of the full moves.
+    pub fn fullmove_number(&self) -> &i32 {
+        &self.fullmove_number
+    }
+
+    /// Returns the square of the duck, if it has been placed.
+    pub fn duck(&self) -> &Option<BoardPosition> {
+        &self.duck
+    }
+}
+
+impl fmt::Display for Fen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Piece placement
+        for (rank, pieces) in self
+            .piece_placement
+            .iter()
+            .enumerate()
+            .skip(self.size.top_rank())
+        {
+            let mut empty_squares = 0;
+            for (file, piece) in pieces[..self.size.files()].iter().enumerate() {
+                if self.duck == Some(BoardPosition::new(rank, file)) {
+                    if empty_squares > 0 {
+                        write!(f, "{}", empty_squares)?;
+                        empty_squares = 0;
+                    }
+                    write!(f, "*")?;
+                    continue;
+                }
+                match piece {
+                    Some((color, piece_type)) => {
+                        if empty_squares > 0 {
+                            write!(f, "{}", empty_squares)?;
+                            empty_squares = 0;
+                        }
+                        let symbol = piece_type.letter();
+                        match color {
+                            PieceColor::White => write!(f, "{}", symbol)?,
+                            PieceColor::Black => write!(f, "{}", symbol.to_ascii_lowercase())?,
+                        }
+                    }
+                    None => empty_squares += 1,
+                }
+            }
+            if empty_squares > 0 {
+                write!(f, "{}", empty_squares)?;
+            }
+            if rank < self.piece_placement.len() - 1 {
+                write!(f, "/")?;
+            }
+        }
+
+        // Active color
+        match self.active_color {
+            PieceColor::White => write!(f, " w")?,
+            PieceColor::Black => write!(f, " b")?,
+        }
+
+        // Castling rights
+        write!(f, " {}", self.castling_rights.to_fen_string())?;
+
+        // En passant target square
+        match self.ep_target_square {
+            Some(square) => write!(f, " {}", square.to_algebraic())?,
+            None => write!(f, " -")?,
+        }
+
+        write!(f, " {} {}", self.halfmove_clock, self.fullmove_number)
+    }
+}
+
+impl From<Fen> for String {
+    fn from(fen: Fen) -> Self {
+        fen.to_string()
+    }
+}
+
+impl FromStr for Fen {
+    type Err = FenError;
+
+    /// Reads a FEN of any variant, as positions are saved without their variant. A FEN which is
+    /// not valid in any is reported as it fails in standard chess.
+    fn from_str(fen_string: &str) -> Result<Self, Self::Err> {
+        Variant::iter()
+            .find_map(|variant| Fen::read(fen_string, variant).ok())
+            .ok_or_else(|| Fen::read(fen_string, Variant::Standard).unwrap_err())
+    }
+}
+
+impl TryFrom<String> for Fen {
+    type Error = FenError;
+
+    fn try_from(fen_string: String) -> Result<Self, Self::Error> {
+        fen_string.parse()
+    }
+}
+
+impl Default for Fen {
+    fn default() -> Self {
+        Fen::from_string(STARTING_FEN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Unit tests for the [Fen] module.
+    use crate::board::BOARD_SIZE;
+
+    use super::*;
+
+    #[test]
+    #[allow(clippy::needless_range_loop)]
+    fn test_fen_from_string() {
+        // Randomly generated fen
+        let fen_string = "5R2/2p4n/1Q6/6Pp/1R2P3/2P2b1K/P2krq2/2N5 w - - 0 1";
+
+        // Create a new fen from the above string
+        let fen = Fen::from_string(fen_string);
+
+        // Confirm that the fen has the correct properties
+        let expected_placement = [
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some((PieceColor::White, PieceType::Rook)),
+                None,
+                None,
+            ],
+            [
+                None,
+                None,
+                Some((PieceColor::Black, PieceType::Pawn)),
+                None,
+                None,
+                None,
+                None,
+                Some((PieceColor::Black, PieceType::Knight)),
+            ],
+            [
+                None,
+                Some((PieceColor::White, PieceType::Queen)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+            [
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some((PieceColor::White, PieceType::Pawn)),
+                Some((PieceColor::Black, PieceType::Pawn)),
+            ],
+            [
+                None,
+                Some((PieceColor::White, PieceType::Rook)),
+                None,
+                None,
+                Some((PieceColor::White, PieceType::Pawn)),
+                None,
+                None,
+                None,
+            ],
+            [
+                None,
+                None,
+                Some((PieceColor::White, PieceType::Pawn)),
+                None,
+                None,
+                Some((PieceColor::Black, PieceType::Bishop)),
+                None,
+                Some((PieceColor::White, PieceType::King)),
+            ],
+            [
+                Some((PieceColor::White, PieceType::Pawn)),
+                None,
+                None,
+                Some((PieceColor::Black, PieceType::King)),
+                Some((PieceColor::Black, PieceType::Rook)),
+                Some((PieceColor::Black, PieceType::Queen)),
+                None,
+                None,
+            ],
+            [
+                None,
+                None,
+                Some((PieceColor::White, PieceType::Knight)),
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+        ];
+        for rank in 0..BOARD_SIZE {
+            for file in 0..BOARD_SIZE {
+                if fen.piece_placement[rank][file].is_some() {
+                    assert_eq!(
+                        fen.piece_placement[rank][file].unwrap().0,
+                        expected_placement[rank][file].unwrap().0
+                    );
+                    assert_eq!(
+                        fen.piece_placement[rank][file].unwrap().1,
+                        expected_placement[rank][file].unwrap().1
+                    );
+                } else {
+                    assert_eq!(expected_placement[rank][file], None);
+                }
+            }
+        }
+
+        assert_eq!(fen.active_color, PieceColor::White);
+        assert_eq!(
+            fen.castling_rights,
+            CastlingRights {
+                white: [false, false],
+                black: [false, false]
+            }
+        );
+        assert_eq!(fen.ep_target_square, None);
+        assert_eq!(fen.halfmove_clock, 0);
+        assert_eq!(fen.fullmove_number, 1);
+    }
+
+    #[test]
+    fn test_fen_to_string() {
+        for fen_string in [
+            STARTING_FEN,
+            "5R2/2p4n/1Q6/6Pp/1R2P3/2P2b1K/P2krq2/2N5 w - - 0 1",
+            "rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        ] {
+            assert_eq!(Fen::from_string(fen_string).to_string(), fen_string);
+        }
+    }
+
+    #[test]
+    fn test_fen_parse() {
+        assert_eq!(
+            Fen::parse(STARTING_FEN).map(|fen| fen.to_string()),
+            Some(STARTING_FEN.to_string())
+        );
+        assert!(Fen::parse("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 3").is_some());
+        // An en passant target square off the files of the board is refused
+        assert_eq!(
+            Fen::read("4k3/8/8/3pP3/8/8/8/4K3 w - l6 0 3", Variant::Standard).unwrap_err(),
+            FenError::EnPassantTarget
+        );
+        assert!(Fen::parse("5k4/10/10/10/10/10/10/5K4 w - j6 0 3").is_some());
+        for fen_string in [
+            "",
+            "e4 e5 Nf3",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQ1BNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e9 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - a 1",
+        ] {
+            assert!(Fen::parse(fen_string).is_none(), "{}", fen_string);
+        }
+
+        // The variants extend the FEN
+        let duck = "4k3/8/8/3*4/4P3/8/8/4K3 b - - 0 1";
+        assert!(Fen::parse(duck).is_none());
+        let fen = Fen::parse_variant(duck, Variant::Duck).unwrap();
+        assert_eq!(*fen.duck(), BoardPosition::from_algebraic("d5"));
+        assert_eq!(fen.to_string(), duck);
+        assert!(Fen::parse_variant("4k3/8/8/3**3/8/8/8/4K3 b - - 0 1", Variant::Duck).is_none());
+        let antichess = "8/8/8/3p4/8/8/8/R7 w - - 0 30";
+        assert!(Fen::parse(antichess).is_none());
+        assert!(Fen::parse_variant(antichess, Variant::Antichess).is_some());
+    }
+
+    #[test]
+    fn test_fen_from_str() {
+        assert_eq!("e4 e5 Nf3".parse::<Fen>().unwrap_err(), FenError::Fields(3));
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"
+                .parse::<Fen>()
+                .unwrap_err(),
+            FenError::ActiveColor
+        );
+        // Positions of any variant are read
+        let duck: Fen = "4k3/8/8/3*4/4P3/8/8/4K3 b - - 0 1".parse().unwrap();
+        assert_eq!(*duck.duck(), BoardPosition::from_algebraic("d5"));
+        assert_eq!(
+            "4k3/8/8/3**3/8/8/8/4K3 b - - 0 1"
+                .parse::<Fen>()
+                .unwrap_err(),
+            FenError::Duck
+        );
+        // Numbers too large for the board are refused rather than overflowing
+        assert_eq!(
+            "4k3/99999999999999999999/8/8/8/8/8/4K3 w - - 0 1"
+                .parse::<Fen>()
+                .unwrap_err(),
+            FenError::PiecePlacement
+        );
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 3000000000"
+                .parse::<Fen>()
+                .unwrap_err(),
+            FenError::MoveCounters
+        );
+    }
+}
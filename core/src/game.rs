@@ -0,0 +1,598 @@
+//! A [Game] played move by move on a [ChessBoard], keeping the [GameLog] of everything which
+//! happened in it.
+
+use crate::board::game_log::{GameEvent, GameLog};
+use crate::board::r#move::Move;
+use crate::board::{
+    BoardPosition, ChessBoard, GameEndStatus, MoveError, PieceColor, ResetBoardEvent, Variant,
+};
+use crate::fen::{Fen, FenError};
+
+/// The number of moves of both players without a capture or a pawn move after which a draw can
+/// be claimed.
+pub const FIFTY_MOVE_PLIES: i32 = 100;
+
+/// A game of chess or one of its variants, which is ended automatically by checkmate, stalemate
+/// and the like after each move. A draw by the fifty-move rule or threefold repetition must be
+/// [claimed](Game::claim_draw).
+#[derive(Clone)]
+pub struct Game {
+    board: ChessBoard,
+    log: GameLog,
+}
+
+impl Default for Game {
+    /// Starts a game of standard chess from the starting position.
+    fn default() -> Self {
+        Game::new(Fen::default(), Variant::Standard)
+    }
+}
+
+impl Game {
+    /// Starts a game of the given variant from the given position.
+    pub fn new(fen: Fen, variant: Variant) -> Self {
+        let mut game = Game {
+            board: ChessBoard::default(),
+            log: GameLog::default(),
+        };
+        game.record(GameEvent::Reset(
+            ResetBoardEvent::new(fen).with_variant(variant),
+        ));
+        game
+    }
+
+    /// Replays the given moves of a game already played from the given position of the given
+    /// variant, ending the game if it is over after the last of them. The moves are not checked,
+    /// so they must be ones which were checked as they were played.
+    pub fn replay(fen: Fen, variant: Variant, moves: &[Move]) -> Self {
+        let mut game = Game {
+            board: ChessBoard::default(),
+            log: GameLog::default(),
+        };
+        game.log.record(
+            &mut game.board,
+            GameEvent::Reset(ResetBoardEvent::new(fen).with_variant(variant)),
+        );
+        for piece_move in moves {
+            game.log
+                .record(&mut game.board, GameEvent::Moved(*piece_move));
+        }
+        game.end_if_over();
+        game
+    }
+
+    /// Starts a game of standard chess from the position of the given FEN string.
+    pub fn from_fen(fen_string: &str) -> Result<Self, FenError> {
+        Ok(Game::new(
+            Fen::read(fen_string, Variant::Standard)?,
+            Variant::Standard,
+        ))
+    }
+
+    pub fn board(&self) -> &ChessBoard {
+        &self.board
+    }
+
+    /// Returns the events of the game so far, which [fold](crate::board::game_log::fold) back to
+    /// its board.
+    pub fn events(&self) -> &[GameEvent] {
+        self.log.events()
+    }
+
+    /// Returns the board after each half-move of the game, starting with its starting position.
+    /// Each board is the game as it was then, with the castling rights, move number, duck and
+    /// past moves of that position and any draw offer made in it.
+    pub fn positions(&self) -> impl Iterator<Item = ChessBoard> + '_ {
+        let mut board = ChessBoard::default();
+        let mut events = self.events().iter().peekable();
+        std::iter::from_fn(move || {
+            // A position takes in its move and everything which happened up to the next move
+            let mut applied = false;
+            while let Some(event) =
+                events.next_if(|event| !applied || !matches!(event, GameEvent::Moved(_)))
+            {
+                board.apply_event(event);
+                applied = true;
+            }
+            applied.then(|| board.clone())
+        })
+    }
+
+    /// Returns the board after the given number of half-moves from the starting position, if
+    /// that many have been made.
+    pub fn position_at(&self, ply: usize) -> Option<ChessBoard> {
+        self.positions().nth(ply)
+    }
+
+    /// Returns the legal moves of the player to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        self.board.legal_moves_iter().collect()
+    }
+
+    /// Makes the given move, if it is legal.
+    pub fn play(&mut self, piece_move: &Move) -> Result<(), MoveError> {
+        self.board.check_move(piece_move)?;
+        self.record(GameEvent::Moved(*piece_move));
+        Ok(())
+    }
+
+    /// Makes the legal move given in standard algebraic notation, returning it.
+    pub fn play_algebraic(&mut self, text: &str) -> Result<Move, MoveError> {
+        if self.board.active_color().is_none() {
+            return Err(MoveError::GameOver);
+        }
+        let piece_move = Move::from_algebraic(text, &self.board)
+            .ok_or_else(|| MoveError::Unrecognised(text.to_string()))?;
+        self.play(&piece_move)?;
+        Ok(piece_move)
+    }
+
+    /// Places the duck on the given square of a game of [Variant::Duck], once a piece has been
+    /// moved without placing it.
+    pub fn place_duck(&mut self, position: BoardPosition) -> Result<(), MoveError> {
+        if self.board.active_color().is_none() {
+            return Err(MoveError::GameOver);
+        }
+        if !self.board.valid_duck_square(&position) {
+            return Err(MoveError::Illegal);
+        }
+        self.record(GameEvent::DuckPlaced(position));
+        Ok(())
+    }
+
+    /// Ends the game with the given player resigning.
+    pub fn resign(&mut self, color: PieceColor) -> Result<(), MoveError> {
+        if self.board.active_color().is_none() {
+            return Err(MoveError::GameOver);
+        }
+        self.record(GameEvent::Ended {
+            status: GameEndStatus::Resignation,
+            winner: Some(color.opposite()),
+        });
+        Ok(())
+    }
+
+    /// Ends the game in a draw claimed by the player to move, by the fifty-move rule once
+    /// [FIFTY_MOVE_PLIES] have been made without a capture or a pawn move, or by threefold
+    /// repetition once the position has occurred three times. Returns which of them ended it.
+    pub fn claim_draw(&mut self) -> Result<GameEndStatus, MoveError> {
+        if self.board.active_color().is_none() {
+            return Err(MoveError::GameOver);
+        }
+        let status = if *self.board.halfmove_clock() >= FIFTY_MOVE_PLIES {
+            GameEndStatus::FiftyMoves
+        } else if self.repetitions() >= 3 {
+            GameEndStatus::Repetition
+        } else {
+            return Err(MoveError::NoDrawToClaim);
+        };
+        self.record(GameEvent::Ended {
+            status,
+            winner: None,
+        });
+        Ok(status)
+    }
+
+    /// Returns the number of times the current position has occurred in the game, as the same
+    /// pieces on the same squares with the same player to move and the same castling and en
+    /// passant captures, whatever the move counters.
+    fn repetitions(&self) -> usize {
+        let position = |board: &ChessBoard| {
+            let fen = board.to_fen().to_string();
+            fen.split(' ').take(4).collect::<Vec<_>>().join(" ")
+        };
+        let current = position(&self.board);
+        self.positions()
+            .filter(|board| position(board) == current)
+            .count()
+    }
+
+    /// Records the given event, ending the game if it is now over.
+    fn record(&mut self, event: GameEvent) {
+        self.log.record(&mut self.board, event);
+        self.end_if_over();
+    }
+
+    /// Ends the game if it is over by checkmate, stalemate or the like.
+    fn end_if_over(&mut self) {
+        if let Some((status, winner)) = self.board.game_end() {
+            self.log
+                .record(&mut self.board, GameEvent::Ended { status, winner });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::game_log::fold;
+    use crate::board::{CastleFailure, PieceType};
+
+    use super::*;
+
+    #[test]
+    fn test_play() {
+        let mut game = Game::default();
+        for text in ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6"] {
+            game.play_algebraic(text).unwrap();
+        }
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let backwards = Move::from_board(square("e4"), square("e3"), game.board());
+        assert_eq!(game.play(&backwards), Err(MoveError::Illegal));
+        let black_move = Move::from_board(square("e8"), square("e7"), game.board());
+        assert_eq!(
+            game.play(&black_move),
+            Err(MoveError::WrongTurn(PieceColor::White))
+        );
+        assert_eq!(
+            game.play_algebraic("Ke3"),
+            Err(MoveError::Unrecognised("Ke3".to_string()))
+        );
+
+        game.play_algebraic("Qxf7").unwrap();
+        assert_eq!(
+            *game.board().game_end_status(),
+            Some(GameEndStatus::Checkmate)
+        );
+        assert_eq!(game.play_algebraic("Ke7"), Err(MoveError::GameOver));
+        assert_eq!(game.resign(PieceColor::Black), Err(MoveError::GameOver));
+        // The events of the game fold back to its board
+        assert_eq!(
+            fold(game.events()).to_fen().to_string(),
+            game.board().to_fen().to_string()
+        );
+        assert_eq!(fold(game.events()).past_moves(), game.board().past_moves());
+    }
+
+    #[test]
+    fn test_position_at() {
+        let mut game = Game::from_fen("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1").unwrap();
+        for text in ["O-O", "Kd8", "Rf8+"] {
+            game.play_algebraic(text).unwrap();
+        }
+        assert_eq!(game.positions().count(), 4);
+        let castled = game.position_at(1).unwrap();
+        assert_eq!(
+            castled.to_fen().to_string(),
+            "r3k3/8/8/8/8/8/8/5RK1 b q - 1 1"
+        );
+        assert_eq!(castled.past_moves().len(), 1);
+        assert_eq!(*castled.game_end_status(), None);
+        let king_moved = game.position_at(2).unwrap();
+        assert_eq!(
+            king_moved.to_fen().to_string(),
+            "r2k4/8/8/8/8/8/8/5RK1 w - - 2 2"
+        );
+        assert_eq!(game.position_at(3).unwrap().past_moves().len(), 3);
+        assert!(game.position_at(4).is_none());
+
+        // A game replayed from its moves has the same positions
+        let replayed = Game::replay(
+            game.position_at(0).unwrap().to_fen(),
+            Variant::Standard,
+            game.board().past_moves(),
+        );
+        for (replayed, played) in replayed.positions().zip(game.positions()) {
+            assert_eq!(replayed.to_fen().to_string(), played.to_fen().to_string());
+        }
+    }
+
+    #[test]
+    fn test_from_fen() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4r3/R3K3 w Q - 0 1").unwrap();
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let stays_in_check = Move::from_board(square("a1"), square("a2"), game.board());
+        assert_eq!(game.play(&stays_in_check), Err(MoveError::IntoCheck));
+        let castle = Move::from_board(square("e1"), square("c1"), game.board());
+        assert_eq!(
+            game.play(&castle),
+            Err(MoveError::Castle(CastleFailure::InCheck))
+        );
+        game.resign(PieceColor::White).unwrap();
+        assert_eq!(*game.board().winner(), Some(PieceColor::Black));
+        assert_eq!(
+            Game::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1").err(),
+            Some(FenError::Kings)
+        );
+    }
+
+    // The edge cases of the FIDE Laws of Chess, played through games so that they hold however
+    // the board comes to work them out
+
+    /// Returns the game of standard chess from the given position after the given moves.
+    fn played(fen: &str, moves: &[&str]) -> Game {
+        let mut game = Game::from_fen(fen).unwrap();
+        for text in moves {
+            game.play_algebraic(text).unwrap();
+        }
+        game
+    }
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    #[test]
+    fn test_en_passant_immediately() {
+        let cases: [(&str, &[&str], &str, &str); 3] = [
+            (
+                START,
+                &["e4", "a6", "e5", "d5"],
+                "exd6",
+                "rnbqkbnr/1pp1pppp/p2P4/8/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 3",
+            ),
+            (
+                START,
+                &["a3", "h5", "a4", "h4", "g4"],
+                "hxg3",
+                "rnbqkbnr/ppppppp1/8/8/P7/6p1/1PPPPP1P/RNBQKBNR w KQkq - 0 4",
+            ),
+            // The target square can be given by the position the game starts from
+            (
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2",
+                &[],
+                "exd6",
+                "4k3/8/3P4/8/8/8/8/4K3 b - - 0 2",
+            ),
+        ];
+        for (fen, moves, capture, after) in cases {
+            let mut game = played(fen, moves);
+            game.play_algebraic(capture).unwrap();
+            assert_eq!(game.board().to_fen().to_string(), after, "{}", capture);
+        }
+        // The double step which can be captured is written in the position
+        assert_eq!(
+            played(START, &["e4", "a6", "e5", "d5"])
+                .board()
+                .to_fen()
+                .to_string(),
+            "rnbqkbnr/1pp1pppp/p7/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3"
+        );
+    }
+
+    #[test]
+    fn test_en_passant_rules() {
+        // En passant is only possible straight after the pawn passes, only over a double step
+        // and not if it would expose the king
+        let cases: [(&str, &[&str], &str, MoveError); 4] = [
+            (
+                START,
+                &["e4", "a6", "e5", "d5", "a3", "a5"],
+                "e5",
+                MoveError::Illegal,
+            ),
+            (START, &["e4", "d6", "e5", "d5"], "e5", MoveError::Illegal),
+            (START, &["e4", "d5", "e5", "a6"], "e5", MoveError::Illegal),
+            (
+                "4k3/3p4/8/K3P2r/8/8/8/8 b - - 0 1",
+                &["d5"],
+                "e5",
+                MoveError::IntoCheck,
+            ),
+        ];
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        for (fen, moves, from, expected) in cases {
+            let mut game = played(fen, moves);
+            let capture = Move::from_board(square(from), square("d6"), game.board());
+            assert_eq!(game.play(&capture), Err(expected), "{:?}", moves);
+        }
+    }
+
+    #[test]
+    fn test_castling_rules() {
+        let both_sides = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        type Case<'a> = (
+            &'a str,
+            &'a [&'a str],
+            &'a str,
+            &'a str,
+            Result<(), MoveError>,
+        );
+        let cases: [Case; 9] = [
+            (both_sides, &[], "e1", "g1", Ok(())),
+            (both_sides, &[], "e1", "c1", Ok(())),
+            // The rook, and the square the queenside rook passes, may be attacked
+            (
+                "1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1",
+                &[],
+                "e1",
+                "c1",
+                Ok(()),
+            ),
+            ("4k2r/8/8/8/8/8/8/4K2R w Kk - 0 1", &[], "e1", "g1", Ok(())),
+            // Having given or been in check before does not lose the right
+            (
+                "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+                &["Ra8+", "Kd7", "Ra1", "Ke8"],
+                "e1",
+                "g1",
+                Ok(()),
+            ),
+            // The king may not castle out of or through check
+            (
+                "4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+                &[],
+                "e1",
+                "g1",
+                Err(MoveError::Castle(CastleFailure::InCheck)),
+            ),
+            (
+                "5rk1/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+                &[],
+                "e1",
+                "g1",
+                Err(MoveError::Castle(CastleFailure::ThroughCheck)),
+            ),
+            // A king or rook which has moved and come back has lost the right
+            (
+                "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1",
+                &["Rh2", "Kd8", "Rh1", "Ke8"],
+                "e1",
+                "g1",
+                Err(MoveError::Castle(CastleFailure::NoRights)),
+            ),
+            (
+                START,
+                &[],
+                "e1",
+                "g1",
+                Err(MoveError::Castle(CastleFailure::PathOccupied)),
+            ),
+        ];
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        for (fen, moves, from, to, expected) in cases {
+            let game = played(fen, moves);
+            let castle = Move::from_board(square(from), square(to), game.board());
+            let mut after = game.clone();
+            assert_eq!(after.play(&castle), expected, "{} {:?}", fen, moves);
+        }
+
+        // Nor into check
+        let mut game = played("6rk/8/8/8/8/8/8/R3K2R w KQ - 0 1", &[]);
+        let castle = Move::from_board(square("e1"), square("g1"), game.board());
+        assert!(game.play(&castle).is_err());
+
+        // A FEN may give a right without a rook in the corner, which cannot be castled with
+        for (fen, text) in [
+            ("4k3/8/8/8/8/8/8/4K3 w K - 0 1", "O-O"),
+            ("4k3/8/8/8/8/8/8/3K4 w KQ - 0 1", "O-O-O"),
+            ("7k/8/8/8/8/8/8/4K2R w Q - 0 1", "O-O-O"),
+        ] {
+            let mut game = Game::from_fen(fen).unwrap();
+            assert!(game.play_algebraic(text).is_err(), "{} {}", fen, text);
+            assert_eq!(game.board().to_fen().to_string(), fen);
+        }
+        // Nor with a rook of the other color there
+        let mut game = played("4k3/8/8/8/8/8/8/4K2r w K - 0 1", &[]);
+        let castle = Move::from_board(square("e1"), square("g1"), game.board());
+        assert_eq!(
+            game.play(&castle),
+            Err(MoveError::Castle(CastleFailure::NoRights))
+        );
+    }
+
+    #[test]
+    fn test_promotion_rules() {
+        let fen = "1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        let cases = [
+            ("a8=Q", true),
+            ("axb8=Q+", true),
+            ("axb8=N", true),
+            ("axb8=R+", true),
+            // A pawn reaching the last rank must promote, and not to a king or a pawn
+            ("a8", false),
+            ("axb8", false),
+            ("axb8=K", false),
+            ("axb8=P", false),
+        ];
+        let b8 = BoardPosition::from_algebraic("b8").unwrap();
+        for (text, legal) in cases {
+            let mut game = played(fen, &[]);
+            assert_eq!(game.play_algebraic(text).is_ok(), legal, "{}", text);
+            if legal && text.starts_with("axb8") {
+                assert_eq!(game.board().get_piece_color(&b8), Some(PieceColor::White));
+                assert_ne!(game.board().get_piece_type(&b8), Some(PieceType::Pawn));
+            }
+        }
+        // Promoting with a capture can give checkmate
+        let game = played("1r5k/P5pp/8/8/8/8/8/4K3 w - - 0 1", &["axb8=Q#"]);
+        assert_eq!(
+            *game.board().game_end_status(),
+            Some(GameEndStatus::Checkmate)
+        );
+    }
+
+    #[test]
+    fn test_checkmate_and_stalemate() {
+        type Ending = Option<(GameEndStatus, Option<PieceColor>)>;
+        let cases: [(&str, &str, Ending); 5] = [
+            (
+                "7k/5Q2/6K1/8/8/8/8/8 w - - 0 1",
+                "Qg7#",
+                Some((GameEndStatus::Checkmate, Some(PieceColor::White))),
+            ),
+            // No legal moves without being in check is stalemate, a draw
+            (
+                "7k/8/6K1/8/8/8/8/5Q2 w - - 0 1",
+                "Qf7",
+                Some((GameEndStatus::Stalemate, None)),
+            ),
+            // A king cannot escape check by moving along the line it is checked on
+            (
+                "k7/8/1K6/8/8/8/8/7R w - - 0 1",
+                "Rh8#",
+                Some((GameEndStatus::Checkmate, Some(PieceColor::White))),
+            ),
+            // A check which can be blocked or the checker taken is not checkmate
+            ("k7/1r6/8/8/8/8/8/K6R w - - 0 1", "Rh8+", None),
+            // Nor is it stalemate while a pinned piece can move along its pin
+            ("k7/1bK5/1P6/8/8/8/7Q/8 w - - 0 1", "Qh1", None),
+        ];
+        for (fen, text, expected) in cases {
+            let board = played(fen, &[text]).board().clone();
+            let ended = board
+                .game_end_status()
+                .map(|status| (status, *board.winner()));
+            assert_eq!(ended, expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_fifty_moves_and_repetition() {
+        let shuffle = ["Ra2", "Kg8", "Ra1", "Kh8"];
+        // Neither the fifty-move rule nor threefold repetition ends the game by itself, as under
+        // the rules a player must claim the draw, which they can once fifty moves each are made
+        let mut game = played("7k/8/6K1/8/8/8/8/R7 w - - 0 1", &shuffle.repeat(25));
+        assert_eq!(*game.board().halfmove_clock(), FIFTY_MOVE_PLIES);
+        assert_eq!(*game.board().game_end_status(), None);
+        assert_eq!(game.claim_draw(), Ok(GameEndStatus::FiftyMoves));
+        assert_eq!(*game.board().winner(), None);
+        assert_eq!(game.claim_draw(), Err(MoveError::GameOver));
+
+        // The clock is read from and written to the FEN, and a move short of fifty cannot claim
+        let fifty_moves = "7k/8/6K1/8/8/8/8/R7 w - - 98 80";
+        let mut game = played(fifty_moves, &["Ra2"]);
+        assert_eq!(
+            game.board().to_fen().to_string(),
+            "7k/8/6K1/8/8/8/R7/8 b - - 99 80"
+        );
+        assert_eq!(game.claim_draw(), Err(MoveError::NoDrawToClaim));
+        game.play_algebraic("Kg8").unwrap();
+        assert_eq!(game.claim_draw(), Ok(GameEndStatus::FiftyMoves));
+        // A checkmate on the move which reaches fifty moves stands
+        let mated = played("7k/8/6K1/8/8/8/8/R7 w - - 99 80", &["Ra8#"]);
+        assert_eq!(
+            *mated.board().game_end_status(),
+            Some(GameEndStatus::Checkmate)
+        );
+
+        // A capture or a pawn move starts the count again
+        let capture = played("7k/8/6K1/8/8/8/r7/R7 w - - 60 80", &["Rxa2"]);
+        assert_eq!(*capture.board().halfmove_clock(), 0);
+        let pawn = played("7k/8/6K1/8/8/8/P7/R7 w - - 60 80", &["a3", "Kg8", "Ra2"]);
+        assert_eq!(*pawn.board().halfmove_clock(), 2);
+
+        // A position which has occurred three times can be claimed, but not one which has occurred
+        // twice
+        let mut game = played("7k/8/6K1/8/8/8/8/R7 w - - 0 1", &shuffle);
+        assert_eq!(game.claim_draw(), Err(MoveError::NoDrawToClaim));
+        for text in shuffle {
+            game.play_algebraic(text).unwrap();
+        }
+        assert_eq!(*game.board().game_end_status(), None);
+        // The repeated positions have the same pieces on the same squares and the same player to
+        // move, whatever the move number
+        let positions: Vec<String> = game
+            .positions()
+            .map(|board| {
+                let fen = board.to_fen().to_string();
+                fen.split(' ').take(2).collect::<Vec<_>>().join(" ")
+            })
+            .collect();
+        assert_eq!(positions[0], positions[4]);
+        assert_eq!(positions[0], positions[8]);
+        assert_ne!(positions[0], positions[2]);
+        assert_eq!(game.claim_draw(), Ok(GameEndStatus::Repetition));
+        assert_eq!(
+            *game.board().game_end_status(),
+            Some(GameEndStatus::Repetition)
+        );
+    }
+}
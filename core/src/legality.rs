@@ -0,0 +1,330 @@
+//! Checks of positions which are valid [Fen]s but could not arise in a game, such as one where
+//! the player who has just moved left their king in check, with a fix for each problem which has
+//! an obvious one.
+//!
+//! The limits scale with the width of the board, so a board of ten files allows ten pawns and
+//! twenty pieces a side.
+
+use std::fmt;
+
+use crate::board::{BoardPosition, ChessBoard, PieceColor, PieceType, Variant, BOARD_SIZE};
+use crate::castling_rights::CastlingRights;
+use crate::fen::{Fen, PiecePlacement};
+
+/// A reason the position of a FEN could not arise in a game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The player not to move, of the given color, is in check, so their king could be taken.
+    OpponentInCheck(PieceColor),
+    /// Both kings are in check.
+    BothInCheck,
+    /// The player of the given color has the given number of pieces, more than they start with.
+    TooManyPieces(PieceColor, usize),
+    /// The player of the given color has the given number of pawns, more than they start with.
+    TooManyPawns(PieceColor, usize),
+    /// There are pawns on the given squares of the first or last rank, where no pawn can stand.
+    PawnsOnBackRank(Vec<BoardPosition>),
+    /// The given castling rights are held for a king or rook which is not on its starting square.
+    CastlingRights(CastlingRights),
+    /// No pawn can just have passed over the en passant target square.
+    EnPassantTarget(BoardPosition),
+}
+
+impl Problem {
+    /// Returns the locale key of the message explaining the problem.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Problem::OpponentInCheck(_) => "problem-opponent-in-check",
+            Problem::BothInCheck => "problem-both-in-check",
+            Problem::TooManyPieces(..) => "problem-too-many-pieces",
+            Problem::TooManyPawns(..) => "problem-too-many-pawns",
+            Problem::PawnsOnBackRank(_) => "problem-pawns-on-back-rank",
+            Problem::CastlingRights(_) => "problem-castling-rights",
+            Problem::EnPassantTarget(_) => "problem-en-passant-target",
+        }
+    }
+
+    /// Returns the given position with the problem fixed, or None if it has no obvious fix. The
+    /// player to move is switched if only the other is in check, pawns on the back ranks are
+    /// removed, and castling rights and en passant target squares which cannot be used are
+    /// cleared.
+    pub fn fix(&self, fen: &Fen) -> Option<Fen> {
+        let mut placement = *fen.piece_placement();
+        let mut active_color = *fen.active_color();
+        let mut castling_rights = *fen.castling_rights();
+        let mut ep_target_square = *fen.ep_target_square();
+        match self {
+            Problem::OpponentInCheck(color) => active_color = *color,
+            Problem::PawnsOnBackRank(positions) => {
+                for position in positions {
+                    placement[*position.rank()][*position.file()] = None;
+                }
+            }
+            Problem::CastlingRights(invalid) => {
+                for (rights, invalid) in [
+                    (&mut castling_rights.white, invalid.white),
+                    (&mut castling_rights.black, invalid.black),
+                ] {
+                    for (right, invalid) in rights.iter_mut().zip(invalid) {
+                        *right &= !invalid;
+                    }
+                }
+            }
+            Problem::EnPassantTarget(_) => ep_target_square = None,
+            Problem::BothInCheck | Problem::TooManyPieces(..) | Problem::TooManyPawns(..) => {
+                return None
+            }
+        }
+        Some(
+            Fen::new(
+                placement,
+                active_color,
+                castling_rights,
+                ep_target_square,
+                *fen.halfmove_clock(),
+                *fen.fullmove_number(),
+            )
+            .with_size(*fen.size())
+            .with_duck(*fen.duck()),
+        )
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::OpponentInCheck(color) => {
+                write!(f, "{:?} is in check but it is not their move", color)
+            }
+            Problem::BothInCheck => write!(f, "both kings are in check"),
+            Problem::TooManyPieces(color, count) => {
+                write!(f, "{:?} has too many pieces ({})", color, count)
+            }
+            Problem::TooManyPawns(color, count) => {
+                write!(f, "{:?} has too many pawns ({})", color, count)
+            }
+            Problem::PawnsOnBackRank(positions) => {
+                let squares: Vec<String> = positions.iter().map(|p| p.to_algebraic()).collect();
+                write!(
+                    f,
+                    "pawns on the first or last rank ({})",
+                    squares.join(", ")
+                )
+            }
+            Problem::CastlingRights(rights) => {
+                write!(
+                    f,
+                    "castling rights without the king and rook ({})",
+                    rights.to_fen_string()
+                )
+            }
+            Problem::EnPassantTarget(position) => {
+                write!(
+                    f,
+                    "impossible en passant target square ({})",
+                    position.to_algebraic()
+                )
+            }
+        }
+    }
+}
+
+/// Returns whether the piece of the given color and type is on the given square of the placement.
+fn has_piece(
+    placement: &PiecePlacement,
+    position: BoardPosition,
+    color: PieceColor,
+    piece_type: PieceType,
+) -> bool {
+    placement[*position.rank()][*position.file()] == Some((color, piece_type))
+}
+
+/// Returns the castling rights of the given position which are held for a king or a rook which
+/// is not on its starting square, where the king starts anywhere on its back rank between the
+/// rooks in the corners.
+fn invalid_castling_rights(fen: &Fen, placement: &PiecePlacement) -> CastlingRights {
+    let size = fen.size();
+    let last_file = size.files() - 1;
+    let mut invalid = CastlingRights::default();
+    for (color, rights, invalid) in [
+        (
+            PieceColor::White,
+            fen.castling_rights().white,
+            &mut invalid.white,
+        ),
+        (
+            PieceColor::Black,
+            fen.castling_rights().black,
+            &mut invalid.black,
+        ),
+    ] {
+        let back_rank = size.last_rank(&color.opposite());
+        let king_file = (0..size.files()).find(|file| {
+            has_piece(
+                placement,
+                BoardPosition::new(back_rank, *file),
+                color,
+                PieceType::King,
+            )
+        });
+        let rook = |file| {
+            has_piece(
+                placement,
+                BoardPosition::new(back_rank, file),
+                color,
+                PieceType::Rook,
+            )
+        };
+        let kingside = king_file.is_some_and(|king| king < last_file) && rook(last_file);
+        let queenside = king_file.is_some_and(|king| king > 0) && rook(0);
+        *invalid = [rights[0] && !kingside, rights[1] && !queenside];
+    }
+    invalid
+}
+
+/// Returns whether a pawn of the player who has just moved can have passed over the given square
+/// with its last move, landing on the square beyond it from squares which are now empty.
+fn valid_ep_target_square(fen: &Fen, placement: &PiecePlacement, square: BoardPosition) -> bool {
+    let mover = fen.active_color().opposite();
+    // The pawn moves towards the top of the grid for white
+    let (start_rank, forwards) = match mover {
+        PieceColor::White => (BOARD_SIZE - 2, -1),
+        PieceColor::Black => (fen.size().top_rank() + 1, 1),
+    };
+    let rank = |steps: isize| (start_rank as isize + forwards * steps) as usize;
+    let file = *square.file();
+    *square.rank() == rank(1)
+        && placement[rank(0)][file].is_none()
+        && placement[rank(1)][file].is_none()
+        && has_piece(
+            placement,
+            BoardPosition::new(rank(2), file),
+            mover,
+            PieceType::Pawn,
+        )
+}
+
+/// Returns the problems of the position of the given FEN in the given variant, where only
+/// standard chess has check.
+pub fn problems(fen: &Fen, variant: Variant) -> Vec<Problem> {
+    let placement = fen.piece_placement();
+    let size = fen.size();
+    let mut problems = Vec::new();
+
+    if variant == Variant::Standard {
+        let board = ChessBoard::with_position(fen).with_variant(variant);
+        let mover = *fen.active_color();
+        match (board.in_check(&mover), board.in_check(&mover.opposite())) {
+            (true, true) => problems.push(Problem::BothInCheck),
+            (false, true) => problems.push(Problem::OpponentInCheck(mover.opposite())),
+            _ => {}
+        }
+    }
+
+    for color in [PieceColor::White, PieceColor::Black] {
+        let pieces: Vec<PieceType> = size
+            .positions()
+            .filter_map(|position| placement[*position.rank()][*position.file()])
+            .filter(|(piece_color, _)| *piece_color == color)
+            .map(|(_, piece_type)| piece_type)
+            .collect();
+        if pieces.len() > size.files() * 2 {
+            problems.push(Problem::TooManyPieces(color, pieces.len()));
+        }
+        let pawns = pieces
+            .iter()
+            .filter(|piece| **piece == PieceType::Pawn)
+            .count();
+        if pawns > size.files() {
+            problems.push(Problem::TooManyPawns(color, pawns));
+        }
+    }
+
+    let back_rank_pawns: Vec<BoardPosition> = size
+        .positions()
+        .filter(|position| [size.top_rank(), BOARD_SIZE - 1].contains(position.rank()))
+        .filter(|position| {
+            placement[*position.rank()][*position.file()]
+                .is_some_and(|(_, piece_type)| piece_type == PieceType::Pawn)
+        })
+        .collect();
+    if !back_rank_pawns.is_empty() {
+        problems.push(Problem::PawnsOnBackRank(back_rank_pawns));
+    }
+
+    let invalid = invalid_castling_rights(fen, placement);
+    if invalid != CastlingRights::default() {
+        problems.push(Problem::CastlingRights(invalid));
+    }
+
+    if let Some(square) = fen.ep_target_square() {
+        if !valid_ep_target_square(fen, placement, *square) {
+            problems.push(Problem::EnPassantTarget(*square));
+        }
+    }
+    problems
+}
+
+/// Returns the given position with the castling rights it cannot use cleared, which a position is
+/// never played with as castling needs the king and the rook on their starting squares.
+pub fn fix_castling_rights(fen: &Fen, variant: Variant) -> Fen {
+    problems(fen, variant)
+        .iter()
+        .filter(|problem| matches!(problem, Problem::CastlingRights(_)))
+        .fold(fen.clone(), |fen, problem| problem.fix(&fen).unwrap_or(fen))
+}
+
+/// Returns the given position with every problem which has a fix fixed.
+pub fn fix_all(fen: &Fen, variant: Variant) -> Fen {
+    problems(fen, variant)
+        .iter()
+        .fold(fen.clone(), |fen, problem| problem.fix(&fen).unwrap_or(fen))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems() {
+        assert!(problems(&Fen::default(), Variant::Standard).is_empty());
+        let after_e4 =
+            Fen::from_string("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert_eq!(problems(&after_e4, Variant::Standard), []);
+
+        // White is to move with black in check, a white pawn is on the first rank, neither player
+        // has the rook to castle with and no pawn has just passed over d6
+        let fen = Fen::from_string("4k3/8/8/8/8/8/4Q3/P3K3 w Kq d6 0 1");
+        let found = problems(&fen, Variant::Standard);
+        assert_eq!(
+            found,
+            [
+                Problem::OpponentInCheck(PieceColor::Black),
+                Problem::PawnsOnBackRank(vec![BoardPosition::from_algebraic("a1").unwrap()]),
+                Problem::CastlingRights(CastlingRights::from_fen_string("Kq")),
+                Problem::EnPassantTarget(BoardPosition::from_algebraic("d6").unwrap()),
+            ]
+        );
+        assert_eq!(found[1].to_string(), "pawns on the first or last rank (a1)");
+        assert!(Problem::BothInCheck.fix(&fen).is_none());
+        assert_eq!(
+            fix_all(&fen, Variant::Standard).to_string(),
+            "4k3/8/8/8/8/8/4Q3/4K3 b - - 0 1"
+        );
+        assert_eq!(
+            fix_castling_rights(&fen, Variant::Standard).to_string(),
+            "4k3/8/8/8/8/8/4Q3/P3K3 w - d6 0 1"
+        );
+        // The duck and antichess have no check
+        assert_eq!(problems(&fen, Variant::Antichess).len(), 3);
+
+        let fen = Fen::from_string("4k3/8/8/8/8/PPPPPPPP/PPPPPPPP/4K3 w - - 0 1");
+        assert_eq!(
+            problems(&fen, Variant::Standard),
+            [
+                Problem::TooManyPieces(PieceColor::White, 17),
+                Problem::TooManyPawns(PieceColor::White, 16)
+            ]
+        );
+    }
+}
@@ -0,0 +1,34 @@
+//! The rules of chess and its variants: the [ChessBoard] and the [Move]s made on it, positions
+//! read from and written to [Fen] strings, and [Game]s played move by move.
+//!
+//! ```
+//! use chesscomputer_core::{Game, MoveError, PieceColor};
+//!
+//! let mut game = Game::default();
+//! game.play_algebraic("e4").unwrap();
+//! game.play_algebraic("e5").unwrap();
+//! assert_eq!(
+//!     game.play_algebraic("Ke3"),
+//!     Err(MoveError::Unrecognised("Ke3".to_string()))
+//! );
+//! assert_eq!(*game.board().active_color(), Some(PieceColor::White));
+//! ```
+//!
+//! Boards can also be driven by a log of [GameEvent]s, which is how the
+//! [ChessComputer](https://github.com/CrazyPinkAlien/ChessComputer) app keeps its board. With the
+//! `bevy` feature the board's types are Bevy components, resources and events.
+
+pub mod board;
+pub mod castling_rights;
+pub mod fen;
+pub mod game;
+pub mod legality;
+
+pub use board::game_log::{GameEvent, GameLog};
+pub use board::r#move::Move;
+pub use board::{
+    BoardPosition, BoardSize, CastleFailure, ChessBoard, GameEndStatus, MoveError, PieceColor,
+    PieceType, Variant,
+};
+pub use fen::{Fen, FenError};
+pub use game::Game;
@@ -0,0 +1,409 @@
+//! Analysis of the position on the board while the engine is not playing, which evaluates each
+//! position as it is reached in the background.
+//!
+//! Evaluations are cached by the [Zobrist key](crate::zobrist) of their position, so stepping back
+//! and forth through the moves of a game shows the evaluations of positions already visited at
+//! once. The cache holds at most [EvalCache::capacity] positions, forgetting those least recently
+//! shown first.
+//!
+//! The candidate moves of the position can also be compared. Each legal move is searched in turn,
+//! one ply deeper each time all of them have been, and the best of those searched to the last depth
+//! finished are shown while the next is searched. There are no tablebases, so only the moves of the
+//! opening book are marked as known.
+
+use std::collections::{HashMap, VecDeque};
+use std::ops::RangeInclusive;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{Res, ResMut, Resource, Update};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, PieceColor, Variant};
+use crate::engine::{BackgroundSearch, Engine};
+use crate::zobrist::zobrist_hash;
+
+/// The number of positions cached by default.
+const DEFAULT_CAPACITY: usize = 1000;
+
+/// The deepest analysis searches while it is throttled.
+const THROTTLED_DEPTH: u32 = 3;
+
+/// The numbers of positions the cache can be set to hold.
+pub const CAPACITY_RANGE: RangeInclusive<usize> = 10..=100_000;
+
+pub(super) struct AnalysisPlugin;
+
+impl Plugin for AnalysisPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        use bevy::prelude::IntoSystemConfigs;
+
+        app.init_resource::<Analysis>()
+            .add_systems(Update, (analyse_position, compare_candidates).chain());
+    }
+}
+
+/// The engine's evaluation of a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// The evaluation in centipawns, from white's point of view.
+    pub score: i32,
+    /// The moves the engine expects from both players, starting with the best move, which is
+    /// empty if there are no legal moves.
+    pub line: Vec<Move>,
+    /// The depth the position was searched with, in plies, which limits may have cut short.
+    pub depth: u32,
+}
+
+impl Evaluation {
+    /// Returns the best move found, or None if there are no legal moves.
+    pub fn best_move(&self) -> Option<Move> {
+        self.line.first().copied()
+    }
+}
+
+/// A legal move of the position on the board, compared with the others.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub piece_move: Move,
+    /// The evaluation after the move in centipawns, from white's point of view.
+    pub score: i32,
+    /// The moves the engine expects after it.
+    pub continuation: Vec<Move>,
+    /// The depth the move was searched with, in plies, counting the move itself.
+    pub depth: u32,
+    /// Whether the move is in the opening book.
+    pub from_book: bool,
+}
+
+/// The search of the candidate moves of a position, which searches each legal move in turn to
+/// each depth.
+#[derive(Debug, Default)]
+struct CandidateSearch {
+    /// The Zobrist key of the position searched.
+    key: u64,
+    /// The depth the moves are being searched to, after the move itself.
+    depth: u32,
+    /// The moves still to be searched to the depth, the last of which is searched next.
+    queue: Vec<Move>,
+    /// The search of a move, while it is being searched.
+    pending: Option<(Move, BackgroundSearch)>,
+    /// The candidates searched to the depth so far.
+    searched: Vec<Candidate>,
+    /// The candidates searched to the last depth finished, best first.
+    finished: Vec<Candidate>,
+}
+
+impl CandidateSearch {
+    /// Carries on searching the candidate moves of the position on the given board, starting
+    /// again if it is another position, until they have been searched to the given depth.
+    fn update(&mut self, board: &ChessBoard, engine: &Engine, depth: u32) {
+        let key = zobrist_hash(board);
+        if key != self.key {
+            *self = CandidateSearch {
+                key,
+                ..Default::default()
+            };
+        }
+        if let Some((piece_move, search)) = &self.pending {
+            let Some((continuation, evaluation)) = search.try_result() else {
+                return;
+            };
+            // The search evaluates from the point of view of the player replying to the move
+            let score = match board.active_color() {
+                Some(PieceColor::Black) => evaluation,
+                _ => -evaluation,
+            };
+            self.searched.push(Candidate {
+                piece_move: *piece_move,
+                score,
+                continuation,
+                depth: self.depth + 1,
+                from_book: engine.book_moves(board).contains(piece_move),
+            });
+            self.pending = None;
+        }
+        if self.queue.is_empty() {
+            if !self.searched.is_empty() {
+                let sign = match board.active_color() {
+                    Some(PieceColor::Black) => -1,
+                    _ => 1,
+                };
+                self.searched
+                    .sort_by_key(|candidate| -sign * candidate.score);
+                self.finished = std::mem::take(&mut self.searched);
+            }
+            // The move itself is one ply of the depth
+            if self.depth + 1 >= depth.max(2) {
+                return;
+            }
+            self.depth += 1;
+            self.queue = board.legal_moves_iter().collect();
+            self.queue.reverse();
+        }
+        if let Some(piece_move) = self.queue.pop() {
+            let mut after = board.clone();
+            after.apply_move(&piece_move);
+            self.pending = Some((piece_move, engine.analyse_in_background(&after, self.depth)));
+        }
+    }
+}
+
+/// Evaluations of positions by their Zobrist keys, forgetting the least recently used once full.
+#[derive(Debug)]
+pub struct EvalCache {
+    evaluations: HashMap<u64, Evaluation>,
+    /// The keys of the cached positions, from the least to the most recently used.
+    order: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Default for EvalCache {
+    fn default() -> Self {
+        EvalCache::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl EvalCache {
+    pub fn new(capacity: usize) -> Self {
+        EvalCache {
+            evaluations: HashMap::new(),
+            order: VecDeque::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Returns the number of positions cached.
+    pub fn len(&self) -> usize {
+        self.evaluations.len()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Sets the most positions the cache holds, which is at least one, forgetting the least
+    /// recently used positions beyond it.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.evict();
+    }
+
+    /// Returns the evaluation of the position with the given key, marking it as the most
+    /// recently used.
+    pub fn get(&mut self, key: u64) -> Option<Evaluation> {
+        let evaluation = self.evaluations.get(&key)?.clone();
+        self.touch(key);
+        Some(evaluation)
+    }
+
+    pub fn insert(&mut self, key: u64, evaluation: Evaluation) {
+        if self.evaluations.insert(key, evaluation).is_some() {
+            self.touch(key);
+        } else {
+            self.order.push_back(key);
+            self.evict();
+        }
+    }
+
+    /// Moves the given key to the back of the order.
+    fn touch(&mut self, key: u64) {
+        if let Some(index) = self.order.iter().position(|cached| *cached == key) {
+            self.order.remove(index);
+        }
+        self.order.push_back(key);
+    }
+
+    fn evict(&mut self) {
+        while self.order.len() > self.capacity {
+            if let Some(key) = self.order.pop_front() {
+                self.evaluations.remove(&key);
+            }
+        }
+    }
+}
+
+/// The analysis of the position on the board.
+#[derive(Resource, Debug)]
+pub struct Analysis {
+    pub enabled: bool,
+    /// Whether analysis is withheld whatever the player chose, as it is during rated games.
+    pub suspended: bool,
+    /// Whether analysis searches no deeper than [THROTTLED_DEPTH], to save power.
+    pub throttled: bool,
+    pub cache: EvalCache,
+    /// The search of the position with the given key, while it is being evaluated.
+    pending: Option<(u64, BackgroundSearch)>,
+    /// The evaluation of the position on the board, once there is one.
+    current: Option<Evaluation>,
+    /// Whether the candidate moves of the position are compared.
+    pub compare_candidates: bool,
+    /// The number of the best candidate moves shown.
+    pub candidate_count: usize,
+    candidate_search: CandidateSearch,
+}
+
+impl Default for Analysis {
+    fn default() -> Self {
+        Analysis {
+            enabled: true,
+            suspended: false,
+            throttled: false,
+            cache: EvalCache::default(),
+            pending: None,
+            current: None,
+            compare_candidates: false,
+            candidate_count: 3,
+            candidate_search: CandidateSearch::default(),
+        }
+    }
+}
+
+impl Analysis {
+    pub fn current(&self) -> Option<&Evaluation> {
+        self.current.as_ref()
+    }
+
+    /// Returns the best candidate moves searched to the last depth finished, best first.
+    pub fn candidates(&self) -> &[Candidate] {
+        let finished = &self.candidate_search.finished;
+        &finished[..self.candidate_count.min(finished.len())]
+    }
+
+    /// Returns whether the position on the board is being searched.
+    pub fn is_searching(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Cancels the search of the position on the board, which then shows the best line found so
+    /// far.
+    pub fn cancel_search(&self) {
+        if let Some((_, search)) = &self.pending {
+            search.cancel();
+        }
+    }
+
+    /// Returns whether the position is being analysed, which it is while analysis is enabled and
+    /// not suspended, and the engine is not playing.
+    pub fn is_active(&self, engine: &Engine) -> bool {
+        self.enabled && !self.suspended && engine.color().is_none()
+    }
+}
+
+/// Evaluates the position on the board while the engine is not playing, from the cache when it is
+/// there and otherwise by searching it in the background.
+fn analyse_position(mut analysis: ResMut<Analysis>, engine: Res<Engine>, board: Res<ChessBoard>) {
+    // The engine cannot search duck chess, as it cannot place the duck
+    if !analysis.is_active(&engine) || *board.variant() == Variant::Duck {
+        analysis.pending = None;
+        analysis.current = None;
+        return;
+    }
+    let key = zobrist_hash(&board);
+    let depth = match analysis.throttled {
+        true => engine.hint_depth().min(THROTTLED_DEPTH),
+        false => engine.hint_depth(),
+    };
+    if analysis
+        .pending
+        .as_ref()
+        .is_some_and(|(pending, _)| *pending != key)
+    {
+        analysis.pending = None;
+    }
+    if let Some((_, search)) = &analysis.pending {
+        let Some((line, evaluation)) = search.try_result() else {
+            return;
+        };
+        // The search evaluates from the point of view of the player to move
+        let score = match board.active_color() {
+            Some(PieceColor::Black) => -evaluation,
+            _ => evaluation,
+        };
+        analysis.pending = None;
+        analysis
+            .cache
+            .insert(key, Evaluation { score, line, depth });
+    }
+    // Positions searched less deeply than analysis now searches are searched again
+    analysis.current = analysis
+        .cache
+        .get(key)
+        .filter(|evaluation| evaluation.depth >= depth);
+    if analysis.current.is_none() {
+        analysis.pending = Some((key, engine.analyse_in_background(&board, depth)));
+    }
+}
+
+/// Compares the candidate moves of the position on the board while they are to be compared and
+/// the position is analysed.
+fn compare_candidates(mut analysis: ResMut<Analysis>, engine: Res<Engine>, board: Res<ChessBoard>) {
+    if !analysis.compare_candidates
+        || !analysis.is_active(&engine)
+        || *board.variant() == Variant::Duck
+    {
+        analysis.candidate_search = CandidateSearch::default();
+        return;
+    }
+    let depth = match analysis.throttled {
+        true => engine.hint_depth().min(THROTTLED_DEPTH),
+        false => engine.hint_depth(),
+    };
+    analysis.candidate_search.update(&board, &engine, depth);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluation(score: i32) -> Evaluation {
+        Evaluation {
+            score,
+            line: Vec::new(),
+            depth: 1,
+        }
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let mut cache = EvalCache::new(2);
+        cache.insert(1, evaluation(10));
+        cache.insert(2, evaluation(20));
+        // Using the first position makes the second the least recently used
+        assert_eq!(cache.get(1), Some(evaluation(10)));
+        cache.insert(3, evaluation(30));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(2), None);
+        assert_eq!(cache.get(1), Some(evaluation(10)));
+
+        cache.insert(3, evaluation(-30));
+        assert_eq!(cache.get(3), Some(evaluation(-30)));
+        cache.set_capacity(1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(1), None);
+    }
+
+    #[test]
+    fn test_candidate_search() {
+        use crate::engine::MATE_SCORE;
+        use crate::fen::Fen;
+
+        let board =
+            ChessBoard::with_position(&Fen::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap());
+        let engine = Engine::default();
+        let mut search = CandidateSearch::default();
+        while search.finished.first().is_none_or(|best| best.depth < 3) {
+            search.update(&board, &engine, 3);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        // Every legal move is compared, with the mate first
+        assert_eq!(search.finished.len(), board.legal_moves_iter().count());
+        let best = &search.finished[0];
+        assert_eq!(Some(best.piece_move), Move::from_algebraic("Ra8", &board));
+        assert!(best.score > MATE_SCORE / 2);
+        assert!(!best.from_book);
+        // Searching to the depth again leaves the candidates alone
+        search.update(&board, &engine, 3);
+        assert!(search.pending.is_none());
+    }
+}
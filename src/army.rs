@@ -0,0 +1,216 @@
+//! Custom starting armies, where each player chooses the pieces behind their pawns within a
+//! material [BUDGET], e.g. three knights in place of the bishops and the queen.
+//!
+//! An army is a number of queens, rooks, bishops and knights, valued as [exchange](crate::exchange)
+//! values them. They stand on the back rank with the king on its usual square and the pawns in
+//! front of them, rooks first in the corners, then knights, bishops and queens from the outside
+//! in, so the standard army stands as usual. A player keeps the right to castle with a rook in a
+//! corner.
+
+use crate::chess_board::PieceType;
+use crate::exchange::value;
+use crate::fen::Fen;
+
+/// The most an army may be worth, in pawns, which is what the standard army is worth.
+pub const BUDGET: i32 = 31;
+
+/// The pieces an army is chosen from.
+pub const ARMY_PIECES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+/// The files of the back rank other than the king's, in the order they are filled.
+const SLOTS: [usize; 7] = [0, 7, 1, 6, 2, 5, 3];
+
+/// The file of the king.
+const KING_FILE: usize = 4;
+
+/// The order the pieces are placed in.
+const PLACEMENT: [PieceType; 4] = [
+    PieceType::Rook,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Queen,
+];
+
+/// Why an army cannot be played.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArmyError {
+    /// The army is worth the given number of pawns, more than the [BUDGET].
+    OverBudget(i32),
+    /// The army has the given number of pieces, more than fit on the back rank.
+    TooManyPieces(usize),
+}
+
+impl ArmyError {
+    /// Returns the locale key of the error.
+    pub fn key(&self) -> &'static str {
+        match self {
+            ArmyError::OverBudget(_) => "army-over-budget",
+            ArmyError::TooManyPieces(_) => "army-too-many-pieces",
+        }
+    }
+}
+
+/// The pieces a player starts with behind their pawns, besides the king.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Army([u8; 4]);
+
+impl Default for Army {
+    fn default() -> Self {
+        Army([1, 2, 2, 2])
+    }
+}
+
+impl Army {
+    fn index(piece_type: &PieceType) -> usize {
+        ARMY_PIECES
+            .iter()
+            .position(|army_piece| army_piece == piece_type)
+            .expect("Armies are chosen from the army pieces")
+    }
+
+    /// Returns the number of pieces of the given type, which must be one of the [ARMY_PIECES].
+    pub fn count(&self, piece_type: &PieceType) -> u8 {
+        self.0[Self::index(piece_type)]
+    }
+
+    pub fn set_count(&mut self, piece_type: &PieceType, count: u8) {
+        self.0[Self::index(piece_type)] = count;
+    }
+
+    /// Returns the value of the army in pawns.
+    pub fn value(&self) -> i32 {
+        ARMY_PIECES
+            .iter()
+            .map(|piece_type| value(piece_type) * self.count(piece_type) as i32)
+            .sum()
+    }
+
+    /// Returns whether the army can be played, or why not.
+    pub fn validate(&self) -> Result<(), ArmyError> {
+        let pieces = self.0.iter().map(|count| *count as usize).sum();
+        if pieces > SLOTS.len() {
+            return Err(ArmyError::TooManyPieces(pieces));
+        }
+        match self.value() {
+            value if value > BUDGET => Err(ArmyError::OverBudget(value)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the piece on each file of the back rank.
+    fn back_rank(&self) -> [Option<PieceType>; 8] {
+        let mut rank = [None; 8];
+        rank[KING_FILE] = Some(PieceType::King);
+        let pieces = PLACEMENT.iter().flat_map(|piece_type| {
+            std::iter::repeat_n(*piece_type, self.count(piece_type) as usize)
+        });
+        for (file, piece_type) in SLOTS.iter().zip(pieces) {
+            rank[*file] = Some(piece_type);
+        }
+        rank
+    }
+}
+
+/// Writes the given back rank in FEN, in uppercase for white.
+fn write_rank(rank: &[Option<PieceType>; 8], white: bool) -> String {
+    let mut text = String::new();
+    let mut empty = 0;
+    for square in rank {
+        match square {
+            Some(piece_type) => {
+                if empty > 0 {
+                    text.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                let letter = piece_type.letter();
+                text.push(if white {
+                    letter
+                } else {
+                    letter.to_ascii_lowercase()
+                });
+            }
+            None => empty += 1,
+        }
+    }
+    if empty > 0 {
+        text.push_str(&empty.to_string());
+    }
+    text
+}
+
+/// Returns the castling rights of the given back rank in FEN, in uppercase for white.
+fn castling(rank: &[Option<PieceType>; 8], white: bool) -> String {
+    let mut rights = String::new();
+    if rank[7] == Some(PieceType::Rook) {
+        rights.push('K');
+    }
+    if rank[0] == Some(PieceType::Rook) {
+        rights.push('Q');
+    }
+    if white {
+        rights
+    } else {
+        rights.to_ascii_lowercase()
+    }
+}
+
+/// Returns the starting position with the given armies, or why one of them cannot be played.
+pub fn position(white: &Army, black: &Army) -> Result<Fen, ArmyError> {
+    white.validate()?;
+    black.validate()?;
+    let (white_rank, black_rank) = (white.back_rank(), black.back_rank());
+    let mut rights = castling(&white_rank, true) + &castling(&black_rank, false);
+    if rights.is_empty() {
+        rights.push('-');
+    }
+    let text = format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w {} - 0 1",
+        write_rank(&black_rank, false),
+        write_rank(&white_rank, true),
+        rights
+    );
+    Ok(Fen::parse(&text).expect("Armies make valid positions"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position() {
+        let standard = Army::default();
+        assert_eq!(standard.value(), BUDGET);
+        assert_eq!(
+            position(&standard, &standard).unwrap().to_string(),
+            Fen::default().to_string()
+        );
+
+        // Three knights in place of the bishops and the queen and one of the rooks, which leaves
+        // the kingside rook's corner to a knight
+        let mut knights = Army::default();
+        knights.set_count(&PieceType::Bishop, 0);
+        knights.set_count(&PieceType::Queen, 0);
+        knights.set_count(&PieceType::Knight, 3);
+        knights.set_count(&PieceType::Rook, 1);
+        assert_eq!(knights.validate(), Ok(()));
+        assert_eq!(
+            position(&knights, &standard).unwrap().to_string(),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RN2K1NN w Qkq - 0 1"
+        );
+
+        let mut queens = Army::default();
+        queens.set_count(&PieceType::Queen, 3);
+        queens.set_count(&PieceType::Knight, 0);
+        assert_eq!(queens.validate(), Err(ArmyError::OverBudget(43)));
+        assert!(position(&standard, &queens).is_err());
+        let mut crowded = Army::default();
+        crowded.set_count(&PieceType::Knight, 4);
+        crowded.set_count(&PieceType::Queen, 0);
+        assert_eq!(crowded.validate(), Err(ArmyError::TooManyPieces(8)));
+    }
+}
@@ -0,0 +1,239 @@
+//! Autosaves the current game so it can be restored after an unexpected exit.
+//!
+//! The game is saved to [AUTOSAVE_PATH] after every move and every [AUTOSAVE_INTERVAL], and the
+//! file is removed when the app exits normally. A file found on startup therefore belongs to a
+//! game which was interrupted, and the player is offered to restore it. Windows sharing the
+//! autosave keep their games in step through [tab_sync](crate::tab_sync).
+
+use std::time::Duration;
+
+use bevy::app::{App, AppExit, Last, Plugin};
+use bevy::prelude::{
+    DetectChanges, Event, EventReader, PostUpdate, PreUpdate, Res, ResMut, Resource, Startup,
+    Update,
+};
+use bevy::time::Time;
+use serde::{Deserialize, Serialize};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{GameCommand, GameCommands, PieceColor, ResetBoardEvent};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::storage;
+
+/// The file the current game is saved to.
+pub const AUTOSAVE_PATH: &str = "autosave.ron";
+
+/// How often the game is saved while no moves are made, so the clocks are kept up to date.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+pub(super) struct AutosavePlugin;
+
+impl Plugin for AutosavePlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_event::<RestoreGameEvent>()
+            .init_resource::<Autosave>()
+            .add_systems(Startup, find_interrupted_game)
+            .add_systems(PreUpdate, restore_game)
+            .add_systems(Update, save_game)
+            .add_systems(PostUpdate, restore_clock)
+            .add_systems(Last, remove_autosave);
+    }
+}
+
+/// Event sent when the player chooses whether to restore the interrupted game.
+#[derive(Event)]
+pub struct RestoreGameEvent {
+    restore: bool,
+}
+
+impl RestoreGameEvent {
+    pub fn new(restore: bool) -> Self {
+        RestoreGameEvent { restore }
+    }
+}
+
+/// A saved game with its clocks and settings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedGame {
+    /// The FEN of the starting position.
+    start: String,
+    /// The moves made from the starting position, in standard algebraic notation.
+    moves: Vec<String>,
+    /// The time white and black started with.
+    time_controls: Option<[Duration; 2]>,
+    /// The time remaining for white and black.
+    remaining: [Duration; 2],
+    engine_color: Option<PieceColor>,
+    engine_depth: u32,
+}
+
+impl SavedGame {
+    /// Saves the game in the given history, or returns None before its first position is recorded.
+    pub fn new(history: &PositionHistory, clock: &ChessClock, engine: &Engine) -> Option<Self> {
+        let remaining = |color| clock.remaining(&color).unwrap_or_default();
+        Some(SavedGame {
+            start: history.start()?.to_string(),
+            moves: history.moves().iter().map(Move::as_algebraic).collect(),
+            time_controls: *clock.time_controls(),
+            remaining: [remaining(PieceColor::White), remaining(PieceColor::Black)],
+            engine_color: *engine.color(),
+            engine_depth: engine.depth(),
+        })
+    }
+
+    /// Reads the game saved at [AUTOSAVE_PATH], if there is one.
+    pub fn load() -> Option<Self> {
+        storage::read(AUTOSAVE_PATH).and_then(|text| ron::from_str(&text).ok())
+    }
+
+    /// Saves the game at [AUTOSAVE_PATH].
+    pub fn save(&self) {
+        if let Ok(text) = ron::to_string(self) {
+            // A failed save only matters if the app then crashes, so it is not reported
+            storage::write(AUTOSAVE_PATH, &text).ok();
+        }
+    }
+
+    /// Returns the number of moves made in the saved game.
+    pub fn move_count(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Returns whether this game is the other game with the same or more moves made since.
+    pub fn continues(&self, other: &SavedGame) -> bool {
+        self.start == other.start && self.moves.starts_with(&other.moves)
+    }
+
+    /// Replays the saved moves, returning the event which resets the board to the final position,
+    /// or None if a move is not legal.
+    pub fn reset_event(&self) -> Option<ResetBoardEvent> {
+        ResetBoardEvent::from_algebraic_moves(&Fen::from_string(&self.start), &self.moves)
+    }
+}
+
+/// The autosave state.
+#[derive(Resource, Debug, Default)]
+pub struct Autosave {
+    /// The game found on startup, until the player chooses whether to restore it.
+    interrupted: Option<SavedGame>,
+    /// The time since the game was last saved.
+    since_save: Duration,
+    /// The clock times to set once the restored game has been reset.
+    restored_remaining: Option<[Duration; 2]>,
+    /// A game imported from a [diagnostic bundle](crate::bundle), to be restored on the next
+    /// update.
+    imported: Option<SavedGame>,
+}
+
+impl Autosave {
+    /// Returns the game which was interrupted, if the player has not yet chosen whether to
+    /// restore it.
+    pub fn interrupted(&self) -> &Option<SavedGame> {
+        &self.interrupted
+    }
+
+    /// Restores the given game with its clocks and engine settings, as for an interrupted game.
+    pub fn import(&mut self, game: SavedGame) {
+        self.imported = Some(game);
+    }
+}
+
+fn find_interrupted_game(mut autosave: ResMut<Autosave>) {
+    autosave.interrupted = SavedGame::load()
+        // A game without moves is not worth restoring
+        .filter(|game| game.move_count() > 0);
+}
+
+fn restore_game(
+    mut events: EventReader<RestoreGameEvent>,
+    mut autosave: ResMut<Autosave>,
+    mut commands: ResMut<GameCommands>,
+    mut clock: ResMut<ChessClock>,
+    mut engine: ResMut<Engine>,
+) {
+    let game = match events.iter().last() {
+        Some(event) => autosave.interrupted.take().filter(|_| event.restore),
+        None => autosave.imported.take(),
+    };
+    let Some(game) = game else {
+        return;
+    };
+    if let Some(reset_event) = game.reset_event() {
+        commands.push(GameCommand::LoadGame(Box::new(reset_event)));
+        clock.set_time_controls(game.time_controls);
+        autosave.restored_remaining = Some(game.remaining);
+        engine.set_color(game.engine_color);
+        engine.set_depth(game.engine_depth);
+    }
+}
+
+/// Sets the clocks of a restored game once the reset has reset them to the full time control.
+fn restore_clock(mut autosave: ResMut<Autosave>, mut clock: ResMut<ChessClock>) {
+    if let Some([white, black]) = autosave.restored_remaining.take() {
+        clock.set_remaining(&PieceColor::White, white);
+        clock.set_remaining(&PieceColor::Black, black);
+    }
+}
+
+fn save_game(
+    mut autosave: ResMut<Autosave>,
+    history: Res<PositionHistory>,
+    clock: Res<ChessClock>,
+    engine: Res<Engine>,
+    time: Res<Time>,
+) {
+    // Saving now would overwrite the interrupted game before the player has chosen to restore it
+    if autosave.interrupted.is_some() {
+        return;
+    }
+    autosave.since_save += time.delta();
+    if !history.is_changed() && autosave.since_save < AUTOSAVE_INTERVAL {
+        return;
+    }
+    autosave.since_save = Duration::ZERO;
+    if let Some(game) = SavedGame::new(&history, &clock, &engine) {
+        game.save();
+    }
+}
+
+/// Removes the autosave when the app exits normally, so it is not offered for restoring.
+fn remove_autosave(mut exit_events: EventReader<AppExit>) {
+    if exit_events.iter().next().is_some() {
+        storage::remove(AUTOSAVE_PATH);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_game() {
+        let game = SavedGame {
+            start: Fen::default().to_string(),
+            moves: [
+                "e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "Ng5", "d5", "exd5", "Nxd5",
+            ]
+            .map(String::from)
+            .to_vec(),
+            time_controls: Some([Duration::from_secs(300), Duration::from_secs(60)]),
+            remaining: [Duration::from_secs(250), Duration::from_secs(240)],
+            engine_color: Some(PieceColor::Black),
+            engine_depth: 3,
+        };
+        let text = ron::to_string(&game).unwrap();
+        assert_eq!(ron::from_str::<SavedGame>(&text).unwrap(), game);
+
+        let event = game.reset_event().unwrap();
+        assert_eq!(event.past_moves().len(), 10);
+        assert_eq!(event.past_moves()[9].as_algebraic(), "Nxd5");
+
+        let mut illegal = game.clone();
+        illegal.moves.push("Ke4".to_string());
+        assert!(illegal.reset_event().is_none());
+    }
+}
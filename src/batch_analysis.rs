@@ -0,0 +1,270 @@
+//! The batch analysis queue, which reviews games chosen from the [games database](crate::database)
+//! one after another in the background, finding the biggest mistake of each player as a
+//! [review](crate::review) does, and stores the [analysis](GameAnalysis) with each game.
+//!
+//! Each position of the game being analysed is searched to [DEPTH] in a background search, with up
+//! to the chosen number of searches at once, so the app stays responsive however long the queue.
+//! The database is written once each game has been analysed. Pausing the queue starts no more
+//! searches, letting those running finish, and it carries on from there when it is resumed.
+
+use std::collections::VecDeque;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{EventWriter, Res, ResMut, Resource, Update};
+use bevy::window::RequestRedraw;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::ChessBoard;
+use crate::database::{GameAnalysis, GamesDatabase, StoredGame};
+use crate::engine::{BackgroundSearch, Engine};
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::notification::{Argument, Notification};
+use crate::review::biggest_evaluated_mistakes;
+
+/// The depth each position is searched to, deeper than a review in the foreground.
+const DEPTH: u32 = 4;
+
+/// The most searches which can run at once.
+pub const MAX_WORKERS: usize = 4;
+
+pub struct BatchAnalysisPlugin;
+
+impl Plugin for BatchAnalysisPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BatchAnalysis>()
+            .add_systems(Update, run_batch_analysis);
+    }
+}
+
+/// A game being analysed.
+#[derive(Debug)]
+struct AnalysedGame {
+    /// The index of the game in the database.
+    index: usize,
+    positions: Vec<Fen>,
+    moves: Vec<Move>,
+    /// The evaluation of each position, once it has been searched.
+    evaluations: Vec<Option<i32>>,
+    /// The positions being searched, by their indices.
+    searches: Vec<(usize, BackgroundSearch)>,
+    /// The index of the next position to search.
+    next: usize,
+}
+
+impl AnalysedGame {
+    /// Returns the game of the given index to analyse, or None if its moves cannot be replayed.
+    fn new(index: usize, game: &StoredGame) -> Option<Self> {
+        let (start, moves) = game.replay()?;
+        let mut history = PositionHistory::default();
+        history.set_game(&start, &moves);
+        let positions = history.positions().to_vec();
+        Some(AnalysedGame {
+            index,
+            evaluations: vec![None; positions.len()],
+            positions,
+            moves,
+            searches: Vec::new(),
+            next: 0,
+        })
+    }
+
+    /// Returns the fraction of the positions which have been searched.
+    fn progress(&self) -> f32 {
+        let searched = self.evaluations.iter().flatten().count();
+        searched as f32 / self.evaluations.len().max(1) as f32
+    }
+
+    /// Collects the searches which have finished and, unless paused, starts searching the next
+    /// positions until the given number of searches are running. Returns the analysis once every
+    /// position has been searched.
+    fn update(&mut self, engine: &Engine, workers: usize, paused: bool) -> Option<GameAnalysis> {
+        let evaluations = &mut self.evaluations;
+        self.searches
+            .retain(|(position, search)| match search.try_result() {
+                Some((_, evaluation)) => {
+                    evaluations[*position] = Some(evaluation);
+                    false
+                }
+                None => true,
+            });
+        while !paused && self.searches.len() < workers && self.next < self.positions.len() {
+            let board = ChessBoard::with_position(&self.positions[self.next]);
+            let search = engine.analyse_in_background(&board, DEPTH);
+            self.searches.push((self.next, search));
+            self.next += 1;
+        }
+        self.analysis()
+    }
+
+    /// Returns the analysis of the game, once every position has been searched.
+    fn analysis(&self) -> Option<GameAnalysis> {
+        let evaluations: Vec<i32> = self.evaluations.iter().copied().collect::<Option<_>>()?;
+        Some(GameAnalysis {
+            depth: DEPTH,
+            mistakes: biggest_evaluated_mistakes(evaluations.clone(), &self.moves),
+            evaluations,
+        })
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct BatchAnalysis {
+    /// The indices in the database of the games waiting to be analysed.
+    queue: VecDeque<usize>,
+    current: Option<AnalysedGame>,
+    /// Whether no more searches are started until the queue is resumed.
+    pub paused: bool,
+    /// The number of searches run at once, up to [MAX_WORKERS].
+    pub workers: usize,
+    /// The number of games analysed since the queue was last empty.
+    analysed: usize,
+}
+
+impl Default for BatchAnalysis {
+    fn default() -> Self {
+        BatchAnalysis {
+            queue: VecDeque::new(),
+            current: None,
+            paused: false,
+            workers: 2,
+            analysed: 0,
+        }
+    }
+}
+
+impl BatchAnalysis {
+    /// Adds the games of the given indices to the end of the queue, except those already in it.
+    pub fn enqueue(&mut self, indices: impl IntoIterator<Item = usize>) {
+        for index in indices {
+            if !self.contains(index) {
+                self.queue.push_back(index);
+            }
+        }
+    }
+
+    /// Returns whether the game of the given index is waiting or being analysed.
+    pub fn contains(&self, index: usize) -> bool {
+        self.queue.contains(&index) || self.analysing() == Some(index)
+    }
+
+    /// Returns the index of the game being analysed.
+    pub fn analysing(&self) -> Option<usize> {
+        self.current.as_ref().map(|game| game.index)
+    }
+
+    /// Returns the number of games analysed and the number in the queue in all, and the fraction
+    /// of the game being analysed which is done, or None if the queue is empty.
+    pub fn progress(&self) -> Option<(usize, usize, f32)> {
+        let current = self.current.as_ref()?;
+        let total = self.analysed + 1 + self.queue.len();
+        Some((self.analysed, total, current.progress()))
+    }
+
+    /// Empties the queue, cancelling the searches running.
+    pub fn clear(&mut self) {
+        if let Some(game) = self.current.take() {
+            for (_, search) in &game.searches {
+                search.cancel();
+            }
+        }
+        self.queue.clear();
+        self.analysed = 0;
+    }
+}
+
+/// Analyses the game at the front of the queue, storing the analysis of each game once it is
+/// done and moving on to the next.
+fn run_batch_analysis(
+    mut batch: ResMut<BatchAnalysis>,
+    engine: Res<Engine>,
+    mut database: ResMut<GamesDatabase>,
+    mut notifications: EventWriter<Notification>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    let batch = &mut *batch;
+    while batch.current.is_none() {
+        let Some(index) = batch.queue.pop_front() else {
+            return;
+        };
+        // A game whose moves cannot be replayed is passed over
+        batch.current = database
+            .get(index)
+            .and_then(|game| AnalysedGame::new(index, game));
+    }
+    let Some(game) = batch.current.as_mut() else {
+        return;
+    };
+    // The searches are only collected while the app updates
+    redraw_events.send(RequestRedraw);
+    let workers = batch.workers.clamp(1, MAX_WORKERS);
+    let Some(analysis) = game.update(&engine, workers, batch.paused) else {
+        return;
+    };
+    database.set_analysis(game.index, analysis);
+    batch.current = None;
+    batch.analysed += 1;
+    if let Err(error) = database.save() {
+        notifications.send(
+            Notification::error("database-save-failed").with_arg("error", Argument::Text(error)),
+        );
+    }
+    if batch.queue.is_empty() {
+        notifications.send(
+            Notification::info("batch-analysis-done")
+                .with_arg("games", Argument::Text(batch.analysed.to_string())),
+        );
+        batch.analysed = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::PieceColor;
+
+    use super::*;
+
+    #[test]
+    fn test_analysed_game() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut history = PositionHistory::default();
+        history.update(&board);
+        for text in ["e4", "e5", "Qh5", "Ke7", "Qxe5#"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            history.update(&board);
+        }
+        let mut game = AnalysedGame {
+            index: 0,
+            positions: history.positions().to_vec(),
+            moves: history.moves().to_vec(),
+            evaluations: vec![None; 6],
+            searches: Vec::new(),
+            next: 0,
+        };
+        // Paused, the game waits with nothing searched
+        assert_eq!(game.update(&Engine::default(), 2, true), None);
+        assert_eq!(game.next, 0);
+
+        game.evaluations = [
+            Some(20),
+            Some(-20),
+            Some(30),
+            Some(-30),
+            Some(900),
+            Some(-10_000),
+        ]
+        .to_vec();
+        assert!((game.progress() - 1.0).abs() < f32::EPSILON);
+        let analysis = game.analysis().unwrap();
+        let black = analysis.mistakes[PieceColor::Black as usize].unwrap();
+        assert_eq!(black.ply, 3);
+        assert_eq!(analysis.evaluations.len(), 6);
+
+        let mut batch = BatchAnalysis::default();
+        batch.enqueue([3, 1, 3]);
+        assert_eq!(batch.queue, [3, 1]);
+        assert!(batch.contains(1));
+        assert_eq!(batch.progress(), None);
+    }
+}
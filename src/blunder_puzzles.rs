@@ -0,0 +1,247 @@
+//! A personal set of [puzzles](crate::puzzle) from the player's own blunders, each a position of
+//! a stored game in which the player made a move the [review](crate::review) classifies as a
+//! blunder, solved by a move which wins back at least half of what it lost as a review does.
+//!
+//! Every game stored in the [games database](crate::database) is reviewed once, a game a frame so
+//! that reviewing a long database does not stall the app. The puzzles are tried again on a
+//! schedule of spaced repetition: each is kept in a box, moving up a box when it is solved and back
+//! to the first when it is missed, and is due again after the interval of its box. The puzzles due
+//! are served in turn, those due the longest first, and the set is stored in [BLUNDERS_PATH].
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{DetectChanges, EventWriter, Res, ResMut, Resource, Startup, Update};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{GamesDatabase, StoredGame};
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::notification::{Argument, Notification};
+use crate::puzzle::{Goal, Puzzle};
+use crate::review::{mistakes, Classification};
+use crate::storage;
+
+/// The file the blunder puzzles are stored in.
+const BLUNDERS_PATH: &str = "blunders.ron";
+
+/// The days until a puzzle is due again in each box, from the first.
+const INTERVALS: [i64; 5] = [1, 3, 7, 14, 30];
+
+pub struct BlunderPuzzlesPlugin;
+
+impl Plugin for BlunderPuzzlesPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BlunderPuzzles>()
+            .add_systems(Startup, load_blunders)
+            .add_systems(Update, (review_stored_games, save_blunders));
+    }
+}
+
+/// A position in which the player blundered.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Blunder {
+    /// The name of the player who blundered, which games stored before there were profiles lack.
+    player: Option<String>,
+    /// The FEN of the position.
+    fen: String,
+    /// The move played, in standard algebraic notation.
+    played: String,
+    /// The evaluation in centipawns a move must reach to solve the puzzle.
+    target: i32,
+    /// The box the puzzle is in, from 0.
+    level: usize,
+    /// The day the puzzle is next due, in days since 1970.01.01.
+    due: i64,
+}
+
+impl Blunder {
+    pub fn played(&self) -> &str {
+        &self.played
+    }
+
+    /// Returns the puzzle of finding a better move, or None if its position cannot be read.
+    pub fn puzzle(&self) -> Option<Puzzle> {
+        Some(Puzzle::new(
+            Fen::parse(&self.fen)?,
+            0,
+            Goal::Improve(self.target),
+        ))
+    }
+}
+
+/// The puzzles of every player's blunders.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct BlunderPuzzles {
+    blunders: Vec<Blunder>,
+    /// The number of stored games which have been reviewed, in the order they were stored.
+    reviewed: usize,
+}
+
+impl BlunderPuzzles {
+    pub fn get(&self, index: usize) -> Option<&Blunder> {
+        self.blunders.get(index)
+    }
+
+    /// Adds the blunders the player made in the given game due today, returning how many were
+    /// new. Both sides are the player's in a game played at the same board.
+    fn review(&mut self, game: &StoredGame, today: i64) -> usize {
+        let Some((start, moves)) = game.replay() else {
+            return 0;
+        };
+        let mut history = PositionHistory::default();
+        history.set_game(&start, &moves);
+        let positions = history.positions();
+        let mut added = 0;
+        for mistake in mistakes(positions, &moves) {
+            let piece_move = &moves[mistake.ply];
+            let fen = positions[mistake.ply].to_string();
+            if mistake.classification() != Some(Classification::Blunder)
+                || game
+                    .player_color()
+                    .is_some_and(|color| color != *piece_move.piece_color())
+                || self
+                    .blunders
+                    .iter()
+                    .any(|blunder| blunder.fen == fen && blunder.player == *game.player())
+            {
+                continue;
+            }
+            self.blunders.push(Blunder {
+                player: game.player().clone(),
+                fen,
+                played: piece_move.as_algebraic(),
+                target: mistake.target(),
+                level: 0,
+                due: today,
+            });
+            added += 1;
+        }
+        added
+    }
+
+    /// Returns the index of each puzzle of the player with the given name due on the given day,
+    /// those due the longest first.
+    pub fn due(&self, player: &str, today: i64) -> Vec<usize> {
+        let mut due: Vec<usize> = (0..self.blunders.len())
+            .filter(|index| {
+                let blunder = &self.blunders[*index];
+                blunder.due <= today && blunder.player.as_deref().is_none_or(|name| name == player)
+            })
+            .collect();
+        due.sort_by_key(|index| self.blunders[*index].due);
+        due
+    }
+
+    /// Moves the puzzle at the given index up a box if it was solved on the given day, or back to
+    /// the first if it was missed, and schedules it for the interval of its box.
+    pub fn answer(&mut self, index: usize, solved: bool, today: i64) {
+        let Some(blunder) = self.blunders.get_mut(index) else {
+            return;
+        };
+        blunder.level = if solved {
+            (blunder.level + 1).min(INTERVALS.len() - 1)
+        } else {
+            0
+        };
+        blunder.due = today + INTERVALS[blunder.level];
+    }
+}
+
+fn load_blunders(mut blunders: ResMut<BlunderPuzzles>) {
+    if let Some(stored) = storage::read(BLUNDERS_PATH).and_then(|text| ron::from_str(&text).ok()) {
+        *blunders = stored;
+    }
+}
+
+/// Reviews the next stored game which has not been reviewed yet.
+fn review_stored_games(database: Res<GamesDatabase>, mut blunders: ResMut<BlunderPuzzles>) {
+    let Some(game) = database.get(blunders.reviewed) else {
+        return;
+    };
+    blunders.review(game, crate::database::days_today());
+    blunders.reviewed += 1;
+}
+
+/// Writes the blunder puzzles whenever they change.
+fn save_blunders(blunders: Res<BlunderPuzzles>, mut notifications: EventWriter<Notification>) {
+    if !blunders.is_changed() || blunders.is_added() {
+        return;
+    }
+    let saved = ron::to_string(&*blunders)
+        .map_err(|error| error.to_string())
+        .and_then(|text| storage::write(BLUNDERS_PATH, &text));
+    if let Err(error) = saved {
+        notifications.send(
+            Notification::error("blunders-save-failed").with_arg("error", Argument::Text(error)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blunder_puzzles() {
+        let mut blunders = BlunderPuzzles {
+            blunders: (0..3)
+                .map(|due| Blunder {
+                    player: (due != 1).then(|| "Ann".to_string()),
+                    fen: Fen::default().to_string(),
+                    played: "e4".to_string(),
+                    target: 0,
+                    level: 0,
+                    due: 10 - due,
+                })
+                .collect(),
+            reviewed: 0,
+        };
+        blunders.blunders[0].player = Some("Bob".to_string());
+        // The puzzles due the longest come first, with those of no player served to everyone
+        assert_eq!(blunders.due("Ann", 10), [2, 1]);
+        assert_eq!(blunders.due("Ann", 8), [2]);
+
+        blunders.answer(2, true, 10);
+        assert_eq!(blunders.blunders[2].due, 13);
+        blunders.answer(2, true, 13);
+        assert_eq!(blunders.blunders[2].due, 20);
+        // A missed puzzle goes back to the first box
+        blunders.answer(2, false, 20);
+        assert_eq!(
+            (blunders.blunders[2].level, blunders.blunders[2].due),
+            (0, 21)
+        );
+        assert_eq!(blunders.due("Ann", 20), [1]);
+    }
+
+    #[test]
+    fn test_review() {
+        // Black hangs the queen on its second move, and white does not blunder
+        let game: StoredGame = ron::from_str(
+            r#"(
+                date: "2024.03.09",
+                player: Some("Ann"),
+                opponent: Human,
+                player_color: None,
+                start: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                moves: ["e4", "e5", "Nf3", "Qh4", "Nxh4"],
+                status: Resignation,
+                winner: Some(White),
+                time_controls: None,
+            )"#,
+        )
+        .unwrap();
+        let mut blunders = BlunderPuzzles::default();
+        assert_eq!(blunders.review(&game, 5), 1);
+        let blunder = blunders.get(0).unwrap();
+        assert_eq!(blunder.played(), "Qh4");
+        let puzzle = blunder.puzzle().unwrap();
+        let board = crate::chess_board::ChessBoard::with_position(puzzle.fen());
+        assert!(puzzle.is_solved_by(
+            &crate::chess_board::r#move::Move::from_algebraic("Nc6", &board).unwrap()
+        ));
+        assert_eq!(blunders.due("Ann", 5), [0]);
+        // The same position is only added once
+        assert_eq!(blunders.review(&game, 5), 0);
+    }
+}
@@ -0,0 +1,376 @@
+//! Builds and reads opening books in the [Polyglot](http://hgm.nubati.net/book_format.html)
+//! format, which the [Engine](crate::engine::Engine) plays from before it starts searching.
+//!
+//! A book is built from a folder of PGN files with `chess_computer book <folder> [options]`.
+//! Every move made in the first plies of the games is added to the book, weighted by the results
+//! of the games for the player who made it. The options are:
+//!
+//! - `--output <file>` sets the file the book is written to, [BOOK_PATH] by default.
+//! - `--plies <number>` sets the number of plies of each game which are added.
+//!
+//! Books use the Polyglot entry layout and move encoding, but the positions are hashed with
+//! [RANDOM], which is generated here rather than copied from the Polyglot specification. They
+//! are therefore read by this program but not by other Polyglot tools.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType, Variant, BOARD_SIZE};
+use crate::pgn::{read_games, GameResult, PgnGame};
+
+/// The file the engine reads its book from.
+pub const BOOK_PATH: &str = "book.bin";
+
+/// The number of plies of each game added to a book by default.
+const DEFAULT_PLIES: usize = 20;
+
+/// The size of an entry in a book file, in bytes.
+const ENTRY_SIZE: usize = 16;
+
+/// The random numbers the positions are hashed with: 768 for the pieces on each square, four
+/// for the castling rights, eight for the en passant file and one for white to move.
+const RANDOM: [u64; 781] = random_numbers();
+
+/// Generates the random numbers with the SplitMix64 generator from a fixed seed.
+const fn random_numbers() -> [u64; 781] {
+    let mut numbers = [0; 781];
+    let mut state: u64 = 0x4368_6573_7343_6f6d;
+    let mut index = 0;
+    while index < numbers.len() {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut number = state;
+        number = (number ^ (number >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        number = (number ^ (number >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        numbers[index] = number ^ (number >> 31);
+        index += 1;
+    }
+    numbers
+}
+
+/// Returns the row of the given square as numbered by Polyglot, counting up from white's side.
+fn row(position: &BoardPosition) -> usize {
+    BOARD_SIZE - 1 - position.rank()
+}
+
+/// Returns the hash of the position on the given board, or None if it has fairy pieces or is on a
+/// board of another size, which cannot be in a book.
+fn position_key(board: &ChessBoard) -> Option<u64> {
+    if !board.size().is_standard() {
+        return None;
+    }
+    let mut key = 0;
+    for rank in 0..BOARD_SIZE {
+        for file in 0..BOARD_SIZE {
+            let position = BoardPosition::new(rank, file);
+            if let (Some(piece_type), Some(color)) = (
+                board.get_piece_type(&position),
+                board.get_piece_color(&position),
+            ) {
+                let kind = match piece_type {
+                    PieceType::Pawn => 0,
+                    PieceType::Knight => 1,
+                    PieceType::Bishop => 2,
+                    PieceType::Rook => 3,
+                    PieceType::Queen => 4,
+                    PieceType::King => 5,
+                    PieceType::Fairy(_) => return None,
+                };
+                let piece = 2 * kind + usize::from(color == PieceColor::White);
+                key ^= RANDOM[64 * piece + 8 * row(&position) + file];
+            }
+        }
+    }
+
+    let fen = board.to_fen();
+    let rights = fen.castling_rights();
+    for (index, right) in rights.white.iter().chain(&rights.black).enumerate() {
+        if *right {
+            key ^= RANDOM[768 + index];
+        }
+    }
+
+    // The en passant file only counts if a pawn of the player to move can capture en passant
+    if let Some(last_move) = board.past_moves().last() {
+        let (from, to) = (last_move.from(), last_move.to());
+        if *last_move.piece_type() == PieceType::Pawn && from.rank().abs_diff(*to.rank()) == 2 {
+            let can_capture = [to.file().checked_sub(1), Some(to.file() + 1)]
+                .into_iter()
+                .flatten()
+                .filter(|file| *file < BOARD_SIZE)
+                .any(|file| {
+                    let position = BoardPosition::new(*to.rank(), file);
+                    board.get_piece_type(&position) == Some(PieceType::Pawn)
+                        && board.get_piece_color(&position) == Some(*fen.active_color())
+                });
+            if can_capture {
+                key ^= RANDOM[772 + to.file()];
+            }
+        }
+    }
+
+    if *fen.active_color() == PieceColor::White {
+        key ^= RANDOM[780];
+    }
+    Some(key)
+}
+
+/// Returns the Polyglot encoding of the given move. Castling is encoded as the king moving to the
+/// square of the rook it castles with.
+fn encode_move(piece_move: &Move) -> u16 {
+    let (from, to) = (piece_move.from(), piece_move.to());
+    let to_file = match (piece_move.is_castle(), to.file() > from.file()) {
+        (true, true) => BOARD_SIZE - 1,
+        (true, false) => 0,
+        (false, _) => *to.file(),
+    };
+    (to_file | row(to) << 3 | from.file() << 6 | row(from) << 9) as u16
+}
+
+/// An entry of a book, giving a move in a position and its weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BookEntry {
+    key: u64,
+    piece_move: u16,
+    weight: u16,
+    /// Information for engines which learn from their games, which is not used.
+    learn: u32,
+}
+
+/// An opening book, with its entries sorted by position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Book {
+    entries: Vec<BookEntry>,
+}
+
+impl Book {
+    /// Builds a book from the moves made in the given number of plies of each of the given games.
+    /// Each move is weighted two points for each game won by the player who made it and one for
+    /// each game drawn, so moves which were only made in lost or unfinished games are left out.
+    /// The book is for standard chess, so games of the variants are left out too.
+    pub fn build(games: &[PgnGame], plies: usize) -> Self {
+        let mut points: HashMap<(u64, u16), u64> = HashMap::new();
+        let standard = games
+            .iter()
+            .filter(|game| *game.variant() == Variant::Standard);
+        for game in standard {
+            let mut board = ChessBoard::with_position(game.start());
+            for piece_move in game.moves().iter().take(plies) {
+                let move_points = match game.result() {
+                    GameResult::Win(winner) if winner == *piece_move.piece_color() => 2,
+                    GameResult::Win(_) => 0,
+                    GameResult::Draw => 1,
+                    GameResult::Unfinished => 0,
+                };
+                if let Some(key) = position_key(&board) {
+                    *points.entry((key, encode_move(piece_move))).or_default() += move_points;
+                }
+                board.apply_move(piece_move);
+            }
+        }
+
+        // Scale the weights down if any is too large to store
+        let max_points = points.values().copied().max().unwrap_or(0);
+        let scale = |points: u64| {
+            if max_points > u16::MAX as u64 {
+                (points * u16::MAX as u64 / max_points).max(1)
+            } else {
+                points
+            }
+        };
+        let mut entries: Vec<BookEntry> = points
+            .into_iter()
+            .filter(|(_, points)| *points > 0)
+            .map(|((key, piece_move), points)| BookEntry {
+                key,
+                piece_move,
+                weight: scale(points) as u16,
+                learn: 0,
+            })
+            .collect();
+        entries.sort_by_key(|entry| (entry.key, u16::MAX - entry.weight, entry.piece_move));
+        Book { entries }
+    }
+
+    /// Reads a book from the contents of a book file, ignoring any incomplete entry at the end.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| BookEntry {
+                key: u64::from_be_bytes(entry[0..8].try_into().unwrap()),
+                piece_move: u16::from_be_bytes(entry[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(entry[10..12].try_into().unwrap()),
+                learn: u32::from_be_bytes(entry[12..16].try_into().unwrap()),
+            })
+            .collect();
+        // Books are meant to be sorted, but lookups rely on it
+        entries.sort_by_key(|entry| entry.key);
+        Book { entries }
+    }
+
+    /// Returns the contents of the book file.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.entries.len() * ENTRY_SIZE);
+        for entry in &self.entries {
+            bytes.extend_from_slice(&entry.key.to_be_bytes());
+            bytes.extend_from_slice(&entry.piece_move.to_be_bytes());
+            bytes.extend_from_slice(&entry.weight.to_be_bytes());
+            bytes.extend_from_slice(&entry.learn.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Returns the number of entries in the book.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the legal moves the book has for the position on the given board, with their
+    /// weights.
+    pub fn moves(&self, board: &ChessBoard) -> Vec<(Move, u16)> {
+        let Some(key) = position_key(board) else {
+            return Vec::new();
+        };
+        let first = self.entries.partition_point(|entry| entry.key < key);
+        let entries: Vec<&BookEntry> = self.entries[first..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .collect();
+        if entries.is_empty() {
+            return Vec::new();
+        }
+        board
+            .legal_moves_iter()
+            .filter_map(|piece_move| {
+                entries
+                    .iter()
+                    .find(|entry| entry.piece_move == encode_move(&piece_move))
+                    .map(|entry| (piece_move, entry.weight))
+            })
+            .collect()
+    }
+
+    /// Returns the move with the largest weight in the position on the given board, if the
+    /// position is in the book.
+    pub fn best_move(&self, board: &ChessBoard) -> Option<Move> {
+        self.moves(board)
+            .into_iter()
+            .max_by_key(|(_, weight)| *weight)
+            .map(|(piece_move, _)| piece_move)
+    }
+}
+
+/// Returns the games in the PGN files in the given folder.
+fn read_folder(folder: &Path) -> Result<Vec<PgnGame>, String> {
+    let entries = std::fs::read_dir(folder)
+        .map_err(|error| format!("Unable to read {}: {}.", folder.display(), error))?;
+    let mut games = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_pgn = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pgn"));
+        if is_pgn {
+            let text = std::fs::read_to_string(&path)
+                .map_err(|error| format!("Unable to read {}: {}.", path.display(), error))?;
+            games.extend(read_games(&text));
+        }
+    }
+    Ok(games)
+}
+
+/// Builds a book with the given command line arguments.
+#[cfg(not(tarpaulin_include))]
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    let folder = args.next().ok_or("Missing the folder of PGN files.")?;
+    let mut output = PathBuf::from(BOOK_PATH);
+    let mut plies = DEFAULT_PLIES;
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}.", flag))?;
+        match flag.as_str() {
+            "--output" => output = PathBuf::from(value),
+            "--plies" => {
+                plies = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for {}: {}.", flag, value))?
+            }
+            _ => return Err(format!("Unknown option: {}.", flag)),
+        }
+    }
+
+    let games = read_folder(Path::new(folder))?;
+    let book = Book::build(&games, plies);
+    std::fs::write(&output, book.to_bytes())
+        .map_err(|error| format!("Unable to write {}: {}.", output.display(), error))?;
+    println!(
+        "Wrote {} entries from {} games to {}.",
+        book.len(),
+        games.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_position_key() {
+        let start = ChessBoard::with_position(&Fen::default());
+        let mut board = start.clone();
+        for text in ["Nf3", "Nf6", "Ng1", "Ng8"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        assert_eq!(position_key(&board), position_key(&start));
+
+        // The en passant file only counts when the capture is possible
+        let mut board = ChessBoard::with_position(&Fen::from_string(
+            "rnbqkbnr/ppp1pppp/8/8/3p4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 3",
+        ));
+        for (text, en_passant) in [("e4", true), ("Nf6", false), ("a4", false)] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            let without_last_move = ChessBoard::with_position(&board.to_fen());
+            assert_eq!(
+                position_key(&board) != position_key(&without_last_move),
+                en_passant
+            );
+        }
+
+        // Castling is encoded as the king capturing its own rook
+        let board = ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/8/4K2R w K - 0 1"));
+        let castle = Move::from_algebraic("0-0", &board).unwrap();
+        assert_eq!(encode_move(&castle), 7 | 4 << 6);
+    }
+
+    #[test]
+    fn test_book() {
+        let games = read_games(
+            "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 1-0\n\n\
+            [Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2\n\n\
+            [Result \"0-1\"]\n\n1. e4 c5 0-1\n",
+        );
+        let book = Book::build(&games, 2);
+        // 1. e4, 1. d4, 1... d5 and 1... c5, but not the lost 1... e5 or 2. Nf3 past the ply limit
+        assert_eq!(book.len(), 4);
+
+        let board = ChessBoard::with_position(&Fen::default());
+        let mut moves: Vec<(String, u16)> = book
+            .moves(&board)
+            .iter()
+            .map(|(piece_move, weight)| (piece_move.as_algebraic(), *weight))
+            .collect();
+        moves.sort();
+        assert_eq!(moves, [("d4".to_string(), 1), ("e4".to_string(), 2)]);
+        assert_eq!(book.best_move(&board).unwrap().as_algebraic(), "e4");
+
+        let read_book = Book::from_bytes(&book.to_bytes());
+        assert_eq!(read_book, book);
+        assert_eq!(book.to_bytes().len(), 4 * ENTRY_SIZE);
+    }
+}
@@ -0,0 +1,258 @@
+//! Bookmarks of positions, which the player names and tags to come back to, whether the position
+//! was reached in play, in analysis or in an imported game.
+//!
+//! The bookmarks are read from [BOOKMARKS_PATH] when the app starts and written back whenever
+//! one is added, changed or removed. Opening a bookmark resets the board to its position for
+//! analysis, with the engine not playing and the clock off, and the bookmarks can be exported to
+//! [BOOKMARKS_EXPORT_PATH] as a FEN collection, one position a line.
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{Event, EventReader, PreUpdate, Res, ResMut, Resource, Startup};
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::Analysis;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, ResetBoardEvent, Variant};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::fen::Fen;
+use crate::storage;
+
+/// The file the bookmarks are stored in.
+const BOOKMARKS_PATH: &str = "bookmarks.ron";
+
+/// The file the bookmarks are exported to as a FEN collection.
+pub const BOOKMARKS_EXPORT_PATH: &str = "bookmarks.fen";
+
+/// The names of bookmarks are no longer than this many characters.
+pub const MAX_NAME_LENGTH: usize = 40;
+
+pub(super) struct BookmarkPlugin;
+
+impl Plugin for BookmarkPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_event::<OpenBookmarkEvent>()
+            .init_resource::<Bookmarks>()
+            .add_systems(Startup, load_bookmarks)
+            .add_systems(PreUpdate, open_bookmark);
+    }
+}
+
+/// Event sent to open the bookmark at the given index for analysis.
+#[derive(Event)]
+pub struct OpenBookmarkEvent {
+    index: usize,
+}
+
+impl OpenBookmarkEvent {
+    pub fn new(index: usize) -> Self {
+        OpenBookmarkEvent { index }
+    }
+}
+
+/// Returns the tags in the given text, separated by commas, trimmed and in lower case, without
+/// any empty or repeated.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(',').map(|tag| tag.trim().to_lowercase()) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// A named position.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub tags: Vec<String>,
+    /// The FEN of the position.
+    fen: String,
+    #[serde(default)]
+    variant: Variant,
+    /// The date the position was bookmarked, in the format of the PGN `Date` tag.
+    date: String,
+}
+
+impl Bookmark {
+    /// Bookmarks the position on the given board.
+    pub fn new(name: &str, tags: Vec<String>, board: &ChessBoard, date: String) -> Self {
+        Bookmark {
+            name: name.chars().take(MAX_NAME_LENGTH).collect(),
+            tags,
+            fen: board.to_fen().to_string(),
+            variant: *board.variant(),
+            date,
+        }
+    }
+
+    pub fn fen(&self) -> &str {
+        &self.fen
+    }
+
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+
+    /// Returns the event resetting the board to the position, or None if its FEN is not valid.
+    pub fn reset_event(&self) -> Option<ResetBoardEvent> {
+        let fen = Fen::parse_variant(&self.fen, self.variant)?;
+        Some(ResetBoardEvent::new(fen).with_variant(self.variant))
+    }
+}
+
+/// The bookmarked positions, in the order they were bookmarked.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmarks {
+    bookmarks: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn get(&self, index: usize) -> Option<&Bookmark> {
+        self.bookmarks.get(index)
+    }
+
+    /// Adds the given bookmark, or renames and retags the bookmark of the same position, returning
+    /// false if there was one.
+    pub fn add(&mut self, bookmark: Bookmark) -> bool {
+        match self
+            .bookmarks
+            .iter_mut()
+            .find(|stored| stored.fen == bookmark.fen && stored.variant == bookmark.variant)
+        {
+            Some(stored) => {
+                stored.name = bookmark.name;
+                stored.tags = bookmark.tags;
+                false
+            }
+            None => {
+                self.bookmarks.push(bookmark);
+                true
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.bookmarks.len() {
+            self.bookmarks.remove(index);
+        }
+    }
+
+    /// Returns every tag of the bookmarks, sorted.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self
+            .bookmarks
+            .iter()
+            .flat_map(|bookmark| bookmark.tags.iter().cloned())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    /// Returns the bookmarks with the given tag, or every bookmark if it is None, with their
+    /// indices.
+    pub fn tagged<'a>(
+        &'a self,
+        tag: Option<&'a str>,
+    ) -> impl Iterator<Item = (usize, &'a Bookmark)> + 'a {
+        self.bookmarks
+            .iter()
+            .enumerate()
+            .filter(move |(_, bookmark)| {
+                tag.is_none_or(|tag| bookmark.tags.iter().any(|other| other == tag))
+            })
+    }
+
+    /// Returns the FENs of the bookmarks with the given tag, or of every bookmark, one a line.
+    pub fn fen_collection(&self, tag: Option<&str>) -> String {
+        self.tagged(tag)
+            .map(|(_, bookmark)| format!("{}\n", bookmark.fen))
+            .collect()
+    }
+
+    /// Writes the bookmarks to [BOOKMARKS_PATH].
+    pub fn save(&self) -> Result<(), String> {
+        ron::to_string(self)
+            .map_err(|error| error.to_string())
+            .and_then(|text| storage::write(BOOKMARKS_PATH, &text))
+    }
+}
+
+fn load_bookmarks(mut bookmarks: ResMut<Bookmarks>) {
+    if let Some(stored) =
+        storage::read(BOOKMARKS_PATH).and_then(|text| ron::from_str::<Bookmarks>(&text).ok())
+    {
+        *bookmarks = stored;
+    }
+}
+
+/// Resets the board to the position of the bookmark opened, with the engine not playing, the
+/// clock off and the analysis on.
+fn open_bookmark(
+    mut events: EventReader<OpenBookmarkEvent>,
+    bookmarks: Res<Bookmarks>,
+    mut commands: ResMut<GameCommands>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+    mut analysis: ResMut<Analysis>,
+) {
+    let Some(event) = events.iter().last() else {
+        return;
+    };
+    let Some(reset_event) = bookmarks.get(event.index).and_then(Bookmark::reset_event) else {
+        return;
+    };
+    engine.set_color(None);
+    clock.set_time_control(None);
+    analysis.enabled = true;
+    commands.push(GameCommand::LoadGame(Box::new(reset_event)));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+
+    use super::*;
+
+    #[test]
+    fn test_bookmarks() {
+        assert_eq!(parse_tags(" Endgame, rook ,,endgame"), ["endgame", "rook"]);
+
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut bookmarks = Bookmarks::default();
+        let start = Bookmark::new(
+            "Start",
+            vec!["opening".to_string()],
+            &board,
+            "2024.03.01".into(),
+        );
+        assert!(bookmarks.add(start));
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        let tags = parse_tags("opening, king pawn");
+        assert!(bookmarks.add(Bookmark::new("e4", tags, &board, "2024.03.01".into())));
+        // Bookmarking the same position again renames it
+        assert!(!bookmarks.add(Bookmark::new("1. e4", vec![], &board, "2024.03.02".into())));
+        assert_eq!(bookmarks.get(1).unwrap().name, "1. e4");
+        assert_eq!(bookmarks.get(1).unwrap().date(), "2024.03.01");
+        assert_eq!(bookmarks.tags(), ["opening"]);
+
+        let tagged: Vec<usize> = bookmarks
+            .tagged(Some("opening"))
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(tagged, [0]);
+        assert_eq!(
+            bookmarks.fen_collection(None),
+            format!("{}\n{}\n", Fen::default(), board.to_fen())
+        );
+        let reset_event = bookmarks.get(1).unwrap().reset_event().unwrap();
+        assert_eq!(reset_event.fen().to_string(), board.to_fen().to_string());
+
+        let text = ron::to_string(&bookmarks).unwrap();
+        assert_eq!(ron::from_str::<Bookmarks>(&text).unwrap(), bookmarks);
+        bookmarks.remove(0);
+        assert_eq!(bookmarks.get(0).unwrap().name, "1. e4");
+    }
+}
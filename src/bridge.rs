@@ -0,0 +1,268 @@
+//! The bot bridge, which lets external scripts or homemade robots play on the board through two
+//! plain text files, without a network protocol.
+//!
+//! Started with `--bridge <input> <output>`, the app watches the input file, which may be a FIFO,
+//! for moves in long algebraic notation as used by UCI, one per line such as `e2e4` or `e7e8q`. A
+//! line may also be written as a UCI engine prints its move, e.g. `bestmove e2e4 ponder e7e5`.
+//! Each legal move is played for the side to move, and every other move made on the board, by the
+//! player or the engine, is appended to the output file in the same notation. Only lines added to
+//! the input after the app starts are read.
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::log::{info, info_span, warn};
+use bevy::prelude::{EventWriter, Res, ResMut, Resource, Update};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands};
+use crate::notification::{Argument, Notification};
+
+/// The time between checks for lines added to the input.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub(super) struct BridgePlugin;
+
+impl Plugin for BridgePlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, run_bridge);
+    }
+}
+
+/// Returns the move written on the given line of the input, or None if the line is blank.
+fn parse_line(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    match words.next()? {
+        "bestmove" => words.next(),
+        word => Some(word),
+    }
+}
+
+/// Reads the lines added to the input file at the given path, sending each with the given sender.
+/// The thread ends if the file cannot be read, which drops the sender so the app can tell.
+fn watch_input(path: PathBuf, sender: Sender<String>) {
+    let _span = info_span!("bridge", input = %path.display()).entered();
+    // Opening a FIFO waits for a writer
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(error) => {
+            warn!(%error, "Unable to open the input");
+            return;
+        }
+    };
+    // Moves already in a regular file are from before the app started, and a FIFO has none
+    file.seek(SeekFrom::End(0)).ok();
+    info!("Watching the input");
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line) {
+            // At the end of the file, and of a FIFO without a writer, lines may still be added
+            Ok(0) => thread::sleep(POLL_INTERVAL),
+            // A line without its newline is kept until the rest of it is written
+            Ok(_) if !line.ends_with('\n') => {}
+            Ok(_) => {
+                if sender.send(line.trim().to_string()).is_err() {
+                    return;
+                }
+                line.clear();
+            }
+            Err(error) => {
+                warn!(%error, "Unable to read the input");
+                return;
+            }
+        }
+    }
+}
+
+/// Appends each line received to the output file at the given path.
+fn write_output(path: PathBuf, lines: Receiver<String>) {
+    let _span = info_span!("bridge", output = %path.display()).entered();
+    // Opening a FIFO waits for a reader, so the file is only opened once there is a move to write
+    let Ok(first) = lines.recv() else {
+        return;
+    };
+    let mut file = match OpenOptions::new().create(true).append(true).open(&path) {
+        Ok(file) => file,
+        Err(error) => {
+            warn!(%error, "Unable to open the output");
+            return;
+        }
+    };
+    for line in std::iter::once(first).chain(lines) {
+        if let Err(error) = writeln!(file, "{}", line) {
+            warn!(%error, "Unable to write to the output");
+            return;
+        }
+    }
+}
+
+/// The files the moves of the board are exchanged through.
+#[derive(Resource, Debug)]
+pub struct Bridge {
+    /// The lines read from the input on another thread.
+    input: Mutex<Receiver<String>>,
+    /// The lines read which have not been played yet, as each is only played once the move
+    /// before it has been made.
+    lines: VecDeque<String>,
+    /// Whether the input is still being read, until it cannot be.
+    connected: bool,
+    /// The lines written to the output on another thread.
+    output: Sender<String>,
+    /// The number of moves on the board when it was last checked.
+    moves_seen: usize,
+    /// The move read from the input which has been pushed onto the [GameCommands] but not yet
+    /// made on the board.
+    pending: Option<Move>,
+}
+
+impl Bridge {
+    fn new(input: Receiver<String>, output: Sender<String>) -> Self {
+        Bridge {
+            input: Mutex::new(input),
+            lines: VecDeque::new(),
+            connected: true,
+            output,
+            moves_seen: 0,
+            pending: None,
+        }
+    }
+
+    /// Starts watching the input file at the given path and writing moves to the output file at
+    /// the other.
+    #[cfg(not(tarpaulin_include))]
+    pub fn open(input: &Path, output: &Path) -> Self {
+        let (input_sender, input_receiver) = channel();
+        let (output_sender, output_receiver) = channel();
+        let input = input.to_path_buf();
+        thread::spawn(move || watch_input(input, input_sender));
+        let output = output.to_path_buf();
+        thread::spawn(move || write_output(output, output_receiver));
+        Bridge::new(input_receiver, output_sender)
+    }
+
+    /// Writes the moves made on the board since it was last checked, other than those read from
+    /// the input. Taking back moves or starting a new game writes nothing.
+    fn write_moves(&mut self, board: &ChessBoard) {
+        let moves = board.past_moves();
+        if moves.len() < self.moves_seen {
+            self.pending = None;
+        }
+        for piece_move in moves.iter().skip(self.moves_seen) {
+            // A move read from the input which another move was made instead of is dropped
+            if self.pending.take() != Some(*piece_move) {
+                self.output.send(piece_move.as_long_algebraic()).ok();
+            }
+        }
+        self.moves_seen = moves.len();
+    }
+}
+
+/// Plays the moves read from the input and writes the other moves made on the board to the
+/// output, if the bridge was started.
+fn run_bridge(
+    bridge: Option<ResMut<Bridge>>,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let Some(mut bridge) = bridge else {
+        return;
+    };
+    bridge.write_moves(&board);
+    let disconnected = {
+        let Bridge { input, lines, .. } = &mut *bridge;
+        let input = input.get_mut().unwrap();
+        loop {
+            match input.try_recv() {
+                Ok(line) => lines.push_back(line),
+                Err(error) => break error == TryRecvError::Disconnected,
+            }
+        }
+    };
+    if disconnected && bridge.connected {
+        bridge.connected = false;
+        notifications.send(Notification::error("bridge-disconnected"));
+    }
+    while bridge.pending.is_none() {
+        let Some(line) = bridge.lines.pop_front() else {
+            break;
+        };
+        let Some(text) = parse_line(&line) else {
+            continue;
+        };
+        match Move::from_long_algebraic(text, &board)
+            .filter(|piece_move| board.valid_move(piece_move, board.active_color(), &true))
+        {
+            Some(piece_move) => {
+                info!(piece_move = text, "Bridge move");
+                bridge.pending = Some(piece_move);
+                commands.push(GameCommand::MakeMove(piece_move));
+            }
+            None => {
+                warn!(line, "Invalid bridge move");
+                notifications.send(
+                    Notification::warning("bridge-invalid-move")
+                        .with_arg("move", Argument::Text(text.to_string())),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::ChessBoardPlugin;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("e2e4\n"), Some("e2e4"));
+        assert_eq!(parse_line("bestmove e7e8q ponder a2a3"), Some("e7e8q"));
+        assert_eq!(parse_line("  "), None);
+    }
+
+    #[test]
+    fn test_bridge() {
+        let (input, lines) = channel();
+        let (output, written) = channel();
+        let mut app = App::new();
+        app.add_plugins((ChessBoardPlugin::default(), BridgePlugin))
+            .insert_resource(Bridge::new(lines, output));
+        app.update();
+
+        input.send("e2e4".to_string()).unwrap();
+        app.update();
+        app.update();
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(
+            board.to_fen().to_string(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+
+        // Moves made on the board are written, but not those read from the input
+        let board = board.clone();
+        let reply = Move::from_algebraic("e5", &board).unwrap();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::MakeMove(reply));
+        app.update();
+        app.update();
+        assert_eq!(written.try_iter().collect::<Vec<_>>(), ["e7e5"]);
+
+        // Illegal moves are not played
+        input.send("e1e3".to_string()).unwrap();
+        app.update();
+        app.update();
+        assert_eq!(app.world.resource::<ChessBoard>().past_moves().len(), 2);
+    }
+}
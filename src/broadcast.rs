@@ -0,0 +1,299 @@
+//! Broadcasts, live games followed from a PGN file which is fetched again every
+//! [POLL_INTERVAL] as new moves are added to it, like those published for tournament rounds.
+//!
+//! The PGN is fetched over plain HTTP, or read from a local file when it is given as a path,
+//! and may hold every game of a round, of which one is followed. The board is kept at the live
+//! position, [spectated](crate::spectating) so it cannot be played on, until the player chooses to
+//! [analyse](Broadcast::analyse) it or it otherwise leaves the live position, after which the live
+//! game is still fetched and the board returns to it on [Broadcast::resync].
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::log::{info, info_span, warn};
+use bevy::prelude::{EventWriter, Res, ResMut, Resource, Update};
+use chesscomputer_core::Game;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, ResetBoardEvent};
+use crate::notification::{Argument, Notification};
+use crate::pgn::{read_games, PgnGame};
+
+/// The time between fetches of the PGN.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The longest a fetch waits for the server.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+pub(super) struct BroadcastPlugin;
+
+impl Plugin for BroadcastPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Broadcast>()
+            .add_systems(Update, follow_broadcast);
+    }
+}
+
+/// Returns the body of the given HTTP response, or why there is none.
+fn response_body(response: &str) -> Result<String, String> {
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("the response was cut off")?;
+    let status = head.lines().next().unwrap_or_default();
+    match status.split_whitespace().nth(1) {
+        Some("200") => Ok(body.to_string()),
+        _ => Err(format!("the server replied {}", status)),
+    }
+}
+
+/// Fetches the given `http://` URL.
+fn fetch_http(url: &str) -> Result<String, String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or("only http URLs are supported")?;
+    let (host, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let mut stream = TcpStream::connect(&address).map_err(|error| error.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok();
+    // HTTP/1.0 is asked for so the body is neither chunked nor kept open
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {}\r\nUser-Agent: ChessComputer\r\n\r\n",
+        path, host
+    )
+    .map_err(|error| error.to_string())?;
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|error| error.to_string())?;
+    response_body(&String::from_utf8_lossy(&response))
+}
+
+/// Fetches the PGN at the given URL, or reads it from the file at the given path.
+fn fetch(source: &str) -> Result<String, String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        fetch_http(source)
+    } else {
+        std::fs::read_to_string(source).map_err(|error| error.to_string())
+    }
+}
+
+/// The live game being followed.
+#[derive(Resource, Debug, Default)]
+pub struct Broadcast {
+    /// The URL or path of the PGN, while a broadcast is being followed.
+    source: Option<String>,
+    /// The PGN fetched on another thread, or why it could not be.
+    fetched: Option<Mutex<Receiver<Result<String, String>>>>,
+    /// The games in the PGN when it was last fetched.
+    games: Vec<PgnGame>,
+    /// The index of the game followed.
+    selected: usize,
+    /// Whether the board is kept at the live position.
+    following: bool,
+    /// The moves of the live game when it was last loaded on the board.
+    loaded: Option<Vec<Move>>,
+    /// Why the PGN could not be fetched the last time it was tried.
+    error: Option<String>,
+}
+
+impl Broadcast {
+    /// Starts following the broadcast at the given URL or path, fetching it every
+    /// [POLL_INTERVAL].
+    pub fn follow(&mut self, source: &str) {
+        let (sender, receiver) = channel();
+        let url = source.to_string();
+        thread::spawn(move || {
+            let _span = info_span!("broadcast", url).entered();
+            info!("Following broadcast");
+            // The thread stops once the broadcast is no longer followed
+            while sender.send(fetch(&url)).is_ok() {
+                thread::sleep(POLL_INTERVAL);
+            }
+            info!("Stopped following broadcast");
+        });
+        *self = Broadcast {
+            source: Some(source.to_string()),
+            fetched: Some(Mutex::new(receiver)),
+            following: true,
+            ..Broadcast::default()
+        };
+    }
+
+    /// Stops following the broadcast, leaving the board where it is.
+    pub fn stop(&mut self) {
+        *self = Broadcast::default();
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn games(&self) -> &[PgnGame] {
+        &self.games
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Follows the game at the given index of the PGN, loading its live position.
+    pub fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.resync();
+    }
+
+    /// Returns the game followed, once the PGN has been fetched.
+    pub fn game(&self) -> Option<&PgnGame> {
+        self.games.get(self.selected)
+    }
+
+    /// Returns whether the board is kept at the live position, rather than being analysed.
+    pub fn is_following(&self) -> bool {
+        self.following
+    }
+
+    /// Stops keeping the board at the live position, so the player can make moves on it.
+    pub fn analyse(&mut self) {
+        self.following = false;
+    }
+
+    /// Returns the board to the live position and keeps it there again.
+    pub fn resync(&mut self) {
+        self.following = true;
+        self.loaded = None;
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Takes the fetched PGN, returning the error if it could not be fetched and was not the
+    /// last time either.
+    fn receive(&mut self) -> Option<String> {
+        let fetched = self.fetched.as_ref()?.lock().unwrap().try_recv().ok()?;
+        match fetched {
+            Ok(text) => {
+                self.games = read_games(&text);
+                self.error = None;
+                None
+            }
+            Err(error) => {
+                let repeated = self.error.as_ref() == Some(&error);
+                self.error = Some(error.clone());
+                (!repeated).then_some(error)
+            }
+        }
+    }
+
+    /// Returns the event which loads the live position if the board is following the game and
+    /// new moves have been played, and stops following once the board has left the position
+    /// last loaded.
+    fn update(&mut self, board: &ChessBoard) -> Option<ResetBoardEvent> {
+        if self
+            .loaded
+            .as_ref()
+            .is_some_and(|loaded| loaded != board.past_moves())
+        {
+            self.following = false;
+        }
+        let game = self.games.get(self.selected)?;
+        if !self.following || self.loaded.as_deref() == Some(game.moves()) {
+            return None;
+        }
+        let live = Game::replay(game.start().clone(), *game.variant(), game.moves());
+        self.loaded = Some(game.moves().to_vec());
+        Some(
+            ResetBoardEvent::with_past_moves(
+                live.board().to_fen(),
+                live.board().past_moves().clone(),
+            )
+            .with_variant(*game.variant()),
+        )
+    }
+}
+
+/// Loads the live position of the broadcast as new moves are fetched, while the board follows it.
+fn follow_broadcast(
+    mut broadcast: ResMut<Broadcast>,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+    mut notifications: EventWriter<Notification>,
+) {
+    if broadcast.source.is_none() {
+        return;
+    }
+    if let Some(error) = broadcast.receive() {
+        warn!(error, "Broadcast could not be fetched");
+        notifications.send(
+            Notification::warning("broadcast-failed").with_arg("error", Argument::Text(error)),
+        );
+    }
+    if let Some(reset_event) = broadcast.update(&board) {
+        info!(
+            moves = reset_event.past_moves().len(),
+            "Broadcast position loaded"
+        );
+        commands.push(GameCommand::LoadGame(Box::new(reset_event)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_response_body() {
+        assert_eq!(
+            response_body("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n1. e4 *"),
+            Ok("1. e4 *".to_string())
+        );
+        assert_eq!(
+            response_body("HTTP/1.1 404 Not Found\r\n\r\n"),
+            Err("the server replied HTTP/1.1 404 Not Found".to_string())
+        );
+        assert!(fetch("https://example.com/round.pgn").is_err());
+    }
+
+    #[test]
+    fn test_broadcast_update() {
+        let mut broadcast = Broadcast {
+            following: true,
+            games: read_games("[White \"A\"]\n\n1. e4 e5 *"),
+            ..Broadcast::default()
+        };
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let reset_event = broadcast.update(&board).unwrap();
+        for piece_move in reset_event.past_moves() {
+            board.apply_move(piece_move);
+        }
+        assert_eq!(reset_event.past_moves().len(), 2);
+        assert!(broadcast.update(&board).is_none());
+
+        // Analysing on the board stops the live moves from being loaded
+        let analysis = Move::from_algebraic("Nf3", &board).unwrap();
+        board.apply_move(&analysis);
+        broadcast.games = read_games("[White \"A\"]\n\n1. e4 e5 2. Bc4 *");
+        assert!(broadcast.update(&board).is_none());
+        assert!(!broadcast.is_following());
+
+        broadcast.resync();
+        let reset_event = broadcast.update(&board).unwrap();
+        assert_eq!(reset_event.past_moves().len(), 3);
+    }
+}
@@ -0,0 +1,282 @@
+//! Diagnostic bundles, zip files holding everything needed to reproduce a bug report: the version
+//! of the app, the position and the game with its clocks and engine settings, the options of the
+//! board, the [replay log](crate::replay) and the [game log](crate::chess_board::game_log).
+//!
+//! A bundle is exported to [BUNDLE_PATH]. Importing one puts its game and options back as they
+//! were, and its replay log can be run with `chess_computer replay <bundle>` like any other.
+//! The entries are stored uncompressed, so they can be read with any zip tool, but only bundles
+//! with uncompressed entries can be imported.
+
+use serde::Serialize;
+
+use crate::autosave::SavedGame;
+use crate::chess_board::game_log::{fold, GameEvent};
+use crate::chess_board::ChessBoardConfig;
+use crate::replay::LoggedCommand;
+
+/// The file bundles are exported to.
+pub const BUNDLE_PATH: &str = "diagnostic_bundle.zip";
+
+const VERSION_ENTRY: &str = "version.txt";
+const POSITION_ENTRY: &str = "position.fen";
+const GAME_ENTRY: &str = "game.ron";
+const CONFIG_ENTRY: &str = "config.ron";
+pub const REPLAY_ENTRY: &str = "replay.ron";
+const EVENTS_ENTRY: &str = "events.ron";
+
+/// The signatures of the parts of a zip file.
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_DIRECTORY: u32 = 0x06054b50;
+
+/// The date of every entry, 1 January 1980, the earliest a zip file can hold.
+const ENTRY_DATE: u16 = 0x21;
+
+/// Returns the CRC-32 checksum of the given bytes, as used by zip files.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Writes the given entries into a zip file without compressing them.
+fn write_zip(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut zip = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in entries {
+        let offset = zip.len() as u32;
+        // The fields shared by the local and central headers, from the version needed
+        let mut fields = Vec::new();
+        fields.extend(20u16.to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+        fields.extend(ENTRY_DATE.to_le_bytes());
+        fields.extend(crc32(data).to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((data.len() as u32).to_le_bytes());
+        fields.extend((name.len() as u16).to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+
+        zip.extend(LOCAL_HEADER.to_le_bytes());
+        zip.extend(&fields);
+        zip.extend(name.as_bytes());
+        zip.extend(data);
+
+        directory.extend(CENTRAL_HEADER.to_le_bytes());
+        directory.extend(20u16.to_le_bytes());
+        directory.extend(&fields);
+        // No comment, on the first disk, with no attributes
+        directory.extend([0; 10]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let directory_offset = zip.len() as u32;
+    zip.extend(&directory);
+    zip.extend(END_OF_DIRECTORY.to_le_bytes());
+    zip.extend([0; 4]);
+    zip.extend((entries.len() as u16).to_le_bytes());
+    zip.extend((entries.len() as u16).to_le_bytes());
+    zip.extend((directory.len() as u32).to_le_bytes());
+    zip.extend(directory_offset.to_le_bytes());
+    zip.extend(0u16.to_le_bytes());
+    zip
+}
+
+/// Reads the entries of a zip file with uncompressed entries, in the order they are stored.
+fn read_zip(zip: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let u16_at = |offset: usize| {
+        zip.get(offset..offset + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+            .ok_or("the bundle is cut off")
+    };
+    let u32_at = |offset: usize| {
+        zip.get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .ok_or("the bundle is cut off")
+    };
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while u32_at(offset)? == LOCAL_HEADER {
+        if u16_at(offset + 8)? != 0 {
+            return Err("the bundle has compressed entries".to_string());
+        }
+        let crc = u32_at(offset + 14)?;
+        let size = u32_at(offset + 18)? as usize;
+        let name_length = u16_at(offset + 26)?;
+        let extra_length = u16_at(offset + 28)?;
+        let name_start = offset + 30;
+        let data_start = name_start + name_length + extra_length;
+        let (Some(name), Some(data)) = (
+            zip.get(name_start..name_start + name_length),
+            zip.get(data_start..data_start + size),
+        ) else {
+            return Err("the bundle is cut off".to_string());
+        };
+        if crc32(data) != crc {
+            return Err("the bundle is damaged".to_string());
+        }
+        entries.push((String::from_utf8_lossy(name).into_owned(), data.to_vec()));
+        offset = data_start + size;
+    }
+    Ok(entries)
+}
+
+/// Returns the given value written as RON.
+fn to_ron<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    ron::to_string(value)
+        .map(String::into_bytes)
+        .map_err(|error| error.to_string())
+}
+
+/// Returns the text of the entry of the given bundle with the given name.
+pub fn read_entry(zip: &[u8], name: &str) -> Result<String, String> {
+    let (_, data) = read_zip(zip)?
+        .into_iter()
+        .find(|(entry, _)| entry == name)
+        .ok_or_else(|| format!("the bundle has no {}", name))?;
+    String::from_utf8(data).map_err(|error| error.to_string())
+}
+
+/// The state of the app saved in a bundle.
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    /// The version of the app and the system it ran on.
+    version: String,
+    /// The FEN of the position on the board.
+    fen: String,
+    game: SavedGame,
+    config: ChessBoardConfig,
+    replay: Vec<LoggedCommand>,
+    /// The events of the game log, which bundles exported before it was kept do not have.
+    events: Vec<GameEvent>,
+}
+
+impl Bundle {
+    pub fn new(
+        fen: String,
+        game: SavedGame,
+        config: ChessBoardConfig,
+        replay: Vec<LoggedCommand>,
+        events: Vec<GameEvent>,
+    ) -> Self {
+        Bundle {
+            version: format!(
+                "chess_computer {} on {} {}",
+                env!("CARGO_PKG_VERSION"),
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ),
+            fen,
+            game,
+            config,
+            replay,
+            events,
+        }
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns whether replaying the moves of the game, and folding the events of the game log if
+    /// it has any, reaches the position which was on the board, which they do unless the rules
+    /// have changed since the bundle was exported.
+    pub fn reaches_position(&self) -> bool {
+        let folded = self.events.is_empty() || fold(&self.events).to_fen().to_string() == self.fen;
+        folded
+            && self
+                .game
+                .reset_event()
+                .is_some_and(|reset_event| reset_event.fen().to_string() == self.fen)
+    }
+
+    pub fn game(&self) -> &SavedGame {
+        &self.game
+    }
+
+    pub fn config(&self) -> &ChessBoardConfig {
+        &self.config
+    }
+
+    /// Writes the bundle as a zip file.
+    pub fn to_zip(&self) -> Result<Vec<u8>, String> {
+        Ok(write_zip(&[
+            (VERSION_ENTRY, self.version.clone().into_bytes()),
+            (POSITION_ENTRY, self.fen.clone().into_bytes()),
+            (GAME_ENTRY, to_ron(&self.game)?),
+            (CONFIG_ENTRY, to_ron(&self.config)?),
+            (REPLAY_ENTRY, to_ron(&self.replay)?),
+            (EVENTS_ENTRY, to_ron(&self.events)?),
+        ]))
+    }
+
+    /// Reads a bundle from a zip file.
+    pub fn from_zip(zip: &[u8]) -> Result<Self, String> {
+        let parse_error = |error: ron::error::SpannedError| error.to_string();
+        Ok(Bundle {
+            version: read_entry(zip, VERSION_ENTRY)?,
+            fen: read_entry(zip, POSITION_ENTRY)?,
+            game: ron::from_str(&read_entry(zip, GAME_ENTRY)?).map_err(parse_error)?,
+            config: ron::from_str(&read_entry(zip, CONFIG_ENTRY)?).map_err(parse_error)?,
+            replay: ron::from_str(&read_entry(zip, REPLAY_ENTRY)?).map_err(parse_error)?,
+            events: match read_entry(zip, EVENTS_ENTRY) {
+                Ok(text) => ron::from_str(&text).map_err(parse_error)?,
+                Err(_) => Vec::new(),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_bundle() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        let zip = write_zip(&[("a.txt", b"first".to_vec()), ("b.txt", Vec::new())]);
+        assert_eq!(
+            read_zip(&zip).unwrap(),
+            vec![
+                ("a.txt".to_string(), b"first".to_vec()),
+                ("b.txt".to_string(), Vec::new())
+            ]
+        );
+        let mut damaged = zip.clone();
+        damaged[30 + 5] = b'F';
+        assert!(read_zip(&damaged).is_err());
+
+        let game: SavedGame = ron::from_str(&format!(
+            "(start: \"{}\", moves: [\"e4\"], time_controls: None, \
+             remaining: ((secs: 0, nanos: 0), (secs: 0, nanos: 0)), \
+             engine_color: None, engine_depth: 2)",
+            Fen::default()
+        ))
+        .unwrap();
+        let fen = game.reset_event().unwrap().fen().to_string();
+        let events = vec![GameEvent::Reset(game.reset_event().unwrap())];
+        let bundle = Bundle::new(
+            fen,
+            game.clone(),
+            ChessBoardConfig::default(),
+            Vec::new(),
+            events,
+        );
+        let read = Bundle::from_zip(&bundle.to_zip().unwrap()).unwrap();
+        assert_eq!(read.game(), &game);
+        assert_eq!(read.version(), bundle.version());
+        assert!(read.reaches_position());
+        assert_eq!(
+            read_entry(&bundle.to_zip().unwrap(), REPLAY_ENTRY).unwrap(),
+            "[]"
+        );
+    }
+}
@@ -1,116 +1,137 @@
+//! The board of the app, a [ChessBoard] of the rules crate kept as a resource, and the plugin
+//! which carries out the players' [GameCommand]s on it and tells the rest of the app what changed.
+
+use std::collections::VecDeque;
+
 use bevy::app::App;
+use bevy::diagnostic::Diagnostics;
+use bevy::ecs::system::SystemParam;
+use bevy::log::{info, info_span, warn};
 use bevy::prelude::{
-    Component, Event, EventReader, EventWriter, Plugin, PostUpdate, PreUpdate, ResMut, Resource,
-    Startup, Update,
+    DetectChanges, Event, EventWriter, Plugin, PostUpdate, PreUpdate, Res, ResMut, Resource,
+    Startup,
 };
-use strum_macros::EnumIter;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
-use crate::castling_rights::CastlingRights;
+pub use chesscomputer_core::board::*;
+
+use crate::diagnostics::{self, MOVE_GENERATION};
 use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::notification::{Argument, Notification};
 
+use self::game_log::{GameEvent, GameLog};
 use self::r#move::Move;
 
-pub(super) mod r#move;
-mod piece;
-
-pub const BOARD_SIZE: usize = 8;
-
-pub(super) struct ChessBoardPlugin;
-
-impl Plugin for ChessBoardPlugin {
-    #[cfg(not(tarpaulin_include))]
-    fn build(&self, app: &mut App) {
-        app.add_event::<ResetBoardEvent>()
-            .add_event::<PieceMoveEvent>()
-            .add_event::<PieceCreateEvent>()
-            .add_event::<RequestMoveEvent>()
-            .init_resource::<ChessBoard>()
-            .add_systems(Startup, setup)
-            .add_systems(PreUpdate, game_end_checker)
-            .add_systems(Update, reset_board_state)
-            .add_systems(PostUpdate, make_move);
-    }
-}
-
-#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Component, Eq)]
-pub enum PieceColor {
-    White,
-    Black,
+/// The options of the [ChessBoardPlugin].
+#[derive(Resource, Debug, Clone, Serialize, Deserialize)]
+pub struct ChessBoardConfig {
+    /// The position the board starts in, and is reset to for a new game.
+    pub starting_fen: Fen,
+    /// Whether only legal moves can be made. Otherwise the board is a sandbox, in which any piece
+    /// can be moved to any other square, whoever's turn it is, as long as it does not capture a
+    /// king.
+    pub enforce_legality: bool,
+    /// The variants which can be played, where a [GameCommand::SetVariant] to any other is ignored.
+    pub variants: Vec<Variant>,
+    /// Whether the game is ended automatically by checkmate, stalemate and the like after each
+    /// move.
+    pub check_game_end: bool,
 }
 
-impl PieceColor {
-    fn opposite(&self) -> PieceColor {
-        match self {
-            PieceColor::White => PieceColor::Black,
-            PieceColor::Black => PieceColor::White,
-        }
-    }
-}
-
-impl ToString for PieceColor {
-    fn to_string(&self) -> String {
-        match self {
-            PieceColor::White => "White".to_string(),
-            PieceColor::Black => "Black".to_string(),
+impl Default for ChessBoardConfig {
+    fn default() -> Self {
+        ChessBoardConfig {
+            starting_fen: Fen::default(),
+            enforce_legality: true,
+            variants: Variant::iter().collect(),
+            check_game_end: true,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq)]
-pub enum PieceType {
-    King,
-    Queen,
-    Bishop,
-    Knight,
-    Rook,
-    Pawn,
-}
-
-#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq)]
-pub enum GameEndStatus {
-    Checkmate,
-    Resignation,
-    Stalemate,
-    DeadPosition,
-    FlagFall,
-}
-
-#[derive(Component, PartialEq, Debug, Copy, Clone, Eq)]
-pub struct BoardPosition {
-    rank: usize,
-    file: usize,
-}
-
-impl BoardPosition {
-    pub fn new(rank: usize, file: usize) -> Self {
-        if (rank >= BOARD_SIZE) | (file >= BOARD_SIZE) {
-            panic!("Invalid rank or file value: {}, {}", rank, file)
+impl ChessBoardConfig {
+    /// Returns whether the given move can be made on the given board.
+    pub fn allows(&self, board: &ChessBoard, piece_move: &Move) -> bool {
+        if self.enforce_legality {
+            board.valid_move(piece_move, board.active_color(), &true)
+        } else {
+            // The rest of the app expects a game in progress with one king of each color
+            board.active_color().is_some()
+                && board.get_piece_type(piece_move.from()).is_some()
+                && piece_move.from() != piece_move.to()
+                && board.get_piece_type(piece_move.to()) != Some(PieceType::King)
         }
-        BoardPosition { rank, file }
     }
 
-    pub fn rank(&self) -> &usize {
-        &self.rank
+    /// Returns the notification telling the player why the given move, which is not allowed,
+    /// cannot be made on the given board.
+    pub fn refusal(&self, board: &ChessBoard, piece_move: &Move) -> Notification {
+        let piece_move_text = Argument::Move(piece_move.as_algebraic());
+        let notification = match board.check_move(piece_move) {
+            Err(MoveError::GameOver) => Notification::warning("illegal-move-game-over"),
+            Err(MoveError::WrongTurn(color)) if self.enforce_legality => {
+                Notification::warning("illegal-move-turn").with_arg("color", Argument::Color(color))
+            }
+            Err(MoveError::IntoCheck) if self.enforce_legality => {
+                Notification::warning("illegal-move-check")
+            }
+            _ => Notification::warning("illegal-move"),
+        };
+        notification.with_arg("move", piece_move_text)
     }
 
-    pub fn file(&self) -> &usize {
-        &self.file
+    /// Returns the notification telling the player which condition the given castle, which is not
+    /// allowed, fails, or None if it is not a castle of the player to move or fails none of them.
+    pub fn castle_refusal(&self, board: &ChessBoard, piece_move: &Move) -> Option<Notification> {
+        if !self.enforce_legality
+            || !piece_move.is_castle()
+            || *board.active_color() != Some(*piece_move.piece_color())
+        {
+            return None;
+        }
+        let failure = board.castle_failure(piece_move)?;
+        Some(
+            Notification::warning(failure.key())
+                .with_arg("move", Argument::Move(piece_move.as_algebraic())),
+        )
     }
 }
 
-/// Event sent to the [ChessBoard] to reset it with the given [Fen].
-#[derive(Debug, Clone, Event)]
-pub struct ResetBoardEvent {
-    fen: Fen,
+#[derive(Default)]
+pub(super) struct ChessBoardPlugin {
+    config: ChessBoardConfig,
 }
 
-impl ResetBoardEvent {
-    pub fn new(fen: Fen) -> Self {
-        ResetBoardEvent { fen }
+impl ChessBoardPlugin {
+    pub fn new(config: ChessBoardConfig) -> Self {
+        ChessBoardPlugin { config }
     }
+}
 
-    pub fn fen(&self) -> &Fen {
-        &self.fen
+impl Plugin for ChessBoardPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        use bevy::prelude::IntoSystemConfigs;
+
+        // Fairy pieces are registered before anything which draws or sets up pieces is built
+        fairy::load_piece_types(std::path::Path::new(fairy::FAIRY_PIECES_FILE));
+        diagnostics::register(app, MOVE_GENERATION, "move_generation");
+        app.insert_resource(self.config.clone())
+            .add_event::<ResetBoardEvent>()
+            .add_event::<GameCommandEvent>()
+            .add_event::<PieceMoveEvent>()
+            .add_event::<PieceCreateEvent>()
+            .add_event::<Notification>()
+            .init_resource::<ChessBoard>()
+            .init_resource::<LegalMoves>()
+            .init_resource::<GameCommands>()
+            .init_resource::<PositionHistory>()
+            .init_resource::<GameLog>()
+            .add_systems(Startup, setup)
+            .add_systems(PreUpdate, update_legal_moves)
+            .add_systems(PostUpdate, (run_commands, update_legal_moves).chain());
     }
 }
 
@@ -119,11 +140,27 @@ impl ResetBoardEvent {
 pub struct PieceMoveEvent {
     from: BoardPosition,
     to: BoardPosition,
+    /// The square of a pawn captured en passant, which is not the square the piece moves to.
+    captured: Option<BoardPosition>,
 }
 
 impl PieceMoveEvent {
     pub fn new(from: BoardPosition, to: BoardPosition) -> Self {
-        PieceMoveEvent { from, to }
+        PieceMoveEvent {
+            from,
+            to,
+            captured: None,
+        }
+    }
+
+    /// Returns the event with the piece on the given square captured as well.
+    pub fn with_capture(mut self, captured: Option<BoardPosition>) -> Self {
+        self.captured = captured;
+        self
+    }
+
+    pub fn captured(&self) -> &Option<BoardPosition> {
+        &self.captured
     }
 
     pub fn from(&self) -> &BoardPosition {
@@ -135,19 +172,61 @@ impl PieceMoveEvent {
     }
 }
 
-/// Event sent to the [ChessBoard] to request that a move is made.
-#[derive(Event)]
-pub struct RequestMoveEvent {
-    piece_move: Move,
+/// A command to the [ChessBoard], from the players, the engine or a remote opponent. Commands are
+/// pushed onto the [GameCommands] queue and carried out in the order they were pushed, each once
+/// every earlier one has taken effect.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GameCommand {
+    /// Makes the given move, if the [ChessBoardConfig] allows it.
+    MakeMove(Move),
+    /// Places the duck on the given square once a move of [Variant::Duck] has been made.
+    PlaceDuck(BoardPosition),
+    /// Takes back the last move.
+    Undo,
+    /// Resigns the game on behalf of the given player.
+    Resign(PieceColor),
+    /// Offers a draw on behalf of the given player.
+    OfferDraw(PieceColor),
+    /// Accepts or declines, on behalf of the given player, the draw offered by their opponent.
+    RespondDraw { color: PieceColor, accept: bool },
+    /// Asks, on behalf of the given player, to take back their last move.
+    RequestTakeback(PieceColor),
+    /// Accepts or declines, on behalf of the given player, the take back asked for by their
+    /// opponent. Accepting takes back the opponent's last move and any reply to it.
+    RespondTakeback { color: PieceColor, accept: bool },
+    /// Starts a new game of the given variant from the starting position.
+    SetVariant(Variant),
+    /// Turns the sandbox, in which moves are not checked for legality, on or off.
+    SetSandbox(bool),
+    /// Starts a new game from the starting position.
+    Reset,
+    /// Resets the board to the given position, keeping the moves which led to it.
+    LoadGame(Box<ResetBoardEvent>),
+    /// Ends the game on time for the given player, whose clock has run out, if it is their turn.
+    FlagFall(PieceColor),
 }
 
-impl RequestMoveEvent {
-    pub fn new(piece_move: Move) -> Self {
-        RequestMoveEvent { piece_move }
+/// The queue of [GameCommand]s waiting to be carried out, which is drained at the end of each
+/// frame.
+#[derive(Resource, Debug, Default)]
+pub struct GameCommands(VecDeque<GameCommand>);
+
+impl GameCommands {
+    pub fn push(&mut self, command: GameCommand) {
+        self.0.push_back(command);
     }
+}
+
+/// Event sent by the [ChessBoard] for each [GameCommand] it takes off the queue, whether or not
+/// the command could be carried out.
+#[derive(Event, Debug, Clone)]
+pub struct GameCommandEvent {
+    command: GameCommand,
+}
 
-    pub fn piece_move(&self) -> &Move {
-        &self.piece_move
+impl GameCommandEvent {
+    pub fn command(&self) -> &GameCommand {
+        &self.command
     }
 }
 
@@ -160,1230 +239,364 @@ pub struct PieceCreateEvent {
 }
 
 impl PieceCreateEvent {
-    pub fn position(&self) -> &BoardPosition {
-        &self.position
-    }
-
-    pub fn piece_type(&self) -> &PieceType {
-        &self.piece_type
-    }
-
-    pub fn color(&self) -> &PieceColor {
-        &self.color
-    }
-}
-
-#[derive(Resource, Clone)]
-pub struct ChessBoard {
-    board: [[Option<Box<dyn piece::Piece>>; 8]; 8],
-    active_color: Option<PieceColor>,
-    past_moves: Vec<Move>,
-    move_number: i32,
-    castling_rights: CastlingRights,
-    winner: Option<PieceColor>,
-    game_end_status: Option<GameEndStatus>,
-}
-
-impl Default for ChessBoard {
-    fn default() -> Self {
-        ChessBoard::empty_board()
-    }
-}
-
-impl ChessBoard {
-    fn empty_board() -> Self {
-        let board: [[Option<Box<dyn piece::Piece>>; 8]; 8] = Default::default();
-        ChessBoard {
-            board,
-            active_color: None,
-            past_moves: Vec::new(),
-            move_number: 1,
-            castling_rights: CastlingRights::default(),
-            winner: None,
-            game_end_status: None,
-        }
-    }
-
-    fn from_fen(fen: &Fen, create_event: &mut EventWriter<PieceCreateEvent>) -> Self {
-        // Create an empty board state
-        let mut board_state = ChessBoard::empty_board();
-
-        // Populate it from the given fen
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                if fen.piece_placement()[rank][file].is_some() {
-                    board_state.add_piece(
-                        fen.piece_placement()[rank][file].unwrap().0,
-                        fen.piece_placement()[rank][file].unwrap().1,
-                        BoardPosition::new(rank, file),
-                        create_event,
-                    );
-                }
-            }
-        }
-
-        // Set active color
-        board_state.active_color = Some(*fen.active_color());
-        // Set move number
-        board_state.move_number = *fen.fullmove_number();
-        // Set castling rights
-        board_state.castling_rights = *fen.castling_rights();
-
-        board_state
-    }
-
-    pub fn active_color(&self) -> &Option<PieceColor> {
-        &self.active_color
-    }
-
-    pub fn past_moves(&self) -> &Vec<Move> {
-        &self.past_moves
-    }
-
-    pub fn move_number(&self) -> &i32 {
-        &self.move_number
-    }
-
-    pub fn game_end_status(&self) -> &Option<GameEndStatus> {
-        &self.game_end_status
-    }
-
-    pub fn winner(&self) -> &Option<PieceColor> {
-        &self.winner
-    }
-
-    pub fn valid_move(
-        &self,
-        piece_move: &Move,
-        active_color: &Option<PieceColor>,
-        check_for_check: &bool,
-    ) -> bool {
-        // Throw away any move with both the capture and castle tags
-        if piece_move.is_castle() && piece_move.is_capture() {
-            return false;
-        }
-
-        // Get piece
-        if self.board[piece_move.from().rank][piece_move.from().file].is_none() {
-            return false;
-        }
-        let piece = self.board[piece_move.from().rank][piece_move.from().file]
-            .as_ref()
-            .unwrap();
-
-        let file_move_direction = *piece_move.to().file() as i32 - *piece_move.from().file() as i32;
-
-        // Check that there is an active colour
-        active_color.is_some()
-        // Check that the piece is the active colour
-        && (*piece.get_color() == active_color.unwrap())
-        // Check whether or not there are any pieces there
-        && match self.get_piece_color(piece_move.to()) {
-            Some(color) => if color == *piece.get_color() {
-                // If a friendly piece is here this move is invalid
-                false
-            } else {
-                // If an enemy piece is here the move must be a valid capture
-                piece.valid_capture(piece_move.to())
-            }
-            // If no piece is here the move must be a valid move
-            None => piece.valid_move(piece_move.to())
-        }
-        // No piece in the way for sliding pieces
-        && (!piece.is_sliding() || self.no_piece_between_squares(piece_move.from(), piece_move.to()))
-        // The move must not put the active color in check
-        && (!check_for_check
-        ||{
-                let mut test_board = self.clone();
-                test_board.move_piece(piece_move.from(), piece_move.to());
-                !test_board.in_check(&active_color.unwrap())
-            })
-        // Check if a castle is possible
-        && (!check_for_check || !piece_move.is_castle() || (
-            // Check that this is a valid direction in which to castle
-            self.castling_rights.valid_castle_direction(&active_color.unwrap(), file_move_direction)
-            // Check that there are no pieces between the king and the rook
-            && self.no_piece_between_squares(piece_move.from(), &BoardPosition::new(*piece_move.from().rank(), (*piece_move.from().file() as i32 + file_move_direction * BOARD_SIZE as i32).clamp(1, BOARD_SIZE as i32 - 1) as usize))
-            // Check that the king is not currently in check
-            && !self.in_check(&active_color.unwrap())
-            // Check that the king will not move through check
-            && {
-                let mut test_board = self.clone();
-                test_board.move_piece(piece_move.from(), piece_move.to());
-                !test_board.in_check(&active_color.unwrap())
-            }
-        ))
-    }
-
-    pub fn get_valid_moves(
-        &self,
-        active_color: &Option<PieceColor>,
-        check_for_check: &bool,
-    ) -> Vec<Move> {
-        let mut moves = Vec::new();
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                if self.board[rank][file].is_some() {
-                    let piece = &self.board[rank][file].as_ref().unwrap();
-                    let piece_moves = piece.get_moves(&true);
-                    for move_to in piece_moves {
-                        let piece_move =
-                            Move::from_board(BoardPosition::new(rank, file), move_to, self);
-                        if self.valid_move(&piece_move, active_color, check_for_check) {
-                            moves.push(piece_move);
-                        }
-                    }
-                }
-            }
-        }
-        moves
-    }
-
-    fn add_piece(
-        &mut self,
-        piece_color: PieceColor,
-        piece_type: PieceType,
-        position: BoardPosition,
-        create_event: &mut EventWriter<PieceCreateEvent>,
-    ) {
-        let new_piece = piece::new_piece(piece_color, piece_type, position);
-        self.board[position.rank][position.file] = Some(new_piece);
-
-        create_event.send(PieceCreateEvent {
+    pub fn new(position: BoardPosition, piece_type: PieceType, color: PieceColor) -> Self {
+        PieceCreateEvent {
             position,
             piece_type,
-            color: piece_color,
-        });
-    }
-
-    fn move_piece(&mut self, from: &BoardPosition, to: &BoardPosition) {
-        if self.board[*from.rank()][*from.file()].is_none() {
-            panic!("No piece at start location.");
-        }
-        self.board[*from.rank()][*from.file()]
-            .as_mut()
-            .unwrap()
-            .set_position(to);
-        self.board[*to.rank()][*to.file()] = self.board[*from.rank()][*from.file()].clone();
-        self.board[*from.rank()][*from.file()] = None;
-    }
-
-    pub fn get_piece_type(&self, position: &BoardPosition) -> Option<PieceType> {
-        self.board[position.rank][position.file]
-            .as_ref()
-            .map(|piece| *piece.get_type())
-    }
-
-    fn get_piece_color(&self, position: &BoardPosition) -> Option<PieceColor> {
-        self.board[position.rank][position.file]
-            .as_ref()
-            .map(|piece| *piece.get_color())
-    }
-
-    fn in_check(&self, color: &PieceColor) -> bool {
-        // Get king location
-        let mut king_location = BoardPosition::new(0, 0);
-        'outer: for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                if self.board[rank][file].is_some()
-                    && *self.board[rank][file].as_ref().unwrap().get_type() == PieceType::King
-                    && self.board[rank][file].as_ref().unwrap().get_color() == color
-                {
-                    king_location = BoardPosition::new(rank, file);
-                    break 'outer;
-                }
-            }
-        }
-        // Get valid moves
-        let moves = self.get_valid_moves(&Some(color.opposite()), &false);
-        // Check if any valid moves can take the king
-        for piece_move in moves {
-            if *piece_move.to() == king_location {
-                return true;
-            }
-        }
-        false
-    }
-
-    fn no_piece_between_squares(&self, start: &BoardPosition, end: &BoardPosition) -> bool {
-        let mut rank = *start.rank() as i32;
-        let mut file = *start.file() as i32;
-        rank += (*end.rank() as i32 - *start.rank() as i32).signum();
-        file += (*end.file() as i32 - *start.file() as i32).signum();
-        while rank as usize != *end.rank() || file as usize != *end.file() {
-            if self.board[rank as usize][file as usize].is_some() {
-                return false;
-            }
-            rank += (*end.rank() as i32 - *start.rank() as i32).signum();
-            file += (*end.file() as i32 - *start.file() as i32).signum();
-        }
-        true
-    }
-}
-
-fn setup(mut create_event: EventWriter<PieceCreateEvent>, mut board: ResMut<ChessBoard>) {
-    *board = ChessBoard::from_fen(&Fen::default(), &mut create_event);
-}
-
-fn make_move(
-    mut request_events: EventReader<RequestMoveEvent>,
-    mut move_events: EventWriter<PieceMoveEvent>,
-    mut board: ResMut<ChessBoard>,
-) {
-    for request_event in request_events.iter() {
-        // First confirm that the move is valid
-        if board.valid_move(request_event.piece_move(), board.active_color(), &true) {
-            // Move the piece
-            board.move_piece(
-                request_event.piece_move().from(),
-                request_event.piece_move().to(),
-            );
-            move_events.send(PieceMoveEvent::new(
-                *request_event.piece_move().from(),
-                *request_event.piece_move().to(),
-            ));
-
-            // If the move was a castle, also move the rook
-            if request_event.piece_move().is_castle() {
-                let file_move_direction = *request_event.piece_move().to().file() as i32
-                    - *request_event.piece_move().from().file() as i32;
-                let from = BoardPosition::new(
-                    *request_event.piece_move().from().rank(),
-                    (*request_event.piece_move().from().file() as i32
-                        + file_move_direction * BOARD_SIZE as i32)
-                        .clamp(1, BOARD_SIZE as i32 - 1) as usize,
-                );
-                let to = BoardPosition::new(
-                    *request_event.piece_move().to().rank(),
-                    (*request_event.piece_move().to().file() as i32 - file_move_direction.signum())
-                        as usize,
-                );
-                board.move_piece(&from, &to);
-                move_events.send(PieceMoveEvent::new(from, to));
-            }
-
-            // Change the active color
-            board.active_color = Some(board.active_color.unwrap().opposite());
-
-            // Make a record of the move
-            board.past_moves.push(*request_event.piece_move());
-
-            // Increment the move number if it is now white's turn
-            if board.active_color == Some(PieceColor::White) {
-                board.move_number += 1;
-            }
-
-            // Update castling rights
-            board
-                .castling_rights
-                .update_after_move(request_event.piece_move());
-        }
-    }
-}
-
-fn reset_board_state(
-    mut setup_events: EventReader<ResetBoardEvent>,
-    mut board: ResMut<ChessBoard>,
-    mut create_event: EventWriter<PieceCreateEvent>,
-) {
-    for event in setup_events.iter() {
-        *board = ChessBoard::from_fen(event.fen(), &mut create_event);
-    }
-}
-
-fn game_end_checker(mut board: ResMut<ChessBoard>, mut events: EventReader<PieceMoveEvent>) {
-    for _event in events.iter() {
-        // Check for checkmate or stalemate
-        if board.active_color().is_some()
-            && board
-                .get_valid_moves(board.active_color(), &true)
-                .is_empty()
-        {
-            if board.in_check(&board.active_color().unwrap()) {
-                // Checkmate
-                board.game_end_status = Some(GameEndStatus::Checkmate);
-                board.winner = Some(board.active_color().unwrap().opposite());
-            } else {
-                // Stalemate
-                board.game_end_status = Some(GameEndStatus::Stalemate);
-            }
-            // The game has ended, set the active color to None.
-            board.active_color = None;
-        }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use bevy::prelude::{Events, Startup};
-
-    use super::*;
-
-    #[test]
-    #[should_panic(expected = "Invalid rank or file value: 8, 4")]
-    fn test_board_position_new_rank_too_large() {
-        BoardPosition::new(8, 4);
-    }
-
-    #[test]
-    #[should_panic(expected = "Invalid rank or file value: 1, 10")]
-    fn test_board_position_new_file_too_large() {
-        BoardPosition::new(1, 10);
-    }
-
-    #[test]
-    fn test_chess_board_empty_board() {
-        let empty_board = ChessBoard::empty_board();
-
-        assert_eq!(*empty_board.active_color(), None);
-        assert_eq!(empty_board.past_moves.len(), 0);
-        assert_eq!(*empty_board.move_number(), 1);
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                assert!(empty_board.board[rank][file].is_none());
-            }
+            color,
         }
     }
 
-    #[test]
-    fn test_chess_board_from_fen() {
-        let fen = Fen::from_string(
-            "rk1r1bb1/ppp1pp1p/3n2n1/1q1p2p1/4P3/1N2Q1PP/PPPP1P2/RK2RBBN b - - 0 1",
-        );
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<PieceCreateEvent>();
-        app.add_event::<ResetBoardEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Confirm that the chessboard has been set up correctly
-        let pieces = vec![
-            vec![
-                Some((PieceType::Rook, PieceColor::Black)),
-                Some((PieceType::King, PieceColor::Black)),
-                None,
-                Some((PieceType::Rook, PieceColor::Black)),
-                None,
-                Some((PieceType::Bishop, PieceColor::Black)),
-                Some((PieceType::Bishop, PieceColor::Black)),
-                None,
-            ],
-            vec![
-                Some((PieceType::Pawn, PieceColor::Black)),
-                Some((PieceType::Pawn, PieceColor::Black)),
-                Some((PieceType::Pawn, PieceColor::Black)),
-                None,
-                Some((PieceType::Pawn, PieceColor::Black)),
-                Some((PieceType::Pawn, PieceColor::Black)),
-                None,
-                Some((PieceType::Pawn, PieceColor::Black)),
-            ],
-            vec![
-                None,
-                None,
-                None,
-                Some((PieceType::Knight, PieceColor::Black)),
-                None,
-                None,
-                Some((PieceType::Knight, PieceColor::Black)),
-                None,
-            ],
-            vec![
-                None,
-                Some((PieceType::Queen, PieceColor::Black)),
-                None,
-                Some((PieceType::Pawn, PieceColor::Black)),
-                None,
-                None,
-                Some((PieceType::Pawn, PieceColor::Black)),
-                None,
-            ],
-            vec![
-                None,
-                None,
-                None,
-                None,
-                Some((PieceType::Pawn, PieceColor::White)),
-                None,
-                None,
-                None,
-            ],
-            vec![
-                None,
-                Some((PieceType::Knight, PieceColor::White)),
-                None,
-                None,
-                Some((PieceType::Queen, PieceColor::White)),
-                None,
-                Some((PieceType::Pawn, PieceColor::White)),
-                Some((PieceType::Pawn, PieceColor::White)),
-            ],
-            vec![
-                Some((PieceType::Pawn, PieceColor::White)),
-                Some((PieceType::Pawn, PieceColor::White)),
-                Some((PieceType::Pawn, PieceColor::White)),
-                Some((PieceType::Pawn, PieceColor::White)),
-                None,
-                Some((PieceType::Pawn, PieceColor::White)),
-                None,
-                None,
-            ],
-            vec![
-                Some((PieceType::Rook, PieceColor::White)),
-                Some((PieceType::King, PieceColor::White)),
-                None,
-                None,
-                Some((PieceType::Rook, PieceColor::White)),
-                Some((PieceType::Bishop, PieceColor::White)),
-                Some((PieceType::Bishop, PieceColor::White)),
-                Some((PieceType::Knight, PieceColor::White)),
-            ],
-        ];
-
-        // Check active color
-        assert_eq!(
-            *app.world
-                .get_resource::<ChessBoard>()
-                .unwrap()
-                .active_color(),
-            Some(PieceColor::Black)
-        );
-
-        // Check past moves
-        assert_eq!(
-            app.world
-                .get_resource::<ChessBoard>()
-                .unwrap()
-                .past_moves
-                .len(),
-            0
-        );
-
-        // Check move number
-        assert_eq!(
-            *app.world
-                .get_resource::<ChessBoard>()
-                .unwrap()
-                .move_number(),
-            1
-        );
-
-        // Check pieces
-        let board = &app.world.get_resource::<ChessBoard>().unwrap().board;
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                if pieces[rank][file].is_none() {
-                    assert!(board[rank][file].is_none());
-                } else {
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_type(),
-                        pieces[rank][file].unwrap().0
-                    );
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_color(),
-                        pieces[rank][file].unwrap().1
-                    );
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_position(),
-                        BoardPosition::new(rank, file)
-                    );
-                }
-            }
-        }
-    }
-
-    // TODO: This test should expect the message: "Unrecognised symbol in FEN: X"
-    #[test]
-    #[should_panic]
-    fn test_chess_board_from_fen_unrecognised_symbol() {
-        let fen = Fen::from_string(
-            "rk1x1bb1/ppp1pp1p/3n2n1/1q1p2p1/4P3/1N2Q1PP/PPPP1P2/RK2RBBN b - - 0 1",
-        );
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-    }
-
-    // TODO: This test should expect the message: "Unrecognised active color in FEN: l"
-    #[test]
-    #[should_panic]
-    fn test_chess_board_from_fen_unrecognised_active_color() {
-        let fen = Fen::from_string(
-            "rk1r1bb1/ppp1pp1p/3n2n1/1q1p2p1/4P3/1N2Q1PP/PPPP1P2/RK2RBBN l - - 0 1",
-        );
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-    }
-
-    #[test]
-    fn test_chess_board_valid_move_true() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp1ppp1p/5n2/qp4p1/4P3/2N2N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Create move
-        let board = &app.world.get_resource::<ChessBoard>().unwrap();
-        let piece_move =
-            Move::from_board(BoardPosition::new(5, 2), BoardPosition::new(3, 1), board);
-
-        // Confirm that the move is valid
-        assert!(board.valid_move(&piece_move, board.active_color(), &true));
-    }
-
-    #[test]
-    fn test_chess_board_valid_move_false() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Create move
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        let piece_move =
-            Move::from_board(BoardPosition::new(6, 3), BoardPosition::new(5, 3), board);
-
-        // Confirm that the move is not valid
-        assert!(!board.valid_move(&piece_move, board.active_color(), &true));
-    }
-
-    #[test]
-    #[should_panic(expected = "No piece found.")]
-    fn test_chess_board_valid_move_no_piece() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Create move
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        let _piece_move =
-            Move::from_board(BoardPosition::new(5, 3), BoardPosition::new(5, 3), board);
-    }
-
-    #[test]
-    fn test_chess_board_get_valid_moves() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Expected valid moves
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        let expected_valid_moves = vec![
-            Move {
-                from: BoardPosition::new(3, 1),
-                to: BoardPosition::new(1, 0),
-                piece_type: PieceType::Knight,
-                is_capture: true,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(3, 1),
-                to: BoardPosition::new(1, 2),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(3, 1),
-                to: BoardPosition::new(2, 3),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(3, 1),
-                to: BoardPosition::new(4, 3),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(3, 1),
-                to: BoardPosition::new(5, 0),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(3, 1),
-                to: BoardPosition::new(5, 2),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(4, 4),
-                to: BoardPosition::new(3, 4),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(4, 4),
-                to: BoardPosition::new(3, 3),
-                piece_type: PieceType::Pawn,
-                is_capture: true,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(5, 5),
-                to: BoardPosition::new(3, 4),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(5, 5),
-                to: BoardPosition::new(3, 6),
-                piece_type: PieceType::Knight,
-                is_capture: true,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(5, 5),
-                to: BoardPosition::new(4, 3),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(5, 5),
-                to: BoardPosition::new(4, 7),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(5, 5),
-                to: BoardPosition::new(7, 6),
-                piece_type: PieceType::Knight,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 0),
-                to: BoardPosition::new(5, 0),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 0),
-                to: BoardPosition::new(4, 0),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 1),
-                to: BoardPosition::new(5, 1),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 1),
-                to: BoardPosition::new(4, 1),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 2),
-                to: BoardPosition::new(5, 2),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 2),
-                to: BoardPosition::new(4, 2),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 6),
-                to: BoardPosition::new(5, 6),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 6),
-                to: BoardPosition::new(4, 6),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 7),
-                to: BoardPosition::new(5, 7),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(6, 7),
-                to: BoardPosition::new(4, 7),
-                piece_type: PieceType::Pawn,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(7, 0),
-                to: BoardPosition::new(7, 1),
-                piece_type: PieceType::Rook,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(7, 3),
-                to: BoardPosition::new(6, 4),
-                piece_type: PieceType::Queen,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(7, 4),
-                to: BoardPosition::new(6, 4),
-                piece_type: PieceType::King,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(7, 4),
-                to: BoardPosition::new(7, 5),
-                piece_type: PieceType::King,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(7, 4),
-                to: BoardPosition::new(7, 6),
-                piece_type: PieceType::King,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: true,
-            },
-            Move {
-                from: BoardPosition::new(7, 7),
-                to: BoardPosition::new(7, 5),
-                piece_type: PieceType::Rook,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-            Move {
-                from: BoardPosition::new(7, 7),
-                to: BoardPosition::new(7, 6),
-                piece_type: PieceType::Rook,
-                is_capture: false,
-                piece_color: PieceColor::White,
-                is_castle: false,
-            },
-        ];
-
-        // Get valid moves
-        let valid_moves = board.get_valid_moves(board.active_color(), &true);
-
-        // Confirm that the results match
-        assert_eq!(expected_valid_moves, valid_moves);
-    }
-
-    #[test]
-    fn test_chess_board_move_piece() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Confirm that the piece starts in the expected location
-        let mut board = app.world.get_resource_mut::<ChessBoard>().unwrap();
-        assert!(board.board[2][5].is_some());
-        assert_eq!(
-            *board.board[2][5].as_ref().unwrap().get_color(),
-            PieceColor::Black
-        );
-        assert_eq!(
-            *board.board[2][5].as_ref().unwrap().get_type(),
-            PieceType::Knight
-        );
-
-        // Move the piece
-        board.move_piece(&BoardPosition::new(2, 5), &BoardPosition::new(4, 6));
-
-        // Confirm that the piece has been moved
-        assert!(board.board[2][5].is_none());
-        assert!(board.board[4][6].is_some());
-        assert_eq!(
-            *board.board[4][6].as_ref().unwrap().get_color(),
-            PieceColor::Black
-        );
-        assert_eq!(
-            *board.board[4][6].as_ref().unwrap().get_type(),
-            PieceType::Knight
-        );
-    }
-
-    #[test]
-    #[should_panic(expected = "No piece at start location.")]
-    fn test_chess_board_move_piece_no_piece() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Attempt to move a non-existent piece
-        let mut board = app.world.get_resource_mut::<ChessBoard>().unwrap();
-        board.move_piece(&BoardPosition::new(2, 1), &BoardPosition::new(4, 6));
-    }
-
-    #[test]
-    fn test_chess_board_get_piece_type() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Confirm that get_piece_type returns the correct result
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        assert_eq!(
-            board.get_piece_type(&BoardPosition::new(1, 4)),
-            Some(PieceType::Pawn)
-        );
-        assert_eq!(board.get_piece_type(&BoardPosition::new(2, 6)), None);
-        assert_eq!(
-            board.get_piece_type(&BoardPosition::new(7, 2)),
-            Some(PieceType::Bishop)
-        );
-    }
-
-    #[test]
-    fn test_chess_board_get_piece_color() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/5n2/qN1p2p1/4P3/5N2/PPPP1PPP/R1BQK2R w KQkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Confirm that get_piece_color returns the correct result
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        assert_eq!(
-            board.get_piece_color(&BoardPosition::new(1, 4)),
-            Some(PieceColor::Black)
-        );
-        assert_eq!(board.get_piece_color(&BoardPosition::new(2, 6)), None);
-        assert_eq!(
-            board.get_piece_color(&BoardPosition::new(7, 2)),
-            Some(PieceColor::White)
-        );
-    }
-
-    #[test]
-    fn test_chess_board_in_check_white() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2pp1p/8/qN1p2N1/4P3/2Pn4/PP1P2PP/1RBQK2R w Kkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Confirm that we get the correct result
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        assert!(board.in_check(&PieceColor::White));
-        assert!(!board.in_check(&PieceColor::Black));
-    }
-
-    #[test]
-    fn test_chess_board_in_check_black() {
-        let fen =
-            Fen::from_string("rnb1kb1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R w Kkq - 0 1");
-
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
-
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
-
-        // Run systems
-        app.update();
-
-        // Confirm that we get the correct result
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        assert!(board.in_check(&PieceColor::Black));
-        assert!(!board.in_check(&PieceColor::White));
-    }
-
-    #[test]
-    fn test_chess_board_in_check_none() {
-        let fen =
-            Fen::from_string("rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1");
+    pub fn position(&self) -> &BoardPosition {
+        &self.position
+    }
 
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
+    pub fn piece_type(&self) -> &PieceType {
+        &self.piece_type
+    }
 
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
+    pub fn color(&self) -> &PieceColor {
+        &self.color
+    }
+}
 
-        // Run systems
-        app.update();
+/// The legal moves in the current position of the [ChessBoard], which are only generated once for
+/// each position.
+#[derive(Resource, Debug, Default)]
+pub struct LegalMoves(Vec<Move>);
 
-        // Confirm that we get the correct result
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        assert!(!board.in_check(&PieceColor::White));
-        assert!(!board.in_check(&PieceColor::Black));
+impl LegalMoves {
+    /// Generates the legal moves of the player to move on the given board.
+    pub fn of(board: &ChessBoard) -> Self {
+        LegalMoves(board.get_valid_moves(board.active_color(), &true))
     }
 
-    #[test]
-    fn test_chess_board_no_piece_between_squares_true() {
-        let fen =
-            Fen::from_string("rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1");
+    /// Returns the legal moves of the piece on the given square.
+    pub fn moves_from<'a>(
+        &'a self,
+        position: &'a BoardPosition,
+    ) -> impl Iterator<Item = &'a Move> + 'a {
+        self.0
+            .iter()
+            .filter(move |piece_move| piece_move.from() == position)
+    }
+}
 
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
+fn setup(
+    mut create_event: EventWriter<PieceCreateEvent>,
+    mut board: ResMut<ChessBoard>,
+    mut log: ResMut<GameLog>,
+    config: Res<ChessBoardConfig>,
+) {
+    let fen = &config.starting_fen;
+    log.record(
+        &mut board,
+        GameEvent::Reset(ResetBoardEvent::new(fen.clone())),
+    );
+
+    // Notify that each piece has been placed
+    for position in fen.size().positions() {
+        if let Some((color, piece_type)) = fen.piece_placement()[*position.rank()][*position.file()]
+        {
+            create_event.send(PieceCreateEvent {
+                position,
+                piece_type,
+                color,
+            });
+        }
+    }
+}
 
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
+/// The events sent by the [ChessBoard] as it carries out commands.
+#[derive(SystemParam)]
+struct BoardEvents<'w> {
+    moves: EventWriter<'w, PieceMoveEvent>,
+    creates: EventWriter<'w, PieceCreateEvent>,
+    resets: EventWriter<'w, ResetBoardEvent>,
+    commands: EventWriter<'w, GameCommandEvent>,
+    notifications: EventWriter<'w, Notification>,
+}
 
-        // Run systems
-        app.update();
+/// Applies and logs the given [GameEvent::Reset] or [GameEvent::TakenBack], telling whatever
+/// draws the pieces that the board has been reset.
+fn reset_board(
+    board: &mut ChessBoard,
+    log: &mut GameLog,
+    event: GameEvent,
+    events: &mut BoardEvents,
+) {
+    if let GameEvent::Reset(reset_event) | GameEvent::TakenBack(reset_event) = &event {
+        events.resets.send(reset_event.clone());
+    }
+    log.record(board, event);
+}
 
-        // Confirm that we get the correct result
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        assert!(
-            board.no_piece_between_squares(&BoardPosition::new(2, 1), &BoardPosition::new(6, 5))
-        );
+/// Ends the game if it is over once a move has been made or the duck placed, and the
+/// [ChessBoardConfig] checks for the end of the game.
+fn record_game_end(board: &mut ChessBoard, log: &mut GameLog, config: &ChessBoardConfig) {
+    if !config.check_game_end {
+        return;
     }
+    if let Some((status, winner)) = board.game_end() {
+        info!(?status, ?winner, "Game over");
+        log.record(board, GameEvent::Ended { status, winner });
+    }
+}
 
-    #[test]
-    fn test_chess_board_no_piece_between_squares_false() {
-        let fen =
-            Fen::from_string("rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1");
+/// Carries out the queued commands in order, logging the [GameEvent]s they come to.
+fn run_commands(
+    mut commands: ResMut<GameCommands>,
+    mut board: ResMut<ChessBoard>,
+    mut log: ResMut<GameLog>,
+    mut history: ResMut<PositionHistory>,
+    mut config: ResMut<ChessBoardConfig>,
+    mut events: BoardEvents,
+) {
+    // The queue is only touched when it has commands, so the board is not marked as changed
+    if commands.0.is_empty() {
+        return;
+    }
+    let log = &mut *log;
+    while let Some(command) = commands.0.pop_front() {
+        let _span = info_span!("command").entered();
+        events.commands.send(GameCommandEvent {
+            command: command.clone(),
+        });
+        match command {
+            GameCommand::MakeMove(piece_move) => {
+                if !config.allows(&board, &piece_move) {
+                    let refusal = config.refusal(&board, &piece_move);
+                    warn!(
+                        piece_move = %piece_move.as_algebraic(),
+                        reason = refusal.key(),
+                        "Move refused"
+                    );
+                    events.notifications.send(refusal);
+                    continue;
+                }
+                let captured = board.en_passant_capture(&piece_move);
+                let rook_move = log.record(&mut board, GameEvent::Moved(piece_move));
+                info!(
+                    piece_move = %piece_move.as_algebraic(),
+                    fen = %board.to_fen(),
+                    "Move made"
+                );
+                events.moves.send(
+                    PieceMoveEvent::new(*piece_move.from(), *piece_move.to())
+                        .with_capture(captured),
+                );
+                if let Some((from, to)) = rook_move {
+                    events.moves.send(PieceMoveEvent::new(from, to));
+                }
+                // The promoted piece replaces the pawn
+                if let Some(piece_type) = piece_move.promotion() {
+                    events.creates.send(PieceCreateEvent {
+                        position: *piece_move.to(),
+                        piece_type,
+                        color: *piece_move.piece_color(),
+                    });
+                }
+                record_game_end(&mut board, log, &config);
+            }
+            GameCommand::PlaceDuck(position) => {
+                if board.valid_duck_square(&position) {
+                    info!(square = %position.to_algebraic(), "Duck placed");
+                    log.record(&mut board, GameEvent::DuckPlaced(position));
+                    record_game_end(&mut board, log, &config);
+                }
+            }
+            GameCommand::Undo => {
+                if history.len() > 1 {
+                    info!("Move taken back");
+                    let reset_event = history.reset_event(history.len() - 2);
+                    reset_board(
+                        &mut board,
+                        log,
+                        GameEvent::TakenBack(reset_event),
+                        &mut events,
+                    );
+                }
+            }
+            GameCommand::Resign(color) => {
+                if board.active_color().is_some() {
+                    info!(%color, "Resigned");
+                    log.record(
+                        &mut board,
+                        GameEvent::Ended {
+                            status: GameEndStatus::Resignation,
+                            winner: Some(color.opposite()),
+                        },
+                    );
+                }
+            }
+            GameCommand::OfferDraw(color) => {
+                if board.active_color().is_some() && board.draw_offer().is_none() {
+                    log.record(&mut board, GameEvent::DrawOffered(color));
+                    info!(%color, "Draw offered");
+                    events.notifications.send(
+                        Notification::info("offers-draw")
+                            .with_arg("player", Argument::Color(color)),
+                    );
+                }
+            }
+            GameCommand::RespondDraw { color, accept } => {
+                // Only the opponent of the player who offered the draw can respond
+                if *board.draw_offer() == Some(color.opposite()) {
+                    info!(%color, accept, "Draw offer answered");
+                    log.record(&mut board, GameEvent::DrawAnswered { color, accept });
+                    if !accept {
+                        events.notifications.send(
+                            Notification::info("draw-declined")
+                                .with_arg("player", Argument::Color(color)),
+                        );
+                    }
+                }
+            }
+            GameCommand::RequestTakeback(color) => {
+                let plies = board.takeback_plies(&color);
+                if board.takeback_request().is_none()
+                    && plies.is_some_and(|plies| history.len() > plies)
+                {
+                    log.record(&mut board, GameEvent::TakebackRequested(color));
+                    info!(%color, "Take back requested");
+                    events.notifications.send(
+                        Notification::info("requests-takeback")
+                            .with_arg("player", Argument::Color(color)),
+                    );
+                }
+            }
+            GameCommand::RespondTakeback { color, accept } => {
+                // Only the opponent of the player who asked can respond
+                let requester = color.opposite();
+                if *board.takeback_request() != Some(requester) {
+                    continue;
+                }
+                info!(%color, accept, "Take back answered");
+                log.record(&mut board, GameEvent::TakebackAnswered { color, accept });
+                if !accept {
+                    events.notifications.send(
+                        Notification::info("takeback-declined")
+                            .with_arg("player", Argument::Color(color)),
+                    );
+                } else if let Some(plies) = board
+                    .takeback_plies(&requester)
+                    .filter(|plies| history.len() > *plies)
+                {
+                    let reset_event = history.reset_event(history.len() - 1 - plies);
+                    reset_board(
+                        &mut board,
+                        log,
+                        GameEvent::TakenBack(reset_event),
+                        &mut events,
+                    );
+                }
+            }
+            GameCommand::SetVariant(variant) => {
+                if !config.variants.contains(&variant) {
+                    warn!(?variant, "Variant refused");
+                    events
+                        .notifications
+                        .send(Notification::warning("variant-disabled"));
+                    continue;
+                }
+                info!(?variant, "Variant set");
+                let reset_event =
+                    ResetBoardEvent::new(config.starting_fen.clone()).with_variant(variant);
+                reset_board(&mut board, log, GameEvent::Reset(reset_event), &mut events);
+            }
+            GameCommand::SetSandbox(sandbox) => {
+                info!(sandbox, "Sandbox set");
+                config.enforce_legality = !sandbox;
+            }
+            GameCommand::Reset => {
+                info!("Board reset");
+                let reset_event = ResetBoardEvent::new(config.starting_fen.clone());
+                reset_board(&mut board, log, GameEvent::Reset(reset_event), &mut events);
+            }
+            GameCommand::LoadGame(reset_event) => {
+                if let Some(variant) = reset_event
+                    .variant()
+                    .filter(|variant| !config.variants.contains(variant))
+                {
+                    warn!(?variant, "Game of a refused variant not loaded");
+                    events
+                        .notifications
+                        .send(Notification::warning("variant-disabled"));
+                    continue;
+                }
+                info!(
+                    fen = %reset_event.fen(),
+                    moves = reset_event.past_moves().len(),
+                    variant = ?reset_event.variant(),
+                    "Game loaded"
+                );
+                reset_board(&mut board, log, GameEvent::Reset(*reset_event), &mut events);
+            }
+            GameCommand::FlagFall(color) => {
+                if *board.active_color() == Some(color) {
+                    // A player who runs out of time only loses if their opponent could still
+                    // checkmate, which only matters in standard chess
+                    let winner = (*board.variant() != Variant::Standard
+                        || board.has_mating_material(&color.opposite()))
+                    .then(|| color.opposite());
+                    info!(%color, "Flag fell");
+                    log.record(
+                        &mut board,
+                        GameEvent::Ended {
+                            status: GameEndStatus::FlagFall,
+                            winner,
+                        },
+                    );
+                }
+            }
+        }
+        // The history is otherwise only recorded at the end of the frame, so is kept up to date
+        // for any later undo in the queue
+        history.update(&board);
+    }
+}
 
-        // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_systems(Update, reset_board_state);
+fn update_legal_moves(
+    board: Res<ChessBoard>,
+    mut legal_moves: ResMut<LegalMoves>,
+    mut diagnostics: Diagnostics,
+) {
+    if board.is_changed() {
+        *legal_moves =
+            diagnostics::measure(&mut diagnostics, MOVE_GENERATION, || LegalMoves::of(&board));
+    }
+}
 
-        // Trigger reset board event
-        app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{Events, Startup, Update};
 
-        // Run systems
-        app.update();
+    use super::*;
 
-        // Confirm that we get the correct result
-        let board = app.world.get_resource::<ChessBoard>().unwrap();
-        assert!(
-            !board.no_piece_between_squares(&BoardPosition::new(1, 6), &BoardPosition::new(4, 3))
-        );
+    /// Returns an app with an empty board which carries out the commands pushed to it.
+    fn command_app() -> App {
+        let mut app = App::new();
+        app.insert_resource(ChessBoard::default());
+        app.init_resource::<GameLog>();
+        app.init_resource::<GameCommands>();
+        app.init_resource::<PositionHistory>();
+        app.init_resource::<ChessBoardConfig>();
+        app.add_event::<PieceCreateEvent>();
+        app.add_event::<PieceMoveEvent>();
+        app.add_event::<ResetBoardEvent>();
+        app.add_event::<GameCommandEvent>();
+        app.add_event::<Notification>();
+        app.add_systems(Update, run_commands);
+        app
     }
 
     #[test]
     fn test_setup() {
         // Setup app
         let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
+        app.insert_resource(ChessBoard::default());
+        app.init_resource::<GameLog>();
+        app.init_resource::<ChessBoardConfig>();
         app.add_event::<PieceCreateEvent>();
         app.add_systems(Startup, setup);
 
@@ -1391,7 +604,7 @@ mod tests {
         app.update();
 
         // Confirm that the chessboard has been set up correctly
-        let pieces = vec![
+        let pieces = [
             vec![
                 Some((PieceType::Rook, PieceColor::Black)),
                 Some((PieceType::Knight, PieceColor::Black)),
@@ -1452,7 +665,7 @@ mod tests {
             app.world
                 .get_resource::<ChessBoard>()
                 .unwrap()
-                .past_moves
+                .past_moves()
                 .len(),
             0
         );
@@ -1467,25 +680,16 @@ mod tests {
         );
 
         // Check pieces
-        let board = &app.world.get_resource::<ChessBoard>().unwrap().board;
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                if pieces[rank][file].is_none() {
-                    assert!(board[rank][file].is_none());
-                } else {
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_type(),
-                        pieces[rank][file].unwrap().0
-                    );
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_color(),
-                        pieces[rank][file].unwrap().1
-                    );
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_position(),
-                        BoardPosition::new(rank, file)
-                    );
-                }
+        let board = app.world.resource::<ChessBoard>();
+        for (rank, rank_pieces) in pieces.iter().enumerate() {
+            for (file, piece) in rank_pieces.iter().enumerate() {
+                let position = BoardPosition::new(rank, file);
+                assert_eq!(
+                    board
+                        .get_piece_type(&position)
+                        .zip(board.get_piece_color(&position)),
+                    *piece
+                );
             }
         }
     }
@@ -1496,145 +700,351 @@ mod tests {
             Fen::from_string("rnbk1b1r/pp2p2p/5p2/qN1p2NQ/4P3/2Pn4/PP1P2PP/1RB2K1R b Kkq - 0 1");
 
         // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<ResetBoardEvent>();
-        app.add_event::<PieceCreateEvent>();
-        app.add_event::<PieceMoveEvent>();
-        app.add_event::<RequestMoveEvent>();
-        app.add_systems(Update, (reset_board_state, make_move));
+        let mut app = command_app();
 
-        // Trigger reset board event
+        // Load the position
         app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
 
         // Run systems
         app.update();
 
-        // Trigger request move event
+        // Request a move
         let move_from = BoardPosition::new(2, 5);
         let move_to = BoardPosition::new(3, 6);
+        let piece_move = Move::from_board(move_from, move_to, app.world.resource::<ChessBoard>());
         app.world
-            .resource_mut::<Events<RequestMoveEvent>>()
-            .send(RequestMoveEvent::new(Move {
-                from: move_from,
-                to: move_to,
-                piece_type: PieceType::Pawn,
-                piece_color: PieceColor::Black,
-                is_capture: true,
-                is_castle: false,
-            }));
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::MakeMove(piece_move));
 
         // Run systems
         app.update();
 
         // Confirm that the piece has been correctly moved
-        let board = &app.world.get_resource::<ChessBoard>().unwrap().board;
-        assert_eq!(
-            app.world.get_resource::<ChessBoard>().unwrap().active_color,
-            Some(PieceColor::White)
-        );
-        assert!(board[3][6].is_some());
-        assert_eq!(
-            *board[3][6].as_ref().unwrap().get_color(),
-            PieceColor::Black
-        );
-        assert_eq!(*board[3][6].as_ref().unwrap().get_type(), PieceType::Pawn);
-        assert_eq!(*board[3][6].as_ref().unwrap().get_position(), move_to);
-        assert!(board[2][5].is_none());
-        assert_eq!(
-            *app.world
-                .get_resource::<ChessBoard>()
-                .unwrap()
-                .active_color(),
-            Some(PieceColor::White)
-        );
-        assert_eq!(
-            &app.world.get_resource::<ChessBoard>().unwrap().past_moves,
-            &vec![Move {
-                from: move_from,
-                to: move_to,
-                piece_type: PieceType::Pawn,
-                piece_color: PieceColor::Black,
-                is_capture: true,
-                is_castle: false
-            }]
-        );
-        assert_eq!(
-            *app.world
-                .get_resource::<ChessBoard>()
-                .unwrap()
-                .move_number(),
-            2
-        );
-
-        // Trigger request move event
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(*board.active_color(), Some(PieceColor::White));
+        assert_eq!(board.get_piece_color(&move_to), Some(PieceColor::Black));
+        assert_eq!(board.get_piece_type(&move_to), Some(PieceType::Pawn));
+        assert_eq!(board.get_piece_type(&move_from), None);
+        assert_eq!(board.past_moves(), &vec![piece_move]);
+        assert_eq!(*board.move_number(), 2);
+
+        // Request a move
         let move_from = BoardPosition::new(3, 7);
-        let move_to = BoardPosition::new(3, 6);
+        let piece_move = Move::from_board(move_from, move_to, board);
         app.world
-            .resource_mut::<Events<RequestMoveEvent>>()
-            .send(RequestMoveEvent::new(Move {
-                from: move_from,
-                to: move_to,
-                piece_type: PieceType::Pawn,
-                piece_color: PieceColor::White,
-                is_capture: false,
-                is_castle: false,
-            }));
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::MakeMove(piece_move));
 
         // Run systems
         app.update();
 
         // Confirm that the piece has been correctly moved
-        let board = &app.world.get_resource::<ChessBoard>().unwrap().board;
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(*board.active_color(), Some(PieceColor::Black));
+        assert_eq!(board.get_piece_color(&move_to), Some(PieceColor::White));
+        assert_eq!(board.get_piece_type(&move_to), Some(PieceType::Queen));
+        assert_eq!(board.get_piece_type(&move_from), None);
+        let moves: Vec<String> = board
+            .past_moves()
+            .iter()
+            .map(|piece_move| piece_move.as_algebraic())
+            .collect();
+        assert_eq!(moves, vec!["fxg5", "Qxg5"]);
+        assert_eq!(*board.move_number(), 2);
+    }
+
+    #[test]
+    fn test_draw_offers() {
+        // Setup app
+        let mut app = command_app();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(
+                Fen::default(),
+            ))));
+        app.update();
+
+        // White offers a draw, which white cannot accept themselves
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::OfferDraw(PieceColor::White));
+        app.update();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::RespondDraw {
+                color: PieceColor::White,
+                accept: true,
+            });
+        app.update();
+        let board = app.world.get_resource::<ChessBoard>().unwrap();
+        assert_eq!(*board.draw_offer(), Some(PieceColor::White));
+        assert_eq!(*board.game_end_status(), None);
+
+        // Black declines
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::RespondDraw {
+                color: PieceColor::Black,
+                accept: false,
+            });
+        app.update();
         assert_eq!(
-            app.world.get_resource::<ChessBoard>().unwrap().active_color,
-            Some(PieceColor::Black)
+            *app.world.get_resource::<ChessBoard>().unwrap().draw_offer(),
+            None
+        );
+
+        // Black offers a draw and white accepts
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::OfferDraw(PieceColor::Black));
+        app.update();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::RespondDraw {
+                color: PieceColor::White,
+                accept: true,
+            });
+        app.update();
+        let board = app.world.get_resource::<ChessBoard>().unwrap();
+        assert_eq!(*board.game_end_status(), Some(GameEndStatus::Agreement));
+        assert_eq!(*board.winner(), None);
+        assert_eq!(*board.draw_offer(), None);
+    }
+
+    #[test]
+    fn test_takebacks() {
+        let mut app = command_app();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::Reset);
+        app.update();
+        for text in ["e4", "e5", "Nf3"] {
+            let board = app.world.resource::<ChessBoard>();
+            let piece_move = Move::from_algebraic(text, board).unwrap();
+            app.world
+                .resource_mut::<GameCommands>()
+                .push(GameCommand::MakeMove(piece_move));
+            app.update();
+        }
+
+        // Black asks after white has replied, and cannot answer their own request
+        let mut commands = app.world.resource_mut::<GameCommands>();
+        commands.push(GameCommand::RequestTakeback(PieceColor::Black));
+        commands.push(GameCommand::RequestTakeback(PieceColor::White));
+        commands.push(GameCommand::RespondTakeback {
+            color: PieceColor::Black,
+            accept: true,
+        });
+        app.update();
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(*board.takeback_request(), Some(PieceColor::Black));
+        assert_eq!(board.past_moves().len(), 3);
+
+        // Accepting takes back both white's reply and black's move
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::RespondTakeback {
+                color: PieceColor::White,
+                accept: true,
+            });
+        app.update();
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(board.past_moves().len(), 1);
+        assert_eq!(*board.active_color(), Some(PieceColor::Black));
+        assert_eq!(*board.takeback_request(), None);
+
+        // A declined request leaves the moves, and black has no move left to take back
+        let mut commands = app.world.resource_mut::<GameCommands>();
+        commands.push(GameCommand::RequestTakeback(PieceColor::White));
+        commands.push(GameCommand::RespondTakeback {
+            color: PieceColor::Black,
+            accept: false,
+        });
+        commands.push(GameCommand::RequestTakeback(PieceColor::Black));
+        app.update();
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(board.past_moves().len(), 1);
+        assert_eq!(*board.takeback_request(), None);
+    }
+
+    #[test]
+    fn test_command_order() {
+        let mut app = command_app();
+        let board = ChessBoard::with_position(&Fen::default());
+        let e4 = Move::from_algebraic("e4", &board).unwrap();
+        let mut commands = app.world.resource_mut::<GameCommands>();
+        commands.push(GameCommand::Reset);
+        commands.push(GameCommand::MakeMove(e4));
+        // The same move is no longer legal once made
+        commands.push(GameCommand::MakeMove(e4));
+        commands.push(GameCommand::Undo);
+        commands.push(GameCommand::Resign(PieceColor::White));
+        app.update();
+
+        // The commands ran in the order they were pushed, all in the same frame
+        let board = app.world.get_resource::<ChessBoard>().unwrap();
+        assert!(board.past_moves().is_empty());
+        assert_eq!(*board.game_end_status(), Some(GameEndStatus::Resignation));
+        assert_eq!(*board.winner(), Some(PieceColor::Black));
+        assert!(app.world.resource::<GameCommands>().0.is_empty());
+    }
+
+    #[test]
+    fn test_config() {
+        let starting_fen = "4k3/8/4K3/8/8/8/8/R7 w - - 0 1";
+        let mut app = App::new();
+        app.add_plugins(ChessBoardPlugin::new(ChessBoardConfig {
+            starting_fen: Fen::from_string(starting_fen),
+            enforce_legality: false,
+            variants: vec![Variant::Standard],
+            check_game_end: false,
+        }));
+        app.update();
+        assert_eq!(
+            app.world.resource::<ChessBoard>().to_fen().to_string(),
+            starting_fen
+        );
+
+        // Mate does not end the game, and the king can then move next to the other king
+        for (from, to) in [("a1", "a8"), ("e8", "e7")] {
+            let board = app.world.resource::<ChessBoard>();
+            let piece_move = Move::from_board(
+                BoardPosition::from_algebraic(from).unwrap(),
+                BoardPosition::from_algebraic(to).unwrap(),
+                board,
+            );
+            let mut commands = app.world.resource_mut::<GameCommands>();
+            commands.push(GameCommand::MakeMove(piece_move));
+            app.update();
+        }
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(board.past_moves().len(), 2);
+        assert_eq!(*board.game_end_status(), None);
+
+        // Variants which are not enabled cannot be played, and a new game starts from the
+        // starting position
+        let mut commands = app.world.resource_mut::<GameCommands>();
+        commands.push(GameCommand::SetVariant(Variant::Duck));
+        commands.push(GameCommand::Reset);
+        app.update();
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(*board.variant(), Variant::Standard);
+        assert_eq!(board.to_fen().to_string(), starting_fen);
+    }
+
+    #[test]
+    fn test_refusal() {
+        let mut app = command_app();
+        let fen = Fen::from_string("4k3/8/8/8/8/8/4r3/R3K3 w - - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let king_stays_in_check = Move::from_board(square("a1"), square("a2"), &board);
+        let black_move = Move::from_board(square("e8"), square("d8"), &board);
+        let mut commands = app.world.resource_mut::<GameCommands>();
+        commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+        commands.push(GameCommand::MakeMove(king_stays_in_check));
+        commands.push(GameCommand::MakeMove(black_move));
+        app.update();
+        let events = app.world.resource::<Events<Notification>>();
+        let keys: Vec<&str> = events
+            .get_reader()
+            .iter(events)
+            .map(Notification::key)
+            .collect();
+        assert_eq!(keys, vec!["illegal-move-check", "illegal-move-turn"]);
+
+        let config = ChessBoardConfig::default();
+        let mut board = app.world.resource::<ChessBoard>().clone();
+        let rook_diagonal = Move::from_board(square("a1"), square("b2"), &board);
+        assert_eq!(config.refusal(&board, &rook_diagonal).key(), "illegal-move");
+        board.end_game(GameEndStatus::Resignation, Some(PieceColor::Black));
+        assert_eq!(
+            config.refusal(&board, &rook_diagonal).key(),
+            "illegal-move-game-over"
         );
-        assert!(board[3][6].is_some());
+    }
+
+    #[test]
+    fn test_castle_failure() {
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let failure = |fen, to| {
+            let board = ChessBoard::with_position(&Fen::from_string(fen));
+            let castle = Move::from_board(square("e1"), square(to), &board);
+            assert!(!board.valid_move(&castle, board.active_color(), &true));
+            (
+                board.castle_failure(&castle),
+                ChessBoardConfig::default()
+                    .castle_refusal(&board, &castle)
+                    .map(|notification| notification.key()),
+            )
+        };
         assert_eq!(
-            *board[3][6].as_ref().unwrap().get_color(),
-            PieceColor::White
+            failure("4k3/8/8/8/8/5r2/8/RN2K2R w KQ - 0 1", "c1"),
+            (
+                Some(CastleFailure::PathOccupied),
+                Some("castle-path-occupied")
+            )
         );
-        assert_eq!(*board[3][6].as_ref().unwrap().get_type(), PieceType::Queen);
-        assert_eq!(*board[3][6].as_ref().unwrap().get_position(), move_to);
-        assert!(board[3][7].is_none());
         assert_eq!(
-            *app.world
-                .get_resource::<ChessBoard>()
-                .unwrap()
-                .active_color(),
-            Some(PieceColor::Black)
+            failure("4k3/8/8/8/8/5r2/8/RN2K2R w KQ - 0 1", "g1").0,
+            Some(CastleFailure::ThroughCheck)
         );
         assert_eq!(
-            &app.world.get_resource::<ChessBoard>().unwrap().past_moves,
-            &vec![
-                Move {
-                    from: BoardPosition::new(2, 5),
-                    to: BoardPosition::new(3, 6),
-                    piece_type: PieceType::Pawn,
-                    piece_color: PieceColor::Black,
-                    is_capture: true,
-                    is_castle: false
-                },
-                Move {
-                    from: move_from,
-                    to: move_to,
-                    piece_type: PieceType::Pawn,
-                    piece_color: PieceColor::White,
-                    is_capture: false,
-                    is_castle: false
-                }
-            ]
+            failure("4k3/8/8/8/8/4r3/8/R3K2R w KQ - 0 1", "g1").0,
+            Some(CastleFailure::InCheck)
         );
         assert_eq!(
-            *app.world
-                .get_resource::<ChessBoard>()
-                .unwrap()
-                .move_number(),
-            2
+            failure("4k3/8/8/8/8/8/8/R3K2R w K - 0 1", "c1").0,
+            Some(CastleFailure::NoRights)
         );
+
+        // A castle which fails is not reported as leaving the king in check
+        let board = ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/8/R3K2R w K - 0 1"));
+        let castle = Move::from_board(square("e1"), square("c1"), &board);
+        let config = ChessBoardConfig::default();
+        assert_eq!(config.refusal(&board, &castle).key(), "illegal-move");
+        let castle = Move::from_board(square("e1"), square("g1"), &board);
+        assert_eq!(board.castle_failure(&castle), None);
+        assert!(config.castle_refusal(&board, &castle).is_none());
+    }
+
+    #[test]
+    fn test_sandbox() {
+        let mut app = command_app();
+        let fen = Fen::from_string("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let rook_jump = Move::from_board(square("a1"), square("h8"), &board);
+        let mut commands = app.world.resource_mut::<GameCommands>();
+        commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+        commands.push(GameCommand::MakeMove(rook_jump));
+        app.update();
+        assert!(app.world.resource::<ChessBoard>().past_moves().is_empty());
+
+        // In the sandbox the rook can jump over the king, but not capture it
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::SetSandbox(true));
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::MakeMove(rook_jump));
+        app.update();
+        let board = app.world.resource::<ChessBoard>().clone();
+        assert_eq!(board.past_moves().len(), 1);
+        let capture = Move::from_board(square("h8"), square("e8"), &board);
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::MakeMove(capture));
+        app.update();
+        assert_eq!(app.world.resource::<ChessBoard>().past_moves().len(), 1);
+
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::SetSandbox(false));
+        app.update();
+        assert!(app.world.resource::<ChessBoardConfig>().enforce_legality);
     }
 
     #[test]
@@ -1644,22 +1054,18 @@ mod tests {
         );
 
         // Setup app
-        let mut app = App::new();
-        app.insert_resource(ChessBoard::empty_board());
-        app.add_event::<PieceCreateEvent>();
-        app.add_event::<ResetBoardEvent>();
-        app.add_systems(Update, reset_board_state);
+        let mut app = command_app();
 
-        // Trigger reset board event
+        // Load the position
         app.world
-            .resource_mut::<Events<ResetBoardEvent>>()
-            .send(ResetBoardEvent::new(fen));
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
 
         // Run systems
         app.update();
 
         // Confirm that the chessboard has been set up correctly
-        let pieces = vec![
+        let pieces = [
             vec![
                 Some((PieceType::Rook, PieceColor::Black)),
                 Some((PieceType::King, PieceColor::Black)),
@@ -1756,7 +1162,7 @@ mod tests {
             app.world
                 .get_resource::<ChessBoard>()
                 .unwrap()
-                .past_moves
+                .past_moves()
                 .len(),
             0
         );
@@ -1771,26 +1177,71 @@ mod tests {
         );
 
         // Check pieces
-        let board = &app.world.get_resource::<ChessBoard>().unwrap().board;
-        for rank in 0..BOARD_SIZE {
-            for file in 0..BOARD_SIZE {
-                if pieces[rank][file].is_none() {
-                    assert!(board[rank][file].is_none());
-                } else {
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_type(),
-                        pieces[rank][file].unwrap().0
-                    );
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_color(),
-                        pieces[rank][file].unwrap().1
-                    );
-                    assert_eq!(
-                        *board[rank][file].as_ref().unwrap().get_position(),
-                        BoardPosition::new(rank, file)
-                    );
-                }
+        let board = app.world.resource::<ChessBoard>();
+        for (rank, rank_pieces) in pieces.iter().enumerate() {
+            for (file, piece) in rank_pieces.iter().enumerate() {
+                let position = BoardPosition::new(rank, file);
+                assert_eq!(
+                    board
+                        .get_piece_type(&position)
+                        .zip(board.get_piece_color(&position)),
+                    *piece
+                );
             }
         }
     }
+
+    #[test]
+    fn test_game_log() {
+        let mut app = App::new();
+        app.add_plugins(ChessBoardPlugin::default());
+        app.update();
+        let push = |app: &mut App, command| {
+            app.world.resource_mut::<GameCommands>().push(command);
+            app.update();
+        };
+        for text in ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6"] {
+            let piece_move =
+                Move::from_algebraic(text, app.world.resource::<ChessBoard>()).unwrap();
+            push(&mut app, GameCommand::MakeMove(piece_move));
+        }
+        push(&mut app, GameCommand::Undo);
+        push(&mut app, GameCommand::OfferDraw(PieceColor::White));
+        // Answering a draw offered by oneself is refused, so is not logged
+        push(
+            &mut app,
+            GameCommand::RespondDraw {
+                color: PieceColor::White,
+                accept: true,
+            },
+        );
+        for text in ["Nf6", "Qxf7"] {
+            let piece_move =
+                Move::from_algebraic(text, app.world.resource::<ChessBoard>()).unwrap();
+            push(&mut app, GameCommand::MakeMove(piece_move));
+        }
+
+        let log = app.world.resource::<GameLog>();
+        assert!(matches!(log.events()[0], GameEvent::Reset(_)));
+        assert!(matches!(log.events()[7], GameEvent::TakenBack(_)));
+        assert!(matches!(
+            log.events()[8],
+            GameEvent::DrawOffered(PieceColor::White)
+        ));
+        assert!(matches!(
+            log.events().last(),
+            Some(GameEvent::Ended {
+                status: GameEndStatus::Checkmate,
+                winner: Some(PieceColor::White)
+            })
+        ));
+        assert_eq!(log.events().len(), 12);
+
+        let folded = game_log::fold(log.events());
+        let board = app.world.resource::<ChessBoard>();
+        assert_eq!(folded.to_fen().to_string(), board.to_fen().to_string());
+        assert_eq!(folded.past_moves(), board.past_moves());
+        assert_eq!(*folded.game_end_status(), Some(GameEndStatus::Checkmate));
+        assert_eq!(*folded.draw_offer(), None);
+    }
 }
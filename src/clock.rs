@@ -0,0 +1,369 @@
+//! Contains the [ChessClock] which tracks the time remaining for each player.
+
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{Event, EventReader, EventWriter, Res, ResMut, Resource, Update};
+use bevy::time::Time;
+
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor, ResetBoardEvent};
+
+/// The default time below which a player is warned that they are low on time.
+const DEFAULT_LOW_TIME_THRESHOLD: Duration = Duration::from_secs(30);
+
+pub(super) struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        use bevy::prelude::IntoSystemConfigs;
+
+        app.add_event::<LowTimeEvent>()
+            .init_resource::<ChessClock>()
+            .add_systems(Update, (reset_clock, tick_clock).chain());
+    }
+}
+
+/// Event sent by the [ChessClock] for every second that passes while a player is low on time.
+#[derive(Debug, Clone, Copy, Event, PartialEq, Eq)]
+pub struct LowTimeEvent {
+    color: PieceColor,
+    remaining: Duration,
+}
+
+impl LowTimeEvent {
+    pub fn color(&self) -> &PieceColor {
+        &self.color
+    }
+
+    pub fn remaining(&self) -> &Duration {
+        &self.remaining
+    }
+}
+
+/// The clock counting down the time remaining for each player.
+#[derive(Resource, Debug, Clone)]
+pub struct ChessClock {
+    /// The time white and black start with, which differ when one player is given time odds, or
+    /// [None] if the game is untimed.
+    time_controls: Option<[Duration; 2]>,
+    /// The time remaining for white and black.
+    remaining: [Duration; 2],
+    /// The time below which a player is low on time.
+    low_time_threshold: Duration,
+    /// Whether neither clock runs, e.g. while the opponent of a network game is disconnected.
+    paused: bool,
+    /// Whether neither clock runs while the player is away from the window.
+    away: bool,
+    /// Whether the player has just come back to the window, so the time the app spent without
+    /// updating while they were away is not counted.
+    returning: bool,
+}
+
+impl Default for ChessClock {
+    fn default() -> Self {
+        ChessClock::new(None)
+    }
+}
+
+impl ChessClock {
+    /// Creates a new [ChessClock] with the given time for each player.
+    pub fn new(time_control: Option<Duration>) -> Self {
+        let time_controls = time_control.map(|time_control| [time_control; 2]);
+        ChessClock {
+            time_controls,
+            remaining: time_controls.unwrap_or_default(),
+            low_time_threshold: DEFAULT_LOW_TIME_THRESHOLD,
+            paused: false,
+            away: false,
+            returning: false,
+        }
+    }
+
+    /// Returns the times white and black start with.
+    pub fn time_controls(&self) -> &Option<[Duration; 2]> {
+        &self.time_controls
+    }
+
+    /// Sets the time each player starts with and resets the clock.
+    pub fn set_time_control(&mut self, time_control: Option<Duration>) {
+        self.set_time_controls(time_control.map(|time_control| [time_control; 2]));
+    }
+
+    /// Sets the times white and black start with and resets the clock.
+    pub fn set_time_controls(&mut self, time_controls: Option<[Duration; 2]>) {
+        self.time_controls = time_controls;
+        self.reset();
+    }
+
+    /// Returns whether the players start with different times.
+    pub fn has_time_odds(&self) -> bool {
+        self.time_controls
+            .is_some_and(|[white, black]| white != black)
+    }
+
+    /// Returns the time remaining for the given player, or [None] if the game is untimed.
+    pub fn remaining(&self, color: &PieceColor) -> Option<Duration> {
+        self.time_controls.map(|_| self.remaining[*color as usize])
+    }
+
+    /// Sets the time remaining for the given player, e.g. when restoring an interrupted game.
+    pub fn set_remaining(&mut self, color: &PieceColor, remaining: Duration) {
+        self.remaining[*color as usize] = remaining;
+    }
+
+    /// Returns the time below which a player is low on time.
+    pub fn low_time_threshold(&self) -> &Duration {
+        &self.low_time_threshold
+    }
+
+    pub fn set_low_time_threshold(&mut self, threshold: Duration) {
+        self.low_time_threshold = threshold;
+    }
+
+    /// Returns whether the given player is low on time.
+    pub fn is_low(&self, color: &PieceColor) -> bool {
+        self.remaining(color)
+            .is_some_and(|remaining| remaining < self.low_time_threshold)
+    }
+
+    /// Stops or restarts both clocks, keeping the time remaining.
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Stops both clocks while the player is away from the window, or restarts them when they come
+    /// back, keeping the time remaining.
+    pub fn set_away(&mut self, away: bool) {
+        self.returning = self.away && !away;
+        self.away = away;
+    }
+
+    /// Resets both players to their full time control.
+    pub fn reset(&mut self) {
+        self.remaining = self.time_controls.unwrap_or_default();
+    }
+
+    /// Runs the given player's clock for the given time.
+    /// Returns a [LowTimeEvent] if this crossed into a new second while the player is low on time.
+    fn tick(&mut self, color: &PieceColor, delta: Duration) -> Option<LowTimeEvent> {
+        self.time_controls?;
+        let before = self.remaining[*color as usize];
+        let after = before.saturating_sub(delta);
+        self.remaining[*color as usize] = after;
+        if after < self.low_time_threshold
+            && (before >= self.low_time_threshold || before.as_secs() != after.as_secs())
+        {
+            Some(LowTimeEvent {
+                color: *color,
+                remaining: after,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+fn reset_clock(mut reset_events: EventReader<ResetBoardEvent>, mut clock: ResMut<ChessClock>) {
+    if !reset_events.is_empty() {
+        reset_events.clear();
+        clock.reset();
+    }
+}
+
+fn tick_clock(
+    mut clock: ResMut<ChessClock>,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+    mut low_time_events: EventWriter<LowTimeEvent>,
+    time: Res<Time>,
+) {
+    // The clock starts once the first move has been made
+    if board.past_moves().is_empty() || clock.paused || clock.away {
+        return;
+    }
+    if clock.returning {
+        clock.returning = false;
+        return;
+    }
+    if let Some(color) = *board.active_color() {
+        if let Some(event) = clock.tick(&color, time.delta()) {
+            low_time_events.send(event);
+        }
+        if clock.remaining(&color) == Some(Duration::ZERO) {
+            commands.push(GameCommand::FlagFall(color));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chess_clock_untimed() {
+        let mut clock = ChessClock::default();
+
+        assert_eq!(clock.remaining(&PieceColor::White), None);
+        assert_eq!(clock.tick(&PieceColor::White, Duration::from_secs(5)), None);
+        assert!(!clock.is_low(&PieceColor::White));
+    }
+
+    #[test]
+    fn test_chess_clock_tick() {
+        let mut clock = ChessClock::new(Some(Duration::from_secs(60)));
+
+        assert_eq!(
+            clock.tick(&PieceColor::White, Duration::from_secs(20)),
+            None
+        );
+        assert_eq!(
+            clock.remaining(&PieceColor::White),
+            Some(Duration::from_secs(40))
+        );
+        assert_eq!(
+            clock.remaining(&PieceColor::Black),
+            Some(Duration::from_secs(60))
+        );
+        assert!(!clock.is_low(&PieceColor::White));
+
+        // Crossing the threshold sends a low time event
+        assert_eq!(
+            clock.tick(&PieceColor::White, Duration::from_millis(10500)),
+            Some(LowTimeEvent {
+                color: PieceColor::White,
+                remaining: Duration::from_millis(29500)
+            })
+        );
+        assert!(clock.is_low(&PieceColor::White));
+
+        // An event is sent for each new second while low on time
+        assert_eq!(
+            clock.tick(&PieceColor::White, Duration::from_millis(400)),
+            None
+        );
+        assert!(clock
+            .tick(&PieceColor::White, Duration::from_millis(200))
+            .is_some());
+
+        // The clock stops at zero
+        clock.tick(&PieceColor::White, Duration::from_secs(100));
+        assert_eq!(clock.remaining(&PieceColor::White), Some(Duration::ZERO));
+
+        clock.reset();
+        assert_eq!(
+            clock.remaining(&PieceColor::White),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_chess_clock_time_odds() {
+        let mut clock = ChessClock::new(Some(Duration::from_secs(60)));
+        assert!(!clock.has_time_odds());
+
+        clock.set_time_controls(Some([Duration::from_secs(600), Duration::from_secs(60)]));
+        assert!(clock.has_time_odds());
+        clock.tick(&PieceColor::Black, Duration::from_secs(20));
+        clock.tick(&PieceColor::White, Duration::from_secs(20));
+        assert_eq!(
+            clock.remaining(&PieceColor::White),
+            Some(Duration::from_secs(580))
+        );
+        assert_eq!(
+            clock.remaining(&PieceColor::Black),
+            Some(Duration::from_secs(40))
+        );
+
+        clock.reset();
+        assert_eq!(
+            clock.remaining(&PieceColor::White),
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            clock.remaining(&PieceColor::Black),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[test]
+    fn test_flag_fall() {
+        use crate::chess_board::r#move::Move;
+        use crate::chess_board::{ChessBoardPlugin, GameEndStatus};
+        use crate::fen::Fen;
+
+        for (fen, winner) in [
+            // White runs out of time against a pawn, which could still checkmate
+            ("4k3/4p3/8/8/8/8/8/4K3 w - - 0 1", Some(PieceColor::Black)),
+            // But not against a bare king
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", None),
+        ] {
+            let mut app = App::new();
+            app.add_plugins((ChessBoardPlugin::default(), ClockPlugin));
+            app.init_resource::<Time>();
+            app.insert_resource(ChessClock::new(Some(Duration::from_secs(1))));
+            app.update();
+            app.world
+                .resource_mut::<GameCommands>()
+                .push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(
+                    Fen::from_string(fen),
+                ))));
+            app.update();
+
+            // Make a move so the clock starts, then run white out of time
+            let board = app.world.resource::<ChessBoard>();
+            let piece_move = Move::from_algebraic("Kd1", board).unwrap();
+            app.world
+                .resource_mut::<GameCommands>()
+                .push(GameCommand::MakeMove(piece_move));
+            app.update();
+            let board = app.world.resource::<ChessBoard>();
+            let piece_move = Move::from_algebraic("Kd8", board).unwrap();
+            app.world
+                .resource_mut::<GameCommands>()
+                .push(GameCommand::MakeMove(piece_move));
+            app.update();
+            app.world
+                .resource_mut::<ChessClock>()
+                .set_remaining(&PieceColor::White, Duration::ZERO);
+            app.update();
+
+            let board = app.world.resource::<ChessBoard>();
+            assert_eq!(*board.game_end_status(), Some(GameEndStatus::FlagFall));
+            assert_eq!(*board.winner(), winner);
+        }
+    }
+
+    #[test]
+    fn test_away() {
+        use crate::chess_board::r#move::Move;
+        use crate::chess_board::{ChessBoardPlugin, GameEndStatus};
+
+        let mut app = App::new();
+        app.add_plugins((ChessBoardPlugin::default(), ClockPlugin));
+        app.init_resource::<Time>();
+        app.insert_resource(ChessClock::new(Some(Duration::from_secs(60))));
+        app.update();
+        for text in ["e4", "e5"] {
+            let board = app.world.resource::<ChessBoard>();
+            let piece_move = Move::from_algebraic(text, board).unwrap();
+            app.world
+                .resource_mut::<GameCommands>()
+                .push(GameCommand::MakeMove(piece_move));
+            app.update();
+        }
+        let game_end = |app: &App| *app.world.resource::<ChessBoard>().game_end_status();
+
+        let mut clock = app.world.resource_mut::<ChessClock>();
+        clock.set_away(true);
+        clock.set_remaining(&PieceColor::White, Duration::ZERO);
+        app.update();
+        assert_eq!(game_end(&app), None);
+        // The frame the player comes back in is not counted
+        app.world.resource_mut::<ChessClock>().set_away(false);
+        app.update();
+        assert_eq!(game_end(&app), None);
+        app.update();
+        assert_eq!(game_end(&app), Some(GameEndStatus::FlagFall));
+    }
+}
@@ -0,0 +1,225 @@
+//! Rule-based explanations of moves, given by the coach alongside the engine's hints.
+//!
+//! The reasons are found from the pieces attacking and defending each square before and after
+//! the move and the material it wins, and are ordered from the most to the least important.
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType, BOARD_SIZE};
+use crate::exchange::{value, Target};
+
+/// The centre squares d5, e5, d4 and e4.
+const CENTRE: [(usize, usize); 4] = [(3, 3), (3, 4), (4, 3), (4, 4)];
+
+/// A reason a move is good.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    Checkmate,
+    Check,
+    /// Captures a piece, which was undefended if the capture is free.
+    Captures {
+        target: Target,
+        free: bool,
+    },
+    /// Attacks two pieces at once, each undefended or worth more than the moving piece.
+    Fork {
+        first: Target,
+        second: Target,
+    },
+    AttacksUndefended {
+        target: Target,
+    },
+    /// Attacks a piece worth more than the moving piece.
+    Attacks {
+        target: Target,
+    },
+    /// Moves a piece which was attacked to a square where it is not.
+    Escapes {
+        piece_type: PieceType,
+    },
+    /// Defends a piece which was attacked and undefended.
+    Defends {
+        target: Target,
+    },
+    Castles,
+    /// Moves a knight or bishop off its starting rank.
+    Develops,
+    OccupiesCentre,
+    /// The move has none of the other reasons.
+    Improves {
+        piece_type: PieceType,
+    },
+}
+
+/// Returns whether the piece of the given color on the given square is attacked and undefended.
+fn hanging(board: &ChessBoard, position: &BoardPosition, color: &PieceColor) -> bool {
+    !board.attackers(position, &color.opposite()).is_empty()
+        && board.attackers(position, color).is_empty()
+}
+
+/// Returns the pieces of the given color.
+fn pieces(board: &ChessBoard, color: PieceColor) -> impl Iterator<Item = Target> + '_ {
+    board
+        .size()
+        .positions()
+        .filter(move |position| board.get_piece_color(position) == Some(color))
+        .filter_map(|position| Some((board.get_piece_type(&position)?, position)))
+}
+
+/// Returns the reasons the given legal move is good, most important first.
+pub fn explain(board: &ChessBoard, piece_move: &Move) -> Vec<Reason> {
+    let color = *piece_move.piece_color();
+    let mover = *piece_move.piece_type();
+    let to = *piece_move.to();
+    let mut after = board.clone();
+    after.apply_move(piece_move);
+    let mut reasons = Vec::new();
+
+    let gives_check = after
+        .past_moves()
+        .last()
+        .is_some_and(|last_move| last_move.is_check());
+    if gives_check && after.legal_moves_iter().next().is_none() {
+        return vec![Reason::Checkmate];
+    }
+    if gives_check {
+        reasons.push(Reason::Check);
+    }
+
+    if let Some(captured) = board.get_piece_type(&to) {
+        let free = board.attackers(&to, &color.opposite()).is_empty();
+        reasons.push(Reason::Captures {
+            target: (captured, to),
+            free,
+        });
+    }
+
+    // The pieces the moved piece now attacks which are worth attacking, most valuable first
+    let mut targets: Vec<Target> = pieces(&after, color.opposite())
+        .filter(|(_, position)| after.attackers(position, &color).contains(&to))
+        .filter(|(piece_type, position)| {
+            value(piece_type) > value(&mover)
+                || after.attackers(position, &color.opposite()).is_empty()
+        })
+        .collect();
+    targets.sort_by_key(|(piece_type, _)| -value(piece_type));
+    match targets[..] {
+        [first, second, ..] => reasons.push(Reason::Fork { first, second }),
+        [target] if target.0 != PieceType::King => reasons.push(
+            if after.attackers(&target.1, &color.opposite()).is_empty() {
+                Reason::AttacksUndefended { target }
+            } else {
+                Reason::Attacks { target }
+            },
+        ),
+        _ => {}
+    }
+
+    if !board
+        .attackers(piece_move.from(), &color.opposite())
+        .is_empty()
+        && after.attackers(&to, &color.opposite()).is_empty()
+    {
+        reasons.push(Reason::Escapes { piece_type: mover });
+    }
+
+    for target in pieces(board, color) {
+        if target.1 != *piece_move.from()
+            && hanging(board, &target.1, &color)
+            && after.attackers(&target.1, &color).contains(&to)
+        {
+            reasons.push(Reason::Defends { target });
+        }
+    }
+
+    if piece_move.is_castle() {
+        reasons.push(Reason::Castles);
+    }
+    let home_rank = match color {
+        PieceColor::White => BOARD_SIZE - 1,
+        PieceColor::Black => board.size().top_rank(),
+    };
+    if matches!(mover, PieceType::Knight | PieceType::Bishop)
+        && *piece_move.from().rank() == home_rank
+    {
+        reasons.push(Reason::Develops);
+    }
+    if CENTRE.contains(&(*to.rank(), *to.file())) {
+        reasons.push(Reason::OccupiesCentre);
+    }
+
+    if reasons.is_empty() {
+        reasons.push(Reason::Improves { piece_type: mover });
+    }
+    reasons
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    fn explain_move(fen: &str, text: &str) -> Vec<Reason> {
+        let board = ChessBoard::with_position(&Fen::from_string(fen));
+        explain(&board, &Move::from_algebraic(text, &board).unwrap())
+    }
+
+    fn square(text: &str) -> BoardPosition {
+        BoardPosition::from_algebraic(text).unwrap()
+    }
+
+    #[test]
+    fn test_explain() {
+        // Develops a knight onto an undefended pawn
+        let reasons = explain_move(
+            "rnb1kbnr/ppp1pppp/8/3p4/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+            "Nc3",
+        );
+        assert_eq!(
+            reasons,
+            vec![
+                Reason::AttacksUndefended {
+                    target: (PieceType::Pawn, square("d5"))
+                },
+                Reason::Develops
+            ]
+        );
+
+        // Takes an undefended queen
+        let reasons = explain_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", "Rxd5");
+        assert_eq!(
+            reasons[0],
+            Reason::Captures {
+                target: (PieceType::Queen, square("d5")),
+                free: true
+            }
+        );
+
+        let reasons = explain_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "Ra8");
+        assert_eq!(reasons, vec![Reason::Checkmate]);
+    }
+
+    #[test]
+    fn test_explain_fork() {
+        let reasons = explain_move("r3k3/8/8/3N4/8/8/8/4K3 w - - 0 1", "Nc7");
+        assert_eq!(
+            reasons,
+            vec![
+                Reason::Check,
+                Reason::Fork {
+                    first: (PieceType::King, square("e8")),
+                    second: (PieceType::Rook, square("a8"))
+                }
+            ]
+        );
+
+        // Saves a knight attacked by a pawn
+        let reasons = explain_move("4k3/8/8/2p5/3N4/8/8/4K3 w - - 0 1", "Nf3");
+        assert_eq!(
+            reasons,
+            vec![Reason::Escapes {
+                piece_type: PieceType::Knight
+            }]
+        );
+    }
+}
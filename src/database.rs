@@ -0,0 +1,709 @@
+//! Keeps every finished game in the [GamesDatabase], so past games can be browsed and reopened
+//! for review.
+//!
+//! The database is read from [DATABASE_PATH] when the app starts and written back whenever a game
+//! finishes, or kept in SQLite instead with the `sqlite` feature, which takes the games of
+//! [DATABASE_PATH] the first time it starts. Each game is stored with its date, the
+//! [player](crate::profile) playing, their opponent and color, its moves, result and time
+//! controls. A reopened game is loaded with its whole history, so every position can be stepped
+//! through, and is not stored again when it is reopened.
+//!
+//! Any position of a game can also be played from as a drill, with the engine taking the side of
+//! the player's opponent, so the player can try to improve on how the game went on. A finished drill
+//! is stored as a training game linked to the game it was played from, and is not rated.
+//!
+//! Games analysed by the [batch analysis](crate::batch_analysis) are stored with the result.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::app::{App, Plugin};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{
+    DetectChanges, Event, EventReader, EventWriter, PreUpdate, Res, ResMut, Resource, Startup,
+    Update,
+};
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{
+    ChessBoard, GameCommand, GameCommands, GameEndStatus, PieceColor, ResetBoardEvent,
+};
+use crate::clock::ChessClock;
+use crate::eco::{classify, Opening};
+use crate::engine::Engine;
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::notification::{Argument, Notification};
+use crate::profile::Profiles;
+use crate::review::Mistake;
+use crate::storage;
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+/// The file the games database is stored in.
+const DATABASE_PATH: &str = "games.ron";
+
+pub(super) struct DatabasePlugin;
+
+impl Plugin for DatabasePlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_event::<ReviewGameEvent>()
+            .add_event::<DrillEvent>()
+            .init_resource::<GamesDatabase>()
+            .init_resource::<CurrentDrill>()
+            .add_systems(Startup, load_database)
+            .add_systems(PreUpdate, (review_game, start_drill))
+            .add_systems(Update, record_finished_game);
+    }
+}
+
+/// Event sent to reopen the stored game at the given index for review.
+#[derive(Event)]
+pub struct ReviewGameEvent {
+    index: usize,
+}
+
+impl ReviewGameEvent {
+    pub fn new(index: usize) -> Self {
+        ReviewGameEvent { index }
+    }
+}
+
+/// Event sent to play on from the position after the given number of the current game's moves,
+/// counting any made before its starting position, against the engine.
+#[derive(Event)]
+pub struct DrillEvent {
+    moves: usize,
+}
+
+impl DrillEvent {
+    pub fn new(moves: usize) -> Self {
+        DrillEvent { moves }
+    }
+}
+
+/// The game a drill was played from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrillLink {
+    /// The index of the game played from, if it had been stored.
+    pub game: Option<usize>,
+    /// The number of moves of the game played before the player took over.
+    pub ply: usize,
+}
+
+/// The drill being played, with the moves of the game it was started after.
+#[derive(Resource, Debug, Default)]
+pub struct CurrentDrill(Option<(DrillLink, Vec<Move>)>);
+
+impl CurrentDrill {
+    /// Returns whether the game being played would change the player's rating, as a game against
+    /// the engine does unless it is a drill or played at adaptive strength.
+    pub fn is_rated(&self, engine: &Engine) -> bool {
+        engine.color().is_some() && self.0.is_none() && !engine.adaptive().enabled
+    }
+}
+
+/// Who the player played against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Opponent {
+    /// Another person, at the same board.
+    Human,
+    /// The engine, searching to the given depth.
+    Engine { depth: u32 },
+}
+
+/// The result of a stored game for the player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum PlayerResult {
+    Won,
+    Lost,
+    Drawn,
+}
+
+/// The engine's review of a stored game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameAnalysis {
+    /// The depth each position was searched to.
+    pub depth: u32,
+    /// The evaluation in centipawns of each position, from the point of view of the player to
+    /// move in it.
+    pub evaluations: Vec<i32>,
+    /// The biggest mistake of white and of black.
+    pub mistakes: [Option<Mistake>; 2],
+}
+
+/// A finished game kept in the database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredGame {
+    /// The date the game finished, in the format of the PGN `Date` tag, e.g. "2024.03.09".
+    date: String,
+    /// The name of the player, which games stored before there were profiles lack.
+    #[serde(default)]
+    player: Option<String>,
+    opponent: Opponent,
+    /// The color the player played, or None if both sides were played at the same board.
+    player_color: Option<PieceColor>,
+    /// The FEN of the starting position.
+    start: String,
+    /// The moves made from the starting position, in standard algebraic notation.
+    moves: Vec<String>,
+    /// The opening the game reached, which games stored before openings were classified lack.
+    #[serde(default)]
+    opening: Option<Opening>,
+    status: GameEndStatus,
+    winner: Option<PieceColor>,
+    /// The time white and black started with.
+    time_controls: Option<[Duration; 2]>,
+    /// The game this one was played from as a drill, which games not played as drills lack.
+    #[serde(default)]
+    drill: Option<DrillLink>,
+    /// The engine's review of the game, once it has been analysed.
+    #[serde(default)]
+    analysis: Option<GameAnalysis>,
+}
+
+impl StoredGame {
+    /// Stores the finished game on the given board, or returns None if it has not finished or
+    /// has no moves.
+    fn new(
+        board: &ChessBoard,
+        history: &PositionHistory,
+        clock: &ChessClock,
+        engine: &Engine,
+        player: &str,
+        date: String,
+    ) -> Option<Self> {
+        if history.moves().is_empty() {
+            return None;
+        }
+        let opponent = match engine.color() {
+            Some(_) => Opponent::Engine {
+                depth: engine.depth(),
+            },
+            None => Opponent::Human,
+        };
+        Some(StoredGame {
+            date,
+            player: Some(player.to_string()),
+            opponent,
+            player_color: engine.color().map(|color| color.opposite()),
+            start: history.start()?.to_string(),
+            moves: history.moves().iter().map(Move::as_algebraic).collect(),
+            opening: classify(history.positions().iter()).cloned(),
+            status: (*board.game_end_status())?,
+            winner: *board.winner(),
+            time_controls: *clock.time_controls(),
+            drill: None,
+            analysis: None,
+        })
+    }
+
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+
+    pub fn player(&self) -> &Option<String> {
+        &self.player
+    }
+
+    pub fn opponent(&self) -> &Opponent {
+        &self.opponent
+    }
+
+    pub fn player_color(&self) -> &Option<PieceColor> {
+        &self.player_color
+    }
+
+    pub fn opening(&self) -> &Option<Opening> {
+        &self.opening
+    }
+
+    pub fn status(&self) -> &GameEndStatus {
+        &self.status
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn drill(&self) -> &Option<DrillLink> {
+        &self.drill
+    }
+
+    pub fn analysis(&self) -> &Option<GameAnalysis> {
+        &self.analysis
+    }
+
+    /// Returns the result for the player, or for white if both sides were played at the same
+    /// board.
+    pub fn result(&self) -> PlayerResult {
+        match self.winner {
+            None => PlayerResult::Drawn,
+            Some(winner) if winner == self.player_color.unwrap_or(PieceColor::White) => {
+                PlayerResult::Won
+            }
+            Some(_) => PlayerResult::Lost,
+        }
+    }
+
+    /// Replays the stored moves, returning the starting position and the moves, or None if a
+    /// move is not legal.
+    pub fn replay(&self) -> Option<(Fen, Vec<Move>)> {
+        let start = Fen::parse(&self.start)?;
+        let reset_event = ResetBoardEvent::from_algebraic_moves(&start, &self.moves)?;
+        Some((start, reset_event.past_moves().clone()))
+    }
+
+    /// Returns the position the game ended in, or None if a move is not legal.
+    pub fn final_position(&self) -> Option<Fen> {
+        let start = Fen::parse(&self.start)?;
+        let reset_event = ResetBoardEvent::from_algebraic_moves(&start, &self.moves)?;
+        Some(reset_event.fen().clone())
+    }
+
+    /// Returns a hash of the game's start and moves, the same from one run of the app to the
+    /// next, which games with the same moves share.
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a, with a separator so the fields cannot run into each other
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for field in std::iter::once(&self.start).chain(&self.moves) {
+            for byte in field.bytes().chain([0]) {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        }
+        hash
+    }
+}
+
+/// The filters the database browser shows games with. Filters which are None or empty match
+/// every game.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GameFilter {
+    pub player: Option<String>,
+    pub opponent: Option<Opponent>,
+    pub player_color: Option<PieceColor>,
+    pub result: Option<PlayerResult>,
+    /// The earliest date, or the start of it such as "2024.03".
+    pub from: String,
+    /// The latest date, or the start of it such as "2024.03".
+    pub to: String,
+}
+
+impl GameFilter {
+    /// Returns whether the given game passes the filters.
+    pub fn matches(&self, game: &StoredGame) -> bool {
+        let (from, to) = (self.from.trim(), self.to.trim());
+        let date_start = |length: usize| &game.date[..length.min(game.date.len())];
+        self.player
+            .as_ref()
+            .is_none_or(|player| Some(player) == game.player.as_ref())
+            && self
+                .opponent
+                .is_none_or(|opponent| opponent == game.opponent)
+            && self
+                .player_color
+                .is_none_or(|color| Some(color) == game.player_color)
+            && self.result.is_none_or(|result| result == game.result())
+            && date_start(from.len()) >= from
+            && (to.is_empty() || date_start(to.len()) <= to)
+    }
+}
+
+/// The finished games, oldest first.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct GamesDatabase {
+    games: Vec<StoredGame>,
+}
+
+impl GamesDatabase {
+    pub fn get(&self, index: usize) -> Option<&StoredGame> {
+        self.games.get(index)
+    }
+
+    /// Returns the index of the stored game with the given moves from the given starting
+    /// position.
+    fn find(&self, start: &Fen, moves: &[Move]) -> Option<usize> {
+        let start = start.to_string();
+        self.games.iter().position(|game| {
+            game.start == start
+                && game.moves.len() == moves.len()
+                && game
+                    .moves
+                    .iter()
+                    .zip(moves)
+                    .all(|(stored, piece_move)| *stored == piece_move.as_algebraic())
+        })
+    }
+
+    /// Returns the index and game of each game which passes the given filters, newest first.
+    pub fn filtered<'a>(
+        &'a self,
+        filter: &'a GameFilter,
+    ) -> impl Iterator<Item = (usize, &'a StoredGame)> + 'a {
+        self.games
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, game)| filter.matches(game))
+    }
+
+    /// Returns the distinct opponents of the stored games.
+    pub fn opponents(&self) -> Vec<Opponent> {
+        let mut opponents: Vec<Opponent> = Vec::new();
+        for game in &self.games {
+            if !opponents.contains(&game.opponent) {
+                opponents.push(game.opponent);
+            }
+        }
+        opponents
+    }
+
+    /// Returns the distinct players of the stored games.
+    pub fn players(&self) -> Vec<String> {
+        let mut players: Vec<String> = Vec::new();
+        for player in self.games.iter().filter_map(|game| game.player.as_ref()) {
+            if !players.contains(player) {
+                players.push(player.clone());
+            }
+        }
+        players
+    }
+
+    /// Returns the number of stored games of the player with the given name with the given
+    /// result.
+    pub fn count(&self, player: &str, result: PlayerResult) -> usize {
+        self.games
+            .iter()
+            .filter(|game| game.player.as_deref() == Some(player) && game.result() == result)
+            .count()
+    }
+
+    /// Stores the given analysis with the game of the given index.
+    pub fn set_analysis(&mut self, index: usize, analysis: GameAnalysis) {
+        if let Some(game) = self.games.get_mut(index) {
+            game.analysis = Some(analysis);
+        }
+    }
+
+    /// Writes the database to [DATABASE_PATH], or to the SQLite database with the `sqlite`
+    /// feature.
+    pub fn save(&self) -> Result<(), String> {
+        #[cfg(feature = "sqlite")]
+        return sqlite::write(sqlite::SQLITE_PATH, &self.games);
+        #[cfg(not(feature = "sqlite"))]
+        ron::to_string(self)
+            .map_err(|error| error.to_string())
+            .and_then(|text| storage::write(DATABASE_PATH, &text))
+    }
+
+    /// Adds the given game, returning false if the same moves from the same position have
+    /// already been stored, as happens when a stored game is reopened and finished again.
+    fn add(&mut self, game: StoredGame) -> bool {
+        if self
+            .games
+            .iter()
+            .any(|stored| stored.start == game.start && stored.moves == game.moves)
+        {
+            return false;
+        }
+        self.games.push(game);
+        true
+    }
+}
+
+/// Returns the date of the given number of days since 1970.01.01 in the format of the PGN `Date`
+/// tag, using Howard Hinnant's `civil_from_days` algorithm.
+fn date_from_days(days: i64) -> String {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Returns the number of days from 1970.01.01 to today in UTC.
+pub fn days_today() -> i64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    (seconds / (24 * 60 * 60)) as i64
+}
+
+/// Returns today's date in UTC, in the format of the PGN `Date` tag.
+pub fn today() -> String {
+    date_from_days(days_today())
+}
+
+fn load_database(mut database: ResMut<GamesDatabase>) {
+    // An SQLite database without games is new, so the games are taken from DATABASE_PATH
+    #[cfg(feature = "sqlite")]
+    if let Some(games) = sqlite::read(sqlite::SQLITE_PATH)
+        .ok()
+        .filter(|games| !games.is_empty())
+    {
+        database.games = games;
+        return;
+    }
+    if let Some(stored) =
+        storage::read(DATABASE_PATH).and_then(|text| ron::from_str::<GamesDatabase>(&text).ok())
+    {
+        *database = stored;
+    }
+}
+
+/// The game which may have finished, with the clock and the engine it was played with.
+#[derive(SystemParam)]
+struct FinishedGame<'w> {
+    board: Res<'w, ChessBoard>,
+    history: Res<'w, PositionHistory>,
+    clock: Res<'w, ChessClock>,
+    engine: Res<'w, Engine>,
+}
+
+/// Stores the game for the player playing once it has finished and the history has caught up
+/// with its last move, rating the player if they played the engine at its own strength rather
+/// than an adaptive one.
+fn record_finished_game(
+    finished: FinishedGame,
+    mut database: ResMut<GamesDatabase>,
+    mut profiles: ResMut<Profiles>,
+    mut drill: ResMut<CurrentDrill>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let FinishedGame {
+        board,
+        history,
+        clock,
+        engine,
+    } = finished;
+    if !board.is_changed() && !history.is_changed() {
+        return;
+    }
+    if board.past_moves().last() != history.moves().last() {
+        return;
+    }
+    let player = profiles.active().name().to_string();
+    let Some(mut game) = StoredGame::new(&board, &history, &clock, &engine, &player, today())
+    else {
+        return;
+    };
+    // Whichever game finishes ends the drill, which it is only part of if it went on from there
+    game.drill = drill
+        .0
+        .take()
+        .filter(|(_, moves)| history.moves().starts_with(moves))
+        .map(|(link, _)| link);
+    let (result, opponent) = (game.result(), game.opponent);
+    let casual = game.drill.is_some() || engine.adaptive().enabled;
+    if database.add(game) {
+        if let (Opponent::Engine { depth }, false) = (opponent, casual) {
+            profiles.active_mut().rate(result, depth);
+            if let Err(error) = profiles.save() {
+                notifications.send(
+                    Notification::error("profile-save-failed")
+                        .with_arg("error", Argument::Text(error)),
+                );
+            }
+        }
+        // The game is kept until the app exits, and written again with the next game
+        if let Err(error) = database.save() {
+            notifications.send(
+                Notification::error("database-save-failed")
+                    .with_arg("error", Argument::Text(error)),
+            );
+        }
+    }
+}
+
+/// Reopens a stored game on an analysis board, where the engine does not play and the clock is
+/// off, with the history of the game so each position can be reviewed.
+fn review_game(
+    mut events: EventReader<ReviewGameEvent>,
+    database: Res<GamesDatabase>,
+    mut history: ResMut<PositionHistory>,
+    mut commands: ResMut<GameCommands>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+) {
+    let Some(event) = events.iter().last() else {
+        return;
+    };
+    let Some((start, moves)) = database.games.get(event.index).and_then(StoredGame::replay) else {
+        return;
+    };
+    engine.set_color(None);
+    clock.set_time_control(None);
+    history.set_game(&start, &moves);
+    commands.push(GameCommand::LoadGame(Box::new(
+        history.reset_event(history.len() - 1),
+    )));
+}
+
+/// Resets the board to the position to play the drill from, with the engine taking the side of
+/// the player who has just moved and the clock off.
+fn start_drill(
+    mut events: EventReader<DrillEvent>,
+    database: Res<GamesDatabase>,
+    history: Res<PositionHistory>,
+    mut drill: ResMut<CurrentDrill>,
+    mut commands: ResMut<GameCommands>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+) {
+    let Some(event) = events.iter().last() else {
+        return;
+    };
+    let (Some(ply), Some(start)) = (history.index_after(event.moves), history.start()) else {
+        return;
+    };
+    let link = DrillLink {
+        game: database.find(start, history.moves()),
+        ply,
+    };
+    engine.set_color(Some(history.positions()[ply].active_color().opposite()));
+    clock.set_time_control(None);
+    drill.0 = Some((link, history.moves()[..ply].to_vec()));
+    commands.push(GameCommand::LoadGame(Box::new(history.reset_event(ply))));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::ChessBoardPlugin;
+    use crate::history::HistoryPlugin;
+
+    use super::*;
+
+    fn stored_game(date: &str, opponent: Opponent, player_color: Option<PieceColor>) -> StoredGame {
+        StoredGame {
+            date: date.to_string(),
+            player: Some("Ada".to_string()),
+            opponent,
+            player_color,
+            start: Fen::default().to_string(),
+            moves: ["f3", "e5", "g4", "Qh4"].map(String::from).to_vec(),
+            opening: None,
+            status: GameEndStatus::Checkmate,
+            winner: Some(PieceColor::Black),
+            time_controls: None,
+            drill: None,
+            analysis: None,
+        }
+    }
+
+    #[test]
+    fn test_date_from_days() {
+        assert_eq!(date_from_days(0), "1970.01.01");
+        assert_eq!(date_from_days(19_782), "2024.02.29");
+        assert_eq!(date_from_days(-1), "1969.12.31");
+    }
+
+    #[test]
+    fn test_games_database() {
+        let mut database = GamesDatabase::default();
+        let engine = Opponent::Engine { depth: 3 };
+        assert!(database.add(stored_game("2024.02.29", engine, Some(PieceColor::Black))));
+        // The same game reopened and finished again
+        assert!(!database.add(stored_game("2024.03.01", engine, Some(PieceColor::Black))));
+        let mut human = stored_game("2024.03.02", Opponent::Human, None);
+        human.moves.pop();
+        human.status = GameEndStatus::Agreement;
+        human.winner = None;
+        assert!(database.add(human));
+        assert_eq!(database.opponents(), [engine, Opponent::Human]);
+        assert_eq!(database.players(), ["Ada"]);
+        assert_eq!(database.count("Ada", PlayerResult::Won), 1);
+        assert_eq!(database.count("Ada", PlayerResult::Drawn), 1);
+        assert_eq!(database.count("Bob", PlayerResult::Won), 0);
+
+        let matching = |filter: &GameFilter| -> Vec<usize> {
+            database.filtered(filter).map(|(index, _)| index).collect()
+        };
+        assert_eq!(matching(&GameFilter::default()), [1, 0]);
+        let won = GameFilter {
+            result: Some(PlayerResult::Won),
+            ..Default::default()
+        };
+        assert_eq!(matching(&won), [0]);
+        let march = GameFilter {
+            from: "2024.03".to_string(),
+            to: "2024.03".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(matching(&march), [1]);
+        let bob = GameFilter {
+            player: Some("Bob".to_string()),
+            ..Default::default()
+        };
+        assert!(matching(&bob).is_empty());
+        let black = GameFilter {
+            player_color: Some(PieceColor::Black),
+            opponent: Some(engine),
+            to: "2024.02.29".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(matching(&black), [0]);
+
+        let text = ron::to_string(&database).unwrap();
+        let read: GamesDatabase = ron::from_str(&text).unwrap();
+        assert_eq!(read.games, database.games);
+        let (start, moves) = read.games[0].replay().unwrap();
+        assert_eq!(start.to_string(), Fen::default().to_string());
+        assert_eq!(moves.len(), 4);
+        assert_eq!(read.games[0].fingerprint(), database.games[0].fingerprint());
+        assert_ne!(read.games[0].fingerprint(), read.games[1].fingerprint());
+        let end = read.games[0].final_position().unwrap().to_string();
+        assert!(end.starts_with("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w"));
+    }
+
+    #[test]
+    fn test_start_drill() {
+        let mut app = App::new();
+        app.add_plugins((ChessBoardPlugin::default(), HistoryPlugin))
+            .add_event::<DrillEvent>()
+            .init_resource::<GamesDatabase>()
+            .init_resource::<CurrentDrill>()
+            .init_resource::<Engine>()
+            .init_resource::<ChessClock>()
+            .add_systems(PreUpdate, start_drill);
+        app.update();
+        let game = stored_game("2024.03.09", Opponent::Human, None);
+        let (start, moves) = game.replay().unwrap();
+        app.world.resource_mut::<GamesDatabase>().add(game);
+        app.world
+            .resource_mut::<PositionHistory>()
+            .set_game(&start, &moves);
+
+        // Playing on after white's second move leaves black to the player and white to the engine
+        app.world.send_event(DrillEvent::new(3));
+        app.update();
+        assert_eq!(app.world.resource::<ChessBoard>().past_moves().len(), 3);
+        assert_eq!(
+            *app.world.resource::<Engine>().color(),
+            Some(PieceColor::White)
+        );
+        let (link, drilled) = app.world.resource::<CurrentDrill>().0.clone().unwrap();
+        assert_eq!(
+            link,
+            DrillLink {
+                game: Some(0),
+                ply: 3
+            }
+        );
+        assert_eq!(drilled, moves[..3]);
+    }
+}
@@ -0,0 +1,206 @@
+//! Storage of the [GamesDatabase](super::GamesDatabase) in SQLite, with the `sqlite` feature.
+//!
+//! Each game is a row of the `games` table, keyed by its index in the database. The details the
+//! database browser filters by each have a column, and the opening, drill link and analysis are
+//! stored as RON.
+
+use std::time::Duration;
+
+use rusqlite::{params, Connection, Row};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{Opponent, StoredGame};
+
+/// The file the games database is stored in.
+pub(super) const SQLITE_PATH: &str = "games.sqlite";
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS games (
+    id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL,
+    player TEXT,
+    engine_depth INTEGER,
+    player_color TEXT,
+    start TEXT NOT NULL,
+    moves TEXT NOT NULL,
+    opening TEXT,
+    status TEXT NOT NULL,
+    winner TEXT,
+    white_time_ms INTEGER,
+    black_time_ms INTEGER,
+    drill TEXT,
+    analysis TEXT
+)";
+
+/// Opens the database at the given path, creating the table of games if it has none.
+fn open(path: &str) -> Result<Connection, String> {
+    let connection = Connection::open(path).map_err(|error| error.to_string())?;
+    connection
+        .execute(CREATE_TABLE, [])
+        .map_err(|error| error.to_string())?;
+    Ok(connection)
+}
+
+fn to_ron<T: Serialize>(value: &T) -> Result<String, String> {
+    ron::to_string(value).map_err(|error| error.to_string())
+}
+
+fn from_ron<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    ron::from_str(text).map_err(|error| error.to_string())
+}
+
+/// Returns the stored game in the given row.
+fn game(row: &Row) -> Result<StoredGame, String> {
+    let text = |index: usize| -> Result<Option<String>, String> {
+        row.get(index).map_err(|error| error.to_string())
+    };
+    let engine_depth: Option<u32> = row.get(3).map_err(|error| error.to_string())?;
+    let time_ms: [Option<i64>; 2] = [
+        row.get(10).map_err(|error| error.to_string())?,
+        row.get(11).map_err(|error| error.to_string())?,
+    ];
+    Ok(StoredGame {
+        date: text(1)?.unwrap_or_default(),
+        player: text(2)?,
+        opponent: match engine_depth {
+            Some(depth) => Opponent::Engine { depth },
+            None => Opponent::Human,
+        },
+        player_color: text(4)?.map(|text| from_ron(&text)).transpose()?,
+        start: text(5)?.unwrap_or_default(),
+        moves: text(6)?
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(String::from)
+            .collect(),
+        opening: text(7)?.map(|text| from_ron(&text)).transpose()?,
+        status: from_ron(&text(8)?.unwrap_or_default())?,
+        winner: text(9)?.map(|text| from_ron(&text)).transpose()?,
+        time_controls: match time_ms {
+            [Some(white), Some(black)] => {
+                Some([white, black].map(|milliseconds| Duration::from_millis(milliseconds as u64)))
+            }
+            _ => None,
+        },
+        drill: text(12)?.map(|text| from_ron(&text)).transpose()?,
+        analysis: text(13)?.map(|text| from_ron(&text)).transpose()?,
+    })
+}
+
+/// Reads the games stored in the database at the given path, oldest first.
+pub(super) fn read(path: &str) -> Result<Vec<StoredGame>, String> {
+    let connection = open(path)?;
+    let mut statement = connection
+        .prepare("SELECT * FROM games ORDER BY id")
+        .map_err(|error| error.to_string())?;
+    let mut rows = statement.query([]).map_err(|error| error.to_string())?;
+    let mut games = Vec::new();
+    while let Some(row) = rows.next().map_err(|error| error.to_string())? {
+        games.push(game(row)?);
+    }
+    Ok(games)
+}
+
+/// Writes the given games to the database at the given path in one transaction, replacing the
+/// rows of games already stored so their analysis is kept up to date.
+pub(super) fn write(path: &str, games: &[StoredGame]) -> Result<(), String> {
+    let mut connection = open(path)?;
+    let transaction = connection
+        .transaction()
+        .map_err(|error| error.to_string())?;
+    {
+        let mut statement = transaction
+            .prepare(
+                "INSERT OR REPLACE INTO games VALUES \
+                 (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            )
+            .map_err(|error| error.to_string())?;
+        for (index, game) in games.iter().enumerate() {
+            let engine_depth = match game.opponent {
+                Opponent::Engine { depth } => Some(depth),
+                Opponent::Human => None,
+            };
+            let time_ms = game
+                .time_controls
+                .map(|times| times.map(|time| time.as_millis() as i64));
+            statement
+                .execute(params![
+                    index as i64,
+                    game.date,
+                    game.player,
+                    engine_depth,
+                    game.player_color.as_ref().map(to_ron).transpose()?,
+                    game.start,
+                    game.moves.join(" "),
+                    game.opening.as_ref().map(to_ron).transpose()?,
+                    to_ron(&game.status)?,
+                    game.winner.as_ref().map(to_ron).transpose()?,
+                    time_ms.map(|[white, _]| white),
+                    time_ms.map(|[_, black]| black),
+                    game.drill.as_ref().map(to_ron).transpose()?,
+                    game.analysis.as_ref().map(to_ron).transpose()?,
+                ])
+                .map_err(|error| error.to_string())?;
+        }
+    }
+    transaction.commit().map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::{GameEndStatus, PieceColor};
+    use crate::fen::Fen;
+
+    use super::super::{DrillLink, GameAnalysis};
+    use super::*;
+
+    #[test]
+    fn test_read_write() {
+        let path = std::env::temp_dir().join(format!("chess_games_{}.sqlite", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::remove_file(path).ok();
+        let engine_game = StoredGame {
+            date: "2024.02.29".to_string(),
+            player: Some("Ada".to_string()),
+            opponent: Opponent::Engine { depth: 3 },
+            player_color: Some(PieceColor::Black),
+            start: Fen::default().to_string(),
+            moves: ["f3", "e5", "g4", "Qh4"].map(String::from).to_vec(),
+            opening: Some(from_ron("(code: \"A00\", name: \"Barnes Opening\")").unwrap()),
+            status: GameEndStatus::Checkmate,
+            winner: Some(PieceColor::Black),
+            time_controls: Some([Duration::from_secs(300), Duration::from_secs(180)]),
+            drill: None,
+            analysis: None,
+        };
+        let drill = StoredGame {
+            date: "2024.03.01".to_string(),
+            player: None,
+            opponent: Opponent::Human,
+            player_color: None,
+            moves: ["f3", "e5"].map(String::from).to_vec(),
+            opening: None,
+            status: GameEndStatus::Agreement,
+            winner: None,
+            time_controls: None,
+            drill: Some(DrillLink {
+                game: Some(0),
+                ply: 2,
+            }),
+            ..engine_game.clone()
+        };
+        let mut games = vec![engine_game, drill];
+        write(path, &games).unwrap();
+        assert_eq!(read(path).unwrap(), games);
+
+        // Writing again replaces the rows of the games already stored
+        games[0].analysis = Some(GameAnalysis {
+            depth: 2,
+            evaluations: vec![0, -20, -40, -90, -30_000],
+            mistakes: [None, None],
+        });
+        write(path, &games).unwrap();
+        assert_eq!(read(path).unwrap(), games);
+        std::fs::remove_file(path).unwrap();
+    }
+}
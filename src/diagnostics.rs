@@ -0,0 +1,66 @@
+//! Timings of the chess systems, recorded as Bevy [diagnostics](bevy::diagnostic) alongside its
+//! frame time, so a report of slowness can be pinned on the generation of the legal moves, the
+//! highlighting of the squares or the exchanges with the engine.
+//!
+//! Each plugin registers the timings of its own systems, which record a measurement in
+//! milliseconds each time they do their work rather than every frame, so the averages are of the
+//! work itself.
+
+use std::time::Instant;
+
+use bevy::app::App;
+use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics, RegisterDiagnostic};
+
+/// Generating the legal moves of the position whenever the board changes.
+pub const MOVE_GENERATION: DiagnosticId =
+    DiagnosticId::from_u128(0x5a1e_7d1c_0b4f_4e8a_9c3d_2f6b_8e01_a7c4);
+/// Coloring the squares with their highlights.
+pub const HIGHLIGHTING: DiagnosticId =
+    DiagnosticId::from_u128(0x0c93_48e2_61d7_4b5f_a2e8_7f14_c9b3_5d60);
+/// Starting the engine's searches, collecting their results and reading the external engine.
+pub const ENGINE: DiagnosticId = DiagnosticId::from_u128(0x8f27_b6d0_3e4c_4a19_b751_0d9e_62fa_3c88);
+
+/// The number of measurements the averages are taken over.
+const HISTORY: usize = 120;
+
+/// Registers the timing with the given ID and name, in milliseconds.
+pub fn register(app: &mut App, id: DiagnosticId, name: &'static str) {
+    app.register_diagnostic(Diagnostic::new(id, name, HISTORY).with_suffix("ms"));
+}
+
+/// Runs the given work, recording how long it took against the timing with the given ID.
+pub fn measure<T>(diagnostics: &mut Diagnostics, id: DiagnosticId, work: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = work();
+    let elapsed = start.elapsed();
+    diagnostics.add_measurement(id, || elapsed.as_secs_f64() * 1000.);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::diagnostic::DiagnosticsStore;
+    use bevy::prelude::Update;
+
+    use super::*;
+
+    #[test]
+    fn test_measure() {
+        let mut app = App::new();
+        register(&mut app, ENGINE, "engine");
+        app.add_systems(Update, |mut diagnostics: Diagnostics| {
+            let value = measure(&mut diagnostics, ENGINE, || {
+                std::thread::sleep(Duration::from_millis(2));
+                7
+            });
+            assert_eq!(value, 7);
+        });
+        app.update();
+        let store = app.world.resource::<DiagnosticsStore>();
+        let diagnostic = store.get(ENGINE).unwrap();
+        assert_eq!(diagnostic.history_len(), 1);
+        assert!(diagnostic.value().unwrap() >= 2.);
+    }
+}
@@ -0,0 +1,292 @@
+//! Shows the game being played as Discord rich presence, enabled with the `discord` feature, e.g.
+//! "Playing as White — move 24" and "vs Engine level 5" with the time since the game started.
+//!
+//! The presence is set over Discord's local IPC socket, with the application in the
+//! `DISCORD_CLIENT_ID` environment variable, and is updated as moves are made and games are
+//! loaded. It can be turned off in the settings, which clears it. If Discord is not running, or
+//! the connection is lost, the next update tries to connect again.
+
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::app::{App, Plugin};
+use bevy::log::{info, info_span, warn};
+use bevy::prelude::{DetectChanges, EventReader, Res, ResMut, Resource, Update};
+use serde_json::{json, Value};
+
+use crate::chess_board::{ChessBoard, PieceColor, ResetBoardEvent};
+use crate::engine::Engine;
+use crate::network::NetworkGame;
+
+/// The opcode of the handshake frame.
+const HANDSHAKE: u32 = 0;
+
+/// The opcode of a command frame.
+const FRAME: u32 = 1;
+
+/// The number of IPC sockets Discord may listen on, one for each running client.
+const SOCKETS: usize = 10;
+
+pub(super) struct DiscordPlugin;
+
+impl Plugin for DiscordPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        let client_id = std::env::var("DISCORD_CLIENT_ID")
+            .expect("The DISCORD_CLIENT_ID environment variable must be set.");
+        let (activity_sender, activity_receiver) = channel();
+        thread::spawn(move || set_activities(&client_id, &activity_receiver));
+
+        app.insert_resource(RichPresence::new(activity_sender))
+            .add_systems(Update, update_presence);
+    }
+}
+
+/// What the presence shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Activity {
+    /// The first line, the side played and the move reached.
+    details: String,
+    /// The second line, the opponent.
+    state: String,
+    /// When the game started, in seconds since the Unix epoch.
+    start: u64,
+}
+
+impl Activity {
+    /// Returns the activity of the game on the given board, played against the given engine or
+    /// network game, which started at the given time.
+    fn new(board: &ChessBoard, engine: &Engine, network: Option<&NetworkGame>, start: u64) -> Self {
+        let color_name = |color: PieceColor| match color {
+            PieceColor::White => "White",
+            PieceColor::Black => "Black",
+        };
+        let move_number = board.to_fen().fullmove_number().to_owned();
+        let (player, state) = match (network, engine.color()) {
+            (Some(network), _) => (*network.color(), "vs an online opponent".to_string()),
+            (None, Some(color)) => (
+                Some(color.opposite()),
+                format!("vs Engine level {}", engine.depth()),
+            ),
+            (None, None) => (None, "Over the board".to_string()),
+        };
+        let details = match (board.game_end_status(), player) {
+            (Some(_), _) => format!("Game over — move {}", move_number),
+            (None, Some(color)) => {
+                format!("Playing as {} — move {}", color_name(color), move_number)
+            }
+            (None, None) if network.is_some() => format!("Watching — move {}", move_number),
+            (None, None) => format!("Playing both sides — move {}", move_number),
+        };
+        Activity {
+            details,
+            state,
+            start,
+        }
+    }
+
+    /// Returns the SET_ACTIVITY command setting this activity, or clearing it if there is none.
+    fn command(activity: Option<&Activity>, nonce: usize) -> Value {
+        let mut args = json!({ "pid": std::process::id() });
+        if let Some(activity) = activity {
+            args["activity"] = json!({
+                "details": activity.details,
+                "state": activity.state,
+                "timestamps": { "start": activity.start },
+            });
+        }
+        json!({ "cmd": "SET_ACTIVITY", "args": args, "nonce": nonce.to_string() })
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct RichPresence {
+    /// Whether the presence is shown.
+    pub enabled: bool,
+    /// When the game on the board started.
+    started: SystemTime,
+    /// The activity last sent, None once it has been cleared.
+    sent: Option<Option<Activity>>,
+    activities: Sender<Option<Activity>>,
+}
+
+impl RichPresence {
+    fn new(activities: Sender<Option<Activity>>) -> Self {
+        RichPresence {
+            enabled: true,
+            started: SystemTime::now(),
+            sent: None,
+            activities,
+        }
+    }
+
+    /// Sends the given activity to be shown, unless it is already.
+    fn show(&mut self, activity: Option<Activity>) {
+        if self.sent.as_ref() != Some(&activity) {
+            self.activities.send(activity.clone()).ok();
+            self.sent = Some(activity);
+        }
+    }
+}
+
+/// A connection to Discord's IPC socket.
+trait Pipe: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Pipe for T {}
+
+/// Connects to the first IPC socket Discord is listening on.
+#[cfg(unix)]
+fn connect() -> io::Result<Box<dyn Pipe>> {
+    let directory = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .unwrap_or_else(|| "/tmp".to_string());
+    let mut error = io::Error::from(io::ErrorKind::NotFound);
+    for socket in 0..SOCKETS {
+        let path = format!("{}/discord-ipc-{}", directory.trim_end_matches('/'), socket);
+        match std::os::unix::net::UnixStream::connect(path) {
+            Ok(stream) => return Ok(Box::new(stream)),
+            Err(failure) => error = failure,
+        }
+    }
+    Err(error)
+}
+
+/// Connects to the first named pipe Discord is listening on.
+#[cfg(not(unix))]
+fn connect() -> io::Result<Box<dyn Pipe>> {
+    let mut error = io::Error::from(io::ErrorKind::NotFound);
+    for socket in 0..SOCKETS {
+        let path = format!(r"\\?\pipe\discord-ipc-{}", socket);
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+        {
+            Ok(pipe) => return Ok(Box::new(pipe)),
+            Err(failure) => error = failure,
+        }
+    }
+    Err(error)
+}
+
+/// Encodes a frame of the given opcode and payload: the opcode and the payload's length, each as
+/// a little-endian 32-bit integer, followed by the payload.
+fn encode_frame(opcode: u32, payload: &Value) -> Vec<u8> {
+    let payload = payload.to_string();
+    let mut frame = Vec::with_capacity(payload.len() + 8);
+    frame.extend(opcode.to_le_bytes());
+    frame.extend((payload.len() as u32).to_le_bytes());
+    frame.extend(payload.as_bytes());
+    frame
+}
+
+/// Sends a frame and reads Discord's reply to it.
+fn exchange(pipe: &mut dyn Pipe, opcode: u32, payload: &Value) -> io::Result<Value> {
+    pipe.write_all(&encode_frame(opcode, payload))?;
+    let mut header = [0; 8];
+    pipe.read_exact(&mut header)?;
+    let length = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let mut reply = vec![0; length as usize];
+    pipe.read_exact(&mut reply)?;
+    serde_json::from_slice(&reply).map_err(io::Error::from)
+}
+
+/// Connects to Discord and says which application the presence is for.
+fn handshake(client_id: &str) -> io::Result<Box<dyn Pipe>> {
+    let mut pipe = connect()?;
+    exchange(
+        pipe.as_mut(),
+        HANDSHAKE,
+        &json!({ "v": 1, "client_id": client_id }),
+    )?;
+    Ok(pipe)
+}
+
+/// Sets each activity received as the presence of the given application, until the app closes.
+fn set_activities(client_id: &str, activities: &Receiver<Option<Activity>>) {
+    let _span = info_span!("discord").entered();
+    let mut pipe = None;
+    for (nonce, activity) in activities.iter().enumerate() {
+        if pipe.is_none() {
+            match handshake(client_id) {
+                Ok(connected) => {
+                    info!("Connected");
+                    pipe = Some(connected);
+                }
+                Err(error) => {
+                    warn!(%error, "Unable to connect");
+                    continue;
+                }
+            }
+        }
+        let command = Activity::command(activity.as_ref(), nonce);
+        if let Some(connected) = &mut pipe {
+            if let Err(error) = exchange(connected.as_mut(), FRAME, &command) {
+                warn!(%error, "Connection lost");
+                pipe = None;
+            }
+        }
+    }
+}
+
+fn update_presence(
+    mut presence: ResMut<RichPresence>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+    network: Option<Res<NetworkGame>>,
+    mut reset_events: EventReader<ResetBoardEvent>,
+) {
+    if reset_events.iter().count() > 0 {
+        presence.started = SystemTime::now();
+    }
+    if !presence.enabled {
+        presence.show(None);
+        return;
+    }
+    if !board.is_changed() && !engine.is_changed() && presence.sent.is_some() {
+        return;
+    }
+    let start = presence
+        .started
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let activity = Activity::new(&board, &engine, network.as_deref(), start);
+    presence.show(Some(activity));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_activity() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        for text in ["e4", "e5", "Nf3"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let mut engine = Engine::default();
+        engine.set_color(Some(PieceColor::Black));
+        engine.set_depth(5);
+        let activity = Activity::new(&board, &engine, None, 60);
+        assert_eq!(activity.details, "Playing as White — move 2");
+        assert_eq!(activity.state, "vs Engine level 5");
+
+        let network = NetworkGame::new("localhost:8080", None, false);
+        let activity = Activity::new(&board, &engine, Some(&network), 60);
+        assert_eq!(activity.details, "Watching — move 2");
+
+        let command = Activity::command(None, 3);
+        assert_eq!(command["cmd"], "SET_ACTIVITY");
+        assert!(command["args"].get("activity").is_none());
+        let frame = encode_frame(HANDSHAKE, &json!({ "v": 1 }));
+        assert_eq!(&frame[..8], [0, 0, 0, 0, 7, 0, 0, 0]);
+        assert_eq!(&frame[8..], br#"{"v":1}"#);
+    }
+}
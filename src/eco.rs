@@ -0,0 +1,146 @@
+//! Classifies games by their opening, with the codes of the
+//! [Encyclopaedia of Chess Openings (ECO)](https://en.wikipedia.org/wiki/Encyclopaedia_of_Chess_Openings).
+//!
+//! The openings are embedded from `assets/eco.tsv`, which has the `eco`, `name` and `pgn` columns
+//! of the [lichess opening tables](https://github.com/lichess-org/chess-openings). Each opening is
+//! recognised by the position it reaches rather than by its moves, so openings reached by a
+//! transposition are classified too, and a game is classified by the last position it reached
+//! which is in the tables.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::ChessBoard;
+use crate::fen::Fen;
+
+/// The embedded opening tables.
+const ECO_TABLES: &str = include_str!("../assets/eco.tsv");
+
+/// An opening with its ECO code, e.g. "B20" for the Sicilian Defense.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Opening {
+    code: String,
+    name: String,
+}
+
+impl Opening {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.code, self.name)
+    }
+}
+
+/// Returns the fields of the given position which identify it for classification: the piece
+/// placement, the active color and the castling rights.
+fn position_key(fen: &Fen) -> String {
+    let fen = fen.to_string();
+    fen.split_whitespace().take(3).collect::<Vec<_>>().join(" ")
+}
+
+/// Reads a line of the opening tables, returning the position the opening reaches and the
+/// opening, or None if the line is not an opening with legal moves.
+fn parse_line(line: &str) -> Option<(String, Opening)> {
+    let mut columns = line.split('\t');
+    let (code, name, pgn) = (columns.next()?, columns.next()?, columns.next()?);
+    let mut board = ChessBoard::with_position(&Fen::default());
+    for token in pgn.split_whitespace() {
+        // Skip move numbers such as "1."
+        if token.ends_with('.') {
+            continue;
+        }
+        let piece_move = Move::from_algebraic(token, &board)?;
+        board.apply_move(&piece_move);
+    }
+    let opening = Opening {
+        code: code.to_string(),
+        name: name.to_string(),
+    };
+    Some((position_key(&board.to_fen()), opening))
+}
+
+/// Reads the given opening tables, skipping their header. Where several openings reach the same
+/// position, the first is kept.
+fn parse_tables(text: &str) -> HashMap<String, Opening> {
+    let mut openings = HashMap::new();
+    for (position, opening) in text.lines().skip(1).filter_map(parse_line) {
+        openings.entry(position).or_insert(opening);
+    }
+    openings
+}
+
+/// Returns the openings by the position they reach, read from the embedded tables the first time
+/// they are needed.
+fn openings() -> &'static HashMap<String, Opening> {
+    static OPENINGS: OnceLock<HashMap<String, Opening>> = OnceLock::new();
+    OPENINGS.get_or_init(|| parse_tables(ECO_TABLES))
+}
+
+/// Returns the opening of a game with the given positions, in the order they were reached, or
+/// None if none of them is in the tables.
+pub fn classify<'a>(
+    positions: impl DoubleEndedIterator<Item = &'a Fen>,
+) -> Option<&'static Opening> {
+    let openings = openings();
+    positions
+        .rev()
+        .find_map(|position| openings.get(&position_key(position)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the positions reached by the given moves from the standard starting position.
+    fn positions(moves: &[&str]) -> Vec<Fen> {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut positions = vec![board.to_fen()];
+        for text in moves {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            positions.push(board.to_fen());
+        }
+        positions
+    }
+
+    #[test]
+    fn test_eco_tables() {
+        // Every line of the embedded tables is an opening with legal moves
+        let lines = ECO_TABLES.lines().skip(1);
+        assert_eq!(lines.clone().filter_map(parse_line).count(), lines.count());
+        assert!(classify(positions(&[]).iter()).is_none());
+    }
+
+    #[test]
+    fn test_classify() {
+        let sicilian = positions(&["e4", "c5", "Nf3", "d6", "d4", "cxd4", "Nxd4", "Nf6", "Nc3"]);
+        let opening = classify(sicilian.iter()).unwrap();
+        assert_eq!(opening.code(), "B54");
+        assert_eq!(
+            opening.to_string(),
+            "B54 Sicilian Defense: Modern Variations"
+        );
+
+        // The Queen's Gambit Declined reached from the English Opening
+        let transposition = positions(&["c4", "e6", "d4", "d5"]);
+        assert_eq!(
+            classify(transposition.iter()).unwrap().name(),
+            "Queen's Gambit Declined"
+        );
+
+        // Moves outside the tables keep the last opening reached
+        let french = positions(&["e4", "e6", "d4", "d5", "e5", "c5", "c3"]);
+        assert_eq!(classify(french.iter()).unwrap().code(), "C02");
+    }
+}
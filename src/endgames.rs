@@ -0,0 +1,131 @@
+//! The theoretical endgames of the endgame trainer, each a standard position the player must win,
+//! or in the Philidor position hold, against the engine searching deeply in place of a tablebase.
+//!
+//! The player moves first, with the side the position is set up for. A win must be converted
+//! within the fifty-move rule: once fifty moves each have passed without a capture or a pawn move
+//! the attempt has failed, and a draw held that long has succeeded.
+
+use strum_macros::EnumIter;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameEndStatus, PieceColor, PieceType};
+use crate::fen::Fen;
+
+/// The number of moves of both players without a capture or a pawn move which draws the game.
+pub const FIFTY_MOVE_PLIES: usize = 100;
+
+/// The depth the engine searches to as the opponent, deep enough to find the best defence of
+/// these endings without a tablebase.
+pub const OPPONENT_DEPTH: u32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum Endgame {
+    /// King and queen against a lone king.
+    KingQueen,
+    /// King and rook against a lone king.
+    KingRook,
+    /// King and pawn against king, with the king in front of its pawn on the sixth rank.
+    KingPawn,
+    /// The Lucena position, won by building a bridge with the rook.
+    Lucena,
+    /// The Philidor position, held by keeping the rook on the sixth rank until the pawn advances.
+    Philidor,
+}
+
+/// What the player must do in an endgame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    Win,
+    Draw,
+}
+
+impl Endgame {
+    /// Returns the starting position, with the player to move.
+    pub fn fen(&self) -> Fen {
+        Fen::parse(match self {
+            Endgame::KingQueen => "8/8/8/3k4/8/8/8/4K2Q w - - 0 1",
+            Endgame::KingRook => "8/8/8/3k4/8/8/8/4K2R w - - 0 1",
+            Endgame::KingPawn => "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1",
+            Endgame::Lucena => "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1",
+            Endgame::Philidor => "4k3/7R/1r6/3KP3/8/8/8/8 b - - 0 1",
+        })
+        .expect("Invalid endgame position.")
+    }
+
+    pub fn goal(&self) -> Goal {
+        match self {
+            Endgame::Philidor => Goal::Draw,
+            _ => Goal::Win,
+        }
+    }
+
+    /// Returns the side the player plays.
+    pub fn player(&self) -> PieceColor {
+        *self.fen().active_color()
+    }
+
+    /// Returns whether the player has met the goal at the given board, or None while the attempt
+    /// goes on.
+    pub fn result(&self, board: &ChessBoard) -> Option<bool> {
+        let player = self.player();
+        if let Some(status) = board.game_end_status() {
+            let won = *board.winner() == Some(player);
+            let drawn = board.winner().is_none() && *status != GameEndStatus::Resignation;
+            return Some(match self.goal() {
+                Goal::Win => won,
+                Goal::Draw => won || drawn,
+            });
+        }
+        (halfmove_clock(board.past_moves()) >= FIFTY_MOVE_PLIES)
+            .then_some(self.goal() == Goal::Draw)
+    }
+}
+
+/// Returns the number of plies since the last capture or pawn move of the given moves.
+pub fn halfmove_clock(moves: &[Move]) -> usize {
+    moves
+        .iter()
+        .rev()
+        .take_while(|piece_move| {
+            !piece_move.is_capture() && *piece_move.piece_type() != PieceType::Pawn
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    #[test]
+    fn test_endgames() {
+        for endgame in Endgame::iter() {
+            let board = ChessBoard::with_position(&endgame.fen());
+            assert!(!board.legal_moves_iter().collect::<Vec<_>>().is_empty());
+            assert_eq!(endgame.result(&board), None);
+        }
+        assert_eq!(Endgame::Lucena.player(), PieceColor::White);
+        assert_eq!(Endgame::Philidor.player(), PieceColor::Black);
+
+        // Shuffling the rook fifty moves each fails to win, but holds the Philidor position
+        let shuffle = |fen: &Fen, squares: [&str; 4]| {
+            let mut board = ChessBoard::with_position(fen);
+            for text in squares.iter().cycle().take(FIFTY_MOVE_PLIES) {
+                board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            }
+            board
+        };
+        let board = shuffle(&Endgame::KingRook.fen(), ["Rh2", "Kd6", "Rh1", "Kd5"]);
+        assert_eq!(halfmove_clock(board.past_moves()), FIFTY_MOVE_PLIES);
+        assert_eq!(Endgame::KingRook.result(&board), Some(false));
+        let board = shuffle(&Endgame::Philidor.fen(), ["Rb5+", "Kd6", "Rb6+", "Kd5"]);
+        assert_eq!(Endgame::Philidor.result(&board), Some(true));
+
+        let mut board = ChessBoard::with_position(&Endgame::KingQueen.fen());
+        board.end_game(GameEndStatus::Checkmate, Some(PieceColor::White));
+        assert_eq!(Endgame::KingQueen.result(&board), Some(true));
+        board.end_game(GameEndStatus::Stalemate, None);
+        assert_eq!(Endgame::KingQueen.result(&board), Some(false));
+    }
+}
@@ -0,0 +1,746 @@
+//! Contains the [Engine], a computer opponent which can play either color.
+//!
+//! The engine searches on a separate thread so the app stays responsive, or in a
+//! [web worker](worker) in the web build, and a search can be
+//! cancelled, in which case it plays the best move found so far at once. After each search it
+//! decides, following its [EngineConduct], whether to resign or offer a draw, and it responds to
+//! draws offered by its opponent. With [AdaptiveStrength] on, it also searches each move more or
+//! less deeply than its own depth to keep the game close.
+
+use std::sync::mpsc::{channel, Receiver, Sender};
+#[cfg(feature = "nnue")]
+use std::sync::Arc;
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::diagnostic::Diagnostics;
+use bevy::log::{info, info_span, warn};
+use bevy::prelude::{EventReader, Res, ResMut, Resource, Startup, Update};
+use bevy::time::Time;
+
+use crate::book::{Book, BOOK_PATH};
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{
+    ChessBoard, GameCommand, GameCommands, PieceColor, ResetBoardEvent, Variant,
+};
+use crate::diagnostics::{self, ENGINE};
+
+pub use self::adaptive::AdaptiveStrength;
+use self::conduct::Decision;
+pub use self::conduct::EngineConduct;
+#[cfg(feature = "nnue")]
+use self::nnue::{Network, NETWORK_PATH};
+#[cfg(feature = "nnue")]
+use self::search::search_with_network;
+use self::search::{search, search_with_limits, SearchCancel, SearchResult};
+pub use self::search::{SearchLimits, SearchOptions, MATE_SCORE};
+pub use self::uci::{ExternalEngine, UciOption, UciOptionKind};
+pub use self::weights::{EvalWeights, WEIGHTS_PATH};
+
+mod adaptive;
+mod conduct;
+#[cfg(feature = "nnue")]
+mod nnue;
+mod search;
+mod uci;
+mod weights;
+#[cfg(any(target_arch = "wasm32", test))]
+mod worker;
+
+/// The default depth the engine searches to, in plies.
+const DEFAULT_DEPTH: u32 = 2;
+
+/// The default minimum time the engine takes to reply.
+const DEFAULT_MIN_THINK_TIME: Duration = Duration::from_millis(500);
+
+/// The default depth hints and analysis search to, in plies.
+const DEFAULT_HINT_DEPTH: u32 = 4;
+
+/// The default longest time hints and analysis search for.
+const DEFAULT_HINT_TIME: Duration = Duration::from_secs(1);
+
+pub(super) struct EnginePlugin;
+
+impl Plugin for EnginePlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        use bevy::prelude::IntoSystemConfigs;
+
+        diagnostics::register(app, ENGINE, "engine");
+        app.init_resource::<Engine>()
+            .add_systems(Startup, (load_book, load_weights))
+            .add_systems(
+                Update,
+                (
+                    reset_engine,
+                    poll_external_engine,
+                    start_search,
+                    finish_search,
+                    respond_to_draw_offers,
+                    respond_to_takebacks,
+                )
+                    .chain(),
+            );
+        #[cfg(feature = "nnue")]
+        app.add_systems(Startup, load_network);
+        #[cfg(target_arch = "wasm32")]
+        app.add_systems(bevy::prelude::PreUpdate, worker::poll_searches);
+    }
+}
+
+/// A search running on another thread.
+#[derive(Debug)]
+struct PendingSearch {
+    /// The FEN of the position being searched, used to discard results for stale positions.
+    fen: String,
+    receiver: Mutex<Receiver<SearchResult>>,
+    /// The time the search has been running for.
+    elapsed: Duration,
+    /// The result, which is held back until the minimum thinking time has passed.
+    result: Option<SearchResult>,
+    /// Whether the move was taken from the opening book, in which case it has no evaluation.
+    from_book: bool,
+    /// The token cancelling the search, after which the result is no longer held back.
+    cancel: SearchCancel,
+}
+
+/// A search started with [Engine::search_in_background].
+#[derive(Debug)]
+pub struct BackgroundSearch(Mutex<Receiver<SearchResult>>, SearchCancel);
+
+impl BackgroundSearch {
+    /// Returns the principal line, which starts with the best move and is empty if there are no
+    /// legal moves, and the evaluation in centipawns from the point of view of the player to move
+    /// once the search has finished.
+    pub fn try_result(&self) -> Option<(Vec<Move>, i32)> {
+        let result = self.0.lock().unwrap().try_recv().ok()?;
+        Some((result.line, result.evaluation))
+    }
+
+    /// Cancels the search, which then finishes with the best line found so far.
+    pub fn cancel(&self) {
+        self.1.cancel();
+    }
+}
+
+/// The computer opponent.
+#[derive(Resource, Debug)]
+pub struct Engine {
+    /// The color the engine plays, or None if it is not playing.
+    color: Option<PieceColor>,
+    /// The depth the engine searches to, in plies.
+    depth: u32,
+    /// The minimum time the engine takes to reply, so it does not move instantly at low depths.
+    min_think_time: Duration,
+    /// The depth hints and analysis search to, in plies, which is separate from the engine's own
+    /// so a weak opponent can still give strong hints.
+    hint_depth: u32,
+    /// The limits on how long hints and analysis search for.
+    hint_limits: SearchLimits,
+    /// How the engine resigns and offers draws.
+    conduct: EngineConduct,
+    adaptive: AdaptiveStrength,
+    /// The depth the engine's next move is searched to in place of its own depth, if it is
+    /// overridden.
+    move_depth: Option<u32>,
+    /// The heuristics the engine searches with.
+    search_options: SearchOptions,
+    /// The weights the engine evaluates positions with.
+    weights: EvalWeights,
+    /// The network the engine can evaluate positions with instead of the weights.
+    #[cfg(feature = "nnue")]
+    network: Option<Arc<Network>>,
+    /// Whether the engine evaluates with the network when there is one.
+    #[cfg(feature = "nnue")]
+    use_network: bool,
+    /// The evaluation after each of the engine's searches this game, from its point of view.
+    evaluations: Vec<i32>,
+    /// The number of evaluations made when the engine last offered a draw.
+    draw_offered_at: Option<usize>,
+    search: Option<PendingSearch>,
+    /// The opening book the engine plays from while the position is in it.
+    book: Option<Book>,
+    /// The external engine which plays standard chess in place of the search, if one is attached.
+    external: Option<ExternalEngine>,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine {
+            color: None,
+            depth: DEFAULT_DEPTH,
+            min_think_time: DEFAULT_MIN_THINK_TIME,
+            hint_depth: DEFAULT_HINT_DEPTH,
+            hint_limits: SearchLimits {
+                nodes: None,
+                time: Some(DEFAULT_HINT_TIME),
+            },
+            conduct: EngineConduct::default(),
+            adaptive: AdaptiveStrength::default(),
+            move_depth: None,
+            search_options: SearchOptions::default(),
+            weights: EvalWeights::default(),
+            #[cfg(feature = "nnue")]
+            network: None,
+            #[cfg(feature = "nnue")]
+            use_network: true,
+            evaluations: Vec::new(),
+            draw_offered_at: None,
+            search: None,
+            book: None,
+            external: None,
+        }
+    }
+}
+
+impl Engine {
+    pub fn color(&self) -> &Option<PieceColor> {
+        &self.color
+    }
+
+    pub fn set_color(&mut self, color: Option<PieceColor>) {
+        self.color = color;
+    }
+
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Sets the search depth in plies, which is at least one.
+    pub fn set_depth(&mut self, depth: u32) {
+        self.depth = depth.max(1);
+    }
+
+    pub fn min_think_time(&self) -> Duration {
+        self.min_think_time
+    }
+
+    pub fn set_min_think_time(&mut self, min_think_time: Duration) {
+        self.min_think_time = min_think_time;
+    }
+
+    pub fn hint_depth(&self) -> u32 {
+        self.hint_depth
+    }
+
+    /// Sets the depth hints and analysis search to in plies, which is at least one.
+    pub fn set_hint_depth(&mut self, depth: u32) {
+        self.hint_depth = depth.max(1);
+    }
+
+    pub fn hint_limits_mut(&mut self) -> &mut SearchLimits {
+        &mut self.hint_limits
+    }
+
+    pub fn conduct_mut(&mut self) -> &mut EngineConduct {
+        &mut self.conduct
+    }
+
+    /// Returns the evaluation after each of the engine's searches this game, from its point of
+    /// view.
+    pub fn evaluations(&self) -> &[i32] {
+        &self.evaluations
+    }
+
+    pub fn adaptive(&self) -> &AdaptiveStrength {
+        &self.adaptive
+    }
+
+    pub fn adaptive_mut(&mut self) -> &mut AdaptiveStrength {
+        &mut self.adaptive
+    }
+
+    /// Returns the depth the engine's next move is searched to.
+    pub fn move_depth(&self) -> u32 {
+        self.move_depth.unwrap_or(self.depth)
+    }
+
+    /// Overrides the depth the engine's moves are searched to until the next game, or searches
+    /// them to its own depth again.
+    pub fn set_move_depth(&mut self, depth: Option<u32>) {
+        self.move_depth = depth.map(|depth| depth.max(1));
+    }
+
+    /// Returns whether a network was loaded from [NETWORK_PATH].
+    #[cfg(feature = "nnue")]
+    pub fn has_network(&self) -> bool {
+        self.network.is_some()
+    }
+
+    #[cfg(feature = "nnue")]
+    pub fn use_network(&self) -> bool {
+        self.use_network
+    }
+
+    #[cfg(feature = "nnue")]
+    pub fn set_use_network(&mut self, use_network: bool) {
+        self.use_network = use_network;
+    }
+
+    /// Returns the move the engine suggests for the player to move, searching within the hint
+    /// depth and limits on the calling thread, or None if there are no legal moves.
+    pub fn hint(&self, board: &ChessBoard) -> Option<Move> {
+        search_with_limits(
+            board,
+            self.hint_depth,
+            &self.hint_limits,
+            None,
+            &self.search_options,
+            &self.weights,
+        )
+        .best_move
+    }
+
+    /// Returns the moves the opening book has for the position on the given board, which has none
+    /// unless it is standard chess.
+    pub fn book_moves(&self, board: &ChessBoard) -> Vec<Move> {
+        match &self.book {
+            Some(book) if *board.variant() == Variant::Standard => book
+                .moves(board)
+                .into_iter()
+                .map(|(piece_move, _)| piece_move)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns whether the engine is currently searching.
+    pub fn is_searching(&self) -> bool {
+        self.search.is_some()
+    }
+
+    /// Cancels the engine's search, if it is searching, so it plays the best move found so far
+    /// without waiting for the minimum thinking time.
+    pub fn cancel_search(&mut self) {
+        let Some(pending) = &self.search else {
+            return;
+        };
+        pending.cancel.cancel();
+        if let Some(external) = &mut self.external {
+            external.hurry();
+        }
+    }
+
+    pub fn external_mut(&mut self) -> Option<&mut ExternalEngine> {
+        self.external.as_mut()
+    }
+
+    /// Starts the external engine at the given path and plays standard chess with it, replacing
+    /// any attached before.
+    pub fn attach_external(&mut self, path: &str) -> Result<(), String> {
+        self.search = None;
+        self.external = Some(ExternalEngine::spawn(path)?);
+        Ok(())
+    }
+
+    /// Stops the external engine, if one is attached, and plays with the search again.
+    pub fn detach_external(&mut self) {
+        self.search = None;
+        self.external = None;
+    }
+
+    /// Starts searching the given position on another thread, for a board other than the one the
+    /// engine is playing on.
+    pub fn search_in_background(&self, board: &ChessBoard) -> BackgroundSearch {
+        let (sender, receiver) = channel();
+        let cancel = self.spawn_search(board, self.depth, SearchLimits::default(), sender);
+        BackgroundSearch(Mutex::new(receiver), cancel)
+    }
+
+    /// Starts searching the given position on another thread to the given depth within the hint
+    /// limits, to analyse it rather than play in it.
+    pub fn analyse_in_background(&self, board: &ChessBoard, depth: u32) -> BackgroundSearch {
+        let (sender, receiver) = channel();
+        let cancel = self.spawn_search(board, depth, self.hint_limits, sender);
+        BackgroundSearch(Mutex::new(receiver), cancel)
+    }
+
+    /// Searches the given position to the given depth within the given limits on another thread,
+    /// sending the result to the given sender, and returns the token cancelling the search.
+    fn spawn_search(
+        &self,
+        board: &ChessBoard,
+        depth: u32,
+        limits: SearchLimits,
+        sender: Sender<SearchResult>,
+    ) -> SearchCancel {
+        let cancel = SearchCancel::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.search_on_thread(board, depth, limits, sender, cancel.clone());
+        // The network is not sent to the worker, which evaluates with the weights
+        #[cfg(target_arch = "wasm32")]
+        worker::spawn_search(
+            worker::WorkerRequest::new(board, depth, self.search_options, self.weights.clone()),
+            limits.time,
+            sender,
+            cancel.clone(),
+        );
+        cancel
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn search_on_thread(
+        &self,
+        board: &ChessBoard,
+        depth: u32,
+        limits: SearchLimits,
+        sender: Sender<SearchResult>,
+        search_cancel: SearchCancel,
+    ) {
+        let search_board = board.clone();
+        let options = self.search_options;
+        let weights = self.weights.clone();
+        #[cfg(feature = "nnue")]
+        let network = self.network.clone().filter(|_| self.use_network);
+        let fen = search_board.to_fen().to_string();
+        thread::spawn(move || {
+            let _span = info_span!("search", depth, fen).entered();
+            let started = std::time::Instant::now();
+            #[cfg(feature = "nnue")]
+            let result = match network {
+                Some(network) => search_with_network(
+                    &search_board,
+                    depth,
+                    &limits,
+                    Some(&search_cancel),
+                    &options,
+                    &network,
+                ),
+                None => search_with_limits(
+                    &search_board,
+                    depth,
+                    &limits,
+                    Some(&search_cancel),
+                    &options,
+                    &weights,
+                ),
+            };
+            #[cfg(not(feature = "nnue"))]
+            let result = search_with_limits(
+                &search_board,
+                depth,
+                &limits,
+                Some(&search_cancel),
+                &options,
+                &weights,
+            );
+            info!(
+                best_move = result.best_move.map(|piece_move| piece_move.as_algebraic()),
+                evaluation = result.evaluation,
+                milliseconds = started.elapsed().as_millis() as u64,
+                "Search finished"
+            );
+            sender.send(result).ok()
+        });
+    }
+
+    /// Returns whether the engine should offer a draw now, allowing one offer every
+    /// [EngineConduct::draw_moves] engine moves.
+    fn can_offer_draw(&self) -> bool {
+        self.draw_offered_at.is_none_or(|offered_at| {
+            self.evaluations.len() >= offered_at + self.conduct.draw_moves.max(1)
+        })
+    }
+}
+
+/// Returns the move the engine would play in the given position when searching to the given
+/// depth with the given heuristics and evaluation weights, or None if there are no legal moves.
+/// The search runs on the calling thread.
+pub fn best_move(
+    board: &ChessBoard,
+    depth: u32,
+    options: &SearchOptions,
+    weights: &EvalWeights,
+) -> Option<Move> {
+    search(board, depth.max(1), options, weights).best_move
+}
+
+/// Returns the move the engine would play like [best_move], with the evaluation in centipawns of
+/// the position from the point of view of the player to move.
+pub fn best_move_with_evaluation(
+    board: &ChessBoard,
+    depth: u32,
+    options: &SearchOptions,
+    weights: &EvalWeights,
+) -> Option<(Move, i32)> {
+    let result = search(board, depth.max(1), options, weights);
+    Some((result.best_move?, result.evaluation))
+}
+
+/// Returns the evaluation in centipawns of the given position from the point of view of the
+/// player to move, when searching to the given depth with the default heuristics and weights.
+/// The search runs on the calling thread.
+pub fn evaluate_position(board: &ChessBoard, depth: u32) -> i32 {
+    search(
+        board,
+        depth.max(1),
+        &SearchOptions::default(),
+        &EvalWeights::default(),
+    )
+    .evaluation
+}
+
+/// Returns the evaluation of the given position like [evaluate_position], with the line the
+/// engine expects from it, starting with its best move, which is empty if there are no legal
+/// moves.
+pub fn evaluate_line(board: &ChessBoard, depth: u32) -> (i32, Vec<Move>) {
+    let result = search(
+        board,
+        depth.max(1),
+        &SearchOptions::default(),
+        &EvalWeights::default(),
+    );
+    (result.evaluation, result.line)
+}
+
+/// Loads the opening book from [BOOK_PATH], if there is one.
+fn load_book(mut engine: ResMut<Engine>) {
+    engine.book = std::fs::read(BOOK_PATH)
+        .ok()
+        .map(|bytes| Book::from_bytes(&bytes));
+}
+
+/// Loads the network from [NETWORK_PATH], if there is one.
+#[cfg(feature = "nnue")]
+fn load_network(mut engine: ResMut<Engine>) {
+    engine.network = Network::read(NETWORK_PATH).map(Arc::new);
+}
+
+/// Loads the tuned evaluation weights from [WEIGHTS_PATH], if there are any.
+fn load_weights(mut engine: ResMut<Engine>) {
+    if let Some(weights) = EvalWeights::read(WEIGHTS_PATH) {
+        engine.weights = weights;
+    }
+}
+
+fn reset_engine(mut events: EventReader<ResetBoardEvent>, mut engine: ResMut<Engine>) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    engine.evaluations.clear();
+    engine.draw_offered_at = None;
+    engine.move_depth = None;
+    engine.search = None;
+    if let Some(external) = &mut engine.external {
+        external.new_game();
+    }
+}
+
+/// Handles what the external engine has sent, detaching it if it has stopped running.
+fn poll_external_engine(mut engine: ResMut<Engine>, mut diagnostics: Diagnostics) {
+    let Some(external) = &mut engine.external else {
+        return;
+    };
+    if !diagnostics::measure(&mut diagnostics, ENGINE, || external.poll()) {
+        warn!("External engine stopped running");
+        engine.detach_external();
+    }
+}
+
+fn start_search(mut engine: ResMut<Engine>, board: Res<ChessBoard>, mut diagnostics: Diagnostics) {
+    // The engine does not play duck chess, as it cannot place the duck
+    if engine.search.is_some()
+        || engine.color.is_none()
+        || *board.active_color() != engine.color
+        || *board.variant() == Variant::Duck
+    {
+        return;
+    }
+    diagnostics::measure(&mut diagnostics, ENGINE, || {
+        begin_search(&mut engine, &board)
+    });
+}
+
+/// Starts the engine's search of the position on the given board, with the external engine, the
+/// opening book or a search of its own.
+fn begin_search(engine: &mut Engine, board: &ChessBoard) {
+    let (sender, receiver) = channel();
+    // The external engine only plays standard chess, and picks its own opening moves
+    let depth = engine.move_depth();
+    if let Some(external) = engine
+        .external
+        .as_mut()
+        .filter(|_| *board.variant() == Variant::Standard)
+    {
+        if !external.is_ready() {
+            return;
+        }
+        external.start_search(board, depth, sender);
+        engine.search = Some(PendingSearch {
+            fen: board.to_fen().to_string(),
+            receiver: Mutex::new(receiver),
+            elapsed: Duration::ZERO,
+            result: None,
+            from_book: false,
+            cancel: SearchCancel::default(),
+        });
+        return;
+    }
+    // The opening book is only for standard chess
+    let book_move = engine
+        .book
+        .as_ref()
+        .filter(|_| *board.variant() == Variant::Standard)
+        .and_then(|book| book.best_move(board));
+    let cancel = match book_move {
+        Some(piece_move) => {
+            info!(piece_move = %piece_move.as_algebraic(), "Book move");
+            sender
+                .send(SearchResult {
+                    best_move: Some(piece_move),
+                    evaluation: 0,
+                    line: vec![piece_move],
+                })
+                .ok();
+            SearchCancel::default()
+        }
+        None => engine.spawn_search(board, depth, SearchLimits::default(), sender),
+    };
+    engine.search = Some(PendingSearch {
+        fen: board.to_fen().to_string(),
+        receiver: Mutex::new(receiver),
+        elapsed: Duration::ZERO,
+        result: None,
+        from_book: book_move.is_some(),
+        cancel,
+    });
+}
+
+fn finish_search(
+    mut engine: ResMut<Engine>,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+    time: Res<Time>,
+) {
+    let min_think_time = engine.min_think_time;
+    let Some(pending) = &mut engine.search else {
+        return;
+    };
+    pending.elapsed += time.delta();
+    if pending.result.is_none() {
+        pending.result = pending.receiver.lock().unwrap().try_recv().ok();
+    }
+    let Some(result) = pending
+        .result
+        .take_if(|_| pending.elapsed >= min_think_time || pending.cancel.is_cancelled())
+    else {
+        return;
+    };
+    let stale = pending.fen != board.to_fen().to_string();
+    let from_book = pending.from_book;
+    engine.search = None;
+    let (Some(color), false) = (engine.color, stale) else {
+        return;
+    };
+    // The move would be made on a position about to be taken back
+    if *board.active_color() != Some(color) || *board.takeback_request() == Some(color.opposite()) {
+        return;
+    }
+
+    // Book moves are not evaluated, so they leave the resign and draw decisions alone
+    if !from_book {
+        engine.evaluations.push(result.evaluation);
+        if engine.adaptive.enabled {
+            let depth = engine
+                .adaptive
+                .next_depth(engine.move_depth(), result.evaluation);
+            engine.set_move_depth(Some(depth));
+        }
+        match engine.conduct.decide(
+            &engine.evaluations,
+            board.has_mating_material(&color.opposite()),
+            board.has_mating_material(&color),
+        ) {
+            Some(Decision::Resign) => {
+                commands.push(GameCommand::Resign(color));
+                return;
+            }
+            Some(Decision::OfferDraw)
+                if engine.can_offer_draw() && board.draw_offer().is_none() =>
+            {
+                engine.draw_offered_at = Some(engine.evaluations.len());
+                commands.push(GameCommand::OfferDraw(color));
+            }
+            _ => {}
+        }
+    }
+    if let Some(piece_move) = result.best_move {
+        commands.push(GameCommand::MakeMove(piece_move));
+    }
+}
+
+fn respond_to_draw_offers(
+    engine: Res<Engine>,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+) {
+    let Some(color) = engine.color else {
+        return;
+    };
+    if *board.draw_offer() == Some(color.opposite()) {
+        let accept = engine.conduct.accepts_draw(
+            engine.evaluations.last().copied(),
+            board.has_mating_material(&color),
+        );
+        commands.push(GameCommand::RespondDraw { color, accept });
+    }
+}
+
+/// Accepts every take back asked for by the engine's opponent, so playing the engine a take back
+/// is made at once.
+fn respond_to_takebacks(
+    engine: Res<Engine>,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+) {
+    let Some(color) = engine.color else {
+        return;
+    };
+    if *board.takeback_request() == Some(color.opposite()) {
+        commands.push(GameCommand::RespondTakeback {
+            color,
+            accept: true,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_can_offer_draw() {
+        let mut engine = Engine::default();
+        assert!(engine.can_offer_draw());
+
+        engine.evaluations = vec![0; 3];
+        engine.draw_offered_at = Some(3);
+        assert!(!engine.can_offer_draw());
+
+        engine.evaluations = vec![0; 13];
+        assert!(engine.can_offer_draw());
+    }
+
+    #[test]
+    fn test_cancel_search() {
+        let mut engine = Engine::default();
+        // A search this deep would not finish, so the result comes from cancelling it
+        engine.set_depth(30);
+        let board = ChessBoard::with_position(&crate::fen::Fen::default());
+        let search = engine.search_in_background(&board);
+        search.cancel();
+        let started = std::time::Instant::now();
+        let result = loop {
+            if let Some(result) = search.try_result() {
+                break result;
+            }
+            assert!(started.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        };
+        assert!(!result.0.is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+//! Adaptive strength, which quietly makes the engine search more or less deeply during a game to
+//! keep the game close.
+
+use std::ops::RangeInclusive;
+
+/// The depths, in plies, adaptive strength searches to.
+const DEPTHS: RangeInclusive<u32> = 1..=4;
+
+/// Whether the engine adapts its strength to its opponent, and how close it keeps the game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveStrength {
+    pub enabled: bool,
+    /// The largest evaluation in centipawns, either way, at which the game is close.
+    pub band: i32,
+}
+
+impl Default for AdaptiveStrength {
+    fn default() -> Self {
+        AdaptiveStrength {
+            enabled: false,
+            band: 150,
+        }
+    }
+}
+
+impl AdaptiveStrength {
+    /// Returns the depth to search the engine's next move to, given the depth its last move was
+    /// searched to and the evaluation after it from the engine's point of view. The engine searches
+    /// less deeply while it is further ahead than the band, and more deeply while it is further
+    /// behind.
+    pub(super) fn next_depth(&self, depth: u32, evaluation: i32) -> u32 {
+        let depth = if evaluation > self.band {
+            depth.saturating_sub(1)
+        } else if evaluation < -self.band {
+            depth + 1
+        } else {
+            depth
+        };
+        depth.clamp(*DEPTHS.start(), *DEPTHS.end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_depth() {
+        let adaptive = AdaptiveStrength {
+            enabled: true,
+            band: 100,
+        };
+        assert_eq!(adaptive.next_depth(3, 100), 3);
+        assert_eq!(adaptive.next_depth(3, 101), 2);
+        assert_eq!(adaptive.next_depth(3, -101), 4);
+        // The depth stays within the depths the engine can be set to
+        assert_eq!(adaptive.next_depth(1, 500), 1);
+        assert_eq!(adaptive.next_depth(4, -500), 4);
+        assert_eq!(adaptive.next_depth(8, 0), 4);
+    }
+}
@@ -0,0 +1,143 @@
+//! How the engine decides to resign, offer a draw or accept a draw offer.
+
+/// The thresholds the engine uses to resign hopeless positions and to offer or accept draws.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineConduct {
+    /// Whether the engine resigns hopeless positions.
+    pub resign: bool,
+    /// The evaluation in centipawns below which the position is hopeless.
+    pub resign_threshold: i32,
+    /// The number of consecutive engine moves the position must be hopeless for before resigning.
+    pub resign_moves: usize,
+    /// Whether the engine offers and accepts draws.
+    pub draws: bool,
+    /// The largest evaluation in centipawns, either way, at which the position is dead equal.
+    pub draw_threshold: i32,
+    /// The number of consecutive engine moves the position must be dead equal for before offering a draw.
+    pub draw_moves: usize,
+}
+
+impl Default for EngineConduct {
+    fn default() -> Self {
+        EngineConduct {
+            resign: true,
+            resign_threshold: -900,
+            resign_moves: 5,
+            draws: true,
+            draw_threshold: 20,
+            draw_moves: 10,
+        }
+    }
+}
+
+/// A decision made by the engine in addition to its move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Decision {
+    Resign,
+    OfferDraw,
+}
+
+impl EngineConduct {
+    /// Decides whether to resign or offer a draw given the evaluations after each of the engine's
+    /// searches this game, most recent last, from the engine's point of view.
+    ///
+    /// The engine never resigns while the opponent lacks the material to checkmate, and always
+    /// offers a draw once neither side has it.
+    pub(super) fn decide(
+        &self,
+        evaluations: &[i32],
+        opponent_can_mate: bool,
+        engine_can_mate: bool,
+    ) -> Option<Decision> {
+        let last = |moves: usize| {
+            (moves > 0 && evaluations.len() >= moves)
+                .then(|| &evaluations[evaluations.len() - moves..])
+        };
+        if self.resign && opponent_can_mate {
+            if let Some(recent) = last(self.resign_moves) {
+                if recent
+                    .iter()
+                    .all(|evaluation| *evaluation < self.resign_threshold)
+                {
+                    return Some(Decision::Resign);
+                }
+            }
+        }
+        if self.draws {
+            if !opponent_can_mate && !engine_can_mate {
+                return Some(Decision::OfferDraw);
+            }
+            if let Some(recent) = last(self.draw_moves) {
+                if recent
+                    .iter()
+                    .all(|evaluation| evaluation.abs() <= self.draw_threshold)
+                {
+                    return Some(Decision::OfferDraw);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns whether the engine accepts a draw offer given its latest evaluation.
+    /// Draws are accepted when the engine is not better or cannot checkmate.
+    pub(super) fn accepts_draw(&self, evaluation: Option<i32>, engine_can_mate: bool) -> bool {
+        self.draws
+            && (!engine_can_mate
+                || evaluation.is_some_and(|evaluation| evaluation <= self.draw_threshold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_engine_conduct_decide() {
+        let conduct = EngineConduct::default();
+
+        assert_eq!(conduct.decide(&[], true, true), None);
+        assert_eq!(
+            conduct.decide(&[0, -1000, -1000, -1000, -1000, -1000], true, true),
+            Some(Decision::Resign)
+        );
+        // One move was not hopeless
+        assert_eq!(
+            conduct.decide(&[-1000, -1000, -500, -1000, -1000], true, true),
+            None
+        );
+        // The opponent cannot checkmate, so the engine plays on
+        assert_eq!(
+            conduct.decide(&[-1000, -1000, -1000, -1000, -1000], false, true),
+            None
+        );
+        assert_eq!(
+            conduct.decide(&[0], false, false),
+            Some(Decision::OfferDraw)
+        );
+        assert_eq!(
+            conduct.decide(&[5; 10], true, true),
+            Some(Decision::OfferDraw)
+        );
+        assert_eq!(conduct.decide(&[5; 9], true, true), None);
+
+        let passive = EngineConduct {
+            resign: false,
+            draws: false,
+            ..Default::default()
+        };
+        assert_eq!(passive.decide(&[-1000; 5], true, true), None);
+        assert_eq!(passive.decide(&[0], false, false), None);
+    }
+
+    #[test]
+    fn test_engine_conduct_accepts_draw() {
+        let conduct = EngineConduct::default();
+
+        assert!(conduct.accepts_draw(Some(-300), true));
+        assert!(conduct.accepts_draw(Some(10), true));
+        assert!(!conduct.accepts_draw(Some(300), true));
+        assert!(!conduct.accepts_draw(None, true));
+        assert!(conduct.accepts_draw(Some(300), false));
+    }
+}
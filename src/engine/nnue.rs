@@ -0,0 +1,248 @@
+//! An efficiently updatable neural network (NNUE) evaluation, used in place of the
+//! [EvalWeights](super::EvalWeights) when a network is loaded from [NETWORK_PATH].
+//!
+//! The network has the common `768 -> N x 2 -> 1` layout: each piece on each square is an input,
+//! seen from both white's and black's point of view, and feeds a hidden layer of N neurons for
+//! each side. The hidden layers are kept in an [Accumulator] which is updated with the pieces a
+//! move changes rather than recomputed, and their clipped values give the evaluation for the side
+//! to move.
+//!
+//! Network files are little-endian and hold the magic bytes `CCNN`, the hidden layer size N as a
+//! `u32`, the `768 x N` input weights, the N hidden biases, the `2 x N` output weights and the
+//! output bias, all as `i16` quantised by [QA] and [QB].
+
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType, BOARD_SIZE};
+
+/// The file the engine reads its network from.
+pub const NETWORK_PATH: &str = "network.nnue";
+
+/// The magic bytes at the start of a network file.
+const MAGIC: &[u8; 4] = b"CCNN";
+
+/// The number of inputs: each of the six piece types of each color on each square.
+const INPUTS: usize = 2 * 6 * BOARD_SIZE * BOARD_SIZE;
+
+/// The quantisation of the hidden layer, which is also the value it is clipped to.
+const QA: i32 = 255;
+
+/// The quantisation of the output weights.
+const QB: i32 = 64;
+
+/// The centipawns of an output of one.
+const SCALE: i32 = 400;
+
+/// The hidden layers for white's and black's points of view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator([Vec<i16>; 2]);
+
+/// A network loaded from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Network {
+    hidden: usize,
+    input_weights: Vec<i16>,
+    hidden_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+/// Returns the input for the given piece on the given square from the given point of view, or
+/// None for fairy pieces and files beyond those of a standard board, which the network does not
+/// know.
+fn input(
+    piece_type: &PieceType,
+    color: &PieceColor,
+    position: &BoardPosition,
+    perspective: &PieceColor,
+) -> Option<usize> {
+    let rank = match perspective {
+        PieceColor::White => *position.rank(),
+        PieceColor::Black => BOARD_SIZE - 1 - position.rank(),
+    };
+    if *position.file() >= BOARD_SIZE {
+        return None;
+    }
+    let side = usize::from(color != perspective);
+    Some(((side * 6 + piece_type.index()?) * BOARD_SIZE + rank) * BOARD_SIZE + position.file())
+}
+
+/// Returns the piece on the given square of the given board, if there is one.
+fn piece(board: &ChessBoard, position: &BoardPosition) -> Option<(PieceType, PieceColor)> {
+    board
+        .get_piece_type(position)
+        .zip(board.get_piece_color(position))
+}
+
+impl Network {
+    /// Reads a network from the bytes of a network file, or returns None if they are not one.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let hidden = u32::from_le_bytes(bytes.get(4..8)?.try_into().ok()?) as usize;
+        if bytes.get(..4)? != MAGIC || hidden == 0 || !bytes.len().is_multiple_of(2) {
+            return None;
+        }
+        let values: Vec<i16> = bytes[8..]
+            .chunks_exact(2)
+            .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]))
+            .collect();
+        if values.len() != INPUTS * hidden + 3 * hidden + 1 {
+            return None;
+        }
+        let (input_weights, rest) = values.split_at(INPUTS * hidden);
+        let (hidden_biases, rest) = rest.split_at(hidden);
+        let (output_weights, output_bias) = rest.split_at(2 * hidden);
+        Some(Network {
+            hidden,
+            input_weights: input_weights.to_vec(),
+            hidden_biases: hidden_biases.to_vec(),
+            output_weights: output_weights.to_vec(),
+            output_bias: output_bias[0],
+        })
+    }
+
+    /// Reads the network from the given file, or returns None if it is missing or invalid.
+    pub fn read(path: &str) -> Option<Self> {
+        Self::from_bytes(&std::fs::read(path).ok()?)
+    }
+
+    /// Adds the weights of the given input to the hidden layer, or subtracts them.
+    fn apply(&self, values: &mut [i16], input: usize, add: bool) {
+        let weights = &self.input_weights[input * self.hidden..(input + 1) * self.hidden];
+        for (value, weight) in values.iter_mut().zip(weights) {
+            if add {
+                *value = value.wrapping_add(*weight);
+            } else {
+                *value = value.wrapping_sub(*weight);
+            }
+        }
+    }
+
+    /// Adds the given piece on the given square to the hidden layers, or removes it.
+    fn apply_piece(
+        &self,
+        accumulator: &mut Accumulator,
+        (piece_type, color): (PieceType, PieceColor),
+        position: &BoardPosition,
+        add: bool,
+    ) {
+        for perspective in [PieceColor::White, PieceColor::Black] {
+            let Some(input) = input(&piece_type, &color, position, &perspective) else {
+                return;
+            };
+            self.apply(&mut accumulator.0[perspective as usize], input, add);
+        }
+    }
+
+    /// Computes the hidden layers for the position on the given board from scratch.
+    pub fn accumulator(&self, board: &ChessBoard) -> Accumulator {
+        let mut accumulator = Accumulator([self.hidden_biases.clone(), self.hidden_biases.clone()]);
+        for rank in 0..BOARD_SIZE {
+            for file in 0..BOARD_SIZE {
+                let position = BoardPosition::new(rank, file);
+                if let Some(piece) = piece(board, &position) {
+                    self.apply_piece(&mut accumulator, piece, &position, true);
+                }
+            }
+        }
+        accumulator
+    }
+
+    /// Returns the hidden layers after a move from the board before it to the board after it,
+    /// given the hidden layers before the move. Only the squares the move changed are updated.
+    pub fn update(
+        &self,
+        accumulator: &Accumulator,
+        before: &ChessBoard,
+        after: &ChessBoard,
+    ) -> Accumulator {
+        let mut accumulator = accumulator.clone();
+        for rank in 0..BOARD_SIZE {
+            for file in 0..BOARD_SIZE {
+                let position = BoardPosition::new(rank, file);
+                let (old, new) = (piece(before, &position), piece(after, &position));
+                if old == new {
+                    continue;
+                }
+                if let Some(old) = old {
+                    self.apply_piece(&mut accumulator, old, &position, false);
+                }
+                if let Some(new) = new {
+                    self.apply_piece(&mut accumulator, new, &position, true);
+                }
+            }
+        }
+        accumulator
+    }
+
+    /// Returns the evaluation in centipawns for the given side to move.
+    pub fn evaluate(&self, accumulator: &Accumulator, color: &PieceColor) -> i32 {
+        let us = &accumulator.0[*color as usize];
+        let them = &accumulator.0[color.opposite() as usize];
+        let output: i32 = us
+            .iter()
+            .chain(them)
+            .zip(&self.output_weights)
+            .map(|(value, weight)| (*value as i32).clamp(0, QA) * *weight as i32)
+            .sum();
+        (output + self.output_bias as i32) * SCALE / (QA * QB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    /// Returns the bytes of a network with a hidden layer of two neurons, where the first counts
+    /// the queens of the side whose point of view it is and the second counts the opponent's.
+    fn network_bytes() -> Vec<u8> {
+        let mut values = vec![0i16; INPUTS * 2 + 7];
+        for perspective in [PieceColor::White, PieceColor::Black] {
+            for rank in 0..BOARD_SIZE {
+                for file in 0..BOARD_SIZE {
+                    let position = BoardPosition::new(rank, file);
+                    for (color, neuron) in [(perspective, 0), (perspective.opposite(), 1)] {
+                        let input =
+                            input(&PieceType::Queen, &color, &position, &perspective).unwrap();
+                        values[input * 2 + neuron] = 100;
+                    }
+                }
+            }
+        }
+        // Only the side to move's neurons count
+        values[INPUTS * 2 + 2..INPUTS * 2 + 4].copy_from_slice(&[64, -64]);
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(2u32.to_le_bytes());
+        bytes.extend(values.iter().flat_map(|value| value.to_le_bytes()));
+        bytes
+    }
+
+    #[test]
+    fn test_network_from_bytes() {
+        let bytes = network_bytes();
+        assert!(Network::from_bytes(&bytes).is_some());
+        assert!(Network::from_bytes(&bytes[..bytes.len() - 2]).is_none());
+        assert!(Network::from_bytes(b"CCNN").is_none());
+    }
+
+    #[test]
+    fn test_network_update() {
+        let network = Network::from_bytes(&network_bytes()).unwrap();
+        let mut board =
+            ChessBoard::with_position(&Fen::from_string("3qk3/8/8/8/8/8/8/R2QK2R w KQ - 0 1"));
+        let mut accumulator = network.accumulator(&board);
+        assert_eq!(network.evaluate(&accumulator, &PieceColor::White), 0);
+
+        let mut evaluations = Vec::new();
+        for text in ["O-O", "Qxd1", "Raxd1"] {
+            let before = board.clone();
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            accumulator = network.update(&accumulator, &before, &board);
+            assert_eq!(accumulator, network.accumulator(&board));
+            let color = board.active_color().unwrap();
+            evaluations.push(network.evaluate(&accumulator, &color));
+        }
+        // White is a queen down until it recaptures
+        assert_eq!(evaluations, [0, -156, 0]);
+    }
+}
@@ -0,0 +1,776 @@
+//! An evaluation of material, piece placement and pawn structure and an alpha-beta search over the legal moves of a [ChessBoard],
+//! strengthened by null move pruning, late move reductions and killer and history move ordering.
+//! Games of [Variant::Antichess] are evaluated by the material each player still has to give away.
+//! A search can be limited to a number of positions or a time, deepening one ply at a time until
+//! it runs out, or cancelled from another thread with a [SearchCancel]. The search also finds its
+//! principal line, the moves it expects both players to make.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::chess_board::fairy::fairy_piece;
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{
+    BoardPosition, ChessBoard, PieceColor, PieceType, Variant, BOARD_SIZE, MAX_FILES,
+};
+use crate::pawn_structure::pawn_structure;
+
+#[cfg(feature = "nnue")]
+use super::nnue::{Accumulator, Network};
+use super::weights::EvalWeights;
+
+/// The score of a checkmate, reduced by the number of plies needed to reach it.
+pub const MATE_SCORE: i32 = 100_000;
+
+/// The result of a search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct SearchResult {
+    /// The best move found, or None if there are no legal moves.
+    pub(super) best_move: Option<Move>,
+    /// The evaluation of the position in centipawns, from the point of view of the player to move.
+    pub(super) evaluation: i32,
+    /// The principal line, starting with the best move, which is as long as the search was deep
+    /// unless the game ends in it.
+    pub(super) line: Vec<Move>,
+}
+
+fn piece_value(piece_type: &PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 100,
+        PieceType::Knight => 300,
+        PieceType::Bishop => 300,
+        PieceType::Rook => 500,
+        PieceType::Queen => 900,
+        PieceType::King => 0,
+        PieceType::Fairy(index) => fairy_piece(*index).map_or(0, |piece| piece.value),
+    }
+}
+
+/// Returns the balance of the values of the pieces and the pawn structure given by the weights in
+/// centipawns, from the point of view of the given color. Positions where neither side can checkmate are evaluated as
+/// level.
+pub(super) fn evaluate(board: &ChessBoard, color: &PieceColor, weights: &EvalWeights) -> i32 {
+    if !board.has_mating_material(&PieceColor::White)
+        && !board.has_mating_material(&PieceColor::Black)
+    {
+        return 0;
+    }
+    let mut evaluation = 0;
+    for position in board.size().positions() {
+        if let (Some(piece_type), Some(piece_color)) = (
+            board.get_piece_type(&position),
+            board.get_piece_color(&position),
+        ) {
+            let value = weights.value(&piece_type, &piece_color, &position);
+            if piece_color == *color {
+                evaluation += value;
+            } else {
+                evaluation -= value;
+            }
+        }
+    }
+    for pawn in pawn_structure(board) {
+        let value = weights.pawn_value(&pawn);
+        if pawn.color == *color {
+            evaluation += value;
+        } else {
+            evaluation -= value;
+        }
+    }
+    evaluation
+}
+
+/// The value of each piece in antichess, where every piece is a burden as any of them may be
+/// forced to capture into a worse position, and the king is an ordinary piece.
+const ANTICHESS_PIECE_VALUE: i32 = 100;
+
+/// Returns the balance of the pieces in antichess from the point of view of the given color, who
+/// is better off with fewer pieces.
+fn evaluate_antichess(board: &ChessBoard, color: &PieceColor) -> i32 {
+    board
+        .size()
+        .positions()
+        .filter_map(|position| board.get_piece_color(&position))
+        .map(|piece_color| {
+            if piece_color == *color {
+                -ANTICHESS_PIECE_VALUE
+            } else {
+                ANTICHESS_PIECE_VALUE
+            }
+        })
+        .sum()
+}
+
+/// Returns the score of a position where the player to move has no legal moves, reached after the
+/// given number of plies.
+fn no_moves_score(board: &ChessBoard, color: &PieceColor, ply: usize) -> i32 {
+    match board.variant() {
+        // Running out of moves wins antichess
+        Variant::Antichess => MATE_SCORE - ply as i32,
+        _ if board.in_check(color) => -MATE_SCORE + ply as i32,
+        _ => 0,
+    }
+}
+
+/// The search heuristics, each of which can be turned off to measure its effect with the engine
+/// match runner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    /// Whether to prune positions which are still too good for the opponent after passing the turn.
+    pub null_move_pruning: bool,
+    /// Whether to search quiet moves late in the move order to a reduced depth first.
+    pub late_move_reductions: bool,
+    /// Whether to try the quiet moves which caused cutoffs in sibling positions first.
+    pub killer_moves: bool,
+    /// Whether to order the remaining quiet moves by how deep the cutoffs they caused were.
+    pub history_heuristic: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            null_move_pruning: true,
+            late_move_reductions: true,
+            killer_moves: true,
+            history_heuristic: true,
+        }
+    }
+}
+
+/// Limits on how long a search runs for, beyond the depth it searches to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchLimits {
+    /// The most positions to search.
+    pub nodes: Option<u64>,
+    /// The longest time to search for.
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    fn is_unlimited(&self) -> bool {
+        self.nodes.is_none() && self.time.is_none()
+    }
+}
+
+/// A token cancelling a search running on another thread, which then finishes with the best move
+/// found so far. Clones share the token, so any of them cancels the search.
+#[derive(Debug, Clone, Default)]
+pub(super) struct SearchCancel(Arc<AtomicBool>);
+
+impl SearchCancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The number of positions searched between checks of the time limit.
+const TIME_CHECK_NODES: u64 = 1024;
+
+/// The number of plies the null move search is reduced by, in addition to the null move itself.
+const NULL_MOVE_REDUCTION: u32 = 2;
+
+/// The number of moves searched to full depth before late move reductions apply.
+const FULL_DEPTH_MOVES: usize = 3;
+
+/// The smallest remaining depth at which late moves are reduced.
+const REDUCTION_DEPTH: u32 = 3;
+
+/// The ordering scores of captures and killer moves, which are tried before other quiet moves.
+const CAPTURE_SCORE: i32 = 2_000_000;
+const KILLER_SCORE: i32 = 1_000_000;
+
+/// The number of squares in the grid of squares of any board.
+const SQUARES: usize = BOARD_SIZE * MAX_FILES;
+
+/// Returns the index of the given square in the grid, from 0 for a8.
+fn square_index(position: &BoardPosition) -> usize {
+    position.rank() * MAX_FILES + position.file()
+}
+
+/// Returns whether the given color has any pieces other than its king and pawns, without which
+/// passing the turn is unsafe as zugzwang is likely.
+fn has_pieces(board: &ChessBoard, color: &PieceColor) -> bool {
+    board.size().positions().any(|position| {
+        board.get_piece_color(&position) == Some(*color)
+            && !matches!(
+                board.get_piece_type(&position),
+                Some(PieceType::King | PieceType::Pawn)
+            )
+    })
+}
+
+/// The state of a single search, which the move ordering heuristics learn from as it goes.
+struct Searcher<'a> {
+    options: SearchOptions,
+    weights: &'a EvalWeights,
+    /// The network to evaluate with instead of the weights, with its hidden layers for each
+    /// position from the root of the search to the current one.
+    #[cfg(feature = "nnue")]
+    network: Option<(&'a Network, Vec<Accumulator>)>,
+    /// The last two quiet moves which caused a cutoff at each ply.
+    killers: Vec<[Option<Move>; 2]>,
+    /// How deep the cutoffs caused by each quiet move were, indexed by its start and end squares.
+    history: Vec<[i32; SQUARES]>,
+    /// The best line found from the position being searched at each ply.
+    lines: Vec<Vec<Move>>,
+    limits: SearchLimits,
+    /// Whether the limits apply yet, which they do once the first ply has been searched, so
+    /// there is always a move.
+    limited: bool,
+    /// The number of positions searched so far.
+    nodes: u64,
+    started: Instant,
+    /// The token which cancels the search like running out of its limits.
+    cancel: Option<SearchCancel>,
+    /// Whether the search ran out of its limits or was cancelled, in which case the depth being
+    /// searched is abandoned.
+    stopped: bool,
+}
+
+impl<'a> Searcher<'a> {
+    fn new(options: SearchOptions, weights: &'a EvalWeights) -> Self {
+        Searcher {
+            options,
+            weights,
+            #[cfg(feature = "nnue")]
+            network: None,
+            killers: Vec::new(),
+            history: vec![[0; SQUARES]; SQUARES],
+            lines: Vec::new(),
+            limits: SearchLimits::default(),
+            limited: false,
+            nodes: 0,
+            started: Instant::now(),
+            cancel: None,
+            stopped: false,
+        }
+    }
+
+    fn with_limits(mut self, limits: SearchLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn with_cancel(mut self, cancel: Option<&SearchCancel>) -> Self {
+        self.cancel = cancel.cloned();
+        self
+    }
+
+    /// Counts another position searched and returns whether the search has run out of its limits.
+    fn out_of_limits(&mut self) -> bool {
+        self.nodes += 1;
+        if self.limited && !self.stopped {
+            let nodes_out = self.limits.nodes.is_some_and(|nodes| self.nodes > nodes);
+            let time_out = self.nodes.is_multiple_of(TIME_CHECK_NODES)
+                && self
+                    .limits
+                    .time
+                    .is_some_and(|time| self.started.elapsed() >= time);
+            let cancelled = self.cancel.as_ref().is_some_and(SearchCancel::is_cancelled);
+            self.stopped = nodes_out || time_out || cancelled;
+        }
+        self.stopped
+    }
+
+    /// Evaluates with the given network, starting the search from the given board.
+    #[cfg(feature = "nnue")]
+    fn with_network(mut self, network: &'a Network, board: &ChessBoard) -> Self {
+        self.network = Some((network, vec![network.accumulator(board)]));
+        self
+    }
+
+    /// Returns the board after the given move, updating the hidden layers of the network.
+    fn make_move(&mut self, board: &ChessBoard, piece_move: &Move) -> ChessBoard {
+        let mut next_board = board.clone();
+        next_board.apply_move(piece_move);
+        #[cfg(feature = "nnue")]
+        if let Some((network, accumulators)) = &mut self.network {
+            let accumulator = network.update(accumulators.last().unwrap(), board, &next_board);
+            accumulators.push(accumulator);
+        }
+        next_board
+    }
+
+    /// Takes back the hidden layers of the last move made with [Searcher::make_move].
+    fn unmake_move(&mut self) {
+        #[cfg(feature = "nnue")]
+        if let Some((_, accumulators)) = &mut self.network {
+            accumulators.pop();
+        }
+    }
+
+    /// Evaluates the position on the given board from the point of view of the given color.
+    fn evaluate(&self, board: &ChessBoard, color: &PieceColor) -> i32 {
+        // Neither the weights nor the network are trained for antichess
+        if *board.variant() == Variant::Antichess {
+            return evaluate_antichess(board, color);
+        }
+        #[cfg(feature = "nnue")]
+        if let Some((network, accumulators)) = &self.network {
+            let dead = !board.has_mating_material(&PieceColor::White)
+                && !board.has_mating_material(&PieceColor::Black);
+            return if dead {
+                0
+            } else {
+                network.evaluate(accumulators.last().unwrap(), color)
+            };
+        }
+        evaluate(board, color, self.weights)
+    }
+
+    /// Returns the score a move is ordered by at the given ply, where higher scores go first.
+    fn order_score(&self, board: &ChessBoard, piece_move: &Move, ply: usize) -> i32 {
+        if let Some(piece_type) = board.get_piece_type(piece_move.to()) {
+            return CAPTURE_SCORE + piece_value(&piece_type);
+        }
+        if self.options.killer_moves {
+            if let Some(slot) = self.killers.get(ply).and_then(|killers| {
+                killers
+                    .iter()
+                    .position(|killer| *killer == Some(*piece_move))
+            }) {
+                return KILLER_SCORE - slot as i32;
+            }
+        }
+        if self.options.history_heuristic {
+            self.history[square_index(piece_move.from())][square_index(piece_move.to())]
+                .min(KILLER_SCORE - 2)
+        } else {
+            0
+        }
+    }
+
+    /// Returns the legal moves of the player to move, with the most valuable captures first and
+    /// then the quiet moves the heuristics expect to be best.
+    fn ordered_moves(&self, board: &ChessBoard, ply: usize) -> Vec<Move> {
+        let mut moves: Vec<Move> = board.legal_moves_iter().collect();
+        moves.sort_by_key(|piece_move| -self.order_score(board, piece_move, ply));
+        moves
+    }
+
+    /// Records that the given quiet move caused a cutoff at the given ply and remaining depth.
+    fn record_cutoff(&mut self, piece_move: &Move, depth: u32, ply: usize) {
+        if self.options.killer_moves {
+            if self.killers.len() <= ply {
+                self.killers.resize(ply + 1, [None; 2]);
+            }
+            let killers = &mut self.killers[ply];
+            if killers[0] != Some(*piece_move) {
+                killers[1] = killers[0];
+                killers[0] = Some(*piece_move);
+            }
+        }
+        if self.options.history_heuristic {
+            let entry =
+                &mut self.history[square_index(piece_move.from())][square_index(piece_move.to())];
+            *entry = entry.saturating_add((depth * depth) as i32);
+        }
+    }
+
+    /// Returns the given move followed by the best line found after it at the next ply.
+    fn line_after(&self, piece_move: Move, ply: usize) -> Vec<Move> {
+        let mut line = vec![piece_move];
+        line.extend(self.lines.get(ply + 1).into_iter().flatten());
+        line
+    }
+
+    fn negamax(
+        &mut self,
+        board: &ChessBoard,
+        depth: u32,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+        allow_null_move: bool,
+    ) -> i32 {
+        // The line is only kept for positions whose score is within the window
+        if self.lines.len() <= ply {
+            self.lines.resize(ply + 1, Vec::new());
+        }
+        self.lines[ply].clear();
+        // The score does not matter, as the depth being searched is abandoned
+        if self.out_of_limits() {
+            return 0;
+        }
+        let Some(color) = *board.active_color() else {
+            return 0;
+        };
+        let moves = self.ordered_moves(board, ply);
+        if moves.is_empty() {
+            return no_moves_score(board, &color, ply);
+        }
+        let in_check = *board.variant() == Variant::Standard && board.in_check(&color);
+        if depth == 0 {
+            return self.evaluate(board, &color);
+        }
+
+        // If the position is still too good for the opponent after passing the turn, a real move
+        // will be too, so the position is pruned. Forced captures make passing meaningless in
+        // antichess.
+        if self.options.null_move_pruning
+            && allow_null_move
+            && *board.variant() != Variant::Antichess
+            && !in_check
+            && depth > NULL_MOVE_REDUCTION
+            && has_pieces(board, &color)
+        {
+            let mut next_board = board.clone();
+            next_board.pass_turn();
+            let score = -self.negamax(
+                &next_board,
+                depth - 1 - NULL_MOVE_REDUCTION,
+                -beta,
+                -beta + 1,
+                ply + 1,
+                false,
+            );
+            if score >= beta {
+                return beta;
+            }
+        }
+
+        for (index, piece_move) in moves.into_iter().enumerate() {
+            let next_board = self.make_move(board, &piece_move);
+            let quiet = !piece_move.is_capture();
+            let gives_check = next_board
+                .past_moves()
+                .last()
+                .is_some_and(|last_move| last_move.is_check());
+            let reduce = self.options.late_move_reductions
+                && index >= FULL_DEPTH_MOVES
+                && depth >= REDUCTION_DEPTH
+                && quiet
+                && !in_check
+                && !gives_check;
+            // A reduced search which beats alpha is repeated to full depth
+            let mut score = alpha + 1;
+            if reduce {
+                score = -self.negamax(&next_board, depth - 2, -alpha - 1, -alpha, ply + 1, true);
+            }
+            if score > alpha {
+                score = -self.negamax(&next_board, depth - 1, -beta, -alpha, ply + 1, true);
+            }
+            self.unmake_move();
+            if score >= beta {
+                if quiet {
+                    self.record_cutoff(&piece_move, depth, ply);
+                }
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                self.lines[ply] = self.line_after(piece_move, ply);
+            }
+        }
+        alpha
+    }
+
+    /// Searches the position to the given depth in plies, which must be at least one. With limits
+    /// or a cancel token it searches each depth in turn, returning the result of the deepest
+    /// finished before they ran out or it was cancelled.
+    fn search(&mut self, board: &ChessBoard, depth: u32) -> SearchResult {
+        if self.limits.is_unlimited() && self.cancel.is_none() {
+            return self.search_to_depth(board, depth);
+        }
+        self.started = Instant::now();
+        let mut result = self.search_to_depth(board, 1);
+        self.limited = true;
+        for depth in 2..=depth {
+            let deeper = self.search_to_depth(board, depth);
+            if self.stopped {
+                break;
+            }
+            result = deeper;
+        }
+        result
+    }
+
+    fn search_to_depth(&mut self, board: &ChessBoard, depth: u32) -> SearchResult {
+        let mut result = SearchResult {
+            best_move: None,
+            evaluation: -MATE_SCORE,
+            line: Vec::new(),
+        };
+        let Some(color) = *board.active_color() else {
+            return SearchResult {
+                best_move: None,
+                evaluation: 0,
+                line: Vec::new(),
+            };
+        };
+        let moves = self.ordered_moves(board, 0);
+        if moves.is_empty() {
+            result.evaluation = no_moves_score(board, &color, 0);
+        }
+        for piece_move in moves {
+            let next_board = self.make_move(board, &piece_move);
+            let score = -self.negamax(
+                &next_board,
+                depth - 1,
+                -MATE_SCORE,
+                -result.evaluation,
+                1,
+                true,
+            );
+            self.unmake_move();
+            if self.stopped {
+                break;
+            }
+            if result.best_move.is_none() || score > result.evaluation {
+                result = SearchResult {
+                    best_move: Some(piece_move),
+                    evaluation: score,
+                    line: self.line_after(piece_move, 0),
+                };
+            }
+        }
+        result
+    }
+}
+
+/// Searches the position to the given depth in plies, which must be at least one, using the
+/// heuristics enabled in the given options and evaluating with the given weights.
+pub(super) fn search(
+    board: &ChessBoard,
+    depth: u32,
+    options: &SearchOptions,
+    weights: &EvalWeights,
+) -> SearchResult {
+    search_with_limits(
+        board,
+        depth,
+        &SearchLimits::default(),
+        None,
+        options,
+        weights,
+    )
+}
+
+/// Searches the position like [search], but stopping at the given limits or once the given token
+/// is cancelled.
+pub(super) fn search_with_limits(
+    board: &ChessBoard,
+    depth: u32,
+    limits: &SearchLimits,
+    cancel: Option<&SearchCancel>,
+    options: &SearchOptions,
+    weights: &EvalWeights,
+) -> SearchResult {
+    Searcher::new(*options, weights)
+        .with_limits(*limits)
+        .with_cancel(cancel)
+        .search(board, depth)
+}
+
+/// Searches the position like [search_with_limits], but evaluating with the given network.
+#[cfg(feature = "nnue")]
+pub(super) fn search_with_network(
+    board: &ChessBoard,
+    depth: u32,
+    limits: &SearchLimits,
+    cancel: Option<&SearchCancel>,
+    options: &SearchOptions,
+    network: &Network,
+) -> SearchResult {
+    let weights = EvalWeights::default();
+    Searcher::new(*options, &weights)
+        .with_limits(*limits)
+        .with_cancel(cancel)
+        .with_network(network, board)
+        .search(board, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::App;
+
+    use crate::chess_board::{ChessBoardPlugin, GameCommand, GameCommands, ResetBoardEvent};
+    use crate::fen::Fen;
+
+    use super::*;
+
+    fn board_from_fen(fen_string: &str) -> ChessBoard {
+        let mut app = App::new();
+        app.add_plugins(ChessBoardPlugin::default());
+        app.update();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(
+                Fen::from_string(fen_string),
+            ))));
+        app.update();
+        app.world.get_resource::<ChessBoard>().unwrap().clone()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let mut weights = EvalWeights::default();
+        let board = board_from_fen("4k3/8/8/8/8/8/3QP3/4K3 w - - 0 1");
+        // The pawn on e2 is isolated and passed
+        assert_eq!(evaluate(&board, &PieceColor::White, &weights), 1005);
+        assert_eq!(evaluate(&board, &PieceColor::Black, &weights), -1005);
+
+        // A bonus for the pawn on e2
+        weights.squares[PieceType::Pawn.index().unwrap()][6][4] = 20;
+        assert_eq!(evaluate(&board, &PieceColor::White, &weights), 1025);
+
+        // Neither side can checkmate
+        let board = board_from_fen("4k3/8/8/8/8/8/3N4/4K3 w - - 0 1");
+        assert_eq!(evaluate(&board, &PieceColor::White, &weights), 0);
+    }
+
+    #[test]
+    fn test_search_finds_mate() {
+        let board = board_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let result = search(
+            &board,
+            2,
+            &SearchOptions::default(),
+            &EvalWeights::default(),
+        );
+        assert_eq!(result.best_move.unwrap().as_algebraic(), "Ra8");
+        assert_eq!(result.evaluation, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_search_takes_free_material() {
+        let board = board_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        let result = search(
+            &board,
+            1,
+            &SearchOptions::default(),
+            &EvalWeights::default(),
+        );
+        assert_eq!(result.best_move.unwrap().as_algebraic(), "Rxd5");
+
+        // The line holds a move for each ply, each of which can be played in turn
+        let result = search(
+            &board,
+            3,
+            &SearchOptions::default(),
+            &EvalWeights::default(),
+        );
+        assert_eq!(result.line.len(), 3);
+        assert_eq!(result.line.first(), result.best_move.as_ref());
+        let mut line_board = board.clone();
+        for piece_move in &result.line {
+            assert!(line_board.valid_move(piece_move, line_board.active_color(), &true));
+            line_board.apply_move(piece_move);
+        }
+    }
+
+    #[test]
+    fn test_search_antichess() {
+        // Giving the last pawn away wins
+        let board = board_from_fen("8/8/8/8/1r6/8/P7/8 w - - 0 1").with_variant(Variant::Antichess);
+        let result = search(
+            &board,
+            3,
+            &SearchOptions::default(),
+            &EvalWeights::default(),
+        );
+        assert_eq!(result.best_move.unwrap().as_algebraic(), "a4");
+        assert_eq!(result.evaluation, MATE_SCORE - 2);
+        let board =
+            board_from_fen("8/8/8/8/1r6/8/PP6/8 w - - 0 1").with_variant(Variant::Antichess);
+        assert_eq!(
+            evaluate_antichess(&board, &PieceColor::White),
+            -ANTICHESS_PIECE_VALUE
+        );
+
+        // A player with no legal moves has won
+        let board = board_from_fen("8/8/8/8/8/r7/P7/8 w - - 0 1").with_variant(Variant::Antichess);
+        let result = search(
+            &board,
+            1,
+            &SearchOptions::default(),
+            &EvalWeights::default(),
+        );
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.evaluation, MATE_SCORE);
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let board = board_from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1");
+        let options = SearchOptions::default();
+        let weights = EvalWeights::default();
+        // The first ply is always searched, so there is a move however tight the limits
+        for limits in [
+            SearchLimits {
+                nodes: Some(0),
+                time: None,
+            },
+            SearchLimits {
+                nodes: None,
+                time: Some(Duration::ZERO),
+            },
+        ] {
+            let result = search_with_limits(&board, 6, &limits, None, &options, &weights);
+            assert_eq!(result.best_move.unwrap().as_algebraic(), "Rxd5");
+        }
+        // A search cancelled before it starts still searches the first ply
+        let cancel = SearchCancel::default();
+        cancel.clone().cancel();
+        let result = search_with_limits(
+            &board,
+            6,
+            &SearchLimits::default(),
+            Some(&cancel),
+            &options,
+            &weights,
+        );
+        assert_eq!(result.best_move.unwrap().as_algebraic(), "Rxd5");
+        let mut searcher = Searcher::new(options, &weights).with_limits(SearchLimits {
+            nodes: Some(100),
+            time: None,
+        });
+        let result = searcher.search(&board, 6);
+        assert!(searcher.stopped);
+        assert_eq!(result.best_move.unwrap().as_algebraic(), "Rxd5");
+    }
+
+    #[test]
+    fn test_search_heuristics() {
+        let board = board_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        let weights = EvalWeights::default();
+        let none = SearchOptions {
+            null_move_pruning: false,
+            late_move_reductions: false,
+            killer_moves: false,
+            history_heuristic: false,
+        };
+        for options in [SearchOptions::default(), none] {
+            let result = search(&board, 3, &options, &weights);
+            assert_eq!(result.best_move.unwrap().as_algebraic(), "Ra8");
+        }
+
+        // A quiet move which caused a cutoff is tried first at the same ply as a killer move, and
+        // at every ply by the history heuristic
+        let cutoff = Move::from_algebraic("Kf1", &board).unwrap();
+        let killers = SearchOptions {
+            killer_moves: true,
+            ..none
+        };
+        let history = SearchOptions {
+            history_heuristic: true,
+            ..none
+        };
+        for (options, first_at_ply_1) in [(killers, false), (history, true)] {
+            let mut searcher = Searcher::new(options, &weights);
+            searcher.record_cutoff(&cutoff, 2, 0);
+            assert_eq!(searcher.ordered_moves(&board, 0)[0], cutoff);
+            assert_eq!(
+                searcher.ordered_moves(&board, 1)[0] == cutoff,
+                first_at_ply_1
+            );
+        }
+    }
+}
@@ -0,0 +1,526 @@
+//! External engines speaking the Universal Chess Interface (UCI), which play in place of the
+//! built-in search in standard chess while one is attached.
+//!
+//! An engine is started from the path of its executable and declares its options in reply to
+//! `uci`. The values the player sets are sent with `setoption` and saved to [PROFILES_PATH] under
+//! the name the engine gives, so they are sent again whenever the same engine is attached.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+
+use bevy::log::{debug, info};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::ChessBoard;
+use crate::storage;
+
+use super::search::{SearchResult, MATE_SCORE};
+
+/// The file the option values set for each external engine are saved to.
+const PROFILES_PATH: &str = "uci_profiles.ron";
+
+/// The values set for the options of each engine, by the name of the engine and then the option.
+type Profiles = BTreeMap<String, BTreeMap<String, String>>;
+
+/// The type of an option and what it can be set to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciOptionKind {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    /// One of a list of choices.
+    Combo {
+        default: String,
+        choices: Vec<String>,
+    },
+    /// An action without a value, such as clearing the hash table.
+    Button,
+    String {
+        default: String,
+    },
+}
+
+/// An option declared by an engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UciOption {
+    name: String,
+    kind: UciOptionKind,
+}
+
+impl UciOption {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> &UciOptionKind {
+        &self.kind
+    }
+
+    /// Returns the value of the option until it is set, or None for a button.
+    fn default_value(&self) -> Option<String> {
+        match &self.kind {
+            UciOptionKind::Check { default } => Some(default.to_string()),
+            UciOptionKind::Spin { default, .. } => Some(default.to_string()),
+            UciOptionKind::Combo { default, .. } | UciOptionKind::String { default } => {
+                Some(default.clone())
+            }
+            UciOptionKind::Button => None,
+        }
+    }
+}
+
+/// Parses an option declaration such as `option name Hash type spin default 16 min 1 max 1024`,
+/// or returns None if it is not one or is invalid.
+fn parse_option(line: &str) -> Option<UciOption> {
+    let rest = line.strip_prefix("option ")?;
+    // Names and values can contain spaces, so each field runs up to the next keyword
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for token in rest.split_whitespace() {
+        match token {
+            "name" | "type" | "default" | "min" | "max" | "var" => {
+                fields.push((token, String::new()))
+            }
+            _ => {
+                let (_, value) = fields.last_mut()?;
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(token);
+            }
+        }
+    }
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.clone())
+    };
+    let name = field("name").filter(|name| !name.is_empty())?;
+    let default = field("default").unwrap_or_default();
+    let kind = match field("type")?.as_str() {
+        "check" => UciOptionKind::Check {
+            default: default == "true",
+        },
+        "spin" => UciOptionKind::Spin {
+            default: default.parse().ok()?,
+            min: field("min")?.parse().ok()?,
+            max: field("max")?.parse().ok()?,
+        },
+        "combo" => UciOptionKind::Combo {
+            default,
+            choices: fields
+                .iter()
+                .filter(|(name, _)| *name == "var")
+                .map(|(_, value)| value.clone())
+                .collect(),
+        },
+        "button" => UciOptionKind::Button,
+        "string" if default == "<empty>" => UciOptionKind::String {
+            default: String::new(),
+        },
+        "string" => UciOptionKind::String { default },
+        _ => return None,
+    };
+    Some(UciOption { name, kind })
+}
+
+/// Returns the score in an `info` line, in centipawns from the point of view of the engine, or
+/// None if it has none. Mates are scored like the built-in search scores them.
+fn parse_score(line: &str) -> Option<i32> {
+    let mut tokens = line.strip_prefix("info ")?.split_whitespace();
+    tokens.find(|token| *token == "score")?;
+    let kind = tokens.next()?;
+    let value: i32 = tokens.next()?.parse().ok()?;
+    match kind {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(MATE_SCORE - (2 * value - 1)),
+        "mate" => Some(-MATE_SCORE - 2 * value),
+        _ => None,
+    }
+}
+
+/// Returns the `setoption` command setting the given option, or pressing it if it is a button.
+fn set_option_command(name: &str, value: Option<&str>) -> String {
+    match value {
+        Some(value) => format!("setoption name {} value {}", name, value),
+        None => format!("setoption name {}", name),
+    }
+}
+
+/// A search the engine is running.
+struct ExternalSearch {
+    /// The position being searched, which the best move is read in.
+    board: ChessBoard,
+    sender: Sender<SearchResult>,
+    /// The last evaluation the engine reported.
+    evaluation: i32,
+}
+
+impl fmt::Debug for ExternalSearch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExternalSearch")
+            .field("fen", &self.board.to_fen().to_string())
+            .field("evaluation", &self.evaluation)
+            .finish()
+    }
+}
+
+/// What is known about an engine from what it has sent.
+#[derive(Debug, Default)]
+struct UciState {
+    /// The name the engine gave, once it has.
+    name: Option<String>,
+    options: Vec<UciOption>,
+    /// Whether the engine has declared all its options.
+    ready: bool,
+    /// The values set for the engine's options.
+    values: BTreeMap<String, String>,
+    /// The saved values of every engine, of which this engine's are sent once it is ready.
+    profiles: Profiles,
+    search: Option<ExternalSearch>,
+    /// The number of best moves still to come from stopped searches, which are ignored.
+    stopped_searches: usize,
+}
+
+impl UciState {
+    /// Handles a line sent by the engine, returning the commands to send in reply.
+    fn handle_line(&mut self, line: &str) -> Vec<String> {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("id name ") {
+            self.name = Some(name.to_string());
+        } else if let Some(option) = parse_option(line) {
+            self.options.push(option);
+        } else if line == "uciok" {
+            self.ready = true;
+            let saved = self
+                .name
+                .as_ref()
+                .and_then(|name| self.profiles.get(name))
+                .cloned()
+                .unwrap_or_default();
+            self.values = saved
+                .into_iter()
+                .filter(|(name, _)| self.options.iter().any(|option| option.name == *name))
+                .collect();
+            return self
+                .values
+                .iter()
+                .map(|(name, value)| set_option_command(name, Some(value)))
+                .collect();
+        } else if let Some(evaluation) = parse_score(line) {
+            if let Some(search) = &mut self.search {
+                search.evaluation = evaluation;
+            }
+        } else if let Some(rest) = line.strip_prefix("bestmove") {
+            if self.stopped_searches > 0 {
+                self.stopped_searches -= 1;
+            } else if let Some(search) = self.search.take() {
+                let best_move = rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|text| Move::from_long_algebraic(text, &search.board));
+                search
+                    .sender
+                    .send(SearchResult {
+                        best_move,
+                        evaluation: search.evaluation,
+                        // Only the best move of the engine's line is read
+                        line: best_move.into_iter().collect(),
+                    })
+                    .ok();
+            }
+        }
+        Vec::new()
+    }
+
+    /// Returns the value of the given option, as set or else its default.
+    fn value(&self, option: &UciOption) -> Option<String> {
+        self.values
+            .get(&option.name)
+            .cloned()
+            .or_else(|| option.default_value())
+    }
+
+    /// Sets the given option, saving its value in the engine's profile, and returns the command
+    /// to send.
+    fn set_option(&mut self, name: &str, value: Option<String>) -> String {
+        let command = set_option_command(name, value.as_deref());
+        if let Some(value) = value {
+            self.values.insert(name.to_string(), value);
+            if let Some(engine) = &self.name {
+                self.profiles.insert(engine.clone(), self.values.clone());
+            }
+        }
+        command
+    }
+}
+
+/// A running external engine.
+#[derive(Debug)]
+pub struct ExternalEngine {
+    process: Child,
+    stdin: ChildStdin,
+    /// The lines the engine has sent, read on another thread.
+    lines: Mutex<Receiver<String>>,
+    state: UciState,
+}
+
+impl ExternalEngine {
+    /// Starts the engine at the given path and asks it for its options.
+    pub fn spawn(path: &str) -> Result<Self, String> {
+        let mut process = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| error.to_string())?;
+        let (Some(stdin), Some(stdout)) = (process.stdin.take(), process.stdout.take()) else {
+            process.kill().ok();
+            return Err("the engine's input and output could not be opened".to_string());
+        };
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let profiles = storage::read(PROFILES_PATH)
+            .and_then(|text| ron::from_str(&text).ok())
+            .unwrap_or_default();
+        let mut engine = ExternalEngine {
+            process,
+            stdin,
+            lines: Mutex::new(receiver),
+            state: UciState {
+                profiles,
+                ..UciState::default()
+            },
+        };
+        info!(path, "External engine started");
+        engine.send("uci");
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) {
+        debug!(command, "Sent to external engine");
+        writeln!(self.stdin, "{}", command).ok();
+    }
+
+    /// Handles the lines the engine has sent since the last poll, returning false if it has
+    /// stopped running.
+    pub(super) fn poll(&mut self) -> bool {
+        loop {
+            let line = self.lines.lock().unwrap().try_recv();
+            match line {
+                Ok(line) => {
+                    debug!(line, "Received from external engine");
+                    let was_ready = self.state.ready;
+                    for command in self.state.handle_line(&line) {
+                        self.send(&command);
+                    }
+                    if self.state.ready && !was_ready {
+                        info!(name = self.state.name, "External engine ready");
+                    }
+                }
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Returns the name the engine gave, once it has.
+    pub fn name(&self) -> Option<&str> {
+        self.state.name.as_deref()
+    }
+
+    /// Returns whether the engine has declared its options and can play.
+    pub fn is_ready(&self) -> bool {
+        self.state.ready
+    }
+
+    pub fn options(&self) -> &[UciOption] {
+        &self.state.options
+    }
+
+    /// Returns the current value of the given option, or None for a button.
+    pub fn value(&self, option: &UciOption) -> Option<String> {
+        self.state.value(option)
+    }
+
+    /// Sets the given option to the given value, or presses it if it is a button, and saves the
+    /// engine's profile.
+    pub fn set_option(&mut self, name: &str, value: Option<String>) {
+        let command = self.state.set_option(name, value);
+        self.send(&command);
+        if let Ok(text) = ron::to_string(&self.state.profiles) {
+            // A failed save only loses the profile, so it is not reported
+            storage::write(PROFILES_PATH, &text).ok();
+        }
+    }
+
+    /// Starts searching the given position to the given depth, sending the result to the given
+    /// sender.
+    pub(super) fn start_search(
+        &mut self,
+        board: &ChessBoard,
+        depth: u32,
+        sender: Sender<SearchResult>,
+    ) {
+        self.stop();
+        self.send(&format!("position fen {}", board.to_fen()));
+        self.send(&format!("go depth {}", depth));
+        self.state.search = Some(ExternalSearch {
+            board: board.clone(),
+            sender,
+            evaluation: 0,
+        });
+    }
+
+    /// Asks the engine to finish the search in progress, if any, with the best move it has found.
+    pub(super) fn hurry(&mut self) {
+        if self.state.search.is_some() {
+            self.send("stop");
+        }
+    }
+
+    /// Stops the search in progress, if any, ignoring its result.
+    fn stop(&mut self) {
+        if self.state.search.take().is_some() {
+            self.state.stopped_searches += 1;
+            self.send("stop");
+        }
+    }
+
+    /// Stops the search in progress and tells the engine a new game has started.
+    pub(super) fn new_game(&mut self) {
+        self.stop();
+        self.send("ucinewgame");
+    }
+}
+
+impl Drop for ExternalEngine {
+    fn drop(&mut self) {
+        self.send("quit");
+        // The engine is killed in case it does not quit, which does nothing if it has
+        self.process.kill().ok();
+        self.process.wait().ok();
+        info!("External engine stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_option() {
+        assert_eq!(
+            parse_option("option name Hash type spin default 16 min 1 max 33554432"),
+            Some(UciOption {
+                name: "Hash".to_string(),
+                kind: UciOptionKind::Spin {
+                    default: 16,
+                    min: 1,
+                    max: 33554432
+                }
+            })
+        );
+        assert_eq!(
+            parse_option("option name Analysis Contempt type combo default Both var Off var White var Black var Both"),
+            Some(UciOption {
+                name: "Analysis Contempt".to_string(),
+                kind: UciOptionKind::Combo {
+                    default: "Both".to_string(),
+                    choices: ["Off", "White", "Black", "Both"].map(String::from).to_vec(),
+                }
+            })
+        );
+        assert_eq!(
+            parse_option("option name Debug Log File type string default <empty>")
+                .map(|option| option.kind),
+            Some(UciOptionKind::String {
+                default: String::new()
+            })
+        );
+        assert_eq!(
+            parse_option("option name Clear Hash type button").map(|option| option.kind),
+            Some(UciOptionKind::Button)
+        );
+        assert_eq!(
+            parse_option("option name Threads type spin default 1"),
+            None
+        );
+        assert_eq!(
+            parse_score("info depth 12 score cp -35 nodes 1000"),
+            Some(-35)
+        );
+        assert_eq!(
+            parse_score("info depth 3 score mate 2"),
+            Some(MATE_SCORE - 3)
+        );
+        assert_eq!(parse_score("info string hello"), None);
+    }
+
+    #[test]
+    fn test_uci_state() {
+        let mut state = UciState::default();
+        state.profiles.insert(
+            "Fish".to_string(),
+            BTreeMap::from([
+                ("Threads".to_string(), "4".to_string()),
+                ("Removed".to_string(), "1".to_string()),
+            ]),
+        );
+        for line in [
+            "id name Fish",
+            "option name Threads type spin default 1 min 1 max 64",
+            "option name Ponder type check default false",
+        ] {
+            assert!(state.handle_line(line).is_empty());
+        }
+        assert!(!state.ready);
+        assert_eq!(
+            state.handle_line("uciok"),
+            vec!["setoption name Threads value 4"]
+        );
+        assert_eq!(state.value(&state.options[0]).as_deref(), Some("4"));
+        assert_eq!(state.value(&state.options[1]).as_deref(), Some("false"));
+
+        assert_eq!(
+            state.set_option("Ponder", Some("true".to_string())),
+            "setoption name Ponder value true"
+        );
+        assert_eq!(state.profiles["Fish"]["Ponder"], "true");
+
+        let (sender, receiver) = channel();
+        state.search = Some(ExternalSearch {
+            board: ChessBoard::with_position(&Fen::default()),
+            sender,
+            evaluation: 0,
+        });
+        state.stopped_searches = 1;
+        state.handle_line("bestmove d2d4");
+        state.handle_line("info depth 5 score cp 20");
+        state.handle_line("bestmove e2e4 ponder e7e5");
+        let result = receiver.try_recv().unwrap();
+        assert_eq!(result.evaluation, 20);
+        assert_eq!(
+            result.best_move.map(|piece_move| piece_move.as_algebraic()),
+            Some("e4".to_string())
+        );
+    }
+}
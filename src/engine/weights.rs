@@ -0,0 +1,112 @@
+//! The weights of the engine's evaluation, which are tuned with the `tune` command and loaded
+//! from [WEIGHTS_PATH] when the app starts.
+
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::fairy::fairy_piece;
+use crate::chess_board::{BoardPosition, PieceColor, PieceType, BOARD_SIZE};
+use crate::pawn_structure::{PawnFeature, StructuredPawn};
+
+/// The file the engine reads its evaluation weights from.
+pub const WEIGHTS_PATH: &str = "weights.ron";
+
+/// The value of each piece, the bonus for each piece on each square and for the features of each
+/// pawn, in centipawns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalWeights {
+    /// The value of each piece type, indexed in the order of [PieceType].
+    pub piece_values: [i32; 6],
+    /// The bonus for each piece type on each square, indexed by piece type, rank and file as seen
+    /// by white, so rank 0 is the 8th rank. Black's bonuses are mirrored.
+    pub squares: [[[i32; BOARD_SIZE]; BOARD_SIZE]; 6],
+    /// The bonus for a pawn with each feature, indexed in the order of [PawnFeature], which is
+    /// negative for the weaknesses. Weights tuned before pawn structure was weighed lack them.
+    #[serde(default = "default_pawn_structure")]
+    pub pawn_structure: [i32; 4],
+}
+
+fn default_pawn_structure() -> [i32; 4] {
+    [-10, -15, -10, 20]
+}
+
+impl Default for EvalWeights {
+    /// The weights of material and pawn structure the engine uses until it has been tuned.
+    fn default() -> Self {
+        EvalWeights {
+            piece_values: [0, 900, 300, 300, 500, 100],
+            squares: [[[0; BOARD_SIZE]; BOARD_SIZE]; 6],
+            pawn_structure: default_pawn_structure(),
+        }
+    }
+}
+
+impl EvalWeights {
+    /// Reads the weights from the given file, or returns None if it is missing or invalid.
+    pub fn read(path: &str) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        ron::from_str(&text).ok()
+    }
+
+    /// Returns the rank of the given square as seen by the given color.
+    pub fn relative_rank(position: &BoardPosition, color: &PieceColor) -> usize {
+        match color {
+            PieceColor::White => *position.rank(),
+            PieceColor::Black => BOARD_SIZE - 1 - position.rank(),
+        }
+    }
+
+    /// Returns the value of the given piece on the given square.
+    pub fn value(
+        &self,
+        piece_type: &PieceType,
+        color: &PieceColor,
+        position: &BoardPosition,
+    ) -> i32 {
+        // Fairy pieces are worth the value they were registered with on any square, and there are
+        // no bonuses for the files beyond those of a standard board
+        let Some(index) = piece_type.index() else {
+            return match piece_type {
+                PieceType::Fairy(index) => fairy_piece(*index).map_or(0, |piece| piece.value),
+                _ => 0,
+            };
+        };
+        let rank = Self::relative_rank(position, color);
+        let square = self.squares[index][rank].get(*position.file());
+        self.piece_values[index] + square.copied().unwrap_or_default()
+    }
+
+    /// Returns the bonus for the features of the given pawn.
+    pub fn pawn_value(&self, pawn: &StructuredPawn) -> i32 {
+        PawnFeature::iter()
+            .filter(|feature| pawn.has(*feature))
+            .map(|feature| self.pawn_structure[feature as usize])
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_weights_value() {
+        let mut weights = EvalWeights::default();
+        // A pawn on e7 from white's point of view
+        weights.squares[PieceType::Pawn.index().unwrap()][1][4] = 50;
+        let e7 = BoardPosition::new(1, 4);
+        let e2 = BoardPosition::new(6, 4);
+        assert_eq!(
+            weights.value(&PieceType::Pawn, &PieceColor::White, &e7),
+            150
+        );
+        assert_eq!(
+            weights.value(&PieceType::Pawn, &PieceColor::White, &e2),
+            100
+        );
+        assert_eq!(
+            weights.value(&PieceType::Pawn, &PieceColor::Black, &e2),
+            150
+        );
+    }
+}
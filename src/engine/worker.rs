@@ -0,0 +1,270 @@
+//! Searches for the web build, which cannot start threads, in a web worker.
+//!
+//! The worker loads the same module as the app from `assets/engine_worker.js`, and the two exchange a
+//! [WorkerRequest] for each search and a [WorkerResponse] for each depth searched, as RON text.
+//! The worker searches one depth at a time and reports the result of each, so a search which is
+//! cancelled or runs out of time is stopped by terminating its worker and finishes with the
+//! deepest result reported, as a search on another thread does. The worker has no clock, so the
+//! app keeps the time limit of a search; a limit on the number of positions is not kept.
+
+use serde::{Deserialize, Serialize};
+
+use crate::chess_board::game_log::{fold, GameEvent};
+use crate::chess_board::{ChessBoard, ResetBoardEvent};
+
+use super::search::{search, SearchOptions, SearchResult};
+use super::weights::EvalWeights;
+
+/// A search for the worker to run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct WorkerRequest {
+    /// The event resetting a board to the position, with the moves which led to it.
+    position: ResetBoardEvent,
+    depth: u32,
+    options: SearchOptions,
+    weights: EvalWeights,
+}
+
+/// The result of searching one depth of a [WorkerRequest].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct WorkerResponse {
+    depth: u32,
+    /// Whether this was the last depth to search.
+    done: bool,
+    result: SearchResult,
+}
+
+impl WorkerRequest {
+    pub(super) fn new(
+        board: &ChessBoard,
+        depth: u32,
+        options: SearchOptions,
+        weights: EvalWeights,
+    ) -> Self {
+        WorkerRequest {
+            position: ResetBoardEvent::with_past_moves(board.to_fen(), board.past_moves().clone())
+                .with_variant(*board.variant()),
+            depth: depth.max(1),
+            options,
+            weights,
+        }
+    }
+
+    /// Searches the position to the given depth.
+    fn search(&self, depth: u32) -> SearchResult {
+        let board = fold(&[GameEvent::Reset(self.position.clone())]);
+        search(&board, depth, &self.options, &self.weights)
+    }
+
+    /// Searches each depth in turn, passing the result of each to the given function.
+    fn run(&self, mut report: impl FnMut(WorkerResponse)) {
+        for depth in 1..=self.depth {
+            report(WorkerResponse {
+                depth,
+                done: depth == self.depth,
+                result: self.search(depth),
+            });
+        }
+    }
+}
+
+/// Runs the search of the given request text, posting the text of each response with the given
+/// function. Returns false if the request cannot be read.
+fn handle_request(text: &str, mut post: impl FnMut(String)) -> bool {
+    let Ok(request) = ron::from_str::<WorkerRequest>(text) else {
+        return false;
+    };
+    request.run(|response| {
+        if let Ok(text) = ron::to_string(&response) {
+            post(text);
+        }
+    });
+    true
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(super) use self::browser::{poll_searches, spawn_search};
+
+#[cfg(target_arch = "wasm32")]
+mod browser {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::mpsc::Sender;
+    use std::time::Duration;
+
+    use bevy::log::warn;
+    use bevy::prelude::Res;
+    use bevy::time::Time;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::prelude::wasm_bindgen;
+    use wasm_bindgen::{JsCast, JsValue};
+    use web_sys::{MessageEvent, Worker, WorkerOptions, WorkerType};
+
+    use super::super::search::{SearchCancel, SearchResult};
+    use super::{handle_request, WorkerRequest, WorkerResponse};
+
+    /// The script each worker runs, which loads the app's module and passes it the requests it
+    /// is sent.
+    pub(super) const WORKER_SCRIPT: &str = "assets/engine_worker.js";
+
+    /// Runs the search of the given request, posting each response back to the app with the given
+    /// function. The worker script calls this for each message it is sent.
+    #[wasm_bindgen]
+    pub fn engine_worker_search(request: &str, post: &js_sys::Function) {
+        let read = handle_request(request, |response| {
+            post.call1(&JsValue::NULL, &JsValue::from_str(&response))
+                .ok();
+        });
+        if !read {
+            warn!("The engine worker could not read a search request");
+        }
+    }
+
+    /// A search running in a worker.
+    struct WorkerSearch {
+        worker: Worker,
+        request: WorkerRequest,
+        /// The deepest result the worker has reported, and whether it has finished.
+        latest: Rc<RefCell<(Option<SearchResult>, bool)>>,
+        /// The listener for the worker's responses, kept for as long as the search runs.
+        _listener: Closure<dyn FnMut(MessageEvent)>,
+        sender: Sender<SearchResult>,
+        cancel: SearchCancel,
+        /// The time the search may still run for, if it is limited.
+        time_left: Option<Duration>,
+    }
+
+    thread_local! {
+        static SEARCHES: RefCell<Vec<WorkerSearch>> = RefCell::new(Vec::new());
+        /// The workers which finished their searches, kept so the module is not loaded again
+        /// for every search.
+        static IDLE_WORKERS: RefCell<Vec<Worker>> = RefCell::new(Vec::new());
+    }
+
+    fn new_worker() -> Result<Worker, JsValue> {
+        let options = WorkerOptions::new();
+        // The script imports the app's module, which needs a module worker
+        options.set_type(WorkerType::Module);
+        Worker::new_with_options(WORKER_SCRIPT, &options)
+    }
+
+    /// Starts the search of the given request in a worker, sending the result to the given sender
+    /// once it finishes, runs out of the given time or is cancelled with the given token.
+    pub(in super::super) fn spawn_search(
+        request: WorkerRequest,
+        time: Option<Duration>,
+        sender: Sender<SearchResult>,
+        cancel: SearchCancel,
+    ) {
+        let worker = match IDLE_WORKERS.with(|idle| idle.borrow_mut().pop()) {
+            Some(worker) => Ok(worker),
+            None => new_worker(),
+        };
+        let (worker, text) = match (worker, ron::to_string(&request)) {
+            (Ok(worker), Ok(text)) => (worker, text),
+            _ => {
+                // Searching here freezes the app, but still lets the engine play
+                warn!("Could not start the engine worker, searching on the main thread");
+                sender.send(request.search(request.depth)).ok();
+                return;
+            }
+        };
+        let latest = Rc::new(RefCell::new((None, false)));
+        let reported = latest.clone();
+        let listener = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            let response = event
+                .data()
+                .as_string()
+                .and_then(|text| ron::from_str::<WorkerResponse>(&text).ok());
+            if let Some(response) = response {
+                *reported.borrow_mut() = (Some(response.result), response.done);
+            }
+        });
+        worker.set_onmessage(Some(listener.as_ref().unchecked_ref()));
+        worker.post_message(&JsValue::from_str(&text)).ok();
+        SEARCHES.with(|searches| {
+            searches.borrow_mut().push(WorkerSearch {
+                worker,
+                request,
+                latest,
+                _listener: listener,
+                sender,
+                cancel,
+                time_left: time,
+            })
+        });
+    }
+
+    /// Sends the result of each search which finished, ran out of time or was cancelled.
+    pub(in super::super) fn poll_searches(time: Res<Time>) {
+        SEARCHES.with(|searches| {
+            searches.borrow_mut().retain_mut(|search| {
+                if let Some(time_left) = &mut search.time_left {
+                    *time_left = time_left.saturating_sub(time.delta());
+                }
+                let (result, done) = search.latest.borrow().clone();
+                let stopped =
+                    search.cancel.is_cancelled() || search.time_left == Some(Duration::ZERO);
+                if !done && !stopped {
+                    return true;
+                }
+                if done {
+                    search.worker.set_onmessage(None);
+                    IDLE_WORKERS.with(|idle| idle.borrow_mut().push(search.worker.clone()));
+                } else {
+                    // The worker cannot be told to stop while it searches, so it is replaced
+                    search.worker.terminate();
+                }
+                // A search stopped before its first depth was reported finishes with one here,
+                // as a search on another thread always searches the first depth
+                let result = result.unwrap_or_else(|| search.request.search(1));
+                search.sender.send(result).ok();
+                false
+            })
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::chess_board::BoardPosition;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_worker_request() {
+        // Black mates at once with Qh4, which the worker finds from the first depth
+        let mut board = ChessBoard::with_position(&Fen::default());
+        for text in ["f3", "e5", "g4"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let request =
+            WorkerRequest::new(&board, 2, SearchOptions::default(), EvalWeights::default());
+        let mut responses = Vec::new();
+        let text = ron::to_string(&request).unwrap();
+        assert!(handle_request(&text, |response| responses.push(response)));
+        let responses: Vec<WorkerResponse> = responses
+            .iter()
+            .map(|text| ron::from_str(text).unwrap())
+            .collect();
+        assert_eq!(
+            responses
+                .iter()
+                .map(|response| response.depth)
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert!(responses[1].done && !responses[0].done);
+        let mate = BoardPosition::from_algebraic("h4");
+        for response in &responses {
+            assert_eq!(response.result.best_move.map(|best| *best.to()), mate);
+        }
+        assert_eq!(
+            responses[1].result,
+            search(&board, 2, &request.options, &request.weights)
+        );
+        assert!(!handle_request("not a request", |_| {}));
+    }
+}
@@ -0,0 +1,765 @@
+//! A headless match between two engine configurations, for checking whether a change to the
+//! engine makes it stronger.
+//!
+//! The match is run with `chess_computer match [options]` and plays each opening of
+//! [OPENINGS] twice, once with each engine as white, until the sequential probability ratio test
+//! (SPRT) accepts one of its hypotheses or the maximum number of games has been played. The games
+//! and the results, including the Elo difference with its 95% confidence interval, are written to
+//! the output directory. The options are:
+//!
+//! - `--depth-a <plies>` and `--depth-b <plies>` set the search depths of the engines.
+//! - `--disable-a <heuristics>` and `--disable-b <heuristics>` turn off a comma separated list of
+//!   the search heuristics of [HEURISTICS] for each engine, e.g. `--disable-a null-move,lmr`.
+//! - `--weights-a <file>` and `--weights-b <file>` load evaluation weights written by the `tune`
+//!   command for each engine, which otherwise use the default weights.
+//! - `--games <number>` sets the maximum number of games.
+//! - `--elo0 <elo>` and `--elo1 <elo>` set the Elo differences of engine A over engine B under
+//!   the null and alternative hypotheses of the SPRT.
+//! - `--output <directory>` sets the directory the results are written to.
+//! - `--random-start <seed>` plays each pair of games from a balanced
+//!   [random position](crate::start_position) instead of an opening, generated from the seed for
+//!   the first pair and from the following seeds for the rest.
+//!
+//! Games are [adjudicated](Adjudication) so every game finishes with a result, by the options:
+//!
+//! - `--resign-score <centipawns>` and `--resign-moves <moves>` resign for an engine once both
+//!   engines have evaluated its position as lost by at least the score for the number of moves
+//!   each. The score can be `off`.
+//! - `--draw-score <centipawns>`, `--draw-moves <moves>` and `--draw-from <move>` draw the game
+//!   once both engines have evaluated it within the score for the number of moves each, from the
+//!   given move number on. The score can be `off`.
+//! - `--max-plies <plies>` draws the game once it has lasted the number of plies.
+//! - `--known-endings <on|off>` ends the game as won once a lone king faces a king and queen or
+//!   rook whose side is to move. There are no tablebases to adjudicate other endings by.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameEndStatus, PieceColor, PieceType};
+use crate::engine::{best_move_with_evaluation, EvalWeights, SearchOptions};
+use crate::fen::Fen;
+use crate::pgn::write_pgn;
+use crate::start_position;
+
+/// The opening lines the games start from, each of which leaves a roughly level position.
+pub const OPENINGS: [&str; 12] = [
+    "e4 e5 Nf3 Nc6 Bb5 a6",
+    "e4 e5 Nf3 Nc6 Bc4 Bc5",
+    "e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6",
+    "e4 e6 d4 d5 Nc3 Nf6",
+    "e4 c6 d4 d5 Nc3 dxe4 Nxe4 Bf5",
+    "e4 d5 exd5 Qxd5 Nc3 Qa5",
+    "d4 d5 c4 e6 Nc3 Nf6",
+    "d4 d5 c4 c6 Nf3 Nf6",
+    "d4 Nf6 c4 g6 Nc3 Bg7 e4 d6",
+    "d4 Nf6 c4 e6 Nc3 Bb4",
+    "c4 e5 Nc3 Nf6 Nf3 Nc6",
+    "Nf3 d5 g3 Nf6 Bg2 e6",
+];
+
+/// The names of the search heuristics which can be disabled.
+const HEURISTICS: [&str; 4] = ["null-move", "lmr", "killers", "history"];
+
+/// Returns the setting of the heuristic with the given name from [HEURISTICS].
+fn heuristic<'a>(options: &'a mut SearchOptions, name: &str) -> Option<&'a mut bool> {
+    match name {
+        "null-move" => Some(&mut options.null_move_pruning),
+        "lmr" => Some(&mut options.late_move_reductions),
+        "killers" => Some(&mut options.killer_moves),
+        "history" => Some(&mut options.history_heuristic),
+        _ => None,
+    }
+}
+
+/// The settings of one of the engines in a match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineSettings {
+    /// The search depth in plies.
+    depth: u32,
+    options: SearchOptions,
+    weights: EvalWeights,
+    /// The file the weights were loaded from, or None for the default weights.
+    weights_path: Option<String>,
+}
+
+impl EngineSettings {
+    pub fn new(depth: u32) -> Self {
+        EngineSettings {
+            depth,
+            options: SearchOptions::default(),
+            weights: EvalWeights::default(),
+            weights_path: None,
+        }
+    }
+
+    /// Loads the evaluation weights from the given file.
+    /// Returns None if the file is missing or does not hold weights.
+    pub fn load_weights(&mut self, path: &str) -> Option<()> {
+        self.weights = EvalWeights::read(path)?;
+        self.weights_path = Some(path.to_string());
+        Some(())
+    }
+
+    /// Turns off the heuristics in the given comma separated list of names.
+    /// Returns None if any of the names is not in [HEURISTICS].
+    pub fn disable(&mut self, names: &str) -> Option<()> {
+        for name in names.split(',') {
+            *heuristic(&mut self.options, name.trim())? = false;
+        }
+        Some(())
+    }
+
+    /// Returns the name of the engine with the given label, e.g.
+    /// "Engine A (depth 2, no lmr, weights.ron)".
+    pub fn name(&self, label: &str) -> String {
+        let mut options = self.options;
+        let disabled: String = HEURISTICS
+            .iter()
+            .filter(|name| heuristic(&mut options, name).is_some_and(|enabled| !*enabled))
+            .map(|name| format!(", no {}", name))
+            .chain(self.weights_path.iter().map(|path| format!(", {}", path)))
+            .collect();
+        format!("Engine {} (depth {}{})", label, self.depth, disabled)
+    }
+}
+
+/// The number of plies after which a game is adjudicated as a draw by default.
+const MAX_PLIES: usize = 300;
+
+/// The number of times a position is repeated before the game is adjudicated as a draw.
+const REPETITIONS: u32 = 3;
+
+/// The z-score of a two-sided 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+/// The rules ending the games the engines would otherwise play on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Adjudication {
+    /// The evaluation in centipawns at or beyond which a position is lost, or None if the engines
+    /// never resign.
+    resign_score: Option<i32>,
+    /// The number of consecutive moves of each engine the position must be lost for.
+    resign_moves: usize,
+    /// The largest evaluation in centipawns, either way, of a drawn position, or None if draws
+    /// are not adjudicated by the evaluation.
+    draw_score: Option<i32>,
+    /// The number of consecutive moves of each engine the position must be drawn for.
+    draw_moves: usize,
+    /// The move number from which draws are adjudicated by the evaluation.
+    draw_from: i32,
+    /// The number of plies after which the game is drawn.
+    max_plies: usize,
+    /// Whether the endings known to be won without a tablebase are adjudicated.
+    known_endings: bool,
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Adjudication {
+            resign_score: Some(1000),
+            resign_moves: 3,
+            draw_score: Some(10),
+            draw_moves: 8,
+            draw_from: 40,
+            max_plies: MAX_PLIES,
+            known_endings: true,
+        }
+    }
+}
+
+/// Why a game was adjudicated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Adjudicated {
+    /// Both engines evaluated the position as lost for the given color.
+    Resignation(PieceColor),
+    /// Both engines evaluated the position as drawn.
+    Draw,
+    /// The position is an ending known to be won by the given color.
+    KnownEnding(PieceColor),
+    /// The game lasted the most plies allowed.
+    MoveLimit,
+}
+
+impl Adjudicated {
+    /// Ends the game on the given board with the result of the adjudication.
+    fn end(&self, board: &mut ChessBoard) {
+        match self {
+            Adjudicated::Resignation(loser) => {
+                board.end_game(GameEndStatus::Resignation, Some(loser.opposite()))
+            }
+            Adjudicated::KnownEnding(winner) => {
+                board.end_game(GameEndStatus::Resignation, Some(*winner))
+            }
+            Adjudicated::Draw | Adjudicated::MoveLimit => {
+                board.end_game(GameEndStatus::Agreement, None)
+            }
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Adjudicated::Resignation(_) => "resignation",
+            Adjudicated::Draw => "draw",
+            Adjudicated::KnownEnding(_) => "known ending",
+            Adjudicated::MoveLimit => "move limit",
+        }
+    }
+}
+
+/// Returns the winner of the position on the given board if it is a lone king against a king
+/// and queen or rook whose side is to move, which always wins.
+fn known_ending_winner(board: &ChessBoard) -> Option<PieceColor> {
+    let color = (*board.active_color())?;
+    let mut pieces = board.size().positions().filter_map(|position| {
+        let piece_type = board.get_piece_type(&position)?;
+        (piece_type != PieceType::King).then(|| (board.get_piece_color(&position), piece_type))
+    });
+    let (piece_color, piece_type) = pieces.next()?;
+    let won = matches!(piece_type, PieceType::Queen | PieceType::Rook);
+    (pieces.next().is_none() && won && piece_color == Some(color)).then_some(color)
+}
+
+impl Adjudication {
+    /// Returns how the game on the given board is adjudicated, given the evaluations of each of
+    /// its moves the engines have searched from white's point of view, or None if it plays on.
+    fn adjudicate(&self, board: &ChessBoard, evaluations: &[i32]) -> Option<Adjudicated> {
+        // Both engines must agree, so the last moves of each are checked
+        let recent = |moves: usize| {
+            let plies = moves.max(1) * 2;
+            (evaluations.len() >= plies).then(|| &evaluations[evaluations.len() - plies..])
+        };
+        if let Some(winner) = known_ending_winner(board).filter(|_| self.known_endings) {
+            return Some(Adjudicated::KnownEnding(winner));
+        }
+        if let (Some(score), Some(recent)) = (self.resign_score, recent(self.resign_moves)) {
+            if recent.iter().all(|evaluation| *evaluation <= -score) {
+                return Some(Adjudicated::Resignation(PieceColor::White));
+            }
+            if recent.iter().all(|evaluation| *evaluation >= score) {
+                return Some(Adjudicated::Resignation(PieceColor::Black));
+            }
+        }
+        if let (Some(score), Some(recent)) = (self.draw_score, recent(self.draw_moves)) {
+            let drawn = recent.iter().all(|evaluation| evaluation.abs() <= score);
+            if drawn && *board.move_number() >= self.draw_from {
+                return Some(Adjudicated::Draw);
+            }
+        }
+        (board.past_moves().len() >= self.max_plies).then_some(Adjudicated::MoveLimit)
+    }
+}
+
+/// Parses the given score in centipawns, which is None for `off`.
+fn parse_score(value: &str) -> Option<Option<i32>> {
+    match value {
+        "off" => Some(None),
+        _ => value.parse().ok().map(Some),
+    }
+}
+
+/// The settings of a match.
+#[derive(Debug, Clone, PartialEq)]
+struct MatchOptions {
+    /// The settings of engines A and B.
+    engines: [EngineSettings; 2],
+    /// The maximum number of games to play.
+    games: u32,
+    sprt: Sprt,
+    /// The directory the results and games are written to.
+    output: PathBuf,
+    /// The seed of the random position the first pair of games starts from, if the games start
+    /// from random positions rather than the openings.
+    random_start: Option<u64>,
+    adjudication: Adjudication,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        MatchOptions {
+            engines: [EngineSettings::new(2), EngineSettings::new(1)],
+            games: 200,
+            sprt: Sprt::default(),
+            output: PathBuf::from("match"),
+            random_start: None,
+            adjudication: Adjudication::default(),
+        }
+    }
+}
+
+impl MatchOptions {
+    /// Reads the options from the given command line arguments.
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = MatchOptions::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}.", flag))?;
+            let invalid = || format!("Invalid value for {}: {}.", flag, value);
+            match flag.as_str() {
+                "--depth-a" => options.engines[0].depth = value.parse().map_err(|_| invalid())?,
+                "--depth-b" => options.engines[1].depth = value.parse().map_err(|_| invalid())?,
+                "--disable-a" => options.engines[0].disable(value).ok_or_else(invalid)?,
+                "--disable-b" => options.engines[1].disable(value).ok_or_else(invalid)?,
+                "--weights-a" => options.engines[0].load_weights(value).ok_or_else(invalid)?,
+                "--weights-b" => options.engines[1].load_weights(value).ok_or_else(invalid)?,
+                "--games" => options.games = value.parse().map_err(|_| invalid())?,
+                "--elo0" => options.sprt.elo0 = value.parse().map_err(|_| invalid())?,
+                "--elo1" => options.sprt.elo1 = value.parse().map_err(|_| invalid())?,
+                "--output" => options.output = PathBuf::from(value),
+                "--random-start" => {
+                    options.random_start = Some(value.parse().map_err(|_| invalid())?)
+                }
+                "--resign-score" => {
+                    options.adjudication.resign_score = parse_score(value).ok_or_else(invalid)?
+                }
+                "--resign-moves" => {
+                    options.adjudication.resign_moves = value.parse().map_err(|_| invalid())?
+                }
+                "--draw-score" => {
+                    options.adjudication.draw_score = parse_score(value).ok_or_else(invalid)?
+                }
+                "--draw-moves" => {
+                    options.adjudication.draw_moves = value.parse().map_err(|_| invalid())?
+                }
+                "--draw-from" => {
+                    options.adjudication.draw_from = value.parse().map_err(|_| invalid())?
+                }
+                "--max-plies" => {
+                    options.adjudication.max_plies = value.parse().map_err(|_| invalid())?
+                }
+                "--known-endings" => {
+                    options.adjudication.known_endings = match value.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(format!("Unknown option: {}.", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// The results of a match from the point of view of engine A.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct MatchScore {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl MatchScore {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the mean score per game, counting a draw as half a point.
+    fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.) / self.games() as f64
+    }
+
+    /// Returns the variance of the score of a single game.
+    fn variance(&self) -> f64 {
+        let score = self.score();
+        let games = self.games() as f64;
+        (self.wins as f64 * (1. - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games
+    }
+
+    /// Returns the Elo difference of engine A over engine B.
+    fn elo(&self) -> f64 {
+        elo(self.score())
+    }
+
+    /// Returns the lower and upper bounds of the 95% confidence interval of the Elo difference.
+    fn elo_interval(&self) -> (f64, f64) {
+        let error = Z_95 * (self.variance() / self.games() as f64).sqrt();
+        (elo(self.score() - error), elo(self.score() + error))
+    }
+
+    /// Records the result of a game in which engine A played the given color.
+    fn record(&mut self, board: &ChessBoard, engine_a: PieceColor) {
+        match board.winner() {
+            Some(winner) if *winner == engine_a => self.wins += 1,
+            Some(_) => self.losses += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+/// Returns the Elo difference corresponding to the given expected score.
+fn elo(score: f64) -> f64 {
+    -400. * (1. / score - 1.).log10()
+}
+
+/// Returns the expected score corresponding to the given Elo difference.
+pub fn expected_score(elo: f64) -> f64 {
+    1. / (1. + 10f64.powf(-elo / 400.))
+}
+
+/// The hypotheses and error rates of a sequential probability ratio test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sprt {
+    /// The Elo difference under the null hypothesis.
+    elo0: f64,
+    /// The Elo difference under the alternative hypothesis.
+    elo1: f64,
+    /// The probability of accepting the alternative hypothesis when the null hypothesis is true.
+    alpha: f64,
+    /// The probability of accepting the null hypothesis when the alternative hypothesis is true.
+    beta: f64,
+}
+
+impl Default for Sprt {
+    fn default() -> Self {
+        Sprt {
+            elo0: 0.,
+            elo1: 50.,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+}
+
+impl Sprt {
+    /// Returns the bounds of the log-likelihood ratio at which the null and alternative
+    /// hypotheses are accepted.
+    fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1. - self.alpha)).ln(),
+            ((1. - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Returns the log-likelihood ratio of the alternative hypothesis over the null hypothesis
+    /// given the score, using the normal approximation of the generalised SPRT.
+    fn llr(&self, score: &MatchScore) -> f64 {
+        let variance = score.variance();
+        if score.games() == 0 || variance == 0. {
+            return 0.;
+        }
+        let (score0, score1) = (expected_score(self.elo0), expected_score(self.elo1));
+        score.games() as f64 * (score1 - score0) * (2. * score.score() - score0 - score1)
+            / (2. * variance)
+    }
+
+    /// Returns whether the alternative hypothesis has been accepted, or None if the test has not
+    /// yet finished.
+    fn verdict(&self, score: &MatchScore) -> Option<bool> {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            Some(false)
+        } else if llr >= upper {
+            Some(true)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the board after the moves of the given opening.
+pub fn opening_board(opening: &str) -> ChessBoard {
+    let mut board = ChessBoard::with_position(&Fen::default());
+    for text in opening.split_whitespace() {
+        let piece_move = Move::from_algebraic(text, &board)
+            .unwrap_or_else(|| panic!("Illegal move {} in opening {}.", text, opening));
+        board.apply_move(&piece_move);
+    }
+    board
+}
+
+/// Plays a game from the given board between the given engines for white and black, returning
+/// the board once the game has ended, and why it was adjudicated if it was.
+pub fn play_game(
+    mut board: ChessBoard,
+    engines: [&EngineSettings; 2],
+    adjudication: &Adjudication,
+) -> (ChessBoard, Option<Adjudicated>) {
+    let mut repetitions: HashMap<String, u32> = HashMap::new();
+    // The evaluation of each move searched, from white's point of view
+    let mut evaluations = Vec::new();
+    while let Some(color) = *board.active_color() {
+        // The position without the move counters
+        let fen = board.to_fen().to_string();
+        let position = fen.rsplitn(3, ' ').nth(2).unwrap_or(&fen).to_string();
+        let count = repetitions.entry(position).or_default();
+        *count += 1;
+        if !board.has_mating_material(&PieceColor::White)
+            && !board.has_mating_material(&PieceColor::Black)
+        {
+            board.end_game(GameEndStatus::DeadPosition, None);
+        } else if *count >= REPETITIONS {
+            // The engines do not claim draws, so the match agrees them on their behalf
+            board.end_game(GameEndStatus::Agreement, None);
+        } else if let Some(adjudicated) = adjudication.adjudicate(&board, &evaluations) {
+            adjudicated.end(&mut board);
+            return (board, Some(adjudicated));
+        } else {
+            let engine = match color {
+                PieceColor::White => engines[0],
+                PieceColor::Black => engines[1],
+            };
+            match best_move_with_evaluation(&board, engine.depth, &engine.options, &engine.weights)
+            {
+                Some((piece_move, evaluation)) => {
+                    evaluations.push(match color {
+                        PieceColor::White => evaluation,
+                        PieceColor::Black => -evaluation,
+                    });
+                    board.apply_move(&piece_move);
+                }
+                None if board.in_check(&color) => {
+                    board.end_game(GameEndStatus::Checkmate, Some(color.opposite()))
+                }
+                None => board.end_game(GameEndStatus::Stalemate, None),
+            }
+        }
+    }
+    (board, None)
+}
+
+/// Returns a summary of the match so far, in which the given number of games were adjudicated.
+fn summary(options: &MatchOptions, score: &MatchScore, adjudicated: u32) -> String {
+    let (lower, upper) = score.elo_interval();
+    let (llr_lower, llr_upper) = options.sprt.bounds();
+    let verdict = match options.sprt.verdict(score) {
+        Some(true) => "H1 accepted",
+        Some(false) => "H0 accepted",
+        None => "inconclusive",
+    };
+    format!(
+        "{} vs {}\n\
+        Games: {}, wins: {}, draws: {}, losses: {}, adjudicated: {}\n\
+        Score: {:.1}%\n\
+        Elo difference: {:+.1} (95% CI {:+.1} to {:+.1})\n\
+        SPRT: elo0 {}, elo1 {}, LLR {:.2} ({:.2}, {:.2}), {}\n",
+        options.engines[0].name("A"),
+        options.engines[1].name("B"),
+        score.games(),
+        score.wins,
+        score.draws,
+        score.losses,
+        adjudicated,
+        score.score() * 100.,
+        score.elo(),
+        lower,
+        upper,
+        options.sprt.elo0,
+        options.sprt.elo1,
+        options.sprt.llr(score),
+        llr_lower,
+        llr_upper,
+        verdict
+    )
+}
+
+/// Runs the match with the given command line arguments, writing the results and games to the
+/// output directory.
+#[cfg(not(tarpaulin_include))]
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = MatchOptions::parse(args)?;
+    let names = [options.engines[0].name("A"), options.engines[1].name("B")];
+    let mut score = MatchScore::default();
+    let mut adjudicated_games = 0;
+    let mut pgn = String::new();
+    for game in 0..options.games {
+        let pair = game / 2;
+        let (start, board) = match options.random_start {
+            Some(seed) => {
+                let seed = seed.wrapping_add(pair as u64);
+                let fen = start_position::generate(seed)
+                    .ok_or_else(|| format!("No balanced position found for seed {}.", seed))?;
+                let board = ChessBoard::with_position(&fen);
+                (fen, board)
+            }
+            None => (
+                Fen::default(),
+                opening_board(OPENINGS[pair as usize % OPENINGS.len()]),
+            ),
+        };
+        // Engine A plays white in even games and black in odd games
+        let (engine_a, engines, players) = if game % 2 == 0 {
+            let [a, b] = &options.engines;
+            (PieceColor::White, [a, b], [&names[0], &names[1]])
+        } else {
+            let [a, b] = &options.engines;
+            (PieceColor::Black, [b, a], [&names[1], &names[0]])
+        };
+        let (board, adjudicated) = play_game(board, engines, &options.adjudication);
+        score.record(&board, engine_a);
+        if adjudicated.is_some() {
+            adjudicated_games += 1;
+        }
+        pgn.push_str(&write_pgn(
+            &start,
+            board.past_moves(),
+            &board,
+            players.map(String::as_str),
+            &None,
+            None,
+        ));
+        pgn.push('\n');
+        println!(
+            "Game {}{}: {:.1}% after {} games, LLR {:.2}",
+            game + 1,
+            adjudicated.map_or(String::new(), |adjudicated| format!(
+                " (adjudicated, {})",
+                adjudicated.description()
+            )),
+            score.score() * 100.,
+            score.games(),
+            options.sprt.llr(&score)
+        );
+        if options.sprt.verdict(&score).is_some() {
+            break;
+        }
+    }
+
+    let summary = summary(&options, &score, adjudicated_games);
+    print!("{}", summary);
+    let write_error = |error: std::io::Error| format!("Unable to write results: {}.", error);
+    std::fs::create_dir_all(&options.output).map_err(write_error)?;
+    std::fs::write(options.output.join("results.txt"), summary).map_err(write_error)?;
+    std::fs::write(options.output.join("games.pgn"), pgn).map_err(write_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_match_options() {
+        let args = [
+            "--depth-a",
+            "3",
+            "--disable-b",
+            "null-move, killers",
+            "--games",
+            "20",
+            "--elo1",
+            "25",
+            "--output",
+            "out",
+            "--random-start",
+            "42",
+            "--resign-score",
+            "off",
+            "--draw-from",
+            "30",
+        ]
+        .map(String::from);
+        let options = MatchOptions::parse(&args).unwrap();
+        assert_eq!(options.engines[0], EngineSettings::new(3));
+        assert_eq!(
+            options.engines[1].name("B"),
+            "Engine B (depth 1, no null-move, no killers)"
+        );
+        assert_eq!(options.games, 20);
+        assert_eq!(options.sprt.elo1, 25.);
+        assert_eq!(options.output, PathBuf::from("out"));
+        assert_eq!(options.random_start, Some(42));
+        assert_eq!(options.adjudication.resign_score, None);
+        assert_eq!(options.adjudication.draw_from, 30);
+
+        assert!(MatchOptions::parse(&["--games".to_string()]).is_err());
+        assert!(MatchOptions::parse(&["--depth-a", "deep"].map(String::from)).is_err());
+        assert!(MatchOptions::parse(&["--disable-a", "luck"].map(String::from)).is_err());
+        assert!(MatchOptions::parse(&["--weights-b", "missing.ron"].map(String::from)).is_err());
+        assert!(MatchOptions::parse(&["--speed", "1"].map(String::from)).is_err());
+        assert!(MatchOptions::parse(&["--known-endings", "maybe"].map(String::from)).is_err());
+    }
+
+    #[test]
+    fn test_match_statistics() {
+        let even = MatchScore {
+            wins: 30,
+            draws: 40,
+            losses: 30,
+        };
+        assert_eq!(even.score(), 0.5);
+        assert!(even.elo().abs() < 1e-9);
+        let (lower, upper) = even.elo_interval();
+        assert!(lower < 0. && upper > 0.);
+        assert!((lower + upper).abs() < 1e-9);
+
+        // A 64% score is about 100 Elo
+        let better = MatchScore {
+            wins: 50,
+            draws: 28,
+            losses: 22,
+        };
+        assert!((better.elo() - 100.).abs() < 1.);
+
+        let sprt = Sprt::default();
+        assert!(sprt.llr(&even) < 0.);
+        assert_eq!(sprt.verdict(&MatchScore::default()), None);
+        let strong = MatchScore {
+            wins: 150,
+            draws: 30,
+            losses: 20,
+        };
+        assert_eq!(sprt.verdict(&strong), Some(true));
+        let level = MatchScore {
+            wins: 300,
+            draws: 400,
+            losses: 300,
+        };
+        assert_eq!(sprt.verdict(&level), Some(false));
+    }
+
+    #[test]
+    fn test_play_game() {
+        for opening in OPENINGS {
+            assert!(opening_board(opening).active_color().is_some());
+        }
+        let adjudication = Adjudication::default();
+        let (board, _) = play_game(
+            opening_board(OPENINGS[0]),
+            [&EngineSettings::new(1); 2],
+            &adjudication,
+        );
+        assert!(board.game_end_status().is_some());
+        assert!(board.past_moves().len() <= MAX_PLIES);
+    }
+
+    #[test]
+    fn test_adjudication() {
+        let adjudication = Adjudication::default();
+        let board = opening_board(OPENINGS[0]);
+        assert_eq!(adjudication.adjudicate(&board, &[]), None);
+        // Both engines must agree the position is lost
+        let lost = [-1200; 6];
+        assert_eq!(
+            adjudication.adjudicate(&board, &lost),
+            Some(Adjudicated::Resignation(PieceColor::White))
+        );
+        assert_eq!(
+            adjudication.adjudicate(&board, &[-1200, 0, -1200, 0, -1200, 0]),
+            None
+        );
+        // Draws are only adjudicated from the draw move number on
+        assert_eq!(adjudication.adjudicate(&board, &[0; 16]), None);
+        let late =
+            ChessBoard::with_position(&Fen::from_string("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 45"));
+        assert_eq!(
+            adjudication.adjudicate(&late, &[0; 16]),
+            Some(Adjudicated::Draw)
+        );
+
+        let rook = ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"));
+        assert_eq!(
+            adjudication.adjudicate(&rook, &[]),
+            Some(Adjudicated::KnownEnding(PieceColor::White))
+        );
+        // With the lone king to move the rook could be hanging
+        let rook = ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"));
+        assert_eq!(adjudication.adjudicate(&rook, &[]), None);
+    }
+}
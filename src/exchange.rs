@@ -0,0 +1,198 @@
+//! Static exchange evaluation (SEE), which finds the material won or lost on a square when both
+//! players capture on it in turn with their least valuable piece, each stopping when capturing
+//! no longer pays.
+//!
+//! Pins are ignored, so the result is an estimate, but it is enough to tell when a move leaves
+//! material en prise.
+
+use crate::chess_board::fairy::fairy_piece;
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType};
+
+/// A piece and the square it is on.
+pub type Target = (PieceType, BoardPosition);
+
+/// Returns the usual value of the given piece in pawns, with the king worth more than any other.
+pub fn value(piece_type: &PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn => 1,
+        PieceType::Knight | PieceType::Bishop => 3,
+        PieceType::Rook => 5,
+        PieceType::Queen => 9,
+        PieceType::King => 100,
+        // Rounded to the nearest pawn
+        PieceType::Fairy(index) => fairy_piece(*index).map_or(0, |piece| (piece.value + 50) / 100),
+    }
+}
+
+/// Returns the material balance of the given position in pawns, which is positive when white has
+/// more material and negative when black has. Kings are not counted.
+pub fn material_balance(board: &ChessBoard) -> i32 {
+    let mut balance = 0;
+    for position in board.size().positions() {
+        let (Some(piece_type), Some(color)) = (
+            board.get_piece_type(&position),
+            board.get_piece_color(&position),
+        ) else {
+            continue;
+        };
+        if piece_type == PieceType::King {
+            continue;
+        }
+        balance += match color {
+            PieceColor::White => value(&piece_type),
+            PieceColor::Black => -value(&piece_type),
+        };
+    }
+    balance
+}
+
+/// Returns the material the given color wins by capturing the piece on the given square, and
+/// exchanging on it for as long as that pays, or zero if it should not capture.
+fn exchange(board: &ChessBoard, position: &BoardPosition, color: &PieceColor) -> i32 {
+    let Some(captured) = board.get_piece_type(position) else {
+        return 0;
+    };
+    let Some(attacker) = board
+        .attackers(position, color)
+        .into_iter()
+        .min_by_key(|attacker| board.get_piece_type(attacker).map(|piece| value(&piece)))
+    else {
+        return 0;
+    };
+    // Capturing the king ends the exchange
+    if captured == PieceType::King {
+        return value(&captured);
+    }
+    let mut after = board.clone();
+    after.apply_move(&Move::from_board(attacker, *position, board));
+    (value(&captured) - exchange(&after, position, &color.opposite())).max(0)
+}
+
+/// Returns the material the given move wins, less the material lost when the opponent exchanges
+/// on its destination square.
+pub fn see(board: &ChessBoard, piece_move: &Move) -> i32 {
+    let gain = board
+        .get_piece_type(piece_move.to())
+        .map_or(0, |piece| value(&piece));
+    let mut after = board.clone();
+    after.apply_move(piece_move);
+    gain - exchange(
+        &after,
+        piece_move.to(),
+        &piece_move.piece_color().opposite(),
+    )
+}
+
+/// Returns the piece the player loses by making the given move, either the moved piece itself or
+/// another piece the move leaves en prise, or None if the move loses no material. Where several
+/// pieces are lost, the one costing the most material is returned.
+pub fn material_lost(board: &ChessBoard, piece_move: &Move) -> Option<Target> {
+    let color = *piece_move.piece_color();
+    let gain = board
+        .get_piece_type(piece_move.to())
+        .map_or(0, |piece| value(&piece));
+    let mut after = board.clone();
+    after.apply_move(piece_move);
+    let checkmate = after
+        .past_moves()
+        .last()
+        .is_some_and(|last_move| last_move.is_check())
+        && after.legal_moves_iter().next().is_none();
+    if checkmate {
+        return None;
+    }
+
+    let moved = (*piece_move.piece_type(), *piece_move.to());
+    let mut losses = vec![(moved, -see(board, piece_move))];
+    for position in after.size().positions() {
+        let Some(piece_type) = after
+            .get_piece_type(&position)
+            .filter(|_| after.get_piece_color(&position) == Some(color))
+        else {
+            continue;
+        };
+        if position == *piece_move.to() {
+            continue;
+        }
+        let loss = exchange(&after, &position, &color.opposite());
+        // Only pieces the move puts en prise count, not those which were already
+        if loss > exchange(board, &position, &color.opposite()) {
+            losses.push(((piece_type, position), loss - gain));
+        }
+    }
+    losses
+        .into_iter()
+        .filter(|(_, loss)| *loss > 0)
+        .max_by_key(|(_, loss)| *loss)
+        .map(|(target, _)| target)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    fn board(fen: &str) -> ChessBoard {
+        ChessBoard::with_position(&Fen::from_string(fen))
+    }
+
+    fn square(text: &str) -> BoardPosition {
+        BoardPosition::from_algebraic(text).unwrap()
+    }
+
+    #[test]
+    fn test_material_balance() {
+        assert_eq!(
+            material_balance(&ChessBoard::with_position(&Fen::default())),
+            0
+        );
+        // White has a rook against a pawn, and black a queen against a knight
+        assert_eq!(
+            material_balance(&board("4k3/p7/8/8/8/8/8/R3K3 w - - 0 1")),
+            4
+        );
+        assert_eq!(
+            material_balance(&board("3qk3/8/8/8/8/8/8/2N1K3 w - - 0 1")),
+            -6
+        );
+    }
+
+    #[test]
+    fn test_see() {
+        let undefended = board("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1");
+        let capture = Move::from_algebraic("Qxd5", &undefended).unwrap();
+        assert_eq!(see(&undefended, &capture), 1);
+        assert_eq!(material_lost(&undefended, &capture), None);
+
+        // The pawn is defended, so taking it loses the queen for a pawn
+        let defended = board("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1");
+        let capture = Move::from_algebraic("Qxd5", &defended).unwrap();
+        assert_eq!(see(&defended, &capture), -8);
+        assert_eq!(
+            material_lost(&defended, &capture),
+            Some((PieceType::Queen, square("d5")))
+        );
+
+        // Rooks doubled on the file win the defended pawn
+        let doubled = board("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1");
+        let capture = Move::from_algebraic("Rxd5", &doubled).unwrap();
+        assert_eq!(see(&doubled, &capture), 1);
+    }
+
+    #[test]
+    fn test_material_lost() {
+        // Pushing the pawn leaves the knight it defended to the bishop
+        let position = board("4k3/8/8/8/3b4/2N5/1P6/4K3 w - - 0 1");
+        let push = Move::from_algebraic("b4", &position).unwrap();
+        assert_eq!(
+            material_lost(&position, &push),
+            Some((PieceType::Knight, square("c3")))
+        );
+
+        let start = board("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let develop = Move::from_algebraic("Nf3", &start).unwrap();
+        assert_eq!(material_lost(&start, &develop), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Explanations of how a game ended, which show a checkmate as the pieces giving check and what
+//! stops the king escaping to each square around it, and a stalemate as why each move the
+//! stalemated player could try would be illegal.
+//!
+//! They are worked out from the attacks on the final position, so only games of standard chess,
+//! where kings can be in check, are explained.
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{
+    BoardPosition, CastleFailure, ChessBoard, GameEndStatus, PieceColor, Variant,
+};
+
+/// Why the king cannot move to a square next to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flight {
+    /// One of the king's own pieces stands there.
+    Occupied,
+    /// The enemy pieces on the given squares would attack the king there.
+    Covered(Vec<BoardPosition>),
+}
+
+/// Why a move would be illegal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Illegality {
+    /// The enemy pieces on the given squares would attack the king after it.
+    Exposes(Vec<BoardPosition>),
+    /// The move is a castle which fails the given condition.
+    Castle(CastleFailure),
+}
+
+/// How a game ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Explanation {
+    Checkmate {
+        /// The square of the mated king.
+        king: BoardPosition,
+        /// The squares of the pieces giving check.
+        checkers: Vec<BoardPosition>,
+        /// Each square around the king which is on the board, with why the king cannot go there.
+        flights: Vec<(BoardPosition, Flight)>,
+    },
+    Stalemate {
+        /// The square of the stalemated king.
+        king: BoardPosition,
+        /// Each move the stalemated player's pieces could make, with why it would be illegal.
+        moves: Vec<(Move, Illegality)>,
+        /// The squares of the pieces which have no moves at all.
+        immobile: Vec<BoardPosition>,
+    },
+}
+
+/// Returns the squares next to the given one which are on the board.
+fn neighbours(board: &ChessBoard, position: &BoardPosition) -> Vec<BoardPosition> {
+    let (rank, file) = (*position.rank() as i32, *position.file() as i32);
+    (-1..=1)
+        .flat_map(|rank_step| (-1..=1).map(move |file_step| (rank + rank_step, file + file_step)))
+        .filter(|(other_rank, other_file)| {
+            (*other_rank, *other_file) != (rank, file) && *other_rank >= 0 && *other_file >= 0
+        })
+        .map(|(other_rank, other_file)| {
+            BoardPosition::new(other_rank as usize, other_file as usize)
+        })
+        .filter(|other| board.size().contains(other))
+        .collect()
+}
+
+/// Returns why the given move, which the player to move's pieces could make if it were not for
+/// check, is illegal, or None if it is legal or is a castle the player has no right to.
+fn illegality(board: &ChessBoard, piece_move: &Move) -> Option<Illegality> {
+    if piece_move.is_castle() {
+        match board.castle_failure(piece_move) {
+            Some(CastleFailure::NoRights) => return None,
+            Some(failure) => return Some(Illegality::Castle(failure)),
+            None => {}
+        }
+    }
+    let checkers = board.checkers_after(piece_move);
+    (!checkers.is_empty()).then_some(Illegality::Exposes(checkers))
+}
+
+/// Returns the explanation of how the game on the given board ended, or None if it has not ended
+/// in checkmate or stalemate, or is not a game of standard chess.
+pub fn explain(board: &ChessBoard) -> Option<Explanation> {
+    let status = (*board.game_end_status())?;
+    if *board.variant() != Variant::Standard
+        || !matches!(status, GameEndStatus::Checkmate | GameEndStatus::Stalemate)
+    {
+        return None;
+    }
+    // The board of the final position with the loser to move, as the ended game has no one to move
+    let fen = board.to_fen();
+    let color = *fen.active_color();
+    let board = ChessBoard::with_position(&fen);
+    let king = board.king_position(&color)?;
+    if status == GameEndStatus::Checkmate {
+        let flights = neighbours(&board, &king)
+            .into_iter()
+            .map(|square| {
+                let flight = match board.get_piece_color(&square) {
+                    Some(piece_color) if piece_color == color => Flight::Occupied,
+                    _ => Flight::Covered(
+                        board.checkers_after(&Move::from_board(king, square, &board)),
+                    ),
+                };
+                (square, flight)
+            })
+            .collect();
+        return Some(Explanation::Checkmate {
+            king,
+            checkers: board.attackers(&king, &color.opposite()),
+            flights,
+        });
+    }
+    let candidates = board.get_valid_moves(&Some(color), &false);
+    let moves = candidates
+        .iter()
+        .filter_map(|piece_move| Some((*piece_move, illegality(&board, piece_move)?)))
+        .collect();
+    let immobile = board
+        .size()
+        .positions()
+        .filter(|position| board.get_piece_color(position) == Some(color))
+        .filter(|position| {
+            !candidates
+                .iter()
+                .any(|piece_move| piece_move.from() == position)
+        })
+        .collect();
+    Some(Explanation::Stalemate {
+        king,
+        moves,
+        immobile,
+    })
+}
+
+/// Returns the color whose king the explanation is about.
+pub fn loser(board: &ChessBoard) -> PieceColor {
+    *board.to_fen().active_color()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    /// Returns the board after the given moves, ended as given, as the game plugin ends games.
+    fn play(fen: &str, moves: &[&str], end: Option<GameEndStatus>) -> ChessBoard {
+        let mut board = ChessBoard::with_position(&Fen::parse(fen).unwrap());
+        for text in moves {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        if let Some(status) = end {
+            let winner = (status == GameEndStatus::Checkmate).then_some(PieceColor::White);
+            board.end_game(status, winner);
+        }
+        board
+    }
+
+    #[test]
+    fn test_checkmate() {
+        let square = |text| BoardPosition::from_algebraic(text).unwrap();
+        let board = play(
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            &["Ra8"],
+            Some(GameEndStatus::Checkmate),
+        );
+        let Some(Explanation::Checkmate {
+            king,
+            checkers,
+            flights,
+        }) = explain(&board)
+        else {
+            panic!("The back rank mate is explained as a checkmate");
+        };
+        assert_eq!(king, square("g8"));
+        assert_eq!(checkers, vec![square("a8")]);
+        // The rook covers the square behind the king, which the king would no longer block
+        let flight = |text| flights.iter().find(|(other, _)| *other == square(text));
+        assert_eq!(
+            flight("h8").map(|(_, flight)| flight),
+            Some(&Flight::Covered(vec![square("a8")]))
+        );
+        assert_eq!(
+            flight("g7").map(|(_, flight)| flight),
+            Some(&Flight::Occupied)
+        );
+        assert_eq!(flights.len(), 5);
+
+        assert_eq!(
+            explain(&play("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", &[], None)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_stalemate() {
+        let board = play(
+            "k7/8/8/1Q6/8/8/8/7K w - - 0 1",
+            &["Qb6"],
+            Some(GameEndStatus::Stalemate),
+        );
+        let Some(Explanation::Stalemate {
+            king,
+            moves,
+            immobile,
+        }) = explain(&board)
+        else {
+            panic!("The queen stalemates the king");
+        };
+        assert_eq!(king, BoardPosition::from_algebraic("a8").unwrap());
+        assert_eq!(moves.len(), 3);
+        let queen = BoardPosition::from_algebraic("b6").unwrap();
+        assert!(moves
+            .iter()
+            .all(|(_, illegality)| *illegality == Illegality::Exposes(vec![queen])));
+        assert!(immobile.is_empty());
+        assert_eq!(loser(&board), PieceColor::Black);
+    }
+}
@@ -0,0 +1,100 @@
+//! Fuzzers for the move generation of the [ChessBoard](crate::chess_board::ChessBoard) and for
+//! the parsers of what players and engines type.
+//!
+//! The move generation is fuzzed differentially by the tests of [differential], which play random
+//! games and check the legal moves and the position after each move against the independent move
+//! generator of the `shakmaty` crate. As it is a dev-dependency, this fuzzer is run with
+//! `cargo test fuzz`, and for hundreds more games with `cargo test fuzz -- --ignored`.
+//!
+//! The parsers are fuzzed with `chess_computer fuzz [options]`, which reads mutated inputs and
+//! stops at the first which panics, printing its seed so it can be read again with
+//! `--seed <seed> --games 1`. The options are:
+//!
+//! - `--games <number>` sets the number of inputs to read for each parser.
+//! - `--seed <number>` sets the seed of the first input, which is incremented for each input.
+//! - `--target <parser>` fuzzes only the [input] parser `fen`, `san`, `pgn` or `uci`, rather
+//!   than each of them in turn.
+
+use self::input::{fuzz_input, Target};
+
+#[cfg(test)]
+mod differential;
+mod input;
+
+/// The settings of a fuzzing run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FuzzOptions {
+    games: u64,
+    seed: u64,
+    /// The parsers fuzzed.
+    targets: Vec<Target>,
+}
+
+impl Default for FuzzOptions {
+    fn default() -> Self {
+        FuzzOptions {
+            games: 100,
+            seed: 1,
+            targets: Target::ALL.to_vec(),
+        }
+    }
+}
+
+impl FuzzOptions {
+    /// Reads the options from the given command line arguments.
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = FuzzOptions::default();
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}.", flag))?;
+            let invalid = || format!("Invalid value for {}: {}.", flag, value);
+            match flag.as_str() {
+                "--games" => options.games = value.parse().map_err(|_| invalid())?,
+                "--seed" => options.seed = value.parse().map_err(|_| invalid())?,
+                "--target" => options.targets = vec![Target::from_name(value).ok_or_else(invalid)?],
+                _ => return Err(format!("Unknown option: {}.", flag)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Runs the fuzzer with the given command line arguments, failing at the first panic.
+#[cfg(not(tarpaulin_include))]
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = FuzzOptions::parse(args)?;
+    // The panics are reported with their inputs, so the default hook would only repeat them
+    std::panic::set_hook(Box::new(|_| {}));
+    for target in &options.targets {
+        for case in 0..options.games {
+            if let Some(panic) = fuzz_input(*target, options.seed.wrapping_add(case)) {
+                return Err(panic);
+            }
+        }
+        println!(
+            "No panics in {} {:?} inputs from seed {}.",
+            options.games, target, options.seed
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzz_options() {
+        let args = ["--games", "5", "--seed", "9"].map(String::from);
+        let options = FuzzOptions::parse(&args).unwrap();
+        assert_eq!(options.games, 5);
+        assert_eq!(options.seed, 9);
+        assert_eq!(options.targets, Target::ALL);
+        let args = ["--target", "pgn"].map(String::from);
+        assert_eq!(FuzzOptions::parse(&args).unwrap().targets, [Target::Pgn]);
+        assert!(FuzzOptions::parse(&["--target", "json"].map(String::from)).is_err());
+        assert!(FuzzOptions::parse(&["--plies", "40"].map(String::from)).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! The differential fuzzer of the move generation, which plays random games on the board and on a
+//! `shakmaty` position side by side, stopping at the first divergence between their legal moves
+//! or between the positions the moves reach. Every field of the positions is compared, with the
+//! en passant target square written as it is by the board, only when a pawn stands beside the
+//! pawn which passed over it.
+
+use shakmaty::fen::Fen as ReferenceFen;
+use shakmaty::uci::UciMove;
+use shakmaty::{CastlingMode, Chess, EnPassantMode, Position};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::ChessBoard;
+use crate::fen::Fen;
+use crate::random::Random;
+
+/// Returns the FEN string of the reference's position.
+fn reference_fen(position: &Chess) -> String {
+    ReferenceFen::from_position(position, EnPassantMode::PseudoLegal).to_string()
+}
+
+/// Returns the legal moves of the reference's position in long algebraic notation, sorted.
+fn reference_moves(position: &Chess) -> Vec<String> {
+    let mut moves: Vec<String> = position
+        .legal_moves()
+        .iter()
+        .map(|reference_move| reference_move.to_uci(CastlingMode::Standard).to_string())
+        .collect();
+    moves.sort();
+    moves
+}
+
+/// Describes how the legal moves of the board differ from those of the reference, if they do.
+fn compare_moves(board: &ChessBoard, position: &Chess) -> Option<String> {
+    let mut ours: Vec<String> = board
+        .legal_moves_iter()
+        .map(|piece_move| piece_move.as_long_algebraic())
+        .collect();
+    ours.sort();
+    let theirs = reference_moves(position);
+    if ours == theirs {
+        return None;
+    }
+    let missing: Vec<&String> = theirs.iter().filter(|m| !ours.contains(m)).collect();
+    let extra: Vec<&String> = ours.iter().filter(|m| !theirs.contains(m)).collect();
+    Some(format!(
+        "The board is missing the moves {:?} and has the extra moves {:?}.",
+        missing, extra
+    ))
+}
+
+/// Describes a divergence found in the game with the given seed, after the given moves.
+fn divergence(seed: u64, played: &[String], board: &ChessBoard, description: String) -> String {
+    format!(
+        "Divergence in the game with seed {} after the moves [{}] from {}:\n{}",
+        seed,
+        played.join(" "),
+        board.to_fen(),
+        description
+    )
+}
+
+/// Plays a random game from the given seed, returning a description of the first divergence
+/// between the board and the reference, if there is one.
+fn fuzz_game(seed: u64, plies: usize) -> Option<String> {
+    let mut board = ChessBoard::with_position(&Fen::default());
+    let mut position = Chess::default();
+    let mut random = Random::new(seed);
+    let mut played: Vec<String> = Vec::new();
+    for _ in 0..plies {
+        if let Some(description) = compare_moves(&board, &position) {
+            return Some(divergence(seed, &played, &board, description));
+        }
+        let moves = reference_moves(&position);
+        if moves.is_empty() {
+            return None;
+        }
+
+        let uci = &moves[random.below(moves.len())];
+        let reference_move = uci.parse::<UciMove>().ok()?.to_move(&position).ok()?;
+        let board_move = Move::from_long_algebraic(uci, &board)?;
+        let before = board.clone();
+        board.apply_move(&board_move);
+        position.play_unchecked(reference_move);
+        let (ours, theirs) = (board.to_fen().to_string(), reference_fen(&position));
+        if ours != theirs {
+            return Some(divergence(
+                seed,
+                &played,
+                &before,
+                format!(
+                    "After {} the board reached {} but the reference reached {}.",
+                    uci, ours, theirs
+                ),
+            ));
+        }
+        played.push(uci.clone());
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzz_game() {
+        // Games long enough to have captures en passant, castling and promotions
+        for seed in 0..20 {
+            assert_eq!(fuzz_game(seed, 200), None);
+        }
+    }
+
+    #[test]
+    #[ignore = "plays hundreds of games, run with --ignored"]
+    fn test_fuzz_many_games() {
+        for seed in 0..300 {
+            assert_eq!(fuzz_game(seed, 300), None);
+        }
+    }
+
+    #[test]
+    fn test_en_passant_divergence() {
+        // A board which had lost the en passant target square would be missing the capture
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2";
+        let board = ChessBoard::with_position(&Fen::from_string(&fen.replace("d6", "-")));
+        let position: Chess = fen
+            .parse::<ReferenceFen>()
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        assert_eq!(
+            compare_moves(&board, &position).unwrap(),
+            "The board is missing the moves [\"e5d6\"] and has the extra moves []."
+        );
+        let board = ChessBoard::with_position(&Fen::from_string(fen));
+        assert_eq!(compare_moves(&board, &position), None);
+        assert_eq!(board.to_fen().to_string(), reference_fen(&position));
+    }
+}
@@ -0,0 +1,268 @@
+//! A fuzzer for the parsers of what players and engines type, paste and send: FEN strings, moves
+//! in standard algebraic notation, PGN and the long algebraic moves of UCI engines.
+//!
+//! Each input is a valid one mutated at random, which is read by its parser and, if it is read,
+//! played on the board, so a malformed input must be refused and never panic however it is
+//! mangled.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use strum::IntoEnumIterator;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, Variant};
+use crate::fen::Fen;
+use crate::pgn::{read_games, replay_pgn};
+use crate::random::Random;
+
+/// The parsers which can be fuzzed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Target {
+    Fen,
+    San,
+    Pgn,
+    Uci,
+}
+
+impl Target {
+    pub(super) const ALL: [Target; 4] = [Target::Fen, Target::San, Target::Pgn, Target::Uci];
+
+    /// Returns the target of the given name, as given to `--target`.
+    pub(super) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fen" => Some(Target::Fen),
+            "san" => Some(Target::San),
+            "pgn" => Some(Target::Pgn),
+            "uci" => Some(Target::Uci),
+            _ => None,
+        }
+    }
+
+    /// Returns the valid inputs which are mutated.
+    fn corpus(&self) -> &'static [&'static str] {
+        match self {
+            Target::Fen => &[
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                "r3k2r/pPpppppp/8/8/8/8/PpPPPPPP/R3K2R b KQkq - 3 12",
+                "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2",
+                "4k3/8/8/3*4/4P3/8/8/4K3 b - - 0 1",
+                "8/8/8/3p4/8/8/8/R7 w - - 0 30",
+                "rnbqkbnr/pppppppp/5/5/PPPPPPPP/RNBQKBNR w - - 0 1",
+            ],
+            Target::San => &[
+                "e4", "Nf3", "exd5+", "Nbd2", "O-O", "0-0-0", "e8=Q#", "bxa1=N", "Qh4xe1!?",
+                "e4@e5", "Ke2",
+            ],
+            Target::Pgn => &[
+                "[Event \"Casual\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0\n",
+                "[FEN \"4k3/8/8/8/8/8/8/R3K3 w Q - 0 1\"]\n[SetUp \"1\"]\n\n1. O-O-O {castles} Kd7 (1... Ke7 2. Rd1) 2. Kb1 *\n",
+                "[Variant \"Duck\"]\n\n1. e4@e5 d5@d4 ; line comment\n2. exd5 $1 *\n",
+                "1. d4 d5 2. c4 e6 1/2-1/2\n\n[Event \"Second\"]\n\n1. Nf3 *\n",
+            ],
+            Target::Uci => &["e2e4", "g1f3", "e1g1", "e7e8q", "a2a1n", "h7h8K", "0000"],
+        }
+    }
+
+    /// Returns the boards the moves are read on.
+    fn boards() -> Vec<ChessBoard> {
+        [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                Variant::Standard,
+            ),
+            (
+                "r3k2r/pPpppppp/8/8/8/8/PpPPPPPP/R3K2R b KQkq - 3 12",
+                Variant::Standard,
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                Variant::Duck,
+            ),
+            ("8/8/8/3p4/8/8/8/R7 w - - 0 30", Variant::Antichess),
+        ]
+        .map(|(fen, variant)| {
+            ChessBoard::with_position(&Fen::read(fen, variant).unwrap()).with_variant(variant)
+        })
+        .to_vec()
+    }
+
+    /// Reads the given input, playing whatever is read on the board.
+    fn read(&self, input: &str) {
+        match self {
+            Target::Fen => {
+                let _ = input.parse::<Fen>();
+                for variant in Variant::iter() {
+                    if let Ok(fen) = Fen::read(input, variant) {
+                        play_out(ChessBoard::with_position(&fen).with_variant(variant));
+                    }
+                }
+            }
+            Target::San => {
+                for mut board in Self::boards() {
+                    if let Some(piece_move) = Move::from_algebraic(input, &board) {
+                        board.apply_move(&piece_move);
+                        play_out(board);
+                    }
+                }
+            }
+            Target::Pgn => {
+                read_games(input);
+                if let Some(mut replay) = replay_pgn(input) {
+                    replay.step(usize::MAX);
+                    replay.reset_event();
+                }
+            }
+            Target::Uci => {
+                for mut board in Self::boards() {
+                    // An engine's move is only played once the board has checked it
+                    if let Some(piece_move) = Move::from_long_algebraic(input, &board)
+                        .filter(|piece_move| board.check_move(piece_move).is_ok())
+                    {
+                        board.apply_move(&piece_move);
+                        play_out(board);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The text inserted into inputs, which is mostly what the parsers look for.
+const SYMBOLS: &[&str] = &[
+    "/",
+    " ",
+    "-",
+    "=",
+    "@",
+    "+",
+    "#",
+    "x",
+    ".",
+    "*",
+    "[",
+    "]",
+    "{",
+    "}",
+    "(",
+    ")",
+    ";",
+    "$",
+    "\"",
+    "\n",
+    "w",
+    "b",
+    "K",
+    "Q",
+    "q",
+    "N",
+    "p",
+    "O-O",
+    "a",
+    "h",
+    "l",
+    "1",
+    "3",
+    "8",
+    "9",
+    "0",
+    "12",
+    "3000000000",
+    "99999999999",
+    "-1",
+    "1-0",
+    "é",
+    "♞",
+];
+
+/// Returns the given input with a few random insertions, deletions and repetitions.
+fn mutate(input: &str, random: &mut Random) -> String {
+    let mut chars: Vec<char> = input.chars().collect();
+    for _ in 0..=random.below(4) {
+        let at = random.below(chars.len() + 1);
+        match random.below(4) {
+            0 => {
+                let symbol = SYMBOLS[random.below(SYMBOLS.len())];
+                chars.splice(at..at, symbol.chars());
+            }
+            1 => {
+                let end = (at + 1 + random.below(4)).min(chars.len());
+                chars.drain(at.min(end)..end);
+            }
+            2 => {
+                let end = (at + 1 + random.below(8)).min(chars.len());
+                let repeated: Vec<char> = chars[at.min(end)..end].to_vec();
+                chars.splice(at..at, repeated);
+            }
+            _ => {
+                if at < chars.len() {
+                    let symbol = SYMBOLS[random.below(SYMBOLS.len())];
+                    chars[at] = symbol.chars().next().unwrap();
+                }
+            }
+        }
+    }
+    chars.into_iter().collect()
+}
+
+/// Plays a few moves from the given board, so that odd positions which were read are also
+/// played in.
+fn play_out(mut board: ChessBoard) {
+    for _ in 0..4 {
+        board.to_fen();
+        board.game_end();
+        let Some(piece_move) = board.legal_moves_iter().next() else {
+            return;
+        };
+        board.apply_move(&piece_move);
+        if board.awaiting_duck() {
+            let Some(duck) = board
+                .size()
+                .positions()
+                .find(|position| board.valid_duck_square(position))
+            else {
+                return;
+            };
+            board.place_duck(duck);
+        }
+    }
+}
+
+/// Reads a mutated input of the given target from the given seed, returning a description of
+/// the panic if reading it panics.
+pub(super) fn fuzz_input(target: Target, seed: u64) -> Option<String> {
+    let mut random = Random::new(seed);
+    let corpus = target.corpus();
+    let input = mutate(corpus[random.below(corpus.len())], &mut random);
+    panic::catch_unwind(AssertUnwindSafe(|| target.read(&input)))
+        .err()
+        .map(|_| {
+            format!(
+                "Reading the {:?} input {:?} from seed {} panicked.",
+                target, input, seed
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutate() {
+        let mut random = Random::new(3);
+        let inputs: Vec<String> = (0..20).map(|_| mutate("e4", &mut random)).collect();
+        assert!(inputs.iter().any(|input| input != "e4"));
+        // Inputs are mutated the same from the same seed
+        let mut random = Random::new(3);
+        assert_eq!(mutate("e4", &mut random), inputs[0]);
+    }
+
+    #[test]
+    fn test_fuzz_input() {
+        for target in Target::ALL {
+            for seed in 0..300 {
+                assert_eq!(fuzz_input(target, seed), None);
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! The phase of the game a position is in, found from the pieces left on the board and the move
+//! number.
+//!
+//! Each knight and bishop counts one towards the phase, each rook two and each queen four, so the
+//! pieces of the starting position count 24. A fairy piece counts one for each minor
+//! piece it is worth. The game is in the opening for its first [OPENING_MOVES] moves while almost
+//! all of the pieces are on the board, and in the endgame once those left count [ENDGAME_PHASE]
+//! or less, about a rook and a minor piece each.
+
+use crate::chess_board::fairy::fairy_piece;
+use crate::chess_board::{ChessBoard, PieceType};
+
+/// The number of moves the opening lasts for at most.
+const OPENING_MOVES: i32 = 10;
+
+/// The phase below which the opening is over, even in its first moves.
+const OPENING_PHASE: i32 = 20;
+
+/// The phase at and below which the game is in the endgame.
+const ENDGAME_PHASE: i32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GamePhase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+/// Returns how much the given piece counts towards the phase.
+fn phase_weight(piece_type: &PieceType) -> i32 {
+    match piece_type {
+        PieceType::Pawn | PieceType::King => 0,
+        PieceType::Knight | PieceType::Bishop => 1,
+        PieceType::Rook => 2,
+        PieceType::Queen => 4,
+        PieceType::Fairy(index) => fairy_piece(*index).map_or(0, |piece| piece.value / 300),
+    }
+}
+
+/// Returns the phase of the pieces on the given board.
+pub fn phase(board: &ChessBoard) -> i32 {
+    board
+        .size()
+        .positions()
+        .filter_map(|position| board.get_piece_type(&position))
+        .map(|piece_type| phase_weight(&piece_type))
+        .sum()
+}
+
+/// Returns the phase of the game the position on the given board is in.
+pub fn game_phase(board: &ChessBoard) -> GamePhase {
+    let phase = phase(board);
+    if phase <= ENDGAME_PHASE {
+        GamePhase::Endgame
+    } else if *board.move_number() <= OPENING_MOVES && phase >= OPENING_PHASE {
+        GamePhase::Opening
+    } else {
+        GamePhase::Middlegame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_game_phase() {
+        let phase_of =
+            |fen: &str| game_phase(&ChessBoard::with_position(&Fen::parse(fen).unwrap()));
+        let start = ChessBoard::with_position(&Fen::default());
+        assert_eq!(phase(&start), 24);
+        assert_eq!(game_phase(&start), GamePhase::Opening);
+        // All the pieces are still on, but the opening is long over
+        assert_eq!(
+            phase_of("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 25"),
+            GamePhase::Middlegame
+        );
+        // The queens are off early
+        assert_eq!(
+            phase_of("rnb1kbnr/ppp2ppp/8/4p3/4P3/8/PPP2PPP/RNB1KBNR w KQkq - 0 5"),
+            GamePhase::Middlegame
+        );
+        assert_eq!(
+            phase_of("4k3/pp3ppp/2n5/8/8/5B2/PP3PPP/3RK3 w - - 0 30"),
+            GamePhase::Endgame
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Contains the [PositionHistory], a FEN snapshot of each position visited in the current game.
+//!
+//! Jumping back to a snapshot resets the board to that position with the moves which led to it,
+//! so everything which listens for [ResetBoardEvent]s, such as the clocks, highlights and
+//! annotations, is reset in the same way as for a new game.
+
+use bevy::app::Last;
+use bevy::app::{App, Plugin};
+use bevy::prelude::{DetectChanges, Res, ResMut, Resource};
+use chesscomputer_core::Game;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, PieceColor, ResetBoardEvent, Variant};
+use crate::fen::Fen;
+
+pub(super) struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PositionHistory>()
+            .add_systems(Last, record_positions);
+    }
+}
+
+/// The positions visited in the current game, starting with its starting position.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PositionHistory {
+    /// The FEN of each position, where the position at each index is reached after that many
+    /// moves from the starting position.
+    snapshots: Vec<Fen>,
+    /// The moves made in the game, including any made before the starting position.
+    moves: Vec<Move>,
+    /// The number of moves made before the starting position, for games which were reset part
+    /// way through.
+    offset: usize,
+}
+
+impl PositionHistory {
+    /// Returns the number of visited positions.
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Returns the starting position, or None before the first position has been recorded.
+    pub fn start(&self) -> Option<&Fen> {
+        self.snapshots.first()
+    }
+
+    /// Returns the visited positions, starting with the starting position.
+    pub fn positions(&self) -> &[Fen] {
+        &self.snapshots
+    }
+
+    /// Returns the moves made from the starting position.
+    pub fn moves(&self) -> &[Move] {
+        &self.moves[self.offset..]
+    }
+
+    /// Returns the number of the game's moves made before the starting position.
+    pub fn moves_before_start(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the index of the position reached after the given number of the game's moves,
+    /// counting any made before the starting position, if it was visited.
+    pub fn index_after(&self, moves: usize) -> Option<usize> {
+        moves
+            .checked_sub(self.offset)
+            .filter(|index| *index < self.len())
+    }
+
+    /// Returns a label for the position at the given index, e.g. "12... Nf6".
+    pub fn label(&self, index: usize) -> String {
+        if index == 0 {
+            return "Start".to_string();
+        }
+        let piece_move = &self.moves[self.offset + index - 1];
+        let move_number = self.snapshots[index - 1].fullmove_number();
+        match piece_move.piece_color() {
+            PieceColor::White => format!("{}. {}", move_number, piece_move.as_algebraic()),
+            PieceColor::Black => format!("{}... {}", move_number, piece_move.as_algebraic()),
+        }
+    }
+
+    /// Returns the event which resets the board to the position at the given index.
+    pub fn reset_event(&self, index: usize) -> ResetBoardEvent {
+        ResetBoardEvent::with_past_moves(
+            self.snapshots[index].clone(),
+            self.moves[..self.offset + index].to_vec(),
+        )
+    }
+
+    /// Replaces the history with the given moves from the given starting position, so each
+    /// position of a game can be stepped through once the board is reset to its last one.
+    pub fn set_game(&mut self, start: &Fen, moves: &[Move]) {
+        self.snapshots = Game::replay(start.clone(), Variant::Standard, moves)
+            .positions()
+            .map(|board| board.to_fen())
+            .collect();
+        self.moves = moves.to_vec();
+        self.offset = 0;
+    }
+
+    /// Brings the history in line with the given board, keeping the snapshots of the moves
+    /// the board has in common with the history.
+    pub fn update(&mut self, board: &ChessBoard) {
+        let past_moves = board.past_moves();
+        let common_moves = self
+            .moves
+            .iter()
+            .zip(past_moves)
+            .take_while(|(recorded, made)| recorded == made)
+            .count();
+        let current_fen = board.to_fen();
+        // A board which has diverged before the starting position, or which has no further moves
+        // but is in a different position, is a new game
+        let new_game = self.snapshots.is_empty()
+            || common_moves < self.offset
+            || (past_moves.len() == common_moves
+                && self
+                    .snapshots
+                    .get(common_moves - self.offset)
+                    .map(|fen| fen.to_string())
+                    != Some(current_fen.to_string()));
+        if new_game {
+            self.snapshots = vec![current_fen];
+            self.moves = past_moves.clone();
+            self.offset = past_moves.len();
+            return;
+        }
+        self.moves.truncate(common_moves);
+        self.snapshots.truncate(common_moves - self.offset + 1);
+
+        // Replay any new moves from the last snapshot
+        let new_moves = &past_moves[common_moves..];
+        let replay = Game::replay(
+            self.snapshots[common_moves - self.offset].clone(),
+            Variant::Standard,
+            new_moves,
+        );
+        self.snapshots
+            .extend(replay.positions().skip(1).map(|board| board.to_fen()));
+        self.moves.extend_from_slice(new_moves);
+    }
+}
+
+fn record_positions(board: Res<ChessBoard>, mut history: ResMut<PositionHistory>) {
+    if board.is_changed() {
+        history.update(&board);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::{ChessBoardPlugin, GameCommand, GameCommands};
+
+    use super::*;
+
+    fn play(app: &mut App, text: &str) {
+        let piece_move =
+            Move::from_algebraic(text, app.world.get_resource::<ChessBoard>().unwrap()).unwrap();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::MakeMove(piece_move));
+        app.update();
+    }
+
+    #[test]
+    fn test_position_history() {
+        let mut app = App::new();
+        app.add_plugins((ChessBoardPlugin::default(), HistoryPlugin));
+        app.update();
+        for text in ["e4", "e5", "Nf3"] {
+            play(&mut app, text);
+        }
+
+        let history = app.world.get_resource::<PositionHistory>().unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.label(0), "Start");
+        assert_eq!(history.label(2), "1... e5");
+        assert_eq!(history.label(3), "2. Nf3");
+        assert_eq!(
+            history.snapshots[1].to_string(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+
+        // Jump back to after 1. e4 and play a different move
+        let event = history.reset_event(1);
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(event)));
+        app.update();
+        let board = app.world.get_resource::<ChessBoard>().unwrap();
+        assert_eq!(board.past_moves().len(), 1);
+        assert_eq!(*board.active_color(), Some(PieceColor::Black));
+        assert_eq!(
+            app.world.get_resource::<PositionHistory>().unwrap().len(),
+            2
+        );
+
+        play(&mut app, "c5");
+        let history = app.world.get_resource::<PositionHistory>().unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.label(2), "1... c5");
+
+        // A new game starts a new history
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(
+                Fen::from_string("4k3/8/8/8/8/8/8/4K2R w K - 0 1"),
+            ))));
+        app.update();
+        let history = app.world.get_resource::<PositionHistory>().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            history.snapshots[0].to_string(),
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 1"
+        );
+    }
+
+    #[test]
+    fn test_position_history_set_game() {
+        let mut app = App::new();
+        app.add_plugins((ChessBoardPlugin::default(), HistoryPlugin));
+        app.update();
+
+        let start = Fen::default();
+        let event =
+            ResetBoardEvent::from_algebraic_moves(&start, &["f3", "e5", "g4", "Qh4"]).unwrap();
+        let mut history = app.world.resource_mut::<PositionHistory>();
+        history.set_game(&start, event.past_moves());
+        let event = history.reset_event(4);
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(event)));
+        app.update();
+
+        // The board reset to the last position keeps the whole game
+        let history = app.world.get_resource::<PositionHistory>().unwrap();
+        assert_eq!(history.len(), 5);
+        assert_eq!(history.label(4), "2... Qh4");
+        assert_eq!(history.start().unwrap().to_string(), start.to_string());
+    }
+}
@@ -0,0 +1,189 @@
+//! The game journal, which appends every finished game to a file as one line of JSON, so other
+//! tools can follow the games played as they finish, e.g. with `tail -f`.
+//!
+//! The journal is kept with `--journal <file>`. Unlike the [games database](crate::database),
+//! the file is only ever appended to, and a game finished again after taking back moves is
+//! journaled again with its new ending. Each line holds the date, the players, the starting
+//! position, the moves in standard algebraic and UCI notation, the result and how the game ended.
+
+use std::path::{Path, PathBuf};
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{DetectChanges, EventWriter, Res, ResMut, Resource, Update};
+use serde::Serialize;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameEndStatus, PieceColor, Variant};
+use crate::clock::ChessClock;
+use crate::database::today;
+use crate::engine::Engine;
+use crate::history::PositionHistory;
+use crate::notification::{Argument, Notification};
+use crate::pgn::GameResult;
+use crate::storage;
+
+pub(super) struct JournalPlugin;
+
+impl Plugin for JournalPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, journal_finished_game);
+    }
+}
+
+/// A line of the journal.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct JournalEntry {
+    /// The date the game finished, in the format of the PGN `Date` tag.
+    date: String,
+    /// The names of white and black, "Human" or "Engine" with the depth it searched to.
+    white: String,
+    black: String,
+    variant: Variant,
+    /// The FEN of the starting position.
+    start: String,
+    /// The moves in standard algebraic notation.
+    moves: Vec<String>,
+    /// The moves in long algebraic notation, as used by UCI.
+    uci: Vec<String>,
+    /// The result as the PGN token, e.g. "1-0".
+    result: &'static str,
+    termination: GameEndStatus,
+    /// The time white and black started with, in seconds.
+    time_controls: Option<[u64; 2]>,
+}
+
+impl JournalEntry {
+    /// Returns the entry of the finished game on the given board, or None if it has not finished.
+    fn new(
+        board: &ChessBoard,
+        history: &PositionHistory,
+        clock: &ChessClock,
+        engine: &Engine,
+        date: String,
+    ) -> Option<Self> {
+        let player = |color: PieceColor| match engine.color() {
+            Some(engine_color) if *engine_color == color => {
+                format!("Engine (depth {})", engine.depth())
+            }
+            _ => "Human".to_string(),
+        };
+        Some(JournalEntry {
+            date,
+            white: player(PieceColor::White),
+            black: player(PieceColor::Black),
+            variant: *board.variant(),
+            start: history.start()?.to_string(),
+            moves: history.moves().iter().map(Move::as_algebraic).collect(),
+            uci: history
+                .moves()
+                .iter()
+                .map(Move::as_long_algebraic)
+                .collect(),
+            result: GameResult::of(board).as_token(),
+            termination: (*board.game_end_status())?,
+            time_controls: clock
+                .time_controls()
+                .map(|times| times.map(|time| time.as_secs())),
+        })
+    }
+}
+
+/// The file finished games are appended to.
+#[derive(Resource, Debug)]
+pub struct Journal {
+    path: PathBuf,
+    /// The moves of the last game journaled, so it is only journaled once.
+    journaled: Option<Vec<Move>>,
+}
+
+impl Journal {
+    pub fn new(path: &Path) -> Self {
+        Journal {
+            path: path.to_path_buf(),
+            journaled: None,
+        }
+    }
+
+    /// Appends the given entry to the journal.
+    fn append(&self, entry: &JournalEntry) -> Result<(), String> {
+        let line = serde_json::to_string(entry).map_err(|error| error.to_string())?;
+        storage::append(&self.path.to_string_lossy(), &format!("{}\n", line))
+    }
+}
+
+/// Journals the game once it has finished and the history has caught up with its last move, if
+/// the journal is kept.
+fn journal_finished_game(
+    journal: Option<ResMut<Journal>>,
+    board: Res<ChessBoard>,
+    history: Res<PositionHistory>,
+    clock: Res<ChessClock>,
+    engine: Res<Engine>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let Some(mut journal) = journal else {
+        return;
+    };
+    if !board.is_changed() && !history.is_changed() {
+        return;
+    }
+    if board.game_end_status().is_none() || board.past_moves().last() != history.moves().last() {
+        return;
+    }
+    if journal.journaled.as_deref() == Some(history.moves()) {
+        return;
+    }
+    let Some(entry) = JournalEntry::new(&board, &history, &clock, &engine, today()) else {
+        return;
+    };
+    journal.journaled = Some(history.moves().to_vec());
+    if let Err(error) = journal.append(&entry) {
+        notifications.send(
+            Notification::error("journal-save-failed").with_arg("error", Argument::Text(error)),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_journal_entry() {
+        let mut history = PositionHistory::default();
+        let mut board = ChessBoard::with_position(&Fen::default());
+        history.update(&board);
+        for text in ["f3", "e5", "g4", "Qh4"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            history.update(&board);
+        }
+        board.end_game(GameEndStatus::Checkmate, Some(PieceColor::Black));
+        let mut engine = Engine::default();
+        engine.set_color(Some(PieceColor::Black));
+        let entry = JournalEntry::new(
+            &board,
+            &history,
+            &ChessClock::default(),
+            &engine,
+            "2024.03.09".to_string(),
+        )
+        .unwrap();
+        assert_eq!(entry.uci, ["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert_eq!(entry.result, "0-1");
+        assert_eq!(entry.white, "Human");
+
+        let path = std::env::temp_dir().join(format!("chess_journal_{}", std::process::id()));
+        let journal = Journal::new(&path);
+        journal.append(&entry).unwrap();
+        journal.append(&entry).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        let line: serde_json::Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+        assert_eq!(line["termination"], "Checkmate");
+        assert_eq!(line["moves"][3], "Qh4");
+    }
+}
@@ -0,0 +1,218 @@
+//! Structured logging with [tracing](bevy::log), set up in place of Bevy's log plugin so that
+//! the log is also kept in memory for the debug console.
+//!
+//! The board logs each command it carries out and each move it refuses, the engine each search,
+//! the server each request and Twitch chat its connection and votes, each within a span naming
+//! what was being done. The log is printed to stderr and filtered with the `RUST_LOG` environment
+//! variable, or [DEFAULT_FILTER] if it is not set.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::Resource;
+use bevy::utils::tracing::field::{Field, Visit};
+use bevy::utils::tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Registry};
+
+/// The filter used when `RUST_LOG` is not set, which is the same as Bevy's.
+const DEFAULT_FILTER: &str = "info,wgpu=error,naga=warn";
+
+/// The number of entries kept for the debug console, where the oldest are dropped first.
+const MAX_ENTRIES: usize = 500;
+
+/// The target prefix of the events logged by this app rather than by its dependencies.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+pub struct LoggingPlugin;
+
+impl Plugin for LoggingPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        let buffer = LogBuffer::default();
+        let filter =
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+        let subscriber = Registry::default()
+            .with(filter)
+            .with(tracing_subscriber::fmt::Layer::default().with_writer(std::io::stderr))
+            .with(ConsoleLayer(buffer.clone()));
+        // Only the first app in the process can set up logging, and later ones log through it
+        subscriber.try_init().ok();
+        app.insert_resource(buffer);
+    }
+}
+
+/// An event logged by this app.
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    /// The time since logging was set up.
+    time: Duration,
+    level: Level,
+    /// The module the event was logged from, such as `engine`.
+    subsystem: String,
+    /// The names of the spans the event was logged in, from the outermost.
+    spans: Vec<&'static str>,
+    /// The message followed by the other fields.
+    message: String,
+}
+
+impl LogEntry {
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn subsystem(&self) -> &str {
+        &self.subsystem
+    }
+}
+
+impl fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>9.3} {:<5} {}",
+            self.time.as_secs_f32(),
+            self.level,
+            self.subsystem
+        )?;
+        if !self.spans.is_empty() {
+            write!(f, ":{}", self.spans.join(":"))?;
+        }
+        write!(f, " {}", self.message)
+    }
+}
+
+/// The most recent entries of the log, shared with the layer which records them.
+#[derive(Resource, Debug, Clone)]
+pub struct LogBuffer {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+    started: Instant,
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        LogBuffer {
+            entries: Arc::new(Mutex::new(VecDeque::new())),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl LogBuffer {
+    /// Returns a copy of the entries, from the oldest.
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries.lock().unwrap().iter().cloned().collect()
+    }
+
+    fn push(&self, entry: LogEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+/// Collects the fields of an event into its message.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: Vec<String>,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        } else {
+            self.fields.push(format!("{}={}", field.name(), value));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        } else {
+            self.fields.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// A layer which records the events logged by this app in a [LogBuffer].
+struct ConsoleLayer(LogBuffer);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for ConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let Some(path) = metadata.target().strip_prefix(CRATE_TARGET) else {
+            return;
+        };
+        let subsystem = path
+            .trim_start_matches("::")
+            .split("::")
+            .next()
+            .filter(|module| !module.is_empty())
+            .unwrap_or("main");
+        let spans = ctx
+            .event_scope(event)
+            .map(|scope| scope.from_root().map(|span| span.name()).collect())
+            .unwrap_or_default();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let mut message = visitor.message;
+        for field in visitor.fields {
+            message.push(' ');
+            message.push_str(&field);
+        }
+        self.0.push(LogEntry {
+            time: self.0.started.elapsed(),
+            level: *metadata.level(),
+            subsystem: subsystem.to_string(),
+            spans,
+            message,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::log::{info, info_span, warn};
+    use bevy::utils::tracing::subscriber::with_default;
+
+    use super::*;
+
+    #[test]
+    fn test_console_layer() {
+        let buffer = LogBuffer::default();
+        with_default(
+            Registry::default().with(ConsoleLayer(buffer.clone())),
+            || {
+                let _span = info_span!("command").entered();
+                info!(piece_move = "e4", "Move made");
+                warn!(target: "wgpu", "Not from this app");
+            },
+        );
+        let entries = buffer.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].level(), Level::INFO);
+        assert_eq!(entries[0].subsystem(), "logging");
+        assert!(entries[0]
+            .to_string()
+            .ends_with("INFO  logging:command Move made piece_move=e4"));
+
+        for number in 0..MAX_ENTRIES {
+            with_default(
+                Registry::default().with(ConsoleLayer(buffer.clone())),
+                || info!(number),
+            );
+        }
+        let entries = buffer.entries();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert!(entries[0].to_string().ends_with(" number=0"));
+    }
+}
@@ -1,22 +1,283 @@
 #![doc = include_str!("../README.md")]
 
-use bevy::app::App;
+use std::path::Path;
+
+use bevy::app::{App, PluginGroup};
+use bevy::log::LogPlugin;
 use bevy::winit::WinitSettings;
 use bevy::DefaultPlugins;
+// The rules live in their own crate, with FEN kept at its path in this one
+use chesscomputer_core::fen;
+use chesscomputer_core::legality::{self, Problem};
 
-use crate::chess_board::ChessBoardPlugin;
+use crate::analysis::AnalysisPlugin;
+use crate::autosave::AutosavePlugin;
+use crate::batch_analysis::BatchAnalysisPlugin;
+use crate::blunder_puzzles::BlunderPuzzlesPlugin;
+use crate::bookmark::BookmarkPlugin;
+use crate::bridge::{Bridge, BridgePlugin};
+use crate::broadcast::BroadcastPlugin;
+use crate::chess_board::{ChessBoardConfig, ChessBoardPlugin, PieceColor, Variant};
+use crate::clock::ClockPlugin;
+use crate::database::DatabasePlugin;
+use crate::engine::EnginePlugin;
+use crate::fen::Fen;
+use crate::history::HistoryPlugin;
+use crate::journal::{Journal, JournalPlugin};
+use crate::logging::LoggingPlugin;
+use crate::network::{NetworkGame, NetworkPlugin};
+use crate::profile::ProfilePlugin;
+use crate::replay::{Playback, ReplayPlugin};
+use crate::share::{SharePlugin, SharedGame};
+use crate::simul::SimulPlugin;
+use crate::spectating::SpectatingPlugin;
+use crate::tab_sync::TabSyncPlugin;
+use crate::telemetry::TelemetryPlugin;
 use crate::ui::UIPlugin;
 
-mod castling_rights;
+mod analysis;
+mod army;
+mod autosave;
+mod batch_analysis;
+mod blunder_puzzles;
+mod book;
+mod bookmark;
+mod bridge;
+mod broadcast;
+mod bundle;
 mod chess_board;
-mod fen;
+mod clock;
+mod coach;
+mod database;
+mod diagnostics;
+#[cfg(feature = "discord")]
+mod discord;
+mod eco;
+mod endgames;
+mod engine;
+mod engine_match;
+mod exchange;
+mod explanation;
+mod fuzz;
+mod game_phase;
+mod history;
+mod journal;
+mod logging;
+mod network;
+mod notification;
+mod pawn_structure;
+mod pgn;
+mod practice;
+mod profile;
+mod puzzle;
+mod puzzle_set;
+mod random;
+mod replay;
+mod report;
+mod review;
+#[cfg(feature = "scripting")]
+mod scripting;
+#[cfg(feature = "server")]
+mod server;
+mod share;
+mod simul;
+#[cfg(test)]
+mod smoke;
+mod spectating;
+mod start_position;
+mod storage;
+mod tab_sync;
+mod telemetry;
+mod thumbnail;
+mod tournament;
+mod tune;
+#[cfg(feature = "twitch")]
+mod twitch;
 mod ui;
+mod underpromotion;
+mod zobrist;
+
+/// The options of the app read from its arguments.
+struct Options {
+    config: ChessBoardConfig,
+    playback: Option<Playback>,
+    bridge: Option<Bridge>,
+    journal: Option<Journal>,
+    network: Option<NetworkGame>,
+    /// The game of the [share link](share) the app was opened with.
+    shared: Option<SharedGame>,
+}
+
+/// Reads the options of the app from its arguments, which are `--fen <fen>` to start from a
+/// position other than the standard one, `--sandbox` to let any piece move to any square,
+/// `--replay <file>` to play back a [replay log](replay), `--bridge <input> <output>` to
+/// exchange moves with another program through the [bridge](bridge) files,
+/// `--journal <file>` to append finished games to a [journal](journal), `--open <link>` to
+/// open the position or game of a [share link](share) and `--connect <address> <color>` to play
+/// a [network game](network) or `--watch <address>` to spectate one, with `--keep-clocks` to keep
+/// the clocks running while it is disconnected.
+#[cfg(not(tarpaulin_include))]
+fn read_options(args: &[String]) -> Result<Options, String> {
+    let mut config = ChessBoardConfig::default();
+    let mut playback = None;
+    let mut bridge = None;
+    let mut journal = None;
+    let mut shared = None;
+    let mut connect = None;
+    let mut pause_clocks = true;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--sandbox" => config.enforce_legality = false,
+            "--fen" => {
+                let fen = args.next().ok_or("Missing the starting FEN.")?;
+                let fen = Fen::parse(fen).ok_or_else(|| format!("Invalid FEN: {}.", fen))?;
+                // A position which could not arise in a game is still played, with a warning,
+                // but castling rights which cannot be used are cleared
+                for problem in legality::problems(&fen, Variant::Standard) {
+                    match problem {
+                        Problem::CastlingRights(_) => eprintln!("Warning: {}, cleared.", problem),
+                        _ => eprintln!("Warning: {}.", problem),
+                    }
+                }
+                config.starting_fen = legality::fix_castling_rights(&fen, Variant::Standard);
+            }
+            "--replay" => {
+                let path = args.next().ok_or("Missing the replay log file.")?;
+                playback = Some(Playback::load(Path::new(path))?);
+            }
+            "--bridge" => {
+                let input = args.next().ok_or("Missing the bridge input file.")?;
+                let output = args.next().ok_or("Missing the bridge output file.")?;
+                bridge = Some(Bridge::open(Path::new(input), Path::new(output)));
+            }
+            "--journal" => {
+                let path = args.next().ok_or("Missing the journal file.")?;
+                journal = Some(Journal::new(Path::new(path)));
+            }
+            "--open" => {
+                let link = args.next().ok_or("Missing the link to open.")?;
+                shared = Some(
+                    SharedGame::parse(link).ok_or_else(|| format!("Invalid link: {}.", link))?,
+                );
+            }
+            "--connect" => {
+                let address = args.next().ok_or("Missing the address to connect to.")?;
+                let color = match args.next().map(String::as_str) {
+                    Some("white") => PieceColor::White,
+                    Some("black") => PieceColor::Black,
+                    _ => return Err("The color to play must be white or black.".to_string()),
+                };
+                connect = Some((address.clone(), Some(color)));
+            }
+            "--watch" => {
+                let address = args.next().ok_or("Missing the address to watch.")?;
+                connect = Some((address.clone(), None));
+            }
+            "--keep-clocks" => pause_clocks = false,
+            _ => return Err(format!("Unknown option: {}.", flag)),
+        }
+    }
+    Ok(Options {
+        config,
+        playback,
+        bridge,
+        journal,
+        network: connect.map(|(address, color)| NetworkGame::new(&address, color, pause_clocks)),
+        shared,
+    })
+}
 
 #[cfg(not(tarpaulin_include))]
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugins((ChessBoardPlugin, UIPlugin))
-        .insert_resource(WinitSettings::desktop_app())
-        .run();
+    // The engine's web workers load the same module as the app, only to search
+    #[cfg(target_arch = "wasm32")]
+    if web_sys::window().is_none() {
+        return;
+    }
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command: Option<fn(&[String]) -> Result<(), String>> =
+        match args.first().map(String::as_str) {
+            Some("match") => Some(engine_match::run),
+            Some("tournament") => Some(tournament::run),
+            Some("book") => Some(book::run),
+            Some("fuzz") => Some(fuzz::run),
+            Some("replay") => Some(replay::run),
+            Some("tune") => Some(tune::run),
+            _ => None,
+        };
+    if let Some(command) = command {
+        if let Err(error) = command(&args[1..]) {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let options = match read_options(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut app = App::new();
+    // Logging is set up by the app's own plugin, which also keeps the log for the debug console
+    app.add_plugins(DefaultPlugins.build().disable::<LogPlugin>())
+        .add_plugins((
+            LoggingPlugin,
+            AnalysisPlugin,
+            AutosavePlugin,
+            BridgePlugin,
+            BroadcastPlugin,
+            ChessBoardPlugin::new(options.config),
+            ClockPlugin,
+            DatabasePlugin,
+            EnginePlugin,
+            HistoryPlugin,
+            JournalPlugin,
+            ProfilePlugin,
+            ReplayPlugin,
+            SimulPlugin,
+            UIPlugin,
+        ))
+        .add_plugins((
+            BatchAnalysisPlugin,
+            BlunderPuzzlesPlugin,
+            BookmarkPlugin,
+            NetworkPlugin,
+            SharePlugin,
+            SpectatingPlugin,
+            TabSyncPlugin,
+            TelemetryPlugin,
+        ))
+        .insert_resource(WinitSettings::desktop_app());
+    if let Some(playback) = options.playback {
+        app.insert_resource(playback);
+    }
+    if let Some(bridge) = options.bridge {
+        app.insert_resource(bridge);
+    }
+    if let Some(journal) = options.journal {
+        app.insert_resource(journal);
+    }
+    if let Some(network) = options.network {
+        app.insert_resource(network);
+    }
+    let shared = options.shared;
+    // The web build has no arguments, so it opens the link of the page it was loaded from
+    #[cfg(target_arch = "wasm32")]
+    let shared = share::page_game();
+    if let Some(shared) = shared {
+        app.insert_resource(shared);
+    }
+    #[cfg(feature = "discord")]
+    app.add_plugins(discord::DiscordPlugin);
+    #[cfg(feature = "scripting")]
+    app.add_plugins(scripting::ScriptingPlugin);
+    #[cfg(feature = "server")]
+    app.add_plugins(server::ServerPlugin);
+    #[cfg(feature = "twitch")]
+    app.add_plugins(twitch::TwitchPlugin);
+    app.run();
 }
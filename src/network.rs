@@ -0,0 +1,451 @@
+//! Network games, played against another copy of the app whose board is served with the `server`
+//! feature.
+//!
+//! Started with `--connect <address> <color>`, the app plays the given color on the served board,
+//! or with `--watch <address>` only [spectates](crate::spectating) the game played on it.
+//! The player's moves are sent to the server, and the opponent's are fetched from it every
+//! [POLL_INTERVAL], asking the server only for the squares which changed since the position last
+//! fetched, and for the whole game once they have. A dropped connection keeps the game on the
+//! board and is tried again after a wait which doubles with each failed attempt, up to
+//! [MAX_BACKOFF]. Moves made while disconnected are sent once the connection is back, and the
+//! whole game is then fetched again to bring the board back in line with the server's. Both
+//! clocks are paused while disconnected, unless `--keep-clocks` is given.
+
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::log::{info, info_span, warn};
+use bevy::prelude::{DetectChanges, EventWriter, Res, ResMut, Resource, Update};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor, ResetBoardEvent};
+use crate::clock::ChessClock;
+use crate::fen::Fen;
+use crate::notification::{Argument, Notification};
+
+/// The time between fetches of the opponent's moves.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The longest a request waits for the server.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The wait before the first attempt to reconnect.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest wait between attempts to reconnect.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+pub(super) struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, play_network_game);
+    }
+}
+
+/// Returns the wait before the given attempt to reconnect, counting from zero.
+fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF)
+}
+
+/// Sends the given HTTP request to the server at the given address, returning the status and body
+/// of the response.
+fn request(address: &str, method: &str, path: &str, body: &str) -> Result<(u16, String), String> {
+    let mut stream = TcpStream::connect(address).map_err(|error| error.to_string())?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok();
+    stream.set_write_timeout(Some(TIMEOUT)).ok();
+    write!(
+        stream,
+        "{} {} HTTP/1.0\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        address,
+        body.len(),
+        body
+    )
+    .map_err(|error| error.to_string())?;
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .map_err(|error| error.to_string())?;
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or("the response was cut off")?;
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or("the response has no status")?;
+    Ok((status, body.to_string()))
+}
+
+/// The game on the server's board.
+#[derive(Debug, Clone)]
+struct RemoteGame {
+    start: Fen,
+    moves: Vec<Move>,
+}
+
+impl RemoteGame {
+    /// Reads the game from the JSON of the server's position, returning None if it is not valid.
+    fn from_json(text: &str) -> Option<Self> {
+        let json: serde_json::Value = serde_json::from_str(text).ok()?;
+        let start = Fen::parse(json["start"].as_str()?)?;
+        let mut board = ChessBoard::with_position(&start);
+        for text in json["uci"].as_array()? {
+            let piece_move = Move::from_long_algebraic(text.as_str()?, &board)?;
+            if !board.valid_move(&piece_move, board.active_color(), &false) {
+                return None;
+            }
+            board.apply_move(&piece_move);
+        }
+        Some(RemoteGame {
+            start,
+            moves: board.past_moves().clone(),
+        })
+    }
+
+    /// Returns the event resetting the board to the game, with its moves played.
+    fn reset_event(&self) -> ResetBoardEvent {
+        let mut board = ChessBoard::with_position(&self.start);
+        for piece_move in &self.moves {
+            board.apply_move(piece_move);
+        }
+        ResetBoardEvent::with_past_moves(board.to_fen(), self.moves.clone())
+    }
+}
+
+/// What the connection reports to the app.
+#[derive(Debug)]
+enum NetworkEvent {
+    /// The connection was made, or made again after being lost.
+    Connected,
+    /// The connection was lost, for the given reason.
+    Disconnected(String),
+    /// The game on the server's board, fetched as it changed.
+    Game(Box<RemoteGame>),
+    /// The server refused the given move, in long algebraic notation.
+    Refused(String),
+}
+
+/// The state of the connection kept between requests.
+#[derive(Debug, Default)]
+struct Connection {
+    /// The moves waiting to be sent, which are kept while the connection is down.
+    unsent: VecDeque<Move>,
+    /// The FEN of the position last fetched, which is fetched again whole after reconnecting.
+    fetched: Option<String>,
+}
+
+impl Connection {
+    /// Sends the waiting moves and fetches the game if it has changed, failing if the server
+    /// cannot be reached.
+    fn sync(&mut self, address: &str, events: &Sender<NetworkEvent>) -> Result<(), String> {
+        while let Some(piece_move) = self.unsent.front() {
+            let text = piece_move.as_long_algebraic();
+            let (status, _) = request(address, "POST", "/move", &text)?;
+            if status != 200 {
+                events.send(NetworkEvent::Refused(text)).ok();
+            }
+            self.unsent.pop_front();
+        }
+        if let Some(fen) = &self.fetched {
+            let (status, diff) = request(address, "POST", "/position/diff", fen)?;
+            if status == 200 && diff.trim() == "[]" {
+                return Ok(());
+            }
+        }
+        let (status, body) = request(address, "GET", "/position", "")?;
+        if status != 200 {
+            return Err(format!("the server replied {}", status));
+        }
+        let game = RemoteGame::from_json(&body).ok_or("the server sent an invalid game")?;
+        self.fetched = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|json| json["fen"].as_str().map(str::to_string));
+        events.send(NetworkEvent::Game(Box::new(game))).ok();
+        Ok(())
+    }
+}
+
+/// Keeps the connection to the server at the given address, sending the moves received and
+/// reporting what happens with the given sender, until the app stops listening.
+fn run_connection(address: String, moves: Receiver<Move>, events: Sender<NetworkEvent>) {
+    let _span = info_span!("network", address).entered();
+    let mut connection = Connection::default();
+    let mut connected = false;
+    let mut attempt = 0;
+    loop {
+        loop {
+            match moves.try_recv() {
+                Ok(piece_move) => connection.unsent.push_back(piece_move),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+        let wait = match connection.sync(&address, &events) {
+            Ok(()) => {
+                if !connected {
+                    info!("Connected");
+                    connected = true;
+                    attempt = 0;
+                    events.send(NetworkEvent::Connected).ok();
+                }
+                POLL_INTERVAL
+            }
+            Err(error) => {
+                if connected || attempt == 0 {
+                    warn!(error, "Connection lost");
+                    events.send(NetworkEvent::Disconnected(error)).ok();
+                }
+                connected = false;
+                // The game may have moved on, so it is fetched whole once the connection is back
+                connection.fetched = None;
+                attempt += 1;
+                backoff(attempt - 1)
+            }
+        };
+        thread::sleep(wait);
+    }
+}
+
+/// A game played against the board served at another address.
+#[derive(Resource, Debug)]
+pub struct NetworkGame {
+    address: String,
+    /// The color the player plays, or None if they only watch.
+    color: Option<PieceColor>,
+    /// Whether both clocks are paused while disconnected.
+    pause_clocks: bool,
+    /// The sender of the player's moves and the receiver of what the connection reports, once it
+    /// has been started.
+    connection: Option<(Sender<Move>, Mutex<Receiver<NetworkEvent>>)>,
+    connected: bool,
+    /// The game last fetched from the server.
+    remote: Option<RemoteGame>,
+    /// The player's moves sent since the game was last fetched.
+    pending: Vec<Move>,
+}
+
+impl NetworkGame {
+    pub fn new(address: &str, color: Option<PieceColor>, pause_clocks: bool) -> Self {
+        NetworkGame {
+            address: address.to_string(),
+            color,
+            pause_clocks,
+            connection: None,
+            connected: false,
+            remote: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the color the player plays, or None if they only watch.
+    pub fn color(&self) -> &Option<PieceColor> {
+        &self.color
+    }
+
+    /// Returns whether the player only watches the game.
+    pub fn is_watching(&self) -> bool {
+        self.color().is_none()
+    }
+
+    /// Returns the moves of the game the server has, or will have once the pending moves arrive.
+    fn expected(&self) -> Vec<Move> {
+        let mut moves = self
+            .remote
+            .as_ref()
+            .map(|remote| remote.moves.clone())
+            .unwrap_or_default();
+        moves.extend(&self.pending);
+        moves
+    }
+
+    /// Returns the player's moves on the given board which are yet to be sent, or None if the
+    /// board has left the game.
+    fn new_moves(&self, board: &ChessBoard) -> Option<Vec<Move>> {
+        let expected = self.expected();
+        let added = board.past_moves().strip_prefix(expected.as_slice())?;
+        added
+            .iter()
+            .all(|piece_move| Some(*piece_move.piece_color()) == self.color)
+            .then(|| added.to_vec())
+    }
+
+    /// Takes in the game fetched from the server, returning the commands bringing the given
+    /// board in line with it. The opponent's new moves are made on the board, keeping the clocks
+    /// running, and any other difference loads the server's game.
+    fn receive(&mut self, remote: RemoteGame, board: &ChessBoard) -> Vec<GameCommand> {
+        let expected = self.expected();
+        let first = self.remote.is_none();
+        let commands = if let Some(added) = remote.moves.strip_prefix(expected.as_slice()) {
+            self.pending.clear();
+            match !first && board.past_moves() == &expected {
+                true => added.iter().copied().map(GameCommand::MakeMove).collect(),
+                false => vec![GameCommand::LoadGame(Box::new(remote.reset_event()))],
+            }
+        } else if let Some(pending) = expected.strip_prefix(remote.moves.as_slice()) {
+            // The player's moves are still on their way
+            self.pending = pending.to_vec();
+            Vec::new()
+        } else {
+            self.pending.clear();
+            vec![GameCommand::LoadGame(Box::new(remote.reset_event()))]
+        };
+        self.remote = Some(remote);
+        commands
+    }
+}
+
+/// Sends the player's moves, makes the opponent's, and tells the player when the connection is
+/// lost and back, pausing the clocks in between.
+fn play_network_game(
+    network: Option<ResMut<NetworkGame>>,
+    board: Res<ChessBoard>,
+    mut clock: ResMut<ChessClock>,
+    mut commands: ResMut<GameCommands>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let Some(mut network) = network else {
+        return;
+    };
+    let network = &mut *network;
+    let Some((moves, events)) = &network.connection else {
+        let (move_sender, move_receiver) = channel();
+        let (event_sender, event_receiver) = channel();
+        let address = network.address.clone();
+        thread::spawn(move || run_connection(address, move_receiver, event_sender));
+        network.connection = Some((move_sender, Mutex::new(event_receiver)));
+        return;
+    };
+    // The player's moves are sent first, so a game fetched in the same frame still expects them
+    let mut left_game = false;
+    if board.is_changed() && network.remote.is_some() {
+        match network.new_moves(&board) {
+            Some(added) => {
+                for piece_move in added {
+                    info!(piece_move = %piece_move.as_algebraic(), "Move sent");
+                    moves.send(piece_move).ok();
+                    network.pending.push(piece_move);
+                }
+            }
+            None => left_game = true,
+        }
+    }
+    let events: Vec<NetworkEvent> = events.lock().unwrap().try_iter().collect();
+    let mut synced = false;
+    for event in events {
+        match event {
+            NetworkEvent::Connected => {
+                // The first connection is shown by the game appearing on the board
+                if network.remote.is_some() {
+                    notifications.send(Notification::info("network-reconnected"));
+                }
+                network.connected = true;
+                if network.pause_clocks {
+                    clock.set_paused(false);
+                }
+            }
+            NetworkEvent::Disconnected(error) => {
+                network.connected = false;
+                notifications.send(
+                    Notification::warning("network-disconnected")
+                        .with_arg("error", Argument::Text(error)),
+                );
+                if network.pause_clocks {
+                    clock.set_paused(true);
+                }
+            }
+            NetworkEvent::Game(remote) => {
+                for command in network.receive(*remote, &board) {
+                    commands.push(command);
+                    synced = true;
+                }
+            }
+            NetworkEvent::Refused(text) => {
+                warn!(piece_move = text, "Move refused by the server");
+                network.pending.clear();
+                notifications.send(
+                    Notification::warning("network-move-refused")
+                        .with_arg("move", Argument::Text(text)),
+                );
+                left_game = true;
+            }
+        }
+    }
+    // Moves for the opponent and take backs are undone by loading the server's game
+    if left_game && !synced {
+        if let Some(remote) = &network.remote {
+            commands.push(GameCommand::LoadGame(Box::new(remote.reset_event())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(0), INITIAL_BACKOFF);
+        assert_eq!(backoff(2), INITIAL_BACKOFF * 4);
+        assert_eq!(backoff(10), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+        assert!(request("127.0.0.1:1", "GET", "/position", "").is_err());
+    }
+
+    #[test]
+    fn test_receive() {
+        let json = |moves: &str| {
+            format!(
+                r#"{{"fen": "", "start": "{}", "uci": [{}]}}"#,
+                Fen::default(),
+                moves
+            )
+        };
+        assert!(RemoteGame::from_json(&json(r#""e2e5""#)).is_none());
+        let mut network = NetworkGame::new("localhost:8080", Some(PieceColor::White), true);
+        let mut board = ChessBoard::with_position(&Fen::default());
+
+        // The first game fetched is loaded
+        let remote = RemoteGame::from_json(&json("")).unwrap();
+        let commands = network.receive(remote, &board);
+        assert!(matches!(commands[..], [GameCommand::LoadGame(_)]));
+
+        // The player's move is sent, and kept while the server does not have it yet
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        let sent = network.new_moves(&board).unwrap();
+        assert_eq!(sent.len(), 1);
+        network.pending.extend(sent);
+        let remote = RemoteGame::from_json(&json("")).unwrap();
+        assert!(network.receive(remote, &board).is_empty());
+        assert_eq!(network.pending.len(), 1);
+
+        // The opponent's reply is made on the board
+        let remote = RemoteGame::from_json(&json(r#""e2e4", "e7e5""#)).unwrap();
+        let commands = network.receive(remote, &board);
+        assert!(matches!(commands[..], [GameCommand::MakeMove(_)]));
+        assert!(network.pending.is_empty());
+        board.apply_move(&Move::from_algebraic("e5", &board).unwrap());
+
+        // Moving the opponent's pieces leaves the game, which is then loaded again
+        board.apply_move(&Move::from_algebraic("Nf3", &board).unwrap());
+        board.apply_move(&Move::from_algebraic("Nc6", &board).unwrap());
+        assert!(network.new_moves(&board).is_none());
+        let remote = RemoteGame::from_json(&json(r#""d2d4""#)).unwrap();
+        assert!(matches!(
+            network.receive(remote, &board)[..],
+            [GameCommand::LoadGame(_)]
+        ));
+    }
+}
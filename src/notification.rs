@@ -0,0 +1,75 @@
+//! Notifications, short messages about something which happened, such as a move being refused or
+//! a connection being lost, for the UI to show the player for a few seconds.
+//!
+//! Any system can send a [Notification] event. The message is given as a locale key and its
+//! arguments rather than as text, since only the UI knows the player's language.
+
+use bevy::prelude::Event;
+
+use crate::chess_board::PieceColor;
+
+/// How serious a notification is, which decides how it is shown and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// An argument of the message of a notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Argument {
+    Text(String),
+    /// A color, given in the player's language.
+    Color(PieceColor),
+    /// A move in algebraic notation, given with the player's piece letters.
+    Move(String),
+}
+
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    severity: Severity,
+    /// The locale key of the message.
+    key: &'static str,
+    args: Vec<(&'static str, Argument)>,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, key: &'static str) -> Self {
+        Notification {
+            severity,
+            key,
+            args: Vec::new(),
+        }
+    }
+
+    pub fn info(key: &'static str) -> Self {
+        Notification::new(Severity::Info, key)
+    }
+
+    pub fn warning(key: &'static str) -> Self {
+        Notification::new(Severity::Warning, key)
+    }
+
+    pub fn error(key: &'static str) -> Self {
+        Notification::new(Severity::Error, key)
+    }
+
+    /// Adds the given argument to the message.
+    pub fn with_arg(mut self, name: &'static str, value: Argument) -> Self {
+        self.args.push((name, value));
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    pub fn args(&self) -> &[(&'static str, Argument)] {
+        &self.args
+    }
+}
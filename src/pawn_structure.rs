@@ -0,0 +1,134 @@
+//! Pawn structure, the doubled, isolated, backward and passed pawns of each side, which the
+//! engine's evaluation weighs and the analysis shows over the board.
+//!
+//! A pawn is doubled when another pawn of its side is on its file, and isolated when there are
+//! none on the files either side of it. It is passed when no pawn of the other side stands in
+//! front of it on its file or those either side, so none can stop or capture it on its way to
+//! promote. It is backward when the pawns of its side on the files either side have all gone past
+//! it, so none can come up to defend it, and the square in front of it is attacked by a pawn of the
+//! other side.
+
+use strum_macros::EnumIter;
+
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType};
+
+/// What is weak or strong about a pawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum PawnFeature {
+    Doubled,
+    Isolated,
+    Backward,
+    Passed,
+}
+
+impl PawnFeature {
+    /// Returns the locale key of the name of the feature.
+    pub fn key(&self) -> &'static str {
+        match self {
+            PawnFeature::Doubled => "pawn-doubled",
+            PawnFeature::Isolated => "pawn-isolated",
+            PawnFeature::Backward => "pawn-backward",
+            PawnFeature::Passed => "pawn-passed",
+        }
+    }
+}
+
+/// A pawn on the board, with its features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StructuredPawn {
+    pub position: BoardPosition,
+    pub color: PieceColor,
+    /// Whether the pawn has each feature, in the order of [PawnFeature].
+    features: [bool; 4],
+}
+
+impl StructuredPawn {
+    pub fn has(&self, feature: PawnFeature) -> bool {
+        self.features[feature as usize]
+    }
+}
+
+/// Returns the change in rank index of a pawn of the given color moving forwards.
+fn forwards(color: &PieceColor) -> i32 {
+    match color {
+        PieceColor::White => -1,
+        PieceColor::Black => 1,
+    }
+}
+
+/// Returns every pawn on the board with its features.
+pub fn pawn_structure(board: &ChessBoard) -> Vec<StructuredPawn> {
+    let pawns: Vec<(BoardPosition, PieceColor)> = board
+        .size()
+        .positions()
+        .filter(|position| board.get_piece_type(position) == Some(PieceType::Pawn))
+        .filter_map(|position| Some((position, board.get_piece_color(&position)?)))
+        .collect();
+    pawns
+        .iter()
+        .map(|(position, color)| {
+            let (rank, file) = (*position.rank() as i32, *position.file() as i32);
+            let direction = forwards(color);
+            // How far in front of the pawn another pawn is, which is negative behind it
+            let ahead = |other: &BoardPosition| (*other.rank() as i32 - rank) * direction;
+            let file_distance = |other: &BoardPosition| (*other.file() as i32 - file).abs();
+            let own = || {
+                pawns
+                    .iter()
+                    .filter(|(other, other_color)| other_color == color && other != position)
+                    .map(|(other, _)| other)
+            };
+            let neighbours = || own().filter(|other| file_distance(other) == 1);
+            let doubled = own().any(|other| file_distance(other) == 0);
+            let isolated = neighbours().next().is_none();
+            let passed = !pawns.iter().any(|(other, other_color)| {
+                other_color != color && file_distance(other) <= 1 && ahead(other) > 0
+            });
+            let stop_attacked = pawns.iter().any(|(other, other_color)| {
+                other_color != color && file_distance(other) == 1 && ahead(other) == 2
+            });
+            let backward =
+                !isolated && !passed && stop_attacked && neighbours().all(|other| ahead(other) > 0);
+            StructuredPawn {
+                position: *position,
+                color: *color,
+                features: [doubled, isolated, backward, passed],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_pawn_structure() {
+        let board = ChessBoard::with_position(
+            &Fen::parse("4k3/8/8/P1p5/P1P1P3/3P4/8/4K3 w - - 0 1").unwrap(),
+        );
+        let structure = pawn_structure(&board);
+        let features = |square: &str| {
+            let position = BoardPosition::from_algebraic(square).unwrap();
+            let pawn = structure
+                .iter()
+                .find(|pawn| pawn.position == position)
+                .unwrap();
+            PawnFeature::iter()
+                .map(|feature| pawn.has(feature))
+                .collect::<Vec<bool>>()
+        };
+        assert_eq!(structure.len(), 6);
+        assert_eq!(features("a5"), [true, true, false, true]);
+        assert_eq!(features("a4"), [true, true, false, true]);
+        assert_eq!(features("c4"), [false, false, false, false]);
+        // The pawns either side have gone past it, and c5 attacks the square in front of it
+        assert_eq!(features("d3"), [false, false, true, false]);
+        assert_eq!(features("e4"), [false, false, false, true]);
+        assert_eq!(features("c5"), [false, true, false, false]);
+    }
+}
@@ -0,0 +1,604 @@
+//! Reads and writes games in [Portable Game Notation (PGN)](https://en.wikipedia.org/wiki/Portable_Game_Notation).
+//!
+//! Games are written with the seven tag roster and the `TimeControl` tag, plus the `SetUp` and
+//! `FEN` tags for games which do not start from the standard position. Games of a variant get the
+//! `Variant` tag, and the `SetUp` and `FEN` tags wherever they start, with the FEN
+//! [extended](crate::fen) for the variant. Games played with time odds also get
+//! `WhiteTimeControl` and `BlackTimeControl` tags, and games which reach a known opening get the
+//! `ECO` and `Opening` tags. When reading, the tags are kept and the game is played in the
+//! variant of the `Variant` tag from the `FEN` tag, and only the main line of the movetext is
+//! used; comments, variations and annotation glyphs are skipped.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, PieceColor, PieceType, ResetBoardEvent, Variant};
+use crate::eco::classify;
+use crate::fen::Fen;
+use crate::telemetry::GameTelemetry;
+
+/// The result of a game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    Win(PieceColor),
+    Draw,
+    /// The game is still in progress or its result is unknown.
+    Unfinished,
+}
+
+impl GameResult {
+    /// Returns the result of the game on the given board.
+    pub fn of(board: &ChessBoard) -> Self {
+        match (board.game_end_status(), board.winner()) {
+            (None, _) => GameResult::Unfinished,
+            (Some(_), Some(winner)) => GameResult::Win(*winner),
+            (Some(_), None) => GameResult::Draw,
+        }
+    }
+
+    /// Reads the result from the token which ends the movetext.
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(GameResult::Win(PieceColor::White)),
+            "0-1" => Some(GameResult::Win(PieceColor::Black)),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unfinished),
+            _ => None,
+        }
+    }
+
+    /// Returns the token for the result.
+    pub fn as_token(&self) -> &'static str {
+        match self {
+            GameResult::Win(PieceColor::White) => "1-0",
+            GameResult::Win(PieceColor::Black) => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unfinished => "*",
+        }
+    }
+}
+
+/// Returns the value of the `Variant` tag of games of the given variant.
+fn variant_tag(variant: &Variant) -> &'static str {
+    match variant {
+        Variant::Standard => "Standard",
+        Variant::Duck => "Duck",
+        Variant::Antichess => "Antichess",
+    }
+}
+
+/// Reads the variant from the value of a `Variant` tag, as written by this and other programs.
+fn read_variant_tag(value: &str) -> Option<Variant> {
+    match value.to_lowercase().as_str() {
+        "standard" | "chess" | "from position" => Some(Variant::Standard),
+        "duck" | "duck chess" => Some(Variant::Duck),
+        "antichess" => Some(Variant::Antichess),
+        _ => None,
+    }
+}
+
+/// Returns the standard algebraic notation of the given move on the given board, which is the
+/// board before the move is made. Unlike [Move::as_algebraic], the square the piece moves from
+/// is given where another piece of the same type could also move to the square, checks are
+/// marked and castles are written with the letter O, as PGN requires.
+fn san(piece_move: &Move, board: &ChessBoard) -> String {
+    let mut text = piece_move.as_algebraic();
+    if piece_move.is_castle() {
+        // No square has a 0, so only the castle itself is replaced, and not a duck's square
+        text = text.replace('0', "O");
+    }
+    if !piece_move.is_castle() && *piece_move.piece_type() != PieceType::Pawn {
+        let others: Vec<Move> = board
+            .legal_moves_iter()
+            .filter(|other| {
+                other.piece_type() == piece_move.piece_type()
+                    && other.to() == piece_move.to()
+                    && other.from() != piece_move.from()
+            })
+            .collect();
+        if !others.is_empty() {
+            let from = piece_move.from();
+            let file = (b'a' + *from.file() as u8) as char;
+            let rank = (b'0' + (8 - *from.rank()) as u8) as char;
+            let disambiguation = if others
+                .iter()
+                .all(|other| other.from().file() != from.file())
+            {
+                file.to_string()
+            } else if others
+                .iter()
+                .all(|other| other.from().rank() != from.rank())
+            {
+                rank.to_string()
+            } else {
+                format!("{}{}", file, rank)
+            };
+            text.insert_str(1, &disambiguation);
+        }
+    }
+    if piece_move.is_checkmate() {
+        text.push('#');
+    } else if piece_move.is_check() {
+        text.push('+');
+    }
+    text
+}
+
+/// Writes the game played on the given board from the given starting position, where the given
+/// moves are the moves made from the starting position, the players are the names of white and
+/// black and the time controls are the times white and black started with. The
+/// [telemetry](crate::telemetry) of the game is written with it when it has been recorded.
+pub fn write_pgn(
+    start: &Fen,
+    moves: &[Move],
+    board: &ChessBoard,
+    players: [&str; 2],
+    time_controls: &Option<[Duration; 2]>,
+    telemetry: Option<&GameTelemetry>,
+) -> String {
+    let move_times = telemetry
+        .and_then(GameTelemetry::move_times)
+        .unwrap_or_default();
+    let result = GameResult::of(board).as_token();
+    let variant = *board.variant();
+    let mut replay_board = ChessBoard::with_position(start).with_variant(variant);
+    let mut positions = vec![replay_board.to_fen()];
+    let mut tokens: Vec<String> = Vec::new();
+    for (index, piece_move) in moves.iter().enumerate() {
+        match piece_move.piece_color() {
+            PieceColor::White => tokens.push(format!("{}.", replay_board.move_number())),
+            PieceColor::Black if index == 0 => {
+                tokens.push(format!("{}...", replay_board.move_number()))
+            }
+            PieceColor::Black => {}
+        }
+        tokens.push(san(piece_move, &replay_board));
+        if let Some(time) = move_times.get(index) {
+            let seconds = time.as_secs();
+            tokens.push(format!(
+                "{{[%emt {}:{:02}:{:02}]}}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ));
+        }
+        replay_board.apply_move(piece_move);
+        positions.push(replay_board.to_fen());
+    }
+
+    let mut pgn = String::new();
+    for (tag, value) in [
+        ("Event", "Casual game"),
+        ("Site", "ChessComputer"),
+        ("Date", "????.??.??"),
+        ("Round", "-"),
+        ("White", players[0]),
+        ("Black", players[1]),
+        ("Result", result),
+    ] {
+        pgn.push_str(&format!("[{} \"{}\"]\n", tag, value));
+    }
+    match time_controls {
+        None => pgn.push_str("[TimeControl \"-\"]\n"),
+        Some([white, black]) if white == black => {
+            pgn.push_str(&format!("[TimeControl \"{}\"]\n", white.as_secs()))
+        }
+        Some([white, black]) => pgn.push_str(&format!(
+            "[TimeControl \"?\"]\n[WhiteTimeControl \"{}\"]\n[BlackTimeControl \"{}\"]\n",
+            white.as_secs(),
+            black.as_secs()
+        )),
+    }
+    if variant != Variant::Standard {
+        pgn.push_str(&format!("[Variant \"{}\"]\n", variant_tag(&variant)));
+    }
+    if variant != Variant::Standard || start.to_string() != Fen::default().to_string() {
+        pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", start));
+    }
+    if let Some(analysed) = telemetry.and_then(GameTelemetry::analysed) {
+        let used = if analysed { "Yes" } else { "No" };
+        pgn.push_str(&format!("[AnalysisUsed \"{}\"]\n", used));
+    }
+    // The openings are of standard chess
+    if let Some(opening) = classify(positions.iter()).filter(|_| variant == Variant::Standard) {
+        pgn.push_str(&format!(
+            "[ECO \"{}\"]\n[Opening \"{}\"]\n",
+            opening.code(),
+            opening.name()
+        ));
+    }
+    pgn.push('\n');
+
+    tokens.push(result.to_string());
+    pgn.push_str(&tokens.join(" "));
+    pgn.push('\n');
+    pgn
+}
+
+/// A game read from PGN.
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    /// The names and values of the tags, in the order they were given.
+    tags: Vec<(String, String)>,
+    start: Fen,
+    variant: Variant,
+    /// The moves of the main line.
+    moves: Vec<Move>,
+    result: GameResult,
+}
+
+impl PgnGame {
+    pub fn start(&self) -> &Fen {
+        &self.start
+    }
+
+    pub fn variant(&self) -> &Variant {
+        &self.variant
+    }
+
+    /// Returns the board at the start of the game, played in its variant.
+    pub fn start_board(&self) -> ChessBoard {
+        ChessBoard::with_position(&self.start).with_variant(self.variant)
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn result(&self) -> GameResult {
+        self.result
+    }
+
+    /// Returns the value of the tag with the given name, if the game has it.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Splits the given PGN into the text of each game, where each game starts at the first tag after
+/// the movetext of the previous game.
+fn split_games(text: &str) -> Vec<String> {
+    let mut games = vec![String::new()];
+    let mut has_movetext = false;
+    for line in text.lines().map(str::trim) {
+        let is_tag = line.starts_with('[');
+        if is_tag && has_movetext {
+            games.push(String::new());
+            has_movetext = false;
+        }
+        has_movetext |= !is_tag && !line.is_empty();
+        let game = games.last_mut().unwrap();
+        game.push_str(line);
+        game.push('\n');
+    }
+    games
+}
+
+/// A game being read from PGN a few moves at a time, so that long games can be replayed over
+/// several frames rather than all at once.
+#[derive(Clone)]
+pub struct PgnReplay {
+    tags: Vec<(String, String)>,
+    start: Fen,
+    variant: Variant,
+    /// The moves of the main line in standard algebraic notation.
+    moves: Vec<String>,
+    result: GameResult,
+    /// The position reached by the moves replayed so far.
+    board: ChessBoard,
+}
+
+impl PgnReplay {
+    /// Reads the tags and main line of the text of a single game without replaying its moves, or
+    /// returns None if it has neither, or its variant or starting position is not valid.
+    pub fn new(text: &str) -> Option<Self> {
+        let mut tags = Vec::new();
+        let mut movetext = String::new();
+        for line in text.lines() {
+            if let Some(tag) = line.strip_prefix('[').and_then(|tag| tag.strip_suffix(']')) {
+                let (name, value) = tag.split_once(' ')?;
+                let value = value.trim().trim_matches('"');
+                tags.push((name.to_string(), value.to_string()));
+            } else if !line.starts_with('%') {
+                movetext.push_str(line);
+                movetext.push('\n');
+            }
+        }
+        // The FEN is read once the variant is known, as the tags can come in any order
+        let tag = |name: &str| {
+            tags.iter()
+                .find(|(tag, _)| tag == name)
+                .map(|(_, value): &(String, String)| value.as_str())
+        };
+        let variant = match tag("Variant") {
+            Some(value) => read_variant_tag(value)?,
+            None => Variant::Standard,
+        };
+        let start = match tag("FEN") {
+            Some(value) => Fen::parse_variant(value, variant)?,
+            None => Fen::default(),
+        };
+
+        // Remove comments and variations, which may be nested
+        let mut main_line = String::new();
+        let mut variation_depth = 0;
+        let mut in_comment = false;
+        let mut in_line_comment = false;
+        for symbol in movetext.chars() {
+            match symbol {
+                _ if in_line_comment => in_line_comment = symbol != '\n',
+                '}' if in_comment => in_comment = false,
+                _ if in_comment => {}
+                '{' => in_comment = true,
+                ';' => in_line_comment = true,
+                '(' => variation_depth += 1,
+                ')' => variation_depth -= 1,
+                _ if variation_depth > 0 => {}
+                _ => main_line.push(symbol),
+            }
+        }
+
+        let mut moves = Vec::new();
+        let mut result = GameResult::Unfinished;
+        for token in main_line.split_whitespace() {
+            if let Some(token_result) = GameResult::from_token(token) {
+                result = token_result;
+                break;
+            }
+            // Skip move numbers such as "12." and "12...", which may be attached to the move
+            let token =
+                token.trim_start_matches(|symbol: char| symbol.is_ascii_digit() || symbol == '.');
+            if token.is_empty() || token.starts_with('$') {
+                continue;
+            }
+            moves.push(token.to_string());
+        }
+        // Text without tags or moves is not a game, even though it is an empty one
+        if moves.is_empty() && tags.is_empty() {
+            return None;
+        }
+        Some(PgnReplay {
+            tags,
+            board: ChessBoard::with_position(&start).with_variant(variant),
+            start,
+            variant,
+            moves,
+            result,
+        })
+    }
+
+    /// Returns the number of moves in the game.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Returns the value of the tag with the given name, if the game has it.
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the number of moves replayed so far.
+    pub fn replayed(&self) -> usize {
+        self.board.past_moves().len()
+    }
+
+    /// Replays up to the given number of further moves, returning whether every move has now
+    /// been replayed, or None if a move is not legal.
+    pub fn step(&mut self, moves: usize) -> Option<bool> {
+        let replayed = self.replayed();
+        let end = self.moves.len().min(replayed.saturating_add(moves));
+        for text in &self.moves[replayed..end] {
+            let piece_move = Move::from_algebraic(text, &self.board)?;
+            self.board.apply_move(&piece_move);
+        }
+        Some(end == self.moves.len())
+    }
+
+    /// Returns the game with the moves replayed so far.
+    pub fn game(&self) -> PgnGame {
+        PgnGame {
+            tags: self.tags.clone(),
+            start: self.start.clone(),
+            variant: self.variant,
+            moves: self.board.past_moves().clone(),
+            result: self.result,
+        }
+    }
+
+    /// Returns the event which resets the board to the position reached by the moves replayed so
+    /// far.
+    pub fn reset_event(&self) -> ResetBoardEvent {
+        ResetBoardEvent::with_past_moves(self.board.to_fen(), self.board.past_moves().clone())
+            .with_variant(self.variant)
+    }
+}
+
+impl fmt::Debug for PgnReplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PgnReplay")
+            .field("tags", &self.tags)
+            .field("start", &self.start)
+            .field("moves", &self.moves)
+            .field("result", &self.result)
+            .field("replayed", &self.replayed())
+            .finish()
+    }
+}
+
+/// Reads the text of a single game, or returns None if it is not a game with only legal moves.
+fn read_game(text: &str) -> Option<PgnGame> {
+    let mut replay = PgnReplay::new(text)?;
+    replay.step(usize::MAX)?;
+    Some(replay.game())
+}
+
+/// Reads every game in the given PGN, skipping any with illegal moves.
+pub fn read_games(text: &str) -> Vec<PgnGame> {
+    split_games(text)
+        .iter()
+        .filter_map(|game| read_game(game))
+        .collect()
+}
+
+/// Reads the first game in the given PGN to be replayed, or returns None if the text is not a
+/// game.
+pub fn replay_pgn(text: &str) -> Option<PgnReplay> {
+    PgnReplay::new(&split_games(text)[0])
+}
+
+/// Reads every game in the given PGN to be replayed, skipping any text which is not a game.
+pub fn replay_games(text: &str) -> Vec<PgnReplay> {
+    split_games(text)
+        .iter()
+        .filter_map(|game| PgnReplay::new(game))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replays the first game in the given PGN, returning the event which resets the board to its
+    /// final position.
+    fn read_pgn(text: &str) -> Option<ResetBoardEvent> {
+        let mut replay = replay_pgn(text)?;
+        replay.step(usize::MAX)?;
+        Some(replay.reset_event())
+    }
+
+    #[test]
+    fn test_write_pgn() {
+        let moves = [
+            "e4", "e5", "Nf3", "Nc6", "Nc3", "Nf6", "Nd5", "Nxd5", "exd5",
+        ];
+        let event = ResetBoardEvent::from_algebraic_moves(&Fen::default(), &moves).unwrap();
+        let board = ChessBoard::with_position(event.fen());
+        let time_controls = Some([Duration::from_secs(300); 2]);
+        let pgn = write_pgn(
+            &Fen::default(),
+            event.past_moves(),
+            &board,
+            ["?", "?"],
+            &time_controls,
+            None,
+        );
+        assert!(pgn.starts_with("[Event \"Casual game\"]\n"));
+        assert!(pgn.contains("[Result \"*\"]\n[TimeControl \"300\"]\n"));
+        assert!(pgn.contains("[ECO \"C47\"]\n[Opening \"Four Knights Game\"]\n\n"));
+        assert!(!pgn.contains("[FEN"));
+        assert!(pgn.ends_with("\n1. e4 e5 2. Nf3 Nc6 3. Nc3 Nf6 4. Nd5 Nxd5 5. exd5 *\n"));
+
+        // Black to move from a set up position, with a move which needs disambiguation
+        let start = Fen::from_string("4k3/8/8/8/8/8/4K3/R6R b - - 0 7");
+        let event = ResetBoardEvent::from_algebraic_moves(&start, &["Kd7", "Rad1+"]).unwrap();
+        let time_controls = Some([Duration::from_secs(300), Duration::from_secs(60)]);
+        let pgn = write_pgn(
+            &start,
+            event.past_moves(),
+            &board,
+            ["Alice", "Bob"],
+            &time_controls,
+            None,
+        );
+        assert!(pgn.contains("[White \"Alice\"]\n[Black \"Bob\"]\n"));
+        assert!(pgn.contains("[WhiteTimeControl \"300\"]\n[BlackTimeControl \"60\"]\n"));
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4K3/R6R b - - 0 7\"]\n"));
+        assert!(pgn.ends_with("\n7... Kd7 8. Rad1+ *\n"), "{}", pgn);
+
+        // Castles are written with the letter O, and read back as the same moves
+        let start = Fen::from_string("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+        let event = ResetBoardEvent::from_algebraic_moves(&start, &["0-0", "0-0-0"]).unwrap();
+        let board = ChessBoard::with_position(event.fen());
+        let pgn = write_pgn(&start, event.past_moves(), &board, ["?", "?"], &None, None);
+        assert!(pgn.ends_with("\n1. O-O O-O-O *\n"), "{}", pgn);
+        let games = read_games(&pgn);
+        assert_eq!(games[0].moves(), event.past_moves().as_slice());
+    }
+
+    #[test]
+    fn test_read_pgn() {
+        let pgn = "[Event \"Casual game\"]\n\
+            [FEN \"4k3/8/8/8/8/8/4K3/R6R b - - 0 7\"]\n\
+            \n\
+            7... Kd7 {The king heads for the centre} 8. Rad1+ $1 (8. O-O-O+ Ke6) Kc7\n\
+            ; A line comment\n\
+            9.Rd2 1-0\n\
+            \n\
+            [Event \"The next game\"]\n\
+            \n\
+            1. e4 *\n";
+        let event = read_pgn(pgn).unwrap();
+        assert_eq!(event.past_moves().len(), 4);
+        assert_eq!(event.fen().to_string(), "8/2k5/8/8/8/8/3RK3/7R b - - 4 9");
+
+        let event = read_pgn("1. e4 e5 2. Nf3").unwrap();
+        assert_eq!(event.past_moves().len(), 3);
+
+        assert!(read_pgn("1. e4 e5 2. Ke3").is_none());
+
+        let games = read_games(pgn);
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].result(), GameResult::Win(PieceColor::White));
+        assert_eq!(games[0].tag("Event"), Some("Casual game"));
+        assert_eq!(games[0].tag("White"), None);
+        assert_eq!(games[1].moves().len(), 1);
+        assert_eq!(games[1].result(), GameResult::Unfinished);
+        assert!(read_pgn("Some text which is not a game").is_none());
+        assert!(read_pgn("").is_none());
+    }
+
+    #[test]
+    fn test_pgn_replay() {
+        let mut replay = replay_pgn("1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 *").unwrap();
+        assert_eq!((replay.replayed(), replay.len()), (0, 6));
+        assert_eq!(replay.step(4), Some(false));
+        assert_eq!(replay.replayed(), 4);
+        assert_eq!(replay.step(4), Some(true));
+        assert_eq!(replay.replayed(), 6);
+        assert_eq!(
+            replay.reset_event().fen().to_string(),
+            "r1bqkbnr/1ppp1ppp/p1n5/1B2p3/4P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 0 4"
+        );
+
+        // Games of the variants are replayed by their rules and load the board in their variant
+        let mut board = ChessBoard::with_position(&Fen::default()).with_variant(Variant::Antichess);
+        for text in ["e3", "b5", "Bxb5", "a6", "Bxd7", "Bxd7"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let pgn = write_pgn(
+            &Fen::default(),
+            board.past_moves(),
+            &board,
+            ["?", "?"],
+            &None,
+            None,
+        );
+        assert!(pgn.contains("[Variant \"Antichess\"]\n[SetUp \"1\"]\n[FEN"));
+        assert!(!pgn.contains("[ECO"));
+        let event = read_pgn(&pgn).unwrap();
+        assert_eq!(*event.variant(), Some(Variant::Antichess));
+        assert_eq!(event.fen().to_string(), board.to_fen().to_string());
+
+        let start = Fen::from_string("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let mut board = ChessBoard::with_position(&start).with_variant(Variant::Duck);
+        for text in ["Kd2@e5", "Kd7@d4"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let pgn = write_pgn(&start, board.past_moves(), &board, ["?", "?"], &None, None);
+        let event = read_pgn(&pgn).unwrap();
+        assert_eq!(event.fen().to_string(), "8/3k4/8/8/3*4/8/3K4/8 w - - 2 2");
+        assert!(read_pgn("[Variant \"Crazyhouse\"]\n\n1. e4 *").is_none());
+
+        // The moves before an illegal move are still replayed
+        let mut replay = replay_pgn("1. e4 e5 2. Ke3").unwrap();
+        assert_eq!(replay.step(usize::MAX), None);
+        assert_eq!(replay.replayed(), 2);
+    }
+}
@@ -0,0 +1,194 @@
+//! Checkmate and stalemate practice, [puzzles](crate::puzzle) generated from templates of the
+//! basic mates and kept only when a search confirms them.
+//!
+//! Each template places the pieces at random around a familiar pattern, such as a king on the
+//! back rank behind its pawns, and all of them have white to move against a lone or boxed-in
+//! king. The templates are rated so the practice starts with mates in one and ends with mates in
+//! two, and the positions of each template get a little harder to rate as they go on.
+
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType, BOARD_SIZE};
+use crate::fen::Fen;
+use crate::puzzle::{Goal, Puzzle};
+use crate::random::Random;
+use crate::underpromotion::random_square;
+
+/// The number of puzzles generated from each template.
+const PER_TEMPLATE: usize = 3;
+/// The rating added to each puzzle of a template after the first.
+const RATING_STEP: u32 = 50;
+/// The number of positions tried for each puzzle before giving up on it.
+const ATTEMPTS: usize = 500;
+
+/// A pattern the pieces of a puzzle are placed around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+enum Template {
+    /// A queen mates a king held on the back rank by the other king.
+    QueenMate,
+    /// A rook mates a king shut in behind its own pawns.
+    BackRankMate,
+    /// A queen must hem in a cornered king without leaving it without a move.
+    AvoidStalemate,
+    /// A rook and king need a quiet move before the mate.
+    RookMateInTwo,
+}
+
+impl Template {
+    fn goal(&self) -> Goal {
+        match self {
+            Template::QueenMate | Template::BackRankMate => Goal::MateInOne,
+            Template::AvoidStalemate => Goal::AvoidStalemate,
+            Template::RookMateInTwo => Goal::MateInTwo,
+        }
+    }
+
+    /// Returns the rating of the first puzzle of the template.
+    fn rating(&self) -> u32 {
+        match self {
+            Template::QueenMate => 400,
+            Template::BackRankMate => 600,
+            Template::AvoidStalemate => 800,
+            Template::RookMateInTwo => 1000,
+        }
+    }
+
+    /// Places pieces at random following the pattern.
+    fn candidate(&self, random: &mut Random) -> Vec<(PieceColor, PieceType, BoardPosition)> {
+        match self {
+            Template::QueenMate | Template::RookMateInTwo => {
+                // The white king stands two ranks in front of the black king, or beside that
+                let file = random.below(BOARD_SIZE);
+                let black_king = BoardPosition::new(0, file);
+                let white_file = (file + random.below(3)).saturating_sub(1);
+                let white_king = BoardPosition::new(2, white_file.min(BOARD_SIZE - 1));
+                let piece_type = match self {
+                    Template::QueenMate => PieceType::Queen,
+                    _ => PieceType::Rook,
+                };
+                let piece = random_square(random, 0..BOARD_SIZE, &[black_king, white_king]);
+                vec![
+                    (PieceColor::Black, PieceType::King, black_king),
+                    (PieceColor::White, PieceType::King, white_king),
+                    (PieceColor::White, piece_type, piece),
+                ]
+            }
+            Template::BackRankMate => {
+                let file = 1 + random.below(BOARD_SIZE - 2);
+                let mut pieces = vec![(
+                    PieceColor::Black,
+                    PieceType::King,
+                    BoardPosition::new(0, file),
+                )];
+                for pawn_file in file - 1..=file + 1 {
+                    pieces.push((
+                        PieceColor::Black,
+                        PieceType::Pawn,
+                        BoardPosition::new(1, pawn_file),
+                    ));
+                }
+                let white_king = BoardPosition::new(BOARD_SIZE - 1, random.below(BOARD_SIZE));
+                let rook = random_square(random, 2..BOARD_SIZE, &[white_king]);
+                pieces.push((PieceColor::White, PieceType::King, white_king));
+                pieces.push((PieceColor::White, PieceType::Rook, rook));
+                pieces
+            }
+            Template::AvoidStalemate => {
+                let black_king = BoardPosition::new(0, [0, BOARD_SIZE - 1][random.below(2)]);
+                let white_king = random_square(random, 0..4, &[black_king]);
+                let queen = random_square(random, 0..4, &[black_king, white_king]);
+                vec![
+                    (PieceColor::Black, PieceType::King, black_king),
+                    (PieceColor::White, PieceType::King, white_king),
+                    (PieceColor::White, PieceType::Queen, queen),
+                ]
+            }
+        }
+    }
+}
+
+/// Returns whether white can stalemate black with a move on the given board.
+fn can_stalemate(board: &ChessBoard) -> bool {
+    board.legal_moves_iter().any(|piece_move| {
+        let mut after = board.clone();
+        after.apply_move(&piece_move);
+        !after.in_check(&PieceColor::Black) && after.legal_moves_iter().next().is_none()
+    })
+}
+
+/// Generates a puzzle from the given template, or None if no position was found.
+fn generate_puzzle(template: Template, rating: u32, random: &mut Random) -> Option<Puzzle> {
+    (0..ATTEMPTS).find_map(|_| {
+        let fen = Fen::with_pieces(&template.candidate(random), PieceColor::White);
+        let board = ChessBoard::with_position(&fen);
+        // The player not to move cannot be in check
+        if board.in_check(&PieceColor::Black) {
+            return None;
+        }
+        // Searching is slow, so positions which cannot fit the template are dropped beforehand
+        let mate_in_one = Puzzle::new(fen.clone(), rating, Goal::MateInOne);
+        let fits = match template.goal() {
+            Goal::MateInOne | Goal::Improve(_) | Goal::FindLine => true,
+            Goal::MateInTwo => mate_in_one.solution().is_none(),
+            Goal::AvoidStalemate => mate_in_one.solution().is_none() && can_stalemate(&board),
+        };
+        let puzzle = Puzzle::new(fen, rating, template.goal());
+        (fits && puzzle.solution().is_some()).then_some(puzzle)
+    })
+}
+
+/// Generates the practice puzzles from the given seed, from the easiest to the hardest.
+pub fn generate(seed: u64) -> Vec<Puzzle> {
+    let mut random = Random::new(seed);
+    let mut puzzles = Vec::new();
+    for template in Template::iter() {
+        for index in 0..PER_TEMPLATE as u32 {
+            let rating = template.rating() + index * RATING_STEP;
+            puzzles.extend(generate_puzzle(template, rating, &mut random));
+        }
+    }
+    puzzles
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let puzzles = generate(3);
+        assert_eq!(puzzles.len(), PER_TEMPLATE * Template::iter().count());
+        assert!(puzzles
+            .windows(2)
+            .all(|pair| pair[0].rating() <= pair[1].rating()));
+        for puzzle in &puzzles {
+            let board = ChessBoard::with_position(puzzle.fen());
+            let solution = puzzle.solution().unwrap();
+            assert!(board.valid_move(&solution, &Some(PieceColor::White), &true));
+        }
+        assert_eq!(
+            generate(3)
+                .iter()
+                .map(|puzzle| puzzle.fen().to_string())
+                .collect::<Vec<_>>(),
+            puzzles
+                .iter()
+                .map(|puzzle| puzzle.fen().to_string())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_avoid_stalemate() {
+        // Qc7 stalemates the king in the corner, where Qb6 leaves it a move
+        let fen = Fen::from_string("k7/8/1K6/8/8/8/2Q5/8 w - - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        let puzzle = Puzzle::new(fen, 800, Goal::AvoidStalemate);
+        assert!(can_stalemate(&board));
+        assert!(!puzzle.is_solved_by(&Move::from_algebraic("Qc7", &board).unwrap()));
+        assert!(puzzle.is_solved_by(&Move::from_algebraic("Qc6", &board).unwrap()));
+    }
+}
@@ -0,0 +1,370 @@
+//! Local player profiles, so each person sharing the computer keeps their own rating, settings and
+//! statistics.
+//!
+//! The profiles are read from [PROFILES_PATH] when the app starts and written back whenever one
+//! changes. There is always at least one, and the active player is the one games are stored for
+//! in the [games database](crate::database). Each profile remembers the engine depth and time
+//! control its player last used, which are applied when the player is chosen, and its rating is
+//! updated after each game against the engine.
+
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{DetectChanges, Res, ResMut, Resource, Startup, Update};
+use serde::{Deserialize, Serialize};
+
+use crate::clock::ChessClock;
+use crate::database::PlayerResult;
+use crate::engine::Engine;
+use crate::engine_match::expected_score;
+use crate::storage;
+
+/// The file the profiles are stored in.
+const PROFILES_PATH: &str = "players.ron";
+
+/// The rating of a new player.
+const DEFAULT_RATING: i32 = 1200;
+
+/// The most a rating changes after a single game.
+const RATING_CHANGE: f64 = 32.;
+
+/// The names of the players are no longer than this many characters.
+pub const MAX_NAME_LENGTH: usize = 24;
+
+pub(super) struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Profiles>()
+            .add_systems(Startup, load_profiles)
+            .add_systems(Update, track_preferences);
+    }
+}
+
+/// Returns the rough rating of the engine searching to the given depth.
+fn engine_rating(depth: u32) -> i32 {
+    600 + 400 * depth as i32
+}
+
+/// The result of a skill calibration, which estimates a player's rating from a few positions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// The date of the calibration, in the format of the PGN `Date` tag.
+    pub date: String,
+    pub rating: i32,
+    /// The recommended search depth of the engine.
+    pub depth: u32,
+    /// The recommended time control, in minutes for each player.
+    pub minutes: u64,
+}
+
+/// The settings a player last used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preferences {
+    /// The search depth of the engine.
+    pub depth: u32,
+    /// The time control, in seconds for each player, if the player has played with the clock.
+    pub time_control: Option<u64>,
+}
+
+impl Preferences {
+    /// Returns the preferences the engine and clock are set to.
+    fn of(engine: &Engine, clock: &ChessClock) -> Self {
+        Preferences {
+            depth: engine.depth(),
+            time_control: clock
+                .time_controls()
+                .map(|time_controls| time_controls[0].as_secs()),
+        }
+    }
+}
+
+/// How well a player knows the names of the squares, from the notation trainer asking them to
+/// click named squares.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotationStats {
+    pub asked: u32,
+    pub correct: u32,
+    /// The time taken to find the squares clicked correctly, in milliseconds.
+    pub correct_millis: u64,
+    /// The quickest square clicked correctly, in milliseconds.
+    pub best_millis: Option<u64>,
+}
+
+impl NotationStats {
+    /// Counts an answer, which took the given time.
+    pub fn record(&mut self, correct: bool, time: Duration) {
+        self.asked += 1;
+        if correct {
+            let millis = time.as_millis() as u64;
+            self.correct += 1;
+            self.correct_millis += millis;
+            self.best_millis = Some(self.best_millis.map_or(millis, |best| best.min(millis)));
+        }
+    }
+
+    /// Returns the percentage of squares clicked correctly, if any were asked.
+    pub fn accuracy(&self) -> Option<u32> {
+        (self.asked > 0).then(|| (100 * self.correct + self.asked / 2) / self.asked)
+    }
+
+    /// Returns the average time taken to click a square correctly.
+    pub fn average_time(&self) -> Option<Duration> {
+        (self.correct > 0).then(|| Duration::from_millis(self.correct_millis / self.correct as u64))
+    }
+
+    pub fn best_time(&self) -> Option<Duration> {
+        self.best_millis.map(Duration::from_millis)
+    }
+}
+
+/// A player sharing the computer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerProfile {
+    name: String,
+    rating: i32,
+    preferences: Preferences,
+    /// The result of the player's last calibration, if they have calibrated.
+    calibration: Option<Calibration>,
+    /// Missing from the profiles saved before the notation trainer.
+    #[serde(default)]
+    notation: NotationStats,
+}
+
+impl PlayerProfile {
+    fn new(name: String, preferences: Preferences) -> Self {
+        PlayerProfile {
+            name,
+            rating: DEFAULT_RATING,
+            preferences,
+            calibration: None,
+            notation: NotationStats::default(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rating(&self) -> i32 {
+        self.rating
+    }
+
+    pub fn calibration(&self) -> &Option<Calibration> {
+        &self.calibration
+    }
+
+    /// Keeps the given calibration, which also sets the player's rating.
+    pub fn calibrate(&mut self, calibration: Calibration) {
+        self.rating = calibration.rating;
+        self.calibration = Some(calibration);
+    }
+
+    pub fn notation(&self) -> &NotationStats {
+        &self.notation
+    }
+
+    pub fn notation_mut(&mut self) -> &mut NotationStats {
+        &mut self.notation
+    }
+
+    /// Updates the rating with the given result against the engine searching to the given depth.
+    pub fn rate(&mut self, result: PlayerResult, depth: u32) {
+        let score = match result {
+            PlayerResult::Won => 1.,
+            PlayerResult::Drawn => 0.5,
+            PlayerResult::Lost => 0.,
+        };
+        let expected = expected_score((self.rating - engine_rating(depth)) as f64);
+        self.rating += (RATING_CHANGE * (score - expected)).round() as i32;
+    }
+
+    /// Sets the engine and the clock to the player's preferences.
+    pub fn apply_preferences(&self, engine: &mut Engine, clock: &mut ChessClock) {
+        engine.set_depth(self.preferences.depth);
+        if let Some(seconds) = self.preferences.time_control {
+            clock.set_time_control(Some(Duration::from_secs(seconds)));
+        }
+    }
+}
+
+/// The players sharing the computer, and which of them is playing.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Profiles {
+    players: Vec<PlayerProfile>,
+    active: usize,
+}
+
+impl Default for Profiles {
+    fn default() -> Self {
+        Profiles {
+            players: vec![PlayerProfile::new(
+                "Player 1".to_string(),
+                Preferences::of(&Engine::default(), &ChessClock::default()),
+            )],
+            active: 0,
+        }
+    }
+}
+
+impl Profiles {
+    pub fn players(&self) -> &[PlayerProfile] {
+        &self.players
+    }
+
+    pub fn active(&self) -> &PlayerProfile {
+        &self.players[self.active]
+    }
+
+    pub fn active_mut(&mut self) -> &mut PlayerProfile {
+        &mut self.players[self.active]
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    /// Makes the player with the given index the one playing.
+    pub fn select(&mut self, index: usize) {
+        if index < self.players.len() {
+            self.active = index;
+        }
+    }
+
+    /// Adds a player with the given name, using the settings of the player who was playing, and
+    /// makes them the one playing. Returns why the player cannot be added if the name is blank,
+    /// too long or taken.
+    pub fn add(&mut self, name: &str) -> Result<(), &'static str> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err("profile-invalid-name");
+        }
+        if self.players.iter().any(|player| player.name == name) {
+            return Err("profile-name-taken");
+        }
+        let preferences = self.active().preferences;
+        self.players
+            .push(PlayerProfile::new(name.to_string(), preferences));
+        self.active = self.players.len() - 1;
+        Ok(())
+    }
+
+    /// Removes the player playing, unless they are the only one. Their games stay in the
+    /// database.
+    pub fn remove_active(&mut self) {
+        if self.players.len() > 1 {
+            self.players.remove(self.active);
+            self.active = self.active.min(self.players.len() - 1);
+        }
+    }
+
+    /// Writes the profiles to [PROFILES_PATH].
+    pub fn save(&self) -> Result<(), String> {
+        let text = ron::to_string(self).map_err(|error| error.to_string())?;
+        storage::write(PROFILES_PATH, &text)
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn load_profiles(
+    mut profiles: ResMut<Profiles>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+) {
+    let Some(stored) = storage::read(PROFILES_PATH)
+        .and_then(|text| ron::from_str::<Profiles>(&text).ok())
+        .filter(|stored| stored.active < stored.players.len())
+    else {
+        return;
+    };
+    *profiles = stored;
+    profiles.active().apply_preferences(&mut engine, &mut clock);
+}
+
+/// Remembers the engine depth and time control of the player playing whenever they change. The
+/// clock being turned off is not remembered, as the trainers turn it off for their positions.
+fn track_preferences(engine: Res<Engine>, clock: Res<ChessClock>, mut profiles: ResMut<Profiles>) {
+    if !engine.is_changed() && !clock.is_changed() {
+        return;
+    }
+    let current = profiles.active().preferences;
+    let mut preferences = Preferences::of(&engine, &clock);
+    preferences.time_control = preferences.time_control.or(current.time_control);
+    if preferences != current {
+        profiles.active_mut().preferences = preferences;
+        // The preferences are saved again with the next change
+        profiles.save().ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let mut profiles = Profiles::default();
+        assert_eq!(profiles.add(" "), Err("profile-invalid-name"));
+        assert_eq!(profiles.add("Player 1"), Err("profile-name-taken"));
+        profiles.add(" Ada ").unwrap();
+        assert_eq!(profiles.active().name(), "Ada");
+        assert_eq!(profiles.active_index(), 1);
+
+        // Each player is rated on their own
+        profiles.active_mut().rate(PlayerResult::Won, 2);
+        assert_eq!(profiles.active().rating(), DEFAULT_RATING + 24);
+        profiles.select(0);
+        profiles.active_mut().rate(PlayerResult::Drawn, 2);
+        assert_eq!(profiles.active().rating(), DEFAULT_RATING + 8);
+
+        let text = ron::to_string(&profiles).unwrap();
+        let read: Profiles = ron::from_str(&text).unwrap();
+        assert_eq!(read, profiles);
+
+        profiles.select(1);
+        profiles.remove_active();
+        profiles.remove_active();
+        assert_eq!(profiles.players().len(), 1);
+        assert_eq!(profiles.active().name(), "Player 1");
+    }
+
+    #[test]
+    fn test_notation_stats() {
+        let mut stats = NotationStats::default();
+        assert_eq!(stats.accuracy(), None);
+        stats.record(true, Duration::from_millis(1500));
+        stats.record(false, Duration::from_millis(400));
+        stats.record(true, Duration::from_millis(900));
+        assert_eq!(stats.accuracy(), Some(67));
+        assert_eq!(stats.average_time(), Some(Duration::from_millis(1200)));
+        assert_eq!(stats.best_time(), Some(Duration::from_millis(900)));
+
+        // Profiles saved before the trainer are read without statistics
+        let profiles = Profiles::default();
+        let text = ron::to_string(&profiles).unwrap().replace(
+            ",notation:(asked:0,correct:0,correct_millis:0,best_millis:None)",
+            "",
+        );
+        assert!(!text.contains("notation"));
+        let read: Profiles = ron::from_str(&text).unwrap();
+        assert_eq!(read.active().notation(), &NotationStats::default());
+    }
+
+    #[test]
+    fn test_apply_preferences() {
+        let mut profiles = Profiles::default();
+        profiles.active_mut().preferences = Preferences {
+            depth: 3,
+            time_control: Some(300),
+        };
+        let (mut engine, mut clock) = (Engine::default(), ChessClock::default());
+        profiles.active().apply_preferences(&mut engine, &mut clock);
+        assert_eq!(engine.depth(), 3);
+        assert_eq!(*clock.time_controls(), Some([Duration::from_secs(300); 2]));
+        assert_eq!(
+            Preferences::of(&engine, &clock),
+            profiles.active().preferences
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Puzzles, positions in which the player to move can checkmate in one or two moves, or must
+//! avoid stalemating a lone king.
+//!
+//! The puzzles are embedded from `assets/puzzles.tsv`, which has the `rating` and `fen` columns
+//! of mates in one, and are kept in order of rating so they get harder as they are worked
+//! through. More puzzles are generated for [practice](crate::practice). Any move which reaches
+//! the goal solves a puzzle, so puzzles with more than one solution need no list of solutions,
+//! except those of [puzzle sets](crate::puzzle_set), which give the line to find.
+
+use std::sync::OnceLock;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::ChessBoard;
+use crate::engine::evaluate_position;
+use crate::fen::Fen;
+
+/// The embedded puzzles.
+const PUZZLES: &str = include_str!("../assets/puzzles.tsv");
+
+/// The depth the position after a move is searched to when checking it keeps the win.
+const DEPTH: u32 = 2;
+/// The evaluation in centipawns beyond which a player is taken to be winning.
+const WINNING: i32 = 500;
+
+/// What the player to move must do to solve a puzzle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Goal {
+    MateInOne,
+    /// Make a move after which every reply allows a mate in one.
+    MateInTwo,
+    /// Make a move which neither stalemates the opponent nor throws away the win.
+    AvoidStalemate,
+    /// Make a move after which the evaluation is above the given centipawns, from the player's
+    /// point of view, as a better move than a mistake made in the position.
+    Improve(i32),
+    /// Play the first move of the puzzle's solution line.
+    FindLine,
+}
+
+impl Goal {
+    /// Returns the locale key of the description of the goal.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Goal::MateInOne => "goal-mate-in-one",
+            Goal::MateInTwo => "goal-mate-in-two",
+            Goal::AvoidStalemate => "goal-avoid-stalemate",
+            Goal::Improve(_) => "goal-improve",
+            Goal::FindLine => "goal-find-line",
+        }
+    }
+
+    /// Returns the theme puzzles of the goal are given in exported puzzle sets.
+    pub fn theme(&self) -> &'static str {
+        match self {
+            Goal::MateInOne => "mate-in-one",
+            Goal::MateInTwo => "mate-in-two",
+            Goal::AvoidStalemate => "avoid-stalemate",
+            Goal::Improve(_) => "improve",
+            Goal::FindLine => "find-line",
+        }
+    }
+}
+
+/// Returns the given board after the given move.
+fn after_move(board: &ChessBoard, piece_move: &Move) -> ChessBoard {
+    let mut after = board.clone();
+    after.apply_move(piece_move);
+    after
+}
+
+/// Returns whether the player to move on the given board has been checkmated.
+fn is_mated(board: &ChessBoard) -> bool {
+    board
+        .active_color()
+        .is_some_and(|color| board.in_check(&color))
+        && board.legal_moves_iter().next().is_none()
+}
+
+/// Returns whether the player to move on the given board can checkmate in one move.
+fn has_mate_in_one(board: &ChessBoard) -> bool {
+    board
+        .legal_moves_iter()
+        .any(|piece_move| is_mated(&after_move(board, &piece_move)))
+}
+
+/// A position with a goal for the player to move.
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    fen: Fen,
+    /// How hard the puzzle is, on the scale of a player rating.
+    rating: u32,
+    goal: Goal,
+    /// The moves of the solution, from the player's first, if the puzzle gives them.
+    line: Vec<Move>,
+    /// What the puzzle is about, e.g. "fork" or "back-rank".
+    themes: Vec<String>,
+}
+
+impl Puzzle {
+    pub fn new(fen: Fen, rating: u32, goal: Goal) -> Self {
+        Puzzle {
+            fen,
+            rating,
+            goal,
+            line: Vec::new(),
+            themes: Vec::new(),
+        }
+    }
+
+    pub fn with_line(mut self, line: Vec<Move>) -> Self {
+        self.line = line;
+        self
+    }
+
+    pub fn with_themes(mut self, themes: Vec<String>) -> Self {
+        self.themes = themes;
+        self
+    }
+
+    pub fn fen(&self) -> &Fen {
+        &self.fen
+    }
+
+    pub fn rating(&self) -> u32 {
+        self.rating
+    }
+
+    pub fn goal(&self) -> Goal {
+        self.goal
+    }
+
+    pub fn themes(&self) -> &[String] {
+        &self.themes
+    }
+
+    /// Returns whether the given move solves the puzzle.
+    pub fn is_solved_by(&self, piece_move: &Move) -> bool {
+        let board = ChessBoard::with_position(&self.fen);
+        if !board.valid_move(piece_move, board.active_color(), &true) {
+            return false;
+        }
+        let after = after_move(&board, piece_move);
+        match self.goal {
+            Goal::MateInOne => is_mated(&after),
+            // A stalemate has no replies, so it is ruled out first
+            Goal::MateInTwo => {
+                is_mated(&after)
+                    || (after.legal_moves_iter().next().is_some()
+                        && after
+                            .legal_moves_iter()
+                            .all(|reply| has_mate_in_one(&after_move(&after, &reply))))
+            }
+            Goal::AvoidStalemate => {
+                is_mated(&after)
+                    || (after.legal_moves_iter().next().is_some()
+                        && -evaluate_position(&after, DEPTH) > WINNING)
+            }
+            Goal::Improve(target) => is_mated(&after) || -evaluate_position(&after, DEPTH) > target,
+            Goal::FindLine => {
+                is_mated(&after)
+                    || self.line.first().is_some_and(|solution| {
+                        solution.from() == piece_move.from()
+                            && solution.to() == piece_move.to()
+                            && solution.promotion() == piece_move.promotion()
+                    })
+            }
+        }
+    }
+
+    /// Returns a move which solves the puzzle.
+    pub fn solution(&self) -> Option<Move> {
+        ChessBoard::with_position(&self.fen)
+            .legal_moves_iter()
+            .find(|piece_move| self.is_solved_by(piece_move))
+    }
+
+    /// Returns the moves of the solution line in algebraic notation, or a move which solves the
+    /// puzzle if it gives no line.
+    pub fn solution_line(&self) -> Option<Vec<String>> {
+        if self.line.is_empty() {
+            return self
+                .solution()
+                .map(|solution| vec![solution.as_algebraic()]);
+        }
+        Some(self.line.iter().map(Move::as_algebraic).collect())
+    }
+}
+
+/// Reads a line of the puzzles, returning None if it is not a puzzle.
+fn parse_line(line: &str) -> Option<Puzzle> {
+    let (rating, fen) = line.split_once('\t')?;
+    Some(Puzzle::new(
+        Fen::parse(fen)?,
+        rating.parse().ok()?,
+        Goal::MateInOne,
+    ))
+}
+
+/// Returns the embedded puzzles, from the easiest to the hardest.
+pub fn puzzles() -> &'static [Puzzle] {
+    static LOADED: OnceLock<Vec<Puzzle>> = OnceLock::new();
+    LOADED.get_or_init(|| {
+        let mut puzzles: Vec<Puzzle> = PUZZLES.lines().filter_map(parse_line).collect();
+        puzzles.sort_by_key(Puzzle::rating);
+        puzzles
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_puzzles() {
+        let puzzles = puzzles();
+        assert_eq!(puzzles.len(), PUZZLES.lines().count() - 1);
+        for puzzle in puzzles {
+            let solution = puzzle.solution();
+            assert!(solution.is_some(), "{} has no mate", puzzle.fen());
+        }
+        assert!(puzzles
+            .windows(2)
+            .all(|pair| pair[0].rating() <= pair[1].rating()));
+    }
+
+    #[test]
+    fn test_is_solved_by() {
+        let puzzle = parse_line("600\t6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let board = ChessBoard::with_position(puzzle.fen());
+        assert!(puzzle.is_solved_by(&Move::from_algebraic("Ra8", &board).unwrap()));
+        assert!(!puzzle.is_solved_by(&Move::from_algebraic("Ra7", &board).unwrap()));
+    }
+}
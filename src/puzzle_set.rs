@@ -0,0 +1,281 @@
+//! Puzzle sets, files of [puzzles](crate::puzzle) which players make and share as training packs.
+//!
+//! A set is written in RON, or in JSON if its file name ends in `.json`, with the same fields:
+//!
+//! ```text
+//! (
+//!     name: "Knight forks",
+//!     puzzles: [
+//!         (
+//!             fen: "r3k2r/8/8/3N4/8/8/8/4K3 w kq - 0 1",
+//!             solution: ["Nc7+", "Kd7", "Nxa8"],
+//!             themes: ["fork"],
+//!             rating: 1100,
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! Each puzzle has the position as a FEN with the player to move, the solution line in algebraic
+//! notation from the player's first move, alternating with the opponent's replies, any number of
+//! themes, which may be left out, and a rating on the scale of a player rating. A puzzle is solved
+//! by the first move of its line, or by any checkmate. Reading a set checks every position and
+//! every move of every line, and fails with the puzzle and the move which is wrong, and unknown
+//! fields are refused so misspelt ones are not silently dropped.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, Variant};
+use crate::fen::{Fen, FenError};
+use crate::puzzle::{Goal, Puzzle};
+
+/// The file puzzle sets are exported to, and imported from, by default.
+pub const PUZZLE_SET_PATH: &str = "puzzle_set.ron";
+
+/// The languages a puzzle set can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ron,
+    Json,
+}
+
+impl Format {
+    /// Returns the format of the file at the given path, from its extension.
+    pub fn of_path(path: &str) -> Self {
+        match path.to_lowercase().ends_with(".json") {
+            true => Format::Json,
+            false => Format::Ron,
+        }
+    }
+}
+
+/// A puzzle as it is written in a set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleEntry {
+    fen: String,
+    solution: Vec<String>,
+    #[serde(default)]
+    themes: Vec<String>,
+    rating: u32,
+}
+
+/// A puzzle set as it is written.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PuzzleSetFile {
+    name: String,
+    puzzles: Vec<PuzzleEntry>,
+}
+
+/// Why a puzzle set cannot be read, with the number of the puzzle at fault counted from one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PuzzleSetError {
+    /// The text is not a puzzle set, as the parser explains.
+    Syntax(String),
+    /// The set has no puzzles.
+    Empty,
+    Position {
+        puzzle: usize,
+        error: FenError,
+    },
+    /// The puzzle's solution has no moves.
+    NoSolution {
+        puzzle: usize,
+    },
+    /// The given move of the puzzle's solution, counted from one, is not a legal move.
+    IllegalMove {
+        puzzle: usize,
+        ply: usize,
+        text: String,
+    },
+}
+
+impl fmt::Display for PuzzleSetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PuzzleSetError::Syntax(error) => write!(f, "not a puzzle set: {}", error),
+            PuzzleSetError::Empty => write!(f, "the set has no puzzles"),
+            PuzzleSetError::Position { puzzle, error } => {
+                write!(f, "puzzle {} has an invalid position: {}", puzzle, error)
+            }
+            PuzzleSetError::NoSolution { puzzle } => {
+                write!(f, "puzzle {} has no solution moves", puzzle)
+            }
+            PuzzleSetError::IllegalMove { puzzle, ply, text } => write!(
+                f,
+                "move {} of the solution of puzzle {}, \"{}\", is not a legal move",
+                ply, puzzle, text
+            ),
+        }
+    }
+}
+
+/// A named set of puzzles with solution lines.
+#[derive(Debug, Clone)]
+pub struct PuzzleSet {
+    pub name: String,
+    pub puzzles: Vec<Puzzle>,
+}
+
+impl PuzzleSet {
+    /// Reads a puzzle set written in the given format, checking each of its puzzles.
+    pub fn read(text: &str, format: Format) -> Result<Self, PuzzleSetError> {
+        let file: PuzzleSetFile = match format {
+            Format::Ron => ron::from_str(text).map_err(|error| error.to_string()),
+            Format::Json => serde_json::from_str(text).map_err(|error| error.to_string()),
+        }
+        .map_err(PuzzleSetError::Syntax)?;
+        if file.puzzles.is_empty() {
+            return Err(PuzzleSetError::Empty);
+        }
+        let puzzles = file
+            .puzzles
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| read_puzzle(entry, index + 1))
+            .collect::<Result<_, _>>()?;
+        Ok(PuzzleSet {
+            name: file.name,
+            puzzles,
+        })
+    }
+
+    /// Writes the puzzle set in the given format. A puzzle without a line is written with a move
+    /// which solves it, and the theme of its goal if it has none, and one which cannot be solved
+    /// is left out.
+    pub fn write(&self, format: Format) -> String {
+        let file = PuzzleSetFile {
+            name: self.name.clone(),
+            puzzles: self.puzzles.iter().filter_map(write_puzzle).collect(),
+        };
+        match format {
+            Format::Ron => ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default())
+                .expect("A puzzle set can always be written."),
+            Format::Json => {
+                serde_json::to_string_pretty(&file).expect("A puzzle set can always be written.")
+            }
+        }
+    }
+}
+
+/// Reads and checks the given puzzle, of the given number.
+fn read_puzzle(entry: PuzzleEntry, puzzle: usize) -> Result<Puzzle, PuzzleSetError> {
+    let fen = Fen::read(&entry.fen, Variant::Standard)
+        .map_err(|error| PuzzleSetError::Position { puzzle, error })?;
+    if entry.solution.is_empty() {
+        return Err(PuzzleSetError::NoSolution { puzzle });
+    }
+    let mut board = ChessBoard::with_position(&fen);
+    let mut line = Vec::new();
+    for (index, text) in entry.solution.iter().enumerate() {
+        let piece_move = Move::from_algebraic(text, &board)
+            .filter(|_| board.game_end_status().is_none())
+            .ok_or_else(|| PuzzleSetError::IllegalMove {
+                puzzle,
+                ply: index + 1,
+                text: text.clone(),
+            })?;
+        board.apply_move(&piece_move);
+        line.push(piece_move);
+    }
+    Ok(Puzzle::new(fen, entry.rating, Goal::FindLine)
+        .with_line(line)
+        .with_themes(entry.themes))
+}
+
+/// Returns the given puzzle as it is written in a set, if it can be solved.
+fn write_puzzle(puzzle: &Puzzle) -> Option<PuzzleEntry> {
+    let solution = puzzle.solution_line()?;
+    let themes = match puzzle.themes() {
+        [] => vec![puzzle.goal().theme().to_string()],
+        themes => themes.to_vec(),
+    };
+    Some(PuzzleEntry {
+        fen: puzzle.fen().to_string(),
+        solution,
+        themes,
+        rating: puzzle.rating(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORKS: &str = r#"(
+        name: "Knight forks",
+        puzzles: [
+            (
+                fen: "r3k2r/8/8/3N4/8/8/8/4K3 w kq - 0 1",
+                solution: ["Nc7+", "Kd7", "Nxa8"],
+                themes: ["fork"],
+                rating: 1100,
+            ),
+        ],
+    )"#;
+
+    #[test]
+    fn test_read_and_write() {
+        let set = PuzzleSet::read(FORKS, Format::Ron).unwrap();
+        assert_eq!(set.name, "Knight forks");
+        let puzzle = &set.puzzles[0];
+        assert_eq!(puzzle.solution_line().unwrap(), ["Nc7", "Kd7", "Nxa8"]);
+        assert_eq!(puzzle.themes(), ["fork"]);
+        let board = ChessBoard::with_position(puzzle.fen());
+        assert!(puzzle.is_solved_by(&Move::from_algebraic("Nc7", &board).unwrap()));
+        assert!(!puzzle.is_solved_by(&Move::from_algebraic("Nf6", &board).unwrap()));
+
+        // The set reads back the same from either format
+        for format in [Format::Ron, Format::Json] {
+            let written = set.write(format);
+            let read = PuzzleSet::read(&written, format).unwrap();
+            assert_eq!(read.write(format), written);
+        }
+        assert_eq!(Format::of_path("packs/forks.JSON"), Format::Json);
+
+        // Puzzles without a line are written with a solving move and the theme of their goal
+        let mate = PuzzleSet {
+            name: "Mates".to_string(),
+            puzzles: crate::puzzle::puzzles()[..1].to_vec(),
+        };
+        let read = PuzzleSet::read(&mate.write(Format::Json), Format::Json).unwrap();
+        assert_eq!(read.puzzles[0].themes(), ["mate-in-one"]);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        let read = |text: &str| PuzzleSet::read(text, Format::Ron).unwrap_err();
+        assert!(matches!(
+            read(&FORKS.replace("rating", "level")),
+            PuzzleSetError::Syntax(_)
+        ));
+        assert_eq!(
+            read(r#"(name: "None", puzzles: [])"#),
+            PuzzleSetError::Empty
+        );
+        assert_eq!(
+            read(&FORKS.replace("r3k2r", "r3k3r")),
+            PuzzleSetError::Position {
+                puzzle: 1,
+                error: FenError::PiecePlacement
+            }
+        );
+        let error = read(&FORKS.replace("\"Kd7\"", "\"Qd7\""));
+        assert_eq!(
+            error,
+            PuzzleSetError::IllegalMove {
+                puzzle: 1,
+                ply: 2,
+                text: "Qd7".to_string()
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "move 2 of the solution of puzzle 1, \"Qd7\", is not a legal move"
+        );
+    }
+}
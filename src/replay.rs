@@ -0,0 +1,246 @@
+//! The replay log, a record of every [GameCommand] taken off the queue with the frame and time it
+//! was carried out on, so a game which went wrong can be run again to reproduce the bug.
+//!
+//! The log is saved to [REPLAY_PATH] whenever a command is recorded. It is run again without a
+//! window with `chess_computer replay <file>`, which prints the position it ends in, or watched on
+//! the board by starting the app with `--replay <file>`. Each command is pushed on the frame it
+//! was recorded on, so the board carries out the commands in the same order and frames as before.
+//! Without a window the board has its default [options](crate::chess_board::ChessBoardConfig), so
+//! a log recorded with other options is best watched on a board started with the same ones.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use bevy::app::{App, Last, Plugin};
+use bevy::prelude::{EventReader, Res, ResMut, Resource, Update};
+use bevy::time::Time;
+use serde::{Deserialize, Serialize};
+
+use crate::bundle::{read_entry, REPLAY_ENTRY};
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{
+    ChessBoard, ChessBoardPlugin, GameCommand, GameCommandEvent, GameCommands,
+};
+use crate::engine::Engine;
+
+/// The file the replay log is saved to.
+const REPLAY_PATH: &str = "replay.ron";
+
+pub(super) struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayLog>()
+            .add_systems(Update, play_back)
+            .add_systems(Last, record_commands);
+    }
+}
+
+/// A command together with when it was carried out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedCommand {
+    /// The number of frames since the app started.
+    frame: u64,
+    /// The time since the app started, which is kept to help make sense of the log.
+    time: Duration,
+    command: GameCommand,
+}
+
+/// The commands carried out since the app started.
+#[derive(Resource, Debug)]
+pub struct ReplayLog {
+    entries: Vec<LoggedCommand>,
+    /// The number of the current frame.
+    frame: u64,
+    /// The file the log is saved to, or None if it is only kept in memory.
+    path: Option<PathBuf>,
+}
+
+impl Default for ReplayLog {
+    fn default() -> Self {
+        ReplayLog {
+            entries: Vec::new(),
+            frame: 0,
+            path: Some(PathBuf::from(REPLAY_PATH)),
+        }
+    }
+}
+
+impl ReplayLog {
+    pub fn entries(&self) -> &[LoggedCommand] {
+        &self.entries
+    }
+
+    /// Returns a log which is not saved to a file.
+    fn in_memory() -> Self {
+        ReplayLog {
+            path: None,
+            ..Default::default()
+        }
+    }
+}
+
+/// A replay log being played back, holding the commands yet to be pushed.
+#[derive(Resource, Debug, Default)]
+pub struct Playback(VecDeque<LoggedCommand>);
+
+impl Playback {
+    /// Plays back the given commands.
+    pub fn new(entries: Vec<LoggedCommand>) -> Self {
+        Playback(entries.into())
+    }
+
+    /// Loads the replay log saved in the given file, which may also be a
+    /// [diagnostic bundle](crate::bundle).
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = std::fs::read(path)
+            .map_err(|error| format!("Unable to read {}: {}.", path.display(), error))?;
+        let text = match path.extension().is_some_and(|extension| extension == "zip") {
+            true => read_entry(&bytes, REPLAY_ENTRY)
+                .map_err(|error| format!("Unable to read {}: {}.", path.display(), error))?,
+            false => String::from_utf8_lossy(&bytes).into_owned(),
+        };
+        let entries = ron::from_str::<Vec<LoggedCommand>>(&text)
+            .map_err(|error| format!("Unable to parse {}: {}.", path.display(), error))?;
+        Ok(Playback::new(entries))
+    }
+
+    /// Returns whether every command has been pushed.
+    pub fn is_finished(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Pushes the commands of the log being played back which were recorded on the current frame.
+/// The engine only makes the moves it made in the recording, so it is stopped from playing.
+fn play_back(
+    playback: Option<ResMut<Playback>>,
+    log: Res<ReplayLog>,
+    mut commands: ResMut<GameCommands>,
+    engine: Option<ResMut<Engine>>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    if playback.is_finished() {
+        return;
+    }
+    if let Some(mut engine) = engine.filter(|engine| engine.color().is_some()) {
+        engine.set_color(None);
+    }
+    while let Some(entry) = playback.0.front() {
+        if entry.frame > log.frame {
+            break;
+        }
+        commands.push(playback.0.pop_front().unwrap().command);
+    }
+}
+
+/// Adds the commands carried out this frame to the log, saving it if any were.
+fn record_commands(
+    mut events: EventReader<GameCommandEvent>,
+    mut log: ResMut<ReplayLog>,
+    time: Option<Res<Time>>,
+    playback: Option<Res<Playback>>,
+) {
+    let frame = log.frame;
+    let elapsed = time.map_or(Duration::ZERO, |time| time.elapsed());
+    let count = log.entries.len();
+    for event in events.iter() {
+        log.entries.push(LoggedCommand {
+            frame,
+            time: elapsed,
+            command: event.command().clone(),
+        });
+    }
+    log.frame += 1;
+    // Saving while a log is played back could cut short the file it is being played from
+    if log.entries.len() == count || playback.is_some_and(|playback| !playback.is_finished()) {
+        return;
+    }
+    if let (Some(path), Ok(text)) = (&log.path, ron::to_string(&log.entries)) {
+        // A failed save only matters if the app then crashes, so it is not reported
+        std::fs::write(path, text).ok();
+    }
+}
+
+/// Returns the board reached by playing back the given commands from the start of the app.
+fn replay(playback: Playback) -> ChessBoard {
+    let mut app = App::new();
+    app.add_plugins((ChessBoardPlugin::default(), ReplayPlugin))
+        .insert_resource(ReplayLog::in_memory())
+        .insert_resource(playback);
+    while !app.world.resource::<Playback>().is_finished() {
+        app.update();
+    }
+    app.world.resource::<ChessBoard>().clone()
+}
+
+/// Runs the replay command, which plays back the replay log in the given file without a window
+/// and prints the position it ends in.
+#[cfg(not(tarpaulin_include))]
+pub fn run(args: &[String]) -> Result<(), String> {
+    let path = args.first().ok_or("Missing the replay log file.")?;
+    let playback = Playback::load(Path::new(path))?;
+    let count = playback.0.len();
+    let board = replay(playback);
+    println!("Replayed {} commands.", count);
+    println!("Position: {}", board.to_fen());
+    let moves: Vec<String> = board.past_moves().iter().map(Move::as_algebraic).collect();
+    println!("Moves: {}", moves.join(" "));
+    if let Some(status) = board.game_end_status() {
+        println!("Game over: {:?}", status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::{PieceColor, ResetBoardEvent};
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_replay() {
+        let mut app = App::new();
+        app.add_plugins((ChessBoardPlugin::default(), ReplayPlugin))
+            .insert_resource(ReplayLog::in_memory());
+        app.update();
+        for text in ["e4", "e5", "Ke2"] {
+            let piece_move =
+                Move::from_algebraic(text, app.world.resource::<ChessBoard>()).unwrap();
+            let mut commands = app.world.resource_mut::<GameCommands>();
+            commands.push(GameCommand::MakeMove(piece_move));
+            // Illegal commands are recorded too, as they may be what caused the bug
+            commands.push(GameCommand::MakeMove(piece_move));
+            app.update();
+            app.update();
+        }
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(
+                Fen::from_string("4k3/8/8/8/8/8/8/4K2R w K - 0 1"),
+            ))));
+        app.update();
+        app.world
+            .resource_mut::<GameCommands>()
+            .push(GameCommand::Resign(PieceColor::Black));
+        app.update();
+        let entries = app.world.resource::<ReplayLog>().entries.clone();
+        assert_eq!(entries.len(), 8);
+        assert_eq!(entries[2].frame, 3);
+
+        // The log survives being saved and loaded
+        let text = ron::to_string(&entries).unwrap();
+        let entries = ron::from_str::<Vec<LoggedCommand>>(&text).unwrap();
+
+        let recorded = app.world.resource::<ChessBoard>();
+        let replayed = replay(Playback::new(entries));
+        assert_eq!(replayed.to_fen().to_string(), recorded.to_fen().to_string());
+        assert_eq!(replayed.past_moves(), recorded.past_moves());
+        assert_eq!(*replayed.game_end_status(), *recorded.game_end_status());
+    }
+}
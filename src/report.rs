@@ -0,0 +1,461 @@
+//! Exports a readable report of a game to Markdown or HTML for sharing.
+//!
+//! The report contains the result, the move list with a description of each move and a diagram of
+//! the final position. Once the game has been reviewed, the move list also has the glyph and the
+//! evaluation of each move, and the moves which swung the evaluation the most are shown with a
+//! diagram of the position they were played in.
+
+use std::collections::HashMap;
+
+use strum_macros::EnumIter;
+
+use crate::chess_board::{ChessBoard, GameEndStatus, PieceColor, PieceType, BOARD_SIZE};
+use crate::fen::Fen;
+
+/// The most swings of the evaluation shown with a diagram.
+const KEY_SWINGS: usize = 3;
+
+/// The formats a report can be exported to.
+#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq)]
+pub enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+impl ReportFormat {
+    /// Returns the file extension used for the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+        }
+    }
+}
+
+/// A move which swung the evaluation of the game.
+#[derive(Debug, Clone)]
+pub struct KeySwing {
+    /// The index of the move in the past moves.
+    pub index: usize,
+    /// The position the move was played in.
+    pub fen: Fen,
+    /// The centipawns the move lost.
+    pub swing: i32,
+}
+
+/// What the review of a game and the annotations of its moves add to its report.
+#[derive(Debug, Clone, Default)]
+pub struct ReportReview {
+    /// The glyph of each annotated past move, by its index.
+    pub glyphs: HashMap<usize, &'static str>,
+    /// The evaluation after each reviewed past move as text, by its index.
+    pub evaluations: HashMap<usize, String>,
+    /// The moves which lost the most, the biggest first.
+    pub swings: Vec<KeySwing>,
+}
+
+impl ReportReview {
+    /// Returns the glyph and the evaluation of the past move of the given index, empty if it has
+    /// none.
+    fn cells(&self, index: usize) -> (&str, &str) {
+        (
+            self.glyphs.get(&index).copied().unwrap_or_default(),
+            self.evaluations.get(&index).map_or("", String::as_str),
+        )
+    }
+}
+
+/// A move of a row of the move list, with its index in the past moves.
+struct RowMove {
+    index: usize,
+    text: String,
+}
+
+/// A row of the move list, made up of the move number and the moves of each player.
+struct MoveRow {
+    number: i32,
+    white: Option<RowMove>,
+    black: Option<RowMove>,
+}
+
+/// Returns the cells of the move and the glyph and evaluation of the given move of a row, empty if
+/// the row has no move of its player.
+fn move_cells<'a>(row_move: &'a Option<RowMove>, review: &'a ReportReview) -> [&'a str; 3] {
+    match row_move {
+        Some(row_move) => {
+            let (glyph, evaluation) = review.cells(row_move.index);
+            [&row_move.text, glyph, evaluation]
+        }
+        None => ["", "", ""],
+    }
+}
+
+/// Returns the rows of the move list of the game on the given board.
+fn move_rows(board: &ChessBoard) -> Vec<MoveRow> {
+    let past_moves = board.past_moves();
+    // Each of black's moves increments the move number
+    let black_moves = past_moves
+        .iter()
+        .filter(|piece_move| *piece_move.piece_color() == PieceColor::Black)
+        .count() as i32;
+    let mut number = *board.move_number() - black_moves;
+    let mut rows: Vec<MoveRow> = Vec::new();
+    for (index, piece_move) in past_moves.iter().enumerate() {
+        let text = RowMove {
+            index,
+            text: format!(
+                "{} ({})",
+                piece_move.as_algebraic(),
+                piece_move.as_description()
+            ),
+        };
+        match piece_move.piece_color() {
+            PieceColor::White => rows.push(MoveRow {
+                number,
+                white: Some(text),
+                black: None,
+            }),
+            PieceColor::Black => {
+                match rows.last_mut() {
+                    Some(row) if row.black.is_none() => row.black = Some(text),
+                    _ => rows.push(MoveRow {
+                        number,
+                        white: None,
+                        black: Some(text),
+                    }),
+                }
+                number += 1;
+            }
+        }
+    }
+    rows
+}
+
+/// Returns a sentence describing the result of the game.
+fn result_text(board: &ChessBoard) -> String {
+    let Some(status) = board.game_end_status() else {
+        return "The game is in progress.".to_string();
+    };
+    let reason = match status {
+        GameEndStatus::Checkmate => "checkmate",
+        GameEndStatus::Resignation => "resignation",
+        GameEndStatus::Stalemate => "stalemate",
+        GameEndStatus::DeadPosition => "dead position",
+        GameEndStatus::FlagFall => "flag fall",
+        GameEndStatus::Agreement => "agreement",
+        GameEndStatus::KingCapture => "king capture",
+        GameEndStatus::AllPiecesLost => "all pieces lost",
+        GameEndStatus::FiftyMoves => "the fifty-move rule",
+        GameEndStatus::Repetition => "threefold repetition",
+    };
+    match board.winner() {
+        Some(color) => format!("{} won by {}.", color, reason),
+        None => format!("Draw by {}.", reason),
+    }
+}
+
+/// Returns the symbol used for a piece in diagrams.
+pub fn piece_symbol(color: &PieceColor, piece_type: &PieceType) -> char {
+    match (color, piece_type) {
+        (PieceColor::White, PieceType::King) => '♔',
+        (PieceColor::White, PieceType::Queen) => '♕',
+        (PieceColor::White, PieceType::Rook) => '♖',
+        (PieceColor::White, PieceType::Bishop) => '♗',
+        (PieceColor::White, PieceType::Knight) => '♘',
+        (PieceColor::White, PieceType::Pawn) => '♙',
+        (PieceColor::Black, PieceType::King) => '♚',
+        (PieceColor::Black, PieceType::Queen) => '♛',
+        (PieceColor::Black, PieceType::Rook) => '♜',
+        (PieceColor::Black, PieceType::Bishop) => '♝',
+        (PieceColor::Black, PieceType::Knight) => '♞',
+        (PieceColor::Black, PieceType::Pawn) => '♟',
+        // Fairy pieces have no figurines, so are shown by their letters
+        (PieceColor::White, PieceType::Fairy(_)) => piece_type.letter(),
+        (PieceColor::Black, PieceType::Fairy(_)) => piece_type.letter().to_ascii_lowercase(),
+    }
+}
+
+/// Returns a text diagram of the position on the given board, viewed from white's side.
+fn text_diagram(board: &ChessBoard) -> String {
+    let fen = board.to_fen();
+    let size = fen.size();
+    let mut diagram = String::new();
+    for (rank, squares) in fen
+        .piece_placement()
+        .iter()
+        .enumerate()
+        .skip(size.top_rank())
+    {
+        diagram.push_str(&format!("{} ", BOARD_SIZE - rank));
+        for square in &squares[..size.files()] {
+            diagram.push(' ');
+            diagram.push(match square {
+                Some((color, piece_type)) => piece_symbol(color, piece_type),
+                None => '·',
+            });
+        }
+        diagram.push('\n');
+    }
+    diagram.push_str("  ");
+    for file in (b'a'..).take(size.files()) {
+        diagram.push(' ');
+        diagram.push(char::from(file));
+    }
+    diagram.push('\n');
+    diagram
+}
+
+/// Returns an HTML table diagram of the position on the given board, viewed from white's side.
+fn html_diagram(board: &ChessBoard) -> String {
+    let fen = board.to_fen();
+    let size = fen.size();
+    let mut diagram = String::from("<table class=\"board\">\n");
+    for (rank, squares) in fen
+        .piece_placement()
+        .iter()
+        .enumerate()
+        .skip(size.top_rank())
+    {
+        diagram.push_str("<tr>");
+        for (file, square) in squares[..size.files()].iter().enumerate() {
+            diagram.push_str(&format!(
+                "<td class=\"{}\">{}</td>",
+                if (rank + file).is_multiple_of(2) {
+                    "light"
+                } else {
+                    "dark"
+                },
+                square
+                    .map(|(color, piece_type)| piece_symbol(&color, &piece_type).to_string())
+                    .unwrap_or_default()
+            ));
+        }
+        diagram.push_str("</tr>\n");
+    }
+    diagram.push_str("</table>\n");
+    diagram
+}
+
+/// Returns the title of the given swing, the move with its number and glyph.
+fn swing_title(board: &ChessBoard, swing: &KeySwing, review: &ReportReview) -> String {
+    let number = swing.fen.fullmove_number();
+    let dots = match swing.fen.active_color() {
+        PieceColor::White => ".",
+        PieceColor::Black => "...",
+    };
+    format!(
+        "{}{} {}{}",
+        number,
+        dots,
+        board.past_moves()[swing.index].as_algebraic(),
+        review.cells(swing.index).0
+    )
+}
+
+/// Returns the swings of the given review shown with a diagram, those of moves on the given board.
+fn key_swings<'a>(board: &ChessBoard, review: &'a ReportReview) -> Vec<&'a KeySwing> {
+    review
+        .swings
+        .iter()
+        .filter(|swing| swing.index < board.past_moves().len())
+        .take(KEY_SWINGS)
+        .collect()
+}
+
+/// Returns a report of the game on the given board in the given format, with what the given review
+/// adds to it.
+pub fn game_report(board: &ChessBoard, review: &ReportReview, format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Markdown => markdown_report(board, review),
+        ReportFormat::Html => html_report(board, review),
+    }
+}
+
+fn markdown_report(board: &ChessBoard, review: &ReportReview) -> String {
+    let mut report = format!("# Game Report\n\n{}\n\n## Moves\n\n", result_text(board));
+    let rows = move_rows(board);
+    if rows.is_empty() {
+        report.push_str("No moves have been made.\n");
+    } else {
+        report.push_str(concat!(
+            "| # | White | Glyph | Eval | Black | Glyph | Eval |\n",
+            "|---|---|---|---|---|---|---|\n"
+        ));
+        for row in rows {
+            let [white, white_glyph, white_evaluation] = move_cells(&row.white, review);
+            let [black, black_glyph, black_evaluation] = move_cells(&row.black, review);
+            report.push_str(&format!(
+                "| {} | {} | {} | {} | {} | {} | {} |\n",
+                row.number,
+                white,
+                white_glyph,
+                white_evaluation,
+                black,
+                black_glyph,
+                black_evaluation
+            ));
+        }
+    }
+    let swings = key_swings(board, review);
+    if !swings.is_empty() {
+        report.push_str("\n## Biggest Swings\n");
+        for swing in swings {
+            report.push_str(&format!(
+                "\n### {}\n\n```text\n{}```\n\nLost {} centipawns. FEN: `{}`\n",
+                swing_title(board, swing, review),
+                text_diagram(&ChessBoard::with_position(&swing.fen)),
+                swing.swing,
+                swing.fen
+            ));
+        }
+    }
+    report.push_str(&format!(
+        "\n## Final Position\n\n```text\n{}```\n\nFEN: `{}`\n",
+        text_diagram(board),
+        board.to_fen()
+    ));
+    report
+}
+
+fn html_report(board: &ChessBoard, review: &ReportReview) -> String {
+    let mut report = String::from(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Game Report</title>\n",
+        "<style>\n",
+        "table.board { border-collapse: collapse; font-size: 2em; }\n",
+        "table.board td { width: 1.5em; height: 1.5em; text-align: center; }\n",
+        "td.light { background: #f0d9b5; }\n",
+        "td.dark { background: #b58863; }\n",
+        "</style>\n</head>\n<body>\n<h1>Game Report</h1>\n"
+    ));
+    report.push_str(&format!("<p>{}</p>\n<h2>Moves</h2>\n", result_text(board)));
+    let rows = move_rows(board);
+    if rows.is_empty() {
+        report.push_str("<p>No moves have been made.</p>\n");
+    } else {
+        report.push_str(concat!(
+            "<table>\n<tr><th>#</th><th>White</th><th>Glyph</th><th>Eval</th>",
+            "<th>Black</th><th>Glyph</th><th>Eval</th></tr>\n"
+        ));
+        for row in rows {
+            report.push_str(&format!("<tr><td>{}</td>", row.number));
+            for cell in move_cells(&row.white, review)
+                .into_iter()
+                .chain(move_cells(&row.black, review))
+            {
+                report.push_str(&format!("<td>{}</td>", cell));
+            }
+            report.push_str("</tr>\n");
+        }
+        report.push_str("</table>\n");
+    }
+    let swings = key_swings(board, review);
+    if !swings.is_empty() {
+        report.push_str("<h2>Biggest Swings</h2>\n");
+        for swing in swings {
+            report.push_str(&format!(
+                "<h3>{}</h3>\n{}<p>Lost {} centipawns. FEN: <code>{}</code></p>\n",
+                swing_title(board, swing, review),
+                html_diagram(&ChessBoard::with_position(&swing.fen)),
+                swing.swing,
+                swing.fen
+            ));
+        }
+    }
+    report.push_str(&format!(
+        "<h2>Final Position</h2>\n{}<p>FEN: <code>{}</code></p>\n</body>\n</html>\n",
+        html_diagram(board),
+        board.to_fen()
+    ));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_game_report() {
+        let mut board = ChessBoard::default();
+        board.end_game(GameEndStatus::FlagFall, Some(PieceColor::Black));
+
+        let markdown = game_report(&board, &ReportReview::default(), ReportFormat::Markdown);
+        assert!(markdown.starts_with("# Game Report\n\nBlack won by flag fall.\n"));
+        assert!(markdown.contains("No moves have been made."));
+        assert!(markdown.contains("8  · · · · · · · ·\n"));
+        assert!(markdown.contains(&format!("FEN: `{}`", board.to_fen())));
+
+        let html = game_report(&board, &ReportReview::default(), ReportFormat::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<p>Black won by flag fall.</p>"));
+        assert_eq!(html.matches("<td class=\"dark\"></td>").count(), 32);
+        assert!(!html.contains("Biggest Swings"));
+    }
+
+    #[test]
+    fn test_reviewed_report() {
+        use crate::chess_board::r#move::Move;
+
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut positions = vec![board.to_fen()];
+        for text in ["e4", "f6", "d4"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            positions.push(board.to_fen());
+        }
+        let review = ReportReview {
+            glyphs: HashMap::from([(1, "?"), (2, "!")]),
+            evaluations: HashMap::from([(0, "+0.30".to_string()), (1, "+1.20".to_string())]),
+            swings: vec![KeySwing {
+                index: 1,
+                fen: positions[1].clone(),
+                swing: 90,
+            }],
+        };
+
+        let markdown = game_report(&board, &review, ReportFormat::Markdown);
+        assert!(markdown.contains(
+            "| 1 | e4 (Pawn to e4) |  | +0.30 | f6 (Pawn to f6) | ? | +1.20 |\n| 2 | d4 (Pawn to d4) | ! |  |  |  |  |\n"
+        ));
+        assert!(markdown.contains("## Biggest Swings\n\n### 1... f6?\n"));
+        assert!(markdown.contains(&format!("Lost 90 centipawns. FEN: `{}`", positions[1])));
+
+        let html = game_report(&board, &review, ReportFormat::Html);
+        assert!(html.contains("<td>f6 (Pawn to f6)</td><td>?</td><td>+1.20</td></tr>"));
+        assert!(html.contains("<h3>1... f6?</h3>\n<table class=\"board\">"));
+    }
+
+    #[test]
+    fn test_move_rows() {
+        use bevy::prelude::App;
+
+        use crate::chess_board::r#move::Move;
+        use crate::chess_board::{ChessBoardPlugin, GameCommand, GameCommands};
+
+        let mut app = App::new();
+        app.add_plugins(ChessBoardPlugin::default());
+        app.update();
+        for text in ["e4", "e5", "Nf3"] {
+            let piece_move =
+                Move::from_algebraic(text, app.world.get_resource::<ChessBoard>().unwrap())
+                    .unwrap();
+            app.world
+                .resource_mut::<GameCommands>()
+                .push(GameCommand::MakeMove(piece_move));
+            app.update();
+        }
+
+        let rows = move_rows(app.world.get_resource::<ChessBoard>().unwrap());
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].number, 1);
+        let text =
+            |row_move: &Option<RowMove>| row_move.as_ref().map(|row_move| row_move.text.clone());
+        assert_eq!(text(&rows[0].white).as_deref(), Some("e4 (Pawn to e4)"));
+        assert_eq!(text(&rows[0].black).as_deref(), Some("e5 (Pawn to e5)"));
+        assert_eq!(rows[1].number, 2);
+        assert_eq!(text(&rows[1].white).as_deref(), Some("Nf3 (Knight to f3)"));
+        assert_eq!(rows[1].black.as_ref().map(|row_move| row_move.index), None);
+        assert_eq!(
+            rows[1].white.as_ref().map(|row_move| row_move.index),
+            Some(2)
+        );
+    }
+}
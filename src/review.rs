@@ -0,0 +1,273 @@
+//! Game review, which evaluates every position of a game to find the move of each player that
+//! threw away the most, the biggest swing of the engine's evaluation against them. Each mistake
+//! can be tried again as a [puzzle](crate::puzzle), solved by a move which wins back at least half
+//! of what the mistake lost.
+//!
+//! The review also finds the critical moments of the game, the positions in which only one move
+//! held: it kept the player out of a lost position, and every other move left them losing and at
+//! least [ONLY_MOVE_GAP] behind it.
+//!
+//! Every move is classified by what it lost, as an inaccuracy, a mistake or a blunder from
+//! [INACCURACY], [MISTAKE] and [BLUNDER] centipawns, and these thresholds are the only ones used.
+
+use serde::{Deserialize, Serialize};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::ChessBoard;
+use crate::engine::{evaluate_line, evaluate_position};
+use crate::fen::Fen;
+use crate::puzzle::{Goal, Puzzle};
+
+/// The depth each position is searched to.
+const DEPTH: u32 = 2;
+
+/// The least centipawns the only move of a critical moment is ahead of every other move.
+pub const ONLY_MOVE_GAP: i32 = 200;
+
+/// The centipawns a player must be behind to be losing.
+const LOSING: i32 = 200;
+
+/// The centipawns lost by the smallest inaccuracy, mistake and blunder.
+pub const INACCURACY: i32 = 50;
+pub const MISTAKE: i32 = 100;
+pub const BLUNDER: i32 = 200;
+
+/// The classification of a move by the centipawns it lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Classification {
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+/// A move which lost its player some of their evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mistake {
+    /// The number of moves made before it.
+    pub ply: usize,
+    /// The evaluation in centipawns before the move, from the mover's point of view.
+    pub before: i32,
+    /// The evaluation after the move, from the mover's point of view.
+    pub after: i32,
+}
+
+impl Mistake {
+    /// Returns the centipawns the move lost.
+    pub fn swing(&self) -> i32 {
+        self.before - self.after
+    }
+
+    /// Returns the classification of the move, if it lost at least an inaccuracy.
+    pub fn classification(&self) -> Option<Classification> {
+        match self.swing() {
+            swing if swing >= BLUNDER => Some(Classification::Blunder),
+            swing if swing >= MISTAKE => Some(Classification::Mistake),
+            swing if swing >= INACCURACY => Some(Classification::Inaccuracy),
+            _ => None,
+        }
+    }
+
+    /// Returns the puzzle of finding a better move in the given position, which must be the one
+    /// the mistake was made in.
+    pub fn puzzle(&self, fen: &Fen) -> Puzzle {
+        // Mistakes are not rated, as their puzzles are only tried once
+        Puzzle::new(fen.clone(), 0, Goal::Improve(self.target()))
+    }
+
+    /// Returns the evaluation a better move must reach, which wins back half of the swing.
+    pub fn target(&self) -> i32 {
+        self.after + self.swing() / 2
+    }
+}
+
+/// A position in which only one move kept the player to move from losing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OnlyMove {
+    /// The number of moves made before the position.
+    pub ply: usize,
+    /// The move which held.
+    pub best: Move,
+    /// The evaluation in centipawns after the move which held, from the mover's point of view.
+    pub evaluation: i32,
+    /// The evaluation after the best of the other moves, from the mover's point of view.
+    pub second: i32,
+}
+
+impl OnlyMove {
+    /// Returns the centipawns the move which held is ahead of every other move.
+    pub fn gap(&self) -> i32 {
+        self.evaluation - self.second
+    }
+
+    /// Returns whether the given move, played in the position, is the move which held.
+    pub fn is_found_by(&self, played: &Move) -> bool {
+        played.from() == self.best.from()
+            && played.to() == self.best.to()
+            && played.promotion() == self.best.promotion()
+    }
+}
+
+/// Returns the only move of the given position, if it has one.
+fn only_move(board: &ChessBoard, ply: usize) -> Option<OnlyMove> {
+    let mut ranked: Vec<(Move, i32)> = board
+        .legal_moves_iter()
+        .map(|piece_move| {
+            let mut after = board.clone();
+            after.apply_move(&piece_move);
+            (piece_move, -evaluate_position(&after, DEPTH - 1))
+        })
+        .collect();
+    ranked.sort_by_key(|(_, evaluation)| -evaluation);
+    let [(best, evaluation), (_, second), ..] = ranked[..] else {
+        return None;
+    };
+    let only = OnlyMove {
+        ply,
+        best,
+        evaluation,
+        second,
+    };
+    (evaluation > -LOSING && second <= -LOSING && only.gap() > ONLY_MOVE_GAP).then_some(only)
+}
+
+/// Returns the critical moments of the game with the given positions and the moves between them,
+/// in the order they were reached. A position with a single legal move is not critical, as the
+/// move is forced.
+pub fn only_moves(positions: &[Fen], moves: &[Move]) -> Vec<OnlyMove> {
+    positions
+        .iter()
+        .take(moves.len())
+        .enumerate()
+        .filter_map(|(ply, fen)| only_move(&ChessBoard::with_position(fen), ply))
+        .collect()
+}
+
+/// Returns every move of a game of the given number of moves as a mistake, whether or not it lost
+/// anything, from the evaluations of its positions, each from the point of view of the player to
+/// move in it.
+pub fn evaluated_mistakes(evaluations: Vec<i32>, moves: usize) -> impl Iterator<Item = Mistake> {
+    (0..moves.min(evaluations.len().saturating_sub(1))).map(move |ply| Mistake {
+        ply,
+        before: evaluations[ply],
+        after: -evaluations[ply + 1],
+    })
+}
+
+/// Returns the evaluation of each of the given positions, from the point of view of the player to
+/// move in it.
+pub fn evaluations(positions: &[Fen]) -> Vec<i32> {
+    positions
+        .iter()
+        .map(|fen| evaluate_position(&ChessBoard::with_position(fen), DEPTH))
+        .collect()
+}
+
+/// Returns the evaluation of each of the given positions like [evaluations], with the line the
+/// engine expects from it, starting with its best move.
+pub fn evaluated_lines(positions: &[Fen]) -> Vec<(i32, Vec<Move>)> {
+    positions
+        .iter()
+        .map(|fen| evaluate_line(&ChessBoard::with_position(fen), DEPTH))
+        .collect()
+}
+
+/// Returns every move of the game with the given positions and the moves between them, as a
+/// mistake whether or not it lost anything.
+pub fn mistakes(positions: &[Fen], moves: &[Move]) -> impl Iterator<Item = Mistake> {
+    evaluated_mistakes(evaluations(positions), moves.len())
+}
+
+/// Returns the biggest mistake of white and of black in the game with the given moves, from the
+/// evaluations of its positions, each from the point of view of the player to move in it. A
+/// player none of whose moves lost anything has none.
+pub fn biggest_evaluated_mistakes(evaluations: Vec<i32>, moves: &[Move]) -> [Option<Mistake>; 2] {
+    biggest_of(evaluated_mistakes(evaluations, moves.len()), moves)
+}
+
+/// Returns the biggest of the given mistakes of white and of black in the game with the given
+/// moves.
+fn biggest_of(mistakes: impl Iterator<Item = Mistake>, moves: &[Move]) -> [Option<Mistake>; 2] {
+    let mut found = [None; 2];
+    for mistake in mistakes {
+        let biggest: &mut Option<Mistake> = &mut found[*moves[mistake.ply].piece_color() as usize];
+        if mistake.swing() > biggest.map_or(0, |biggest| biggest.swing()) {
+            *biggest = Some(mistake);
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::PieceColor;
+
+    use super::*;
+
+    #[test]
+    fn test_biggest_mistakes() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut positions = vec![board.to_fen()];
+        let mut moves = Vec::new();
+        // Black hangs the queen on its second move
+        for text in ["e4", "e5", "Nf3", "Qh4", "Nxh4"] {
+            let piece_move = Move::from_algebraic(text, &board).unwrap();
+            board.apply_move(&piece_move);
+            positions.push(board.to_fen());
+            moves.push(piece_move);
+        }
+        let mistakes = biggest_evaluated_mistakes(evaluations(&positions), &moves);
+        let (evaluation, line) = &evaluated_lines(&positions[4..5])[0];
+        assert_eq!(*evaluation, evaluations(&positions[4..5])[0]);
+        assert_eq!(line[0].as_algebraic(), "Nxh4");
+        let black = mistakes[PieceColor::Black as usize].unwrap();
+        assert_eq!(black.ply, 3);
+        assert!(black.swing() > 500);
+        assert_eq!(black.classification(), Some(Classification::Blunder));
+
+        let puzzle = black.puzzle(&positions[3]);
+        let board = ChessBoard::with_position(&positions[3]);
+        assert!(!puzzle.is_solved_by(&moves[3]));
+        assert!(puzzle.is_solved_by(&Move::from_algebraic("Nc6", &board).unwrap()));
+    }
+
+    #[test]
+    fn test_classification() {
+        let lost = |swing: i32| Mistake {
+            ply: 0,
+            before: 0,
+            after: -swing,
+        };
+        assert_eq!(lost(INACCURACY - 1).classification(), None);
+        assert_eq!(
+            lost(INACCURACY).classification(),
+            Some(Classification::Inaccuracy)
+        );
+        assert_eq!(
+            lost(BLUNDER - 1).classification(),
+            Some(Classification::Mistake)
+        );
+        assert_eq!(
+            lost(BLUNDER).classification(),
+            Some(Classification::Blunder)
+        );
+    }
+
+    #[test]
+    fn test_only_moves() {
+        // The queen gives check, and only taking it keeps white from losing
+        let fen = Fen::parse("4k3/8/8/8/8/8/3q4/4K2R w K - 0 1").unwrap();
+        let board = ChessBoard::with_position(&fen);
+        let taken = Move::from_algebraic("Kxd2", &board).unwrap();
+        let found = only_moves(&[fen], &[taken]);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].is_found_by(&taken));
+        assert!(found[0].gap() > ONLY_MOVE_GAP);
+        let fled = Move::from_algebraic("Kf1", &board).unwrap();
+        assert!(!found[0].is_found_by(&fled));
+
+        // Nothing is critical in the opening
+        let board = ChessBoard::with_position(&Fen::default());
+        let opening = Move::from_algebraic("e4", &board).unwrap();
+        assert!(only_moves(&[Fen::default()], &[opening]).is_empty());
+    }
+}
@@ -0,0 +1,477 @@
+//! Hooks for scripting custom trainers, loggers and integrations without recompiling, enabled with
+//! the `scripting` feature.
+//!
+//! The script is read at startup from the file in the `CHESS_SCRIPT` environment variable. It is
+//! made of hooks, each a line `on start`, `on move`, `on end` or `on position` followed by the
+//! statements run when a game is started or loaded, a move is made, the game ends, or the
+//! position changes in any way, including by a take back. Blank lines and those starting with `#`
+//! are skipped. Each statement is on a line of its own:
+//!
+//! - `log <text>` writes the text to the log, where the debug console shows it.
+//! - `notify <text>` shows the text to the player.
+//! - `play <move>` makes the move, in algebraic notation, if it is legal.
+//! - `set <name> <text>` keeps the text in the variable with the given name.
+//! - `add <name> <number>` adds the whole number to the variable, which starts at 0.
+//! - `if <value> <comparison> <value> <statement>` runs the statement if the comparison, one of
+//!   `==`, `!=`, `<` and `>`, holds. Values are compared as numbers if both are, and as text if
+//!   not, in which case only `==` and `!=` can hold.
+//!
+//! Text and values are queries of the board: each `{name}` in them is replaced by `{move}`, the
+//! last move made; `{ply}`, the number of moves made; `{turn}`, `white`, `black` or `none`;
+//! `{fen}` and `{placement}`, the position and its pieces as FEN; `{check}`, `true` if the player
+//! to move is in check; `{legal}`, the number of moves they have; `{result}`, the result as in
+//! PGN; `{square:e4}`, the FEN letter of the piece on the square or `.` if it is empty; and
+//! otherwise the value of the variable with the name. A script which cannot be read is reported
+//! with a [notification](crate::notification) and does not run.
+//!
+//! ```text
+//! on start
+//!     set checks 0
+//! on move
+//!     if {check} == true add checks 1
+//!     if {check} == true notify Check after {move}!
+//! on end
+//!     log Game over after {ply} moves and {checks} checks: {result}
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+use bevy::app::{App, Plugin};
+use bevy::log::{info, warn};
+use bevy::prelude::{
+    DetectChanges, EventReader, EventWriter, Local, Res, ResMut, Resource, Startup, Update,
+};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{
+    BoardPosition, ChessBoard, GameCommand, GameCommands, PieceColor, ResetBoardEvent,
+};
+use crate::notification::{Argument, Notification};
+use crate::pgn::GameResult;
+
+pub(super) struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScriptRunner>()
+            .add_systems(Startup, load_script)
+            .add_systems(Update, run_hooks);
+    }
+}
+
+/// When the statements of a hook are run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hook {
+    Start,
+    Move,
+    End,
+    Position,
+}
+
+impl Hook {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "start" => Some(Hook::Start),
+            "move" => Some(Hook::Move),
+            "end" => Some(Hook::End),
+            "position" => Some(Hook::Position),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    Greater,
+}
+
+impl Comparison {
+    fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "==" => Some(Comparison::Equal),
+            "!=" => Some(Comparison::NotEqual),
+            "<" => Some(Comparison::Less),
+            ">" => Some(Comparison::Greater),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the comparison holds between the given values.
+    fn holds(&self, left: &str, right: &str) -> bool {
+        match (left.parse::<f64>(), right.parse::<f64>()) {
+            (Ok(left), Ok(right)) => match self {
+                Comparison::Equal => left == right,
+                Comparison::NotEqual => left != right,
+                Comparison::Less => left < right,
+                Comparison::Greater => left > right,
+            },
+            _ => match self {
+                Comparison::Equal => left == right,
+                Comparison::NotEqual => left != right,
+                Comparison::Less | Comparison::Greater => false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Statement {
+    Log(String),
+    Notify(String),
+    Play(String),
+    Set(String, String),
+    Add(String, i64),
+    If {
+        left: String,
+        comparison: Comparison,
+        right: String,
+        then: Box<Statement>,
+    },
+}
+
+impl Statement {
+    /// Reads the statement on a line, returning why it could not be read if it is not one.
+    fn parse(line: &str) -> Result<Self, String> {
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let name_and_value = || {
+            rest.split_once(char::is_whitespace)
+                .map(|(name, value)| (name.to_string(), value.trim().to_string()))
+                .ok_or_else(|| format!("{} needs a name and a value", word))
+        };
+        match word {
+            "log" => Ok(Statement::Log(rest.to_string())),
+            "notify" => Ok(Statement::Notify(rest.to_string())),
+            "play" if !rest.is_empty() => Ok(Statement::Play(rest.to_string())),
+            "play" => Err("play needs a move".to_string()),
+            "set" => name_and_value().map(|(name, value)| Statement::Set(name, value)),
+            "add" => {
+                let (name, value) = name_and_value()?;
+                let value = value
+                    .parse()
+                    .map_err(|_| format!("{} is not a whole number", value))?;
+                Ok(Statement::Add(name, value))
+            }
+            "if" => {
+                let mut parts = rest.splitn(4, char::is_whitespace);
+                let (Some(left), Some(symbol), Some(right), Some(then)) =
+                    (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    return Err("if needs a comparison and a statement".to_string());
+                };
+                let comparison = Comparison::from_symbol(symbol)
+                    .ok_or_else(|| format!("{} is not a comparison", symbol))?;
+                Ok(Statement::If {
+                    left: left.to_string(),
+                    comparison,
+                    right: right.to_string(),
+                    then: Box::new(Statement::parse(then.trim())?),
+                })
+            }
+            _ => Err(format!("{} is not a statement", word)),
+        }
+    }
+}
+
+/// Why a script could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError {
+    /// The line the error is on, from 1.
+    line: usize,
+    message: String,
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// The statements of each hook of a script.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Script {
+    hooks: Vec<(Hook, Vec<Statement>)>,
+}
+
+impl Script {
+    fn parse(text: &str) -> Result<Self, ScriptError> {
+        let mut hooks: Vec<(Hook, Vec<Statement>)> = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: String| ScriptError {
+                line: index + 1,
+                message,
+            };
+            if let Some(name) = line.strip_prefix("on ") {
+                let hook = Hook::from_name(name.trim())
+                    .ok_or_else(|| error(format!("{} is not a hook", name.trim())))?;
+                hooks.push((hook, Vec::new()));
+                continue;
+            }
+            let Some((_, statements)) = hooks.last_mut() else {
+                return Err(error("statements must follow a hook".to_string()));
+            };
+            statements.push(Statement::parse(line).map_err(error)?);
+        }
+        Ok(Script { hooks })
+    }
+}
+
+/// What running a statement asks the app to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Log(String),
+    Notify(String),
+    Play(String),
+}
+
+/// The script, with the variables it has set.
+#[derive(Resource, Debug, Default)]
+pub struct ScriptRunner {
+    script: Script,
+    variables: HashMap<String, String>,
+}
+
+impl ScriptRunner {
+    /// Returns the value of the query with the given name on the given board.
+    fn query(&self, name: &str, board: &ChessBoard) -> String {
+        match name {
+            "move" => board
+                .past_moves()
+                .last()
+                .map(Move::as_algebraic)
+                .unwrap_or_default(),
+            "ply" => board.past_moves().len().to_string(),
+            "turn" => match board.active_color() {
+                Some(color) => format!("{:?}", color).to_lowercase(),
+                None => "none".to_string(),
+            },
+            "fen" => board.to_fen().to_string(),
+            "placement" => board
+                .to_fen()
+                .to_string()
+                .split(' ')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            "check" => board
+                .active_color()
+                .is_some_and(|color| board.in_check(&color))
+                .to_string(),
+            "legal" => board.legal_moves_iter().count().to_string(),
+            "result" => GameResult::of(board).as_token().to_string(),
+            _ => match name.strip_prefix("square:") {
+                Some(square) => BoardPosition::from_algebraic(square)
+                    .filter(|position| board.size().contains(position))
+                    .and_then(|position| {
+                        let letter = board.get_piece_type(&position)?.letter();
+                        Some(match board.get_piece_color(&position)? {
+                            PieceColor::White => letter,
+                            PieceColor::Black => letter.to_ascii_lowercase(),
+                        })
+                    })
+                    .unwrap_or('.')
+                    .to_string(),
+                None => self.variables.get(name).cloned().unwrap_or_default(),
+            },
+        }
+    }
+
+    /// Returns the given text with each query in braces replaced by its value.
+    fn expand(&self, text: &str, board: &ChessBoard) -> String {
+        let mut expanded = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            let Some(length) = rest[start..].find('}') else {
+                break;
+            };
+            expanded.push_str(&rest[..start]);
+            expanded.push_str(&self.query(&rest[start + 1..start + length], board));
+            rest = &rest[start + length + 1..];
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+
+    /// Runs the given statement on the given board, adding what it asks for to the actions.
+    fn run(&mut self, statement: &Statement, board: &ChessBoard, actions: &mut Vec<Action>) {
+        match statement {
+            Statement::Log(text) => actions.push(Action::Log(self.expand(text, board))),
+            Statement::Notify(text) => actions.push(Action::Notify(self.expand(text, board))),
+            Statement::Play(text) => actions.push(Action::Play(self.expand(text, board))),
+            Statement::Set(name, text) => {
+                let value = self.expand(text, board);
+                self.variables.insert(name.clone(), value);
+            }
+            Statement::Add(name, amount) => {
+                let value = self.variables.entry(name.clone()).or_default();
+                *value = (value.parse().unwrap_or(0) + amount).to_string();
+            }
+            Statement::If {
+                left,
+                comparison,
+                right,
+                then,
+            } => {
+                if comparison.holds(&self.expand(left, board), &self.expand(right, board)) {
+                    self.run(then, board, actions);
+                }
+            }
+        }
+    }
+
+    /// Runs the statements of each of the given hooks on the given board, in the order the hooks
+    /// are in the script, returning what they ask for.
+    fn run_hooks(&mut self, hooks: &[Hook], board: &ChessBoard) -> Vec<Action> {
+        let mut actions = Vec::new();
+        let script = std::mem::take(&mut self.script);
+        for (_, statements) in script.hooks.iter().filter(|(hook, _)| hooks.contains(hook)) {
+            for statement in statements {
+                self.run(statement, board, &mut actions);
+            }
+        }
+        self.script = script;
+        actions
+    }
+}
+
+fn load_script(mut runner: ResMut<ScriptRunner>, mut notifications: EventWriter<Notification>) {
+    let Ok(path) = std::env::var("CHESS_SCRIPT") else {
+        return;
+    };
+    let script = std::fs::read_to_string(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| Script::parse(&text).map_err(|error| error.to_string()));
+    match script {
+        Ok(script) => {
+            info!("Loaded the script {}", path);
+            runner.script = script;
+        }
+        Err(error) => {
+            warn!("Could not load the script {}: {}", path, error);
+            notifications.send(
+                Notification::error("script-load-failed").with_arg("error", Argument::Text(error)),
+            );
+        }
+    }
+}
+
+/// What the hooks were last run for.
+#[derive(Debug, Default)]
+struct Watched {
+    ply: usize,
+    fen: String,
+    ended: bool,
+}
+
+/// Runs the hooks of whatever changed on the board, and does what they ask for.
+fn run_hooks(
+    mut runner: ResMut<ScriptRunner>,
+    board: Res<ChessBoard>,
+    mut resets: EventReader<ResetBoardEvent>,
+    mut commands: ResMut<GameCommands>,
+    mut notifications: EventWriter<Notification>,
+    mut watched: Local<Option<Watched>>,
+) {
+    if !board.is_changed() && resets.is_empty() {
+        return;
+    }
+    let started = !resets.is_empty() || watched.is_none();
+    resets.clear();
+    let now = Watched {
+        ply: board.past_moves().len(),
+        fen: board.to_fen().to_string(),
+        ended: board.game_end_status().is_some(),
+    };
+    let before = watched.take().unwrap_or_default();
+    let mut hooks = Vec::new();
+    if started {
+        hooks.push(Hook::Start);
+    } else if now.ply > before.ply {
+        hooks.push(Hook::Move);
+    }
+    if started || now.fen != before.fen {
+        hooks.push(Hook::Position);
+    }
+    if now.ended && !before.ended {
+        hooks.push(Hook::End);
+    }
+    *watched = Some(now);
+    for action in runner.run_hooks(&hooks, &board) {
+        match action {
+            Action::Log(text) => info!("{}", text),
+            Action::Notify(text) => notifications.send(
+                Notification::info("script-message").with_arg("message", Argument::Text(text)),
+            ),
+            Action::Play(text) => match Move::from_algebraic(&text, &board) {
+                Some(piece_move) => commands.push(GameCommand::MakeMove(piece_move)),
+                None => warn!("The script's move {} is not legal", text),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let script = Script::parse(
+            "# Counts the checks\non move\n    if {check} == true add checks 1\n\non end\n    log {checks}",
+        )
+        .unwrap();
+        assert_eq!(script.hooks.len(), 2);
+        assert_eq!(
+            script.hooks[0].1[0],
+            Statement::If {
+                left: "{check}".to_string(),
+                comparison: Comparison::Equal,
+                right: "true".to_string(),
+                then: Box::new(Statement::Add("checks".to_string(), 1)),
+            }
+        );
+
+        let error = |text| Script::parse(text).unwrap_err().to_string();
+        assert_eq!(error("log early"), "line 1: statements must follow a hook");
+        assert_eq!(
+            error("on move\n\nadd checks many"),
+            "line 3: many is not a whole number"
+        );
+        assert_eq!(error("on capture"), "line 1: capture is not a hook");
+    }
+
+    #[test]
+    fn test_run_hooks() {
+        let mut runner = ScriptRunner {
+            script: Script::parse(
+                "on start\n    set opening {square:e2}{square:e4}\non position\n    add positions 1\n    \
+                 if {ply} < 1 play e4\n    if {turn} == black notify {opening} then {move}",
+            )
+            .unwrap(),
+            variables: HashMap::new(),
+        };
+        let mut board = ChessBoard::with_position(&Fen::default());
+        assert_eq!(
+            runner.run_hooks(&[Hook::Start, Hook::Position], &board),
+            [Action::Play("e4".to_string())]
+        );
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        assert_eq!(
+            runner.run_hooks(&[Hook::Move, Hook::Position], &board),
+            [Action::Notify("P. then e4".to_string())]
+        );
+        assert_eq!(runner.query("positions", &board), "2");
+        assert_eq!(runner.query("legal", &board), "20");
+    }
+}
@@ -0,0 +1,436 @@
+//! A simple HTTP server exposing the running game to external tools, enabled with the `server` feature.
+//!
+//! The server listens on the address in the `CHESS_SERVER_ADDRESS` environment variable, or
+//! [DEFAULT_ADDRESS] if it is not set, and provides the following endpoints:
+//!
+//! - `GET /position` returns the current position as JSON, with the position the game started
+//!   from and its moves in long algebraic notation so a client can replay the whole game.
+//! - `GET /position/fen` returns the current position as a FEN string.
+//! - `POST /position/diff` returns the squares which differ between the position given as a FEN
+//!   string in the body and the current position, as JSON, so a client can catch up without
+//!   redrawing the whole board.
+//! - `GET /moves` streams the moves as [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html) as they are made.
+//! - `POST /move` requests the move in long algebraic notation (e.g. `e2e4`) given as the body.
+//!
+//! Requests with a body longer than [MAX_BODY_LENGTH] are answered with `413 Payload Too Large`.
+//! If the address cannot be bound the error is logged and the game runs without the server.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::log::{error, info, info_span};
+use bevy::prelude::{DetectChanges, Res, ResMut, Resource, Update};
+use serde_json::json;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::snapshot::{BoardSnapshot, SquareContent};
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor};
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+
+/// The address the server listens on if none is configured.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// The largest request body the server reads, larger requests are refused.
+const MAX_BODY_LENGTH: usize = 64 * 1024;
+
+/// How often the move stream checks for new moves.
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub(super) struct ServerPlugin;
+
+impl Plugin for ServerPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        let address =
+            std::env::var("CHESS_SERVER_ADDRESS").unwrap_or_else(|_| DEFAULT_ADDRESS.to_string());
+        let listener = match TcpListener::bind(&address) {
+            Ok(listener) => listener,
+            Err(error) => {
+                error!(address, %error, "Could not start the server");
+                return;
+            }
+        };
+        let game = SharedGame::default();
+        let (move_sender, move_receiver) = channel();
+
+        let server_game = game.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let game = server_game.clone();
+                let move_sender = move_sender.clone();
+                thread::spawn(move || handle_connection(stream, &game, &move_sender));
+            }
+        });
+
+        app.insert_resource(ServerState {
+            game,
+            move_receiver: Mutex::new(move_receiver),
+        })
+        .add_systems(Update, (update_shared_game, request_server_moves));
+    }
+}
+
+/// The board and the position its game started from.
+#[derive(Clone, Default)]
+struct ServedGame {
+    board: ChessBoard,
+    start: Fen,
+}
+
+/// A copy of the game shared with the server threads.
+#[derive(Clone, Default)]
+struct SharedGame(Arc<Mutex<ServedGame>>);
+
+impl SharedGame {
+    fn game(&self) -> ServedGame {
+        self.0.lock().unwrap().clone()
+    }
+
+    fn board(&self) -> ChessBoard {
+        self.0.lock().unwrap().board.clone()
+    }
+}
+
+#[derive(Resource)]
+struct ServerState {
+    game: SharedGame,
+    move_receiver: Mutex<Receiver<Move>>,
+}
+
+/// A parsed HTTP request.
+#[derive(Debug, PartialEq, Eq)]
+struct Request {
+    method: String,
+    path: String,
+    body: String,
+}
+
+/// Why a request could not be read.
+#[derive(Debug, PartialEq, Eq)]
+enum RequestError {
+    /// The request was cut short or is not valid HTTP.
+    Malformed,
+    /// The body is longer than [MAX_BODY_LENGTH].
+    TooLarge,
+}
+
+/// Reads an HTTP request from the given reader.
+fn read_request(reader: &mut impl BufRead) -> Result<Request, RequestError> {
+    let mut request_line = String::new();
+    reader
+        .read_line(&mut request_line)
+        .map_err(|_| RequestError::Malformed)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or(RequestError::Malformed)?.to_string();
+    let path = parts.next().ok_or(RequestError::Malformed)?.to_string();
+
+    // Read the headers, keeping only the content length
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|_| RequestError::Malformed)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| RequestError::Malformed)?;
+            }
+        }
+    }
+
+    // Checked before allocating the body, so a client cannot make the server allocate any amount
+    if content_length > MAX_BODY_LENGTH {
+        return Err(RequestError::TooLarge);
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|_| RequestError::Malformed)?;
+    Ok(Request {
+        method,
+        path,
+        body: String::from_utf8(body).map_err(|_| RequestError::Malformed)?,
+    })
+}
+
+/// Returns the JSON representation of the position of the given game.
+fn position_json(game: &ServedGame) -> String {
+    let board = &game.board;
+    json!({
+        "fen": board.to_fen().to_string(),
+        "start": game.start.to_string(),
+        "active_color": board.active_color().map(|color| color.to_string()),
+        "move_number": board.move_number(),
+        "moves": board
+            .past_moves()
+            .iter()
+            .map(|piece_move| piece_move.as_algebraic())
+            .collect::<Vec<String>>(),
+        "uci": board
+            .past_moves()
+            .iter()
+            .map(Move::as_long_algebraic)
+            .collect::<Vec<String>>(),
+        "game_end_status": board.game_end_status().map(|status| format!("{:?}", status)),
+        "winner": board.winner().map(|color| color.to_string()),
+    })
+    .to_string()
+}
+
+/// Returns the FEN letter of the given piece, which is lowercase for black.
+fn piece_letter(content: SquareContent) -> Option<String> {
+    content.map(|(color, piece_type)| match color {
+        PieceColor::White => piece_type.letter().to_string(),
+        PieceColor::Black => piece_type.letter().to_ascii_lowercase().to_string(),
+    })
+}
+
+/// Returns the JSON list of the squares whose pieces differ between the given position and the
+/// position on the given board, with the piece on each before and after.
+fn diff_json(fen: &Fen, board: &ChessBoard) -> String {
+    let changes =
+        BoardSnapshot::of(&ChessBoard::with_position(fen)).diff(&BoardSnapshot::of(board));
+    json!(changes
+        .iter()
+        .map(|change| json!({
+            "square": change.position.to_algebraic(),
+            "before": piece_letter(change.before),
+            "after": piece_letter(change.after),
+        }))
+        .collect::<Vec<_>>())
+    .to_string()
+}
+
+/// Returns the status and body of the response to the given request.
+/// Valid moves are sent with the given sender to be made on the board.
+fn respond(request: &Request, game: &ServedGame, move_sender: &Sender<Move>) -> (u16, String) {
+    let board = &game.board;
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/position") => (200, position_json(game)),
+        ("GET", "/position/fen") => (200, board.to_fen().to_string()),
+        ("POST", "/position/diff") => match Fen::parse(request.body.trim()) {
+            Some(fen) => (200, diff_json(&fen, board)),
+            None => (400, format!("Invalid FEN: {}", request.body.trim())),
+        },
+        ("POST", "/move") => match Move::from_long_algebraic(request.body.trim(), board) {
+            Some(piece_move) if board.valid_move(&piece_move, board.active_color(), &true) => {
+                move_sender.send(piece_move).ok();
+                (200, piece_move.as_algebraic())
+            }
+            _ => (400, format!("Invalid move: {}", request.body.trim())),
+        },
+        ("GET", _) | ("POST", _) => (404, "Not found".to_string()),
+        _ => (405, "Method not allowed".to_string()),
+    }
+}
+
+fn handle_connection(mut stream: TcpStream, game: &SharedGame, move_sender: &Sender<Move>) {
+    let request = match read_request(&mut BufReader::new(&mut stream)) {
+        Ok(request) => request,
+        Err(RequestError::TooLarge) => {
+            info!("Refused a request which is too large");
+            write_response(
+                &mut stream,
+                413,
+                format!("The body is larger than {} bytes", MAX_BODY_LENGTH),
+            );
+            return;
+        }
+        Err(RequestError::Malformed) => return,
+    };
+    let _span = info_span!("request", method = request.method, path = request.path).entered();
+    if request.method == "GET" && request.path == "/moves" {
+        info!("Streaming moves");
+        stream_moves(stream, game);
+        return;
+    }
+    let (status, body) = respond(&request, &game.game(), move_sender);
+    info!(status, body, "Responded");
+    write_response(&mut stream, status, body);
+}
+
+/// Writes a response with the given status and body, closing the connection after it.
+fn write_response(stream: &mut TcpStream, status: u16, body: String) {
+    let content_type = if body.starts_with('{') {
+        "application/json"
+    } else {
+        "text/plain"
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        match status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            413 => "Payload Too Large",
+            _ => "Method Not Allowed",
+        },
+        content_type,
+        body.len(),
+        body
+    )
+    .ok();
+}
+
+/// Sends each move as a server-sent event until the client disconnects.
+fn stream_moves(mut stream: TcpStream, game: &SharedGame) {
+    if write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\n\r\n"
+    )
+    .is_err()
+    {
+        return;
+    }
+    let mut sent_moves = game.board().past_moves().len();
+    loop {
+        let board = game.board();
+        let past_moves = board.past_moves();
+        // A reset board starts the stream again
+        if past_moves.len() < sent_moves {
+            sent_moves = 0;
+        }
+        for piece_move in &past_moves[sent_moves..] {
+            if write!(stream, "data: {}\n\n", piece_move.as_algebraic()).is_err() {
+                return;
+            }
+        }
+        sent_moves = past_moves.len();
+        thread::sleep(STREAM_POLL_INTERVAL);
+    }
+}
+
+fn update_shared_game(
+    board: Res<ChessBoard>,
+    history: Res<PositionHistory>,
+    state: Res<ServerState>,
+) {
+    if board.is_changed() || history.is_changed() {
+        *state.game.0.lock().unwrap() = ServedGame {
+            board: board.clone(),
+            start: history.start().cloned().unwrap_or_else(|| board.to_fen()),
+        };
+    }
+}
+
+fn request_server_moves(state: Res<ServerState>, mut commands: ResMut<GameCommands>) {
+    for piece_move in state.move_receiver.lock().unwrap().try_iter() {
+        commands.push(GameCommand::MakeMove(piece_move));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_request() {
+        let request = "POST /move HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4\r\n\r\ne2e4";
+
+        assert_eq!(
+            read_request(&mut request.as_bytes()),
+            Ok(Request {
+                method: "POST".to_string(),
+                path: "/move".to_string(),
+                body: "e2e4".to_string(),
+            })
+        );
+        assert_eq!(
+            read_request(&mut "".as_bytes()),
+            Err(RequestError::Malformed)
+        );
+
+        // The body is refused from its length alone, without being sent
+        let request = format!(
+            "POST /move HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_LENGTH + 1
+        );
+        assert_eq!(
+            read_request(&mut request.as_bytes()),
+            Err(RequestError::TooLarge)
+        );
+        let request = format!(
+            "POST /move HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            MAX_BODY_LENGTH,
+            "a".repeat(MAX_BODY_LENGTH)
+        );
+        assert_eq!(
+            read_request(&mut request.as_bytes()).map(|request| request.body.len()),
+            Ok(MAX_BODY_LENGTH)
+        );
+    }
+
+    #[test]
+    fn test_respond() {
+        let game = ServedGame::default();
+        let board = &game.board;
+        let (move_sender, move_receiver) = channel();
+        let request = |method: &str, path: &str, body: &str| Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.to_string(),
+        };
+
+        assert_eq!(
+            respond(&request("GET", "/position/fen", ""), &game, &move_sender),
+            (200, board.to_fen().to_string())
+        );
+        assert_eq!(
+            respond(&request("GET", "/unknown", ""), &game, &move_sender).0,
+            404
+        );
+        assert_eq!(
+            respond(&request("DELETE", "/move", ""), &game, &move_sender).0,
+            405
+        );
+        // The empty board has no pieces to move
+        assert_eq!(
+            respond(&request("POST", "/move", "e2e4"), &game, &move_sender).0,
+            400
+        );
+        assert!(move_receiver.try_recv().is_err());
+
+        let json: serde_json::Value =
+            serde_json::from_str(&respond(&request("GET", "/position", ""), &game, &move_sender).1)
+                .unwrap();
+        assert_eq!(json["fen"], board.to_fen().to_string());
+        assert_eq!(json["active_color"], serde_json::Value::Null);
+        assert_eq!(json["start"], Fen::default().to_string());
+        assert_eq!(json["uci"], serde_json::json!([]));
+
+        // The empty board differs from the start position on every square of the pieces
+        let start = Fen::default().to_string();
+        let (status, body) = respond(
+            &request("POST", "/position/diff", &start),
+            &game,
+            &move_sender,
+        );
+        assert_eq!(status, 200);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 32);
+        assert_eq!(json[0]["square"], "a8");
+        assert_eq!(json[0]["before"], "r");
+        assert_eq!(json[0]["after"], serde_json::Value::Null);
+        assert_eq!(
+            respond(
+                &request("POST", "/position/diff", "nonsense"),
+                &game,
+                &move_sender
+            )
+            .0,
+            400
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Share links, URLs which open the app on a position or game.
+//!
+//! A link is the address of the web build with the position in its query, such as
+//! `https://chess-computer.pages.dev/?fen=8/8/8/4k3/8/8/4K3/R7+w+-+-+0+1&moves=a1a3+e5d5`. The
+//! `fen` parameter is the starting position, which is left out for the standard one, and the
+//! `moves` parameter the moves played from it in long algebraic notation, separated by `+` as
+//! the spaces of the FEN are. The web build opens the link it was loaded from, and the desktop
+//! app the one given with `--open <link>`.
+
+use bevy::app::{App, Plugin};
+#[cfg(target_arch = "wasm32")]
+use bevy::log::warn;
+use bevy::prelude::{Commands, Res, ResMut, Resource, Update};
+use chesscomputer_core::Game;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, ResetBoardEvent, Variant};
+use crate::fen::Fen;
+
+/// The address of the web build, which links made by the desktop app point to.
+const WEB_URL: &str = "https://chess-computer.pages.dev/";
+
+pub(super) struct SharePlugin;
+
+impl Plugin for SharePlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, open_shared_game);
+    }
+}
+
+/// A position and the moves played from it, read from a share link.
+#[derive(Resource, Debug, Clone)]
+pub struct SharedGame {
+    start: Fen,
+    moves: Vec<Move>,
+}
+
+impl SharedGame {
+    /// Reads the game from the query of the given link, returning None if it holds neither a
+    /// position nor moves, or the position or one of the moves is not valid.
+    pub fn parse(link: &str) -> Option<Self> {
+        let query = link.split_once('?').map_or(link, |(_, query)| query);
+        let query = query.split('#').next().unwrap_or_default();
+        let mut start = None;
+        let mut moves = None;
+        for parameter in query.split('&') {
+            let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+            match name {
+                "fen" => start = Some(Fen::parse(&decode(value)?)?),
+                "moves" => moves = Some(decode(value)?),
+                _ => {}
+            }
+        }
+        if start.is_none() && moves.is_none() {
+            return None;
+        }
+        let start = start.unwrap_or_default();
+        let mut board = ChessBoard::with_position(&start);
+        for text in moves.as_deref().unwrap_or_default().split_whitespace() {
+            let piece_move = Move::from_long_algebraic(text, &board)?;
+            if !board.valid_move(&piece_move, board.active_color(), &false) {
+                return None;
+            }
+            board.apply_move(&piece_move);
+        }
+        Some(SharedGame {
+            start,
+            moves: board.past_moves().clone(),
+        })
+    }
+
+    /// Returns the event resetting the board to the game, with its moves played.
+    fn reset_event(&self) -> ResetBoardEvent {
+        let game = Game::replay(self.start.clone(), Variant::Standard, &self.moves);
+        ResetBoardEvent::with_past_moves(game.board().to_fen(), self.moves.clone())
+    }
+}
+
+/// Returns the link to the game played from the given position with the given moves, at the
+/// page of the web build the app is running in or, on the desktop, at [WEB_URL].
+pub fn link(start: &Fen, moves: &[Move]) -> String {
+    let mut parameters = Vec::new();
+    if start.to_string() != Fen::default().to_string() {
+        parameters.push(format!("fen={}", encode(&start.to_string())));
+    }
+    if !moves.is_empty() {
+        let moves: Vec<String> = moves.iter().map(Move::as_long_algebraic).collect();
+        parameters.push(format!("moves={}", encode(&moves.join(" "))));
+    }
+    format!("{}?{}", base_url(), parameters.join("&"))
+}
+
+/// Returns the address of the page the web build was loaded from.
+#[cfg(target_arch = "wasm32")]
+fn base_url() -> String {
+    let location = web_sys::window().map(|window| window.location());
+    location
+        .and_then(|location| Some(location.origin().ok()? + &location.pathname().ok()?))
+        .unwrap_or_else(|| WEB_URL.to_string())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn base_url() -> String {
+    WEB_URL.to_string()
+}
+
+/// Returns the game of the link the web build was loaded from, warning if the link has a query
+/// which does not hold a valid position or game.
+#[cfg(target_arch = "wasm32")]
+pub fn page_game() -> Option<SharedGame> {
+    let link = web_sys::window()?.location().href().ok()?;
+    let shared = SharedGame::parse(&link);
+    if shared.is_none() && link.contains('?') {
+        warn!(link, "The link does not hold a valid position or game");
+    }
+    shared
+}
+
+/// Escapes the given text for a query, writing spaces as `+`.
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b' ' => "+".to_string(),
+            b'/' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ if byte.is_ascii_alphanumeric() => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Unescapes the given query text, returning None if it has an invalid escape.
+fn decode(text: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut input = text.bytes();
+    while let Some(byte) = input.next() {
+        bytes.push(match byte {
+            b'+' => b' ',
+            b'%' => {
+                let digits = [input.next()?, input.next()?];
+                u8::from_str_radix(std::str::from_utf8(&digits).ok()?, 16).ok()?
+            }
+            _ => byte,
+        });
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Loads the shared game the app was opened with, once.
+fn open_shared_game(
+    mut commands: Commands,
+    shared: Option<Res<SharedGame>>,
+    mut game_commands: ResMut<GameCommands>,
+) {
+    let Some(shared) = shared else {
+        return;
+    };
+    game_commands.push(GameCommand::LoadGame(Box::new(shared.reset_event())));
+    commands.remove_resource::<SharedGame>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link() {
+        let start = Fen::from_string("8/8/8/4k3/8/8/4K3/R7 w - - 0 1");
+        let mut board = ChessBoard::with_position(&start);
+        for text in ["Ra3", "Kd5"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let shared_link = link(&start, board.past_moves());
+        assert_eq!(
+            shared_link,
+            format!(
+                "{}?fen=8/8/8/4k3/8/8/4K3/R7+w+-+-+0+1&moves=a1a3+e5d5",
+                WEB_URL
+            )
+        );
+
+        let shared = SharedGame::parse(&shared_link).unwrap();
+        assert_eq!(shared.start.to_string(), start.to_string());
+        assert_eq!(shared.moves, *board.past_moves());
+        assert_eq!(
+            shared.reset_event().past_moves().len(),
+            board.past_moves().len()
+        );
+
+        // The standard starting position is left out
+        let shared = SharedGame::parse("?moves=e2e4+e7e5#board").unwrap();
+        assert_eq!(shared.moves.len(), 2);
+        assert_eq!(
+            link(&shared.start, &shared.moves),
+            format!("{}?moves=e2e4+e7e5", WEB_URL)
+        );
+
+        for invalid in ["?moves=e2e5", "?fen=8%2", "?fen=nonsense", "?other=1", ""] {
+            assert!(SharedGame::parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+}
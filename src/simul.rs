@@ -0,0 +1,207 @@
+//! Simultaneous exhibitions, where the player plays several games against the engine at once.
+//!
+//! One game at a time is on the board, where the player and the engine play it as usual. The
+//! other games are kept aside, and the engine searches each of them in the background whenever
+//! it is its turn there, so the player finds its reply waiting when they switch back to it.
+
+use bevy::app::{App, Plugin};
+use bevy::log::info;
+use bevy::prelude::{Res, ResMut, Resource, Update};
+
+use crate::chess_board::{ChessBoard, PieceColor, ResetBoardEvent};
+use crate::engine::{BackgroundSearch, Engine};
+use crate::fen::Fen;
+
+/// The most games which can be played at once.
+pub const MAX_BOARDS: usize = 8;
+
+pub(super) struct SimulPlugin;
+
+impl Plugin for SimulPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Simul>()
+            .add_systems(Update, play_aside_boards);
+    }
+}
+
+/// Returns the result of the game on the given board as the winner, which is None for a draw,
+/// or None if the game is not over.
+pub fn outcome(board: &ChessBoard) -> Option<Option<PieceColor>> {
+    if board.game_end_status().is_some() {
+        return Some(*board.winner());
+    }
+    // The games kept aside are not checked for their end as they are played, so a game without
+    // legal moves is over
+    let color = (*board.active_color())?;
+    if board.legal_moves_iter().next().is_some() {
+        return None;
+    }
+    Some(board.in_check(&color).then(|| color.opposite()))
+}
+
+/// A game of the exhibition.
+struct SimulGame {
+    /// The game as it was when it was last on the board.
+    board: ChessBoard,
+    /// The engine's search for its move, while it is searching this game in the background.
+    search: Option<BackgroundSearch>,
+}
+
+/// The games of the exhibition in progress.
+#[derive(Resource, Default)]
+pub struct Simul {
+    games: Vec<SimulGame>,
+    /// The index of the game on the board.
+    focused: usize,
+    /// The color the player plays on every board.
+    player: Option<PieceColor>,
+}
+
+impl Simul {
+    /// Starts an exhibition of the given number of games with the player playing the given
+    /// color, returning the event which puts the first game on the board.
+    pub fn start(&mut self, count: usize, player: PieceColor) -> ResetBoardEvent {
+        let count = count.clamp(1, MAX_BOARDS);
+        let start = ChessBoard::with_position(&Fen::default());
+        self.games = (0..count)
+            .map(|_| SimulGame {
+                board: start.clone(),
+                search: None,
+            })
+            .collect();
+        self.focused = 0;
+        self.player = Some(player);
+        info!(count, player = %player, "Simul started");
+        ResetBoardEvent::new(Fen::default())
+    }
+
+    /// Ends the exhibition, leaving the game on the board where it is.
+    pub fn stop(&mut self) {
+        *self = Simul::default();
+    }
+
+    /// Returns the color the player plays, while an exhibition is in progress.
+    pub fn player(&self) -> Option<PieceColor> {
+        self.player
+    }
+
+    pub fn focused(&self) -> usize {
+        self.focused
+    }
+
+    /// Returns each game, given the board which holds the game currently on it.
+    pub fn boards<'a>(&'a self, board: &'a ChessBoard) -> Vec<&'a ChessBoard> {
+        self.games
+            .iter()
+            .enumerate()
+            .map(|(index, game)| match index == self.focused {
+                true => board,
+                false => &game.board,
+            })
+            .collect()
+    }
+
+    /// Puts the game at the given index on the board, keeping aside the game which was on it,
+    /// and returns the event which loads it.
+    pub fn focus(&mut self, index: usize, board: &ChessBoard) -> Option<ResetBoardEvent> {
+        if index == self.focused || index >= self.games.len() {
+            return None;
+        }
+        self.games[self.focused].board = board.clone();
+        self.focused = index;
+        // The engine plays the game on the board itself, so its background search is dropped
+        let game = &mut self.games[index];
+        game.search = None;
+        let board = &game.board;
+        Some(ResetBoardEvent::with_past_moves(
+            board.to_fen(),
+            board.past_moves().clone(),
+        ))
+    }
+
+    /// Returns the index of the next game after the one on the board where it is the player's
+    /// turn, if there is one.
+    pub fn next_board(&self, board: &ChessBoard) -> Option<usize> {
+        let player = self.player?;
+        let boards = self.boards(board);
+        (1..boards.len())
+            .map(|offset| (self.focused + offset) % boards.len())
+            .find(|index| {
+                *boards[*index].active_color() == Some(player) && outcome(boards[*index]).is_none()
+            })
+    }
+
+    /// Returns the player's wins, draws and losses in the finished games.
+    pub fn score(&self, board: &ChessBoard) -> (usize, usize, usize) {
+        let mut score = (0, 0, 0);
+        for outcome in self.boards(board).into_iter().filter_map(outcome) {
+            match outcome {
+                Some(winner) if Some(winner) == self.player => score.0 += 1,
+                Some(_) => score.2 += 1,
+                None => score.1 += 1,
+            }
+        }
+        score
+    }
+}
+
+/// Has the engine play its moves in the games kept aside.
+fn play_aside_boards(mut simul: ResMut<Simul>, engine: Res<Engine>) {
+    let Some(player) = simul.player else {
+        return;
+    };
+    let focused = simul.focused;
+    for (index, game) in simul.games.iter_mut().enumerate() {
+        if index == focused {
+            continue;
+        }
+        let board = &mut game.board;
+        match &game.search {
+            Some(search) => {
+                let Some((line, _)) = search.try_result() else {
+                    continue;
+                };
+                if let Some(piece_move) = line.first() {
+                    board.apply_move(piece_move);
+                }
+                game.search = None;
+            }
+            None if *board.active_color() == Some(player.opposite())
+                && outcome(board).is_none() =>
+            {
+                game.search = Some(engine.search_in_background(board));
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+
+    use super::*;
+
+    #[test]
+    fn test_simul() {
+        let mut simul = Simul::default();
+        simul.start(3, PieceColor::White);
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let e4 = Move::from_algebraic("e4", &board).unwrap();
+        board.apply_move(&e4);
+        assert_eq!(simul.next_board(&board), Some(1));
+
+        // The game moved away from is kept as it was
+        let reset_event = simul.focus(2, &board).unwrap();
+        assert!(reset_event.past_moves().is_empty());
+        assert_eq!(simul.games[0].board.past_moves(), &vec![e4]);
+        assert_eq!(simul.next_board(&board), Some(1));
+        assert!(simul.focus(2, &board).is_none());
+
+        let mated = ChessBoard::with_position(&Fen::from_string("7k/6Q1/6K1/8/8/8/8/8 b - - 0 1"));
+        simul.games[1].board = mated;
+        assert_eq!(simul.score(&board), (1, 0, 0));
+        assert_eq!(simul.next_board(&board), None);
+    }
+}
@@ -0,0 +1,215 @@
+//! Smoke tests of the board's plugin stack, which run the real [ChessBoardPlugin] headless under
+//! [MinimalPlugins] with a mock of the UI's pieces in place of the UI, and play scripted games
+//! through the [GameCommands] queue as the players would.
+//!
+//! The mock keeps the pieces it has been told about by the board's events, as the UI keeps its
+//! piece entities, so each game checks both the final position and that the events sent along the
+//! way leave whatever draws the board in step with it.
+
+use std::collections::HashMap;
+
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::{EventReader, EventWriter, IntoSystemConfigs, Res, ResMut, Resource};
+use bevy::MinimalPlugins;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::snapshot::BoardSnapshot;
+use crate::chess_board::{
+    BoardPosition, ChessBoard, ChessBoardPlugin, GameCommand, GameCommands, GameEndStatus,
+    PieceColor, PieceCreateEvent, PieceMoveEvent, PieceType, ResetBoardEvent,
+};
+use crate::fen::Fen;
+use crate::notification::Notification;
+
+/// The pieces the mock UI has drawn, by their squares.
+#[derive(Resource, Debug, Default)]
+struct DrawnPieces(HashMap<BoardPosition, (PieceColor, PieceType)>);
+
+/// The keys of the notifications the board has sent.
+#[derive(Resource, Debug, Default)]
+struct Notifications(Vec<&'static str>);
+
+/// A mock of the UI, which moves, creates and resets its pieces on the board's events as the UI
+/// moves, creates and resets its piece entities.
+struct MockUiPlugin;
+
+impl Plugin for MockUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DrawnPieces>()
+            .init_resource::<Notifications>()
+            .add_systems(
+                Update,
+                (
+                    move_pieces,
+                    create_pieces,
+                    reset_pieces,
+                    record_notifications,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn move_pieces(mut events: EventReader<PieceMoveEvent>, mut drawn: ResMut<DrawnPieces>) {
+    for event in events.iter() {
+        // Any piece on the square is captured
+        drawn.0.remove(event.to());
+        if let Some(captured) = event.captured() {
+            drawn.0.remove(captured);
+        }
+        if let Some(piece) = drawn.0.remove(event.from()) {
+            drawn.0.insert(*event.to(), piece);
+        }
+    }
+}
+
+fn create_pieces(mut events: EventReader<PieceCreateEvent>, mut drawn: ResMut<DrawnPieces>) {
+    for event in events.iter() {
+        drawn
+            .0
+            .insert(*event.position(), (*event.color(), *event.piece_type()));
+    }
+}
+
+/// Redraws the squares which differ from a reset board, as the UI does.
+fn reset_pieces(
+    mut events: EventReader<ResetBoardEvent>,
+    board: Res<ChessBoard>,
+    mut drawn: ResMut<DrawnPieces>,
+    mut creates: EventWriter<PieceCreateEvent>,
+) {
+    if events.iter().last().is_none() {
+        return;
+    }
+    for change in drawn.snapshot().diff(&BoardSnapshot::of(&board)) {
+        drawn.0.remove(&change.position);
+        if let Some((color, piece_type)) = change.after {
+            creates.send(PieceCreateEvent::new(change.position, piece_type, color));
+        }
+    }
+}
+
+fn record_notifications(
+    mut events: EventReader<Notification>,
+    mut notifications: ResMut<Notifications>,
+) {
+    notifications
+        .0
+        .extend(events.iter().map(|notification| notification.key()));
+}
+
+impl DrawnPieces {
+    fn snapshot(&self) -> BoardSnapshot {
+        BoardSnapshot::from_pieces(
+            self.0
+                .iter()
+                .map(|(position, (color, piece_type))| (*position, *color, *piece_type)),
+        )
+    }
+}
+
+/// The board's plugin stack, run headless.
+struct Harness {
+    app: App,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ChessBoardPlugin::default(), MockUiPlugin));
+        let mut harness = Harness { app };
+        harness.settle();
+        harness
+    }
+
+    /// Runs enough frames for the commands queued to be carried out and the mock to catch up.
+    fn settle(&mut self) {
+        for _ in 0..3 {
+            self.app.update();
+        }
+    }
+
+    fn board(&self) -> &ChessBoard {
+        self.app.world.resource::<ChessBoard>()
+    }
+
+    fn push(&mut self, command: GameCommand) {
+        self.app.world.resource_mut::<GameCommands>().push(command);
+        self.settle();
+    }
+
+    /// Resets the board to the given position.
+    fn load(&mut self, fen: &str) {
+        let fen = Fen::parse(fen).unwrap();
+        self.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+    }
+
+    /// Makes each of the given moves, in standard algebraic notation, in turn.
+    fn play(&mut self, moves: &[&str]) {
+        for text in moves {
+            let piece_move = Move::from_algebraic(text, self.board())
+                .unwrap_or_else(|| panic!("{} is a move in {}", text, self.board().to_fen()));
+            self.push(GameCommand::MakeMove(piece_move));
+        }
+    }
+
+    /// Checks the position on the board, and that the mock has drawn it.
+    fn assert_position(&self, fen: &str) {
+        assert_eq!(self.board().to_fen().to_string(), fen);
+        let drawn = self.app.world.resource::<DrawnPieces>();
+        assert_eq!(drawn.snapshot(), BoardSnapshot::of(self.board()));
+    }
+
+    fn notifications(&self) -> &[&'static str] {
+        &self.app.world.resource::<Notifications>().0
+    }
+}
+
+#[test]
+fn test_scholars_mate() {
+    let mut harness = Harness::new();
+    harness.assert_position(&Fen::default().to_string());
+    harness.play(&["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7"]);
+    harness.assert_position("r1bqkb1r/pppp1Qpp/2n2n2/4p3/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 0 4");
+    let board = harness.board();
+    assert_eq!(*board.game_end_status(), Some(GameEndStatus::Checkmate));
+    assert_eq!(*board.winner(), Some(PieceColor::White));
+}
+
+#[test]
+fn test_castling() {
+    let mut harness = Harness::new();
+    harness.load("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/R3K2R w KQkq - 0 1");
+    harness.play(&["O-O", "O-O-O"]);
+    harness.assert_position("2kr3r/pppppppp/8/8/8/8/PPPPPPPP/R4RK1 w - - 2 2");
+}
+
+#[test]
+fn test_en_passant() {
+    let mut harness = Harness::new();
+    harness.load("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1");
+    harness.play(&["d5"]);
+    harness.assert_position("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2");
+    harness.play(&["exd6"]);
+    harness.assert_position("4k3/8/3P4/8/8/8/8/4K3 b - - 0 2");
+
+    // The capture is only possible straight after the pawn passes
+    harness.load("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1");
+    harness.play(&["d5", "Kf1", "Kd8"]);
+    let capture = Move::from_board(
+        BoardPosition::from_algebraic("e5").unwrap(),
+        BoardPosition::from_algebraic("d6").unwrap(),
+        harness.board(),
+    );
+    harness.push(GameCommand::MakeMove(capture));
+    harness.assert_position("3k4/8/8/3pP3/8/8/8/5K2 w - - 2 3");
+    assert_eq!(harness.notifications(), ["illegal-move"]);
+}
+
+#[test]
+fn test_promotion() {
+    let mut harness = Harness::new();
+    harness.load("1r2k3/P1P5/8/8/8/8/8/4K3 w - - 0 1");
+    harness.play(&["cxb8=N", "Ke7", "a8=Q"]);
+    harness.assert_position("QN6/4k3/8/8/8/8/8/4K3 b - - 0 2");
+}
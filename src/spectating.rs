@@ -0,0 +1,95 @@
+//! The read-only mode of a board which is only watched, as while following a
+//! [broadcast](crate::broadcast) or watching a [network game](crate::network) with `--watch`.
+//!
+//! While the board is spectated the UI's board input does not run at all, so no piece can be
+//! picked up, dropped or premoved and no move is made from the board, without each input handler
+//! having to check for itself. The UI shows a banner saying what is being spectated instead.
+
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::prelude::{DetectChangesMut, Res, ResMut, Resource};
+
+use crate::broadcast::Broadcast;
+use crate::network::NetworkGame;
+
+pub(super) struct SpectatingPlugin;
+
+impl Plugin for SpectatingPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Spectating>()
+            .add_systems(PreUpdate, update_spectating);
+    }
+}
+
+/// What the board is spectating.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spectated {
+    Broadcast,
+    Network,
+}
+
+/// What the board is spectating, if it is only being watched.
+#[derive(Resource, Debug, Default, PartialEq, Eq)]
+pub struct Spectating(Option<Spectated>);
+
+impl Spectating {
+    pub fn get(&self) -> Option<Spectated> {
+        self.0
+    }
+}
+
+/// Returns whether the board can be played on, for the run conditions of the board's input.
+pub fn not_spectating(spectating: Res<Spectating>) -> bool {
+    spectating.0.is_none()
+}
+
+/// Returns what is spectated while the given broadcast and network game are followed.
+fn spectated(broadcast: &Broadcast, network: Option<&NetworkGame>) -> Option<Spectated> {
+    if network.is_some_and(NetworkGame::is_watching) {
+        Some(Spectated::Network)
+    } else if broadcast.source().is_some() && broadcast.is_following() {
+        Some(Spectated::Broadcast)
+    } else {
+        None
+    }
+}
+
+fn update_spectating(
+    broadcast: Res<Broadcast>,
+    network: Option<Res<NetworkGame>>,
+    mut spectating: ResMut<Spectating>,
+) {
+    spectating.set_if_neq(Spectating(spectated(&broadcast, network.as_deref())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spectated() {
+        let mut broadcast = Broadcast::default();
+        assert_eq!(spectated(&broadcast, None), None);
+        let playing = NetworkGame::new(
+            "localhost:8080",
+            Some(crate::chess_board::PieceColor::White),
+            true,
+        );
+        assert_eq!(spectated(&broadcast, Some(&playing)), None);
+        let watching = NetworkGame::new("localhost:8080", None, true);
+        assert_eq!(
+            spectated(&broadcast, Some(&watching)),
+            Some(Spectated::Network)
+        );
+
+        // A broadcast is spectated until the player analyses it
+        broadcast.follow("missing.pgn");
+        assert_eq!(spectated(&broadcast, None), Some(Spectated::Broadcast));
+        broadcast.analyse();
+        assert_eq!(spectated(&broadcast, None), None);
+        broadcast.resync();
+        assert_eq!(spectated(&broadcast, None), Some(Spectated::Broadcast));
+        broadcast.stop();
+        assert_eq!(spectated(&broadcast, None), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Random starting positions, balanced positions with all the pieces still on the board for the
+//! trainers and engine matches to start from, and sharp ones for the time scramble practice.
+//!
+//! A position is reached by playing random quiet moves from the starting position, and is kept
+//! only when a short search finds neither player better off by more than [BALANCE]. A sharp
+//! position is reached by random moves which may capture, and is kept when the player to move has
+//! at least [SHARP_TENSION] captures or checks to choose from and neither player is better off by
+//! more than [SHARP_BALANCE]. Each position is generated from a seed, so it can be shared by its
+//! seed alone.
+
+use std::ops::RangeInclusive;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::ChessBoard;
+use crate::engine::evaluate_position;
+use crate::fen::Fen;
+use crate::random::Random;
+
+/// The numbers of plies played from the starting position, which leave the players developed.
+const PLIES: RangeInclusive<usize> = 16..=24;
+/// The depth positions are searched to when checking they are balanced.
+const DEPTH: u32 = 2;
+/// The largest evaluation in centipawns, for either player, of a balanced position.
+const BALANCE: i32 = 50;
+/// The number of positions tried before giving up on a seed.
+const ATTEMPTS: usize = 100;
+
+/// The numbers of plies played from the starting position to reach a sharp position.
+const SHARP_PLIES: RangeInclusive<usize> = 14..=28;
+/// The fewest captures and checks the player to move of a sharp position has.
+const SHARP_TENSION: usize = 3;
+/// The largest evaluation in centipawns, for either player, of a sharp position.
+const SHARP_BALANCE: i32 = 150;
+
+/// Plays random moves from the starting position, quiet ones unless captures are allowed,
+/// returning the board once they have all been played, or None if a player ran out of moves or
+/// was left in check.
+fn candidate(
+    random: &mut Random,
+    plies: &RangeInclusive<usize>,
+    captures: bool,
+) -> Option<ChessBoard> {
+    let mut board = ChessBoard::with_position(&Fen::default());
+    let plies = plies.start() + random.below(plies.end() - plies.start() + 1);
+    for _ in 0..plies {
+        let moves: Vec<Move> = board
+            .legal_moves_iter()
+            .filter(|piece_move| captures || !piece_move.is_capture())
+            .collect();
+        if moves.is_empty() {
+            return None;
+        }
+        board.apply_move(&moves[random.below(moves.len())]);
+    }
+    let color = (*board.active_color())?;
+    (!board.in_check(&color)).then_some(board)
+}
+
+/// Generates a balanced position from the given seed, or None if none was found.
+pub fn generate(seed: u64) -> Option<Fen> {
+    let mut random = Random::new(seed);
+    (0..ATTEMPTS).find_map(|_| {
+        // Captures are left out so all the pieces stay on the board
+        let board = candidate(&mut random, &PLIES, false)?;
+        (evaluate_position(&board, DEPTH).abs() <= BALANCE).then(|| board.to_fen())
+    })
+}
+
+/// Returns the number of captures and checks the player to move can choose from.
+fn tension(board: &ChessBoard) -> usize {
+    board
+        .legal_moves_iter()
+        .filter(|piece_move| {
+            piece_move.is_capture() || {
+                let mut after = board.clone();
+                after.apply_move(piece_move);
+                (*after.active_color()).is_some_and(|color| after.in_check(&color))
+            }
+        })
+        .count()
+}
+
+/// Generates a sharp position from the given seed, or None if none was found.
+pub fn generate_sharp(seed: u64) -> Option<Fen> {
+    let mut random = Random::new(seed);
+    (0..ATTEMPTS).find_map(|_| {
+        let board = candidate(&mut random, &SHARP_PLIES, true)?;
+        (tension(&board) >= SHARP_TENSION
+            && evaluate_position(&board, DEPTH).abs() <= SHARP_BALANCE)
+            .then(|| board.to_fen())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let fen = generate(7).unwrap();
+        assert_eq!(generate(7).unwrap().to_string(), fen.to_string());
+        assert_ne!(
+            generate(8).map(|other| other.to_string()),
+            Some(fen.to_string())
+        );
+
+        let board = ChessBoard::with_position(&fen);
+        let pieces = board
+            .size()
+            .positions()
+            .filter(|position| board.get_piece_type(position).is_some())
+            .count();
+        assert_eq!(pieces, 32);
+        assert!(board.legal_moves_iter().next().is_some());
+        assert!(evaluate_position(&board, DEPTH).abs() <= BALANCE);
+    }
+
+    #[test]
+    fn test_generate_sharp() {
+        let fen = generate_sharp(7).unwrap();
+        assert_eq!(generate_sharp(7).unwrap().to_string(), fen.to_string());
+
+        let board = ChessBoard::with_position(&fen);
+        assert!(tension(&board) >= SHARP_TENSION);
+        assert!(evaluate_position(&board, DEPTH).abs() <= SHARP_BALANCE);
+        assert!(!board.in_check(&board.active_color().unwrap()));
+    }
+}
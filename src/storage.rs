@@ -0,0 +1,163 @@
+//! Persistent storage, which keeps the settings, saved games, databases and journals between runs.
+//!
+//! The storage is a set of texts stored by key. The desktop app keeps each in the file named by
+//! its key, relative to the working directory, and the web build in the browser's local storage,
+//! the backend being chosen when the app is compiled. The functions of this module use the
+//! backend of the platform, so persistence works the same on both.
+
+/// A store of texts by key.
+pub trait StorageBackend {
+    /// Returns the text stored at the given key, or None if there is none or it cannot be read.
+    fn read(&self, key: &str) -> Option<String>;
+
+    /// Stores the given text at the given key, replacing any text stored there.
+    fn write(&self, key: &str, text: &str) -> Result<(), String>;
+
+    /// Adds the given text to the end of the text stored at the given key.
+    fn append(&self, key: &str, text: &str) -> Result<(), String> {
+        let stored = self.read(key).unwrap_or_default();
+        self.write(key, &(stored + text))
+    }
+
+    /// Removes the text stored at the given key, if there is one.
+    fn remove(&self, key: &str);
+}
+
+/// Storage in files, the key of each text being the path of its file.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FileStorage;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StorageBackend for FileStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(key).ok()
+    }
+
+    fn write(&self, key: &str, text: &str) -> Result<(), String> {
+        std::fs::write(key, text).map_err(|error| error.to_string())
+    }
+
+    fn append(&self, key: &str, text: &str) -> Result<(), String> {
+        use std::io::Write;
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(key)
+            .and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|error| error.to_string())
+    }
+
+    fn remove(&self, key: &str) {
+        std::fs::remove_file(key).ok();
+    }
+}
+
+/// Storage in the browser's local storage, which is kept for the site the web build is loaded
+/// from.
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LocalStorage;
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    fn storage() -> Result<web_sys::Storage, String> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| "Local storage is not available".to_string())
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl StorageBackend for LocalStorage {
+    fn read(&self, key: &str) -> Option<String> {
+        Self::storage().ok()?.get_item(key).ok().flatten()
+    }
+
+    fn write(&self, key: &str, text: &str) -> Result<(), String> {
+        Self::storage()?
+            .set_item(key, text)
+            // Writing fails when the site has used up its quota
+            .map_err(|_| format!("Could not store {}", key))
+    }
+
+    fn remove(&self, key: &str) {
+        if let Ok(storage) = Self::storage() {
+            storage.remove_item(key).ok();
+        }
+    }
+}
+
+/// The backend of the platform the app is compiled for.
+#[cfg(not(target_arch = "wasm32"))]
+const BACKEND: FileStorage = FileStorage;
+#[cfg(target_arch = "wasm32")]
+const BACKEND: LocalStorage = LocalStorage;
+
+/// Returns the text stored at the given key, or None if there is none or it cannot be read.
+pub fn read(key: &str) -> Option<String> {
+    BACKEND.read(key)
+}
+
+/// Stores the given text at the given key, replacing any text stored there.
+pub fn write(key: &str, text: &str) -> Result<(), String> {
+    BACKEND.write(key, text)
+}
+
+/// Adds the given text to the end of the text stored at the given key.
+pub fn append(key: &str, text: &str) -> Result<(), String> {
+    BACKEND.append(key, text)
+}
+
+/// Removes the text stored at the given key, if there is one.
+pub fn remove(key: &str) {
+    BACKEND.remove(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// Storage in memory, for the default append.
+    #[derive(Default)]
+    struct MemoryStorage(RefCell<HashMap<String, String>>);
+
+    impl StorageBackend for MemoryStorage {
+        fn read(&self, key: &str) -> Option<String> {
+            self.0.borrow().get(key).cloned()
+        }
+
+        fn write(&self, key: &str, text: &str) -> Result<(), String> {
+            self.0
+                .borrow_mut()
+                .insert(key.to_string(), text.to_string());
+            Ok(())
+        }
+
+        fn remove(&self, key: &str) {
+            self.0.borrow_mut().remove(key);
+        }
+    }
+
+    #[test]
+    fn test_storage() {
+        let path = std::env::temp_dir().join(format!("chess_storage_{}", std::process::id()));
+        let key = path.to_str().unwrap();
+        let memory = MemoryStorage::default();
+        let backends: [&dyn StorageBackend; 2] = [&FileStorage, &memory];
+        for backend in backends {
+            assert_eq!(backend.read(key), None);
+            backend.write(key, "first\n").unwrap();
+            backend.append(key, "second\n").unwrap();
+            assert_eq!(backend.read(key).as_deref(), Some("first\nsecond\n"));
+            backend.write(key, "replaced").unwrap();
+            assert_eq!(backend.read(key).as_deref(), Some("replaced"));
+            backend.remove(key);
+            assert_eq!(backend.read(key), None);
+        }
+    }
+}
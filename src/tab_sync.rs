@@ -0,0 +1,272 @@
+//! Keeps the game in step between windows sharing the [autosave](crate::autosave): tabs of the
+//! web build, which share the site's local storage, or copies of the desktop app started in the
+//! same directory.
+//!
+//! The web build learns that another tab saved from the browser's storage events, and the desktop
+//! app reads the autosave every [CHECK_INTERVAL]. The saved game is then compared with this
+//! window's:
+//!
+//! - If it continues this window's game, the moves made in the other window are loaded here.
+//! - If this window's game continues it, or another window removed it on closing, this window's
+//!   game is saved at once, so closing a window never loses the moves made in the others.
+//! - If the two games went different ways, the player is asked which to keep.
+
+#[cfg(target_arch = "wasm32")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_arch = "wasm32")]
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{Event, EventReader, Res, ResMut, Resource, Startup, Update};
+use bevy::time::Time;
+
+use crate::autosave::{Autosave, SavedGame};
+use crate::chess_board::snapshot::BoardSnapshot;
+use crate::chess_board::ChessBoard;
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::history::PositionHistory;
+
+/// How often the desktop app reads the autosave for games saved by other windows.
+#[cfg(not(target_arch = "wasm32"))]
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(super) struct TabSyncPlugin;
+
+impl Plugin for TabSyncPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.add_event::<ResolveConflictEvent>()
+            .init_resource::<TabSync>()
+            .add_systems(Startup, listen_for_saves)
+            .add_systems(Update, (sync_game, resolve_conflict));
+    }
+}
+
+/// Event sent when the player chooses which of two conflicting games to keep.
+#[derive(Event)]
+pub struct ResolveConflictEvent {
+    /// Whether to keep this window's game rather than the other window's.
+    keep_this: bool,
+}
+
+impl ResolveConflictEvent {
+    pub fn new(keep_this: bool) -> Self {
+        ResolveConflictEvent { keep_this }
+    }
+}
+
+/// How the game saved by another window stands against this window's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    /// The games have the same moves.
+    Same,
+    /// This window's game continues the saved game.
+    Ahead,
+    /// The saved game continues this window's game.
+    Behind,
+    /// The games went different ways.
+    Diverged,
+}
+
+fn compare(this: &SavedGame, saved: &SavedGame) -> Comparison {
+    match (this.continues(saved), saved.continues(this)) {
+        (true, true) => Comparison::Same,
+        (true, false) => Comparison::Ahead,
+        (false, true) => Comparison::Behind,
+        (false, false) => Comparison::Diverged,
+    }
+}
+
+/// A game saved by another window which went a different way from this window's.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    saved: SavedGame,
+    /// The number of squares whose pieces differ between the final positions of the two games.
+    squares: usize,
+}
+
+impl Conflict {
+    fn new(this: &SavedGame, saved: SavedGame) -> Self {
+        let snapshot = |game: &SavedGame| {
+            game.reset_event()
+                .map(|event| BoardSnapshot::of(&ChessBoard::with_position(event.fen())))
+        };
+        let squares = match (snapshot(this), snapshot(&saved)) {
+            (Some(this), Some(saved)) => this.diff(&saved).len(),
+            _ => 0,
+        };
+        Conflict { saved, squares }
+    }
+
+    /// Returns the number of moves made in the other window's game.
+    pub fn saved_moves(&self) -> usize {
+        self.saved.move_count()
+    }
+
+    /// Returns the number of squares whose pieces differ between the two games.
+    pub fn squares(&self) -> usize {
+        self.squares
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TabSync {
+    /// The game of another window which conflicts with this window's, until the player chooses
+    /// which to keep.
+    conflict: Option<Conflict>,
+    /// Set by the storage event listener whenever another tab changes the local storage.
+    #[cfg(target_arch = "wasm32")]
+    changed: Arc<AtomicBool>,
+    /// The time since the autosave was last read.
+    #[cfg(not(target_arch = "wasm32"))]
+    since_check: Duration,
+}
+
+impl TabSync {
+    /// Returns the game of another window which conflicts with this window's, if the player has
+    /// not yet chosen which to keep.
+    pub fn conflict(&self) -> &Option<Conflict> {
+        &self.conflict
+    }
+
+    /// Returns whether another window may have saved since the last check.
+    #[cfg(target_arch = "wasm32")]
+    fn should_check(&mut self, _time: &Time) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn should_check(&mut self, time: &Time) -> bool {
+        self.since_check += time.delta();
+        if self.since_check < CHECK_INTERVAL {
+            return false;
+        }
+        self.since_check = Duration::ZERO;
+        true
+    }
+
+    /// Brings this window's game in step with the saved game, which is None if there is none.
+    fn sync(&mut self, this: &SavedGame, saved: Option<SavedGame>, autosave: &mut Autosave) {
+        self.conflict = None;
+        let Some(saved) = saved else {
+            this.save();
+            return;
+        };
+        match compare(this, &saved) {
+            Comparison::Same => {}
+            Comparison::Ahead => this.save(),
+            Comparison::Behind => autosave.import(saved),
+            Comparison::Diverged => self.conflict = Some(Conflict::new(this, saved)),
+        }
+    }
+}
+
+/// Listens for other tabs changing the autosave in the local storage.
+#[cfg(target_arch = "wasm32")]
+fn listen_for_saves(sync: Res<TabSync>) {
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::JsCast;
+
+    let Some(window) = web_sys::window() else {
+        return;
+    };
+    let changed = sync.changed.clone();
+    let listener =
+        Closure::<dyn FnMut(web_sys::StorageEvent)>::new(move |event: web_sys::StorageEvent| {
+            // A cleared storage has no key, and loses the autosave too
+            let key = event.key();
+            if key.is_none() || key.as_deref() == Some(crate::autosave::AUTOSAVE_PATH) {
+                changed.store(true, Ordering::Relaxed);
+            }
+        });
+    window
+        .add_event_listener_with_callback("storage", listener.as_ref().unchecked_ref())
+        .ok();
+    // The listener is kept for as long as the page is open
+    listener.forget();
+}
+
+/// The desktop app has no storage events, and reads the autosave instead.
+#[cfg(not(target_arch = "wasm32"))]
+fn listen_for_saves() {}
+
+fn sync_game(
+    mut sync: ResMut<TabSync>,
+    mut autosave: ResMut<Autosave>,
+    history: Res<PositionHistory>,
+    clock: Res<ChessClock>,
+    engine: Res<Engine>,
+    time: Res<Time>,
+) {
+    if !sync.should_check(&time) {
+        return;
+    }
+    // Until the player chooses whether to restore the game found on startup, this window has no
+    // game of its own to compare
+    if autosave.interrupted().is_some() {
+        return;
+    }
+    let Some(this) = SavedGame::new(&history, &clock, &engine) else {
+        return;
+    };
+    sync.sync(&this, SavedGame::load(), &mut autosave);
+}
+
+fn resolve_conflict(
+    mut events: EventReader<ResolveConflictEvent>,
+    mut sync: ResMut<TabSync>,
+    mut autosave: ResMut<Autosave>,
+    history: Res<PositionHistory>,
+    clock: Res<ChessClock>,
+    engine: Res<Engine>,
+) {
+    let Some(event) = events.iter().last() else {
+        return;
+    };
+    let Some(conflict) = sync.conflict.take() else {
+        return;
+    };
+    if !event.keep_this {
+        autosave.import(conflict.saved);
+    } else if let Some(this) = SavedGame::new(&history, &clock, &engine) {
+        this.save();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    fn game(moves: &[&str]) -> SavedGame {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut history = PositionHistory::default();
+        history.update(&board);
+        for text in moves {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            history.update(&board);
+        }
+        SavedGame::new(&history, &ChessClock::default(), &Engine::default()).unwrap()
+    }
+
+    #[test]
+    fn test_compare() {
+        let (start, e4, e4_e5, d4) = (game(&[]), game(&["e4"]), game(&["e4", "e5"]), game(&["d4"]));
+        assert_eq!(compare(&e4, &e4), Comparison::Same);
+        assert_eq!(compare(&e4_e5, &e4), Comparison::Ahead);
+        assert_eq!(compare(&start, &e4), Comparison::Behind);
+        assert_eq!(compare(&e4_e5, &d4), Comparison::Diverged);
+
+        let mut sync = TabSync::default();
+        sync.sync(&e4_e5, Some(d4), &mut Autosave::default());
+        let conflict = sync.conflict().as_ref().unwrap();
+        assert_eq!(conflict.saved_moves(), 1);
+        // The e-pawns left e2 and e7 for e4 and e5, and the d-pawn d2 for d4
+        assert_eq!(conflict.squares(), 6);
+    }
+}
@@ -0,0 +1,162 @@
+//! Move timing telemetry, which records how long each move of a game took and, optionally,
+//! whether analysis was shown while it was played, for organisers of friendly matches to check.
+//!
+//! The telemetry is recorded by default when the app hosts the game by serving its board with the
+//! `server` feature, and can be turned on for any game. It is attached to the game's PGN, each
+//! move's time as an `[%emt]` comment and the use of analysis as an `AnalysisUsed` tag. Taking back
+//! moves drops their times.
+
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::prelude::{EventReader, Res, ResMut, Resource, Update};
+use bevy::time::Time;
+
+use crate::analysis::Analysis;
+use crate::chess_board::{ChessBoard, ResetBoardEvent};
+use crate::engine::Engine;
+
+pub(super) struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameTelemetry>()
+            .add_systems(Update, record_telemetry);
+    }
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct GameTelemetry {
+    /// Whether the move times are recorded.
+    pub enabled: bool,
+    /// Whether the use of analysis is recorded with them.
+    pub record_assistance: bool,
+    /// The time each move of the game took, from the move before it.
+    move_times: Vec<Duration>,
+    /// The time since the last move, while the game is in progress.
+    since_move: Duration,
+    /// Whether analysis has been shown while the game was in progress.
+    analysed: bool,
+}
+
+impl Default for GameTelemetry {
+    fn default() -> Self {
+        GameTelemetry {
+            enabled: cfg!(feature = "server"),
+            record_assistance: false,
+            move_times: Vec::new(),
+            since_move: Duration::ZERO,
+            analysed: false,
+        }
+    }
+}
+
+impl GameTelemetry {
+    /// Returns the times of the moves recorded, or None if the telemetry is not recorded.
+    pub fn move_times(&self) -> Option<&[Duration]> {
+        self.enabled.then_some(self.move_times.as_slice())
+    }
+
+    /// Returns whether analysis was shown during the game, or None if it is not recorded.
+    pub fn analysed(&self) -> Option<bool> {
+        (self.enabled && self.record_assistance).then_some(self.analysed)
+    }
+
+    /// Starts recording again for a new game.
+    fn clear(&mut self) {
+        self.move_times.clear();
+        self.since_move = Duration::ZERO;
+        self.analysed = false;
+    }
+
+    /// Records the given time passing with the given number of moves made, while the game is in
+    /// progress and analysis is shown as given. Moves made in the same frame share its time.
+    fn record(&mut self, moves: usize, in_progress: bool, analysing: bool, delta: Duration) {
+        if in_progress {
+            self.since_move += delta;
+            self.analysed |= analysing;
+        }
+        if moves < self.move_times.len() {
+            self.move_times.truncate(moves);
+        }
+        while self.move_times.len() < moves {
+            self.move_times.push(self.since_move);
+            self.since_move = Duration::ZERO;
+        }
+    }
+}
+
+fn record_telemetry(
+    mut telemetry: ResMut<GameTelemetry>,
+    mut reset_events: EventReader<ResetBoardEvent>,
+    board: Res<ChessBoard>,
+    analysis: Res<Analysis>,
+    engine: Res<Engine>,
+    time: Res<Time>,
+) {
+    if !telemetry.enabled {
+        return;
+    }
+    // A take back resets the board to the moves before it, which keep their times
+    if let Some(reset_event) = reset_events.iter().last() {
+        if reset_event.past_moves().is_empty() {
+            telemetry.clear();
+        }
+        telemetry.since_move = Duration::ZERO;
+    }
+    telemetry.record(
+        board.past_moves().len(),
+        board.active_color().is_some(),
+        analysis.is_active(&engine),
+        time.delta(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+    use crate::pgn::write_pgn;
+
+    use super::*;
+
+    #[test]
+    fn test_telemetry() {
+        let mut telemetry = GameTelemetry {
+            enabled: true,
+            record_assistance: true,
+            ..GameTelemetry::default()
+        };
+        telemetry.record(0, true, false, Duration::from_secs(3));
+        telemetry.record(1, true, false, Duration::from_millis(200));
+        telemetry.record(1, true, true, Duration::from_secs(62));
+        telemetry.record(2, true, false, Duration::ZERO);
+        assert_eq!(
+            telemetry.move_times(),
+            Some(&[Duration::from_millis(3200), Duration::from_secs(62)][..])
+        );
+        assert_eq!(telemetry.analysed(), Some(true));
+
+        let mut board = ChessBoard::with_position(&Fen::default());
+        for text in ["e4", "e5"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let pgn = write_pgn(
+            &Fen::default(),
+            board.past_moves(),
+            &board,
+            ["?", "?"],
+            &None,
+            Some(&telemetry),
+        );
+        assert!(pgn.contains("[AnalysisUsed \"Yes\"]"));
+        assert!(pgn.contains("1. e4 {[%emt 0:00:03]} e5 {[%emt 0:01:02]} *"));
+
+        // A take back drops the times of the moves taken back
+        telemetry.record(1, true, false, Duration::ZERO);
+        assert_eq!(telemetry.move_times().map(<[Duration]>::len), Some(1));
+        telemetry.record_assistance = false;
+        assert_eq!(telemetry.analysed(), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Small static pictures of positions, drawn by a software rasteriser so they need neither the
+//! piece sprites nor a render pass, for lists such as the [database browser](crate::database) to
+//! show many of at once.
+//!
+//! Each square is [SQUARE_PIXELS] wide, and each piece a [GLYPH_PIXELS] glyph of its kind in its
+//! color, outlined in the other so it shows on either square. The desktop app caches the
+//! thumbnails in [THUMBNAILS_DIRECTORY] as binary PPM images, named by the key they were stored
+//! with, so the games they show need not be replayed again; the web build draws them afresh.
+
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType};
+
+/// The directory the thumbnails are cached in.
+pub const THUMBNAILS_DIRECTORY: &str = "thumbnails";
+
+/// The width and height of a square, in pixels.
+pub const SQUARE_PIXELS: usize = 12;
+
+/// The width and height of a piece's glyph, in pixels.
+const GLYPH_PIXELS: usize = 8;
+
+const LIGHT_SQUARE: [u8; 3] = [240, 217, 181];
+const DARK_SQUARE: [u8; 3] = [181, 136, 99];
+const WHITE_PIECE: [u8; 3] = [255, 255, 255];
+const BLACK_PIECE: [u8; 3] = [20, 20, 20];
+const DUCK: [u8; 3] = [250, 200, 20];
+
+/// Returns the glyph of the given kind of piece, a row of pixels to a byte from the top with the
+/// leftmost pixel in the highest bit. Fairy pieces are drawn as discs.
+fn glyph(piece_type: PieceType) -> [u8; GLYPH_PIXELS] {
+    match piece_type {
+        PieceType::King => [0x18, 0x3C, 0x18, 0x7E, 0x7E, 0x3C, 0x7E, 0x00],
+        PieceType::Queen => [0x00, 0xA5, 0xA5, 0x7E, 0x7E, 0x3C, 0x7E, 0x00],
+        PieceType::Bishop => [0x18, 0x3C, 0x34, 0x3C, 0x18, 0x3C, 0x7E, 0x00],
+        PieceType::Knight => [0x00, 0x18, 0x3C, 0x6C, 0x1C, 0x3C, 0x7E, 0x00],
+        PieceType::Rook => [0x00, 0x5A, 0x7E, 0x3C, 0x3C, 0x3C, 0x7E, 0x00],
+        PieceType::Pawn => [0x00, 0x18, 0x3C, 0x18, 0x18, 0x3C, 0x7E, 0x00],
+        PieceType::Fairy(_) => [0x00, 0x3C, 0x7E, 0x7E, 0x7E, 0x7E, 0x3C, 0x00],
+    }
+}
+
+/// Returns whether the given pixel of the glyph is set, where pixels off the glyph are not.
+fn is_set(glyph: &[u8; GLYPH_PIXELS], x: isize, y: isize) -> bool {
+    (0..GLYPH_PIXELS as isize).contains(&x)
+        && (0..GLYPH_PIXELS as isize).contains(&y)
+        && glyph[y as usize] & (0x80 >> x) != 0
+}
+
+/// A picture of a position, in rows of RGB pixels from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Thumbnail {
+    /// Draws the position on the given board from white's side.
+    pub fn render(board: &ChessBoard) -> Self {
+        let size = board.size();
+        let (width, height) = (size.files() * SQUARE_PIXELS, size.ranks() * SQUARE_PIXELS);
+        let mut thumbnail = Thumbnail {
+            width,
+            height,
+            pixels: vec![0; width * height * 3],
+        };
+        for position in size.positions() {
+            let (rank, file) = (position.rank() - size.top_rank(), *position.file());
+            let square = match (rank + file) % 2 {
+                0 => LIGHT_SQUARE,
+                _ => DARK_SQUARE,
+            };
+            thumbnail.fill(
+                file * SQUARE_PIXELS,
+                rank * SQUARE_PIXELS,
+                SQUARE_PIXELS,
+                square,
+            );
+            if board.duck() == &Some(position) {
+                let margin = SQUARE_PIXELS / 4;
+                let (x, y) = (file * SQUARE_PIXELS + margin, rank * SQUARE_PIXELS + margin);
+                thumbnail.fill(x, y, SQUARE_PIXELS - margin * 2, DUCK);
+            }
+            thumbnail.draw_piece(board, &position, file, rank);
+        }
+        thumbnail
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the RGB pixels, in rows from the top left.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        let index = (y * self.width + x) * 3;
+        self.pixels[index..index + 3].copy_from_slice(&color);
+    }
+
+    /// Fills the square of the given size with its top left corner at the given pixel.
+    fn fill(&mut self, x: usize, y: usize, size: usize, color: [u8; 3]) {
+        for row in y..y + size {
+            for column in x..x + size {
+                self.set_pixel(column, row, color);
+            }
+        }
+    }
+
+    /// Draws the piece on the given square, if there is one, with the square at the given file and
+    /// rank from the top left.
+    fn draw_piece(
+        &mut self,
+        board: &ChessBoard,
+        position: &BoardPosition,
+        file: usize,
+        rank: usize,
+    ) {
+        let (Some(piece_type), Some(color)) = (
+            board.get_piece_type(position),
+            board.get_piece_color(position),
+        ) else {
+            return;
+        };
+        let (fill, outline) = match color {
+            PieceColor::White => (WHITE_PIECE, BLACK_PIECE),
+            PieceColor::Black => (BLACK_PIECE, WHITE_PIECE),
+        };
+        let glyph = glyph(piece_type);
+        let margin = (SQUARE_PIXELS - GLYPH_PIXELS) / 2;
+        // The outline is a pixel wide around the glyph, in the margin of the square
+        for y in -1..=GLYPH_PIXELS as isize {
+            for x in -1..=GLYPH_PIXELS as isize {
+                let outlined = [(-1, 0), (1, 0), (0, -1), (0, 1)]
+                    .iter()
+                    .any(|(dx, dy)| is_set(&glyph, x + dx, y + dy));
+                let color = match is_set(&glyph, x, y) {
+                    true => fill,
+                    false if outlined => outline,
+                    false => continue,
+                };
+                let column = (file * SQUARE_PIXELS + margin) as isize + x;
+                let row = (rank * SQUARE_PIXELS + margin) as isize + y;
+                self.set_pixel(column as usize, row as usize, color);
+            }
+        }
+    }
+
+    /// Returns the thumbnail as a binary PPM image.
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend(&self.pixels);
+        ppm
+    }
+
+    /// Reads a binary PPM image written by [Thumbnail::to_ppm], or returns None if it is not one.
+    pub fn from_ppm(ppm: &[u8]) -> Option<Self> {
+        let mut fields = ppm.splitn(5, |byte| byte.is_ascii_whitespace());
+        let mut field = || std::str::from_utf8(fields.next()?).ok().map(str::to_string);
+        if field()? != "P6" {
+            return None;
+        }
+        let width: usize = field()?.parse().ok()?;
+        let height: usize = field()?.parse().ok()?;
+        if field()? != "255" {
+            return None;
+        }
+        let pixels = fields.next()?.to_vec();
+        (pixels.len() == width * height * 3).then_some(Thumbnail {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Returns the path of the cached thumbnail of the given key.
+fn cache_path(key: u64) -> std::path::PathBuf {
+    std::path::Path::new(THUMBNAILS_DIRECTORY).join(format!("{:016x}.ppm", key))
+}
+
+/// Returns the cached thumbnail of the given key, if there is one.
+pub fn cached(key: u64) -> Option<Thumbnail> {
+    if cfg!(target_arch = "wasm32") {
+        return None;
+    }
+    Thumbnail::from_ppm(&std::fs::read(cache_path(key)).ok()?)
+}
+
+/// Caches the given thumbnail with the given key.
+pub fn cache(key: u64, thumbnail: &Thumbnail) -> Result<(), String> {
+    if cfg!(target_arch = "wasm32") {
+        return Ok(());
+    }
+    std::fs::create_dir_all(THUMBNAILS_DIRECTORY)
+        .and_then(|_| std::fs::write(cache_path(key), thumbnail.to_ppm()))
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let board = ChessBoard::with_position(&Fen::default());
+        let thumbnail = Thumbnail::render(&board);
+        assert_eq!(thumbnail.width(), 8 * SQUARE_PIXELS);
+        assert_eq!(thumbnail.height(), 8 * SQUARE_PIXELS);
+        let pixel = |x: usize, y: usize| {
+            let index = (y * thumbnail.width() + x) * 3;
+            [
+                thumbnail.pixels()[index],
+                thumbnail.pixels()[index + 1],
+                thumbnail.pixels()[index + 2],
+            ]
+        };
+        // a8 is a light square with a black rook, outlined in white above the top of its glyph
+        assert_eq!(pixel(0, 0), LIGHT_SQUARE);
+        assert_eq!(pixel(SQUARE_PIXELS, 0), DARK_SQUARE);
+        assert_eq!(pixel(5, 3), BLACK_PIECE);
+        assert_eq!(pixel(5, 2), WHITE_PIECE);
+        // e4 is empty, and the pawn on e2 is white
+        assert_eq!(
+            pixel(4 * SQUARE_PIXELS + 6, 4 * SQUARE_PIXELS + 6),
+            LIGHT_SQUARE
+        );
+        assert_eq!(
+            pixel(4 * SQUARE_PIXELS + 5, 6 * SQUARE_PIXELS + 4),
+            WHITE_PIECE
+        );
+
+        assert_eq!(Thumbnail::from_ppm(&thumbnail.to_ppm()), Some(thumbnail));
+        assert_eq!(Thumbnail::from_ppm(b"P6\n2 2\n255\n\x00"), None);
+    }
+}
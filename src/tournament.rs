@@ -0,0 +1,578 @@
+//! A headless tournament between several engine configurations, for ranking more of them than a
+//! [match](crate::engine_match) compares at once.
+//!
+//! The tournament is run with `chess_computer tournament [options]` and plays its rounds one after
+//! another, each game from an opening of [OPENINGS] and adjudicated as the games of a match are.
+//! The standings, with their tie-breaks, are written to `standings.csv` in the output directory
+//! and the games to `games.pgn`. The options are:
+//!
+//! - `--player <plies>` adds a player searching to the given depth, and can be given any number
+//!   of times. Without it, players of depths 1 to 4 play.
+//! - `--disable <heuristics>` and `--weights <file>` turn off search heuristics and load
+//!   evaluation weights for the player added last, as `--disable-a` and `--weights-a` do for a
+//!   match.
+//! - `--pairing <round-robin|swiss>` sets how the players are paired. In a round robin everyone
+//!   plays everyone once, by the circle method. In a Swiss tournament each round pairs the players
+//!   in order of their standings with the next player they have not met, and whoever has played
+//!   fewer games as white gets white.
+//! - `--rounds <number>` sets the number of rounds of a Swiss tournament, by default enough for
+//!   one player to be able to win every game and finish alone on top.
+//! - `--bye <points>` sets the points for a bye, `1`, `0.5` or `0`, which is half a point by
+//!   default. With an odd number of players one sits out each round: in a round robin the player
+//!   paired with nobody, and in a Swiss tournament the lowest player in the standings who has not
+//!   had a bye.
+//! - `--output <directory>` sets the directory the results are written to.
+//!
+//! Players level on points are ranked by their Buchholz score, the sum of their opponents'
+//! points, and then by their Sonneborn-Berger score, the sum of the points of the opponents they
+//! beat and half of those they drew with. A bye counts towards neither.
+
+use std::cmp::Ordering;
+use std::path::PathBuf;
+
+use crate::chess_board::PieceColor;
+use crate::engine_match::{opening_board, play_game, Adjudication, EngineSettings, OPENINGS};
+use crate::fen::Fen;
+use crate::pgn::{write_pgn, GameResult};
+
+/// How the players of each round are paired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pairing {
+    RoundRobin,
+    Swiss,
+}
+
+/// A game of a round between the players of the given indices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Game {
+    white: usize,
+    black: usize,
+    result: GameResult,
+}
+
+impl Game {
+    fn new(white: usize, black: usize) -> Self {
+        Game {
+            white,
+            black,
+            result: GameResult::Unfinished,
+        }
+    }
+
+    /// Returns the opponent of the given player and the points the player scored, if they played
+    /// in this game.
+    fn scored(&self, player: usize) -> Option<(usize, f64)> {
+        let (color, opponent) = match player {
+            _ if player == self.white => (PieceColor::White, self.black),
+            _ if player == self.black => (PieceColor::Black, self.white),
+            _ => return None,
+        };
+        let points = match self.result {
+            GameResult::Win(winner) if winner == color => 1.,
+            GameResult::Draw => 0.5,
+            _ => 0.,
+        };
+        Some((opponent, points))
+    }
+}
+
+/// The games of a round, and the player who sat it out if there were an odd number of players.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Round {
+    games: Vec<Game>,
+    bye: Option<usize>,
+}
+
+/// A player's place in the standings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Standing {
+    player: usize,
+    points: f64,
+    buchholz: f64,
+    sonneborn_berger: f64,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+    byes: u32,
+}
+
+impl Standing {
+    /// Orders standings from first to last: by points, then by the tie-breaks, then by the order
+    /// the players were added in.
+    fn rank(&self, other: &Standing) -> Ordering {
+        other
+            .points
+            .total_cmp(&self.points)
+            .then(other.buchholz.total_cmp(&self.buchholz))
+            .then(other.sonneborn_berger.total_cmp(&self.sonneborn_berger))
+            .then(self.player.cmp(&other.player))
+    }
+}
+
+/// The rounds of a tournament played so far.
+#[derive(Debug, Clone, PartialEq)]
+struct Crosstable {
+    players: usize,
+    /// The points for a bye.
+    bye_points: f64,
+    rounds: Vec<Round>,
+}
+
+impl Crosstable {
+    fn new(players: usize, bye_points: f64) -> Self {
+        Crosstable {
+            players,
+            bye_points,
+            rounds: Vec::new(),
+        }
+    }
+
+    /// Returns the opponents of the given player in each game they played, with the points they
+    /// scored against them.
+    fn results(&self, player: usize) -> impl Iterator<Item = (usize, f64)> + '_ {
+        self.rounds
+            .iter()
+            .flat_map(|round| &round.games)
+            .filter_map(move |game| game.scored(player))
+    }
+
+    /// Returns the number of byes the given player has had.
+    fn byes(&self, player: usize) -> u32 {
+        self.rounds
+            .iter()
+            .filter(|round| round.bye == Some(player))
+            .count() as u32
+    }
+
+    fn points(&self, player: usize) -> f64 {
+        let scored: f64 = self.results(player).map(|(_, points)| points).sum();
+        scored + self.byes(player) as f64 * self.bye_points
+    }
+
+    /// Returns how many more games the given player has played as white than as black.
+    fn white_balance(&self, player: usize) -> i32 {
+        self.rounds
+            .iter()
+            .flat_map(|round| &round.games)
+            .map(|game| match player {
+                _ if player == game.white => 1,
+                _ if player == game.black => -1,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    /// Returns the standings, from first to last.
+    fn standings(&self) -> Vec<Standing> {
+        let points: Vec<f64> = (0..self.players)
+            .map(|player| self.points(player))
+            .collect();
+        let mut standings: Vec<Standing> = (0..self.players)
+            .map(|player| {
+                let mut standing = Standing {
+                    player,
+                    points: points[player],
+                    buchholz: 0.,
+                    sonneborn_berger: 0.,
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                    byes: self.byes(player),
+                };
+                for (opponent, scored) in self.results(player) {
+                    standing.buchholz += points[opponent];
+                    standing.sonneborn_berger += scored * points[opponent];
+                    match scored {
+                        _ if scored == 1. => standing.wins += 1,
+                        _ if scored == 0.5 => standing.draws += 1,
+                        _ => standing.losses += 1,
+                    }
+                }
+                standing
+            })
+            .collect();
+        standings.sort_by(Standing::rank);
+        standings
+    }
+
+    /// Returns the number of rounds of a round robin.
+    fn round_robin_rounds(&self) -> usize {
+        (self.players + self.players % 2).saturating_sub(1)
+    }
+
+    /// Pairs the given round of a round robin by the circle method: the first player stays put
+    /// while the others rotate around them, and with an odd number of players the one facing the
+    /// empty seat has the bye.
+    fn pair_round_robin(&self, round: usize) -> Round {
+        let seats = self.players + self.players % 2;
+        let rotating = seats - 1;
+        let seat = |index: usize| match index {
+            0 => 0,
+            _ => 1 + (index - 1 + round) % rotating,
+        };
+        let mut paired = Round::default();
+        for index in 0..seats / 2 {
+            let (first, second) = (seat(index), seat(seats - 1 - index));
+            // The empty seat is the last, so the other player has the bye
+            if second == self.players {
+                paired.bye = Some(first);
+                continue;
+            }
+            if first == self.players {
+                paired.bye = Some(second);
+                continue;
+            }
+            // Colours alternate round by round for the player who stays put, and from board to
+            // board for the rest
+            let first_white = match index {
+                0 => round.is_multiple_of(2),
+                _ => index.is_multiple_of(2),
+            };
+            paired.games.push(match first_white {
+                true => Game::new(first, second),
+                false => Game::new(second, first),
+            });
+        }
+        paired
+    }
+
+    /// Pairs the next round of a Swiss tournament from the standings: each player from the top
+    /// meets the next player below them they have not met, or the next player if they have met
+    /// them all.
+    fn pair_swiss(&self) -> Round {
+        let mut unpaired: Vec<usize> = self
+            .standings()
+            .iter()
+            .map(|standing| standing.player)
+            .collect();
+        let mut paired = Round::default();
+        if !unpaired.len().is_multiple_of(2) {
+            let fewest = unpaired.iter().map(|player| self.byes(*player)).min();
+            let bye = unpaired
+                .iter()
+                .rposition(|player| Some(self.byes(*player)) == fewest)
+                .map(|index| unpaired.remove(index));
+            paired.bye = bye;
+        }
+        let met = |first: usize, second: usize| {
+            self.results(first).any(|(opponent, _)| opponent == second)
+        };
+        while !unpaired.is_empty() {
+            let first = unpaired.remove(0);
+            let index = unpaired
+                .iter()
+                .position(|second| !met(first, *second))
+                .unwrap_or(0);
+            let second = unpaired.remove(index);
+            // The higher player gets white unless they have had it more often
+            paired.games.push(
+                match self.white_balance(first) <= self.white_balance(second) {
+                    true => Game::new(first, second),
+                    false => Game::new(second, first),
+                },
+            );
+        }
+        paired
+    }
+}
+
+/// Returns the given points as text, e.g. "2.5".
+fn points_text(points: f64) -> String {
+    format!("{:.1}", points)
+}
+
+/// Returns the given text as a CSV field, quoted if it holds a comma or a quote.
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+/// Returns the standings as CSV, with the given names of the players.
+fn standings_csv(crosstable: &Crosstable, names: &[String]) -> String {
+    let mut csv =
+        String::from("rank,player,points,buchholz,sonneborn_berger,wins,draws,losses,byes\n");
+    for (rank, standing) in crosstable.standings().iter().enumerate() {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{}\n",
+            rank + 1,
+            csv_field(&names[standing.player]),
+            points_text(standing.points),
+            points_text(standing.buchholz),
+            points_text(standing.sonneborn_berger),
+            standing.wins,
+            standing.draws,
+            standing.losses,
+            standing.byes
+        ));
+    }
+    csv
+}
+
+/// The settings of a tournament.
+#[derive(Debug, Clone, PartialEq)]
+struct TournamentOptions {
+    players: Vec<EngineSettings>,
+    pairing: Pairing,
+    /// The number of rounds of a Swiss tournament, or None for the default.
+    rounds: Option<usize>,
+    bye_points: f64,
+    /// The directory the standings and games are written to.
+    output: PathBuf,
+}
+
+impl TournamentOptions {
+    /// Reads the options from the given command line arguments.
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut options = TournamentOptions {
+            players: Vec::new(),
+            pairing: Pairing::RoundRobin,
+            rounds: None,
+            bye_points: 0.5,
+            output: PathBuf::from("tournament"),
+        };
+        let mut args = args.iter();
+        while let Some(flag) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| format!("Missing value for {}.", flag))?;
+            let invalid = || format!("Invalid value for {}: {}.", flag, value);
+            let unplaced = || format!("{} must follow a --player.", flag);
+            match flag.as_str() {
+                "--player" => options
+                    .players
+                    .push(EngineSettings::new(value.parse().map_err(|_| invalid())?)),
+                "--disable" => options
+                    .players
+                    .last_mut()
+                    .ok_or_else(unplaced)?
+                    .disable(value)
+                    .ok_or_else(invalid)?,
+                "--weights" => options
+                    .players
+                    .last_mut()
+                    .ok_or_else(unplaced)?
+                    .load_weights(value)
+                    .ok_or_else(invalid)?,
+                "--pairing" => {
+                    options.pairing = match value.as_str() {
+                        "round-robin" => Pairing::RoundRobin,
+                        "swiss" => Pairing::Swiss,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--rounds" => {
+                    options.rounds = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|rounds| *rounds > 0)
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                "--bye" => {
+                    options.bye_points = match value.as_str() {
+                        "1" => 1.,
+                        "0.5" => 0.5,
+                        "0" => 0.,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "--output" => options.output = PathBuf::from(value),
+                _ => return Err(format!("Unknown option: {}.", flag)),
+            }
+        }
+        if options.players.is_empty() {
+            options.players = (1..=4).map(EngineSettings::new).collect();
+        }
+        if options.players.len() < 2 {
+            return Err("A tournament needs at least two players.".to_string());
+        }
+        Ok(options)
+    }
+
+    /// Returns the number of rounds to play.
+    fn rounds(&self, crosstable: &Crosstable) -> usize {
+        match self.pairing {
+            Pairing::RoundRobin => crosstable.round_robin_rounds(),
+            Pairing::Swiss => self.rounds.unwrap_or_else(|| {
+                self.players.len().next_power_of_two().trailing_zeros() as usize
+            }),
+        }
+    }
+}
+
+/// Runs the tournament with the given command line arguments, writing the standings and games to
+/// the output directory.
+#[cfg(not(tarpaulin_include))]
+pub fn run(args: &[String]) -> Result<(), String> {
+    let options = TournamentOptions::parse(args)?;
+    let names: Vec<String> = options
+        .players
+        .iter()
+        .enumerate()
+        .map(|(index, player)| player.name(&(index + 1).to_string()))
+        .collect();
+    let mut crosstable = Crosstable::new(options.players.len(), options.bye_points);
+    let adjudication = Adjudication::default();
+    let mut pgn = String::new();
+    for round in 0..options.rounds(&crosstable) {
+        let mut paired = match options.pairing {
+            Pairing::RoundRobin => crosstable.pair_round_robin(round),
+            Pairing::Swiss => crosstable.pair_swiss(),
+        };
+        println!("Round {}", round + 1);
+        for (board_number, game) in paired.games.iter_mut().enumerate() {
+            let opening = OPENINGS[(round + board_number) % OPENINGS.len()];
+            let engines = [&options.players[game.white], &options.players[game.black]];
+            let (board, adjudicated) = play_game(opening_board(opening), engines, &adjudication);
+            game.result = GameResult::of(&board);
+            let players = [names[game.white].as_str(), names[game.black].as_str()];
+            pgn.push_str(&write_pgn(
+                &Fen::default(),
+                board.past_moves(),
+                &board,
+                players,
+                &None,
+                None,
+            ));
+            pgn.push('\n');
+            println!(
+                "  {} {} {}{}",
+                players[0],
+                game.result.as_token(),
+                players[1],
+                adjudicated.map_or(String::new(), |adjudicated| format!(
+                    " (adjudicated, {})",
+                    adjudicated.description()
+                ))
+            );
+        }
+        if let Some(bye) = paired.bye {
+            println!("  {} has a bye", names[bye]);
+        }
+        crosstable.rounds.push(paired);
+    }
+
+    let standings = standings_csv(&crosstable, &names);
+    print!("{}", standings);
+    let write_error = |error: std::io::Error| format!("Unable to write results: {}.", error);
+    std::fs::create_dir_all(&options.output).map_err(write_error)?;
+    std::fs::write(options.output.join("standings.csv"), standings).map_err(write_error)?;
+    std::fs::write(options.output.join("games.pgn"), pgn).map_err(write_error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Plays every game of the given round with white winning on the given boards and drawing on
+    /// the rest.
+    fn play(crosstable: &mut Crosstable, mut round: Round, white_wins: &[usize]) {
+        for (board_number, game) in round.games.iter_mut().enumerate() {
+            game.result = match white_wins.contains(&board_number) {
+                true => GameResult::Win(PieceColor::White),
+                false => GameResult::Draw,
+            };
+        }
+        crosstable.rounds.push(round);
+    }
+
+    #[test]
+    fn test_round_robin() {
+        let mut crosstable = Crosstable::new(5, 0.5);
+        assert_eq!(crosstable.round_robin_rounds(), 5);
+        for round in 0..5 {
+            let paired = crosstable.pair_round_robin(round);
+            assert_eq!(paired.games.len(), 2);
+            play(&mut crosstable, paired, &[]);
+        }
+        // Everyone has met everyone once and sat out once
+        for player in 0..5 {
+            let mut opponents: Vec<usize> = crosstable
+                .results(player)
+                .map(|(opponent, _)| opponent)
+                .collect();
+            opponents.sort();
+            let others: Vec<usize> = (0..5).filter(|other| *other != player).collect();
+            assert_eq!(opponents, others);
+            assert_eq!(crosstable.byes(player), 1);
+            assert_eq!(crosstable.points(player), 2.5);
+            assert!(crosstable.white_balance(player).abs() <= 2);
+        }
+    }
+
+    #[test]
+    fn test_swiss_and_tie_breaks() {
+        let mut crosstable = Crosstable::new(5, 0.5);
+        let first = crosstable.pair_swiss();
+        // The lowest seed sits out, and the rest meet the next player down
+        assert_eq!(first.bye, Some(4));
+        assert_eq!(first.games, [Game::new(0, 1), Game::new(2, 3)]);
+        play(&mut crosstable, first, &[0, 1]);
+
+        // The winners meet, and the player who had black gets white on the next board
+        let second = crosstable.pair_swiss();
+        assert_eq!(second.games, [Game::new(0, 2), Game::new(1, 4)]);
+        assert_eq!(second.bye, Some(3));
+        play(&mut crosstable, second, &[]);
+        // Nobody has a second bye while others have had none
+        assert_eq!(crosstable.pair_swiss().bye, Some(1));
+
+        let standings = crosstable.standings();
+        let points: Vec<f64> = standings.iter().map(|standing| standing.points).collect();
+        assert_eq!(points, [1.5, 1.5, 1., 0.5, 0.5]);
+        let top = &standings[0];
+        assert_eq!((top.wins, top.draws, top.losses), (1, 1, 0));
+        assert_eq!((top.buchholz, top.sonneborn_berger), (2., 1.25));
+        // Player 1 met stronger opponents than player 3, and the bye counts for neither
+        assert_eq!((standings[3].player, standings[3].buchholz), (1, 2.5));
+        assert_eq!((standings[4].player, standings[4].buchholz), (3, 1.5));
+
+        let names: Vec<String> = (1..=5)
+            .map(|player| format!("Engine {}, tuned", player))
+            .collect();
+        let csv = standings_csv(&crosstable, &names);
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("rank,player,points,buchholz,sonneborn_berger,wins,draws,losses,byes")
+        );
+        assert!(lines.next().unwrap().starts_with("1,\"Engine "));
+        assert_eq!(csv.lines().count(), 6);
+    }
+
+    #[test]
+    fn test_tournament_options() {
+        let args = [
+            "--player",
+            "2",
+            "--disable",
+            "lmr",
+            "--player",
+            "3",
+            "--pairing",
+            "swiss",
+            "--bye",
+            "1",
+        ]
+        .map(String::from);
+        let options = TournamentOptions::parse(&args).unwrap();
+        assert_eq!(options.players.len(), 2);
+        assert_eq!(options.players[0].name("1"), "Engine 1 (depth 2, no lmr)");
+        assert_eq!(options.pairing, Pairing::Swiss);
+        assert_eq!(options.bye_points, 1.);
+        assert_eq!(options.rounds(&Crosstable::new(2, 1.)), 1);
+
+        let options = TournamentOptions::parse(&[]).unwrap();
+        assert_eq!(options.players.len(), 4);
+        assert_eq!(options.rounds(&Crosstable::new(4, 0.5)), 3);
+
+        assert!(TournamentOptions::parse(&["--disable", "lmr"].map(String::from)).is_err());
+        assert!(TournamentOptions::parse(&["--player", "2"].map(String::from)).is_err());
+        assert!(TournamentOptions::parse(&["--bye", "2"].map(String::from)).is_err());
+        assert!(TournamentOptions::parse(&["--rounds", "0"].map(String::from)).is_err());
+    }
+}
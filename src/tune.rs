@@ -0,0 +1,286 @@
+//! Tunes the weights of the engine's evaluation with
+//! [Texel's tuning method](https://www.chessprogramming.org/Texel%27s_Tuning_Method).
+//!
+//! The weights are tuned with `chess_computer tune <positions> [options]`, where each line of the
+//! positions file holds a FEN followed by the result of the game the position was taken from,
+//! e.g. `4k3/8/8/8/8/8/3QP3/4K3 w - - 0 1 1-0`. EPD lines with the result in a `c9` opcode, as in
+//! `4k3/8/8/8/8/8/3QP3/4K3 w - - c9 "1-0";`, are read too. Starting from the default weights,
+//! the evaluation is first scaled to predict the results as well as it can, and the weights are
+//! then fitted by logistic regression to predict them better. The options are:
+//!
+//! - `--output <file>` sets the file the weights are written to, [WEIGHTS_PATH] by default.
+//! - `--iterations <number>` sets the number of optimisation steps.
+
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, BOARD_SIZE};
+use crate::engine::{EvalWeights, WEIGHTS_PATH};
+use crate::fen::Fen;
+
+/// The number of optimisation steps taken by default.
+const DEFAULT_ITERATIONS: usize = 1000;
+
+/// The size of each optimisation step, in centipawns.
+const LEARNING_RATE: f64 = 1.;
+
+/// The number of weights: a value for each piece type and a bonus for each piece on each square.
+const WEIGHT_COUNT: usize = 6 + 6 * BOARD_SIZE * BOARD_SIZE;
+
+/// A position to tune with.
+#[derive(Debug, Clone, PartialEq)]
+struct TuningPosition {
+    /// The indices of the weights which make up the evaluation from white's point of view, with
+    /// the number of times each is added.
+    features: Vec<(usize, f64)>,
+    /// The result of the game from white's point of view: 1 for a win, 0.5 for a draw and 0 for
+    /// a loss.
+    result: f64,
+}
+
+/// Returns the index of the bonus for the piece type with the given index on the given square.
+fn square_index(piece_index: usize, rank: usize, file: usize) -> usize {
+    6 + (piece_index * BOARD_SIZE + rank) * BOARD_SIZE + file
+}
+
+fn to_vector(weights: &EvalWeights) -> Vec<f64> {
+    let mut vector = vec![0.; WEIGHT_COUNT];
+    for piece_index in 0..6 {
+        vector[piece_index] = weights.piece_values[piece_index] as f64;
+        for rank in 0..BOARD_SIZE {
+            for file in 0..BOARD_SIZE {
+                vector[square_index(piece_index, rank, file)] =
+                    weights.squares[piece_index][rank][file] as f64;
+            }
+        }
+    }
+    vector
+}
+
+fn from_vector(vector: &[f64]) -> EvalWeights {
+    let mut weights = EvalWeights::default();
+    for piece_index in 0..6 {
+        weights.piece_values[piece_index] = vector[piece_index].round() as i32;
+        for rank in 0..BOARD_SIZE {
+            for file in 0..BOARD_SIZE {
+                weights.squares[piece_index][rank][file] =
+                    vector[square_index(piece_index, rank, file)].round() as i32;
+            }
+        }
+    }
+    weights
+}
+
+/// Reads a position and its result from a line of the positions file.
+/// Returns None if the line is not a position or if neither side can checkmate, as such
+/// positions are always evaluated as level.
+fn parse_position(line: &str) -> Option<TuningPosition> {
+    let line = line.trim().trim_end_matches(';');
+    let (position, result) = line.rsplit_once(' ')?;
+    let result = match result.trim_matches('"') {
+        "1-0" => 1.,
+        "0-1" => 0.,
+        "1/2-1/2" => 0.5,
+        _ => return None,
+    };
+    let position = position.trim_end().trim_end_matches("c9").trim_end();
+    // EPD leaves out the move counters
+    let fen = if position.split_whitespace().count() == 4 {
+        Fen::parse(&format!("{} 0 1", position))?
+    } else {
+        Fen::parse(position)?
+    };
+    // The weights are for the standard board
+    if !fen.size().is_standard() {
+        return None;
+    }
+    let board = ChessBoard::with_position(&fen);
+    if !board.has_mating_material(&PieceColor::White)
+        && !board.has_mating_material(&PieceColor::Black)
+    {
+        return None;
+    }
+
+    let mut features = Vec::new();
+    for rank in 0..BOARD_SIZE {
+        for file in 0..BOARD_SIZE {
+            let position = BoardPosition::new(rank, file);
+            if let (Some(piece_type), Some(color)) = (
+                board.get_piece_type(&position),
+                board.get_piece_color(&position),
+            ) {
+                // Fairy pieces have no weights to tune
+                let index = piece_type.index()?;
+                let sign = match color {
+                    PieceColor::White => 1.,
+                    PieceColor::Black => -1.,
+                };
+                let relative_rank = EvalWeights::relative_rank(&position, &color);
+                features.push((index, sign));
+                features.push((square_index(index, relative_rank, file), sign));
+            }
+        }
+    }
+    Some(TuningPosition { features, result })
+}
+
+/// Returns the evaluation of the position with the given weights from white's point of view.
+fn evaluation(weights: &[f64], position: &TuningPosition) -> f64 {
+    position
+        .features
+        .iter()
+        .map(|(index, count)| weights[*index] * count)
+        .sum()
+}
+
+/// Returns the expected score for white of a position with the given evaluation, where the
+/// scale sets how confident the prediction is.
+fn expected_score(evaluation: f64, scale: f64) -> f64 {
+    1. / (1. + 10f64.powf(-scale * evaluation / 400.))
+}
+
+/// Returns the mean squared error of the expected scores of the positions.
+fn error(weights: &[f64], positions: &[TuningPosition], scale: f64) -> f64 {
+    positions
+        .iter()
+        .map(|position| {
+            (position.result - expected_score(evaluation(weights, position), scale)).powi(2)
+        })
+        .sum::<f64>()
+        / positions.len() as f64
+}
+
+/// Returns the scale, between 0.01 and 3, which gives the smallest error with the given weights.
+fn fit_scale(weights: &[f64], positions: &[TuningPosition]) -> f64 {
+    (1..=300)
+        .map(|step| step as f64 / 100.)
+        .min_by(|a, b| error(weights, positions, *a).total_cmp(&error(weights, positions, *b)))
+        .unwrap()
+}
+
+/// Tunes the weights to predict the results of the positions, taking the given number of steps
+/// of the Adam optimiser.
+fn tune(weights: &EvalWeights, positions: &[TuningPosition], iterations: usize) -> EvalWeights {
+    const BETA1: f64 = 0.9;
+    const BETA2: f64 = 0.999;
+    const EPSILON: f64 = 1e-8;
+
+    let mut vector = to_vector(weights);
+    let scale = fit_scale(&vector, positions);
+    let mut momentum = vec![0.; WEIGHT_COUNT];
+    let mut velocity = vec![0.; WEIGHT_COUNT];
+    for step in 1..=iterations {
+        let mut gradient = vec![0.; WEIGHT_COUNT];
+        for position in positions {
+            let expected = expected_score(evaluation(&vector, position), scale);
+            let slope =
+                2. * (expected - position.result) * expected * (1. - expected) * 10f64.ln() * scale
+                    / 400.
+                    / positions.len() as f64;
+            for (index, count) in &position.features {
+                gradient[*index] += slope * count;
+            }
+        }
+        for index in 0..WEIGHT_COUNT {
+            momentum[index] = BETA1 * momentum[index] + (1. - BETA1) * gradient[index];
+            velocity[index] = BETA2 * velocity[index] + (1. - BETA2) * gradient[index].powi(2);
+            let momentum = momentum[index] / (1. - BETA1.powi(step as i32));
+            let velocity = velocity[index] / (1. - BETA2.powi(step as i32));
+            vector[index] -= LEARNING_RATE * momentum / (velocity.sqrt() + EPSILON);
+        }
+    }
+    from_vector(&vector)
+}
+
+/// Tunes the evaluation weights with the given command line arguments.
+#[cfg(not(tarpaulin_include))]
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut args = args.iter();
+    let path = args.next().ok_or("Missing the positions file.")?;
+    let mut output = WEIGHTS_PATH.to_string();
+    let mut iterations = DEFAULT_ITERATIONS;
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for {}.", flag))?;
+        match flag.as_str() {
+            "--output" => output = value.clone(),
+            "--iterations" => {
+                iterations = value
+                    .parse()
+                    .map_err(|_| format!("Invalid value for {}: {}.", flag, value))?
+            }
+            _ => return Err(format!("Unknown option: {}.", flag)),
+        }
+    }
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|error| format!("Unable to read {}: {}.", path, error))?;
+    let positions: Vec<TuningPosition> = text.lines().filter_map(parse_position).collect();
+    if positions.is_empty() {
+        return Err(format!("No positions found in {}.", path));
+    }
+    let default = EvalWeights::default();
+    let scale = fit_scale(&to_vector(&default), &positions);
+    let weights = tune(&default, &positions, iterations);
+    println!(
+        "Tuned on {} positions: error {:.5} before, {:.5} after.",
+        positions.len(),
+        error(&to_vector(&default), &positions, scale),
+        error(&to_vector(&weights), &positions, scale)
+    );
+    let text = ron::ser::to_string_pretty(&weights, ron::ser::PrettyConfig::default())
+        .map_err(|error| format!("Unable to write {}: {}.", output, error))?;
+    std::fs::write(&output, text)
+        .map_err(|error| format!("Unable to write {}: {}.", output, error))?;
+    println!("Wrote the weights to {}.", output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::PieceType;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_position() {
+        let position = parse_position("4k3/8/8/8/8/8/3QP3/4K3 w - - 0 1 1-0").unwrap();
+        assert_eq!(position.result, 1.);
+        assert_eq!(
+            evaluation(&to_vector(&EvalWeights::default()), &position),
+            1000.
+        );
+
+        let epd = parse_position("4k3/8/8/8/8/8/3QP3/4K3 w - - c9 \"1/2-1/2\";").unwrap();
+        assert_eq!(epd.features, position.features);
+        assert_eq!(epd.result, 0.5);
+
+        // Neither side can checkmate
+        assert!(parse_position("4k3/8/8/8/8/8/8/4K3 w - - 0 1 1/2-1/2").is_none());
+        assert!(parse_position("4k3/8/8/8/8/8/3QP3/4K3 w - - 0 1 won").is_none());
+    }
+
+    #[test]
+    fn test_tune() {
+        // White wins the games with an extra knight and draws those with an extra pawn, so the
+        // knight is worth more than the default weights expect
+        let lines = [
+            "4k3/8/8/8/8/8/3PN3/4K3 w - - 0 1 1-0",
+            "4k3/3p4/8/8/8/8/3PN3/4K3 b - - 0 1 1-0",
+            "4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1 1/2-1/2",
+            "4k3/3p4/8/8/8/8/3PP3/4K3 b - - 0 1 1/2-1/2",
+        ];
+        let positions: Vec<TuningPosition> = lines
+            .iter()
+            .filter_map(|line| parse_position(line))
+            .collect();
+        assert_eq!(positions.len(), 4);
+        let default = EvalWeights::default();
+        let weights = tune(&default, &positions, 100);
+        let scale = fit_scale(&to_vector(&default), &positions);
+        assert!(
+            error(&to_vector(&weights), &positions, scale)
+                < error(&to_vector(&default), &positions, scale)
+        );
+        let knight = PieceType::Knight.index().unwrap();
+        assert!(weights.piece_values[knight] > default.piece_values[knight]);
+    }
+}
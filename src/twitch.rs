@@ -0,0 +1,287 @@
+//! Lets Twitch chat vote on the moves for one side, enabled with the `twitch` feature.
+//!
+//! Chat is read anonymously over IRC from the channel in the `TWITCH_CHANNEL` environment variable.
+//! While it is chat's turn, each message containing a legal move in algebraic notation (e.g. `Nf3`)
+//! counts as a vote for that move, with one vote per user. Voting closes `TWITCH_VOTE_SECONDS`
+//! seconds after the first vote and the move with the most votes is played. Chat plays the color in
+//! `TWITCH_COLOR` (`white` or `black`, defaulting to black). If chat cannot be read, or the
+//! connection is lost, the player is told with a [notification](crate::notification).
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use bevy::app::{App, Plugin};
+use bevy::log::{debug, info, info_span, warn};
+use bevy::prelude::{EventWriter, Res, ResMut, Resource, Update};
+use bevy::time::{Time, Timer, TimerMode};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor};
+use crate::notification::Notification;
+
+/// The Twitch IRC server.
+const TWITCH_IRC_ADDRESS: &str = "irc.chat.twitch.tv:6667";
+
+/// The default time votes are collected for after the first vote.
+const DEFAULT_VOTE_WINDOW: Duration = Duration::from_secs(30);
+
+pub(super) struct TwitchPlugin;
+
+impl Plugin for TwitchPlugin {
+    #[cfg(not(tarpaulin_include))]
+    fn build(&self, app: &mut App) {
+        let channel_name = std::env::var("TWITCH_CHANNEL")
+            .expect("The TWITCH_CHANNEL environment variable must be set.");
+        let vote_window = std::env::var("TWITCH_VOTE_SECONDS")
+            .ok()
+            .and_then(|seconds| seconds.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_VOTE_WINDOW);
+        let color = match std::env::var("TWITCH_COLOR").as_deref() {
+            Ok("white") => PieceColor::White,
+            _ => PieceColor::Black,
+        };
+        let (message_sender, message_receiver) = channel();
+        thread::spawn(move || read_chat(&channel_name, &message_sender));
+
+        app.insert_resource(TwitchChat {
+            color,
+            messages: Mutex::new(message_receiver),
+            connected: true,
+        })
+        .insert_resource(VoteTally::new(vote_window))
+        .add_systems(Update, tally_votes);
+    }
+}
+
+/// A chat message.
+#[derive(Debug, PartialEq, Eq)]
+struct ChatMessage {
+    user: String,
+    text: String,
+}
+
+#[derive(Resource)]
+struct TwitchChat {
+    /// The color chat plays.
+    color: PieceColor,
+    messages: Mutex<Receiver<ChatMessage>>,
+    /// Whether chat is still being read, until the connection is lost.
+    connected: bool,
+}
+
+/// The votes for the current move.
+#[derive(Resource, Debug)]
+struct VoteTally {
+    /// The vote of each user.
+    votes: HashMap<String, Move>,
+    /// The order in which moves were first voted for, used to break ties.
+    vote_order: Vec<Move>,
+    /// The voting window, started by the first vote.
+    timer: Option<Timer>,
+    vote_window: Duration,
+    /// The number of moves played when the votes were cast.
+    moves_played: usize,
+}
+
+impl VoteTally {
+    fn new(vote_window: Duration) -> Self {
+        VoteTally {
+            votes: HashMap::new(),
+            vote_order: Vec::new(),
+            timer: None,
+            vote_window,
+            moves_played: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.votes.clear();
+        self.vote_order.clear();
+        self.timer = None;
+    }
+
+    fn vote(&mut self, user: String, piece_move: Move) {
+        if self.timer.is_none() {
+            self.timer = Some(Timer::new(self.vote_window, TimerMode::Once));
+        }
+        if !self.vote_order.contains(&piece_move) {
+            self.vote_order.push(piece_move);
+        }
+        self.votes.insert(user, piece_move);
+    }
+
+    /// Returns the move with the most votes, breaking ties by which move was voted for first.
+    fn winner(&self) -> Option<Move> {
+        let count = |piece_move: &Move| {
+            self.votes
+                .values()
+                .filter(|vote| *vote == piece_move)
+                .count()
+        };
+        self.vote_order
+            .iter()
+            .copied()
+            .filter(|piece_move| count(piece_move) > 0)
+            .rev()
+            .max_by_key(count)
+    }
+}
+
+/// Parses a chat message from a line sent by the IRC server.
+fn parse_privmsg(line: &str) -> Option<ChatMessage> {
+    let line = line.strip_prefix(':')?;
+    let (prefix, rest) = line.split_once(' ')?;
+    let user = prefix.split_once('!')?.0;
+    let rest = rest.strip_prefix("PRIVMSG ")?;
+    let text = rest.split_once(" :")?.1;
+    Some(ChatMessage {
+        user: user.to_string(),
+        text: text.trim().to_string(),
+    })
+}
+
+/// Reads the chat of the given channel, sending each message with the given sender. The thread
+/// ends if the connection fails or is lost, which drops the sender so the app can tell.
+fn read_chat(channel_name: &str, message_sender: &Sender<ChatMessage>) {
+    let _span = info_span!("chat", channel = channel_name).entered();
+    let mut stream = match TcpStream::connect(TWITCH_IRC_ADDRESS) {
+        Ok(stream) => stream,
+        Err(error) => {
+            warn!(%error, "Unable to connect");
+            return;
+        }
+    };
+    // Anonymous users can read chat using a "justinfan" nickname
+    let joined = write!(
+        stream,
+        "NICK justinfan{}\r\nJOIN #{}\r\n",
+        std::process::id(),
+        channel_name.to_lowercase()
+    );
+    let Some(reader) = joined
+        .and_then(|_| stream.try_clone())
+        .ok()
+        .map(BufReader::new)
+    else {
+        warn!("Unable to join");
+        return;
+    };
+    info!("Joined");
+    for line in reader.lines().map_while(Result::ok) {
+        if let Some(server) = line.strip_prefix("PING ") {
+            write!(stream, "PONG {}\r\n", server).ok();
+        } else if let Some(message) = parse_privmsg(&line) {
+            debug!(user = message.user, text = message.text, "Message");
+            if message_sender.send(message).is_err() {
+                return;
+            }
+        }
+    }
+    warn!("Connection lost");
+}
+
+fn tally_votes(
+    mut chat: ResMut<TwitchChat>,
+    mut tally: ResMut<VoteTally>,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+    time: Res<Time>,
+    mut notifications: EventWriter<Notification>,
+) {
+    // Votes only count for the position they were cast in
+    if tally.moves_played != board.past_moves().len() {
+        tally.clear();
+        tally.moves_played = board.past_moves().len();
+    }
+    let chat_to_move = *board.active_color() == Some(chat.color);
+    let disconnected = {
+        let messages = chat.messages.lock().unwrap();
+        loop {
+            match messages.try_recv() {
+                Ok(message) => {
+                    if chat_to_move {
+                        if let Some(piece_move) = Move::from_algebraic(&message.text, &board) {
+                            tally.vote(message.user, piece_move);
+                        }
+                    }
+                }
+                Err(error) => break error == TryRecvError::Disconnected,
+            }
+        }
+    };
+    if disconnected && chat.connected {
+        chat.connected = false;
+        notifications.send(Notification::error("twitch-disconnected"));
+    }
+    let voting_closed = tally
+        .timer
+        .as_mut()
+        .is_some_and(|timer| timer.tick(time.delta()).finished());
+    if voting_closed {
+        if let Some(piece_move) = tally.winner() {
+            info!(
+                piece_move = %piece_move.as_algebraic(),
+                votes = tally.votes.len(),
+                "Voting closed"
+            );
+            commands.push(GameCommand::MakeMove(piece_move));
+        }
+        tally.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::ChessBoardPlugin;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_privmsg() {
+        assert_eq!(
+            parse_privmsg(":viewer!viewer@viewer.tmi.twitch.tv PRIVMSG #channel :Nf3 "),
+            Some(ChatMessage {
+                user: "viewer".to_string(),
+                text: "Nf3".to_string(),
+            })
+        );
+        assert_eq!(parse_privmsg("PING :tmi.twitch.tv"), None);
+        assert_eq!(
+            parse_privmsg(":tmi.twitch.tv 001 justinfan1 :Welcome, GLHF!"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_vote_tally_winner() {
+        let mut app = App::new();
+        app.add_plugins(ChessBoardPlugin::default());
+        app.update();
+        let board = app.world.get_resource::<ChessBoard>().unwrap();
+        let first = Move::from_algebraic("e4", board).unwrap();
+        let second = Move::from_algebraic("Nf3", board).unwrap();
+
+        let mut tally = VoteTally::new(DEFAULT_VOTE_WINDOW);
+        assert_eq!(tally.winner(), None);
+
+        // Ties are won by the move voted for first
+        tally.vote("a".to_string(), first);
+        tally.vote("b".to_string(), second);
+        assert!(tally.timer.is_some());
+        assert_eq!(tally.winner(), Some(first));
+
+        // Users can change their vote
+        tally.vote("a".to_string(), second);
+        assert_eq!(tally.winner(), Some(second));
+
+        tally.clear();
+        assert_eq!(tally.winner(), None);
+        assert!(tally.timer.is_none());
+    }
+}
@@ -1,45 +1,351 @@
-use bevy::app::{App, Plugin};
+use bevy::app::{App, Last, Plugin};
+use bevy::ecs::system::SystemParam;
 use bevy::input::mouse::MouseButtonInput;
 use bevy::prelude::{
     Camera, Camera2dBundle, Commands, Component, Event, EventReader, EventWriter, GlobalTransform,
-    Query, Res, Startup, Update, With,
+    Input, KeyCode, Query, Res, ResMut, Startup, Update, Vec2, With,
 };
 use bevy::window::Window;
-use bevy_egui::{egui, EguiContexts};
+use bevy_egui::egui::containers::panel::Side;
+use bevy_egui::EguiContexts;
 
-use crate::chess_board::{BoardPosition, ChessBoard, GameEndStatus, ResetBoardEvent};
-use crate::fen::Fen;
+use crate::chess_board::{BoardPosition, ChessBoard, GameCommands, GameEndStatus};
 
+mod actions;
+mod analysis;
+mod annotation;
+mod army;
+mod audio;
+mod blunder;
 mod board;
+mod board_sync;
+mod bookmarks;
+mod broadcast;
+mod bundle;
+mod calibration;
+mod clipboard;
+mod clock;
+mod coach;
+mod console;
+mod database;
+mod dead_draw;
+mod display;
+mod duck;
+mod endgame_trainer;
+mod energy;
+mod engine;
+mod eval_graph;
+mod explanation;
+mod guess_move;
+mod heatmap;
+mod integrity;
+mod king_safety;
+mod layout;
+#[cfg(feature = "leds")]
+mod leds;
+mod locale;
+mod material_quiz;
+mod move_list;
+mod music;
+mod notation_trainer;
+mod orientation;
+mod palette;
+mod pawn_structure;
+mod performance;
 mod piece;
+mod position_editor;
+mod premove;
+mod profiles;
+mod promotion;
+mod puzzle_rush;
+mod random_position;
+#[cfg(feature = "recognition")]
+mod recognition;
+mod recording;
+mod recovery;
+mod report;
+mod review;
+mod scrub_bar;
+mod simul;
+mod spectating;
+mod square_info;
+mod status_bar;
+mod theme;
+mod time_scramble;
+mod toast;
+mod trails;
+mod turn;
+mod uci;
+mod underpromotion;
+mod user_theme;
+mod voice;
 
 pub(super) struct UIPlugin;
 
 impl Plugin for UIPlugin {
     #[cfg(not(tarpaulin_include))]
     fn build(&self, app: &mut App) {
+        use bevy::diagnostic::FrameTimeDiagnosticsPlugin;
+        use bevy::prelude::IntoSystemConfigs;
         use bevy_egui::EguiPlugin;
 
-        app.add_plugins(EguiPlugin)
+        crate::diagnostics::register(app, crate::diagnostics::HIGHLIGHTING, "highlighting");
+        app.add_plugins((EguiPlugin, FrameTimeDiagnosticsPlugin))
             .init_resource::<piece::PieceProperties>()
+            .init_resource::<piece::VectorPieces>()
             .init_resource::<board::BoardProperties>()
+            .init_resource::<audio::AudioSettings>()
+            .init_resource::<audio::SoundPacks>()
+            .init_resource::<music::Music>()
+            .init_resource::<voice::VoicePacks>()
+            .init_resource::<voice::Announcer>()
+            .init_resource::<clock::ClockWarningSettings>()
+            .init_resource::<orientation::BoardOrientation>()
+            .init_resource::<turn::TurnIndicator>()
+            .init_resource::<move_list::MoveListState>()
+            .init_resource::<report::ReportExportState>()
+            .init_resource::<review::GameReview>()
+            .init_resource::<annotation::MoveAnnotations>()
+            .init_resource::<board::HoverPreview>()
+            .init_resource::<board::SquareHighlights>()
+            .init_resource::<square_info::SquareInfo>()
+            .init_resource::<piece::EngineMoveAnimation>()
+            .init_resource::<locale::Localisation>()
+            .init_resource::<theme::Theme>()
+            .init_resource::<clipboard::PasteState>()
+            .init_resource::<database::DatabaseBrowserState>()
+            .init_resource::<bookmarks::BookmarksWindowState>()
+            .init_resource::<coach::CoachState>()
+            .init_resource::<blunder::BlunderCheck>()
+            .init_resource::<promotion::PromotionChoice>()
+            .init_resource::<underpromotion::UnderpromotionTrainer>()
+            .init_resource::<endgame_trainer::EndgameTrainer>()
+            .init_resource::<dead_draw::DeadDrawAdjudicator>()
+            .init_resource::<puzzle_rush::PuzzleRush>()
+            .init_resource::<calibration::SkillCalibration>()
+            .init_resource::<profiles::ProfilesWindowState>()
+            .init_resource::<guess_move::GuessTheMove>()
+            .init_resource::<layout::Layout>()
+            .init_resource::<toast::Toasts>()
+            .init_resource::<console::ConsoleState>()
+            .init_resource::<uci::UciWindowState>()
+            .init_resource::<broadcast::BroadcastWindowState>()
+            .init_resource::<simul::SimulSetup>()
+            .init_resource::<material_quiz::MaterialQuiz>()
+            .init_resource::<bundle::BundleState>()
+            .init_resource::<user_theme::UserThemes>()
+            .init_resource::<heatmap::Heatmap>()
+            .init_resource::<pawn_structure::PawnStructureOverlay>()
+            .init_resource::<king_safety::KingSafetyOverlay>()
+            .init_resource::<explanation::GameExplanation>()
+            .init_resource::<integrity::GameIntegrity>()
+            .init_resource::<premove::Premoves>()
+            .init_resource::<analysis::AnalysisWindowState>()
+            .init_resource::<display::DisplaySettings>()
+            .init_resource::<random_position::RandomPositionState>()
+            .init_resource::<position_editor::PositionEditor>()
+            .init_resource::<time_scramble::TimeScramble>()
+            .init_resource::<army::ArmyBuilder>()
+            .init_resource::<notation_trainer::NotationTrainer>()
+            .init_resource::<board_sync::BoardSync>()
+            .init_resource::<performance::PerformanceOverlay>()
+            .init_resource::<status_bar::StatusBar>()
+            .init_resource::<scrub_bar::ScrubBar>()
+            .init_resource::<trails::PieceTrails>()
+            .init_resource::<palette::CommandPalette>()
+            .init_resource::<recording::InputRecorder>()
             .add_event::<BoardClickEvent>()
-            .add_systems(Startup, (setup, board::setup))
+            .add_event::<actions::ActionEvent>()
+            .add_systems(
+                Startup,
+                (
+                    setup,
+                    board::setup,
+                    turn::setup,
+                    puzzle_rush::setup,
+                    layout::setup,
+                    display::setup,
+                ),
+            )
             .add_systems(
                 Update,
                 (
-                    mouse_event_handler,
-                    ui_system,
-                    piece::piece_click_handler,
+                    mouse_event_handler.run_if(crate::spectating::not_spectating),
+                    (
+                        layout::distraction_free_shortcut,
+                        actions::action_shortcuts,
+                        actions::run_actions,
+                        ui_system.run_if(layout::shows_interface),
+                        layout::save_layout,
+                    )
+                        .chain(),
+                    (piece::piece_click_handler, duck::duck_click_handler)
+                        .run_if(notation_trainer::not_training),
                     piece::piece_undragger,
-                    piece::piece_creator,
+                    (
+                        user_theme::reload_user_theme,
+                        user_theme::apply_user_theme,
+                        board::resize_board,
+                        piece::piece_creator,
+                        piece::piece_restyler,
+                    )
+                        .chain(),
                     piece::piece_move_audio,
                     piece::piece_dragger,
                     piece::piece_mover,
-                    piece::piece_resetter,
-                    board::highlight_valid_squares,
+                    piece::piece_animator,
+                    (piece::piece_resetter, duck::duck_drawer),
+                    (
+                        (board::hover_preview, board::color_squares).chain(),
+                        square_info::square_tooltip.run_if(notation_trainer::not_training),
+                    ),
+                    (
+                        clock::clock_redraw,
+                        clock::low_time_audio,
+                        clock::low_time_title_flash,
+                        clock::away_from_window,
+                    ),
+                    (
+                        orientation::auto_rotate,
+                        orientation::animate_rotation,
+                        turn::turn_bar,
+                    ),
+                    annotation::clear_annotations,
+                    (
+                        heatmap::heatmap_overlay,
+                        pawn_structure::pawn_structure_overlay,
+                        king_safety::king_safety_overlay,
+                        trails::trail_overlay,
+                        (
+                            explanation::update_explanation,
+                            explanation::explanation_overlay,
+                        )
+                            .chain(),
+                    ),
+                    annotation::annotation_badges,
+                    (
+                        engine::engine_redraw,
+                        (palette::palette_shortcut, palette::palette_window).chain(),
+                        toast::show_toasts,
+                    ),
+                    (recovery::recovery_window, recovery::conflict_window),
+                    (
+                        (database::database_window, bookmarks::bookmarks_window),
+                        blunder::blunder_window,
+                        promotion::promotion_window,
+                        (
+                            underpromotion::trainer_window,
+                            endgame_trainer::endgame_trainer_window,
+                            dead_draw::dead_draw_window,
+                        ),
+                        (
+                            random_position::random_position_window,
+                            position_editor::position_editor_window,
+                            time_scramble::time_scramble_window,
+                        ),
+                        army::army_window,
+                        puzzle_rush::puzzle_rush_window,
+                        calibration::calibration_window,
+                        profiles::profiles_window,
+                        guess_move::guess_move_window,
+                        (console::console_window, recording::recorder_window),
+                        uci::uci_window,
+                        broadcast::broadcast_window,
+                        simul::simul_window,
+                        material_quiz::material_quiz_window,
+                        bundle::bundle_window,
+                        (heatmap::heatmap_window, review::review_window),
+                        notation_trainer::notation_trainer_window,
+                        performance::performance_window,
+                        explanation::explanation_window,
+                    )
+                        .run_if(layout::shows_interface),
+                    (
+                        clipboard::file_drop,
+                        clipboard::paste_window,
+                        clipboard::replay_window,
+                    )
+                        .chain()
+                        .run_if(layout::shows_interface),
                 ),
             );
+        app.add_systems(
+            Update,
+            (
+                premove::play_premoves,
+                premove::show_premoves
+                    .after(piece::piece_undragger)
+                    .after(piece::piece_mover)
+                    .after(piece::piece_animator),
+            ),
+        );
+        app.add_systems(
+            Update,
+            (display::update_window, display::save_display).chain(),
+        );
+        app.add_systems(Last, energy::save_energy);
+        app.add_systems(
+            Update,
+            (spectating::spectating_banner, piece::drop_spectated_pieces),
+        );
+        app.add_systems(Update, notation_trainer::answer_clicks);
+        app.add_systems(
+            Update,
+            recording::replay_input
+                .before(piece::piece_click_handler)
+                .before(duck::duck_click_handler)
+                .before(actions::run_actions)
+                .before(promotion::promotion_window),
+        );
+        app.add_systems(Last, recording::record_input);
+        app.add_systems(
+            Update,
+            (music::track_evaluations, music::play_music).chain(),
+        );
+        app.add_systems(
+            Update,
+            (voice::announce_moves, voice::play_announcements).chain(),
+        );
+        app.add_systems(
+            Update,
+            (
+                status_bar::update_book_status,
+                status_bar::status_bar.run_if(layout::shows_interface),
+                scrub_bar::scrub_bar.run_if(layout::shows_interface),
+            )
+                .chain()
+                .after(ui_system),
+        );
+        app.add_systems(
+            Update,
+            board_sync::check_board_sync
+                .after(piece::piece_mover)
+                .after(piece::piece_resetter),
+        );
+        // The assistance is withheld here rather than by each window, so none can show it
+        app.add_systems(
+            Update,
+            (
+                integrity::withhold_assistance,
+                (coach::coach_window, analysis::analysis_window)
+                    .run_if(layout::shows_interface)
+                    .run_if(integrity::assistance_allowed),
+            )
+                .chain(),
+        );
+        #[cfg(feature = "leds")]
+        app.insert_resource(leds::Leds::load()).add_systems(
+            Update,
+            (leds::track_last_move, leds::update_leds)
+                .chain()
+                .after(board::hover_preview),
+        );
+        #[cfg(feature = "recognition")]
+        app.init_resource::<recognition::RecognitionState>()
+            .add_systems(
+                Update,
+                recognition::recognition_window.run_if(layout::shows_interface),
+            );
     }
 }
 
@@ -50,75 +356,144 @@ fn setup(mut commands: Commands) {
     commands.spawn((Camera2dBundle::default(), MainCamera));
 }
 
+/// The settings shown in the side panels, with the chess clock they set up and the localisation
+/// the interface is shown in.
+#[derive(SystemParam)]
+struct PanelSettings<'w, 's> {
+    sound: audio::Sound<'w>,
+    clocks: clock::Clocks<'w, 's>,
+    board_view: orientation::BoardView<'w>,
+    locale: ResMut<'w, locale::Localisation>,
+    appearance: layout::Appearance<'w>,
+}
+
+/// What the game is exported with: the report and the clipboard.
+#[derive(SystemParam)]
+struct PanelExports<'w> {
+    report_export_state: ResMut<'w, report::ReportExportState>,
+    clipboard_actions: clipboard::ClipboardActions<'w>,
+}
+
 fn ui_system(
     mut contexts: EguiContexts,
-    mut setup_event: EventWriter<ResetBoardEvent>,
+    mut game_commands: ResMut<GameCommands>,
     board: Res<ChessBoard>,
+    settings: PanelSettings,
+    exports: PanelExports,
+    mut past_moves: move_list::PastMoves,
+    mut game_actions: engine::GameActions,
 ) {
+    let PanelSettings {
+        mut sound,
+        mut clocks,
+        mut board_view,
+        mut locale,
+        mut appearance,
+    } = settings;
+    let PanelExports {
+        mut report_export_state,
+        mut clipboard_actions,
+    } = exports;
     let ctx = contexts.ctx_mut();
-    egui::SidePanel::left("left_panel")
-        .default_width(200.0)
-        .show(ctx, |ui| {
-            // Reset board button
-            if ui.button("Reset Board").clicked() {
-                setup_event.send(ResetBoardEvent::new(Fen::default()));
-            }
-        });
-
-    egui::SidePanel::right("right_panel")
-        .default_width(200.0)
-        .show(ctx, |ui| {
-            // Past moves list
-            ui.heading("Past Moves");
-
-            let text_style = egui::TextStyle::Body;
-            let row_height = ui.text_style_height(&text_style);
-            let total_rows = (board.past_moves().len() as f32 / 2.0).ceil() as usize;
-            egui::ScrollArea::vertical()
-                .auto_shrink([false; 2])
-                .stick_to_bottom(true)
-                .max_height(ui.available_height() * 4.0 / 5.0)
-                .show_rows(ui, row_height, total_rows, |ui, row_range| {
-                    for row in row_range {
-                        let white_move = board.past_moves()[row * 2].as_algebraic();
-                        let black_move =
-                            if (row == total_rows - 1) && ((board.past_moves().len() & 1) == 1) {
-                                "".to_string()
-                            } else {
-                                board.past_moves()[row * 2 + 1].as_algebraic()
-                            };
-                        let mut move_number = row + *board.move_number() as usize - total_rows;
-                        if (board.past_moves().len() & 1) == 1 {
-                            move_number += 1;
-                        }
-                        let move_text = format!("{}. {} {}", move_number, white_move, black_move);
-                        ui.label(move_text);
-                    }
-                });
-
-            // Game end status
-            if board.game_end_status().is_some() {
-                ui.label(match board.game_end_status().unwrap() {
-                    GameEndStatus::Checkmate => "Checkmate",
-                    GameEndStatus::Resignation => "Resignation",
-                    GameEndStatus::Stalemate => "Stalemate",
-                    GameEndStatus::DeadPosition => "Dead Position",
-                    GameEndStatus::FlagFall => "Flag Fall",
-                });
-                ui.label(format!(
-                    "Winner: {}",
-                    match board.winner() {
-                        Some(x) => x.to_string(),
-                        None => "Draw".to_string(),
-                    }
-                ));
-            }
-        });
+    let layout = &mut appearance.layout;
+    let theme = &mut appearance.theme;
+    let user_themes = &appearance.user_themes;
+    let display = &mut appearance.display;
+    let trails = &mut appearance.trails;
+    let labels = layout::PanelLabels::new(&locale);
+    layout::side_panel(ctx, Side::Left, layout, &labels, |ui| {
+        // Reset board button
+        if ui.button(locale.get("reset-board")).clicked() {
+            game_actions
+                .actions
+                .send(actions::ActionEvent(actions::Action::NewGame));
+        }
+        engine::game_actions_ui(ui, &board, &mut game_actions, &locale);
+        engine::variant_ui(ui, &board, &mut game_actions, &locale);
+        engine::sandbox_ui(ui, &mut game_actions, &locale);
+
+        orientation::settings_ui(ui, &mut board_view, &locale);
+        locale::settings_ui(ui, &mut locale);
+        engine::settings_ui(ui, &mut game_actions, &locale);
+        clock::settings_ui(ui, &mut clocks, &mut game_commands, &locale);
+        theme::settings_ui(ui, theme, user_themes, &locale);
+        display::settings_ui(ui, display, &locale);
+        trails::settings_ui(ui, trails, &locale);
+        audio::settings_ui(ui, &mut sound, &locale);
+        report::export_ui(ui, &board, &past_moves, &mut report_export_state, &locale);
+        clipboard::clipboard_ui(
+            ui,
+            &board,
+            clocks.clock().time_controls(),
+            &mut clipboard_actions,
+            &locale,
+        );
+    });
+
+    layout::side_panel(ctx, Side::Right, layout, &labels, |ui| {
+        // Clocks
+        clock::clock_ui(ui, &clocks, &locale);
+
+        // Past moves list
+        ui.heading(locale.get("past-moves"));
+
+        move_list::positions_ui(ui, &mut past_moves, &mut game_commands, &locale);
+        move_list::filter_ui(ui, &mut past_moves, &locale);
+        move_list::move_list_ui(ui, &board, &mut past_moves, &locale);
+        move_list::eval_graph_ui(ui, &mut past_moves, &mut game_commands, &locale);
+        move_list::opening_ui(ui, &past_moves, &locale);
+
+        if board.awaiting_duck() {
+            ui.label(locale.get("place-duck"));
+        }
+
+        // Game end status
+        if board.game_end_status().is_some() {
+            ui.label(locale.get(status_key(&board.game_end_status().unwrap())));
+            let winner = match board.winner() {
+                Some(color) => locale.color(color),
+                None => locale.get("draw"),
+            };
+            ui.label(locale.format("winner", &[("winner", &winner)]));
+        }
+    });
+}
+
+/// Returns the locale key of the description of the given game end status.
+fn status_key(status: &GameEndStatus) -> &'static str {
+    match status {
+        GameEndStatus::Checkmate => "status-checkmate",
+        GameEndStatus::Resignation => "status-resignation",
+        GameEndStatus::Stalemate => "status-stalemate",
+        GameEndStatus::DeadPosition => "status-dead-position",
+        GameEndStatus::FlagFall => "status-flag-fall",
+        GameEndStatus::Agreement => "status-agreement",
+        GameEndStatus::KingCapture => "status-king-capture",
+        GameEndStatus::AllPiecesLost => "status-all-pieces-lost",
+        GameEndStatus::FiftyMoves => "status-fifty-moves",
+        GameEndStatus::Repetition => "status-repetition",
+    }
+}
+
+/// Returns the position of the cursor in world coordinates, if it is in the window.
+fn cursor_world_position(
+    window: &Window,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+        .map(|ray| ray.origin.truncate())
 }
 
 #[derive(Debug, Copy, Clone, Event)]
 struct BoardClickEvent {
     position: Option<BoardPosition>,
+    /// The square on the first or last rank the cursor is just beyond, when it is off the board.
+    past_edge: Option<BoardPosition>,
+    /// Whether Alt is held, which asks for the choice of piece when a pawn promotes.
+    alt: bool,
     input: MouseButtonInput,
 }
 
@@ -128,21 +503,20 @@ fn mouse_event_handler(
     properties: Res<board::BoardProperties>,
     mut board_click_event: EventWriter<BoardClickEvent>,
     camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    keys: Res<Input<KeyCode>>,
 ) {
     let window = windows.get_single().expect("No window has been created.");
     let (camera, camera_transform) = camera.single();
     for input in mouse_input.iter() {
         // Check if the cursor is in the window and convert to world coordinates
-        if let Some(world_position) = window
-            .cursor_position()
-            .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-            .map(|ray| ray.origin.truncate())
-        {
+        if let Some(world_position) = cursor_world_position(window, camera, camera_transform) {
             // Check if the mouse is over the board
             let board_position = properties.transform_to_position(&world_position);
             // Send a board click event
             let event = BoardClickEvent {
                 position: board_position,
+                past_edge: properties.position_past_edge(&world_position),
+                alt: keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]),
                 input: *input,
             };
             board_click_event.send(event);
@@ -0,0 +1,201 @@
+//! The registry of the actions of the interface, which the keyboard shortcuts, the buttons and the
+//! [command palette](super::palette) all run in the same way, by sending an [ActionEvent].
+//!
+//! Each action has a name to list it by and may have a shortcut, which runs it while no text field
+//! has focus. A new action is added to [Action] with its name, and run in [run_actions].
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::Input;
+use bevy::prelude::{Event, EventReader, EventWriter, KeyCode, Res, ResMut};
+use bevy_egui::{EguiClipboard, EguiContexts};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::analysis::Analysis;
+use crate::chess_board::{GameCommand, GameCommands};
+use crate::engine::Engine;
+use crate::history::PositionHistory;
+
+use super::annotation::MoveAnnotations;
+use super::clipboard::PasteState;
+use super::king_safety::KingSafetyOverlay;
+use super::layout::Layout;
+use super::locale::Localisation;
+use super::orientation::BoardOrientation;
+use super::pawn_structure::PawnStructureOverlay;
+use super::review::GameReview;
+
+/// Returns whether Control, or Command on a Mac, is held.
+pub(super) fn command_held(keys: &Input<KeyCode>) -> bool {
+    keys.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ])
+}
+
+/// A key which runs an action, with Control or Command held or without.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Shortcut {
+    key: KeyCode,
+    command: bool,
+}
+
+impl Shortcut {
+    /// Returns whether the shortcut has just been pressed.
+    fn pressed(&self, keys: &Input<KeyCode>) -> bool {
+        keys.just_pressed(self.key) && command_held(keys) == self.command
+    }
+
+    /// Returns the shortcut as it is shown, e.g. "Ctrl+V".
+    pub(super) fn label(&self) -> String {
+        let key = match self.key {
+            KeyCode::Escape => "Esc".to_string(),
+            key => format!("{:?}", key),
+        };
+        match self.command {
+            true => format!("Ctrl+{}", key),
+            false => key,
+        }
+    }
+}
+
+/// An action of the interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub(super) enum Action {
+    NewGame,
+    Undo,
+    FlipBoard,
+    /// Offers to load the position or game on the clipboard.
+    Paste,
+    /// Turns the analysis of the position on the board on or off.
+    ToggleAnalysis,
+    /// Stops the engine's search and the analysis of the position.
+    CancelSearch,
+    ReviewGame,
+    TogglePawnStructure,
+    ToggleKingSafety,
+    ToggleDistractionFree,
+}
+
+impl Action {
+    /// Returns the locale key of the name of the action.
+    pub(super) fn key(&self) -> &'static str {
+        match self {
+            Action::NewGame => "action-new-game",
+            Action::Undo => "action-undo",
+            Action::FlipBoard => "action-flip-board",
+            Action::Paste => "action-paste",
+            Action::ToggleAnalysis => "action-toggle-analysis",
+            Action::CancelSearch => "action-cancel-search",
+            Action::ReviewGame => "action-review-game",
+            Action::TogglePawnStructure => "action-toggle-pawn-structure",
+            Action::ToggleKingSafety => "action-toggle-king-safety",
+            Action::ToggleDistractionFree => "action-toggle-distraction-free",
+        }
+    }
+
+    /// Returns the shortcut which runs the action, if it has one.
+    pub(super) fn shortcut(&self) -> Option<Shortcut> {
+        let (key, command) = match self {
+            Action::Paste => (KeyCode::V, true),
+            Action::CancelSearch => (KeyCode::Escape, false),
+            Action::ToggleDistractionFree => (KeyCode::F, false),
+            _ => return None,
+        };
+        Some(Shortcut { key, command })
+    }
+}
+
+/// Event sent to run the given action.
+#[derive(Debug, Clone, Copy, Event)]
+pub(super) struct ActionEvent(pub(super) Action);
+
+/// What the actions act on.
+#[derive(SystemParam)]
+pub(super) struct ActionTargets<'w> {
+    commands: ResMut<'w, GameCommands>,
+    orientation: ResMut<'w, BoardOrientation>,
+    paste: ResMut<'w, PasteState>,
+    clipboard: Res<'w, EguiClipboard>,
+    locale: Res<'w, Localisation>,
+    engine: ResMut<'w, Engine>,
+    analysis: ResMut<'w, Analysis>,
+    history: Res<'w, PositionHistory>,
+    review: ResMut<'w, GameReview>,
+    annotations: ResMut<'w, MoveAnnotations>,
+    pawn_structure: ResMut<'w, PawnStructureOverlay>,
+    king_safety: ResMut<'w, KingSafetyOverlay>,
+    layout: ResMut<'w, Layout>,
+}
+
+/// Sends the action of each shortcut pressed, unless a text field is using the keys.
+pub(super) fn action_shortcuts(
+    mut contexts: EguiContexts,
+    keys: Res<Input<KeyCode>>,
+    mut action_events: EventWriter<ActionEvent>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    for action in Action::iter() {
+        if action
+            .shortcut()
+            .is_some_and(|shortcut| shortcut.pressed(&keys))
+        {
+            action_events.send(ActionEvent(action));
+        }
+    }
+}
+
+/// Runs the actions sent.
+pub(super) fn run_actions(mut events: EventReader<ActionEvent>, mut targets: ActionTargets) {
+    for ActionEvent(action) in events.iter() {
+        match action {
+            Action::NewGame => targets.commands.push(GameCommand::Reset),
+            Action::Undo => targets.commands.push(GameCommand::Undo),
+            Action::FlipBoard => targets.orientation.flip(),
+            Action::Paste => {
+                let contents = targets.clipboard.get_contents();
+                targets.paste.paste(contents, &targets.locale);
+            }
+            Action::ToggleAnalysis => targets.analysis.enabled = !targets.analysis.enabled,
+            Action::CancelSearch => {
+                targets.engine.cancel_search();
+                targets.analysis.cancel_search();
+            }
+            Action::ReviewGame => {
+                if !targets.history.moves().is_empty() {
+                    targets
+                        .review
+                        .review(&targets.history, &mut targets.annotations);
+                }
+            }
+            Action::TogglePawnStructure => targets.pawn_structure.toggle(),
+            Action::ToggleKingSafety => targets.king_safety.toggle(),
+            Action::ToggleDistractionFree => targets.layout.toggle_distraction_free(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions() {
+        let locale = Localisation::default();
+        for action in Action::iter() {
+            assert_ne!(locale.get(action.key()), action.key());
+        }
+        assert_eq!(Action::Paste.shortcut().unwrap().label(), "Ctrl+V");
+        assert_eq!(Action::CancelSearch.shortcut().unwrap().label(), "Esc");
+
+        let mut keys = Input::<KeyCode>::default();
+        keys.press(KeyCode::V);
+        assert!(!Action::Paste.shortcut().unwrap().pressed(&keys));
+        keys.press(KeyCode::ControlLeft);
+        assert!(Action::Paste.shortcut().unwrap().pressed(&keys));
+    }
+}
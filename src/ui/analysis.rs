@@ -0,0 +1,282 @@
+//! The analysis window, which shows the [evaluation](crate::analysis) of the position on the board
+//! while the engine is not playing.
+//!
+//! Each move of the engine's line can be clicked to play the line up to it on the board as a
+//! variation, which is left by returning to the position it was played from.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{EventReader, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::analysis::Analysis;
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor, ResetBoardEvent};
+use crate::engine::{Engine, MATE_SCORE};
+use crate::history::PositionHistory;
+
+use super::king_safety::{self, KingSafetyOverlay};
+use super::locale::Localisation;
+use super::pawn_structure::{self, PawnStructureOverlay};
+
+/// Returns the given evaluation from white's point of view as text, in pawns or as the number of
+/// moves to mate, which is negative when black mates.
+pub(super) fn evaluation_text(score: i32) -> String {
+    let plies = MATE_SCORE - score.abs();
+    if plies < 100 {
+        let moves = (plies + 1) / 2;
+        return match score > 0 {
+            true => format!("#{}", moves),
+            false => format!("#-{}", moves),
+        };
+    }
+    format!("{:+.2}", score as f32 / 100.0)
+}
+
+/// Returns the labels of the moves of the given line, played from the given move number, with the
+/// number before each of white's moves and before the first move.
+pub(super) fn line_labels(line: &[Move], move_number: i32) -> Vec<String> {
+    let mut number = move_number;
+    line.iter()
+        .enumerate()
+        .map(|(index, piece_move)| {
+            let san = piece_move.as_algebraic();
+            let label = match (piece_move.piece_color(), index) {
+                (PieceColor::White, _) => format!("{}. {}", number, san),
+                (PieceColor::Black, 0) => format!("{}... {}", number, san),
+                (PieceColor::Black, _) => san,
+            };
+            if *piece_move.piece_color() == PieceColor::Black {
+                number += 1;
+            }
+            label
+        })
+        .collect()
+}
+
+/// The number of moves of each candidate's continuation shown.
+const CONTINUATION_MOVES: usize = 4;
+
+/// Draws the table comparing the best candidate moves of the position, with a button to play each
+/// of them.
+fn candidates_ui(
+    ui: &mut egui::Ui,
+    analysis: &mut Analysis,
+    board: &ChessBoard,
+    commands: &mut GameCommands,
+    locale: &Localisation,
+) {
+    ui.collapsing(locale.get("candidate-moves"), |ui| {
+        ui.checkbox(
+            &mut analysis.compare_candidates,
+            locale.get("compare-candidates"),
+        );
+        if !analysis.compare_candidates {
+            return;
+        }
+        ui.add(
+            egui::Slider::new(&mut analysis.candidate_count, 1..=8)
+                .text(locale.get("candidate-count")),
+        );
+        if analysis.candidates().is_empty() {
+            ui.label(locale.get("analysis-searching"));
+            return;
+        }
+        // The continuation starts with the reply, which is the next move's when black moves
+        let reply_number = match board.active_color() {
+            Some(PieceColor::Black) => *board.move_number() + 1,
+            _ => *board.move_number(),
+        };
+        let mut played = None;
+        egui::Grid::new("candidate_moves")
+            .striped(true)
+            .show(ui, |ui| {
+                for key in [
+                    "candidate-move",
+                    "candidate-evaluation",
+                    "candidate-depth",
+                    "candidate-continuation",
+                    "candidate-book",
+                ] {
+                    ui.strong(locale.get(key));
+                }
+                ui.end_row();
+                for candidate in analysis.candidates() {
+                    if ui
+                        .button(locale.san(&candidate.piece_move.as_algebraic()))
+                        .on_hover_text(locale.get("play-candidate-hint"))
+                        .clicked()
+                    {
+                        played = Some(candidate.piece_move);
+                    }
+                    ui.label(evaluation_text(candidate.score));
+                    ui.label(candidate.depth.to_string());
+                    let continuation = &candidate.continuation
+                        [..CONTINUATION_MOVES.min(candidate.continuation.len())];
+                    let labels: Vec<String> = line_labels(continuation, reply_number)
+                        .iter()
+                        .map(|label| locale.san(label))
+                        .collect();
+                    ui.label(labels.join(" "));
+                    ui.label(if candidate.from_book { "✔" } else { "" });
+                    ui.end_row();
+                }
+            });
+        if let Some(piece_move) = played {
+            commands.push(GameCommand::MakeMove(piece_move));
+        }
+    });
+}
+
+/// The state of the analysis window.
+#[derive(Resource, Debug, Default)]
+pub(super) struct AnalysisWindowState {
+    /// The position a line was played from, while its variation is on the board.
+    variation_start: Option<ResetBoardEvent>,
+}
+
+/// The board the analysis is of, the history a variation is played from and the resets which
+/// leave it.
+#[derive(SystemParam)]
+pub(super) struct Variation<'w, 's> {
+    state: ResMut<'w, AnalysisWindowState>,
+    board: Res<'w, ChessBoard>,
+    history: Res<'w, PositionHistory>,
+    reset_events: EventReader<'w, 's, ResetBoardEvent>,
+}
+
+/// The overlays shown on the board alongside the analysis.
+#[derive(SystemParam)]
+pub(super) struct AnalysisOverlays<'w> {
+    pawn_overlay: ResMut<'w, PawnStructureOverlay>,
+    king_overlay: ResMut<'w, KingSafetyOverlay>,
+}
+
+/// Shows the analysis window, collapsed until it is opened.
+pub(super) fn analysis_window(
+    mut contexts: EguiContexts,
+    variation: Variation,
+    mut analysis: ResMut<Analysis>,
+    engine: Res<Engine>,
+    mut commands: ResMut<GameCommands>,
+    locale: Res<Localisation>,
+    overlays: AnalysisOverlays,
+) {
+    let Variation {
+        mut state,
+        board,
+        history,
+        mut reset_events,
+    } = variation;
+    let AnalysisOverlays {
+        mut pawn_overlay,
+        mut king_overlay,
+    } = overlays;
+    // Any reset leaves the variation, whether back to where it started or for another game
+    if reset_events.iter().last().is_some() {
+        state.variation_start = None;
+    }
+    egui::Window::new(locale.get("analysis"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut analysis.enabled, locale.get("analysis-enabled"));
+            if engine.color().is_some() {
+                ui.label(locale.get("analysis-engine-playing"));
+                return;
+            }
+            pawn_structure::overlay_ui(ui, &mut pawn_overlay, &locale);
+            king_safety::overlay_ui(ui, &mut king_overlay, &locale);
+            match analysis.current() {
+                Some(evaluation) => {
+                    ui.label(locale.format(
+                        "analysis-evaluation",
+                        &[
+                            ("evaluation", &evaluation_text(evaluation.score)),
+                            ("depth", &evaluation.depth),
+                        ],
+                    ));
+                    if let Some(best_move) = evaluation.best_move() {
+                        ui.label(locale.format(
+                            "analysis-best-move",
+                            &[("move", &locale.san(&best_move.as_algebraic()))],
+                        ));
+                    }
+                    let labels = line_labels(&evaluation.line, *board.move_number());
+                    let mut clicked = None;
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(locale.get("analysis-line"));
+                        for (index, label) in labels.iter().enumerate() {
+                            if ui
+                                .small_button(locale.san(label))
+                                .on_hover_text(locale.get("analysis-line-hint"))
+                                .clicked()
+                            {
+                                clicked = Some(index);
+                            }
+                        }
+                    });
+                    if let Some(index) = clicked {
+                        if state.variation_start.is_none() && history.len() > 0 {
+                            state.variation_start = Some(history.reset_event(history.len() - 1));
+                        }
+                        for piece_move in &evaluation.line[..=index] {
+                            commands.push(GameCommand::MakeMove(*piece_move));
+                        }
+                    }
+                }
+                None if analysis.is_searching() => {
+                    ui.horizontal(|ui| {
+                        ui.label(locale.get("analysis-searching"));
+                        if ui
+                            .button(locale.get("stop-search"))
+                            .on_hover_text(locale.get("stop-search-hint"))
+                            .clicked()
+                        {
+                            analysis.cancel_search();
+                        }
+                    });
+                }
+                None => {}
+            }
+            if let Some(start) = &state.variation_start {
+                if ui.button(locale.get("leave-variation")).clicked() {
+                    commands.push(GameCommand::LoadGame(Box::new(start.clone())));
+                }
+            }
+            candidates_ui(ui, &mut analysis, &board, &mut commands, &locale);
+            ui.label(locale.format(
+                "analysis-cached",
+                &[
+                    ("cached", &analysis.cache.len()),
+                    ("capacity", &analysis.cache.capacity()),
+                ],
+            ));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_evaluation_text() {
+        assert_eq!(evaluation_text(35), "+0.35");
+        assert_eq!(evaluation_text(-120), "-1.20");
+        assert_eq!(evaluation_text(MATE_SCORE - 1), "#1");
+        assert_eq!(evaluation_text(-MATE_SCORE + 4), "#-2");
+    }
+
+    #[test]
+    fn test_line_labels() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut line = Vec::new();
+        for text in ["e4", "e5", "Nf3"] {
+            let piece_move = Move::from_algebraic(text, &board).unwrap();
+            board.apply_move(&piece_move);
+            line.push(piece_move);
+        }
+        assert_eq!(line_labels(&line, 1), ["1. e4", "e5", "2. Nf3"]);
+        assert_eq!(line_labels(&line[1..], 1), ["1... e5", "2. Nf3"]);
+    }
+}
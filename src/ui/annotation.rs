@@ -0,0 +1,173 @@
+//! The annotation layer, which classifies moves by quality and shows the classification of the
+//! selected move as a badge on its destination square.
+
+use std::collections::HashMap;
+
+use bevy::prelude::{
+    default, Color, Commands, Component, DespawnRecursiveExt, Entity, EventReader, Local, Query,
+    Res, ResMut, Resource, Transform, With,
+};
+use bevy::text::{Text, Text2dBundle, TextStyle};
+use bevy_egui::egui;
+use strum_macros::EnumIter;
+
+use crate::chess_board::{ChessBoard, ResetBoardEvent};
+use crate::review::Classification;
+
+use super::board::BoardProperties;
+use super::move_list::MoveListState;
+
+/// The size of the move quality badges.
+const BADGE_FONT_SIZE: f32 = 28.;
+
+/// The quality of a move, shown using the standard annotation glyphs.
+#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq)]
+pub(super) enum MoveQuality {
+    Brilliant,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveQuality {
+    pub(super) fn glyph(&self) -> &'static str {
+        match self {
+            MoveQuality::Brilliant => "!!",
+            MoveQuality::Good => "!",
+            MoveQuality::Inaccuracy => "?!",
+            MoveQuality::Mistake => "?",
+            MoveQuality::Blunder => "??",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            MoveQuality::Brilliant => Color::CYAN,
+            MoveQuality::Good => Color::GREEN,
+            MoveQuality::Inaccuracy => Color::GOLD,
+            MoveQuality::Mistake => Color::ORANGE,
+            MoveQuality::Blunder => Color::RED,
+        }
+    }
+
+    /// Returns whether the move was an inaccuracy, a mistake or a blunder.
+    pub(super) fn is_mistake(&self) -> bool {
+        matches!(
+            self,
+            MoveQuality::Inaccuracy | MoveQuality::Mistake | MoveQuality::Blunder
+        )
+    }
+
+    pub(super) fn egui_color(&self) -> egui::Color32 {
+        let [r, g, b, _] = self.color().as_rgba_u8();
+        egui::Color32::from_rgb(r, g, b)
+    }
+}
+
+impl From<Classification> for MoveQuality {
+    fn from(classification: Classification) -> Self {
+        match classification {
+            Classification::Inaccuracy => MoveQuality::Inaccuracy,
+            Classification::Mistake => MoveQuality::Mistake,
+            Classification::Blunder => MoveQuality::Blunder,
+        }
+    }
+}
+
+/// The quality of each past move which has been classified, by its index in the past moves.
+#[derive(Resource, Debug, Default)]
+pub(super) struct MoveAnnotations(HashMap<usize, MoveQuality>);
+
+impl MoveAnnotations {
+    pub(super) fn get(&self, index: usize) -> Option<MoveQuality> {
+        self.0.get(&index).copied()
+    }
+
+    /// Sets or clears the quality of the move with the given index.
+    pub(super) fn set(&mut self, index: usize, quality: Option<MoveQuality>) {
+        match quality {
+            Some(quality) => self.0.insert(index, quality),
+            None => self.0.remove(&index),
+        };
+    }
+}
+
+/// Marks the badge entity shown on the board.
+#[derive(Component)]
+pub(super) struct AnnotationBadge;
+
+/// Clears the annotations when the board is reset, as they describe the previous game.
+/// Annotations of the moves leading to the new position are kept, so jumping back to an earlier
+/// position keeps the annotations up to it.
+pub(super) fn clear_annotations(
+    mut events: EventReader<ResetBoardEvent>,
+    mut annotations: ResMut<MoveAnnotations>,
+) {
+    for event in events.iter() {
+        let kept_moves = event.past_moves().len();
+        annotations.0.retain(|index, _| *index < kept_moves);
+    }
+}
+
+/// Shows the quality of the selected move as a badge in the corner of its destination square.
+pub(super) fn annotation_badges(
+    annotations: Res<MoveAnnotations>,
+    move_list_state: Res<MoveListState>,
+    board: Res<ChessBoard>,
+    properties: Res<BoardProperties>,
+    badges: Query<Entity, With<AnnotationBadge>>,
+    mut commands: Commands,
+    mut shown: Local<Option<(usize, MoveQuality)>>,
+) {
+    let badge = move_list_state.selected().and_then(|index| {
+        annotations
+            .get(index)
+            .filter(|_| index < board.past_moves().len())
+            .map(|quality| (index, quality))
+    });
+    if badge == *shown {
+        return;
+    }
+    *shown = badge;
+    for entity in badges.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some((index, quality)) = badge {
+        let (x, y) = properties.position_to_transform(board.past_moves()[index].to());
+        let offset = properties.square_size() / 3.;
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    quality.glyph(),
+                    TextStyle {
+                        font_size: BADGE_FONT_SIZE,
+                        color: quality.color(),
+                        ..default()
+                    },
+                ),
+                transform: Transform::from_xyz(x + offset, y + offset, 2.),
+                ..default()
+            },
+            AnnotationBadge,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_annotations() {
+        let mut annotations = MoveAnnotations::default();
+        assert_eq!(annotations.get(3), None);
+
+        annotations.set(3, Some(MoveQuality::Inaccuracy));
+        assert_eq!(annotations.get(3), Some(MoveQuality::Inaccuracy));
+        assert_eq!(annotations.get(3).unwrap().glyph(), "?!");
+
+        annotations.set(3, None);
+        assert_eq!(annotations.get(3), None);
+    }
+}
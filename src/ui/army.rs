@@ -0,0 +1,78 @@
+//! The army builder window, where each player chooses the [army](crate::army) they start with
+//! within the material budget, and the game is started from the position the armies make.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::army::{position, Army, ARMY_PIECES, BUDGET};
+use crate::chess_board::{GameCommand, GameCommands, PieceColor, ResetBoardEvent};
+
+use super::locale::Localisation;
+
+/// The most pieces of one type an army can have, which is as many as fit on the back rank.
+const MAX_COUNT: u8 = 7;
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct ArmyBuilder {
+    /// The armies of white and black.
+    armies: [Army; 2],
+}
+
+/// Draws the choice of the given player's army, with its value against the budget.
+fn army_ui(ui: &mut egui::Ui, color: PieceColor, army: &mut Army, locale: &Localisation) {
+    ui.vertical(|ui| {
+        ui.strong(locale.color(&color));
+        for piece_type in &ARMY_PIECES {
+            let mut count = army.count(piece_type);
+            ui.add(
+                egui::DragValue::new(&mut count)
+                    .clamp_range(0..=MAX_COUNT)
+                    .prefix(format!("{}: ", locale.piece(piece_type))),
+            );
+            army.set_count(piece_type, count);
+        }
+        let value = army.value();
+        let text = locale.format("army-value", &[("value", &value), ("budget", &BUDGET)]);
+        match army.validate() {
+            Ok(()) => ui.label(text),
+            Err(error) => ui
+                .colored_label(egui::Color32::from_rgb(230, 140, 0), text)
+                .on_hover_text(locale.get(error.key())),
+        };
+    });
+}
+
+/// Shows the army builder window, collapsed until it is opened.
+pub(super) fn army_window(
+    mut contexts: EguiContexts,
+    mut builder: ResMut<ArmyBuilder>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+) {
+    egui::Window::new(locale.get("army-builder"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let [white, black] = &mut builder.armies;
+            ui.horizontal(|ui| {
+                army_ui(ui, PieceColor::White, white, &locale);
+                army_ui(ui, PieceColor::Black, black, &locale);
+            });
+            let start = position(white, black);
+            if let Err(error) = start {
+                ui.label(locale.get(error.key()));
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(start.is_ok(), egui::Button::new(locale.get("army-start")))
+                    .clicked()
+                {
+                    if let Ok(fen) = start {
+                        commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+                    }
+                }
+                if ui.button(locale.get("army-standard")).clicked() {
+                    builder.armies = Default::default();
+                }
+            });
+        });
+}
@@ -0,0 +1,329 @@
+//! Audio settings and the sound packs described by the sound pack manifest.
+//!
+//! With positional audio on, the sound of a move pans towards the side of the board its
+//! destination square is on, as the board is seen, and is quieter the further the square is from
+//! the near edge of the board.
+
+use std::collections::HashMap;
+
+use bevy::audio::SpatialSettings;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Res, ResMut, Resource, Transform, Vec3};
+use bevy_egui::egui;
+use serde::Deserialize;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::chess_board::{BoardPosition, BoardSize};
+
+use super::locale::Localisation;
+use super::voice::VoicePacks;
+
+/// The manifest listing the available sound packs.
+const SOUND_PACK_MANIFEST: &str = include_str!("../../assets/sounds/packs.ron");
+
+/// The distance between the listener's ears, which the sounds are placed between so they pan
+/// without being made quieter by their distance.
+const EAR_GAP: f32 = 1.0;
+
+/// The share of a move's volume lost on the far edge of the board.
+const FAR_EDGE_FALLOFF: f32 = 0.4;
+
+/// The game events which can play a sound.
+#[derive(Clone, Copy, Debug, EnumIter, PartialEq, Eq, Hash, Deserialize)]
+pub(super) enum SoundEvent {
+    Move,
+    Capture,
+    Castle,
+    LowTime,
+}
+
+impl SoundEvent {
+    /// Returns the key of the label of the event.
+    fn label(&self) -> &'static str {
+        match self {
+            SoundEvent::Move => "sound-move",
+            SoundEvent::Capture => "sound-capture",
+            SoundEvent::Castle => "sound-castle",
+            SoundEvent::LowTime => "sound-low-time",
+        }
+    }
+}
+
+/// A named set of sounds, one for each [SoundEvent].
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct SoundPack {
+    name: String,
+    sounds: HashMap<SoundEvent, String>,
+}
+
+impl SoundPack {
+    /// Returns the asset path of the sound for the given event, falling back to the move sound.
+    fn sound(&self, event: SoundEvent) -> Option<&str> {
+        self.sounds
+            .get(&event)
+            .or_else(|| self.sounds.get(&SoundEvent::Move))
+            .map(|path| path.as_str())
+    }
+}
+
+/// The sound packs loaded from the sound pack manifest.
+#[derive(Resource, Debug)]
+pub(super) struct SoundPacks(Vec<SoundPack>);
+
+impl SoundPacks {
+    fn from_manifest(manifest: &str) -> Self {
+        SoundPacks(ron::from_str(manifest).expect("Invalid sound pack manifest."))
+    }
+}
+
+impl Default for SoundPacks {
+    fn default() -> Self {
+        SoundPacks::from_manifest(SOUND_PACK_MANIFEST)
+    }
+}
+
+/// The user's audio settings.
+#[derive(Resource, Debug)]
+pub(super) struct AudioSettings {
+    master_volume: f32,
+    event_volumes: HashMap<SoundEvent, f32>,
+    muted: bool,
+    sound_pack: usize,
+    /// Whether the sounds of moves are placed at their destination squares.
+    positional: bool,
+    /// Whether the [music](super::music) for the phase of the game plays.
+    music: bool,
+    music_volume: f32,
+    /// Whether moves are [announced](super::voice) by the voice pack of the selected language.
+    voice: bool,
+    voice_volume: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master_volume: 1.0,
+            event_volumes: SoundEvent::iter().map(|event| (event, 1.0)).collect(),
+            muted: false,
+            sound_pack: 0,
+            positional: false,
+            music: false,
+            music_volume: 0.5,
+            voice: false,
+            voice_volume: 1.0,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// Returns the asset path and volume of the sound to play for the given event, if any.
+    pub(super) fn sound<'a>(
+        &self,
+        event: SoundEvent,
+        packs: &'a SoundPacks,
+    ) -> Option<(&'a str, f32)> {
+        let volume = self.master_volume * self.event_volumes.get(&event).unwrap_or(&1.0);
+        if self.muted || volume <= 0.0 {
+            return None;
+        }
+        packs
+            .0
+            .get(self.sound_pack)
+            .and_then(|pack| pack.sound(event))
+            .map(|path| (path, volume))
+    }
+
+    /// Returns the volume to play the music at, or None if it should not play.
+    pub(super) fn music_volume(&self) -> Option<f32> {
+        let volume = self.master_volume * self.music_volume;
+        (self.music && !self.muted && volume > 0.0).then_some(volume)
+    }
+
+    /// Returns the volume to announce moves at, or None if they should not be announced.
+    pub(super) fn voice_volume(&self) -> Option<f32> {
+        let volume = self.master_volume * self.voice_volume;
+        (self.voice && !self.muted && volume > 0.0).then_some(volume)
+    }
+
+    /// Returns how far left or right the sound of a move to the given square is, from -1 to 1,
+    /// and the share of its volume it is played at, or None if positional audio is off.
+    pub(super) fn placement(
+        &self,
+        position: &BoardPosition,
+        size: &BoardSize,
+        flipped: bool,
+    ) -> Option<(f32, f32)> {
+        if !self.positional {
+            return None;
+        }
+        // How far across and up the board the square is as it is seen, from 0 to 1
+        let fraction = |index: usize, count: usize| index as f32 / (count.max(2) - 1) as f32;
+        let mut across = fraction(*position.file(), size.files());
+        // The first rank is at the bottom, and rank indices start from the eighth
+        let mut up = 1.0 - fraction(*position.rank(), size.ranks());
+        if flipped {
+            across = 1.0 - across;
+            up = 1.0 - up;
+        }
+        Some((across * 2.0 - 1.0, 1.0 - FAR_EDGE_FALLOFF * up))
+    }
+}
+
+/// Returns the spatial settings of a sound the given distance left or right, from -1 to 1.
+pub(super) fn spatial_settings(pan: f32) -> SpatialSettings {
+    let emitter = Vec3::new(pan * EAR_GAP / 2.0, 0.0, 0.0);
+    SpatialSettings::new(Transform::IDENTITY, EAR_GAP, emitter)
+}
+
+/// The audio settings and the sound and voice packs they choose from.
+#[derive(SystemParam)]
+pub(super) struct Sound<'w> {
+    settings: ResMut<'w, AudioSettings>,
+    packs: Res<'w, SoundPacks>,
+    voices: Res<'w, VoicePacks>,
+}
+
+/// Draws the audio settings controls.
+pub(super) fn settings_ui(ui: &mut egui::Ui, sound: &mut Sound, locale: &Localisation) {
+    let Sound {
+        settings,
+        packs,
+        voices,
+    } = sound;
+    ui.collapsing(locale.get("sound"), |ui| {
+        ui.checkbox(&mut settings.muted, locale.get("mute"));
+        ui.checkbox(&mut settings.positional, locale.get("positional-audio"))
+            .on_hover_text(locale.get("positional-audio-hint"));
+        ui.add(
+            egui::Slider::new(&mut settings.master_volume, 0.0..=1.0)
+                .text(locale.get("master-volume")),
+        );
+        for event in SoundEvent::iter() {
+            let volume = settings.event_volumes.entry(event).or_insert(1.0);
+            ui.add(egui::Slider::new(volume, 0.0..=1.0).text(locale.get(event.label())));
+        }
+        ui.checkbox(&mut settings.music, locale.get("music"))
+            .on_hover_text(locale.get("music-hint"));
+        ui.add_enabled(
+            settings.music,
+            egui::Slider::new(&mut settings.music_volume, 0.0..=1.0)
+                .text(locale.get("music-volume")),
+        );
+        let voice = voices.pack_name(locale.code());
+        ui.add_enabled(
+            voice.is_some(),
+            egui::Checkbox::new(&mut settings.voice, locale.get("announce-moves")),
+        )
+        .on_hover_text(match voice {
+            Some(name) => locale.format("announce-moves-hint", &[("voice", &name)]),
+            None => locale.get("no-voice-pack").to_string(),
+        })
+        .on_disabled_hover_text(locale.get("no-voice-pack"));
+        ui.add_enabled(
+            settings.voice && voice.is_some(),
+            egui::Slider::new(&mut settings.voice_volume, 0.0..=1.0)
+                .text(locale.get("voice-volume")),
+        );
+        let selected = packs
+            .0
+            .get(settings.sound_pack)
+            .map(|pack| pack.name.as_str())
+            .unwrap_or_default();
+        egui::ComboBox::from_label(locale.get("sound-pack"))
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (index, pack) in packs.0.iter().enumerate() {
+                    ui.selectable_value(&mut settings.sound_pack, index, &pack.name);
+                }
+            });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_MANIFEST: &str = r#"[
+        (name: "First", sounds: {Move: "move.wav", Capture: "capture.wav"}),
+        (name: "Second", sounds: {Move: "other_move.wav"}),
+    ]"#;
+
+    #[test]
+    fn test_sound_packs_default_manifest() {
+        let packs = SoundPacks::default();
+
+        assert!(!packs.0.is_empty());
+        for pack in &packs.0 {
+            assert!(pack.sound(SoundEvent::Move).is_some());
+        }
+    }
+
+    #[test]
+    fn test_audio_settings_sound() {
+        let packs = SoundPacks::from_manifest(TEST_MANIFEST);
+        let mut settings = AudioSettings::default();
+
+        assert_eq!(
+            settings.sound(SoundEvent::Capture, &packs),
+            Some(("capture.wav", 1.0))
+        );
+        // Events without a sound fall back to the move sound
+        assert_eq!(
+            settings.sound(SoundEvent::Castle, &packs),
+            Some(("move.wav", 1.0))
+        );
+
+        // Volumes are combined
+        settings.master_volume = 0.5;
+        settings.event_volumes.insert(SoundEvent::Move, 0.5);
+        assert_eq!(
+            settings.sound(SoundEvent::Move, &packs),
+            Some(("move.wav", 0.25))
+        );
+
+        // Sound packs can be switched
+        settings.sound_pack = 1;
+        assert_eq!(
+            settings.sound(SoundEvent::Capture, &packs),
+            Some(("other_move.wav", 0.5))
+        );
+    }
+
+    #[test]
+    fn test_audio_settings_placement() {
+        let mut settings = AudioSettings::default();
+        let size = BoardSize::default();
+        let square = |square| BoardPosition::from_algebraic(square).unwrap();
+        assert_eq!(settings.placement(&square("a1"), &size, false), None);
+
+        settings.positional = true;
+        assert_eq!(
+            settings.placement(&square("a1"), &size, false),
+            Some((-1.0, 1.0))
+        );
+        assert_eq!(
+            settings.placement(&square("h8"), &size, false),
+            Some((1.0, 1.0 - FAR_EDGE_FALLOFF))
+        );
+        // Seen from black's side the a-file is on the right, and the eighth rank near
+        assert_eq!(
+            settings.placement(&square("a8"), &size, true),
+            Some((1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn test_audio_settings_sound_muted() {
+        let packs = SoundPacks::from_manifest(TEST_MANIFEST);
+        let mut settings = AudioSettings {
+            muted: true,
+            ..Default::default()
+        };
+        assert_eq!(settings.sound(SoundEvent::Move, &packs), None);
+
+        settings.muted = false;
+        settings.event_volumes.insert(SoundEvent::Move, 0.0);
+        assert_eq!(settings.sound(SoundEvent::Move, &packs), None);
+    }
+}
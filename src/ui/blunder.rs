@@ -0,0 +1,112 @@
+//! The beginner assist which warns before a move that loses material, asking the player to
+//! confirm it. The material lost is found by [static exchange evaluation](crate::exchange).
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, ChessBoardConfig, GameCommand, GameCommands, Variant};
+use crate::exchange::{material_lost, Target};
+
+use super::locale::Localisation;
+
+/// Whether moves which lose material are confirmed first, and the move awaiting confirmation.
+#[derive(Resource, Debug, Default)]
+pub(super) struct BlunderCheck {
+    pub(super) enabled: bool,
+    pending: Option<(Move, Target)>,
+}
+
+impl BlunderCheck {
+    /// Returns whether the given move can be made straight away, or otherwise holds it back until
+    /// the player confirms it. Material is only checked in [Variant::Standard], as in the other
+    /// variants losing a piece can be the point.
+    pub(super) fn check(&mut self, piece_move: Move, board: &ChessBoard) -> bool {
+        if !self.enabled || *board.variant() != Variant::Standard {
+            return true;
+        }
+        match material_lost(board, &piece_move) {
+            Some(target) => {
+                self.pending = Some((piece_move, target));
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+/// Asks the player to confirm a move which loses material, making it if they do. The move is
+/// dropped if the position changes before it is confirmed.
+pub(super) fn blunder_window(
+    mut contexts: EguiContexts,
+    mut blunder_check: ResMut<BlunderCheck>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+    config: Res<ChessBoardConfig>,
+) {
+    let Some((piece_move, (piece_type, position))) = blunder_check.pending else {
+        return;
+    };
+    if !config.allows(&board, &piece_move) {
+        blunder_check.pending = None;
+        return;
+    }
+    egui::Window::new(locale.get("blunder-warning"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(locale.format(
+                "blunder-loses",
+                &[
+                    ("piece", &locale.piece(&piece_type)),
+                    ("square", &position.to_algebraic()),
+                ],
+            ));
+            ui.horizontal(|ui| {
+                if ui.button(locale.get("move-anyway")).clicked() {
+                    commands.push(GameCommand::MakeMove(piece_move));
+                    blunder_check.pending = None;
+                }
+                if ui.button(locale.get("cancel")).clicked() {
+                    blunder_check.pending = None;
+                }
+            });
+        });
+}
+
+/// Shows the setting enabling the warning.
+pub(super) fn settings_ui(
+    ui: &mut egui::Ui,
+    blunder_check: &mut BlunderCheck,
+    locale: &Localisation,
+) {
+    ui.checkbox(&mut blunder_check.enabled, locale.get("warn-blunders"))
+        .on_hover_text(locale.get("warn-blunders-hint"));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::{BoardPosition, PieceType};
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_blunder_check() {
+        let board =
+            ChessBoard::with_position(&Fen::from_string("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1"));
+        let blunder = Move::from_algebraic("Qxd5", &board).unwrap();
+        let mut blunder_check = BlunderCheck::default();
+        assert!(blunder_check.check(blunder, &board));
+
+        blunder_check.enabled = true;
+        assert!(!blunder_check.check(blunder, &board));
+        assert_eq!(
+            blunder_check.pending,
+            Some((blunder, (PieceType::Queen, BoardPosition::new(3, 3))))
+        );
+        assert!(blunder_check.check(Move::from_algebraic("Qd2", &board).unwrap(), &board));
+    }
+}
@@ -1,115 +1,528 @@
-use bevy::ecs::system::Commands;
-use bevy::prelude::{Changed, Color, Query, Res, Resource, Vec2, With};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use bevy::diagnostic::Diagnostics;
+use bevy::ecs::system::{Commands, SystemParam};
+use bevy::prelude::{
+    Added, Camera, Changed, Color, DetectChanges, DetectChangesMut, Entity, EventWriter,
+    GlobalTransform, Query, Res, ResMut, Resource, Vec2, With,
+};
 use bevy::sprite::Sprite;
+use bevy::time::Time;
+use bevy::window::{RequestRedraw, Window};
 
-use crate::chess_board::r#move::Move;
-use crate::chess_board::{BoardPosition, ChessBoard, PieceColor};
+use crate::chess_board::{
+    BoardPosition, BoardSize, ChessBoard, LegalMoves, PieceColor, PieceType, Variant, BOARD_SIZE,
+};
+use crate::diagnostics::{self, HIGHLIGHTING};
 
 use super::piece::{Dragging, PieceTag};
+use super::premove::Premoves;
+use super::theme::{Highlight, Theme};
+use super::{cursor_world_position, MainCamera};
 
 mod square;
 
+/// The colors of the light and dark squares, unless a theme gives its own.
+const LIGHT_SQUARE_COLOR: Color = Color::WHITE;
+const DARK_SQUARE_COLOR: Color = Color::GRAY;
+
 #[derive(Resource)]
 pub(super) struct BoardProperties {
     color_white: Color,
     color_black: Color,
-    highlight_color_white: Color,
-    highlight_color_black: Color,
     center: Vec2,
     square_size: f32,
+    /// The size of the board the squares are drawn for.
+    size: BoardSize,
 }
 
 impl BoardProperties {
+    /// Returns the rank and file of the middle of the board, between squares on boards with an
+    /// even number of ranks or files.
+    fn middle(&self) -> (f32, f32) {
+        (
+            self.size.top_rank() as f32 + self.size.ranks() as f32 / 2.0,
+            self.size.files() as f32 / 2.0,
+        )
+    }
+
     pub(super) fn position_to_transform(&self, position: &BoardPosition) -> (f32, f32) {
-        let x = (*position.file() as f32 - 4.0) * self.square_size
+        let (middle_rank, middle_file) = self.middle();
+        let x = (*position.file() as f32 - middle_file) * self.square_size
             + self.center.x
             + self.square_size / 2.0;
-        let y = -1.0 * (*position.rank() as f32 - 4.0) * self.square_size + self.center.y
+        let y = -(*position.rank() as f32 - middle_rank) * self.square_size + self.center.y
             - self.square_size / 2.0;
         (x, y)
     }
 
-    pub(super) fn transform_to_position(&self, transform: &Vec2) -> Option<BoardPosition> {
+    pub(super) fn square_size(&self) -> f32 {
+        self.square_size
+    }
+
+    pub(super) fn center(&self) -> Vec2 {
+        self.center
+    }
+
+    pub(super) fn size(&self) -> &BoardSize {
+        &self.size
+    }
+
+    /// Returns the rank and file of the square at the given point, which may be off the board.
+    fn transform_to_square(&self, transform: &Vec2) -> (i32, i32) {
+        let (middle_rank, middle_file) = self.middle();
         let file = ((transform[0] - self.center.x - self.square_size / 2.0) / self.square_size
-            + 4.0)
+            + middle_file)
             .round() as i32;
-        let rank = (-1.0 * (transform[1] - self.center.y + self.square_size / 2.0)
-            / self.square_size
-            + 4.0)
+        let rank = (-(transform[1] - self.center.y + self.square_size / 2.0) / self.square_size
+            + middle_rank)
             .round() as i32;
-        if !(0..=7).contains(&rank) || !(0..=7).contains(&file) {
-            None
-        } else {
-            Some(BoardPosition::new(rank as usize, file as usize))
+        (rank, file)
+    }
+
+    /// Returns the position of the given rank and file, if it is on the board.
+    fn square_to_position(&self, rank: i32, file: i32) -> Option<BoardPosition> {
+        if !(0..BOARD_SIZE as i32).contains(&rank) || !(0..self.size.files() as i32).contains(&file)
+        {
+            return None;
         }
+        Some(BoardPosition::new(rank as usize, file as usize))
+            .filter(|position| self.size.contains(position))
+    }
+
+    pub(super) fn transform_to_position(&self, transform: &Vec2) -> Option<BoardPosition> {
+        let (rank, file) = self.transform_to_square(transform);
+        self.square_to_position(rank, file)
+    }
+
+    /// Returns the square on the first or last rank which the given point is just beyond, if it
+    /// is off the board within a square of that rank.
+    pub(super) fn position_past_edge(&self, transform: &Vec2) -> Option<BoardPosition> {
+        let (rank, file) = self.transform_to_square(transform);
+        if self.square_to_position(rank, file).is_some() {
+            return None;
+        }
+        [rank + 1, rank - 1]
+            .into_iter()
+            .find_map(|rank| self.square_to_position(rank, file))
     }
 
     fn position_to_color(&self, position: &BoardPosition) -> PieceColor {
-        if (position.rank() % 2 == 0) == (position.file() % 2 == 0) {
+        if position.rank().is_multiple_of(2) == position.file().is_multiple_of(2) {
             PieceColor::White
         } else {
             PieceColor::Black
         }
     }
+
+    /// Sets the colors of the light and dark squares, using the usual color for each which is None.
+    pub(super) fn set_square_colors(&mut self, light: Option<Color>, dark: Option<Color>) {
+        self.color_white = light.unwrap_or(LIGHT_SQUARE_COLOR);
+        self.color_black = dark.unwrap_or(DARK_SQUARE_COLOR);
+    }
+
+    fn square_color(&self, color: &PieceColor) -> Color {
+        match color {
+            PieceColor::White => self.color_white,
+            PieceColor::Black => self.color_black,
+        }
+    }
+}
+
+/// The time the cursor must rest over a piece before its moves are previewed.
+const HOVER_PREVIEW_DELAY: Duration = Duration::from_millis(300);
+
+/// The preview of the moves of the piece under the cursor.
+#[derive(Resource, Debug, Default)]
+pub(super) struct HoverPreview {
+    /// The square with a piece under the cursor, and how long the cursor has rested over it.
+    hovered: Option<(BoardPosition, Duration)>,
+    /// The square of the piece whose moves are previewed.
+    previewed: Option<BoardPosition>,
+}
+
+impl HoverPreview {
+    /// Updates the hovered square after the given time has passed, returning whether the
+    /// previewed square changed.
+    fn update(&mut self, square: Option<BoardPosition>, delta: Duration) -> bool {
+        self.hovered = match (self.hovered, square) {
+            (Some((hovered, time)), Some(square)) if hovered == square => {
+                Some((square, time + delta))
+            }
+            (_, square) => square.map(|square| (square, Duration::ZERO)),
+        };
+        let previewed = self
+            .hovered
+            .filter(|(_, time)| *time >= HOVER_PREVIEW_DELAY)
+            .map(|(square, _)| square);
+        let changed = previewed != self.previewed;
+        self.previewed = previewed;
+        changed
+    }
+
+    /// Returns the square of the piece whose moves are previewed.
+    pub(super) fn previewed(&self) -> Option<BoardPosition> {
+        self.previewed
+    }
+
+    /// Returns whether the cursor is resting over a piece whose moves are not yet previewed.
+    fn is_waiting(&self) -> bool {
+        self.hovered.is_some() && self.previewed.is_none()
+    }
 }
 
 impl Default for BoardProperties {
     fn default() -> Self {
         BoardProperties {
-            color_white: Color::WHITE,
-            color_black: Color::GRAY,
-            highlight_color_white: Color::AQUAMARINE,
-            highlight_color_black: Color::TEAL,
+            color_white: LIGHT_SQUARE_COLOR,
+            color_black: DARK_SQUARE_COLOR,
             center: Vec2::new(0., 0.),
             square_size: 80.,
+            size: BoardSize::default(),
         }
     }
 }
 
 pub(super) fn setup(mut commands: Commands, properties: Res<BoardProperties>) {
-    let mut squares = Vec::with_capacity(64);
-    for rank in 0..8 {
-        for file in 0..8 {
-            let position = BoardPosition::new(rank, file);
+    spawn_squares(&mut commands, &properties);
+}
+
+fn spawn_squares(commands: &mut Commands, properties: &BoardProperties) {
+    let squares: Vec<square::SquareBundle> = properties
+        .size
+        .positions()
+        .map(|position| {
             let color = properties.position_to_color(&position);
-            squares.push(square::SquareBundle::new(
-                position,
-                properties.square_size,
-                color,
-                &properties,
-            ));
+            square::SquareBundle::new(position, properties.square_size, color, properties)
+        })
+        .collect();
+    commands.spawn_batch(squares);
+}
+
+/// Draws the squares again when a position on a board of another size is set up, before its
+/// pieces are placed on them.
+pub(super) fn resize_board(
+    query: Query<Entity, With<square::Square>>,
+    mut commands: Commands,
+    board: Res<ChessBoard>,
+    mut properties: ResMut<BoardProperties>,
+) {
+    if properties.size == *board.size() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    properties.size = *board.size();
+    spawn_squares(&mut commands, &properties);
+}
+
+/// Tracks the piece the cursor has rested over, while no piece is being dragged, so its legal
+/// moves can be previewed.
+pub(super) fn hover_preview(
+    windows: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    piece_query: Query<(&BoardPosition, &Dragging), With<PieceTag>>,
+    properties: Res<BoardProperties>,
+    mut preview: ResMut<HoverPreview>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+    time: Res<Time>,
+) {
+    let (camera, camera_transform) = camera.single();
+    let dragging = piece_query.iter().any(|(_, dragging)| dragging.get());
+    let hovered = windows
+        .get_single()
+        .ok()
+        .and_then(|window| cursor_world_position(window, camera, camera_transform))
+        .and_then(|world_position| properties.transform_to_position(&world_position))
+        .filter(|square| !dragging && piece_query.iter().any(|(position, _)| position == square));
+    // The preview only counts as changed when the previewed square changes, so the squares are
+    // not recolored every frame
+    if preview
+        .bypass_change_detection()
+        .update(hovered, time.delta())
+    {
+        preview.set_changed();
+    }
+    // The app only updates on input, so keep it updating until the preview is shown
+    if preview.is_waiting() {
+        redraw_events.send(RequestRedraw);
+    }
+}
+
+/// Returns the highlights of the squares which have one. The check and the moves of the dragged
+/// or previewed piece are found once for the whole board rather than for each square, and each
+/// highlight replaces those set before it, so the most important are set last.
+fn square_highlights(
+    dragged: Option<&BoardPosition>,
+    board: &ChessBoard,
+    legal_moves: &LegalMoves,
+    preview: &HoverPreview,
+    premoves: &Premoves,
+) -> HashMap<BoardPosition, Highlight> {
+    let mut highlights = HashMap::new();
+    if let Some(piece_move) = board.past_moves().last() {
+        for position in [piece_move.from(), piece_move.to()] {
+            highlights.insert(*position, Highlight::LastMove);
         }
     }
-    commands.spawn_batch(squares);
+    if let Some(color) = board
+        .active_color()
+        .filter(|color| *board.variant() == Variant::Standard && board.in_check(color))
+    {
+        for position in board.size().positions().filter(|position| {
+            board.get_piece_type(position) == Some(PieceType::King)
+                && board.get_piece_color(position) == Some(color)
+        }) {
+            highlights.insert(position, Highlight::Check);
+        }
+    }
+    for position in board
+        .size()
+        .positions()
+        .filter(|position| premoves.is_queued(position))
+    {
+        highlights.insert(position, Highlight::Premove);
+    }
+    let moving = match dragged {
+        Some(position) => Some((*position, Highlight::ValidMove)),
+        None => preview
+            .previewed()
+            .map(|position| (position, Highlight::Preview)),
+    };
+    if let Some((from, highlight)) = moving {
+        for piece_move in legal_moves.moves_from(&from) {
+            highlights.insert(*piece_move.to(), highlight);
+        }
+    }
+    highlights
 }
 
-pub(super) fn highlight_valid_squares(
-    piece_query: Query<(&BoardPosition, &Dragging), (Changed<Dragging>, With<PieceTag>)>,
+/// The highlights the squares are colored with, kept so that only the squares whose highlight
+/// changes are recolored. Dragging a piece about a large board otherwise recolors every square
+/// each time the piece is picked up or put down.
+#[derive(Resource, Debug, Default)]
+pub(super) struct SquareHighlights(HashMap<BoardPosition, Highlight>);
+
+impl SquareHighlights {
+    /// Replaces the highlights with the given ones, returning the squares whose highlight
+    /// changed.
+    fn update(&mut self, highlights: HashMap<BoardPosition, Highlight>) -> HashSet<BoardPosition> {
+        let changed = self
+            .0
+            .keys()
+            .chain(highlights.keys())
+            .filter(|position| self.0.get(position) != highlights.get(position))
+            .copied()
+            .collect();
+        self.0 = highlights;
+        changed
+    }
+
+    fn get(&self, position: &BoardPosition) -> Option<Highlight> {
+        self.0.get(position).copied()
+    }
+}
+
+/// The pieces, and those picked up or put down since the squares were last colored.
+#[derive(SystemParam)]
+pub(super) struct DraggedPieces<'w, 's> {
+    piece_query: Query<'w, 's, (&'static BoardPosition, &'static Dragging), With<PieceTag>>,
+    changed_pieces: Query<'w, 's, (), (Changed<Dragging>, With<PieceTag>)>,
+}
+
+/// What the squares are highlighted for: the board, its legal moves, the previewed move and the
+/// premoves.
+#[derive(SystemParam)]
+pub(super) struct HighlightSources<'w> {
+    board: Res<'w, ChessBoard>,
+    legal_moves: Res<'w, LegalMoves>,
+    preview: Res<'w, HoverPreview>,
+    premoves: Res<'w, Premoves>,
+}
+
+/// The colors of the squares, from the board's own and the theme's.
+#[derive(SystemParam)]
+pub(super) struct SquareColors<'w> {
+    properties: Res<'w, BoardProperties>,
+    theme: Res<'w, Theme>,
+}
+
+/// Colors each square with its highlight in the theme's colors, or its own color if it has none.
+/// Only the squares whose highlight changed are recolored, unless the colors themselves changed
+/// or the squares were drawn again.
+pub(super) fn color_squares(
+    pieces: DraggedPieces,
+    added_squares: Query<(), Added<square::Square>>,
     mut square_query: Query<
         (&mut Sprite, &BoardPosition, &square::SquareColor),
         With<square::Square>,
     >,
-    board: Res<ChessBoard>,
-    properties: Res<BoardProperties>,
+    sources: HighlightSources,
+    colors: SquareColors,
+    mut highlights: ResMut<SquareHighlights>,
+    mut diagnostics: Diagnostics,
 ) {
-    let valid_moves = board.get_valid_moves(board.active_color(), &true);
-    for (piece_position, dragging) in piece_query.iter() {
+    let DraggedPieces {
+        piece_query,
+        changed_pieces,
+    } = pieces;
+    let HighlightSources {
+        board,
+        legal_moves,
+        preview,
+        premoves,
+    } = sources;
+    let SquareColors { properties, theme } = colors;
+    let recolor_all = theme.is_changed() || properties.is_changed() || !added_squares.is_empty();
+    if !recolor_all
+        && changed_pieces.is_empty()
+        && !board.is_changed()
+        && !legal_moves.is_changed()
+        && !preview.is_changed()
+        && !premoves.is_changed()
+    {
+        return;
+    }
+    let dragged = piece_query
+        .iter()
+        .find(|(_, dragging)| dragging.get())
+        .map(|(position, _)| position);
+    diagnostics::measure(&mut diagnostics, HIGHLIGHTING, || {
+        let changed = highlights.update(square_highlights(
+            dragged,
+            &board,
+            &legal_moves,
+            &preview,
+            &premoves,
+        ));
         for (mut sprite, position, color) in square_query.iter_mut() {
-            // Highlight the square if it's valid
-            let sprite_color = if dragging.get()
-                && valid_moves.contains(&Move::from_board(*piece_position, *position, &board))
-            {
-                match color.get() {
-                    PieceColor::White => properties.highlight_color_white,
-                    PieceColor::Black => properties.highlight_color_black,
-                }
-            } else {
-                match color.get() {
-                    PieceColor::White => properties.color_white,
-                    PieceColor::Black => properties.color_black,
-                }
+            if !recolor_all && !changed.contains(position) {
+                continue;
+            }
+            sprite.color = match highlights.get(position) {
+                Some(highlight) => theme.highlight_color(highlight, &color.get()),
+                None => properties.square_color(&color.get()),
             };
-            sprite.color = sprite_color;
         }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_hover_preview_update() {
+        let mut preview = HoverPreview::default();
+        let square = BoardPosition::new(6, 4);
+
+        assert!(!preview.update(Some(square), Duration::from_millis(100)));
+        assert!(preview.is_waiting());
+        assert!(!preview.update(Some(square), Duration::from_millis(200)));
+        // The preview shows once the cursor has rested for the delay
+        assert!(preview.update(Some(square), Duration::from_millis(100)));
+        assert_eq!(preview.previewed, Some(square));
+        assert!(!preview.is_waiting());
+
+        // Moving to another square hides the preview and restarts the delay
+        assert!(preview.update(Some(BoardPosition::new(6, 3)), Duration::from_millis(500)));
+        assert_eq!(preview.previewed, None);
+        assert!(preview.is_waiting());
+
+        preview.update(None, Duration::from_millis(500));
+        assert!(!preview.is_waiting());
+    }
+
+    #[test]
+    fn test_square_highlights_changes() {
+        let fen =
+            Fen::from_string("rnbqkbnrrr/pppppppppp/10/10/10/10/PPPPPPPPPP/RNBQKBNRRR w - - 0 1");
+        let board = ChessBoard::with_position(&fen);
+        let legal_moves = LegalMoves::of(&board);
+        let (preview, premoves) = (HoverPreview::default(), Premoves::default());
+        let mut highlights = SquareHighlights::default();
+        let squares = board.size().positions().count();
+        let white_pieces: Vec<BoardPosition> = board
+            .size()
+            .positions()
+            .filter(|position| board.get_piece_color(position) == Some(PieceColor::White))
+            .collect();
+
+        // Picking up and putting down pieces in quick succession only recolors the moves of the
+        // pieces dropped and picked up, never the whole board
+        let mut recolored = 0;
+        let mut changes = 0;
+        let mut previous: Option<BoardPosition> = None;
+        for _ in 0..50 {
+            for dragged in white_pieces.iter().map(Some).chain([None]) {
+                let moves = |from: Option<&BoardPosition>| {
+                    from.map_or(0, |from| legal_moves.moves_from(from).count())
+                };
+                let changed = highlights.update(square_highlights(
+                    dragged,
+                    &board,
+                    &legal_moves,
+                    &preview,
+                    &premoves,
+                ));
+                assert!(changed.len() <= moves(previous.as_ref()) + moves(dragged));
+                recolored += changed.len();
+                changes += 1;
+                previous = dragged.copied();
+            }
+        }
+        assert!(recolored * 10 < changes * squares);
+
+        // Nothing changes when the same piece stays picked up
+        let knight = BoardPosition::from_algebraic("b1").unwrap();
+        highlights.update(square_highlights(
+            Some(&knight),
+            &board,
+            &legal_moves,
+            &preview,
+            &premoves,
+        ));
+        let changed = highlights.update(square_highlights(
+            Some(&knight),
+            &board,
+            &legal_moves,
+            &preview,
+            &premoves,
+        ));
+        assert!(changed.is_empty());
+        assert_eq!(
+            highlights.get(&BoardPosition::from_algebraic("c3").unwrap()),
+            Some(Highlight::ValidMove)
+        );
+    }
+
+    #[test]
+    fn test_position_past_edge() {
+        let properties = BoardProperties::default();
+        let a8 = BoardPosition::new(0, 0);
+        let (x, y) = properties.position_to_transform(&a8);
+        let square_size = properties.square_size();
+        assert_eq!(properties.position_past_edge(&Vec2::new(x, y)), None);
+        // Just above the last rank is past the edge there, but further away or off the side is not
+        let above = Vec2::new(x, y + square_size * 0.8);
+        assert_eq!(properties.transform_to_position(&above), None);
+        assert_eq!(properties.position_past_edge(&above), Some(a8));
+        assert_eq!(
+            properties.position_past_edge(&Vec2::new(x, y + square_size * 2.0)),
+            None
+        );
+        assert_eq!(
+            properties.position_past_edge(&Vec2::new(x - square_size, y)),
+            None
+        );
+        let (x, y) = properties.position_to_transform(&BoardPosition::new(7, 4));
+        assert_eq!(
+            properties.position_past_edge(&Vec2::new(x, y - square_size * 0.6)),
+            Some(BoardPosition::new(7, 4))
+        );
     }
 }
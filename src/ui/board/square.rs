@@ -1,4 +1,4 @@
-use bevy::prelude::{default, Bundle, Component, Res, Transform, Vec2};
+use bevy::prelude::{default, Bundle, Component, Transform, Vec2};
 use bevy::sprite::{Sprite, SpriteBundle};
 
 use crate::chess_board::PieceColor;
@@ -30,7 +30,7 @@ impl SquareBundle {
         position: BoardPosition,
         size: f32,
         color: PieceColor,
-        properties: &Res<BoardProperties>,
+        properties: &BoardProperties,
     ) -> Self {
         let (x, y) = properties.position_to_transform(&position);
         let square_color = match color {
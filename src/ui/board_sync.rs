@@ -0,0 +1,186 @@
+//! The board consistency check, which compares the piece entities drawn on the board with the
+//! pieces of the [ChessBoard] every [CHECK_INTERVAL], and redraws them all if they have drifted
+//! apart.
+//!
+//! The pieces are moved a frame or so after the board, so a difference is only acted on when the
+//! next check finds it too, and no check is made while a piece slides into place. The check is on
+//! by default in debug builds, where each difference found is logged.
+
+use std::time::Duration;
+
+use bevy::log::{info, warn};
+use bevy::prelude::{Commands, Entity, EventWriter, Query, Res, ResMut, Resource, With};
+use bevy::time::{Time, Timer, TimerMode};
+use bevy_egui::egui;
+
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceCreateEvent, PieceType};
+
+use super::locale::Localisation;
+use super::piece::{MoveAnimation, PieceTag};
+
+/// The time between checks.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A square where the pieces drawn differ from the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Divergence {
+    /// The board has a piece on the square, but none is drawn there.
+    Missing(BoardPosition),
+    /// A piece is drawn where the board has none, or more than one piece is drawn.
+    Extra(BoardPosition),
+    /// The piece drawn is not the one on the board.
+    Wrong(BoardPosition),
+}
+
+#[derive(Resource, Debug)]
+pub(super) struct BoardSync {
+    enabled: bool,
+    /// Whether the pieces are redrawn when they differ from the board, rather than only logged.
+    repair: bool,
+    timer: Timer,
+    /// The differences found by the last check, which are acted on if the next finds them too.
+    suspected: Vec<Divergence>,
+    /// The number of times the pieces have been redrawn.
+    repairs: u32,
+}
+
+impl Default for BoardSync {
+    fn default() -> Self {
+        BoardSync {
+            enabled: cfg!(debug_assertions),
+            repair: true,
+            timer: Timer::new(CHECK_INTERVAL, TimerMode::Repeating),
+            suspected: Vec::new(),
+            repairs: 0,
+        }
+    }
+}
+
+/// Returns the squares where the given pieces drawn differ from the given board.
+fn divergences(
+    board: &ChessBoard,
+    pieces: &[(BoardPosition, PieceColor, PieceType)],
+) -> Vec<Divergence> {
+    let mut found: Vec<Divergence> = board
+        .size()
+        .positions()
+        .filter_map(|position| {
+            let expected = board
+                .get_piece_color(&position)
+                .zip(board.get_piece_type(&position));
+            let drawn: Vec<(PieceColor, PieceType)> = pieces
+                .iter()
+                .filter(|(square, _, _)| *square == position)
+                .map(|(_, color, piece_type)| (*color, *piece_type))
+                .collect();
+            match (expected, drawn.as_slice()) {
+                (None, []) => None,
+                (Some(expected), [drawn]) if expected == *drawn => None,
+                (Some(_), []) => Some(Divergence::Missing(position)),
+                (Some(_), [_]) => Some(Divergence::Wrong(position)),
+                _ => Some(Divergence::Extra(position)),
+            }
+        })
+        .collect();
+    // Pieces can also be left off the squares of a smaller board
+    found.extend(
+        pieces
+            .iter()
+            .filter(|(square, _, _)| !board.size().contains(square))
+            .map(|(square, _, _)| Divergence::Extra(*square)),
+    );
+    found
+}
+
+/// Draws the consistency check controls.
+pub(super) fn settings_ui(ui: &mut egui::Ui, sync: &mut BoardSync, locale: &Localisation) {
+    ui.checkbox(&mut sync.enabled, locale.get("board-sync"))
+        .on_hover_text(locale.get("board-sync-hint"));
+    ui.add_enabled(
+        sync.enabled,
+        egui::Checkbox::new(&mut sync.repair, locale.get("board-sync-repair")),
+    );
+    if sync.repairs > 0 {
+        ui.label(locale.format("board-sync-repairs", &[("count", &sync.repairs)]));
+    }
+}
+
+/// Checks the pieces drawn against the board, logging any differences which last from one check
+/// to the next and redrawing the pieces from the board if repairs are on.
+pub(super) fn check_board_sync(
+    mut sync: ResMut<BoardSync>,
+    time: Res<Time>,
+    board: Res<ChessBoard>,
+    pieces: Query<(Entity, &BoardPosition, &PieceColor, &PieceType), With<PieceTag>>,
+    animations: Query<(), With<MoveAnimation>>,
+    mut create_events: EventWriter<PieceCreateEvent>,
+    mut commands: Commands,
+) {
+    if !sync.enabled || !sync.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    if !animations.is_empty() {
+        sync.suspected.clear();
+        return;
+    }
+    let drawn: Vec<(BoardPosition, PieceColor, PieceType)> = pieces
+        .iter()
+        .map(|(_, position, color, piece_type)| (*position, *color, *piece_type))
+        .collect();
+    let found = divergences(&board, &drawn);
+    if found.is_empty() || found != sync.suspected {
+        sync.suspected = found;
+        return;
+    }
+    warn!(divergences = ?found, "The pieces drawn differ from the board");
+    sync.suspected.clear();
+    if !sync.repair {
+        return;
+    }
+    for (entity, _, _, _) in pieces.iter() {
+        commands.entity(entity).despawn();
+    }
+    for position in board.size().positions() {
+        let piece = board
+            .get_piece_color(&position)
+            .zip(board.get_piece_type(&position));
+        if let Some((color, piece_type)) = piece {
+            create_events.send(PieceCreateEvent::new(position, piece_type, color));
+        }
+    }
+    sync.repairs += 1;
+    info!(repairs = sync.repairs, "Redrew the pieces from the board");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_divergences() {
+        let board = ChessBoard::with_position(&Fen::from_string("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let mut pieces = vec![
+            (square("e8"), PieceColor::Black, PieceType::King),
+            (square("e2"), PieceColor::White, PieceType::Pawn),
+            (square("e1"), PieceColor::White, PieceType::King),
+        ];
+        assert!(divergences(&board, &pieces).is_empty());
+
+        // A pawn drawn where it moved from, a king of the wrong color and a piece drawn twice
+        pieces[1].0 = square("e4");
+        pieces[2].1 = PieceColor::Black;
+        pieces.push(pieces[0]);
+        assert_eq!(
+            divergences(&board, &pieces),
+            vec![
+                Divergence::Extra(square("e8")),
+                Divergence::Extra(square("e4")),
+                Divergence::Missing(square("e2")),
+                Divergence::Wrong(square("e1")),
+            ]
+        );
+    }
+}
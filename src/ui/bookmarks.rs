@@ -0,0 +1,148 @@
+//! The bookmarks window, which bookmarks the position on the board with a name and tags and lists
+//! the [bookmarks](crate::bookmark), filtered by a tag, to open for analysis, remove or export.
+
+use bevy::prelude::{EventWriter, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::bookmark::{
+    parse_tags, Bookmark, Bookmarks, OpenBookmarkEvent, BOOKMARKS_EXPORT_PATH, MAX_NAME_LENGTH,
+};
+use crate::chess_board::ChessBoard;
+use crate::database::today;
+use crate::notification::{Argument, Notification};
+use crate::storage;
+
+use super::locale::Localisation;
+
+/// The state of the bookmarks window.
+#[derive(Resource, Debug, Default)]
+pub(super) struct BookmarksWindowState {
+    name: String,
+    /// The tags of the next bookmark, separated by commas.
+    tags: String,
+    /// The tag the list is filtered by.
+    filter: Option<String>,
+}
+
+/// Writes the bookmarks, notifying the player if they could not be.
+fn save(bookmarks: &Bookmarks, notifications: &mut EventWriter<Notification>) {
+    if let Err(error) = bookmarks.save() {
+        notifications.send(
+            Notification::error("bookmarks-save-failed").with_arg("error", Argument::Text(error)),
+        );
+    }
+}
+
+/// Shows the bookmarks window, collapsed until it is opened.
+pub(super) fn bookmarks_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<BookmarksWindowState>,
+    mut bookmarks: ResMut<Bookmarks>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut open_events: EventWriter<OpenBookmarkEvent>,
+    mut notifications: EventWriter<Notification>,
+) {
+    let BookmarksWindowState { name, tags, filter } = &mut *state;
+    egui::Window::new(locale.get("bookmarks"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Grid::new("new_bookmark").show(ui, |ui| {
+                ui.label(locale.get("bookmark-name"));
+                ui.add(egui::TextEdit::singleline(name).char_limit(MAX_NAME_LENGTH));
+                ui.end_row();
+                ui.label(locale.get("bookmark-tags"));
+                ui.add(
+                    egui::TextEdit::singleline(tags).hint_text(locale.get("bookmark-tags-hint")),
+                );
+                ui.end_row();
+            });
+            if ui
+                .button(locale.get("bookmark-position"))
+                .on_hover_text(locale.get("bookmark-position-hint"))
+                .clicked()
+            {
+                let named = match name.trim() {
+                    "" => locale.format("bookmark-default-name", &[("move", board.move_number())]),
+                    name => name.to_string(),
+                };
+                let bookmark = Bookmark::new(&named, parse_tags(tags), &board, today());
+                let key = match bookmarks.add(bookmark) {
+                    true => "bookmark-added",
+                    false => "bookmark-updated",
+                };
+                notifications.send(Notification::info(key).with_arg("name", Argument::Text(named)));
+                save(&bookmarks, &mut notifications);
+                name.clear();
+            }
+            ui.separator();
+
+            let all_tags = bookmarks.tags();
+            // A tag removed with the last bookmark it was on no longer filters the list
+            if filter.as_ref().is_some_and(|tag| !all_tags.contains(tag)) {
+                *filter = None;
+            }
+            ui.horizontal(|ui| {
+                let any = locale.get("any").to_string();
+                egui::ComboBox::from_label(locale.get("bookmark-tag"))
+                    .selected_text(filter.clone().unwrap_or(any.clone()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(filter, None, any);
+                        for tag in &all_tags {
+                            ui.selectable_value(filter, Some(tag.clone()), tag);
+                        }
+                    });
+                if ui
+                    .button(locale.get("export-bookmarks"))
+                    .on_hover_text(locale.get("export-bookmarks-hint"))
+                    .clicked()
+                {
+                    let collection = bookmarks.fen_collection(filter.as_deref());
+                    let notification = match storage::write(BOOKMARKS_EXPORT_PATH, &collection) {
+                        Ok(()) => Notification::info("bookmarks-exported")
+                            .with_arg("path", Argument::Text(BOOKMARKS_EXPORT_PATH.to_string())),
+                        Err(error) => Notification::error("bookmarks-export-failed")
+                            .with_arg("error", Argument::Text(error)),
+                    };
+                    notifications.send(notification);
+                }
+            });
+
+            let listed: Vec<(usize, &Bookmark)> = bookmarks.tagged(filter.as_deref()).collect();
+            if listed.is_empty() {
+                ui.label(locale.get("no-bookmarks"));
+                return;
+            }
+            let mut removed = None;
+            egui::ScrollArea::vertical()
+                .max_height(300.)
+                .show(ui, |ui| {
+                    egui::Grid::new("bookmarks").striped(true).show(ui, |ui| {
+                        for heading in ["bookmark-name", "bookmark-tags", "date"] {
+                            ui.strong(locale.get(heading));
+                        }
+                        ui.end_row();
+                        for (index, bookmark) in listed {
+                            ui.label(&bookmark.name).on_hover_text(bookmark.fen());
+                            ui.label(bookmark.tags.join(", "));
+                            ui.label(bookmark.date());
+                            if ui
+                                .button(locale.get("open-bookmark"))
+                                .on_hover_text(locale.get("open-bookmark-hint"))
+                                .clicked()
+                            {
+                                open_events.send(OpenBookmarkEvent::new(index));
+                            }
+                            if ui.button(locale.get("remove-bookmark")).clicked() {
+                                removed = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            if let Some(index) = removed {
+                bookmarks.remove(index);
+                save(&bookmarks, &mut notifications);
+            }
+        });
+}
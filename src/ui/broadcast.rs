@@ -0,0 +1,79 @@
+//! The broadcast window, where a [live game](Broadcast) is followed from the URL of its PGN and
+//! the board is returned to the live position after analysing ahead.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::broadcast::Broadcast;
+
+use super::guess_move::game_label;
+use super::locale::Localisation;
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct BroadcastWindowState {
+    /// The URL or path of the PGN to follow.
+    source: String,
+}
+
+/// Shows the broadcast window, collapsed until it is opened.
+pub(super) fn broadcast_window(
+    mut contexts: EguiContexts,
+    mut broadcast: ResMut<Broadcast>,
+    mut state: ResMut<BroadcastWindowState>,
+    locale: Res<Localisation>,
+) {
+    egui::Window::new(locale.get("broadcast"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let Some(source) = broadcast.source() else {
+                ui.horizontal(|ui| {
+                    ui.label(locale.get("broadcast-url"));
+                    ui.text_edit_singleline(&mut state.source);
+                });
+                let source = state.source.trim();
+                if ui
+                    .add_enabled(!source.is_empty(), egui::Button::new(locale.get("follow")))
+                    .clicked()
+                {
+                    broadcast.follow(source);
+                }
+                return;
+            };
+            ui.label(source);
+            if let Some(error) = broadcast.error() {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+            let games: Vec<String> = broadcast
+                .games()
+                .iter()
+                .enumerate()
+                .map(|(index, game)| game_label(game, index, &locale))
+                .collect();
+            let mut selected = broadcast.selected();
+            egui::ComboBox::from_label(locale.get("broadcast-game"))
+                .selected_text(games.get(selected).map_or("", String::as_str))
+                .show_ui(ui, |ui| {
+                    for (index, label) in games.iter().enumerate() {
+                        ui.selectable_value(&mut selected, index, label);
+                    }
+                });
+            if selected != broadcast.selected() {
+                broadcast.select(selected);
+            }
+            if let Some(game) = broadcast.game() {
+                let number = game.moves().len().div_ceil(2);
+                ui.label(locale.format("live-move", &[("number", &number)]));
+            }
+            if !broadcast.is_following() {
+                ui.label(locale.get("analysing-away"));
+                if ui.button(locale.get("back-to-live")).clicked() {
+                    broadcast.resync();
+                }
+            } else if ui.button(locale.get("analyse-broadcast")).clicked() {
+                broadcast.analyse();
+            }
+            if ui.button(locale.get("stop-following")).clicked() {
+                broadcast.stop();
+            }
+        });
+}
@@ -0,0 +1,114 @@
+//! The bug report window, which exports a [diagnostic bundle](crate::bundle) of the current game
+//! and imports one to put its game and board options back.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{EventWriter, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::autosave::{Autosave, SavedGame};
+use crate::bundle::{Bundle, BUNDLE_PATH};
+use crate::chess_board::game_log::GameLog;
+use crate::chess_board::{ChessBoard, ChessBoardConfig};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::history::PositionHistory;
+use crate::notification::{Argument, Notification};
+use crate::replay::ReplayLog;
+
+use super::locale::Localisation;
+
+#[derive(Resource, Debug)]
+pub(super) struct BundleState {
+    /// The path of the bundle to import.
+    path: String,
+}
+
+impl Default for BundleState {
+    fn default() -> Self {
+        BundleState {
+            path: BUNDLE_PATH.to_string(),
+        }
+    }
+}
+
+/// The current game with its clock and engine, and the logs of what happened in it, which a bundle
+/// is made from.
+#[derive(SystemParam)]
+pub(super) struct BundleContents<'w> {
+    board: Res<'w, ChessBoard>,
+    history: Res<'w, PositionHistory>,
+    clock: Res<'w, ChessClock>,
+    engine: Res<'w, Engine>,
+    log: Res<'w, ReplayLog>,
+    game_log: Res<'w, GameLog>,
+}
+
+/// Writes a bundle of the current game to [BUNDLE_PATH].
+fn export(contents: &BundleContents, config: &ChessBoardConfig) -> Result<(), String> {
+    let game = SavedGame::new(&contents.history, &contents.clock, &contents.engine)
+        .ok_or("there is no game yet")?;
+    let bundle = Bundle::new(
+        contents.board.to_fen().to_string(),
+        game,
+        config.clone(),
+        contents.log.entries().to_vec(),
+        contents.game_log.events().to_vec(),
+    );
+    std::fs::write(BUNDLE_PATH, bundle.to_zip()?).map_err(|error| error.to_string())
+}
+
+/// Reads the bundle at the given path.
+fn import(path: &str) -> Result<Bundle, String> {
+    let zip = std::fs::read(path).map_err(|error| error.to_string())?;
+    Bundle::from_zip(&zip)
+}
+
+/// Shows the bug report window, collapsed until it is opened.
+pub(super) fn bundle_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<BundleState>,
+    contents: BundleContents,
+    mut config: ResMut<ChessBoardConfig>,
+    mut autosave: ResMut<Autosave>,
+    mut notifications: EventWriter<Notification>,
+    locale: Res<Localisation>,
+) {
+    egui::Window::new(locale.get("bug-report"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(locale.get("bug-report-hint"));
+            if ui.button(locale.get("export-bundle")).clicked() {
+                let notification = match export(&contents, &config) {
+                    Ok(()) => Notification::info("bundle-exported")
+                        .with_arg("path", Argument::Text(BUNDLE_PATH.to_string())),
+                    Err(error) => Notification::error("bundle-export-failed")
+                        .with_arg("error", Argument::Text(error)),
+                };
+                notifications.send(notification);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(locale.get("bundle-path"));
+                ui.text_edit_singleline(&mut state.path);
+            });
+            if ui.button(locale.get("import-bundle")).clicked() {
+                match import(state.path.trim()) {
+                    Ok(bundle) => {
+                        if !bundle.reaches_position() {
+                            notifications.send(Notification::warning("bundle-position-differs"));
+                        }
+                        notifications.send(
+                            Notification::info("bundle-imported")
+                                .with_arg("version", Argument::Text(bundle.version().to_string())),
+                        );
+                        *config = bundle.config().clone();
+                        autosave.import(bundle.game().clone());
+                    }
+                    Err(error) => notifications.send(
+                        Notification::error("bundle-import-failed")
+                            .with_arg("error", Argument::Text(error)),
+                    ),
+                }
+            }
+        });
+}
@@ -0,0 +1,245 @@
+//! Skill calibration, which sets the player a few positions to find a move in and estimates their
+//! rating from how much worse each move is than the engine's. From the rating it recommends an
+//! engine depth and a time control, which can be applied with a click.
+//!
+//! The last result is kept in the [profile](crate::profile) of the player playing, and sets their
+//! rating.
+
+use std::time::Duration;
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, ResetBoardEvent};
+use crate::database::today;
+use crate::engine::{best_move, evaluate_position, EvalWeights, SearchOptions};
+use crate::fen::Fen;
+use crate::profile::{Calibration, Profiles};
+
+use super::engine::GameSetup;
+use super::locale::Localisation;
+
+/// The positions the player finds a move in, from the opening to the endgame.
+const POSITIONS: [&str; 6] = [
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "rnb1kbnr/pppp1ppp/8/4p1q1/4P3/3P4/PPP2PPP/RNBQKBNR w KQkq - 1 3",
+    "r1bq1rk1/ppp2ppp/2np1n2/2b1p3/2B1P3/2NP1N2/PPP2PPP/R1BQ1RK1 w - - 0 7",
+    "r3k3/8/8/3N4/8/8/8/4K3 w q - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+    "8/5k2/8/3K4/3P4/8/8/8 w - - 0 1",
+];
+
+/// The depth the moves are compared to.
+const DEPTH: u32 = 2;
+
+/// The most centipawns a move can lose, so a single missed mate does not outweigh the rest.
+const MAX_LOSS: i32 = 1000;
+
+/// The rating of a player who always finds a move as good as the engine's.
+const MAX_RATING: i32 = 2400;
+
+/// The rating lost for each centipawn lost on average.
+const RATING_PER_CENTIPAWN: i32 = 6;
+
+/// The lowest rating estimated.
+const MIN_RATING: i32 = 400;
+
+/// Returns the centipawns the given move loses compared to the engine's move in the position on
+/// the given board.
+fn centipawn_loss(board: &ChessBoard, piece_move: &Move) -> i32 {
+    let Some(engine_move) = best_move(
+        board,
+        DEPTH,
+        &SearchOptions::default(),
+        &EvalWeights::default(),
+    ) else {
+        return 0;
+    };
+    // The evaluations are from the point of view of the player to move after each move
+    let evaluate = |piece_move: &Move| {
+        let mut after = board.clone();
+        after.apply_move(piece_move);
+        -evaluate_position(&after, DEPTH)
+    };
+    evaluate(&engine_move)
+        .saturating_sub(evaluate(piece_move))
+        .clamp(0, MAX_LOSS)
+}
+
+/// Returns the calibration of a player who lost the given centipawns over the positions,
+/// recommending a deeper search and a faster time control the stronger they are.
+fn calibrate(losses: &[i32], date: String) -> Calibration {
+    let average = losses.iter().sum::<i32>() / losses.len().max(1) as i32;
+    let rating = (MAX_RATING - average * RATING_PER_CENTIPAWN).max(MIN_RATING);
+    let (depth, minutes) = match rating {
+        ..=999 => (1, 15),
+        1000..=1499 => (2, 15),
+        1500..=1999 => (3, 10),
+        _ => (4, 5),
+    };
+    Calibration {
+        date,
+        rating,
+        depth,
+        minutes,
+    }
+}
+
+/// A calibration in progress.
+#[derive(Debug, Clone, Default)]
+struct Session {
+    /// The index of the current position.
+    position: usize,
+    /// Whether the current position has been loaded onto the board.
+    started: bool,
+    /// The centipawns lost in each position so far.
+    losses: Vec<i32>,
+}
+
+impl Session {
+    fn fen(&self) -> Option<Fen> {
+        POSITIONS
+            .get(self.position)
+            .map(|fen| Fen::from_string(fen))
+    }
+
+    /// Checks the player's move in the current position, returning whether the next position
+    /// should be loaded.
+    fn update(&mut self, board: &ChessBoard) -> bool {
+        let Some(fen) = self.fen() else {
+            return false;
+        };
+        let start = ChessBoard::with_position(&fen);
+        if !self.started {
+            // The board does not keep the move counters of the position, so it is compared with
+            // the position as the board would give it
+            self.started = board.past_moves().is_empty()
+                && board.to_fen().to_string() == start.to_fen().to_string();
+            return false;
+        }
+        let [piece_move] = &board.past_moves()[..] else {
+            return false;
+        };
+        self.losses.push(centipawn_loss(&start, piece_move));
+        self.position += 1;
+        self.started = false;
+        !self.is_finished()
+    }
+
+    fn is_finished(&self) -> bool {
+        self.position >= POSITIONS.len()
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct SkillCalibration {
+    session: Option<Session>,
+}
+
+/// Shows the calibration, collapsed until it is opened, with the position the player is on during
+/// a calibration and the recommendation from the player's last one.
+pub(super) fn calibration_window(
+    mut contexts: EguiContexts,
+    mut calibration: ResMut<SkillCalibration>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut setup: GameSetup,
+    mut profiles: ResMut<Profiles>,
+) {
+    let mut finished_now = false;
+    if let Some(session) = calibration.session.as_mut() {
+        if session.update(&board) {
+            setup.load_alone(ResetBoardEvent::new(session.fen().unwrap()));
+        }
+        finished_now = session.is_finished();
+    }
+    if finished_now {
+        let session = calibration.session.take().unwrap();
+        profiles
+            .active_mut()
+            .calibrate(calibrate(&session.losses, today()));
+        // A failed save only loses the result once the app closes, so it is not reported
+        profiles.save().ok();
+    }
+    egui::Window::new(locale.get("calibration"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            match &calibration.session {
+                Some(session) => {
+                    ui.label(locale.format(
+                        "calibration-position",
+                        &[
+                            ("number", &(session.position + 1)),
+                            ("total", &POSITIONS.len()),
+                        ],
+                    ));
+                    if ui.button(locale.get("stop-calibration")).clicked() {
+                        calibration.session = None;
+                    }
+                }
+                None => {
+                    if ui.button(locale.get("start-calibration")).clicked() {
+                        let session = Session::default();
+                        setup.load_alone(ResetBoardEvent::new(session.fen().unwrap()));
+                        calibration.session = Some(session);
+                    }
+                }
+            }
+            let Some(result) = profiles.active().calibration() else {
+                return;
+            };
+            ui.separator();
+            ui.label(locale.format(
+                "calibration-rating",
+                &[("rating", &result.rating), ("date", &result.date)],
+            ));
+            ui.label(locale.format(
+                "calibration-recommendation",
+                &[("depth", &result.depth), ("minutes", &result.minutes)],
+            ));
+            if ui.button(locale.get("apply-calibration")).clicked() {
+                setup.engine.set_depth(result.depth);
+                setup
+                    .clock
+                    .set_time_control(Some(Duration::from_secs(result.minutes * 60)));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::default();
+        let mut board = ChessBoard::with_position(&session.fen().unwrap());
+        assert!(!session.update(&board));
+        assert!(session.started);
+        assert!(!session.update(&board));
+        let developing = Move::from_algebraic("Bb5", &board).unwrap();
+        board.apply_move(&developing);
+        assert!(session.update(&board));
+        assert_eq!(session.position, 1);
+
+        // Leaving the queen on g5 alone loses the chance to take it
+        let mut board = ChessBoard::with_position(&session.fen().unwrap());
+        session.update(&board);
+        let blunder = Move::from_algebraic("a3", &board).unwrap();
+        board.apply_move(&blunder);
+        session.update(&board);
+        assert!(session.losses[1] > 500);
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let date = "2024.05.01".to_string();
+        let strong = calibrate(&[0, 20, 10], date.clone());
+        assert_eq!(strong.rating, 2340);
+        assert_eq!((strong.depth, strong.minutes), (4, 5));
+        let weak = calibrate(&[MAX_LOSS, 300], date);
+        assert_eq!(weak.rating, MIN_RATING);
+        assert_eq!((weak.depth, weak.minutes), (1, 15));
+    }
+}
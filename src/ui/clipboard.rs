@@ -0,0 +1,504 @@
+//! Copying the position and the game to the clipboard, and loading a position or game pasted
+//! from it.
+//!
+//! Pasting, with the paste button or the paste [action](super::actions), checks
+//! whether the clipboard holds a FEN or a PGN and offers to load it into a new analysis board,
+//! where the engine does not play and the clock is off. A game is replayed a few moves each
+//! frame with its progress shown, so long games do not freeze the window while they load.
+//!
+//! A `.fen` or `.pgn` file dropped onto the window is offered to load in the same way, and a PGN
+//! holding several games lists them to choose the one to load. A position which could not arise
+//! in a game is listed with its [problems](chesscomputer_core::legality), each of which can be
+//! fixed where it has an obvious fix, before it is loaded. A position loaded anyway still has
+//! the castling rights it cannot use cleared.
+
+use std::path::Path;
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{EventReader, EventWriter, Res, ResMut, Resource};
+use bevy::window::{FileDragAndDrop, RequestRedraw};
+use bevy_egui::{egui, EguiClipboard, EguiContexts};
+use chesscomputer_core::legality::{self, Problem};
+
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, ResetBoardEvent, Variant};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::notification::{Argument, Notification};
+use crate::pgn::{replay_games, replay_pgn, write_pgn, PgnReplay};
+use crate::share;
+use crate::telemetry::GameTelemetry;
+
+use super::locale::Localisation;
+
+/// The number of moves of a pasted game replayed each frame while it loads.
+const REPLAY_MOVES_PER_FRAME: usize = 10;
+
+/// A position or game read from the clipboard, or from a file dropped onto the window.
+#[derive(Debug, Clone)]
+enum Pasted {
+    Position(Box<ResetBoardEvent>),
+    Game(Box<PgnReplay>),
+    /// The games of a PGN holding more than one, to choose from.
+    Games(Vec<PgnReplay>),
+}
+
+impl Pasted {
+    /// Reads the given text as a FEN or, failing that, as a PGN.
+    fn read(text: &str) -> Option<Self> {
+        match Fen::parse(text.trim()) {
+            Some(fen) => Some(Pasted::Position(Box::new(ResetBoardEvent::new(fen)))),
+            None => {
+                // The first move is replayed straight away, so text which is not a game is not
+                // offered as one
+                let mut replay = replay_pgn(text)?;
+                replay.step(1)?;
+                Some(Pasted::Game(Box::new(replay)))
+            }
+        }
+    }
+
+    /// Reads the given text of the file at the given path as a FEN or a PGN by its extension, or
+    /// as either if it has another.
+    fn read_file(path: &Path, text: &str) -> Option<Self> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("fen") => {
+                let fen = Fen::parse(text.lines().next()?.trim())?;
+                Some(Pasted::Position(Box::new(ResetBoardEvent::new(fen))))
+            }
+            Some("pgn") => {
+                let mut games: Vec<PgnReplay> = replay_games(text)
+                    .into_iter()
+                    .filter_map(|mut replay| {
+                        replay.step(1)?;
+                        Some(replay)
+                    })
+                    .collect();
+                match games.len() {
+                    0 => None,
+                    1 => Some(Pasted::Game(Box::new(games.remove(0)))),
+                    _ => Some(Pasted::Games(games)),
+                }
+            }
+            _ => Pasted::read(text),
+        }
+    }
+}
+
+/// Returns the label of the given game in the list of the games of a PGN, e.g.
+/// "Alice – Bob, Club championship (42 moves)".
+fn game_label(replay: &PgnReplay, locale: &Localisation) -> String {
+    let tag = |name| replay.tag(name).unwrap_or("?");
+    locale.format(
+        "dropped-game",
+        &[
+            ("white", &tag("White")),
+            ("black", &tag("Black")),
+            ("event", &tag("Event")),
+            ("moves", &replay.len()),
+        ],
+    )
+}
+
+/// Returns the message explaining the given problem of a position.
+fn problem_text(problem: &Problem, locale: &Localisation) -> String {
+    let key = problem.key();
+    match problem {
+        Problem::OpponentInCheck(color) => locale.format(key, &[("color", &locale.color(color))]),
+        Problem::BothInCheck => locale.get(key).to_string(),
+        Problem::TooManyPieces(color, count) | Problem::TooManyPawns(color, count) => {
+            locale.format(key, &[("color", &locale.color(color)), ("count", count)])
+        }
+        Problem::PawnsOnBackRank(positions) => {
+            let squares: Vec<String> = positions.iter().map(|p| p.to_algebraic()).collect();
+            locale.format(key, &[("squares", &squares.join(", "))])
+        }
+        Problem::CastlingRights(rights) => {
+            locale.format(key, &[("rights", &rights.to_fen_string())])
+        }
+        Problem::EnPassantTarget(square) => {
+            locale.format(key, &[("square", &square.to_algebraic())])
+        }
+    }
+}
+
+/// Lists the given problems of the position of the given FEN, with a button to fix each which
+/// has a fix and one to fix them all, returning the position fixed if one is pressed.
+fn problems_ui(
+    ui: &mut egui::Ui,
+    fen: &Fen,
+    problems: &[Problem],
+    locale: &Localisation,
+) -> Option<Fen> {
+    if problems.is_empty() {
+        return None;
+    }
+    let mut fixed = None;
+    ui.colored_label(
+        egui::Color32::from_rgb(230, 140, 0),
+        locale.get("position-problems"),
+    );
+    for problem in problems {
+        ui.horizontal(|ui| {
+            ui.label(format!("• {}", problem_text(problem, locale)));
+            if let Some(fix) = problem.fix(fen) {
+                if ui.small_button(locale.get("fix-problem")).clicked() {
+                    fixed = Some(fix);
+                }
+            }
+        });
+    }
+    let fixable = problems.iter().filter(|problem| problem.fix(fen).is_some());
+    if fixable.count() > 1 && ui.button(locale.get("fix-all-problems")).clicked() {
+        fixed = Some(legality::fix_all(fen, Variant::Standard));
+    }
+    fixed
+}
+
+/// The state of the clipboard actions.
+#[derive(Resource, Debug, Default)]
+pub(super) struct PasteState {
+    /// The position or game pasted, until the player chooses whether to load it.
+    pasted: Option<Pasted>,
+    /// The name of the file the position or game was dropped from, if it was not pasted.
+    dropped: Option<String>,
+    /// The game being loaded, while its moves are replayed.
+    replaying: Option<PgnReplay>,
+    /// The result of the last action, shown below the buttons.
+    message: Option<String>,
+}
+
+impl PasteState {
+    /// Reads the given clipboard contents, offering to load them if they hold a position or game.
+    pub(super) fn paste(&mut self, contents: Option<String>, locale: &Localisation) {
+        self.pasted = contents.as_deref().and_then(Pasted::read);
+        self.dropped = None;
+        self.message = self
+            .pasted
+            .is_none()
+            .then(|| locale.get("paste-not-recognised").to_string());
+    }
+
+    /// Reads the given text of the file dropped from the given path, offering to load it if it
+    /// holds a position or game. Returns whether it does.
+    fn drop_file(&mut self, path: &Path, text: &str) -> bool {
+        let Some(pasted) = Pasted::read_file(path, text) else {
+            return false;
+        };
+        self.pasted = Some(pasted);
+        self.dropped = Some(file_name(path));
+        self.message = None;
+        true
+    }
+}
+
+/// Returns the name of the file at the given path, without its directory.
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
+
+/// The paste state, the clipboard and the history the game is copied from.
+#[derive(SystemParam)]
+pub(super) struct ClipboardActions<'w> {
+    state: ResMut<'w, PasteState>,
+    clipboard: Res<'w, EguiClipboard>,
+    history: Res<'w, PositionHistory>,
+    telemetry: Res<'w, GameTelemetry>,
+}
+
+/// Draws the copy and paste buttons, where the time controls are those the game was played with.
+pub(super) fn clipboard_ui(
+    ui: &mut egui::Ui,
+    board: &ChessBoard,
+    time_controls: &Option<[Duration; 2]>,
+    actions: &mut ClipboardActions,
+    locale: &Localisation,
+) {
+    let ClipboardActions {
+        state,
+        clipboard,
+        history,
+        telemetry,
+    } = actions;
+    ui.collapsing(locale.get("clipboard"), |ui| {
+        if ui.button(locale.get("copy-position")).clicked() {
+            ui.output_mut(|output| output.copied_text = board.to_fen().to_string());
+            state.message = Some(locale.get("copied-position").to_string());
+        }
+        if let Some(start) = history.start() {
+            if ui.button(locale.get("copy-game")).clicked() {
+                let pgn = write_pgn(
+                    start,
+                    history.moves(),
+                    board,
+                    ["?", "?"],
+                    time_controls,
+                    Some(telemetry),
+                );
+                ui.output_mut(|output| output.copied_text = pgn);
+                state.message = Some(locale.get("copied-game").to_string());
+            }
+            if ui
+                .button(locale.get("copy-link"))
+                .on_hover_text(locale.get("copy-link-hint"))
+                .clicked()
+            {
+                let link = share::link(start, history.moves());
+                ui.output_mut(|output| output.copied_text = link);
+                state.message = Some(locale.get("copied-link").to_string());
+            }
+        }
+        if ui.button(locale.get("paste")).clicked() {
+            state.paste(clipboard.get_contents(), locale);
+        }
+        if let Some(message) = &state.message {
+            ui.label(message);
+        }
+    });
+}
+
+/// Reads each file dropped onto the window, offering to load the position or game it holds.
+pub(super) fn file_drop(
+    mut events: EventReader<FileDragAndDrop>,
+    mut state: ResMut<PasteState>,
+    mut notifications: EventWriter<Notification>,
+) {
+    for event in events.iter() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else {
+            continue;
+        };
+        let file = Argument::Text(file_name(path_buf));
+        match std::fs::read_to_string(path_buf) {
+            Ok(text) if state.drop_file(path_buf, &text) => {}
+            Ok(_) => notifications
+                .send(Notification::error("drop-not-recognised").with_arg("file", file)),
+            Err(error) => notifications.send(
+                Notification::error("drop-read-failed")
+                    .with_arg("file", file)
+                    .with_arg("error", Argument::Text(error.to_string())),
+            ),
+        }
+    }
+}
+
+/// Shows the window offering to load the pasted or dropped position or game into a new analysis
+/// board, or to choose one of the games dropped.
+pub(super) fn paste_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<PasteState>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+) {
+    let Some(pasted) = &state.pasted else {
+        return;
+    };
+    let dropped = state.dropped.is_some();
+    let prompt = match (pasted, dropped) {
+        (Pasted::Position(_), false) => locale.get("paste-position-prompt").to_string(),
+        (Pasted::Position(_), true) => locale.get("drop-position-prompt").to_string(),
+        (Pasted::Game(replay), false) => {
+            locale.format("paste-game-prompt", &[("moves", &replay.len())])
+        }
+        (Pasted::Game(replay), true) => {
+            locale.format("drop-game-prompt", &[("moves", &replay.len())])
+        }
+        (Pasted::Games(games), _) => locale.format("drop-games-prompt", &[("games", &games.len())]),
+    };
+    let title = match &state.dropped {
+        Some(name) => name.clone(),
+        None => locale.get("paste").to_string(),
+    };
+    // The positions pasted are read as standard chess, as they must have a king of each color
+    let problems = match pasted {
+        Pasted::Position(reset_event) => legality::problems(reset_event.fen(), Variant::Standard),
+        _ => Vec::new(),
+    };
+    let load_key = match problems.is_empty() {
+        true => "load",
+        false => "load-anyway",
+    };
+    let mut load = None;
+    let mut chosen = None;
+    let mut fixed = None;
+    egui::Window::new(title)
+        .id(egui::Id::new("paste"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(prompt);
+            if let Pasted::Position(reset_event) = pasted {
+                fixed = problems_ui(ui, reset_event.fen(), &problems, &locale);
+            }
+            if let Pasted::Games(games) = pasted {
+                egui::ScrollArea::vertical()
+                    .max_height(ui.available_height() / 2.0)
+                    .show(ui, |ui| {
+                        for (index, replay) in games.iter().enumerate() {
+                            if ui.button(game_label(replay, &locale)).clicked() {
+                                chosen = Some(index);
+                            }
+                        }
+                    });
+            }
+            ui.horizontal(|ui| {
+                if !matches!(pasted, Pasted::Games(_)) && ui.button(locale.get(load_key)).clicked()
+                {
+                    load = Some(true);
+                }
+                if ui.button(locale.get("cancel")).clicked() {
+                    load = Some(false);
+                }
+            });
+        });
+    if let Some(fen) = fixed {
+        state.pasted = Some(Pasted::Position(Box::new(ResetBoardEvent::new(fen))));
+        return;
+    }
+    if let Some(index) = chosen {
+        if let Some(Pasted::Games(mut games)) = state.pasted.take() {
+            state.pasted = Some(Pasted::Game(Box::new(games.swap_remove(index))));
+            load = Some(true);
+        }
+    }
+    let Some(load) = load else {
+        return;
+    };
+    if let Some(pasted) = state.pasted.take().filter(|_| load) {
+        // An analysis board is for the player alone
+        engine.set_color(None);
+        clock.set_time_control(None);
+        match pasted {
+            Pasted::Position(reset_event) => {
+                // Loading anyway still clears the castling rights which cannot be used, as
+                // castling them is impossible, leaving the other problems to be played
+                let fen = legality::fix_castling_rights(reset_event.fen(), Variant::Standard);
+                commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))))
+            }
+            Pasted::Game(replay) => state.replaying = Some(*replay),
+            Pasted::Games(_) => {}
+        }
+    }
+    state.dropped = None;
+    state.message = None;
+}
+
+/// Replays the moves of the game being loaded, a few each frame, showing how far it has got. The
+/// board is reset to the final position once every move has been replayed.
+pub(super) fn replay_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<PasteState>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    let Some(replay) = state.replaying.as_mut() else {
+        return;
+    };
+    match replay.step(REPLAY_MOVES_PER_FRAME) {
+        Some(true) => {
+            commands.push(GameCommand::LoadGame(Box::new(replay.reset_event())));
+            state.replaying = None;
+            return;
+        }
+        Some(false) => {}
+        None => {
+            // The illegal move is the one after those replayed
+            let illegal = replay.replayed() + 1;
+            state.message = Some(locale.format("paste-illegal-move", &[("move", &illegal)]));
+            state.replaying = None;
+            return;
+        }
+    }
+    let (replayed, moves) = (replay.replayed(), replay.len());
+    let mut cancel = false;
+    egui::Window::new(locale.get("loading-game"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.add(
+                egui::ProgressBar::new(replayed as f32 / moves as f32).text(locale.format(
+                    "loading-game-progress",
+                    &[("replayed", &replayed), ("moves", &moves)],
+                )),
+            );
+            cancel = ui.button(locale.get("cancel")).clicked();
+        });
+    if cancel {
+        state.replaying = None;
+    }
+    // The app only updates on input, so keep it updating until the game has loaded
+    redraw_events.send(RequestRedraw);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::PieceColor;
+
+    use super::*;
+
+    #[test]
+    fn test_paste() {
+        let locale = Localisation::default();
+        let mut state = PasteState::default();
+        state.paste(
+            Some("4k3/8/8/8/8/8/8/4K2R w K - 0 1\n".to_string()),
+            &locale,
+        );
+        assert!(matches!(state.pasted, Some(Pasted::Position(_))));
+        assert!(state.message.is_none());
+        let problem = Problem::TooManyPawns(PieceColor::Black, 9);
+        assert_eq!(
+            problem_text(&problem, &locale),
+            "Black has 9 pawns, more than it can have."
+        );
+
+        state.paste(Some("1. e4 e5 2. Nf3 *".to_string()), &locale);
+        let Some(Pasted::Game(replay)) = &state.pasted else {
+            panic!("Expected a game, found {:?}", state.pasted);
+        };
+        assert_eq!(replay.len(), 3);
+        assert_eq!(replay.replayed(), 1);
+
+        for contents in [Some("Hello".to_string()), None] {
+            state.paste(contents, &locale);
+            assert!(state.pasted.is_none());
+            assert!(state.message.is_some());
+        }
+    }
+
+    #[test]
+    fn test_drop_file() {
+        let mut state = PasteState::default();
+        let pgn = "[Event \"First\"]\n[White \"Alice\"]\n\n1. e4 e5 *\n\n\
+            [Event \"Second\"]\n\n1. d4 *\n\n\
+            [Event \"Illegal\"]\n\n1. Ke2 *\n";
+        assert!(state.drop_file(Path::new("games/club.PGN"), pgn));
+        assert_eq!(state.dropped.as_deref(), Some("club.PGN"));
+        let Some(Pasted::Games(games)) = &state.pasted else {
+            panic!("Expected games, found {:?}", state.pasted);
+        };
+        // The game with an illegal first move is left out
+        assert_eq!(games.len(), 2);
+        let locale = Localisation::default();
+        assert_eq!(game_label(&games[0], &locale), "Alice – ?, First (2 moves)");
+
+        assert!(state.drop_file(Path::new("mate.fen"), "4k3/8/8/8/8/8/8/4K2R w K - 0 1\n"));
+        assert!(matches!(state.pasted, Some(Pasted::Position(_))));
+        // A FEN in a PGN file is not a game
+        assert!(!state.drop_file(Path::new("mate.pgn"), "4k3/8/8/8/8/8/8/4K2R w K - 0 1"));
+        assert!(state.drop_file(Path::new("notes.txt"), "1. e4 *"));
+        assert!(matches!(state.pasted, Some(Pasted::Game(_))));
+    }
+}
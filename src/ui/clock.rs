@@ -0,0 +1,342 @@
+//! Display of the [ChessClock] and the low time warning effects.
+
+use std::time::Duration;
+
+use bevy::audio::Volume;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{
+    AssetServer, AudioBundle, Commands, EventReader, EventWriter, Local, PlaybackSettings, Query,
+    Res, ResMut, Resource,
+};
+use bevy::window::{RequestRedraw, Window};
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor};
+use crate::clock::{ChessClock, LowTimeEvent};
+use crate::database::CurrentDrill;
+use crate::engine::Engine;
+use crate::network::NetworkGame;
+use crate::notification::{Argument, Notification};
+
+use super::audio::{AudioSettings, SoundEvent, SoundPacks};
+use super::locale::Localisation;
+use super::turn::TurnIndicator;
+
+/// The time controls which can be selected, in minutes.
+const TIME_CONTROLS: [u64; 6] = [1, 3, 5, 10, 15, 30];
+
+/// Settings for the low time warning effects.
+#[derive(Resource, Debug)]
+pub(super) struct ClockWarningSettings {
+    /// Whether the window title flashes while the player to move is low on time.
+    flash_title: bool,
+    /// Whether the clocks of a casual game stop while the window is unfocused or minimised. The
+    /// clocks of rated and network games always keep running.
+    pause_when_away: bool,
+}
+
+impl Default for ClockWarningSettings {
+    fn default() -> Self {
+        ClockWarningSettings {
+            flash_title: true,
+            pause_when_away: true,
+        }
+    }
+}
+
+/// The chess clock and its settings.
+#[derive(SystemParam)]
+pub(super) struct Clocks<'w, 's> {
+    clock: ResMut<'w, ChessClock>,
+    warning_settings: ResMut<'w, ClockWarningSettings>,
+    /// Whether a time control is picked for each player.
+    time_odds: Local<'s, bool>,
+}
+
+impl Clocks<'_, '_> {
+    pub(super) fn clock(&self) -> &ChessClock {
+        &self.clock
+    }
+}
+
+/// Formats a duration as minutes and seconds, showing tenths of a second when under ten seconds.
+pub(super) fn format_duration(duration: &Duration) -> String {
+    if duration.as_secs() < 10 {
+        format!(
+            "0:{:02}.{}",
+            duration.as_secs(),
+            duration.subsec_millis() / 100
+        )
+    } else {
+        format!("{}:{:02}", duration.as_secs() / 60, duration.as_secs() % 60)
+    }
+}
+
+/// Draws the time remaining for each player, tinted red when low on time.
+pub(super) fn clock_ui(ui: &mut egui::Ui, clocks: &Clocks, locale: &Localisation) {
+    let clock = &clocks.clock;
+    for color in PieceColor::iter() {
+        if let Some(remaining) = clock.remaining(&color) {
+            let mut text = egui::RichText::new(format!(
+                "{}: {}",
+                locale.color(&color),
+                format_duration(&remaining)
+            ))
+            .monospace()
+            .heading();
+            if clock.is_low(&color) {
+                text = text.color(egui::Color32::RED);
+            }
+            ui.label(text);
+        }
+    }
+}
+
+/// Draws the picker for a time control in minutes, or None for untimed.
+fn time_control_ui(
+    ui: &mut egui::Ui,
+    minutes: &mut Option<u64>,
+    label: impl Into<egui::WidgetText>,
+    locale: &Localisation,
+) {
+    let minutes_text = |minutes: u64| locale.format("minutes", &[("minutes", &minutes)]);
+    let selected = match minutes {
+        Some(minutes) => minutes_text(*minutes),
+        None => locale.get("untimed").to_string(),
+    };
+    egui::ComboBox::from_label(label)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(minutes, None, locale.get("untimed"));
+            for option in TIME_CONTROLS {
+                ui.selectable_value(minutes, Some(option), minutes_text(option));
+            }
+        });
+}
+
+/// Draws the clock settings controls.
+pub(super) fn settings_ui(
+    ui: &mut egui::Ui,
+    clocks: &mut Clocks,
+    commands: &mut GameCommands,
+    locale: &Localisation,
+) {
+    let Clocks {
+        clock,
+        warning_settings,
+        time_odds,
+    } = clocks;
+    ui.collapsing(locale.get("clock"), |ui| {
+        let current = clock
+            .time_controls()
+            .map(|times| times.map(|time| time.as_secs() / 60));
+        let mut minutes = current.map_or([None; 2], |minutes| minutes.map(Some));
+        let mut odds = **time_odds || clock.has_time_odds();
+        ui.checkbox(&mut odds, locale.get("time-odds"));
+        **time_odds = odds;
+        if odds {
+            for color in PieceColor::iter() {
+                let label = locale.format("time-control-for", &[("player", &locale.color(&color))]);
+                time_control_ui(ui, &mut minutes[color as usize], label, locale);
+            }
+        } else {
+            time_control_ui(ui, &mut minutes[0], locale.get("time-control"), locale);
+            minutes[1] = minutes[0];
+        }
+        let selected = match minutes {
+            [Some(white), Some(black)] => Some([white, black]),
+            // Timing one player of an untimed game times both, and vice versa
+            [Some(minutes), None] | [None, Some(minutes)] if current.is_none() => {
+                Some([minutes; 2])
+            }
+            _ => None,
+        };
+        if selected != current {
+            // Changing the time control starts a new game
+            clock.set_time_controls(
+                selected.map(|minutes| minutes.map(|minutes| Duration::from_secs(minutes * 60))),
+            );
+            commands.push(GameCommand::Reset);
+        }
+
+        let mut threshold = clock.low_time_threshold().as_secs();
+        ui.add(egui::Slider::new(&mut threshold, 0..=120).text(locale.get("low-time-warning")));
+        clock.set_low_time_threshold(Duration::from_secs(threshold));
+
+        ui.checkbox(&mut warning_settings.flash_title, locale.get("flash-title"));
+        ui.checkbox(
+            &mut warning_settings.pause_when_away,
+            locale.get("pause-when-away"),
+        )
+        .on_hover_text(locale.get("pause-when-away-hint"));
+    });
+}
+
+/// What the clocks did when the player left the window.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Away {
+    /// The clocks were stopped until the player came back.
+    Paused,
+    /// The clocks kept running from the given times remaining for white and black.
+    Running([Duration; 2]),
+}
+
+/// The engine, the drill and the network game, which decide whether a game is casual.
+#[derive(SystemParam)]
+pub(super) struct CasualGame<'w> {
+    engine: Res<'w, Engine>,
+    drill: Res<'w, CurrentDrill>,
+    network: Option<Res<'w, NetworkGame>>,
+}
+
+/// Stops the clocks of a casual game while the window is unfocused or minimised, or keeps them
+/// running and warns the player how much time went by once they come back.
+pub(super) fn away_from_window(
+    windows: Query<&Window>,
+    mut clock: ResMut<ChessClock>,
+    board: Res<ChessBoard>,
+    casual_game: CasualGame,
+    warning_settings: Res<ClockWarningSettings>,
+    mut notifications: EventWriter<Notification>,
+    mut away: Local<Option<Away>>,
+) {
+    let CasualGame {
+        engine,
+        drill,
+        network,
+    } = casual_game;
+    let focused = windows.get_single().map_or(true, |window| window.focused);
+    match (*away, focused) {
+        (None, false) => {
+            let running = clock.time_controls().is_some()
+                && board.active_color().is_some()
+                && !board.past_moves().is_empty();
+            if !running {
+                return;
+            }
+            if warning_settings.pause_when_away && network.is_none() && !drill.is_rated(&engine) {
+                clock.set_away(true);
+                *away = Some(Away::Paused);
+            } else {
+                let remaining = [PieceColor::White, PieceColor::Black]
+                    .map(|color| clock.remaining(&color).unwrap_or_default());
+                *away = Some(Away::Running(remaining));
+            }
+        }
+        (Some(Away::Paused), true) => {
+            clock.set_away(false);
+            *away = None;
+        }
+        (Some(Away::Running(before)), true) => {
+            let elapsed: Duration = PieceColor::iter()
+                .map(|color| {
+                    before[color as usize]
+                        .saturating_sub(clock.remaining(&color).unwrap_or_default())
+                })
+                .sum();
+            if !elapsed.is_zero() {
+                notifications.send(
+                    Notification::warning("clock-ran-while-away")
+                        .with_arg("time", Argument::Text(format_duration(&elapsed))),
+                );
+            }
+            *away = None;
+        }
+        _ => {}
+    }
+}
+
+/// Keeps the app updating while a clock is running, as the app otherwise only updates on input.
+pub(super) fn clock_redraw(
+    clock: Res<ChessClock>,
+    board: Res<ChessBoard>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    if clock.time_controls().is_some()
+        && board.active_color().is_some()
+        && !board.past_moves().is_empty()
+    {
+        redraw_events.send(RequestRedraw);
+    }
+}
+
+/// Plays a ticking sound for each second a player is low on time.
+pub(super) fn low_time_audio(
+    mut events: EventReader<LowTimeEvent>,
+    audio_settings: Res<AudioSettings>,
+    sound_packs: Res<SoundPacks>,
+    asset_server: Res<AssetServer>,
+    mut commands: Commands,
+) {
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    if let Some((path, volume)) = audio_settings.sound(SoundEvent::LowTime, &sound_packs) {
+        commands.spawn((AudioBundle {
+            source: asset_server.load(path),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+        },));
+    }
+}
+
+/// The clock and the settings of its low time warnings.
+#[derive(SystemParam)]
+pub(super) struct LowTimeWarning<'w> {
+    clock: Res<'w, ChessClock>,
+    warning_settings: Res<'w, ClockWarningSettings>,
+}
+
+/// Flashes the window title every other second while the player to move is low on time,
+/// otherwise showing the title with the [turn indicator](TurnIndicator).
+pub(super) fn low_time_title_flash(
+    mut events: EventReader<LowTimeEvent>,
+    warning: LowTimeWarning,
+    board: Res<ChessBoard>,
+    turn_indicator: Res<TurnIndicator>,
+    locale: Res<Localisation>,
+    mut windows: Query<&mut Window>,
+    mut original_title: Local<Option<String>>,
+) {
+    let LowTimeWarning {
+        clock,
+        warning_settings,
+    } = warning;
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    let original_title = original_title.get_or_insert_with(|| window.title.clone());
+    let title = &turn_indicator.title(original_title, &board, &locale);
+    let mut new_title = window.title.clone();
+    for event in events.iter() {
+        new_title = if event.remaining().as_secs().is_multiple_of(2) {
+            format!("{} - {} is low on time!", original_title, event.color())
+        } else {
+            title.clone()
+        };
+    }
+    // Restore the title once the warning no longer applies
+    if !warning_settings.flash_title
+        || !board
+            .active_color()
+            .is_some_and(|color| clock.is_low(&color))
+    {
+        new_title = title.clone();
+    }
+    if window.title != new_title {
+        window.title = new_title;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(&Duration::from_secs(600)), "10:00");
+        assert_eq!(format_duration(&Duration::from_secs(65)), "1:05");
+        assert_eq!(format_duration(&Duration::from_millis(9750)), "0:09.7");
+    }
+}
@@ -0,0 +1,184 @@
+//! The coach, a window giving the engine's suggested move for the player to move on request
+//! together with a [rule-based explanation](crate::coach) of why it is good.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, Variant};
+use crate::coach::{explain, Reason};
+use crate::engine::Engine;
+use crate::exchange::Target;
+
+use super::blunder::{self, BlunderCheck};
+use super::locale::Localisation;
+
+/// The last hint given by the coach.
+struct Hint {
+    /// The position the hint was given in, as a FEN string.
+    position: String,
+    /// The suggested move and the reasons for it, or None if there were no legal moves.
+    suggestion: Option<(Move, Vec<Reason>)>,
+}
+
+#[derive(Resource, Default)]
+pub(super) struct CoachState {
+    hint: Option<Hint>,
+}
+
+/// Returns the given string with the piece and square of the given target filled in.
+fn format_target(locale: &Localisation, key: &str, (piece_type, position): &Target) -> String {
+    locale.format(
+        key,
+        &[
+            ("piece", &locale.piece(piece_type)),
+            ("square", &position.to_algebraic()),
+        ],
+    )
+}
+
+/// Returns the localised phrase describing the given reason, e.g. "develops a piece".
+fn reason_text(reason: &Reason, locale: &Localisation) -> String {
+    match reason {
+        Reason::Checkmate => locale.get("reason-checkmate").to_string(),
+        Reason::Check => locale.get("reason-check").to_string(),
+        Reason::Captures { target, free: true } => {
+            format_target(locale, "reason-captures-free", target)
+        }
+        Reason::Captures {
+            target,
+            free: false,
+        } => format_target(locale, "reason-captures", target),
+        Reason::Fork { first, second } => locale.format(
+            "reason-fork",
+            &[
+                ("piece", &locale.piece(&first.0)),
+                ("square", &first.1.to_algebraic()),
+                ("other", &locale.piece(&second.0)),
+                ("other_square", &second.1.to_algebraic()),
+            ],
+        ),
+        Reason::AttacksUndefended { target } => {
+            format_target(locale, "reason-attacks-undefended", target)
+        }
+        Reason::Attacks { target } => format_target(locale, "reason-attacks", target),
+        Reason::Escapes { piece_type } => {
+            locale.format("reason-escapes", &[("piece", &locale.piece(piece_type))])
+        }
+        Reason::Defends { target } => format_target(locale, "reason-defends", target),
+        Reason::Castles => locale.get("reason-castles").to_string(),
+        Reason::Develops => locale.get("reason-develops").to_string(),
+        Reason::OccupiesCentre => locale.get("reason-centre").to_string(),
+        Reason::Improves { piece_type } => {
+            locale.format("reason-improves", &[("piece", &locale.piece(piece_type))])
+        }
+    }
+}
+
+/// Returns the explanation of the given move, e.g. "Nc3 develops a piece and attacks the
+/// undefended pawn on d5."
+fn explanation(piece_move: &Move, reasons: &[Reason], locale: &Localisation) -> String {
+    let mut phrases: Vec<String> = reasons
+        .iter()
+        .map(|reason| reason_text(reason, locale))
+        .collect();
+    let last = phrases.pop().unwrap_or_default();
+    let reasons = match phrases.is_empty() {
+        true => last,
+        false => format!(
+            "{} {} {}",
+            phrases.join(", "),
+            locale.get("coach-and"),
+            last
+        ),
+    };
+    locale.format(
+        "coach-explanation",
+        &[
+            ("move", &locale.san(&piece_move.as_algebraic())),
+            ("reasons", &reasons),
+        ],
+    )
+}
+
+/// Shows the coach, collapsed until it is opened, with the beginner assists. A hint is only shown
+/// while the position it was given in is on the board.
+pub(super) fn coach_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<CoachState>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+    locale: Res<Localisation>,
+    mut blunder_check: ResMut<BlunderCheck>,
+) {
+    let position = board.to_fen().to_string();
+    if state
+        .hint
+        .as_ref()
+        .is_some_and(|hint| hint.position != position)
+    {
+        state.hint = None;
+    }
+    egui::Window::new(locale.get("coach"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            blunder::settings_ui(ui, &mut blunder_check, &locale);
+            // The engine does not play duck chess, so it cannot suggest moves in it
+            let can_hint = board.active_color().is_some() && *board.variant() != Variant::Duck;
+            if ui
+                .add_enabled(can_hint, egui::Button::new(locale.get("hint")))
+                .clicked()
+            {
+                let suggestion = engine
+                    .hint(&board)
+                    .map(|piece_move| (piece_move, explain(&board, &piece_move)));
+                state.hint = Some(Hint {
+                    position,
+                    suggestion,
+                });
+            }
+            match state.hint.as_ref().map(|hint| &hint.suggestion) {
+                Some(Some((piece_move, reasons))) => {
+                    ui.label(explanation(piece_move, reasons, &locale));
+                }
+                Some(None) => {
+                    ui.label(locale.get("coach-no-hint"));
+                }
+                None => {}
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::{BoardPosition, PieceType};
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_explanation() {
+        let locale = Localisation::default();
+        let board = ChessBoard::with_position(&Fen::from_string(
+            "rnb1kbnr/ppp1pppp/8/3p4/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+        ));
+        let piece_move = Move::from_algebraic("Nc3", &board).unwrap();
+        assert_eq!(
+            explanation(&piece_move, &explain(&board, &piece_move), &locale),
+            "Nc3 attacks the undefended pawn on d5 and develops a piece."
+        );
+
+        let fork = Reason::Fork {
+            first: (PieceType::King, BoardPosition::new(0, 4)),
+            second: (PieceType::Rook, BoardPosition::new(0, 0)),
+        };
+        assert_eq!(
+            explanation(
+                &piece_move,
+                &[Reason::Check, fork, Reason::Develops],
+                &locale
+            ),
+            "Nc3 gives check, forks the king on e8 and the rook on a8 and develops a piece."
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! The debug console, a window tailing the most recent entries of the [log](crate::logging),
+//! optionally only those of one subsystem.
+
+use bevy::log::Level;
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::logging::{LogBuffer, LogEntry};
+
+use super::locale::Localisation;
+
+/// The height of the list of entries.
+const LOG_HEIGHT: f32 = 300.0;
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct ConsoleState {
+    /// The subsystem whose entries are shown, or None to show every entry.
+    subsystem: Option<String>,
+}
+
+/// Returns the subsystems which logged any of the given entries, in order of name.
+fn subsystems(entries: &[LogEntry]) -> Vec<&str> {
+    let mut subsystems: Vec<&str> = entries.iter().map(LogEntry::subsystem).collect();
+    subsystems.sort_unstable();
+    subsystems.dedup();
+    subsystems
+}
+
+/// Returns the color of the entries of the given level, where information keeps the text color.
+fn level_color(level: Level) -> Option<egui::Color32> {
+    match level {
+        Level::ERROR => Some(egui::Color32::RED),
+        Level::WARN => Some(egui::Color32::from_rgb(255, 165, 0)),
+        Level::INFO => None,
+        Level::DEBUG | Level::TRACE => Some(egui::Color32::GRAY),
+    }
+}
+
+/// Shows the debug console, collapsed until it is opened.
+pub(super) fn console_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<ConsoleState>,
+    buffer: Res<LogBuffer>,
+    locale: Res<Localisation>,
+) {
+    egui::Window::new(locale.get("debug-console"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let entries = buffer.entries();
+            let selected = state.subsystem.clone();
+            egui::ComboBox::from_label(locale.get("subsystem"))
+                .selected_text(selected.as_deref().unwrap_or(locale.get("all-subsystems")))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut state.subsystem, None, locale.get("all-subsystems"));
+                    for subsystem in subsystems(&entries) {
+                        ui.selectable_value(
+                            &mut state.subsystem,
+                            Some(subsystem.to_string()),
+                            subsystem,
+                        );
+                    }
+                });
+            egui::ScrollArea::vertical()
+                .max_height(LOG_HEIGHT)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    let shown = entries.iter().filter(|entry| {
+                        selected
+                            .as_deref()
+                            .is_none_or(|subsystem| entry.subsystem() == subsystem)
+                    });
+                    for entry in shown {
+                        let mut text = egui::RichText::new(entry.to_string()).monospace();
+                        if let Some(color) = level_color(entry.level()) {
+                            text = text.color(color);
+                        }
+                        ui.label(text);
+                    }
+                });
+        });
+}
@@ -0,0 +1,393 @@
+//! The browser of the [games database](crate::database), a window listing the stored games with
+//! filters, from which a game can be reopened for review. Games ticked in the list can be queued
+//! for [batch analysis](crate::batch_analysis), whose progress is shown above the list, and the
+//! biggest swing each player allowed is shown for the games analysed.
+//!
+//! Each game is listed with a [thumbnail](crate::thumbnail) of the position it ended in, shown
+//! larger on hovering over it. Thumbnails are only loaded for the rows scrolled into view, a few
+//! a frame, from the cache on disk or drawn afresh, and kept as textures while the app runs.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use bevy::log::warn;
+use bevy::prelude::{EventWriter, Res, ResMut, Resource};
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::batch_analysis::{BatchAnalysis, MAX_WORKERS};
+use crate::chess_board::{ChessBoard, PieceColor};
+use crate::database::{
+    GameFilter, GamesDatabase, Opponent, PlayerResult, ReviewGameEvent, StoredGame,
+};
+use crate::thumbnail::{self, Thumbnail};
+
+use super::locale::Localisation;
+use super::status_key;
+
+/// The most thumbnails loaded in a frame, so scrolling through many games stays smooth.
+const THUMBNAILS_PER_FRAME: usize = 4;
+
+/// The size of the thumbnails in the list, and on hovering over one.
+const THUMBNAIL_SIZE: f32 = 48.0;
+const PREVIEW_SIZE: f32 = 192.0;
+
+/// The textures of the thumbnails loaded, by the fingerprints of their games, or None for games
+/// whose moves cannot be replayed.
+#[derive(Default)]
+struct ThumbnailTextures(HashMap<u64, Option<egui::TextureHandle>>);
+
+impl fmt::Debug for ThumbnailTextures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ThumbnailTextures({} loaded)", self.0.len())
+    }
+}
+
+impl ThumbnailTextures {
+    /// Returns the texture of the thumbnail of the given game, loading it if fewer than
+    /// [THUMBNAILS_PER_FRAME] have been loaded this frame, or None if it is still to be loaded.
+    fn get(
+        &mut self,
+        ctx: &egui::Context,
+        game: &StoredGame,
+        loaded: &mut usize,
+    ) -> Option<Option<egui::TextureId>> {
+        let key = game.fingerprint();
+        let texture = match self.0.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(_) if *loaded >= THUMBNAILS_PER_FRAME => return None,
+            Entry::Vacant(entry) => {
+                *loaded += 1;
+                entry.insert(load_thumbnail(key, game).map(|thumbnail| {
+                    let image = egui::ColorImage::from_rgb(
+                        [thumbnail.width(), thumbnail.height()],
+                        thumbnail.pixels(),
+                    );
+                    ctx.load_texture(format!("thumbnail-{:016x}", key), image, Default::default())
+                }))
+            }
+        };
+        Some(texture.as_ref().map(egui::TextureHandle::id))
+    }
+}
+
+/// Returns the thumbnail of the given game with the given fingerprint from the cache, or draws
+/// and caches it, or returns None if its moves cannot be replayed.
+fn load_thumbnail(key: u64, game: &StoredGame) -> Option<Thumbnail> {
+    if let Some(thumbnail) = thumbnail::cached(key) {
+        return Some(thumbnail);
+    }
+    let thumbnail = Thumbnail::render(&ChessBoard::with_position(&game.final_position()?));
+    // A thumbnail which cannot be cached is only drawn again the next time the app runs
+    if let Err(error) = thumbnail::cache(key, &thumbnail) {
+        warn!(%error, "Could not cache the thumbnail of a game");
+    }
+    Some(thumbnail)
+}
+
+/// Draws the thumbnail of the given game if it is in view, returning whether it is still to be
+/// loaded.
+fn thumbnail_ui(
+    ui: &mut egui::Ui,
+    game: &StoredGame,
+    thumbnails: &mut ThumbnailTextures,
+    loaded: &mut usize,
+) -> bool {
+    let (rect, response) =
+        ui.allocate_exact_size(egui::Vec2::splat(THUMBNAIL_SIZE), egui::Sense::hover());
+    if !ui.is_rect_visible(rect) {
+        return false;
+    }
+    match thumbnails.get(ui.ctx(), game, loaded) {
+        Some(Some(texture)) => {
+            egui::Image::new(texture, rect.size()).paint_at(ui, rect);
+            response.on_hover_ui(|ui| {
+                ui.image(texture, egui::Vec2::splat(PREVIEW_SIZE));
+            });
+            false
+        }
+        Some(None) => false,
+        None => {
+            ui.painter()
+                .rect_filled(rect, 0.0, ui.visuals().faint_bg_color);
+            true
+        }
+    }
+}
+
+/// The state of the database browser.
+#[derive(Resource, Debug, Default)]
+pub(super) struct DatabaseBrowserState {
+    filter: GameFilter,
+    /// The indices of the games ticked for batch analysis.
+    selected: BTreeSet<usize>,
+    thumbnails: ThumbnailTextures,
+}
+
+/// Returns the localised name of the given opponent.
+fn opponent_name(opponent: &Opponent, locale: &Localisation) -> String {
+    match opponent {
+        Opponent::Human => locale.get("opponent-human").to_string(),
+        Opponent::Engine { depth } => locale.format("opponent-engine", &[("depth", depth)]),
+    }
+}
+
+fn result_name<'a>(result: &PlayerResult, locale: &'a Localisation) -> &'a str {
+    locale.get(match result {
+        PlayerResult::Won => "result-won",
+        PlayerResult::Lost => "result-lost",
+        PlayerResult::Drawn => "result-drawn",
+    })
+}
+
+/// Returns what the list shows of the analysis of the given game of the given index.
+fn analysis_text(
+    index: usize,
+    game: &StoredGame,
+    batch: &BatchAnalysis,
+    locale: &Localisation,
+) -> String {
+    if batch.analysing() == Some(index) {
+        return locale.get("analysing").to_string();
+    }
+    if batch.contains(index) {
+        return locale.get("queued").to_string();
+    }
+    let Some(analysis) = game.analysis() else {
+        return "-".to_string();
+    };
+    let [white, black] = analysis
+        .mistakes
+        .map(|mistake| mistake.map_or("-".to_string(), |mistake| mistake.swing().to_string()));
+    locale.format("analysis-swings", &[("white", &white), ("black", &black)])
+}
+
+/// Draws the controls of the batch analysis, queueing the ticked games, and its progress.
+fn batch_ui(
+    ui: &mut egui::Ui,
+    batch: &mut BatchAnalysis,
+    selected: &mut BTreeSet<usize>,
+    locale: &Localisation,
+) {
+    ui.horizontal(|ui| {
+        if ui
+            .add_enabled(
+                !selected.is_empty(),
+                egui::Button::new(locale.format("analyse-selected", &[("games", &selected.len())])),
+            )
+            .on_hover_text(locale.get("analyse-selected-hint"))
+            .clicked()
+        {
+            batch.enqueue(std::mem::take(selected));
+        }
+        ui.add(
+            egui::Slider::new(&mut batch.workers, 1..=MAX_WORKERS)
+                .text(locale.get("analysis-workers")),
+        );
+    });
+    let Some((analysed, total, fraction)) = batch.progress() else {
+        return;
+    };
+    ui.horizontal(|ui| {
+        let pause = match batch.paused {
+            true => "resume-analysis",
+            false => "pause-analysis",
+        };
+        if ui.button(locale.get(pause)).clicked() {
+            batch.paused = !batch.paused;
+        }
+        if ui.button(locale.get("cancel-analysis")).clicked() {
+            batch.clear();
+        }
+        ui.add(
+            egui::ProgressBar::new((analysed as f32 + fraction) / total as f32).text(
+                locale.format(
+                    "batch-progress",
+                    &[("game", &(analysed + 1)), ("total", &total)],
+                ),
+            ),
+        );
+    });
+}
+
+/// Draws a dropdown choosing one of the given options, or any of them.
+fn filter_combo<T: PartialEq + Clone>(
+    ui: &mut egui::Ui,
+    label: &str,
+    selected: &mut Option<T>,
+    options: &[T],
+    name: impl Fn(&T) -> String,
+    locale: &Localisation,
+) {
+    let any = locale.get("any").to_string();
+    egui::ComboBox::from_label(label)
+        .selected_text(selected.as_ref().map_or(any.clone(), &name))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(selected, None, any);
+            for option in options {
+                ui.selectable_value(selected, Some(option.clone()), name(option));
+            }
+        });
+}
+
+/// Shows the database browser, collapsed until it is opened.
+pub(super) fn database_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<DatabaseBrowserState>,
+    database: Res<GamesDatabase>,
+    mut batch: ResMut<BatchAnalysis>,
+    locale: Res<Localisation>,
+    mut review_events: EventWriter<ReviewGameEvent>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    let DatabaseBrowserState {
+        filter,
+        selected,
+        thumbnails,
+    } = &mut *state;
+    egui::Window::new(locale.get("games-database"))
+        .default_open(false)
+        .anchor(egui::Align2::CENTER_BOTTOM, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                filter_combo(
+                    ui,
+                    locale.get("player"),
+                    &mut filter.player,
+                    &database.players(),
+                    String::clone,
+                    &locale,
+                );
+                filter_combo(
+                    ui,
+                    locale.get("opponent"),
+                    &mut filter.opponent,
+                    &database.opponents(),
+                    |opponent| opponent_name(opponent, &locale),
+                    &locale,
+                );
+                let colors: Vec<PieceColor> = PieceColor::iter().collect();
+                filter_combo(
+                    ui,
+                    locale.get("played-as"),
+                    &mut filter.player_color,
+                    &colors,
+                    |color| locale.color(color).to_string(),
+                    &locale,
+                );
+                let results: Vec<PlayerResult> = PlayerResult::iter().collect();
+                filter_combo(
+                    ui,
+                    locale.get("result"),
+                    &mut filter.result,
+                    &results,
+                    |result| result_name(result, &locale).to_string(),
+                    &locale,
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label(locale.get("date-from"));
+                ui.add(egui::TextEdit::singleline(&mut filter.from).desired_width(80.));
+                ui.label(locale.get("date-to"));
+                ui.add(egui::TextEdit::singleline(&mut filter.to).desired_width(80.));
+            });
+            ui.separator();
+            batch_ui(ui, &mut batch, selected, &locale);
+            ui.separator();
+
+            let games: Vec<_> = database.filtered(filter).collect();
+            if games.is_empty() {
+                ui.label(locale.get("no-games"));
+                return;
+            }
+            egui::ScrollArea::vertical()
+                .max_height(300.)
+                .show(ui, |ui| {
+                    egui::Grid::new("games_database")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            let mut all = games.iter().all(|(index, _)| selected.contains(index));
+                            if ui.checkbox(&mut all, "").changed() {
+                                for (index, _) in &games {
+                                    match all {
+                                        true => selected.insert(*index),
+                                        false => selected.remove(index),
+                                    };
+                                }
+                            }
+                            for heading in [
+                                "position",
+                                "date",
+                                "player",
+                                "opponent",
+                                "played-as",
+                                "result",
+                                "moves",
+                                "eco",
+                                "drill",
+                                "analysis",
+                            ] {
+                                ui.strong(locale.get(heading));
+                            }
+                            ui.end_row();
+                            let mut loaded = 0;
+                            let mut loading = false;
+                            for (index, game) in games {
+                                let mut ticked = selected.contains(&index);
+                                if ui.checkbox(&mut ticked, "").changed() {
+                                    match ticked {
+                                        true => selected.insert(index),
+                                        false => selected.remove(&index),
+                                    };
+                                }
+                                loading |= thumbnail_ui(ui, game, thumbnails, &mut loaded);
+                                ui.label(game.date());
+                                ui.label(game.player().as_deref().unwrap_or("-"));
+                                ui.label(opponent_name(game.opponent(), &locale));
+                                ui.label(match game.player_color() {
+                                    Some(color) => locale.color(color),
+                                    None => locale.get("both"),
+                                });
+                                ui.label(result_name(&game.result(), &locale))
+                                    .on_hover_text(locale.get(status_key(game.status())));
+                                ui.label(game.move_count().to_string());
+                                match game.opening() {
+                                    Some(opening) => {
+                                        ui.label(opening.code()).on_hover_text(opening.name());
+                                    }
+                                    None => {
+                                        ui.label("-");
+                                    }
+                                }
+                                match game.drill() {
+                                    Some(drill) => {
+                                        let from = drill
+                                            .game
+                                            .and_then(|game| database.get(game))
+                                            .map_or("-", |game| game.date());
+                                        ui.label(locale.format(
+                                            "drill-from",
+                                            &[("date", &from), ("ply", &drill.ply)],
+                                        ));
+                                    }
+                                    None => {
+                                        ui.label("-");
+                                    }
+                                }
+                                ui.label(analysis_text(index, game, &batch, &locale))
+                                    .on_hover_text(locale.get("analysis-swings-hint"));
+                                if ui.button(locale.get("review")).clicked() {
+                                    review_events.send(ReviewGameEvent::new(index));
+                                }
+                                ui.end_row();
+                            }
+                            // The rest of the thumbnails in view are loaded in the next frames
+                            if loading {
+                                redraw_events.send(RequestRedraw);
+                            }
+                        });
+                });
+        });
+}
@@ -0,0 +1,232 @@
+//! The dead draw adjudicator, turned on in the engine settings, which offers to end a casual game
+//! as a draw once the players are only shuffling their pieces: when the evaluation has stayed
+//! within [LEVEL_WITHIN] of equal and no pawn has moved and nothing has been taken for the chosen
+//! number of moves, well before the fifty-move rule.
+//!
+//! Both players must agree. The engine agrees to end its games, as the position is level, so
+//! against the engine only the player is asked; at the same board each player confirms in turn.
+//! Rated games, network games and games through the bridge are left to the players' own draw
+//! offers. Once the offer is declined it is made again only after as many more quiet moves.
+
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{DetectChanges, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::analysis::Analysis;
+use crate::bridge::Bridge;
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor};
+use crate::database::CurrentDrill;
+use crate::endgames::{halfmove_clock, FIFTY_MOVE_PLIES};
+use crate::engine::Engine;
+use crate::network::NetworkGame;
+
+use super::locale::Localisation;
+
+/// The largest evaluation in centipawns, either way, at which the position is level.
+const LEVEL_WITHIN: i32 = 15;
+
+#[derive(Resource, Debug)]
+pub(super) struct DeadDrawAdjudicator {
+    enabled: bool,
+    /// The number of quiet moves of each player after which the draw is offered.
+    moves: usize,
+    /// The evaluation of each position of the game, from white's point of view, by the number
+    /// of moves which reached it.
+    evaluations: BTreeMap<usize, i32>,
+    /// The players who have agreed to the draw being offered.
+    agreed: [bool; 2],
+    /// The number of moves made when the draw was last declined.
+    declined_at: Option<usize>,
+}
+
+impl Default for DeadDrawAdjudicator {
+    fn default() -> Self {
+        DeadDrawAdjudicator {
+            enabled: false,
+            moves: 20,
+            evaluations: BTreeMap::new(),
+            agreed: [false; 2],
+            declined_at: None,
+        }
+    }
+}
+
+impl DeadDrawAdjudicator {
+    /// Returns the number of quiet plies after which the draw is offered.
+    fn plies(&self) -> usize {
+        self.moves * 2
+    }
+
+    /// Returns whether the game of the given moves is a dead draw: quiet for long enough, with
+    /// at least half of the quiet positions evaluated and all of those level.
+    fn is_dead_draw(&self, moves: &[Move]) -> bool {
+        let quiet = halfmove_clock(moves);
+        if quiet < self.plies()
+            || self
+                .declined_at
+                .is_some_and(|declined_at| moves.len() < declined_at + self.plies())
+        {
+            return false;
+        }
+        let evaluated: Vec<i32> = self
+            .evaluations
+            .range(moves.len() - quiet..=moves.len())
+            .map(|(_, evaluation)| *evaluation)
+            .collect();
+        evaluated.len() * 2 >= quiet
+            && evaluated
+                .iter()
+                .all(|evaluation| evaluation.abs() <= LEVEL_WITHIN)
+    }
+}
+
+/// Draws the setting turning the adjudicator on, and the number of quiet moves it waits for.
+pub(super) fn settings_ui(
+    ui: &mut egui::Ui,
+    adjudicator: &mut DeadDrawAdjudicator,
+    locale: &Localisation,
+) {
+    ui.checkbox(&mut adjudicator.enabled, locale.get("offer-dead-draws"))
+        .on_hover_text(locale.get("offer-dead-draws-hint"));
+    ui.add_enabled(
+        adjudicator.enabled,
+        egui::Slider::new(&mut adjudicator.moves, 5..=FIFTY_MOVE_PLIES / 2 - 1)
+            .text(locale.get("after-quiet-moves")),
+    );
+}
+
+/// Ends the game on the board as a draw, answering any draw offer or offering and accepting one.
+fn agree_draw(board: &ChessBoard, commands: &mut GameCommands) {
+    let offered_by = board.draw_offer().unwrap_or(PieceColor::White);
+    if board.draw_offer().is_none() {
+        commands.push(GameCommand::OfferDraw(offered_by));
+    }
+    commands.push(GameCommand::RespondDraw {
+        color: offered_by.opposite(),
+        accept: true,
+    });
+}
+
+/// Who the game is played against, which decides whether it is casual: the engine, a drill, a
+/// network game or a game through the bridge.
+#[derive(SystemParam)]
+pub(super) struct Opponents<'w> {
+    engine: Res<'w, Engine>,
+    drill: Res<'w, CurrentDrill>,
+    network: Option<Res<'w, NetworkGame>>,
+    bridge: Option<Res<'w, Bridge>>,
+}
+
+/// Records the evaluation of each position of a casual game, and asks the players whether to end
+/// it as a draw once it is dead.
+pub(super) fn dead_draw_window(
+    mut contexts: EguiContexts,
+    mut adjudicator: ResMut<DeadDrawAdjudicator>,
+    board: Res<ChessBoard>,
+    analysis: Res<Analysis>,
+    opponents: Opponents,
+    mut commands: ResMut<GameCommands>,
+    locale: Res<Localisation>,
+) {
+    let Opponents {
+        engine,
+        drill,
+        network,
+        bridge,
+    } = opponents;
+    let moves = board.past_moves();
+    if board.is_changed() {
+        adjudicator.agreed = [false; 2];
+        if moves.is_empty() {
+            adjudicator.evaluations.clear();
+            adjudicator.declined_at = None;
+        }
+        // Positions after those on the board were taken back or left
+        adjudicator.evaluations.split_off(&(moves.len() + 1));
+    }
+    if let Some(evaluation) = analysis.current() {
+        adjudicator
+            .evaluations
+            .insert(moves.len(), evaluation.score);
+    }
+    let casual = network.is_none() && bridge.is_none() && !drill.is_rated(&engine);
+    if !adjudicator.enabled
+        || !casual
+        || board.active_color().is_none()
+        || !adjudicator.is_dead_draw(moves)
+    {
+        return;
+    }
+    // The engine agrees to the draw of its own accord
+    let players: Vec<PieceColor> = PieceColor::iter()
+        .filter(|color| *engine.color() != Some(*color))
+        .collect();
+    egui::Window::new(locale.get("dead-draw"))
+        .collapsible(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(locale.format("dead-draw-prompt", &[("moves", &adjudicator.moves)]));
+            for color in &players {
+                ui.horizontal(|ui| {
+                    let name = locale.color(color);
+                    if adjudicator.agreed[*color as usize] {
+                        ui.label(locale.format("agrees-to-draw", &[("player", &name)]));
+                        return;
+                    }
+                    if ui
+                        .button(locale.format("agree-draw-as", &[("player", &name)]))
+                        .clicked()
+                    {
+                        adjudicator.agreed[*color as usize] = true;
+                    }
+                    if ui.button(locale.get("play-on")).clicked() {
+                        adjudicator.declined_at = Some(moves.len());
+                    }
+                });
+            }
+        });
+    if players
+        .iter()
+        .all(|color| adjudicator.agreed[*color as usize])
+    {
+        agree_draw(&board, &mut commands);
+        adjudicator.agreed = [false; 2];
+        adjudicator.declined_at = Some(moves.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_is_dead_draw() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        for text in ["e4", "e5"]
+            .into_iter()
+            .chain(["Nf3", "Nc6", "Ng1", "Nb8"].into_iter().cycle().take(40))
+        {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let moves = board.past_moves();
+        let mut adjudicator = DeadDrawAdjudicator::default();
+        // Without evaluations the game is not known to be level
+        assert!(!adjudicator.is_dead_draw(moves));
+
+        adjudicator.evaluations = (0..=moves.len()).map(|ply| (ply, 10)).collect();
+        assert!(!adjudicator.is_dead_draw(&moves[..moves.len() - 1]));
+        assert!(adjudicator.is_dead_draw(moves));
+        // One position out of level is enough to keep the game going
+        adjudicator.evaluations.insert(20, -40);
+        assert!(!adjudicator.is_dead_draw(moves));
+        adjudicator.evaluations.insert(20, 0);
+
+        adjudicator.declined_at = Some(moves.len() - 2);
+        assert!(!adjudicator.is_dead_draw(moves));
+    }
+}
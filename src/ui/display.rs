@@ -0,0 +1,199 @@
+//! The display settings, which put the window in fullscreen or without its borders and turn
+//! vsync on and off, and the window's size and position, which are restored when the app starts.
+//!
+//! The settings are saved to [DISPLAY_PATH] as soon as they change. The size and position are
+//! saved when the window is closed, as they change every frame while the window is dragged, and
+//! are only remembered while the window is neither fullscreen nor minimised.
+
+use bevy::math::IVec2;
+use bevy::prelude::{DetectChanges, EventReader, Local, Query, Res, ResMut, Resource};
+use bevy::window::{PresentMode, Window, WindowCloseRequested, WindowMode, WindowPosition};
+use bevy_egui::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+use super::locale::Localisation;
+
+/// The file the display settings are saved to.
+const DISPLAY_PATH: &str = "display.ron";
+
+/// The size and position of the window, in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct WindowGeometry {
+    width: f32,
+    height: f32,
+    /// The position of the top left corner, if the window has been placed.
+    position: Option<(i32, i32)>,
+}
+
+impl WindowGeometry {
+    /// Returns the geometry of the given window, if it is in a window on the screen.
+    fn of(window: &Window) -> Option<Self> {
+        let on_screen = window.resolution.width() > 0. && window.resolution.height() > 0.;
+        (window.mode == WindowMode::Windowed && on_screen).then(|| WindowGeometry {
+            width: window.resolution.width(),
+            height: window.resolution.height(),
+            position: match window.position {
+                WindowPosition::At(position) => Some((position.x, position.y)),
+                _ => None,
+            },
+        })
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct DisplaySettings {
+    fullscreen: bool,
+    /// Whether the window is drawn without its title bar and borders when it is not fullscreen.
+    borderless: bool,
+    vsync: bool,
+    /// Whether the app saves power, as [energy](super::energy) describes.
+    pub(super) energy_saver: bool,
+    geometry: Option<WindowGeometry>,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        DisplaySettings {
+            fullscreen: false,
+            borderless: false,
+            vsync: true,
+            energy_saver: false,
+            geometry: None,
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// Sets the mode, borders and present mode of the given window.
+    fn apply(&self, window: &mut Window) {
+        let mode = match self.fullscreen {
+            true => WindowMode::BorderlessFullscreen,
+            false => WindowMode::Windowed,
+        };
+        let present_mode = match self.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        };
+        window.mode = mode;
+        window.decorations = !self.borderless;
+        window.present_mode = present_mode;
+    }
+
+    /// Returns the settings without the geometry, which is saved separately.
+    fn without_geometry(&self) -> Self {
+        DisplaySettings {
+            geometry: None,
+            ..*self
+        }
+    }
+}
+
+/// Draws the display settings controls.
+pub(super) fn settings_ui(ui: &mut egui::Ui, display: &mut DisplaySettings, locale: &Localisation) {
+    ui.collapsing(locale.get("display"), |ui| {
+        ui.checkbox(&mut display.fullscreen, locale.get("fullscreen"));
+        ui.add_enabled(
+            !display.fullscreen,
+            egui::Checkbox::new(&mut display.borderless, locale.get("borderless")),
+        );
+        ui.checkbox(&mut display.vsync, locale.get("vsync"));
+        ui.checkbox(&mut display.energy_saver, locale.get("energy-saver"))
+            .on_hover_text(locale.get("energy-saver-hint"));
+    });
+}
+
+/// Loads the display settings saved when the app was last closed, and restores the window to
+/// them and to its last size and position.
+pub(super) fn setup(mut display: ResMut<DisplaySettings>, mut windows: Query<&mut Window>) {
+    if let Some(saved) = storage::read(DISPLAY_PATH).and_then(|text| ron::from_str(&text).ok()) {
+        *display = saved;
+    }
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    display.apply(&mut window);
+    if let Some(geometry) = display.geometry {
+        window.resolution.set(geometry.width, geometry.height);
+        if let Some((x, y)) = geometry.position {
+            window.position = WindowPosition::At(IVec2::new(x, y));
+        }
+    }
+}
+
+/// Applies the display settings to the window when they change, and keeps track of its size and
+/// position.
+pub(super) fn update_window(mut display: ResMut<DisplaySettings>, mut windows: Query<&mut Window>) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    if display.is_changed() {
+        display.apply(&mut window);
+    }
+    let geometry = WindowGeometry::of(&window);
+    if geometry.is_some() && geometry != display.geometry {
+        display.geometry = geometry;
+    }
+}
+
+/// Saves the display settings when they change, and with the window's geometry when it is closed.
+pub(super) fn save_display(
+    display: Res<DisplaySettings>,
+    mut close_events: EventReader<WindowCloseRequested>,
+    mut saved: Local<Option<DisplaySettings>>,
+) {
+    let closing = close_events.iter().last().is_some();
+    let Some(last) = *saved else {
+        // The settings the app started with are already saved
+        *saved = Some(*display);
+        return;
+    };
+    let changed = match closing {
+        true => last != *display,
+        false => last.without_geometry() != display.without_geometry(),
+    };
+    if !changed {
+        return;
+    }
+    if let Ok(text) = ron::to_string(&*display) {
+        // A failed save only loses the settings, so it is not reported
+        storage::write(DISPLAY_PATH, &text).ok();
+    }
+    *saved = Some(*display);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_settings() {
+        let mut window = Window::default();
+        let display = DisplaySettings {
+            fullscreen: true,
+            borderless: true,
+            vsync: false,
+            energy_saver: false,
+            geometry: None,
+        };
+        display.apply(&mut window);
+        assert_eq!(window.mode, WindowMode::BorderlessFullscreen);
+        assert!(!window.decorations);
+        assert_eq!(window.present_mode, PresentMode::AutoNoVsync);
+        // The geometry is not remembered while the window is fullscreen
+        assert_eq!(WindowGeometry::of(&window), None);
+
+        window.mode = WindowMode::Windowed;
+        window.resolution.set(800., 600.);
+        window.position = WindowPosition::At(IVec2::new(40, 30));
+        let geometry = WindowGeometry::of(&window).unwrap();
+        assert_eq!(geometry.position, Some((40, 30)));
+
+        // Settings saved before the geometry was remembered are read with the defaults
+        let saved: DisplaySettings = ron::from_str("(fullscreen: true)").unwrap();
+        assert!(saved.vsync);
+        assert_eq!(saved.geometry, None);
+    }
+}
@@ -0,0 +1,89 @@
+//! The duck of [duck chess](crate::chess_board::Variant::Duck), which is drawn on its square and
+//! placed by clicking an empty square once a piece has been moved.
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::ButtonState;
+use bevy::prelude::{
+    default, Assets, Commands, Component, Entity, EventReader, Image, Local, MouseButton, Query,
+    Res, ResMut, Transform, Vec2, With,
+};
+use bevy::sprite::{Sprite, SpriteBundle};
+use bevy::window::Window;
+
+use crate::chess_board::{BoardPosition, ChessBoard, GameCommand, GameCommands};
+
+use super::board::BoardProperties;
+use super::piece::{PieceAppearance, VectorPieces};
+use super::theme::Theme;
+use super::BoardClickEvent;
+
+#[derive(Component)]
+pub(super) struct DuckTag;
+
+/// Places the duck on the clicked square when the player to move is yet to place it.
+pub(super) fn duck_click_handler(
+    mut board_click_events: EventReader<BoardClickEvent>,
+    mut commands: ResMut<GameCommands>,
+    board: Res<ChessBoard>,
+) {
+    for click in board_click_events.iter() {
+        if click.input.button == MouseButton::Left && click.input.state == ButtonState::Pressed {
+            if let Some(position) = click
+                .position
+                .filter(|position| board.valid_duck_square(position))
+            {
+                commands.push(GameCommand::PlaceDuck(position));
+            }
+        }
+    }
+}
+
+/// The resources needed to draw the duck.
+#[derive(SystemParam)]
+pub(super) struct DuckAssets<'w> {
+    board_properties: Res<'w, BoardProperties>,
+    theme: Res<'w, Theme>,
+    vector_pieces: ResMut<'w, VectorPieces>,
+    images: ResMut<'w, Assets<Image>>,
+}
+
+/// Draws the duck on its square, redrawing it when it moves or the piece appearance changes.
+pub(super) fn duck_drawer(
+    query: Query<Entity, With<DuckTag>>,
+    board: Res<ChessBoard>,
+    assets: DuckAssets,
+    windows: Query<&Window>,
+    mut commands: Commands,
+    mut current: Local<Option<(Option<BoardPosition>, PieceAppearance)>>,
+) {
+    let DuckAssets {
+        board_properties,
+        theme,
+        mut vector_pieces,
+        mut images,
+    } = assets;
+    let appearance = PieceAppearance::new(&theme, &board_properties, &vector_pieces, &windows);
+    let duck = *board.duck();
+    if current.replace((duck, appearance)) == Some((duck, appearance)) {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if let Some(position) = duck {
+        let (x, y) = board_properties.position_to_transform(&position);
+        let square_size = board_properties.square_size();
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(square_size)),
+                    ..default()
+                },
+                texture: vector_pieces.duck_image(appearance.size(), &mut images),
+                transform: Transform::from_xyz(x, y, 1.),
+                ..default()
+            },
+            DuckTag,
+        ));
+    }
+}
@@ -0,0 +1,248 @@
+//! The endgame trainer, a window setting up the [theoretical endgames](crate::endgames) against the
+//! engine, which defends, or in the Philidor position attacks, at [OPPONENT_DEPTH] without
+//! resigning or offering draws. The window counts down the fifty-move rule, can show the line the
+//! engine expects from the position, and keeps count of the endgames converted.
+
+use std::collections::HashMap;
+
+use bevy::prelude::{Local, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, ResetBoardEvent};
+use crate::endgames::{halfmove_clock, Endgame, Goal, FIFTY_MOVE_PLIES, OPPONENT_DEPTH};
+use crate::engine::{BackgroundSearch, Engine, EngineConduct};
+use crate::history::PositionHistory;
+
+use super::engine::GameSetup;
+use super::locale::Localisation;
+
+/// The hint line of a position, searched in the background until it is found.
+#[derive(Debug)]
+enum Hint {
+    Searching(BackgroundSearch),
+    Found(Vec<Move>),
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct EndgameTrainer {
+    endgame: Option<Endgame>,
+    /// Whether the endgame's position has been loaded onto the board.
+    started: bool,
+    /// Whether the player met the goal, once the attempt is over.
+    result: Option<bool>,
+    /// The engine's conduct before the attempt, put back once it is over.
+    conduct: Option<EngineConduct>,
+    /// The hint line and the position it is for.
+    hint: Option<(String, Hint)>,
+    /// The number of attempts at each endgame which met its goal, and the number made.
+    results: HashMap<Endgame, (usize, usize)>,
+}
+
+impl EndgameTrainer {
+    /// Follows the attempt on the board, keeping the engine to its part until the attempt is over
+    /// or abandoned.
+    fn update(&mut self, board: &ChessBoard, history: &PositionHistory, engine: &mut Engine) {
+        let Some(endgame) = self.endgame else {
+            return;
+        };
+        if self.result.is_some() {
+            return;
+        }
+        let fen = endgame.fen().to_string();
+        if !self.started {
+            self.started = board.past_moves().is_empty() && board.to_fen().to_string() == fen;
+            return;
+        }
+        if history.start().map(|start| start.to_string()) != Some(fen) {
+            self.finish(engine);
+            self.endgame = None;
+            return;
+        }
+        // The engine's depth is reset with the board, so it is kept here rather than set once
+        if engine.move_depth() != OPPONENT_DEPTH {
+            engine.set_move_depth(Some(OPPONENT_DEPTH));
+        }
+        let conduct = engine.conduct_mut();
+        self.conduct.get_or_insert(*conduct);
+        conduct.resign = false;
+        conduct.draws = false;
+        if let Some(met) = endgame.result(board) {
+            let (converted, attempted) = self.results.entry(endgame).or_default();
+            *converted += met as usize;
+            *attempted += 1;
+            self.result = Some(met);
+            self.finish(engine);
+        }
+    }
+
+    /// Gives the engine back its own depth and conduct.
+    fn finish(&mut self, engine: &mut Engine) {
+        engine.set_move_depth(None);
+        if let Some(conduct) = self.conduct.take() {
+            *engine.conduct_mut() = conduct;
+        }
+    }
+
+    /// Returns the hint line of the given board once it has been found, searching for it if it
+    /// has not been asked for.
+    fn hint(&mut self, board: &ChessBoard, engine: &Engine) -> Option<&[Move]> {
+        let fen = board.to_fen().to_string();
+        if self.hint.as_ref().is_none_or(|(hinted, _)| *hinted != fen) {
+            if let Some((_, Hint::Searching(search))) = &self.hint {
+                search.cancel();
+            }
+            let search = engine.analyse_in_background(board, OPPONENT_DEPTH);
+            self.hint = Some((fen, Hint::Searching(search)));
+        }
+        let (_, hint) = self.hint.as_mut()?;
+        if let Hint::Searching(search) = hint {
+            *hint = Hint::Found(search.try_result()?.0);
+        }
+        match hint {
+            Hint::Found(line) => Some(line),
+            Hint::Searching(_) => None,
+        }
+    }
+}
+
+/// Returns the name of the given endgame.
+fn endgame_name<'a>(endgame: &Endgame, locale: &'a Localisation) -> &'a str {
+    locale.get(match endgame {
+        Endgame::KingQueen => "endgame-king-queen",
+        Endgame::KingRook => "endgame-king-rook",
+        Endgame::KingPawn => "endgame-king-pawn",
+        Endgame::Lucena => "endgame-lucena",
+        Endgame::Philidor => "endgame-philidor",
+    })
+}
+
+/// Returns the given line in standard algebraic notation, played from the given board.
+fn line_text(board: &ChessBoard, line: &[Move], locale: &Localisation) -> String {
+    let mut board = board.clone();
+    line.iter()
+        .map(|piece_move| {
+            board.apply_move(piece_move);
+            let made = board.past_moves().last().unwrap_or(piece_move);
+            locale.san(&made.as_algebraic())
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Shows the endgame trainer, collapsed until it is opened.
+pub(super) fn endgame_trainer_window(
+    mut contexts: EguiContexts,
+    mut trainer: ResMut<EndgameTrainer>,
+    board: Res<ChessBoard>,
+    history: Res<PositionHistory>,
+    locale: Res<Localisation>,
+    setup: GameSetup,
+    mut show_hint: Local<bool>,
+) {
+    let GameSetup {
+        mut commands,
+        mut engine,
+        mut clock,
+    } = setup;
+    trainer.update(&board, &history, &mut engine);
+    egui::Window::new(locale.get("endgame-trainer"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for endgame in Endgame::iter() {
+                    if ui.button(endgame_name(&endgame, &locale)).clicked() {
+                        trainer.finish(&mut engine);
+                        engine.set_color(Some(endgame.player().opposite()));
+                        clock.set_time_control(None);
+                        commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(
+                            endgame.fen(),
+                        ))));
+                        trainer.endgame = Some(endgame);
+                        trainer.started = false;
+                        trainer.result = None;
+                        trainer.hint = None;
+                    }
+                }
+            });
+            if let Some(endgame) = trainer.endgame {
+                let name = endgame_name(&endgame, &locale);
+                match (trainer.result, endgame.goal()) {
+                    (None, Goal::Win) => {
+                        ui.label(locale.format("endgame-win-task", &[("endgame", &name)]));
+                    }
+                    (None, Goal::Draw) => {
+                        ui.label(locale.format("endgame-draw-task", &[("endgame", &name)]));
+                    }
+                    (Some(true), _) => {
+                        ui.label(locale.get("endgame-met"));
+                    }
+                    (Some(false), _) => {
+                        ui.label(locale.get("endgame-missed"));
+                    }
+                }
+                if trainer.result.is_none() {
+                    let left = FIFTY_MOVE_PLIES.saturating_sub(halfmove_clock(board.past_moves()));
+                    ui.label(locale.format("fifty-move-count", &[("moves", &left.div_ceil(2))]));
+                    ui.checkbox(&mut show_hint, locale.get("show-hint-path"))
+                        .on_hover_text(locale.get("show-hint-path-hint"));
+                    if *show_hint {
+                        match trainer.hint(&board, &engine) {
+                            Some(line) => {
+                                ui.label(line_text(&board, line, &locale));
+                            }
+                            None => {
+                                ui.spinner();
+                            }
+                        }
+                    }
+                }
+            }
+            for endgame in Endgame::iter() {
+                if let Some((converted, attempted)) = trainer.results.get(&endgame) {
+                    ui.label(locale.format(
+                        "endgame-results",
+                        &[
+                            ("endgame", &endgame_name(&endgame, &locale)),
+                            ("converted", converted),
+                            ("attempted", attempted),
+                        ],
+                    ));
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trainer_keeps_engine_to_its_part() {
+        let endgame = Endgame::KingQueen;
+        let mut trainer = EndgameTrainer {
+            endgame: Some(endgame),
+            ..Default::default()
+        };
+        let mut engine = Engine::default();
+        let board = ChessBoard::with_position(&endgame.fen());
+        let mut history = PositionHistory::default();
+        history.update(&board);
+        trainer.update(&board, &history, &mut engine);
+        assert!(trainer.started);
+
+        trainer.update(&board, &history, &mut engine);
+        assert_eq!(engine.move_depth(), OPPONENT_DEPTH);
+        assert!(!engine.conduct_mut().resign);
+
+        // Loading another game abandons the attempt and gives the engine back its conduct
+        let other = ChessBoard::with_position(&crate::fen::Fen::default());
+        let mut history = PositionHistory::default();
+        history.update(&other);
+        trainer.update(&other, &history, &mut engine);
+        assert_eq!(trainer.endgame, None);
+        assert_eq!(*engine.conduct_mut(), EngineConduct::default());
+        assert_eq!(engine.move_depth(), engine.depth());
+    }
+}
@@ -0,0 +1,87 @@
+//! The energy saver, a low power mode for laptops chosen in the
+//! [display settings](super::display).
+//!
+//! While it is on, the app no longer wakes for input outside its window and waits longer between
+//! updates when nothing asks for a redraw, so it only updates as often as usual while a piece is
+//! animated or dragged or the engine searches. Analysis searches less deeply, and while the window
+//! is unfocused the app stops redrawing altogether until the window is focused again.
+
+use bevy::ecs::event::Events;
+use bevy::prelude::{DetectChanges, Query, Res, ResMut};
+use bevy::utils::Duration;
+use bevy::window::{RequestRedraw, Window};
+use bevy::winit::{UpdateMode, WinitSettings};
+
+use crate::analysis::Analysis;
+
+use super::display::DisplaySettings;
+
+/// The longest the app waits between updates while its window is focused.
+const FOCUSED_WAIT: Duration = Duration::from_secs(30);
+
+/// The longest the app waits between updates while its window is unfocused, which is long
+/// enough for it to be paused.
+const UNFOCUSED_WAIT: Duration = Duration::from_secs(60 * 60);
+
+/// Returns how the app updates with the energy saver on or off.
+fn winit_settings(energy_saver: bool) -> WinitSettings {
+    if !energy_saver {
+        return WinitSettings::desktop_app();
+    }
+    WinitSettings {
+        focused_mode: UpdateMode::ReactiveLowPower {
+            max_wait: FOCUSED_WAIT,
+        },
+        unfocused_mode: UpdateMode::ReactiveLowPower {
+            max_wait: UNFOCUSED_WAIT,
+        },
+        ..WinitSettings::desktop_app()
+    }
+}
+
+/// Switches the update modes and analysis to the energy saver setting when it changes, and drops
+/// the redraws asked for by animations, searches and the clocks while the window is unfocused.
+/// Runs after everything which asks for redraws.
+pub(super) fn save_energy(
+    display: Res<DisplaySettings>,
+    mut winit: ResMut<WinitSettings>,
+    mut analysis: ResMut<Analysis>,
+    mut redraws: ResMut<Events<RequestRedraw>>,
+    windows: Query<&Window>,
+) {
+    if display.is_changed() {
+        *winit = winit_settings(display.energy_saver);
+        if analysis.throttled != display.energy_saver {
+            analysis.throttled = display.energy_saver;
+        }
+    }
+    let unfocused = windows.get_single().is_ok_and(|window| !window.focused);
+    if display.energy_saver && unfocused {
+        redraws.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_winit_settings() {
+        assert!(matches!(
+            winit_settings(false).focused_mode,
+            UpdateMode::Reactive { .. }
+        ));
+        assert!(matches!(
+            winit_settings(true).focused_mode,
+            UpdateMode::ReactiveLowPower {
+                max_wait: FOCUSED_WAIT
+            }
+        ));
+        assert!(matches!(
+            winit_settings(true).unfocused_mode,
+            UpdateMode::ReactiveLowPower {
+                max_wait: UNFOCUSED_WAIT
+            }
+        ));
+    }
+}
@@ -0,0 +1,346 @@
+//! Controls for the [Engine] and the actions available to the players during a game.
+
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{EventWriter, Res, ResMut};
+use bevy::window::RequestRedraw;
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+
+use crate::analysis::{Analysis, CAPACITY_RANGE};
+use crate::chess_board::{
+    ChessBoard, ChessBoardConfig, GameCommand, GameCommands, PieceColor, ResetBoardEvent, Variant,
+};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::telemetry::GameTelemetry;
+
+use super::actions::ActionEvent;
+use super::dead_draw::{self, DeadDrawAdjudicator};
+use super::integrity::{self, GameIntegrity};
+use super::locale::Localisation;
+use super::piece::EngineMoveAnimation;
+
+/// The node limit hints start from when one is turned on.
+const DEFAULT_NODE_LIMIT: u64 = 100_000;
+
+/// The time limit hints start from when one is turned on.
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(1);
+
+/// The engine, the analysis of the position, the animation of the engine's moves, the queue the
+/// game actions are pushed onto, the [actions](super::actions) of the interface, the board's
+/// options, game integrity, the move timing telemetry, the dead draw adjudicator and, with the
+/// `discord` feature, the rich presence.
+#[derive(SystemParam)]
+pub(super) struct GameActions<'w> {
+    pub(super) actions: EventWriter<'w, ActionEvent>,
+    engine: ResMut<'w, Engine>,
+    analysis: ResMut<'w, Analysis>,
+    move_animation: ResMut<'w, EngineMoveAnimation>,
+    commands: ResMut<'w, GameCommands>,
+    config: Res<'w, ChessBoardConfig>,
+    integrity: ResMut<'w, GameIntegrity>,
+    telemetry: ResMut<'w, GameTelemetry>,
+    dead_draw: ResMut<'w, DeadDrawAdjudicator>,
+    #[cfg(feature = "discord")]
+    presence: ResMut<'w, crate::discord::RichPresence>,
+}
+
+/// The queue the game actions are pushed onto, with the engine and the clock of the game they
+/// start.
+#[derive(SystemParam)]
+pub(super) struct GameSetup<'w> {
+    pub(super) commands: ResMut<'w, GameCommands>,
+    pub(super) engine: ResMut<'w, Engine>,
+    pub(super) clock: ResMut<'w, ChessClock>,
+}
+
+impl GameSetup<'_> {
+    /// Resets the board with the given event, for the player alone.
+    pub(super) fn load_alone(&mut self, reset_event: ResetBoardEvent) {
+        self.engine.set_color(None);
+        self.clock.set_time_control(None);
+        self.commands
+            .push(GameCommand::LoadGame(Box::new(reset_event)));
+    }
+}
+
+/// Draws the engine settings controls.
+pub(super) fn settings_ui(ui: &mut egui::Ui, actions: &mut GameActions, locale: &Localisation) {
+    let engine = &mut actions.engine;
+    ui.collapsing(locale.get("engine"), |ui| {
+        let mut color = *engine.color();
+        egui::ComboBox::from_label(locale.get("engine-plays"))
+            .selected_text(color.map_or(locale.get("nobody"), |color| locale.color(&color)))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut color, None, locale.get("nobody"));
+                for piece_color in PieceColor::iter() {
+                    ui.selectable_value(&mut color, Some(piece_color), locale.color(&piece_color));
+                }
+            });
+        if color != *engine.color() {
+            engine.set_color(color);
+        }
+
+        let mut depth = engine.depth();
+        ui.add(egui::Slider::new(&mut depth, 1..=4).text(locale.get("search-depth")));
+        if depth != engine.depth() {
+            engine.set_depth(depth);
+        }
+        let adaptive = engine.adaptive_mut();
+        ui.checkbox(&mut adaptive.enabled, locale.get("adaptive-strength"))
+            .on_hover_text(locale.get("adaptive-strength-hint"));
+        ui.add_enabled(
+            adaptive.enabled,
+            egui::Slider::new(&mut adaptive.band, 50..=500).text(locale.get("close-within")),
+        );
+
+        #[cfg(feature = "nnue")]
+        {
+            let mut use_network = engine.use_network();
+            ui.add_enabled(
+                engine.has_network(),
+                egui::Checkbox::new(&mut use_network, locale.get("neural-network")),
+            )
+            .on_disabled_hover_text(locale.get("no-network"));
+            engine.set_use_network(use_network);
+        }
+
+        ui.collapsing(locale.get("hint-budget"), |ui| {
+            let mut hint_depth = engine.hint_depth();
+            ui.add(egui::Slider::new(&mut hint_depth, 1..=8).text(locale.get("search-depth")));
+            if hint_depth != engine.hint_depth() {
+                engine.set_hint_depth(hint_depth);
+            }
+            let limits = engine.hint_limits_mut();
+            let mut limit_nodes = limits.nodes.is_some();
+            ui.checkbox(&mut limit_nodes, locale.get("limit-nodes"));
+            let mut nodes = limits.nodes.unwrap_or(DEFAULT_NODE_LIMIT);
+            ui.add_enabled(
+                limit_nodes,
+                egui::Slider::new(&mut nodes, 1_000..=10_000_000)
+                    .logarithmic(true)
+                    .text(locale.get("nodes")),
+            );
+            limits.nodes = limit_nodes.then_some(nodes);
+            let mut limit_time = limits.time.is_some();
+            ui.checkbox(&mut limit_time, locale.get("limit-time"));
+            let mut time = limits.time.unwrap_or(DEFAULT_TIME_LIMIT).as_millis() as u64;
+            ui.add_enabled(
+                limit_time,
+                egui::Slider::new(&mut time, 100..=30_000)
+                    .logarithmic(true)
+                    .text(locale.get("milliseconds")),
+            );
+            limits.time = limit_time.then(|| Duration::from_millis(time));
+        })
+        .header_response
+        .on_hover_text(locale.get("hint-budget-hint"));
+
+        let mut think_time = engine.min_think_time().as_millis() as u64;
+        ui.add(egui::Slider::new(&mut think_time, 0..=3000).text(locale.get("min-think-time")));
+        engine.set_min_think_time(Duration::from_millis(think_time));
+        ui.add(
+            egui::Slider::new(&mut actions.move_animation.speed, 0.0..=20.0)
+                .text(locale.get("animation-speed")),
+        )
+        .on_hover_text(locale.get("animation-speed-hint"));
+
+        let conduct = engine.conduct_mut();
+        ui.checkbox(&mut conduct.resign, locale.get("resign-hopeless"));
+        ui.add_enabled(
+            conduct.resign,
+            egui::Slider::new(&mut conduct.resign_threshold, -3000..=-100)
+                .text(locale.get("resign-below")),
+        );
+        ui.add_enabled(
+            conduct.resign,
+            egui::Slider::new(&mut conduct.resign_moves, 1..=20).text(locale.get("for-moves")),
+        );
+        ui.checkbox(&mut conduct.draws, locale.get("offer-draws"));
+        ui.add_enabled(
+            conduct.draws,
+            egui::Slider::new(&mut conduct.draw_threshold, 0..=200)
+                .text(locale.get("equal-within")),
+        );
+        ui.add_enabled(
+            conduct.draws,
+            egui::Slider::new(&mut conduct.draw_moves, 1..=30).text(locale.get("for-moves")),
+        );
+        dead_draw::settings_ui(ui, &mut actions.dead_draw, locale);
+
+        let cache = &mut actions.analysis.cache;
+        let mut capacity = cache.capacity();
+        ui.add(
+            egui::Slider::new(&mut capacity, CAPACITY_RANGE)
+                .logarithmic(true)
+                .text(locale.get("eval-cache-size")),
+        )
+        .on_hover_text(locale.get("eval-cache-size-hint"));
+        if capacity != cache.capacity() {
+            cache.set_capacity(capacity);
+        }
+        integrity::settings_ui(ui, &mut actions.integrity, &mut actions.telemetry, locale);
+        #[cfg(feature = "discord")]
+        ui.checkbox(
+            &mut actions.presence.enabled,
+            locale.get("discord-presence"),
+        )
+        .on_hover_text(locale.get("discord-presence-hint"));
+    });
+}
+
+/// Draws the draw offer, resignation and take back controls for the player at the board.
+pub(super) fn game_actions_ui(
+    ui: &mut egui::Ui,
+    board: &ChessBoard,
+    actions: &mut GameActions,
+    locale: &Localisation,
+) {
+    let Some(active_color) = *board.active_color() else {
+        return;
+    };
+    // Against the engine the player is the other color, otherwise it is the player to move
+    let player = actions
+        .engine
+        .color()
+        .map_or(active_color, |color| color.opposite());
+    if actions.engine.is_searching() {
+        ui.horizontal(|ui| {
+            ui.label(locale.get("engine-thinking"));
+            if ui
+                .button(locale.get("move-now"))
+                .on_hover_text(locale.get("move-now-hint"))
+                .clicked()
+            {
+                actions.engine.cancel_search();
+            }
+        });
+    }
+    ui.horizontal(|ui| match *board.draw_offer() {
+        Some(color) if color != player => {
+            ui.label(locale.format("offers-draw", &[("player", &locale.color(&color))]));
+            if ui.button(locale.get("accept")).clicked() {
+                actions.commands.push(GameCommand::RespondDraw {
+                    color: player,
+                    accept: true,
+                });
+            }
+            if ui.button(locale.get("decline")).clicked() {
+                actions.commands.push(GameCommand::RespondDraw {
+                    color: player,
+                    accept: false,
+                });
+            }
+        }
+        Some(_) => {
+            ui.label(locale.get("draw-offered"));
+        }
+        None => {
+            if ui.button(locale.get("offer-draw")).clicked() {
+                actions.commands.push(GameCommand::OfferDraw(player));
+            }
+        }
+    });
+    if ui
+        .button(locale.format("resign-as", &[("player", &locale.color(&player))]))
+        .clicked()
+    {
+        actions.commands.push(GameCommand::Resign(player));
+    }
+    // Against the engine the player takes back their own move, which the engine accepts along
+    // with its reply, otherwise the player who last moved asks the player to move
+    let requester = match actions.engine.color() {
+        Some(_) => player,
+        None => active_color.opposite(),
+    };
+    ui.horizontal(|ui| match *board.takeback_request() {
+        Some(color) if color != player => {
+            ui.label(locale.format("requests-takeback", &[("player", &locale.color(&color))]));
+            if ui.button(locale.get("accept")).clicked() {
+                actions.commands.push(GameCommand::RespondTakeback {
+                    color: player,
+                    accept: true,
+                });
+            }
+            if ui.button(locale.get("decline")).clicked() {
+                actions.commands.push(GameCommand::RespondTakeback {
+                    color: player,
+                    accept: false,
+                });
+            }
+        }
+        Some(_) => {
+            ui.label(locale.get("takeback-requested"));
+        }
+        None => {
+            let has_moved = board
+                .past_moves()
+                .iter()
+                .any(|piece_move| *piece_move.piece_color() == requester);
+            if ui
+                .add_enabled(has_moved, egui::Button::new(locale.get("take-back")))
+                .clicked()
+            {
+                actions
+                    .commands
+                    .push(GameCommand::RequestTakeback(requester));
+            }
+        }
+    });
+}
+
+/// Draws the choice of variant, which starts a new game when changed.
+pub(super) fn variant_ui(
+    ui: &mut egui::Ui,
+    board: &ChessBoard,
+    actions: &mut GameActions,
+    locale: &Localisation,
+) {
+    let variant_name = |variant: &Variant| {
+        locale.get(match variant {
+            Variant::Standard => "variant-standard",
+            Variant::Duck => "variant-duck",
+            Variant::Antichess => "variant-antichess",
+        })
+    };
+    let mut variant = *board.variant();
+    egui::ComboBox::from_label(locale.get("variant"))
+        .selected_text(variant_name(&variant))
+        .show_ui(ui, |ui| {
+            for option in &actions.config.variants {
+                ui.selectable_value(&mut variant, *option, variant_name(option));
+            }
+        })
+        .response
+        .on_hover_text(locale.get("variant-engine"));
+    if variant != *board.variant() {
+        actions.commands.push(GameCommand::SetVariant(variant));
+    }
+}
+
+/// Draws the sandbox toggle, with a warning while it is on so the board is not mistaken for a
+/// real game.
+pub(super) fn sandbox_ui(ui: &mut egui::Ui, actions: &mut GameActions, locale: &Localisation) {
+    let mut sandbox = !actions.config.enforce_legality;
+    ui.checkbox(&mut sandbox, locale.get("sandbox"))
+        .on_hover_text(locale.get("sandbox-hint"));
+    if sandbox == actions.config.enforce_legality {
+        actions.commands.push(GameCommand::SetSandbox(sandbox));
+    }
+    if !actions.config.enforce_legality {
+        ui.label(
+            egui::RichText::new(locale.get("sandbox-active"))
+                .strong()
+                .color(egui::Color32::from_rgb(230, 140, 0)),
+        );
+    }
+}
+
+/// Keeps the app updating while the engine searches, as the app otherwise only updates on input.
+pub(super) fn engine_redraw(engine: Res<Engine>, mut redraw_events: EventWriter<RequestRedraw>) {
+    if engine.is_searching() {
+        redraw_events.send(RequestRedraw);
+    }
+}
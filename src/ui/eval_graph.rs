@@ -0,0 +1,209 @@
+//! The evaluation graph under the move list, a line chart of the engine's evaluation of each
+//! position of the game reviewed, from white's point of view, drawn once the game has been
+//! [reviewed](super::review).
+//!
+//! Hovering over the graph shows the move which reached the nearest position and its evaluation,
+//! and clicking it jumps the board to that position. The moves which lost the most are marked in
+//! the colour of the review's [classification](crate::review::Classification) of them, as
+//! inaccuracies, mistakes or blunders. The graph can be panned and
+//! zoomed along the game, double clicking puts it back, and its height is resized by its corner.
+
+use bevy_egui::egui;
+use egui::plot::{AxisBools, Line, Plot, PlotPoints, Points};
+
+use crate::chess_board::{GameCommand, GameCommands, PieceColor};
+use crate::history::PositionHistory;
+use crate::review::Classification;
+
+use super::analysis::evaluation_text;
+use super::annotation::MoveQuality;
+use super::locale::Localisation;
+use super::move_list::MoveListState;
+
+/// The largest evaluation drawn, in pawns either way, so a mate does not flatten the rest.
+const CLAMP: f64 = 10.0;
+
+/// The height of the graph until it is resized.
+const DEFAULT_HEIGHT: f32 = 120.0;
+
+/// The radius of the marks of the moves which lost the most.
+const MARK_RADIUS: f32 = 4.0;
+
+/// Returns the height of the given evaluation on the graph, in pawns.
+fn height_of(score: i32) -> f64 {
+    (score as f64 / 100.0).clamp(-CLAMP, CLAMP)
+}
+
+/// The evaluations of the game reviewed, with the game to jump through.
+#[derive(Debug, Clone)]
+pub(super) struct EvalGraph {
+    history: PositionHistory,
+    /// The evaluation of each position, from white's point of view.
+    evaluations: Vec<i32>,
+    /// The quality of each move, from the first, if it lost enough to be marked.
+    qualities: Vec<Option<MoveQuality>>,
+}
+
+impl EvalGraph {
+    /// Returns the graph of the game of the given history, from the evaluations of its positions,
+    /// each from the point of view of the player to move in it, and the review's classification
+    /// of each of its moves.
+    pub(super) fn new(
+        history: &PositionHistory,
+        evaluations: Vec<i32>,
+        classifications: &[Option<Classification>],
+    ) -> Self {
+        let qualities = classifications
+            .iter()
+            .map(|classification| classification.map(MoveQuality::from))
+            .collect();
+        let evaluations = history
+            .positions()
+            .iter()
+            .zip(evaluations)
+            .map(|(fen, evaluation)| match fen.active_color() {
+                PieceColor::White => evaluation,
+                PieceColor::Black => -evaluation,
+            })
+            .collect();
+        EvalGraph {
+            history: history.clone(),
+            evaluations,
+            qualities,
+        }
+    }
+
+    /// Returns the label shown on hovering over the position of the given index: the move which
+    /// reached it, with its glyph if it was marked, and its evaluation.
+    fn label(&self, index: usize, locale: &Localisation) -> String {
+        let reached = match index {
+            0 => locale.get("position-start").to_string(),
+            _ => locale.san(&self.history.label(index)),
+        };
+        let glyph = index
+            .checked_sub(1)
+            .and_then(|ply| self.qualities[ply])
+            .map_or("", |quality| quality.glyph());
+        format!(
+            "{}{} {}",
+            reached,
+            glyph,
+            evaluation_text(self.evaluations[index])
+        )
+    }
+}
+
+/// Draws the graph of the game reviewed, jumping the board to the position clicked.
+pub(super) fn eval_graph_ui(
+    ui: &mut egui::Ui,
+    graph: &EvalGraph,
+    move_list: &mut MoveListState,
+    commands: &mut GameCommands,
+    locale: &Localisation,
+) {
+    ui.label(locale.get("evaluation-graph"))
+        .on_hover_text(locale.get("evaluation-graph-hint"));
+    let plies = graph.evaluations.len().saturating_sub(1);
+    let heights: Vec<[f64; 2]> = graph
+        .evaluations
+        .iter()
+        .enumerate()
+        .map(|(index, score)| [index as f64, height_of(*score)])
+        .collect();
+    let labels: Vec<String> = (0..graph.evaluations.len())
+        .map(|index| graph.label(index, locale))
+        .collect();
+    let clicked = egui::Resize::default()
+        .id_source("evaluation-graph")
+        .default_height(DEFAULT_HEIGHT)
+        .min_height(DEFAULT_HEIGHT / 2.0)
+        .show(ui, |ui| {
+            Plot::new("evaluation-graph")
+                .allow_zoom(AxisBools::new(true, false))
+                .allow_drag(AxisBools::new(true, false))
+                .allow_scroll(false)
+                .include_x(0.0)
+                .include_x(plies as f64)
+                .include_y(-1.0)
+                .include_y(1.0)
+                .show_x(false)
+                .show_y(false)
+                .label_formatter(move |_, point| {
+                    let index = point.x.round().clamp(0.0, plies as f64) as usize;
+                    labels[index].clone()
+                })
+                .show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(PlotPoints::from(heights.clone())));
+                    for quality in [
+                        MoveQuality::Inaccuracy,
+                        MoveQuality::Mistake,
+                        MoveQuality::Blunder,
+                    ] {
+                        let marked: Vec<[f64; 2]> = graph
+                            .qualities
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, marked)| **marked == Some(quality))
+                            .map(|(ply, _)| heights[ply + 1])
+                            .collect();
+                        plot_ui.points(
+                            Points::new(PlotPoints::from(marked))
+                                .color(quality.egui_color())
+                                .radius(MARK_RADIUS)
+                                .filled(true),
+                        );
+                    }
+                    plot_ui
+                        .plot_clicked()
+                        .then(|| plot_ui.pointer_coordinate())
+                        .flatten()
+                })
+                .inner
+        });
+    if let Some(point) = clicked {
+        let index = point.x.round().clamp(0.0, plies as f64) as usize;
+        commands.push(GameCommand::LoadGame(Box::new(
+            graph.history.reset_event(index),
+        )));
+        let offset = graph.history.moves_before_start();
+        move_list.select(index.checked_sub(1).map(|ply| offset + ply));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::chess_board::ChessBoard;
+    use crate::engine::MATE_SCORE;
+    use crate::fen::Fen;
+    use crate::review::evaluated_mistakes;
+
+    use super::*;
+
+    #[test]
+    fn test_eval_graph() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut history = PositionHistory::default();
+        history.update(&board);
+        for text in ["e4", "e5", "Qh5", "Ke7"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            history.update(&board);
+        }
+        // Black walks into a mate, each evaluation from the point of view of the player to move
+        let evaluations = vec![20, -20, 30, -30, MATE_SCORE - 1];
+        let classifications: Vec<Option<Classification>> =
+            evaluated_mistakes(evaluations.clone(), 4)
+                .map(|mistake| mistake.classification())
+                .collect();
+        let graph = EvalGraph::new(&history, evaluations, &classifications);
+        assert_eq!(graph.evaluations, [20, 20, 30, 30, MATE_SCORE - 1]);
+        assert_eq!(
+            graph.qualities,
+            [None, None, None, Some(MoveQuality::Blunder)]
+        );
+        assert_eq!(height_of(MATE_SCORE - 1), CLAMP);
+
+        let locale = Localisation::default();
+        assert_eq!(graph.label(4, &locale), "2... Ke7?? #1");
+    }
+}
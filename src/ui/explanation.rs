@@ -0,0 +1,267 @@
+//! The end of game explanation, a window shown once a game ends in checkmate or stalemate saying
+//! how it ended, with the squares concerned highlighted on the final position.
+
+use bevy::prelude::{
+    default, Color, Commands, Component, DetectChanges, Entity, Query, Res, ResMut, Resource,
+    Transform, Vec2, With,
+};
+use bevy::sprite::{Sprite, SpriteBundle};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::{BoardPosition, ChessBoard};
+use crate::explanation::{explain, loser, Explanation, Flight, Illegality};
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+
+/// The height of the highlights above the squares, over the heatmap and below the pieces.
+const HIGHLIGHT_Z: f32 = 0.6;
+
+/// The tints of the king explained, the pieces attacking it or its squares, and the squares
+/// its own pieces fill.
+const KING_TINT: Color = Color::rgba(0.9, 0.1, 0.1, 0.55);
+const ATTACKER_TINT: Color = Color::rgba(1.0, 0.55, 0.0, 0.5);
+const COVERED_TINT: Color = Color::rgba(1.0, 0.85, 0.0, 0.4);
+const BLOCKED_TINT: Color = Color::rgba(0.4, 0.4, 0.4, 0.45);
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct GameExplanation {
+    /// The explanation of the game, if it has ended in checkmate or stalemate.
+    explanation: Option<Explanation>,
+    /// Whether the highlights are hidden while the explanation is shown.
+    hide_highlights: bool,
+}
+
+/// Explains the game again whenever it changes.
+pub(super) fn update_explanation(board: Res<ChessBoard>, mut state: ResMut<GameExplanation>) {
+    if board.is_changed() {
+        state.explanation = explain(&board);
+    }
+}
+
+/// Returns the tint of each highlighted square of the explanation, each square once with the
+/// first tint given to it.
+fn highlights(explanation: &Explanation) -> Vec<(BoardPosition, Color)> {
+    let mut squares: Vec<(BoardPosition, Color)> = Vec::new();
+    let mut add = |square: &BoardPosition, tint: Color| {
+        if !squares.iter().any(|(other, _)| other == square) {
+            squares.push((*square, tint));
+        }
+    };
+    match explanation {
+        Explanation::Checkmate {
+            king,
+            checkers,
+            flights,
+        } => {
+            add(king, KING_TINT);
+            checkers
+                .iter()
+                .for_each(|checker| add(checker, ATTACKER_TINT));
+            for (square, flight) in flights {
+                let tint = match flight {
+                    Flight::Occupied => BLOCKED_TINT,
+                    Flight::Covered(_) => COVERED_TINT,
+                };
+                add(square, tint);
+            }
+        }
+        Explanation::Stalemate {
+            king,
+            moves,
+            immobile,
+        } => {
+            add(king, KING_TINT);
+            for (_, illegality) in moves {
+                if let Illegality::Exposes(attackers) = illegality {
+                    attackers
+                        .iter()
+                        .for_each(|attacker| add(attacker, ATTACKER_TINT));
+                }
+            }
+            immobile.iter().for_each(|square| add(square, BLOCKED_TINT));
+        }
+    }
+    squares
+}
+
+#[derive(Component)]
+pub(super) struct ExplanationTag;
+
+/// Redraws the highlights of the explanation whenever it or the board's layout changes.
+pub(super) fn explanation_overlay(
+    query: Query<Entity, With<ExplanationTag>>,
+    mut commands: Commands,
+    state: Res<GameExplanation>,
+    board_properties: Res<BoardProperties>,
+) {
+    if !state.is_changed() && !board_properties.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Some(explanation) = state
+        .explanation
+        .as_ref()
+        .filter(|_| !state.hide_highlights)
+    else {
+        return;
+    };
+    let square_size = board_properties.square_size();
+    for (position, color) in highlights(explanation) {
+        let (x, y) = board_properties.position_to_transform(&position);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(square_size)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(x, y, HIGHLIGHT_Z),
+                ..default()
+            },
+            ExplanationTag,
+        ));
+    }
+}
+
+/// Lists the pieces on the given squares.
+fn pieces(board: &ChessBoard, squares: &[BoardPosition], locale: &Localisation) -> String {
+    squares
+        .iter()
+        .filter_map(|square| {
+            let piece_type = board.get_piece_type(square)?;
+            Some(locale.format(
+                "piece-on",
+                &[
+                    ("piece", &locale.piece(&piece_type)),
+                    ("square", &square.to_algebraic()),
+                ],
+            ))
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Returns the lines of the explanation.
+fn explanation_lines(
+    explanation: &Explanation,
+    board: &ChessBoard,
+    locale: &Localisation,
+) -> Vec<String> {
+    let color = locale.color(&loser(board)).to_string();
+    match explanation {
+        Explanation::Checkmate {
+            checkers, flights, ..
+        } => {
+            let mut lines = vec![
+                locale.format("explanation-checkmate", &[("color", &color)]),
+                locale.format(
+                    "explanation-checkers",
+                    &[("pieces", &pieces(board, checkers, locale))],
+                ),
+            ];
+            lines.extend(flights.iter().map(|(square, flight)| {
+                let square = square.to_algebraic();
+                match flight {
+                    Flight::Occupied => {
+                        locale.format("explanation-flight-occupied", &[("square", &square)])
+                    }
+                    Flight::Covered(attackers) => locale.format(
+                        "explanation-flight-covered",
+                        &[
+                            ("square", &square),
+                            ("pieces", &pieces(board, attackers, locale)),
+                        ],
+                    ),
+                }
+            }));
+            lines
+        }
+        Explanation::Stalemate {
+            moves, immobile, ..
+        } => {
+            let mut lines = vec![locale.format("explanation-stalemate", &[("color", &color)])];
+            lines.extend(moves.iter().map(|(piece_move, illegality)| {
+                let san = locale.san(&piece_move.as_algebraic());
+                match illegality {
+                    Illegality::Exposes(attackers) => locale.format(
+                        "explanation-move-exposes",
+                        &[
+                            ("move", &san),
+                            ("pieces", &pieces(board, attackers, locale)),
+                        ],
+                    ),
+                    Illegality::Castle(failure) => locale.format(failure.key(), &[("move", &san)]),
+                }
+            }));
+            if !immobile.is_empty() {
+                lines.push(locale.format(
+                    "explanation-immobile",
+                    &[("pieces", &pieces(board, immobile, locale))],
+                ));
+            }
+            lines
+        }
+    }
+}
+
+/// Shows the explanation of the game once it has ended in checkmate or stalemate.
+pub(super) fn explanation_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<GameExplanation>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+) {
+    let Some(explanation) = &state.explanation else {
+        return;
+    };
+    let lines = explanation_lines(explanation, &board, &locale);
+    egui::Window::new(locale.get("explanation")).show(contexts.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .show(ui, |ui| {
+                for line in &lines {
+                    ui.label(line);
+                }
+            });
+        // Only changes mark the explanation as changed, so the highlights are not redrawn every
+        // frame
+        let mut show = !state.hide_highlights;
+        ui.checkbox(&mut show, locale.get("explanation-highlight"));
+        if show == state.hide_highlights {
+            state.hide_highlights = !show;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::chess_board::{GameEndStatus, PieceColor};
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_highlights() {
+        let mut board =
+            ChessBoard::with_position(&Fen::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap());
+        board.apply_move(&Move::from_algebraic("Ra8", &board).unwrap());
+        board.end_game(GameEndStatus::Checkmate, Some(PieceColor::White));
+        let squares = highlights(&explain(&board).unwrap());
+        let tint = |text| {
+            let square = BoardPosition::from_algebraic(text).unwrap();
+            squares
+                .iter()
+                .find(|(other, _)| *other == square)
+                .map(|(_, tint)| *tint)
+        };
+        assert_eq!(tint("g8"), Some(KING_TINT));
+        assert_eq!(tint("a8"), Some(ATTACKER_TINT));
+        assert_eq!(tint("f8"), Some(COVERED_TINT));
+        assert_eq!(tint("h7"), Some(BLOCKED_TINT));
+        assert_eq!(tint("e8"), None);
+    }
+}
@@ -0,0 +1,371 @@
+//! Guess the move, a trainer which steps through an imported game and asks the player to guess
+//! each move of one side. A guess of the master's move scores full points, and any other guess
+//! scores by how close the engine's evaluation of it comes to that of the master's move. The
+//! points are added up for each game, so a game can be gone through again to beat the score.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor, ResetBoardEvent};
+use crate::clock::ChessClock;
+use crate::engine::{evaluate_position, Engine};
+use crate::pgn::{read_games, PgnGame};
+
+use super::locale::Localisation;
+
+/// The depth the guesses are evaluated to.
+const DEPTH: u32 = 2;
+
+/// The points for guessing the master's move.
+const MAX_POINTS: u32 = 10;
+
+/// The centipawns a guess may fall short of the master's move for each point lost.
+const CENTIPAWNS_PER_POINT: i32 = 25;
+
+/// Returns the points for the given guess in the position on the given board, where the master
+/// played the given move.
+fn score(board: &ChessBoard, guess: &Move, master: &Move) -> u32 {
+    if guess == master {
+        return MAX_POINTS;
+    }
+    // The evaluations are from the point of view of the player to move after each move
+    let evaluate = |piece_move: &Move| {
+        let mut after = board.clone();
+        after.apply_move(piece_move);
+        -evaluate_position(&after, DEPTH)
+    };
+    let shortfall = evaluate(master).saturating_sub(evaluate(guess)).max(0);
+    // Only the master's move itself is worth full points, even if the engine prefers the guess
+    (MAX_POINTS - 1).saturating_sub((shortfall / CENTIPAWNS_PER_POINT) as u32)
+}
+
+/// The player's last guess and the move the master played instead.
+#[derive(Debug, Clone)]
+struct Guess {
+    guess: String,
+    master: String,
+    points: u32,
+}
+
+/// The points scored on a game, over every time it was gone through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Score {
+    points: u32,
+    possible: u32,
+    guesses: u32,
+}
+
+/// A game being gone through.
+#[derive(Debug, Clone)]
+struct Session {
+    game: PgnGame,
+    /// The name the game's score is kept under.
+    label: String,
+    /// The side whose moves are guessed.
+    color: PieceColor,
+    /// The number of moves of the game on the board when the player is to guess the next one.
+    ply: usize,
+    /// Whether the position of the next guess has been loaded onto the board.
+    started: bool,
+    last: Option<Guess>,
+}
+
+impl Session {
+    fn new(game: PgnGame, label: String, color: PieceColor) -> Self {
+        let mut session = Session {
+            game,
+            label,
+            color,
+            ply: 0,
+            started: false,
+            last: None,
+        };
+        session.ply = session.next_guess(0);
+        session
+    }
+
+    /// Returns the board after the given number of moves of the game.
+    fn board_after(&self, ply: usize) -> ChessBoard {
+        let mut board = self.game.start_board();
+        for piece_move in &self.game.moves()[..ply] {
+            board.apply_move(piece_move);
+        }
+        board
+    }
+
+    /// Returns the number of moves before the first move of the guessed side from the given one
+    /// on, or the length of the game if it has no more.
+    fn next_guess(&self, from: usize) -> usize {
+        let first = self.game.start().active_color();
+        (from..self.game.moves().len())
+            .find(|ply| {
+                let mover = if ply % 2 == 0 {
+                    *first
+                } else {
+                    first.opposite()
+                };
+                mover == self.color
+            })
+            .unwrap_or(self.game.moves().len())
+    }
+
+    fn is_finished(&self) -> bool {
+        self.ply >= self.game.moves().len()
+    }
+
+    /// Returns the event which loads the position of the next guess.
+    fn reset_event(&self) -> ResetBoardEvent {
+        let board = self.board_after(self.ply);
+        ResetBoardEvent::with_past_moves(board.to_fen(), board.past_moves().clone())
+    }
+
+    /// Checks for the player's guess of the next move, returning its points once they have
+    /// made it.
+    fn update(&mut self, board: &ChessBoard) -> Option<u32> {
+        if self.is_finished() {
+            return None;
+        }
+        let moves = self.game.moves();
+        let past_moves = board.past_moves();
+        if !self.started {
+            self.started = past_moves[..] == moves[..self.ply];
+            return None;
+        }
+        if past_moves.len() != self.ply + 1 || past_moves[..self.ply] != moves[..self.ply] {
+            return None;
+        }
+        let guess = past_moves[self.ply];
+        let master = moves[self.ply];
+        let points = score(&self.board_after(self.ply), &guess, &master);
+        self.last = Some(Guess {
+            guess: guess.as_algebraic(),
+            master: master.as_algebraic(),
+            points,
+        });
+        self.ply = self.next_guess(self.ply + 1);
+        self.started = false;
+        Some(points)
+    }
+}
+
+#[derive(Resource, Debug)]
+pub(super) struct GuessTheMove {
+    /// The PGN being typed or pasted to import.
+    text: String,
+    games: Vec<PgnGame>,
+    /// Whether the last import found no games.
+    not_found: bool,
+    /// The index of the game chosen to go through.
+    selected: usize,
+    color: PieceColor,
+    session: Option<Session>,
+    /// The points scored on each game, by the name of the game.
+    scores: BTreeMap<String, Score>,
+}
+
+impl Default for GuessTheMove {
+    fn default() -> Self {
+        GuessTheMove {
+            text: String::new(),
+            games: Vec::new(),
+            not_found: false,
+            selected: 0,
+            color: PieceColor::White,
+            session: None,
+            scores: BTreeMap::new(),
+        }
+    }
+}
+
+impl GuessTheMove {
+    /// Checks for the player's guess, adding its points to the game's score and returning whether
+    /// the position of the next guess should be loaded.
+    fn update(&mut self, board: &ChessBoard) -> bool {
+        let Some(session) = self.session.as_mut() else {
+            return false;
+        };
+        let Some(points) = session.update(board) else {
+            return false;
+        };
+        let score = self.scores.entry(session.label.clone()).or_default();
+        score.points += points;
+        score.possible += MAX_POINTS;
+        score.guesses += 1;
+        !session.is_finished()
+    }
+}
+
+/// Returns the name of the given game, from its players if it has them.
+pub(super) fn game_label(game: &PgnGame, index: usize, locale: &Localisation) -> String {
+    match (game.tag("White"), game.tag("Black")) {
+        (Some(white), Some(black)) => {
+            locale.format("guess-players", &[("white", &white), ("black", &black)])
+        }
+        _ => locale.format("guess-game-number", &[("number", &(index + 1))]),
+    }
+}
+
+/// Resets the board to the position of the next guess, for the player alone.
+fn load_guess(
+    session: &Session,
+    commands: &mut GameCommands,
+    engine: &mut Engine,
+    clock: &mut ChessClock,
+) {
+    engine.set_color(None);
+    clock.set_time_control(None);
+    commands.push(GameCommand::LoadGame(Box::new(session.reset_event())));
+}
+
+/// Shows the guess the move trainer, collapsed until it is opened, with the games imported, the
+/// result of the last guess and the score of each game.
+pub(super) fn guess_move_window(
+    mut contexts: EguiContexts,
+    mut trainer: ResMut<GuessTheMove>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+) {
+    if trainer.update(&board) {
+        if let Some(session) = &trainer.session {
+            load_guess(session, &mut commands, &mut engine, &mut clock);
+        }
+    }
+    egui::Window::new(locale.get("guess-the-move"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let trainer = &mut *trainer;
+            ui.add(
+                egui::TextEdit::multiline(&mut trainer.text)
+                    .desired_rows(4)
+                    .hint_text(locale.get("guess-pgn-hint")),
+            );
+            if ui.button(locale.get("guess-import")).clicked() {
+                trainer.games = read_games(&trainer.text);
+                trainer.not_found = trainer.games.is_empty();
+                trainer.selected = 0;
+            }
+            if trainer.not_found {
+                ui.label(locale.get("guess-no-games"));
+            }
+            if !trainer.games.is_empty() {
+                let selected = trainer.selected.min(trainer.games.len() - 1);
+                egui::ComboBox::from_label(locale.get("guess-game"))
+                    .selected_text(game_label(&trainer.games[selected], selected, &locale))
+                    .show_ui(ui, |ui| {
+                        for (index, game) in trainer.games.iter().enumerate() {
+                            let label = game_label(game, index, &locale);
+                            ui.selectable_value(&mut trainer.selected, index, label);
+                        }
+                    });
+                egui::ComboBox::from_label(locale.get("guess-side"))
+                    .selected_text(locale.color(&trainer.color))
+                    .show_ui(ui, |ui| {
+                        for color in PieceColor::iter() {
+                            ui.selectable_value(&mut trainer.color, color, locale.color(&color));
+                        }
+                    });
+                if ui.button(locale.get("guess-start")).clicked() {
+                    let game = trainer.games[selected].clone();
+                    let label = game_label(&game, selected, &locale);
+                    let session = Session::new(game, label, trainer.color);
+                    load_guess(&session, &mut commands, &mut engine, &mut clock);
+                    trainer.session = Some(session);
+                }
+            }
+            if let Some(session) = &trainer.session {
+                if let Some(last) = &session.last {
+                    let guess = locale.san(&last.guess);
+                    let master = locale.san(&last.master);
+                    ui.label(if last.guess == last.master {
+                        locale.format(
+                            "guess-match",
+                            &[("move", &master), ("points", &last.points)],
+                        )
+                    } else {
+                        locale.format(
+                            "guess-result",
+                            &[
+                                ("guess", &guess),
+                                ("move", &master),
+                                ("points", &last.points),
+                            ],
+                        )
+                    });
+                }
+                if session.is_finished() {
+                    ui.label(locale.get("guess-over"));
+                } else {
+                    ui.label(
+                        locale.format("guess-task", &[("color", &locale.color(&session.color))]),
+                    );
+                }
+                if ui.button(locale.get("guess-stop")).clicked() {
+                    trainer.session = None;
+                }
+            }
+            for (label, score) in &trainer.scores {
+                ui.label(locale.format(
+                    "guess-score",
+                    &[
+                        ("game", label),
+                        ("points", &score.points),
+                        ("possible", &score.possible),
+                        ("guesses", &score.guesses),
+                    ],
+                ));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_the_move() {
+        let pgn = "[White \"Morphy\"]\n[Black \"Allies\"]\n\n1. e4 e5 2. Nf3 d6 3. d4 *\n";
+        let game = read_games(pgn).remove(0);
+        let mut trainer = GuessTheMove {
+            session: Some(Session::new(game, "Morphy".to_string(), PieceColor::Black)),
+            ..Default::default()
+        };
+        assert_eq!(trainer.session.as_ref().unwrap().ply, 1);
+
+        // The guess is only taken once the position has been loaded
+        let mut board = ChessBoard::with_position(&crate::fen::Fen::default());
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        assert!(!trainer.update(&board));
+        assert!(trainer.session.as_ref().unwrap().started);
+
+        // Guessing the master's move scores full points
+        board.apply_move(&Move::from_algebraic("e5", &board).unwrap());
+        assert!(trainer.update(&board));
+        let session = trainer.session.as_ref().unwrap();
+        assert_eq!(session.ply, 3);
+        assert_eq!(session.reset_event().past_moves().len(), 3);
+
+        // A blunder scores nothing
+        let mut board = session.board_after(3);
+        trainer.update(&board);
+        board.apply_move(&Move::from_algebraic("Qg5", &board).unwrap());
+        assert!(!trainer.update(&board));
+        let session = trainer.session.as_ref().unwrap();
+        assert!(session.is_finished());
+        assert_eq!(session.last.as_ref().unwrap().points, 0);
+        assert_eq!(
+            trainer.scores.get("Morphy"),
+            Some(&Score {
+                points: MAX_POINTS,
+                possible: 2 * MAX_POINTS,
+                guesses: 2
+            })
+        );
+    }
+}
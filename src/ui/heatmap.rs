@@ -0,0 +1,275 @@
+//! The square activity heatmap, a review of the game which tints each square by how often the
+//! pieces of each side stood on it or passed over it, counted from the positions and moves of
+//! the [PositionHistory].
+
+use bevy::prelude::{
+    default, Color, Commands, Component, DetectChanges, Entity, Query, Res, ResMut, Resource,
+    Transform, Vec2, With,
+};
+use bevy::sprite::{Sprite, SpriteBundle};
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{BoardPosition, PieceColor, BOARD_SIZE, MAX_FILES};
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+
+/// The height of the heatmap above the squares, below the pieces.
+const HEATMAP_Z: f32 = 0.5;
+
+/// The opacity of the squares with the most activity.
+const MAX_ALPHA: f32 = 0.65;
+
+/// The tints of the activity of each side.
+const WHITE_TINT: [f32; 3] = [1.0, 0.45, 0.0];
+const BLACK_TINT: [f32; 3] = [0.2, 0.4, 1.0];
+
+/// What the heatmap counts.
+#[derive(Clone, Copy, Debug, Default, EnumIter, PartialEq, Eq)]
+pub(super) enum HeatmapMode {
+    /// The number of positions each square held a piece of each side in.
+    #[default]
+    Occupied,
+    /// The number of moves of each side which started on, ended on or passed over each square.
+    Traversed,
+}
+
+impl HeatmapMode {
+    /// Returns the key of the name of the mode.
+    fn label(&self) -> &'static str {
+        match self {
+            HeatmapMode::Occupied => "heatmap-occupied",
+            HeatmapMode::Traversed => "heatmap-traversed",
+        }
+    }
+}
+
+/// The number of squares on the largest board.
+const SQUARES: usize = BOARD_SIZE * MAX_FILES;
+
+/// A count for each side of each square of the largest board, by rank from the 8th and then file.
+type SquareCounts = [[u32; SQUARES]; 2];
+
+fn square_index(position: &BoardPosition) -> usize {
+    position.rank() * MAX_FILES + position.file()
+}
+
+/// Counts the positions in which each square held a piece of each side.
+fn occupied(positions: &[Fen]) -> SquareCounts {
+    let mut counts = [[0; SQUARES]; 2];
+    for fen in positions {
+        for (rank, squares) in fen.piece_placement().iter().enumerate() {
+            for (file, square) in squares.iter().enumerate() {
+                if let Some((color, _)) = square {
+                    counts[*color as usize][rank * MAX_FILES + file] += 1;
+                }
+            }
+        }
+    }
+    counts
+}
+
+/// Returns the squares the given move starts on, passes over and ends on. Only moves along a
+/// rank, file or diagonal pass over squares, so a knight's move is just its start and end.
+fn path(piece_move: &Move) -> Vec<BoardPosition> {
+    let (from, to) = (piece_move.from(), piece_move.to());
+    let rank_step = (*to.rank() as i32 - *from.rank() as i32).signum();
+    let file_step = (*to.file() as i32 - *from.file() as i32).signum();
+    let ranks = to.rank().abs_diff(*from.rank());
+    let files = to.file().abs_diff(*from.file());
+    if ranks != files && ranks != 0 && files != 0 {
+        return vec![*from, *to];
+    }
+    (0..=ranks.max(files) as i32)
+        .map(|step| {
+            BoardPosition::new(
+                (*from.rank() as i32 + step * rank_step) as usize,
+                (*from.file() as i32 + step * file_step) as usize,
+            )
+        })
+        .collect()
+}
+
+/// Counts the moves of each side which started on, ended on or passed over each square.
+fn traversed(moves: &[Move]) -> SquareCounts {
+    let mut counts = [[0; SQUARES]; 2];
+    for piece_move in moves {
+        for position in path(piece_move) {
+            counts[*piece_move.piece_color() as usize][square_index(&position)] += 1;
+        }
+    }
+    counts
+}
+
+/// Returns the tint of a square with the given activity of each side, relative to the most on
+/// any square, or None if it has none.
+fn heat_color(white: f32, black: f32) -> Option<Color> {
+    let total = white + black;
+    if total <= 0.0 {
+        return None;
+    }
+    let [r, g, b] = [0, 1, 2]
+        .map(|channel| (WHITE_TINT[channel] * white + BLACK_TINT[channel] * black) / total);
+    Some(Color::rgba(r, g, b, MAX_ALPHA * white.max(black)))
+}
+
+/// The sides whose activity is shown and what is counted.
+#[derive(Resource, Debug, Default)]
+pub(super) struct Heatmap {
+    white: bool,
+    black: bool,
+    mode: HeatmapMode,
+}
+
+impl Heatmap {
+    /// Returns the tint of each square for the given game, or None for squares without activity
+    /// of the sides shown.
+    fn colors(&self, history: &PositionHistory) -> Vec<Option<Color>> {
+        let counts = match self.mode {
+            HeatmapMode::Occupied => occupied(history.positions()),
+            HeatmapMode::Traversed => traversed(history.moves()),
+        };
+        let shown = |color: PieceColor, count: u32| match color {
+            PieceColor::White if self.white => count as f32,
+            PieceColor::Black if self.black => count as f32,
+            _ => 0.0,
+        };
+        let most = (0..SQUARES)
+            .flat_map(|square| {
+                PieceColor::iter().map(move |color| shown(color, counts[color as usize][square]))
+            })
+            .fold(0.0, f32::max);
+        if most == 0.0 {
+            return vec![None; SQUARES];
+        }
+        (0..SQUARES)
+            .map(|square| {
+                let activity =
+                    |color: PieceColor| shown(color, counts[color as usize][square]) / most;
+                heat_color(activity(PieceColor::White), activity(PieceColor::Black))
+            })
+            .collect()
+    }
+}
+
+#[derive(Component)]
+pub(super) struct HeatmapTag;
+
+/// Redraws the heatmap over the squares whenever the game or what it shows changes.
+pub(super) fn heatmap_overlay(
+    query: Query<Entity, With<HeatmapTag>>,
+    mut commands: Commands,
+    heatmap: Res<Heatmap>,
+    history: Res<PositionHistory>,
+    board_properties: Res<BoardProperties>,
+) {
+    if !heatmap.is_changed() && !history.is_changed() && !board_properties.is_changed() {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !heatmap.white && !heatmap.black {
+        return;
+    }
+    let square_size = board_properties.square_size();
+    for (square, color) in heatmap.colors(&history).into_iter().enumerate() {
+        let Some(color) = color else {
+            continue;
+        };
+        let position = BoardPosition::new(square / MAX_FILES, square % MAX_FILES);
+        let (x, y) = board_properties.position_to_transform(&position);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(square_size)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(x, y, HEATMAP_Z),
+                ..default()
+            },
+            HeatmapTag,
+        ));
+    }
+}
+
+/// Shows the heatmap controls, collapsed until they are opened.
+pub(super) fn heatmap_window(
+    mut contexts: EguiContexts,
+    mut heatmap: ResMut<Heatmap>,
+    locale: Res<Localisation>,
+) {
+    egui::Window::new(locale.get("heatmap"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(locale.get("heatmap-hint"));
+            // Only changes mark the heatmap as changed, so it is not redrawn every frame
+            let (mut white, mut black, mut mode) = (heatmap.white, heatmap.black, heatmap.mode);
+            ui.checkbox(&mut white, locale.color(&PieceColor::White));
+            ui.checkbox(&mut black, locale.color(&PieceColor::Black));
+            egui::ComboBox::from_label(locale.get("heatmap-mode"))
+                .selected_text(locale.get(mode.label()))
+                .show_ui(ui, |ui| {
+                    for option in HeatmapMode::iter() {
+                        ui.selectable_value(&mut mode, option, locale.get(option.label()));
+                    }
+                });
+            if (white, black, mode) != (heatmap.white, heatmap.black, heatmap.mode) {
+                *heatmap = Heatmap { white, black, mode };
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::ChessBoard;
+
+    use super::*;
+
+    #[test]
+    fn test_square_activity() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut positions = vec![board.to_fen()];
+        let mut moves = Vec::new();
+        for text in ["e4", "Nf6", "Bc4"] {
+            let piece_move = Move::from_algebraic(text, &board).unwrap();
+            board.apply_move(&piece_move);
+            positions.push(board.to_fen());
+            moves.push(piece_move);
+        }
+        let e2 = square_index(&BoardPosition::new(6, 4));
+        let e4 = square_index(&BoardPosition::new(4, 4));
+        let d3 = square_index(&BoardPosition::new(5, 3));
+        let g7 = square_index(&BoardPosition::new(1, 6));
+
+        let counts = occupied(&positions);
+        assert_eq!(counts[PieceColor::White as usize][e2], 1);
+        assert_eq!(counts[PieceColor::White as usize][e4], 3);
+        assert_eq!(counts[PieceColor::Black as usize][e4], 0);
+
+        // The bishop passes over d3 and the knight jumps
+        let counts = traversed(&moves);
+        assert_eq!(counts[PieceColor::White as usize][d3], 1);
+        assert_eq!(counts[PieceColor::White as usize][e2], 2);
+        assert_eq!(counts[PieceColor::Black as usize][g7], 0);
+        assert_eq!(counts.iter().flatten().sum::<u32>(), 3 + 2 + 4);
+    }
+
+    #[test]
+    fn test_heat_color() {
+        assert_eq!(heat_color(0.0, 0.0), None);
+        assert_eq!(
+            heat_color(1.0, 0.0),
+            Some(Color::rgba(1.0, 0.45, 0.0, MAX_ALPHA))
+        );
+        let mixed = heat_color(0.5, 0.5).unwrap();
+        assert_eq!(mixed.a(), MAX_ALPHA * 0.5);
+        assert!((mixed.r() - 0.6).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,97 @@
+//! Game integrity, a mode which withholds the evaluation, the coach's hints and the engine's
+//! analysis while a rated or networked game is in progress, for players who want to play on
+//! their own or whose parents want them to.
+//!
+//! A game against the engine is rated for the [player](crate::profile), and a game through the
+//! [bridge](crate::bridge) is played against another program. When such a game ends, or is left
+//! for a new game which is neither, the assistance comes back. The mode cannot be turned off
+//! during the games it applies to.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::egui;
+
+use crate::analysis::Analysis;
+use crate::bridge::Bridge;
+use crate::chess_board::ChessBoard;
+use crate::engine::Engine;
+use crate::telemetry::GameTelemetry;
+
+use super::locale::Localisation;
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct GameIntegrity {
+    enabled: bool,
+    /// Whether the assistance is being withheld.
+    withholding: bool,
+}
+
+/// Returns whether the game on the given board is rated or networked and still in progress.
+fn is_protected(board: &ChessBoard, engine: &Engine, bridged: bool) -> bool {
+    board.active_color().is_some() && (engine.color().is_some() || bridged)
+}
+
+/// Draws the checkbox turning game integrity on, which is locked while it applies, and those
+/// recording the move timing telemetry.
+pub(super) fn settings_ui(
+    ui: &mut egui::Ui,
+    integrity: &mut GameIntegrity,
+    telemetry: &mut GameTelemetry,
+    locale: &Localisation,
+) {
+    let unlocked = !integrity.withholding;
+    ui.add_enabled(
+        unlocked,
+        egui::Checkbox::new(&mut integrity.enabled, locale.get("game-integrity")),
+    )
+    .on_disabled_hover_text(locale.get("game-integrity-locked"));
+    ui.checkbox(&mut telemetry.enabled, locale.get("record-move-times"))
+        .on_hover_text(locale.get("record-move-times-hint"));
+    ui.add_enabled(
+        telemetry.enabled,
+        egui::Checkbox::new(
+            &mut telemetry.record_assistance,
+            locale.get("record-analysis-use"),
+        ),
+    );
+}
+
+/// Withholds the assistance while game integrity is on and the game applies, and suspends the
+/// analysis with it.
+pub(super) fn withhold_assistance(
+    mut integrity: ResMut<GameIntegrity>,
+    mut analysis: ResMut<Analysis>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+    bridge: Option<Res<Bridge>>,
+) {
+    let withholding = integrity.enabled && is_protected(&board, &engine, bridge.is_some());
+    if withholding != integrity.withholding {
+        integrity.withholding = withholding;
+        analysis.suspended = withholding;
+    }
+}
+
+/// Returns whether the assistance is shown, which is the run condition of the systems showing it.
+pub(super) fn assistance_allowed(integrity: Res<GameIntegrity>) -> bool {
+    !integrity.withholding
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::{GameEndStatus, PieceColor};
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_is_protected() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut engine = Engine::default();
+        assert!(!is_protected(&board, &engine, false));
+        assert!(is_protected(&board, &engine, true));
+        engine.set_color(Some(PieceColor::Black));
+        assert!(is_protected(&board, &engine, false));
+        board.end_game(GameEndStatus::Resignation, Some(PieceColor::Black));
+        assert!(!is_protected(&board, &engine, true));
+    }
+}
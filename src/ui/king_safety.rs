@@ -0,0 +1,193 @@
+//! The king safety overlay, turned on in the [analysis window](super::analysis), which shades the
+//! squares around each king by the pressure on them: red where more enemy pieces attack a square
+//! than friendly pieces defend it, and green where it is defended more than it is attacked. The
+//! king itself does not count as a defender, as it cannot hold off an attack alone.
+
+use bevy::prelude::{
+    default, Color, Commands, Component, DetectChanges, Entity, Local, Query, Res, Resource,
+    Transform, Vec2, With,
+};
+use bevy::sprite::{Sprite, SpriteBundle};
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor};
+use crate::engine::Engine;
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+
+/// The height of the shading above the squares, below the pieces.
+const SHADING_Z: f32 = 0.55;
+
+/// The pressure at which the shading is strongest.
+const MAX_PRESSURE: i32 = 3;
+
+/// The opacity of the strongest shading.
+const MAX_ALPHA: f32 = 0.6;
+
+/// The tints of squares attacked more than they are defended, and defended more.
+const ATTACKED_TINT: [u8; 3] = [220, 30, 30];
+const DEFENDED_TINT: [u8; 3] = [30, 170, 60];
+
+/// Returns each square around the king of the given color, with the king's own, and the number of
+/// enemy pieces attacking it less the number of its other pieces defending it.
+fn king_pressure(board: &ChessBoard, color: &PieceColor) -> Vec<(BoardPosition, i32)> {
+    let Some(king) = board.king_position(color) else {
+        return Vec::new();
+    };
+    let size = board.size();
+    let (rank, file) = (*king.rank(), *king.file());
+    (rank.saturating_sub(1)..(rank + 2).min(size.ranks()))
+        .flat_map(|rank| {
+            (file.saturating_sub(1)..(file + 2).min(size.files()))
+                .map(move |file| BoardPosition::new(rank, file))
+        })
+        .map(|position| {
+            let attackers = board.attackers(&position, &color.opposite()).len() as i32;
+            let defenders = board
+                .attackers(&position, color)
+                .iter()
+                .filter(|defender| **defender != king)
+                .count() as i32;
+            (position, attackers - defenders)
+        })
+        .collect()
+}
+
+/// Returns the shading of a square under the given pressure, or None if it is as well defended
+/// as it is attacked.
+fn pressure_color(pressure: i32) -> Option<Color> {
+    let [r, g, b] = match pressure {
+        0 => return None,
+        1.. => ATTACKED_TINT,
+        _ => DEFENDED_TINT,
+    };
+    let strength = pressure.abs().min(MAX_PRESSURE) as f32 / MAX_PRESSURE as f32;
+    Some(Color::rgba_u8(
+        r,
+        g,
+        b,
+        (255.0 * MAX_ALPHA * strength) as u8,
+    ))
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct KingSafetyOverlay {
+    enabled: bool,
+}
+
+impl KingSafetyOverlay {
+    pub(super) fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+}
+
+#[derive(Component)]
+pub(super) struct KingSafetyTag;
+
+/// Redraws the shading around the kings whenever the position or the overlay changes. The
+/// shading is only shown while the engine is not playing, as the analysis is.
+pub(super) fn king_safety_overlay(
+    query: Query<Entity, With<KingSafetyTag>>,
+    mut commands: Commands,
+    overlay: Res<KingSafetyOverlay>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+    board_properties: Res<BoardProperties>,
+    mut shown: Local<bool>,
+) {
+    let show = overlay.enabled && engine.color().is_none();
+    if show == *shown && !(show && (board.is_changed() || board_properties.is_changed())) {
+        return;
+    }
+    *shown = show;
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !show {
+        return;
+    }
+    let square_size = board_properties.square_size();
+    for color in PieceColor::iter() {
+        for (position, pressure) in king_pressure(&board, &color) {
+            let Some(color) = pressure_color(pressure) else {
+                continue;
+            };
+            let (x, y) = board_properties.position_to_transform(&position);
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(square_size)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(x, y, SHADING_Z),
+                    ..default()
+                },
+                KingSafetyTag,
+            ));
+        }
+    }
+}
+
+/// Draws the toggle of the overlay, with a legend of its shading.
+pub(super) fn overlay_ui(
+    ui: &mut egui::Ui,
+    overlay: &mut KingSafetyOverlay,
+    locale: &Localisation,
+) {
+    ui.checkbox(&mut overlay.enabled, locale.get("king-safety"))
+        .on_hover_text(locale.get("king-safety-hint"));
+    if overlay.enabled {
+        ui.horizontal_wrapped(|ui| {
+            for ([r, g, b], key) in [
+                (ATTACKED_TINT, "king-safety-attacked"),
+                (DEFENDED_TINT, "king-safety-defended"),
+            ] {
+                ui.colored_label(egui::Color32::from_rgb(r, g, b), "■");
+                ui.label(locale.get(key));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_king_pressure() {
+        // The queen on h5 and bishop on c4 bear down on f7, which only the king defends
+        let board = ChessBoard::with_position(
+            &Fen::parse("r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR b KQkq - 3 3")
+                .unwrap(),
+        );
+        let pressure = |square: &str| {
+            let position = BoardPosition::from_algebraic(square).unwrap();
+            king_pressure(&board, &PieceColor::Black)
+                .into_iter()
+                .find(|(around, _)| *around == position)
+                .map(|(_, pressure)| pressure)
+        };
+        assert_eq!(pressure("f7"), Some(2));
+        // The knight defends the queen's square, and nothing attacks or defends the bishop's
+        assert_eq!(pressure("d8"), Some(-1));
+        assert_eq!(pressure("f8"), Some(0));
+        assert_eq!(pressure("c7"), None);
+        assert_eq!(king_pressure(&board, &PieceColor::White).len(), 6);
+    }
+
+    #[test]
+    fn test_pressure_color() {
+        assert_eq!(pressure_color(0), None);
+        assert_eq!(pressure_color(5), pressure_color(MAX_PRESSURE));
+        let attacked = pressure_color(1).unwrap();
+        let defended = pressure_color(-3).unwrap();
+        assert!(attacked.r() > attacked.g());
+        assert!(defended.g() > defended.r());
+        assert!(attacked.a() < defended.a());
+    }
+}
@@ -0,0 +1,222 @@
+//! The layout of the side panels, which are resized by dragging their inner edge and can be
+//! collapsed to a single button, and the distraction-free mode, which hides everything but the
+//! board until its [action](super::actions) is run again or escape is pressed.
+//!
+//! The widths and collapsed state of the panels are saved to [LAYOUT_PATH] whenever they change.
+
+use std::ops::RangeInclusive;
+
+use bevy::ecs::system::SystemParam;
+use bevy::input::Input;
+use bevy::prelude::{KeyCode, Local, Res, ResMut, Resource};
+use bevy_egui::egui::containers::panel::Side;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+use super::display::DisplaySettings;
+use super::locale::Localisation;
+use super::theme::Theme;
+use super::trails::PieceTrails;
+use super::user_theme::UserThemes;
+
+/// The file the layout is saved to.
+const LAYOUT_PATH: &str = "layout.ron";
+
+/// The widths a panel can be resized to.
+const WIDTH_RANGE: RangeInclusive<f32> = 150.0..=500.0;
+
+/// The layout of one of the side panels.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(super) struct PanelLayout {
+    width: f32,
+    collapsed: bool,
+}
+
+impl Default for PanelLayout {
+    fn default() -> Self {
+        PanelLayout {
+            width: 200.0,
+            collapsed: false,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(super) struct Layout {
+    left: PanelLayout,
+    right: PanelLayout,
+    /// Whether everything but the board is hidden, which is not saved so the panels are always
+    /// shown when the app starts.
+    #[serde(skip)]
+    distraction_free: bool,
+}
+
+/// The theme, the user themes it can use, the layout, the display settings and the piece trails,
+/// which together decide how the interface looks.
+#[derive(SystemParam)]
+pub(super) struct Appearance<'w> {
+    pub(super) theme: ResMut<'w, Theme>,
+    pub(super) user_themes: Res<'w, UserThemes>,
+    pub(super) layout: ResMut<'w, Layout>,
+    pub(super) display: ResMut<'w, DisplaySettings>,
+    pub(super) trails: ResMut<'w, PieceTrails>,
+}
+
+impl Layout {
+    pub(super) fn toggle_distraction_free(&mut self) {
+        self.distraction_free = !self.distraction_free;
+    }
+}
+
+/// Loads the layout saved when the app was last closed.
+pub(super) fn setup(mut layout: ResMut<Layout>) {
+    if let Some(saved) = storage::read(LAYOUT_PATH).and_then(|text| ron::from_str(&text).ok()) {
+        *layout = saved;
+    }
+}
+
+/// Returns whether the interface around the board is shown, for systems which draw it.
+pub(super) fn shows_interface(layout: Res<Layout>) -> bool {
+    !layout.distraction_free
+}
+
+/// Turns the distraction-free mode off on escape, unless a text field is using the key.
+pub(super) fn distraction_free_shortcut(
+    mut contexts: EguiContexts,
+    keys: Res<Input<KeyCode>>,
+    mut layout: ResMut<Layout>,
+) {
+    if contexts.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Escape) && layout.distraction_free {
+        layout.distraction_free = false;
+    }
+}
+
+/// Saves the layout when it has changed, waiting until the pointer is released so a panel being
+/// resized is only saved once.
+pub(super) fn save_layout(
+    mut contexts: EguiContexts,
+    layout: Res<Layout>,
+    mut saved: Local<Option<(PanelLayout, PanelLayout)>>,
+) {
+    let current = (layout.left, layout.right);
+    let Some(last) = *saved else {
+        // The layout the app started with is already saved
+        *saved = Some(current);
+        return;
+    };
+    if last == current || contexts.ctx_mut().input(|input| input.pointer.any_down()) {
+        return;
+    }
+    if let Ok(text) = ron::to_string(&*layout) {
+        // A failed save only loses the layout, so it is not reported
+        storage::write(LAYOUT_PATH, &text).ok();
+    }
+    *saved = Some(current);
+}
+
+/// The text of the buttons above the panels, taken from the locale before the panels are shown
+/// as the language can be changed in them.
+pub(super) struct PanelLabels {
+    collapse: String,
+    expand: String,
+    distraction_free: String,
+    distraction_free_hint: String,
+}
+
+impl PanelLabels {
+    pub(super) fn new(locale: &Localisation) -> Self {
+        PanelLabels {
+            collapse: locale.get("collapse-panel").to_string(),
+            expand: locale.get("expand-panel").to_string(),
+            distraction_free: locale.get("distraction-free").to_string(),
+            distraction_free_hint: locale.get("distraction-free-hint").to_string(),
+        }
+    }
+}
+
+/// Shows the side panel on the given side with the given contents, below buttons to collapse
+/// it and to hide everything but the board. A collapsed panel only shows a button to expand it.
+pub(super) fn side_panel(
+    ctx: &egui::Context,
+    side: Side,
+    layout: &mut Layout,
+    labels: &PanelLabels,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) {
+    let (id, collapse, expand) = match side {
+        Side::Left => ("left_panel", "◀", "▶"),
+        Side::Right => ("right_panel", "▶", "◀"),
+    };
+    let mut distraction_free = false;
+    let panel = match side {
+        Side::Left => &mut layout.left,
+        Side::Right => &mut layout.right,
+    };
+    if panel.collapsed {
+        egui::SidePanel::new(side, format!("{}_collapsed", id))
+            .resizable(false)
+            .show(ctx, |ui| {
+                if ui.button(expand).on_hover_text(&labels.expand).clicked() {
+                    panel.collapsed = false;
+                }
+            });
+        return;
+    }
+    let response = egui::SidePanel::new(side, id)
+        .default_width(panel.width)
+        .width_range(WIDTH_RANGE)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui
+                    .button(collapse)
+                    .on_hover_text(&labels.collapse)
+                    .clicked()
+                {
+                    panel.collapsed = true;
+                }
+                distraction_free = ui
+                    .button(&labels.distraction_free)
+                    .on_hover_text(&labels.distraction_free_hint)
+                    .clicked();
+            });
+            add_contents(ui);
+        });
+    panel.width = response.response.rect.width();
+    layout.distraction_free |= distraction_free;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_saving() {
+        let layout = Layout {
+            left: PanelLayout {
+                width: 320.0,
+                collapsed: false,
+            },
+            right: PanelLayout {
+                width: 180.0,
+                collapsed: true,
+            },
+            distraction_free: true,
+        };
+        let text = ron::to_string(&layout).unwrap();
+        let saved: Layout = ron::from_str(&text).unwrap();
+        assert_eq!(saved.left, layout.left);
+        assert_eq!(saved.right, layout.right);
+        assert!(!saved.distraction_free);
+
+        // A panel missing from the saved layout gets the default layout
+        let saved: Layout = ron::from_str("(left: (width: 250.0, collapsed: true))").unwrap();
+        assert!(saved.left.collapsed);
+        assert_eq!(saved.right, PanelLayout::default());
+    }
+}
@@ -0,0 +1,256 @@
+//! LEDs under the squares of a physical board, enabled with the `leds` feature, which light up
+//! the last move and the legal moves of the piece being dragged or previewed on screen.
+//!
+//! The LEDs are set up in the [LEDS_FILE], and are either driven straight from the GPIO pins of
+//! a Raspberry Pi, one pin per square, such as
+//!
+//! ```ron
+//! (output: Gpio({"e2": 17, "e4": 27}))
+//! ```
+//!
+//! which leaves squares without a pin dark, or by a controller on a serial port, such as
+//!
+//! ```ron
+//! (output: Serial("/dev/ttyUSB0"), hints: false)
+//! ```
+//!
+//! which is sent a line for each change with a character for each square by rank from the top
+//! left: `1` for the last move, `2` for a legal move and `0` for neither. The port is used as it
+//! is set up, e.g. by `stty`.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use bevy::log::{info, warn};
+use bevy::prelude::{EventReader, Query, Res, ResMut, Resource, With};
+use serde::Deserialize;
+
+use crate::chess_board::{
+    BoardPosition, BoardSize, ChessBoard, LegalMoves, PieceMoveEvent, ResetBoardEvent,
+};
+
+use super::board::HoverPreview;
+use super::piece::{Dragging, PieceTag};
+
+/// The file the LEDs are set up in.
+const LEDS_FILE: &str = "leds.ron";
+
+/// The directory the GPIO pins are driven through.
+const GPIO_DIRECTORY: &str = "/sys/class/gpio";
+
+/// What the LEDs are driven by.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+enum LedOutput {
+    /// The GPIO pin of each square, by its name in algebraic notation.
+    Gpio(HashMap<String, u32>),
+    /// The path of the serial port of a controller.
+    Serial(String),
+}
+
+fn default_hints() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct LedConfig {
+    output: LedOutput,
+    /// Whether legal moves are lit as well as the last move.
+    #[serde(default = "default_hints")]
+    hints: bool,
+}
+
+/// What the LED of a square shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Led {
+    LastMove,
+    Hint,
+}
+
+/// Returns the line sent to a serial controller to light the given squares of a board of the
+/// given size.
+fn serial_line(size: &BoardSize, lit: &HashMap<BoardPosition, Led>) -> String {
+    size.positions()
+        .map(|position| match lit.get(&position) {
+            Some(Led::LastMove) => '1',
+            Some(Led::Hint) => '2',
+            None => '0',
+        })
+        .collect()
+}
+
+/// Writes the given value to the GPIO file with the given name, such as `gpio17/value`.
+fn write_gpio(file: &str, value: &str) -> std::io::Result<()> {
+    fs::write(Path::new(GPIO_DIRECTORY).join(file), value)
+}
+
+/// The LEDs of the board, if they were set up.
+#[derive(Resource, Default)]
+pub(super) struct Leds {
+    config: Option<LedConfig>,
+    /// The serial port, once it is open.
+    serial: Option<File>,
+    /// The squares of the last move, including the rook's when castling.
+    last_move: Vec<BoardPosition>,
+    /// The squares lit when the LEDs were last set.
+    lit: Option<HashMap<BoardPosition, Led>>,
+}
+
+impl Leds {
+    /// Sets up the LEDs from the [LEDS_FILE], if there is one.
+    pub(super) fn load() -> Self {
+        let Ok(text) = fs::read_to_string(LEDS_FILE) else {
+            return Leds::default();
+        };
+        let config: LedConfig = match ron::from_str(&text) {
+            Ok(config) => config,
+            Err(error) => {
+                warn!(file = LEDS_FILE, %error, "Could not load the LEDs");
+                return Leds::default();
+            }
+        };
+        let mut leds = Leds {
+            config: Some(config),
+            ..Leds::default()
+        };
+        if let Err(error) = leds.open() {
+            warn!(%error, "Could not set up the LEDs");
+            leds.config = None;
+        }
+        leds
+    }
+
+    /// Opens the serial port, or sets the GPIO pins as outputs.
+    fn open(&mut self) -> std::io::Result<()> {
+        match &self.config.as_ref().unwrap().output {
+            LedOutput::Gpio(pins) => {
+                for pin in pins.values() {
+                    // Pins already exported refuse to be exported again
+                    write_gpio("export", &pin.to_string()).ok();
+                    write_gpio(&format!("gpio{}/direction", pin), "out")?;
+                }
+            }
+            LedOutput::Serial(path) => {
+                self.serial = Some(OpenOptions::new().write(true).open(path)?);
+            }
+        }
+        info!("LEDs set up");
+        Ok(())
+    }
+
+    /// Lights the given squares of a board of the given size, if they are not already.
+    fn light(&mut self, size: &BoardSize, lit: HashMap<BoardPosition, Led>) {
+        if self.lit.as_ref() == Some(&lit) {
+            return;
+        }
+        let Some(config) = &self.config else {
+            return;
+        };
+        let result = match &config.output {
+            LedOutput::Gpio(pins) => pins.iter().try_for_each(|(square, pin)| {
+                let on = BoardPosition::from_algebraic(square)
+                    .is_some_and(|position| lit.contains_key(&position));
+                write_gpio(&format!("gpio{}/value", pin), if on { "1" } else { "0" })
+            }),
+            LedOutput::Serial(_) => match &mut self.serial {
+                Some(serial) => writeln!(serial, "{}", serial_line(size, &lit)),
+                None => Ok(()),
+            },
+        };
+        // The LEDs are left as they are rather than warning every move
+        if let Err(error) = result {
+            warn!(%error, "Could not set the LEDs");
+            self.config = None;
+        }
+        self.lit = Some(lit);
+    }
+}
+
+/// Keeps track of the squares of the last move from the pieces moved on the board.
+pub(super) fn track_last_move(
+    mut move_events: EventReader<PieceMoveEvent>,
+    mut reset_events: EventReader<ResetBoardEvent>,
+    board: Res<ChessBoard>,
+    mut leds: ResMut<Leds>,
+) {
+    if leds.config.is_none() {
+        return;
+    }
+    // A reset, such as taking back a move, moves no pieces, so the last move is the board's
+    if reset_events.iter().last().is_some() {
+        leds.last_move = board
+            .past_moves()
+            .last()
+            .map(|piece_move| vec![*piece_move.from(), *piece_move.to()])
+            .unwrap_or_default();
+    }
+    let moved: Vec<BoardPosition> = move_events
+        .iter()
+        .flat_map(|event| [*event.from(), *event.to()])
+        .collect();
+    if !moved.is_empty() {
+        leds.last_move = moved;
+    }
+}
+
+/// Lights the last move and, if hints are on, the legal moves of the piece being dragged or
+/// previewed.
+pub(super) fn update_leds(
+    piece_query: Query<(&BoardPosition, &Dragging), With<PieceTag>>,
+    board: Res<ChessBoard>,
+    legal_moves: Res<LegalMoves>,
+    preview: Res<HoverPreview>,
+    mut leds: ResMut<Leds>,
+) {
+    let Some(config) = &leds.config else {
+        return;
+    };
+    let mut lit: HashMap<BoardPosition, Led> = leds
+        .last_move
+        .iter()
+        .map(|position| (*position, Led::LastMove))
+        .collect();
+    let hinted = piece_query
+        .iter()
+        .find(|(_, dragging)| dragging.get())
+        .map(|(position, _)| *position)
+        .or(preview.previewed());
+    if let Some(from) = hinted.filter(|_| config.hints) {
+        for piece_move in legal_moves.moves_from(&from) {
+            lit.insert(*piece_move.to(), Led::Hint);
+        }
+    }
+    leds.light(board.size(), lit);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_led_config() {
+        let config: LedConfig = ron::from_str(r#"(output: Gpio({"e2": 17}))"#).unwrap();
+        assert_eq!(
+            config.output,
+            LedOutput::Gpio(HashMap::from([("e2".to_string(), 17)]))
+        );
+        assert!(config.hints);
+        let config: LedConfig =
+            ron::from_str(r#"(output: Serial("/dev/ttyUSB0"), hints: false)"#).unwrap();
+        assert!(!config.hints);
+    }
+
+    #[test]
+    fn test_serial_line() {
+        let lit = HashMap::from([
+            (BoardPosition::new(6, 4), Led::LastMove),
+            (BoardPosition::new(4, 4), Led::LastMove),
+            (BoardPosition::new(5, 0), Led::Hint),
+        ]);
+        let line = serial_line(&BoardSize::default(), &lit);
+        assert_eq!(line.len(), 64);
+        assert_eq!(&line[..32], "0".repeat(32));
+        assert_eq!(&line[32..], "00001000200000000000100000000000");
+    }
+}
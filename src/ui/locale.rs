@@ -0,0 +1,217 @@
+//! The localisation layer, which looks up the user-facing strings of the selected language.
+//!
+//! Each language is described by a file in `assets/locales` mapping string keys to text, where
+//! arguments are written in braces, e.g. "Resign as {player}". Strings missing from a language
+//! fall back to English.
+
+use std::collections::HashMap;
+
+use bevy::prelude::Resource;
+use bevy_egui::egui;
+use serde::Deserialize;
+
+use crate::chess_board::fairy::fairy_piece;
+use crate::chess_board::{PieceColor, PieceType};
+
+/// The string tables of the shipped languages, English first.
+const LOCALES: [&str; 2] = [
+    include_str!("../../assets/locales/en.ron"),
+    include_str!("../../assets/locales/de.ron"),
+];
+
+/// The figurines replacing the English piece letters in figurine algebraic notation. They are in the
+/// icon font egui falls back to, so no font needs to be loaded for them.
+const FIGURINES: [(char, &str); 5] = [('K', "♚"), ('Q', "♛"), ('R', "♜"), ('B', "♝"), ('N', "♞")];
+
+/// The letters used for the pieces in standard algebraic notation.
+#[derive(Debug, Clone, Deserialize)]
+struct PieceLetters {
+    king: String,
+    queen: String,
+    rook: String,
+    bishop: String,
+    knight: String,
+}
+
+impl PieceLetters {
+    /// Returns the letter for the piece with the given English letter.
+    fn get(&self, letter: char) -> Option<&str> {
+        match letter {
+            'K' => Some(&self.king),
+            'Q' => Some(&self.queen),
+            'R' => Some(&self.rook),
+            'B' => Some(&self.bishop),
+            'N' => Some(&self.knight),
+            _ => None,
+        }
+    }
+}
+
+/// A language and its strings.
+#[derive(Debug, Clone, Deserialize)]
+struct Locale {
+    name: String,
+    /// The code of the language, e.g. "en", which the [voice packs](super::voice) name.
+    code: String,
+    piece_letters: PieceLetters,
+    strings: HashMap<String, String>,
+}
+
+/// The shipped languages, the one selected and how moves are written.
+#[derive(Resource, Debug)]
+pub(super) struct Localisation {
+    locales: Vec<Locale>,
+    selected: usize,
+    /// Whether moves are written with figurines rather than piece letters, e.g. "♞f3".
+    figurines: bool,
+}
+
+impl Default for Localisation {
+    fn default() -> Self {
+        Localisation {
+            locales: LOCALES
+                .iter()
+                .map(|locale| ron::from_str(locale).expect("Invalid locale file."))
+                .collect(),
+            selected: 0,
+            figurines: false,
+        }
+    }
+}
+
+impl Localisation {
+    fn locale(&self) -> &Locale {
+        &self.locales[self.selected]
+    }
+
+    /// Returns the code of the selected language.
+    pub(super) fn code(&self) -> &str {
+        &self.locale().code
+    }
+
+    /// Returns the string with the given key, falling back to English and then to the key itself.
+    pub(super) fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.locale()
+            .strings
+            .get(key)
+            .or_else(|| self.locales[0].strings.get(key))
+            .map_or(key, |text| text.as_str())
+    }
+
+    /// Returns the string with the given key with its arguments filled in.
+    pub(super) fn format(&self, key: &str, args: &[(&str, &dyn ToString)]) -> String {
+        args.iter()
+            .fold(self.get(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), &value.to_string())
+            })
+    }
+
+    /// Returns the name of the given color.
+    pub(super) fn color(&self, color: &PieceColor) -> &str {
+        match color {
+            PieceColor::White => self.get("white"),
+            PieceColor::Black => self.get("black"),
+        }
+    }
+
+    /// Returns the name of the given piece type.
+    pub(super) fn piece(&self, piece_type: &PieceType) -> String {
+        self.get(match piece_type {
+            PieceType::King => "piece-king",
+            PieceType::Queen => "piece-queen",
+            PieceType::Rook => "piece-rook",
+            PieceType::Bishop => "piece-bishop",
+            PieceType::Knight => "piece-knight",
+            PieceType::Pawn => "piece-pawn",
+            // Fairy pieces are registered with one name for every language
+            PieceType::Fairy(index) => {
+                return fairy_piece(*index).map_or("?".to_string(), |piece| piece.name.clone())
+            }
+        })
+        .to_string()
+    }
+
+    /// Replaces the English piece letters in the given standard algebraic notation, e.g. "1. Nf3",
+    /// with those of the selected language or with figurines.
+    pub(super) fn san(&self, text: &str) -> String {
+        text.split(' ')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next().and_then(|letter| match self.figurines {
+                    true => FIGURINES
+                        .iter()
+                        .find(|(english, _)| *english == letter)
+                        .map(|(_, figurine)| *figurine),
+                    false => self.locale().piece_letters.get(letter),
+                }) {
+                    Some(letter) => format!("{}{}", letter, chars.as_str()),
+                    None => word.to_string(),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+/// Draws the language picker and the notation setting.
+pub(super) fn settings_ui(ui: &mut egui::Ui, localisation: &mut Localisation) {
+    let mut selected = localisation.selected;
+    egui::ComboBox::from_label(localisation.get("language"))
+        .selected_text(&localisation.locale().name)
+        .show_index(ui, &mut selected, localisation.locales.len(), |index| {
+            localisation.locales[index].name.clone()
+        });
+    localisation.selected = selected;
+    let label = localisation.get("figurine-notation").to_string();
+    ui.checkbox(&mut localisation.figurines, label);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locales_are_complete() {
+        let localisation = Localisation::default();
+        let english = &localisation.locales[0];
+        for locale in &localisation.locales {
+            let mut missing: Vec<&String> = english
+                .strings
+                .keys()
+                .filter(|key| !locale.strings.contains_key(*key))
+                .collect();
+            missing.sort();
+            assert!(
+                missing.is_empty(),
+                "{} is missing {:?}",
+                locale.name,
+                missing
+            );
+            assert_eq!(locale.strings.len(), english.strings.len());
+        }
+    }
+
+    #[test]
+    fn test_localisation() {
+        let mut localisation = Localisation::default();
+        assert_eq!(localisation.get("reset-board"), "Reset Board");
+        assert_eq!(localisation.get("not-a-key"), "not-a-key");
+        assert_eq!(
+            localisation.format("resign-as", &[("player", &"White")]),
+            "Resign as White"
+        );
+        assert_eq!(localisation.san("1. Nf3"), "1. Nf3");
+
+        localisation.selected = 1;
+        assert_eq!(localisation.color(&PieceColor::White), "Weiß");
+        assert_eq!(localisation.san("12... Nxe5"), "12... Sxe5");
+        assert_eq!(localisation.san("Qd1"), "Dd1");
+        assert_eq!(localisation.san("exd5"), "exd5");
+        assert_eq!(localisation.san("0-0-0"), "0-0-0");
+
+        // Figurines are the same in every language
+        localisation.figurines = true;
+        assert_eq!(localisation.san("12... Nxe5"), "12... ♞xe5");
+        assert_eq!(localisation.san("Kg1 e4"), "♚g1 e4");
+    }
+}
@@ -0,0 +1,175 @@
+//! The material quiz, a practice mode for counting material, which every few moves asks the
+//! player for the [material balance](crate::exchange::material_balance) of the position on the
+//! board and keeps score of their answers.
+
+use std::ops::RangeInclusive;
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::ChessBoard;
+use crate::exchange::material_balance;
+
+use super::locale::Localisation;
+
+/// The numbers of moves, in plies, which can be played between questions.
+const INTERVAL_RANGE: RangeInclusive<usize> = 2..=20;
+
+/// The answer to the last question and the player's guess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Answer {
+    guess: i32,
+    balance: i32,
+}
+
+#[derive(Resource, Debug)]
+pub(super) struct MaterialQuiz {
+    enabled: bool,
+    /// The number of plies played between questions.
+    interval: usize,
+    /// The number of moves on the board when the last question was asked.
+    asked_at: usize,
+    /// The balance of the position asked about, while a question is waiting for an answer.
+    question: Option<i32>,
+    /// The player's guess at the balance, in pawns for white.
+    guess: i32,
+    last: Option<Answer>,
+    asked: u32,
+    correct: u32,
+    streak: u32,
+}
+
+impl Default for MaterialQuiz {
+    fn default() -> Self {
+        MaterialQuiz {
+            enabled: false,
+            interval: 6,
+            asked_at: 0,
+            question: None,
+            guess: 0,
+            last: None,
+            asked: 0,
+            correct: 0,
+            streak: 0,
+        }
+    }
+}
+
+impl MaterialQuiz {
+    /// Asks about the position on the given board once enough moves have been played since the
+    /// last question.
+    fn update(&mut self, board: &ChessBoard) {
+        let moves = board.past_moves().len();
+        // A new game or a take back starts the count again
+        if moves < self.asked_at {
+            self.asked_at = moves;
+        }
+        if !self.enabled || self.question.is_some() || moves < self.asked_at + self.interval {
+            return;
+        }
+        self.asked_at = moves;
+        self.question = Some(material_balance(board));
+        self.guess = 0;
+    }
+
+    /// Checks the player's guess at the balance asked about.
+    fn answer(&mut self) {
+        let Some(balance) = self.question.take() else {
+            return;
+        };
+        self.asked += 1;
+        if self.guess == balance {
+            self.correct += 1;
+            self.streak += 1;
+        } else {
+            self.streak = 0;
+        }
+        self.last = Some(Answer {
+            guess: self.guess,
+            balance,
+        });
+    }
+}
+
+/// Shows the material quiz, collapsed until it is opened.
+pub(super) fn material_quiz_window(
+    mut contexts: EguiContexts,
+    mut quiz: ResMut<MaterialQuiz>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+) {
+    quiz.update(&board);
+    egui::Window::new(locale.get("material-quiz"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut quiz.enabled, locale.get("material-quiz-enabled"));
+            ui.add(
+                egui::Slider::new(&mut quiz.interval, INTERVAL_RANGE)
+                    .text(locale.get("material-quiz-interval")),
+            );
+            if quiz.question.is_some() {
+                ui.label(locale.get("material-quiz-question"));
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut quiz.guess).clamp_range(-39..=39));
+                    if ui.button(locale.get("submit")).clicked() {
+                        quiz.answer();
+                    }
+                });
+            }
+            match quiz.last {
+                Some(Answer { guess, balance }) if guess == balance => {
+                    ui.label(locale.get("material-quiz-correct"));
+                }
+                Some(Answer { balance, .. }) => {
+                    ui.label(locale.format("material-quiz-wrong", &[("balance", &balance)]));
+                }
+                None => {}
+            }
+            ui.label(locale.format(
+                "material-quiz-score",
+                &[
+                    ("correct", &quiz.correct),
+                    ("asked", &quiz.asked),
+                    ("streak", &quiz.streak),
+                ],
+            ));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_material_quiz() {
+        let mut quiz = MaterialQuiz {
+            enabled: true,
+            interval: 2,
+            ..MaterialQuiz::default()
+        };
+        let mut board =
+            ChessBoard::with_position(&Fen::from_string("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1"));
+        quiz.update(&board);
+        assert_eq!(quiz.question, None);
+
+        for text in ["Qxd5", "Kf8"] {
+            let piece_move = Move::from_algebraic(text, &board).unwrap();
+            board.apply_move(&piece_move);
+        }
+        quiz.update(&board);
+        assert_eq!(quiz.question, Some(9));
+        quiz.guess = 9;
+        quiz.answer();
+        assert_eq!((quiz.correct, quiz.asked, quiz.streak), (1, 1, 1));
+
+        // The next question waits for another two moves
+        quiz.update(&board);
+        assert_eq!(quiz.question, None);
+        board = ChessBoard::with_position(&Fen::default());
+        quiz.update(&board);
+        assert_eq!(quiz.asked_at, 0);
+    }
+}
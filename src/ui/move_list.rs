@@ -0,0 +1,433 @@
+//! The list of past moves, where each move is a selectable widget labelled with a full description,
+//! the dropdown of visited positions and the opening they reached.
+//!
+//! The list can be filtered to show only captures, checks, the moves classified as mistakes or
+//! the moves of one kind of piece, found from what each move records of itself and from the
+//! annotations of the moves.
+//!
+//! The moves are numbered from the position the first of them was made from, so a game started
+//! from a position with black to move begins with a row such as "1... c5".
+
+use std::ops::Range;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{EventWriter, Res, ResMut, Resource};
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor, PieceType};
+use crate::database::DrillEvent;
+use crate::eco::classify;
+use crate::history::PositionHistory;
+
+use super::annotation::{MoveAnnotations, MoveQuality};
+use super::eval_graph;
+use super::locale::Localisation;
+use super::review::GameReview;
+
+/// Which of the past moves the move list shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(super) enum MoveFilter {
+    #[default]
+    All,
+    Captures,
+    /// The moves which gave check, or checkmate.
+    Checks,
+    /// The moves classified as inaccuracies, mistakes or blunders.
+    Mistakes,
+    /// The moves of the given kind of piece.
+    Piece(PieceType),
+}
+
+impl MoveFilter {
+    /// Returns whether the filter shows the given move, which is classified with the given quality.
+    fn matches(&self, piece_move: &Move, quality: Option<MoveQuality>) -> bool {
+        match self {
+            MoveFilter::All => true,
+            MoveFilter::Captures => piece_move.is_capture(),
+            MoveFilter::Checks => piece_move.is_check() || piece_move.is_checkmate(),
+            MoveFilter::Mistakes => quality.is_some_and(|quality| quality.is_mistake()),
+            MoveFilter::Piece(piece_type) => piece_move.piece_type() == piece_type,
+        }
+    }
+
+    fn label(&self, locale: &Localisation) -> String {
+        match self {
+            MoveFilter::All => locale.get("all-moves").to_string(),
+            MoveFilter::Captures => locale.get("captures").to_string(),
+            MoveFilter::Checks => locale.get("checks").to_string(),
+            MoveFilter::Mistakes => locale.get("mistakes").to_string(),
+            MoveFilter::Piece(piece_type) => {
+                locale.format("moves-by-piece", &[("piece", &locale.piece(piece_type))])
+            }
+        }
+    }
+}
+
+/// The state of the move list.
+#[derive(Resource, Debug, Default)]
+pub(super) struct MoveListState {
+    /// The index of the selected move in the past moves.
+    selected: Option<usize>,
+    filter: MoveFilter,
+}
+
+impl MoveListState {
+    pub(super) fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Selects the move with the given index, or none.
+    pub(super) fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+    }
+}
+
+/// The move list state, the annotations of the past moves and the visited positions, with the
+/// drills played from them and the review of the game.
+#[derive(SystemParam)]
+pub(super) struct PastMoves<'w> {
+    state: ResMut<'w, MoveListState>,
+    annotations: ResMut<'w, MoveAnnotations>,
+    positions: Res<'w, PositionHistory>,
+    drills: EventWriter<'w, DrillEvent>,
+    review: Res<'w, GameReview>,
+}
+
+impl PastMoves<'_> {
+    pub(super) fn annotations(&self) -> &MoveAnnotations {
+        &self.annotations
+    }
+
+    pub(super) fn review(&self) -> &GameReview {
+        &self.review
+    }
+}
+
+/// Draws a dropdown of the positions visited this game, which resets the board to the chosen one.
+pub(super) fn positions_ui(
+    ui: &mut egui::Ui,
+    past_moves: &mut PastMoves,
+    commands: &mut GameCommands,
+    locale: &Localisation,
+) {
+    let positions = &past_moves.positions;
+    let Some(current) = positions.len().checked_sub(1) else {
+        return;
+    };
+    let label = |index| match index {
+        0 => locale.get("position-start").to_string(),
+        _ => locale.san(&positions.label(index)),
+    };
+    let mut chosen = current;
+    egui::ComboBox::from_label(locale.get("position"))
+        .selected_text(label(current))
+        .show_index(ui, &mut chosen, positions.len(), label);
+    if chosen != current {
+        commands.push(GameCommand::LoadGame(Box::new(
+            positions.reset_event(chosen),
+        )));
+        past_moves.state.selected = None;
+    }
+}
+
+/// Draws the evaluation graph of the game reviewed, once it has been.
+pub(super) fn eval_graph_ui(
+    ui: &mut egui::Ui,
+    past_moves: &mut PastMoves,
+    commands: &mut GameCommands,
+    locale: &Localisation,
+) {
+    if let Some(graph) = past_moves.review.graph() {
+        eval_graph::eval_graph_ui(ui, graph, &mut past_moves.state, commands, locale);
+    }
+}
+
+/// Shows the opening of the current game, if it has reached one.
+pub(super) fn opening_ui(ui: &mut egui::Ui, past_moves: &PastMoves, locale: &Localisation) {
+    if let Some(opening) = classify(past_moves.positions.positions().iter()) {
+        ui.label(locale.format("opening", &[("opening", opening)]));
+    }
+}
+
+/// The numbering of the past moves of a board, from the fullmove number and player to move of the
+/// position the first of them was made from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct MoveNumbering {
+    /// The number of the first move.
+    first: usize,
+    /// Whether the first move was black's.
+    black_first: bool,
+}
+
+impl MoveNumbering {
+    /// Returns the numbering of the past moves of the given board, counting back from its
+    /// position, whose player to move is the one after the last move once the game has ended.
+    fn of(board: &ChessBoard) -> Self {
+        let moves = board.past_moves();
+        let to_move = match moves.last() {
+            Some(piece_move) => piece_move.piece_color().opposite(),
+            None => board.active_color().unwrap_or(PieceColor::White),
+        };
+        let ply = (*board.move_number() as usize).saturating_sub(1) * 2
+            + usize::from(to_move == PieceColor::Black);
+        // A board reset to a position with fewer moves before it than it has past moves starts at
+        // the first move
+        let first_ply = ply.saturating_sub(moves.len());
+        MoveNumbering {
+            first: first_ply / 2 + 1,
+            black_first: first_ply % 2 == 1,
+        }
+    }
+
+    /// Returns the number of the past move at the given index.
+    fn number(&self, index: usize) -> usize {
+        self.first + (index + usize::from(self.black_first)) / 2
+    }
+
+    /// Returns the number of rows of the given number of moves, two to a row.
+    fn rows(&self, moves: usize) -> usize {
+        (moves + usize::from(self.black_first)).div_ceil(2)
+    }
+
+    /// Returns the indices of the moves on the given row, of the given number of moves, where a
+    /// first move of black's is alone on the first row.
+    fn row_moves(&self, row: usize, moves: usize) -> Range<usize> {
+        let offset = usize::from(self.black_first);
+        let start = (row * 2).saturating_sub(offset);
+        start.min(moves)..(row * 2 + 2 - offset).min(moves)
+    }
+}
+
+/// Returns the full text of a move, e.g. "12... Knight takes e5, check".
+fn move_text(move_number: usize, is_white_move: bool, piece_move: &Move) -> String {
+    format!(
+        "{}{} {}",
+        move_number,
+        if is_white_move { "." } else { "..." },
+        piece_move.as_description()
+    )
+}
+
+/// Draws the filter choosing which of the past moves the move list shows.
+pub(super) fn filter_ui(ui: &mut egui::Ui, past_moves: &mut PastMoves, locale: &Localisation) {
+    let filter = &mut past_moves.state.filter;
+    egui::ComboBox::from_label(locale.get("move-filter"))
+        .selected_text(filter.label(locale))
+        .show_ui(ui, |ui| {
+            let filters = [
+                MoveFilter::All,
+                MoveFilter::Captures,
+                MoveFilter::Checks,
+                MoveFilter::Mistakes,
+            ];
+            for option in filters
+                .into_iter()
+                .chain(PieceType::iter().map(MoveFilter::Piece))
+            {
+                ui.selectable_value(filter, option, option.label(locale));
+            }
+        });
+}
+
+/// Draws the selectable label of the past move with the given index, whose context menu can
+/// classify it by quality or play on from the position after it against the engine. Hovering over
+/// a move of the game reviewed shows the engine's line instead of it.
+fn move_ui(
+    ui: &mut egui::Ui,
+    past_moves: &mut PastMoves,
+    index: usize,
+    move_number: usize,
+    piece_move: &Move,
+    locale: &Localisation,
+) {
+    let PastMoves {
+        state,
+        annotations,
+        positions,
+        drills,
+        review,
+    } = past_moves;
+    let full_text = move_text(
+        move_number,
+        *piece_move.piece_color() == PieceColor::White,
+        piece_move,
+    );
+    let selected = state.selected == Some(index);
+    let san = locale.san(&piece_move.as_algebraic());
+    let mut label = egui::RichText::new(&san);
+    if let Some(quality) = annotations.get(index) {
+        label =
+            egui::RichText::new(format!("{}{}", san, quality.glyph())).color(quality.egui_color());
+    }
+    // A reviewed move shows what the engine would have played instead
+    let what_if = review.what_if(index, piece_move);
+    let response = ui.selectable_label(selected, label).on_hover_ui(|ui| {
+        ui.label(&full_text);
+        if let Some(what_if) = what_if {
+            what_if.hover_ui(ui, locale);
+        }
+    });
+    response.widget_info(|| {
+        egui::WidgetInfo::selected(egui::WidgetType::SelectableLabel, selected, &full_text)
+    });
+    if response.clicked() {
+        state.selected = Some(index);
+    }
+    response.context_menu(|ui| {
+        for quality in MoveQuality::iter() {
+            if ui
+                .button(format!("{} {:?}", quality.glyph(), quality))
+                .clicked()
+            {
+                annotations.set(index, Some(quality));
+                ui.close_menu();
+            }
+        }
+        if ui.button(locale.get("clear")).clicked() {
+            annotations.set(index, None);
+            ui.close_menu();
+        }
+        ui.separator();
+        if ui
+            .add_enabled(
+                positions.index_after(index + 1).is_some(),
+                egui::Button::new(locale.get("play-from-here")),
+            )
+            .on_hover_text(locale.get("play-from-here-hint"))
+            .clicked()
+        {
+            drills.send(DrillEvent::new(index + 1));
+            ui.close_menu();
+        }
+    });
+}
+
+/// Draws the list of past moves, two to a row, or only those the filter lets through, one to a
+/// row with their move numbers.
+pub(super) fn move_list_ui(
+    ui: &mut egui::Ui,
+    board: &ChessBoard,
+    past_moves: &mut PastMoves,
+    locale: &Localisation,
+) {
+    let moves = board.past_moves();
+    let text_style = egui::TextStyle::Body;
+    let row_height = ui
+        .spacing()
+        .interact_size
+        .y
+        .max(ui.text_style_height(&text_style));
+    let numbering = MoveNumbering::of(board);
+    let total_rows = numbering.rows(moves.len());
+    let filter = past_moves.state.filter;
+    let shown: Vec<usize> = match filter {
+        MoveFilter::All => Vec::new(),
+        _ => (0..moves.len())
+            .filter(|index| filter.matches(&moves[*index], past_moves.annotations.get(*index)))
+            .collect(),
+    };
+    let total_rows = match filter {
+        MoveFilter::All => total_rows,
+        _ => shown.len(),
+    };
+    egui::ScrollArea::vertical()
+        .auto_shrink([false; 2])
+        .stick_to_bottom(true)
+        .max_height(ui.available_height() * 4.0 / 5.0)
+        .show_rows(ui, row_height, total_rows, |ui, row_range| {
+            if filter != MoveFilter::All && shown.is_empty() {
+                ui.label(locale.get("no-matching-moves"));
+            }
+            for row in row_range {
+                ui.horizontal(|ui| match filter {
+                    MoveFilter::All => {
+                        let indices = numbering.row_moves(row, moves.len());
+                        let move_number = numbering.number(indices.start);
+                        ui.label(match moves.get(indices.start).map(Move::piece_color) {
+                            Some(PieceColor::Black) => format!("{}...", move_number),
+                            _ => format!("{}.", move_number),
+                        });
+                        for index in indices {
+                            move_ui(ui, past_moves, index, move_number, &moves[index], locale);
+                        }
+                    }
+                    _ => {
+                        let index = shown[row];
+                        let move_number = numbering.number(index);
+                        let piece_move = &moves[index];
+                        ui.label(match piece_move.piece_color() {
+                            PieceColor::White => format!("{}.", move_number),
+                            PieceColor::Black => format!("{}...", move_number),
+                        });
+                        move_ui(ui, past_moves, index, move_number, piece_move, locale);
+                    }
+                });
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_move_filter() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let moves: Vec<Move> = ["e4", "d5", "exd5", "Qxd5", "Nc3", "Qe5+"]
+            .iter()
+            .map(|text| {
+                let piece_move = Move::from_algebraic(text, &board).unwrap();
+                board.apply_move(&piece_move);
+                *board.past_moves().last().unwrap()
+            })
+            .collect();
+        let shown = |filter: MoveFilter, annotations: &MoveAnnotations| {
+            (0..moves.len())
+                .filter(|index| filter.matches(&moves[*index], annotations.get(*index)))
+                .collect::<Vec<_>>()
+        };
+        let mut annotations = MoveAnnotations::default();
+        annotations.set(4, Some(MoveQuality::Inaccuracy));
+        annotations.set(5, Some(MoveQuality::Good));
+
+        assert_eq!(shown(MoveFilter::All, &annotations), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(shown(MoveFilter::Captures, &annotations), [2, 3]);
+        assert_eq!(shown(MoveFilter::Checks, &annotations), [5]);
+        // A good move is not a mistake however it is classified
+        assert_eq!(shown(MoveFilter::Mistakes, &annotations), [4]);
+        assert_eq!(
+            shown(MoveFilter::Piece(PieceType::Queen), &annotations),
+            [3, 5]
+        );
+    }
+
+    #[test]
+    fn test_move_numbering() {
+        let play = |fen: &str, moves: &[&str]| {
+            let mut board = ChessBoard::with_position(&Fen::from_string(fen));
+            for text in moves {
+                board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            }
+            MoveNumbering::of(&board)
+        };
+        let numbering = play(&Fen::default().to_string(), &["e4", "e5", "Nf3"]);
+        assert_eq!((numbering.first, numbering.black_first), (1, false));
+        assert_eq!(numbering.rows(3), 2);
+        assert_eq!(numbering.row_moves(1, 3), 2..3);
+        assert_eq!(numbering.number(2), 2);
+
+        // A game from a position with black to move starts with black's move alone on a row
+        let after_e4 = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 12";
+        let numbering = play(after_e4, &["c5", "Nf3", "d6"]);
+        assert_eq!((numbering.first, numbering.black_first), (12, true));
+        assert_eq!(numbering.rows(3), 2);
+        assert_eq!(numbering.row_moves(0, 3), 0..1);
+        assert_eq!(numbering.row_moves(1, 3), 1..3);
+        assert_eq!([0, 1, 2].map(|index| numbering.number(index)), [12, 13, 13]);
+        assert_eq!(play(after_e4, &[]), numbering);
+    }
+}
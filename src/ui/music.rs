@@ -0,0 +1,250 @@
+//! The music, turned on in the audio settings, which plays a looping track for the mood of the
+//! game and crossfades to another when the mood changes.
+//!
+//! The mood follows the [phase](crate::game_phase) of the game, and a middlegame turns tense while
+//! the evaluation is volatile: when the last [VOLATILE_EVALUATIONS] evaluations of the engine or
+//! the analysis differ by [VOLATILE_SPREAD] or more. Before the first move and once the game is
+//! over the menu track plays. The tracks are listed in the music manifest.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use bevy::audio::{AudioSink, AudioSinkPlayback, Volume};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{
+    AssetServer, AudioBundle, Commands, Component, DetectChanges, Entity, Local, PlaybackSettings,
+    Query, Res, ResMut, Resource,
+};
+use bevy::time::Time;
+use serde::Deserialize;
+
+use crate::analysis::Analysis;
+use crate::chess_board::ChessBoard;
+use crate::engine::Engine;
+use crate::game_phase::{game_phase, GamePhase};
+
+use super::audio::AudioSettings;
+
+/// The manifest listing the track of each mood.
+const MUSIC_MANIFEST: &str = include_str!("../../assets/music/tracks.ron");
+
+/// The time one track takes to fade into the next.
+const CROSSFADE_TIME: Duration = Duration::from_secs(3);
+
+/// The number of recent evaluations compared to find whether the evaluation is volatile.
+const VOLATILE_EVALUATIONS: usize = 4;
+
+/// The spread of the recent evaluations in centipawns at which the evaluation is volatile.
+const VOLATILE_SPREAD: i32 = 150;
+
+/// The mood of the game, which each has a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub(super) enum Mood {
+    Menu,
+    Opening,
+    Middlegame,
+    /// A middlegame in which the evaluation is volatile.
+    Tense,
+    Endgame,
+}
+
+impl Mood {
+    /// Returns the mood whose track plays when this mood has none.
+    fn fallback(&self) -> Option<Mood> {
+        match self {
+            Mood::Tense => Some(Mood::Middlegame),
+            Mood::Middlegame => Some(Mood::Opening),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the mood of the game on the given board, whose evaluation is volatile or not.
+fn mood(board: &ChessBoard, volatile: bool) -> Mood {
+    if board.past_moves().is_empty() || board.game_end_status().is_some() {
+        return Mood::Menu;
+    }
+    match game_phase(board) {
+        GamePhase::Opening => Mood::Opening,
+        GamePhase::Middlegame if volatile => Mood::Tense,
+        GamePhase::Middlegame => Mood::Middlegame,
+        GamePhase::Endgame => Mood::Endgame,
+    }
+}
+
+/// Returns whether the last of the given evaluations differ by [VOLATILE_SPREAD] or more.
+fn is_volatile(evaluations: &[i32]) -> bool {
+    let recent = &evaluations[evaluations.len().saturating_sub(VOLATILE_EVALUATIONS)..];
+    match (recent.iter().max(), recent.iter().min()) {
+        (Some(max), Some(min)) => max - min >= VOLATILE_SPREAD,
+        _ => false,
+    }
+}
+
+/// The track of each mood, loaded from the music manifest.
+#[derive(Debug, Clone)]
+struct Tracks(HashMap<Mood, String>);
+
+impl Tracks {
+    fn from_manifest(manifest: &str) -> Self {
+        Tracks(ron::from_str(manifest).expect("Invalid music manifest."))
+    }
+
+    /// Returns the asset path of the track for the given mood, falling back through the moods
+    /// like it.
+    fn track(&self, mood: Mood) -> Option<&str> {
+        self.0
+            .get(&mood)
+            .map(String::as_str)
+            .or_else(|| self.track(mood.fallback()?))
+    }
+}
+
+#[derive(Resource, Debug)]
+pub(super) struct Music {
+    tracks: Tracks,
+    /// The asset path of the track playing, or fading in.
+    playing: Option<String>,
+    /// The analysis's evaluations of the positions of the game, from white's point of view.
+    evaluations: Vec<i32>,
+}
+
+impl Default for Music {
+    fn default() -> Self {
+        Music {
+            tracks: Tracks::from_manifest(MUSIC_MANIFEST),
+            playing: None,
+            evaluations: Vec::new(),
+        }
+    }
+}
+
+/// A track of the music, fading in or playing until another replaces it.
+#[derive(Component)]
+pub(super) struct MusicTrack {
+    /// The share of the music volume the track plays at, which changes as it fades.
+    level: f32,
+    fading_out: bool,
+}
+
+/// Keeps the analysis's evaluations of the game, which the engine does not keep while it is not
+/// playing.
+pub(super) fn track_evaluations(
+    mut music: ResMut<Music>,
+    analysis: Res<Analysis>,
+    board: Res<ChessBoard>,
+    mut last: Local<Option<i32>>,
+) {
+    if board.is_changed() && board.past_moves().is_empty() {
+        music.evaluations.clear();
+    }
+    let score = analysis.current().map(|evaluation| evaluation.score);
+    if score != *last {
+        *last = score;
+        music.evaluations.extend(score);
+    }
+}
+
+/// The game the mood is of, and the engine whose evaluations tell whether it is volatile while the
+/// engine plays.
+#[derive(SystemParam)]
+pub(super) struct MoodSources<'w> {
+    board: Res<'w, ChessBoard>,
+    engine: Res<'w, Engine>,
+}
+
+/// Plays the track of the mood of the game, crossfading from the last track when it changes.
+pub(super) fn play_music(
+    mut commands: Commands,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+    mut music: ResMut<Music>,
+    settings: Res<AudioSettings>,
+    sources: MoodSources,
+    asset_server: Res<AssetServer>,
+    time: Res<Time>,
+) {
+    let MoodSources { board, engine } = sources;
+    let volume = settings.music_volume();
+    let track = volume.and_then(|_| {
+        let volatile = match engine.color() {
+            Some(_) => is_volatile(engine.evaluations()),
+            None => is_volatile(&music.evaluations),
+        };
+        music.tracks.track(mood(&board, volatile)).map(String::from)
+    });
+    if track != music.playing {
+        for (_, mut playing, _) in tracks.iter_mut() {
+            playing.fading_out = true;
+        }
+        if let Some(path) = &track {
+            commands.spawn((
+                AudioBundle {
+                    source: asset_server.load(path.as_str()),
+                    settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0)),
+                },
+                MusicTrack {
+                    level: 0.0,
+                    fading_out: false,
+                },
+            ));
+        }
+        music.playing = track;
+    }
+    let step = time.delta_seconds() / CROSSFADE_TIME.as_secs_f32();
+    for (entity, mut playing, sink) in tracks.iter_mut() {
+        playing.level = match playing.fading_out {
+            true => (playing.level - step).max(0.0),
+            false => (playing.level + step).min(1.0),
+        };
+        if playing.fading_out && playing.level <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if let Some(sink) = sink {
+            sink.set_volume(playing.level * volume.unwrap_or_default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_mood() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        assert_eq!(mood(&board, false), Mood::Menu);
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        assert_eq!(mood(&board, true), Mood::Opening);
+
+        let mut board = ChessBoard::with_position(
+            &Fen::parse("r1bq1rk1/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R2QK2R w KQ - 0 12")
+                .unwrap(),
+        );
+        board.apply_move(&Move::from_algebraic("O-O", &board).unwrap());
+        assert_eq!(mood(&board, false), Mood::Middlegame);
+        assert_eq!(mood(&board, true), Mood::Tense);
+
+        assert!(!is_volatile(&[]));
+        assert!(!is_volatile(&[20, 60, -40, 90]));
+        // Only the last evaluations count
+        assert!(is_volatile(&[20, 60, -40, 90, 110]));
+    }
+
+    #[test]
+    fn test_tracks() {
+        let tracks = Tracks::from_manifest(
+            r#"{Menu: "menu.ogg", Opening: "opening.ogg", Tense: "tense.ogg"}"#,
+        );
+        assert_eq!(tracks.track(Mood::Menu), Some("menu.ogg"));
+        assert_eq!(tracks.track(Mood::Tense), Some("tense.ogg"));
+        // The middlegame falls back to the opening's track, but the endgame has none
+        assert_eq!(tracks.track(Mood::Middlegame), Some("opening.ogg"));
+        assert_eq!(tracks.track(Mood::Endgame), None);
+        // The manifest shipped with the app can be read
+        Tracks::from_manifest(MUSIC_MANIFEST);
+    }
+}
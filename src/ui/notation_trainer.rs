@@ -0,0 +1,243 @@
+//! The notation trainer, an overlay which names a square and asks the player to click it, timing
+//! how long they take and keeping score.
+//!
+//! While it runs the board only answers the trainer, so pieces cannot be picked up and the square
+//! tooltip, which names the square under the cursor, is hidden. The results of each answer are
+//! also kept in the [profile](crate::profile) of the player playing, so their accuracy and times
+//! build up across sessions.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::input::ButtonState;
+use bevy::prelude::{EventReader, MouseButton, Res, ResMut, Resource};
+use bevy::time::Time;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::{BoardPosition, BoardSize};
+use crate::profile::{NotationStats, Profiles};
+use crate::random::Random;
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+use super::BoardClickEvent;
+
+/// The size of the name of the square asked for.
+const NAME_SIZE: f32 = 48.;
+
+/// The player's answer to the last square asked for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Answer {
+    asked: BoardPosition,
+    clicked: BoardPosition,
+    time: Duration,
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct NotationTrainer {
+    /// The square asked for and the time it was asked at, while the trainer runs.
+    question: Option<(BoardPosition, Duration)>,
+    /// The seed of the session, from which each square is picked.
+    seed: u64,
+    last: Option<Answer>,
+    /// The results of this session alone.
+    session: NotationStats,
+}
+
+impl NotationTrainer {
+    fn is_running(&self) -> bool {
+        self.question.is_some()
+    }
+
+    /// Starts a session on a board of the given size at the given time.
+    fn start(&mut self, seed: u64, size: &BoardSize, now: Duration) {
+        *self = NotationTrainer {
+            seed,
+            ..Default::default()
+        };
+        self.ask(size, now);
+    }
+
+    /// Asks for a square of the given board, other than the one asked for last.
+    fn ask(&mut self, size: &BoardSize, now: Duration) {
+        let squares: Vec<BoardPosition> = size
+            .positions()
+            .filter(|position| self.last.is_none_or(|last| last.asked != *position))
+            .collect();
+        let mut random = Random::new(self.seed.wrapping_add(self.session.asked as u64));
+        self.question = Some((squares[random.below(squares.len())], now));
+    }
+
+    /// Checks the square clicked at the given time and asks for the next, returning the answer.
+    fn answer(
+        &mut self,
+        clicked: BoardPosition,
+        size: &BoardSize,
+        now: Duration,
+    ) -> Option<Answer> {
+        let (asked, asked_at) = self.question?;
+        let answer = Answer {
+            asked,
+            clicked,
+            time: now.saturating_sub(asked_at),
+        };
+        self.session.record(asked == clicked, answer.time);
+        self.last = Some(answer);
+        self.ask(size, now);
+        Some(answer)
+    }
+}
+
+/// Returns whether the trainer is not running, which is the run condition of the systems it
+/// takes the board from.
+pub(super) fn not_training(trainer: Res<NotationTrainer>) -> bool {
+    !trainer.is_running()
+}
+
+/// Answers the trainer with the squares clicked outside the windows, keeping the results in the player's profile.
+pub(super) fn answer_clicks(
+    mut contexts: EguiContexts,
+    mut board_click_events: EventReader<BoardClickEvent>,
+    mut trainer: ResMut<NotationTrainer>,
+    mut profiles: ResMut<Profiles>,
+    properties: Res<BoardProperties>,
+    time: Res<Time>,
+) {
+    // Clicks on the trainer's own window are not answers
+    if !trainer.is_running() || contexts.ctx_mut().is_pointer_over_area() {
+        return;
+    }
+    for click in board_click_events.iter() {
+        let Some(clicked) = click.position else {
+            continue;
+        };
+        if click.input.button != MouseButton::Left || click.input.state != ButtonState::Pressed {
+            continue;
+        }
+        if let Some(answer) = trainer.answer(clicked, properties.size(), time.elapsed()) {
+            profiles
+                .active_mut()
+                .notation_mut()
+                .record(answer.asked == answer.clicked, answer.time);
+            // The statistics are saved again with the next answer
+            profiles.save().ok();
+        }
+    }
+}
+
+/// Returns the given time in seconds, to a tenth of a second.
+fn seconds(time: Duration) -> String {
+    format!("{:.1}", time.as_secs_f32())
+}
+
+/// Shows the lines of the given statistics.
+fn stats_ui(ui: &mut egui::Ui, stats: &NotationStats, locale: &Localisation) {
+    ui.label(locale.format(
+        "notation-score",
+        &[
+            ("correct", &stats.correct),
+            ("asked", &stats.asked),
+            ("accuracy", &stats.accuracy().unwrap_or(0)),
+        ],
+    ));
+    if let (Some(average), Some(best)) = (stats.average_time(), stats.best_time()) {
+        ui.label(locale.format(
+            "notation-times",
+            &[("average", &seconds(average)), ("best", &seconds(best))],
+        ));
+    }
+}
+
+/// Shows the trainer, collapsed until it is opened, and the name of the square asked for over the
+/// board while it runs.
+pub(super) fn notation_trainer_window(
+    mut contexts: EguiContexts,
+    mut trainer: ResMut<NotationTrainer>,
+    profiles: Res<Profiles>,
+    properties: Res<BoardProperties>,
+    time: Res<Time>,
+    locale: Res<Localisation>,
+) {
+    let ctx = contexts.ctx_mut();
+    if let Some((asked, _)) = trainer.question {
+        egui::Area::new("notation-question")
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., 16.))
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(asked.to_algebraic())
+                        .size(NAME_SIZE)
+                        .strong(),
+                );
+            });
+    }
+    egui::Window::new(locale.get("notation-trainer"))
+        .default_open(false)
+        .show(ctx, |ui| {
+            ui.label(locale.get("notation-trainer-hint"));
+            if trainer.is_running() {
+                if ui.button(locale.get("stop-notation")).clicked() {
+                    trainer.question = None;
+                }
+            } else if ui.button(locale.get("start-notation")).clicked() {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_nanos() as u64);
+                trainer.start(seed, properties.size(), time.elapsed());
+            }
+            match trainer.last {
+                Some(answer) if answer.asked == answer.clicked => {
+                    ui.label(locale.format(
+                        "notation-correct",
+                        &[
+                            ("square", &answer.asked.to_algebraic()),
+                            ("time", &seconds(answer.time)),
+                        ],
+                    ));
+                }
+                Some(answer) => {
+                    ui.label(locale.format(
+                        "notation-wrong",
+                        &[
+                            ("clicked", &answer.clicked.to_algebraic()),
+                            ("square", &answer.asked.to_algebraic()),
+                        ],
+                    ));
+                }
+                None => {}
+            }
+            ui.heading(locale.get("notation-session"));
+            stats_ui(ui, &trainer.session, &locale);
+            ui.heading(locale.get("notation-all-time"));
+            stats_ui(ui, profiles.active().notation(), &locale);
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notation_trainer() {
+        let size = BoardSize::default();
+        let second = Duration::from_secs(1);
+        let mut trainer = NotationTrainer::default();
+        assert_eq!(
+            trainer.answer(BoardPosition::new(0, 0), &size, second),
+            None
+        );
+
+        trainer.start(5, &size, second);
+        let (asked, _) = trainer.question.unwrap();
+        let answer = trainer.answer(asked, &size, 3 * second).unwrap();
+        assert_eq!(answer.time, 2 * second);
+        // The same square is never asked for twice in a row
+        let (next, asked_at) = trainer.question.unwrap();
+        assert_ne!(next, asked);
+        assert_eq!(asked_at, 3 * second);
+
+        let wrong = size.positions().find(|position| *position != next).unwrap();
+        trainer.answer(wrong, &size, 4 * second);
+        assert_eq!((trainer.session.correct, trainer.session.asked), (1, 2));
+        assert!(trainer.is_running());
+    }
+}
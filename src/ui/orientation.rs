@@ -0,0 +1,274 @@
+//! Board orientation, including flipping the board and rotating it automatically in hot-seat games.
+//!
+//! With automatic rotation on, the board is viewed from the side to move, turning a short delay
+//! after each move and whenever the board is reset. Against the engine it is viewed from the
+//! player's side instead. Pieces cannot be picked up while the board turns, as they move under
+//! the cursor, but a piece already picked up can be dropped on whichever square is under it.
+
+use std::f32::consts::PI;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{
+    EventReader, EventWriter, Local, Or, Quat, Query, Res, ResMut, Resource, Transform, With,
+    Without,
+};
+use bevy::time::{Time, Timer, TimerMode};
+use bevy::window::RequestRedraw;
+use bevy_egui::egui;
+
+use crate::chess_board::{ChessBoard, PieceColor, PieceMoveEvent, ResetBoardEvent};
+use crate::engine::Engine;
+
+use super::annotation::AnnotationBadge;
+use super::board_sync::{self, BoardSync};
+use super::duck::DuckTag;
+use super::locale::Localisation;
+use super::piece::PieceTag;
+use super::square_info::{self, SquareInfo};
+use super::turn::{self, TurnIndicator};
+use super::MainCamera;
+
+/// The default time to wait after a move before rotating the board in hot-seat mode.
+const DEFAULT_ROTATION_DELAY: Duration = Duration::from_millis(500);
+
+/// The delays after a move which can be chosen, in milliseconds.
+const ROTATION_DELAY_RANGE: RangeInclusive<u64> = 0..=3000;
+
+/// The speed at which the board rotates, in radians per second.
+const ROTATION_SPEED: f32 = 2.0 * PI;
+
+/// The orientation of the board on screen.
+///
+/// The board is flipped by rotating the camera, so the mapping from the cursor to board squares
+/// stays correct at every point of the rotation.
+#[derive(Resource, Debug)]
+pub(super) struct BoardOrientation {
+    /// Whether the board is viewed from black's side.
+    flipped: bool,
+    /// The current rotation of the board, which moves towards the rotation given by `flipped`.
+    angle: f32,
+    /// Whether the board rotates to face the player to move after each move.
+    auto_rotate: bool,
+    /// The time to wait after a move before rotating the board.
+    rotation_delay: Duration,
+    /// A pending automatic rotation towards the given side.
+    pending_rotation: Option<(Timer, PieceColor)>,
+}
+
+impl Default for BoardOrientation {
+    fn default() -> Self {
+        BoardOrientation {
+            flipped: false,
+            angle: 0.,
+            auto_rotate: false,
+            rotation_delay: DEFAULT_ROTATION_DELAY,
+            pending_rotation: None,
+        }
+    }
+}
+
+impl BoardOrientation {
+    /// Flips the board, cancelling any pending automatic rotation.
+    pub(super) fn flip(&mut self) {
+        self.flipped = !self.flipped;
+        self.pending_rotation = None;
+    }
+
+    /// Returns whether the board is viewed, or turning to be viewed, from black's side.
+    pub(super) fn is_flipped(&self) -> bool {
+        self.flipped
+    }
+
+    /// Returns whether the board is rotating or waiting to rotate.
+    pub(super) fn is_animating(&self) -> bool {
+        self.pending_rotation.is_some() || self.angle != self.target_angle()
+    }
+
+    /// Returns whether the board is turning, rather than waiting to turn or still.
+    pub(super) fn is_rotating(&self) -> bool {
+        self.angle != self.target_angle()
+    }
+
+    fn target_angle(&self) -> f32 {
+        if self.flipped {
+            PI
+        } else {
+            0.
+        }
+    }
+
+    /// Moves the current rotation towards the target rotation by at most the given angle.
+    fn step(&mut self, max_step: f32) {
+        let difference = self.target_angle() - self.angle;
+        if difference.abs() <= max_step {
+            self.angle = self.target_angle();
+        } else {
+            self.angle += max_step * difference.signum();
+        }
+    }
+}
+
+/// The board orientation, the turn indicator which follows it, the square info overlay and the
+/// check that the pieces drawn match the board.
+#[derive(SystemParam)]
+pub(super) struct BoardView<'w> {
+    orientation: ResMut<'w, BoardOrientation>,
+    turn_indicator: ResMut<'w, TurnIndicator>,
+    square_info: ResMut<'w, SquareInfo>,
+    board_sync: ResMut<'w, BoardSync>,
+}
+
+/// Draws the board orientation, turn indicator, square info and consistency check controls.
+pub(super) fn settings_ui(ui: &mut egui::Ui, view: &mut BoardView, locale: &Localisation) {
+    if ui.button(locale.get("flip-board")).clicked() {
+        view.orientation.flip();
+    }
+    ui.checkbox(&mut view.orientation.auto_rotate, locale.get("auto-rotate"));
+    if view.orientation.auto_rotate {
+        let mut delay = view.orientation.rotation_delay.as_millis() as u64;
+        if ui
+            .add(
+                egui::Slider::new(&mut delay, ROTATION_DELAY_RANGE)
+                    .text(locale.get("rotation-delay")),
+            )
+            .changed()
+        {
+            view.orientation.rotation_delay = Duration::from_millis(delay);
+        }
+    }
+    turn::settings_ui(ui, &mut view.turn_indicator, locale);
+    square_info::settings_ui(ui, &mut view.square_info, locale);
+    board_sync::settings_ui(ui, &mut view.board_sync, locale);
+}
+
+/// Returns the side the board is viewed from when it rotates automatically: the player's against
+/// the engine, and otherwise the side to move, if the game has not ended.
+fn viewing_side(board: &ChessBoard, engine: &Engine) -> Option<PieceColor> {
+    match engine.color() {
+        Some(engine_color) => Some(engine_color.opposite()),
+        None => *board.active_color(),
+    }
+}
+
+/// Schedules a rotation towards the side the board is viewed from after each move and reset, and
+/// when automatic rotation is turned on.
+pub(super) fn auto_rotate(
+    mut move_events: EventReader<PieceMoveEvent>,
+    mut reset_events: EventReader<ResetBoardEvent>,
+    mut orientation: ResMut<BoardOrientation>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+    mut was_enabled: Local<bool>,
+) {
+    let moved = !move_events.is_empty() || !reset_events.is_empty();
+    move_events.clear();
+    reset_events.clear();
+    let enabled = orientation.auto_rotate && !*was_enabled;
+    *was_enabled = orientation.auto_rotate;
+    if !orientation.auto_rotate || !(moved || enabled) {
+        return;
+    }
+    if let Some(color) = viewing_side(&board, &engine) {
+        // Turning the setting on rotates the board at once
+        let delay = if enabled {
+            Duration::ZERO
+        } else {
+            orientation.rotation_delay
+        };
+        orientation.pending_rotation = Some((Timer::new(delay, TimerMode::Once), color));
+    }
+}
+
+/// The entities which are kept upright as the board rotates.
+type UprightFilter = (
+    Or<(With<PieceTag>, With<DuckTag>, With<AnnotationBadge>)>,
+    Without<MainCamera>,
+);
+
+/// Rotates the camera towards the board orientation, keeping the pieces, duck and badges upright.
+pub(super) fn animate_rotation(
+    mut orientation: ResMut<BoardOrientation>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+    mut upright: Query<&mut Transform, UprightFilter>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+    time: Res<Time>,
+) {
+    // Start any pending rotation once its delay has passed
+    if let Some((timer, color)) = orientation.pending_rotation.as_mut() {
+        if timer.tick(time.delta()).finished() {
+            orientation.flipped = *color == PieceColor::Black;
+            orientation.pending_rotation = None;
+        }
+    }
+    if orientation.is_animating() {
+        orientation.step(ROTATION_SPEED * time.delta_seconds());
+        redraw_events.send(RequestRedraw);
+    }
+
+    let rotation = Quat::from_rotation_z(orientation.angle);
+    for mut transform in camera.iter_mut() {
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+    for mut transform in upright.iter_mut() {
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_board_orientation_flip() {
+        let mut orientation = BoardOrientation {
+            pending_rotation: Some((
+                Timer::new(Duration::from_secs(1), TimerMode::Once),
+                PieceColor::White,
+            )),
+            ..Default::default()
+        };
+
+        orientation.flip();
+
+        assert!(orientation.flipped);
+        assert!(orientation.pending_rotation.is_none());
+        assert!(orientation.is_animating());
+    }
+
+    #[test]
+    fn test_board_orientation_step() {
+        let mut orientation = BoardOrientation::default();
+        orientation.flip();
+
+        orientation.step(PI / 2.);
+        assert_eq!(orientation.angle, PI / 2.);
+        assert!(orientation.is_animating());
+
+        orientation.step(PI);
+        assert_eq!(orientation.angle, PI);
+        assert!(!orientation.is_animating());
+
+        orientation.flip();
+        orientation.step(PI / 4.);
+        assert_eq!(orientation.angle, 3. * PI / 4.);
+    }
+
+    #[test]
+    fn test_viewing_side() {
+        let board = ChessBoard::with_position(&Fen::from_string(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        ));
+        let mut engine = Engine::default();
+        assert_eq!(viewing_side(&board, &engine), Some(PieceColor::Black));
+        engine.set_color(Some(PieceColor::Black));
+        assert_eq!(viewing_side(&board, &engine), Some(PieceColor::White));
+    }
+}
@@ -0,0 +1,183 @@
+//! The command palette, opened with Ctrl+[PALETTE_KEY], which lists every [action](super::actions)
+//! of the interface to search by name and run from the keyboard.
+//!
+//! The search is fuzzy: an action matches if the letters typed appear in its name in order, and
+//! the actions are listed best match first, where letters starting words and runs of letters
+//! count for more and letters skipped count against. The arrow keys move through the list, enter
+//! runs the action chosen and escape closes the palette.
+
+use bevy::input::Input;
+use bevy::prelude::{EventWriter, KeyCode, Res, ResMut, Resource};
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use super::actions::{command_held, Action, ActionEvent};
+use super::locale::Localisation;
+
+/// The key which, with Control or Command held, opens and closes the palette.
+const PALETTE_KEY: KeyCode = KeyCode::P;
+
+/// The score of a letter starting a word, and of one following the letter before it.
+const WORD_START_BONUS: i32 = 3;
+const RUN_BONUS: i32 = 2;
+
+/// Returns how well the given query matches the given text, higher for a better match, or None if
+/// the letters of the query do not all appear in the text in order. Case and spaces in the query
+/// are ignored.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for letter in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..text.len()).find(|index| text[*index] == letter)?;
+        score += 1;
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += RUN_BONUS;
+        }
+        score -= (found - next) as i32;
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+/// Returns the actions whose names match the given query, best match first.
+fn matching_actions(query: &str, locale: &Localisation) -> Vec<Action> {
+    let mut scored: Vec<(Action, i32)> = Action::iter()
+        .filter_map(|action| Some((action, fuzzy_score(query, locale.get(action.key()))?)))
+        .collect();
+    // The sort is stable, so equal matches keep the order of the registry
+    scored.sort_by_key(|(_, score)| -score);
+    scored.into_iter().map(|(action, _)| action).collect()
+}
+
+/// The state of the command palette.
+#[derive(Resource, Debug, Default)]
+pub(super) struct CommandPalette {
+    open: bool,
+    query: String,
+    /// The index of the chosen action among those matching.
+    selected: usize,
+}
+
+impl CommandPalette {
+    fn close(&mut self) {
+        self.open = false;
+        self.query.clear();
+        self.selected = 0;
+    }
+}
+
+/// Opens and closes the palette on its shortcut, which works even while a text field has focus
+/// so the palette's own search can close it.
+pub(super) fn palette_shortcut(keys: Res<Input<KeyCode>>, mut palette: ResMut<CommandPalette>) {
+    if !(command_held(&keys) && keys.just_pressed(PALETTE_KEY)) {
+        return;
+    }
+    match palette.open {
+        true => palette.close(),
+        false => palette.open = true,
+    }
+}
+
+/// Shows the palette while it is open, running the action chosen.
+pub(super) fn palette_window(
+    mut contexts: EguiContexts,
+    mut palette: ResMut<CommandPalette>,
+    locale: Res<Localisation>,
+    mut action_events: EventWriter<ActionEvent>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    if !palette.open {
+        return;
+    }
+    let ctx = contexts.ctx_mut();
+    let (up, down, enter, escape) = ctx.input(|input| {
+        (
+            input.key_pressed(egui::Key::ArrowUp),
+            input.key_pressed(egui::Key::ArrowDown),
+            input.key_pressed(egui::Key::Enter),
+            input.key_pressed(egui::Key::Escape),
+        )
+    });
+    if escape {
+        palette.close();
+        return;
+    }
+    let palette = &mut *palette;
+    let mut chosen = None;
+    egui::Window::new(locale.get("command-palette"))
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 40.0))
+        .show(ctx, |ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut palette.query)
+                    .hint_text(locale.get("command-palette-hint"))
+                    .desired_width(320.0),
+            );
+            response.request_focus();
+            if response.changed() {
+                palette.selected = 0;
+            }
+            let actions = matching_actions(&palette.query, &locale);
+            if actions.is_empty() {
+                ui.label(locale.get("no-matching-actions"));
+                return;
+            }
+            if down {
+                palette.selected = (palette.selected + 1).min(actions.len() - 1);
+            }
+            if up {
+                palette.selected = palette.selected.saturating_sub(1);
+            }
+            palette.selected = palette.selected.min(actions.len() - 1);
+            for (index, action) in actions.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui
+                        .selectable_label(index == palette.selected, locale.get(action.key()))
+                        .clicked()
+                    {
+                        chosen = Some(*action);
+                    }
+                    if let Some(shortcut) = action.shortcut() {
+                        ui.weak(shortcut.label());
+                    }
+                });
+            }
+            if enter {
+                chosen = Some(actions[palette.selected]);
+            }
+        });
+    if let Some(action) = chosen {
+        action_events.send(ActionEvent(action));
+        palette.close();
+        // The action is run in the next update, which would otherwise wait for more input
+        redraw_events.send(RequestRedraw);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_search() {
+        assert_eq!(fuzzy_score("", "Flip board"), Some(0));
+        assert_eq!(fuzzy_score("fb", "Flip board"), Some(4));
+        assert_eq!(fuzzy_score("bf", "Flip board"), None);
+        // A run of letters at the start of a word is the better match
+        assert!(fuzzy_score("flip", "Flip board") > fuzzy_score("flip", "Full lip"));
+
+        let locale = Localisation::default();
+        assert_eq!(matching_actions("flip", &locale)[0], Action::FlipBoard);
+        assert_eq!(matching_actions("", &locale).len(), Action::iter().count());
+        assert!(matching_actions("zzz", &locale).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! The pawn structure overlay, turned on in the [analysis window](super::analysis), which marks
+//! each doubled, isolated, backward and passed pawn of both sides with a mark in a corner of its
+//! square, one corner and color for each [feature](crate::pawn_structure).
+
+use bevy::prelude::{
+    default, Color, Commands, Component, DetectChanges, Entity, Local, Query, Res, Resource,
+    Transform, Vec2, With,
+};
+use bevy::sprite::{Sprite, SpriteBundle};
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::ChessBoard;
+use crate::engine::Engine;
+use crate::pawn_structure::{pawn_structure, PawnFeature};
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+
+/// The height of the marks above the squares, above the pieces.
+const MARK_Z: f32 = 1.5;
+
+/// The size of each mark, as a fraction of the square.
+const MARK_SIZE: f32 = 0.2;
+
+/// Returns the color of the marks of the given feature.
+fn mark_color(feature: PawnFeature) -> [u8; 3] {
+    match feature {
+        PawnFeature::Doubled => [230, 140, 20],
+        PawnFeature::Isolated => [220, 40, 40],
+        PawnFeature::Backward => [150, 60, 200],
+        PawnFeature::Passed => [40, 180, 60],
+    }
+}
+
+/// Returns the corner of the square the marks of the given feature are in, as the direction from
+/// its centre.
+fn mark_corner(feature: PawnFeature) -> Vec2 {
+    match feature {
+        PawnFeature::Doubled => Vec2::new(-1.0, 1.0),
+        PawnFeature::Isolated => Vec2::new(1.0, 1.0),
+        PawnFeature::Backward => Vec2::new(-1.0, -1.0),
+        PawnFeature::Passed => Vec2::new(1.0, -1.0),
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct PawnStructureOverlay {
+    enabled: bool,
+}
+
+impl PawnStructureOverlay {
+    pub(super) fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+}
+
+#[derive(Component)]
+pub(super) struct PawnMarkTag;
+
+/// Redraws the marks over the pawns whenever the position or the overlay changes. The marks are
+/// only shown while the engine is not playing, as the analysis is.
+pub(super) fn pawn_structure_overlay(
+    query: Query<Entity, With<PawnMarkTag>>,
+    mut commands: Commands,
+    overlay: Res<PawnStructureOverlay>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+    board_properties: Res<BoardProperties>,
+    mut shown: Local<bool>,
+) {
+    let show = overlay.enabled && engine.color().is_none();
+    if show == *shown && !(show && (board.is_changed() || board_properties.is_changed())) {
+        return;
+    }
+    *shown = show;
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !show {
+        return;
+    }
+    let square_size = board_properties.square_size();
+    let offset = square_size * (0.5 - MARK_SIZE / 2.0);
+    for pawn in pawn_structure(&board) {
+        let (x, y) = board_properties.position_to_transform(&pawn.position);
+        for feature in PawnFeature::iter().filter(|feature| pawn.has(*feature)) {
+            let [r, g, b] = mark_color(feature);
+            let corner = mark_corner(feature) * offset;
+            commands.spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::rgb_u8(r, g, b),
+                        custom_size: Some(Vec2::splat(square_size * MARK_SIZE)),
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(x + corner.x, y + corner.y, MARK_Z),
+                    ..default()
+                },
+                PawnMarkTag,
+            ));
+        }
+    }
+}
+
+/// Draws the toggle of the overlay, with the color of the marks of each feature.
+pub(super) fn overlay_ui(
+    ui: &mut egui::Ui,
+    overlay: &mut PawnStructureOverlay,
+    locale: &Localisation,
+) {
+    ui.checkbox(&mut overlay.enabled, locale.get("pawn-structure"))
+        .on_hover_text(locale.get("pawn-structure-hint"));
+    if overlay.enabled {
+        ui.horizontal_wrapped(|ui| {
+            for feature in PawnFeature::iter() {
+                let [r, g, b] = mark_color(feature);
+                ui.colored_label(egui::Color32::from_rgb(r, g, b), "■");
+                ui.label(locale.get(feature.key()));
+            }
+        });
+    }
+}
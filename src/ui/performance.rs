@@ -0,0 +1,106 @@
+//! The performance window, showing the frame rate and the time the chess systems take, which can
+//! also be shown as an overlay in the corner of the board while playing.
+
+use bevy::diagnostic::{DiagnosticId, DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::diagnostics::{ENGINE, HIGHLIGHTING, MOVE_GENERATION};
+
+use super::locale::Localisation;
+
+/// The timings shown, with the locale keys of their names.
+const TIMINGS: [(DiagnosticId, &str); 4] = [
+    (FrameTimeDiagnosticsPlugin::FRAME_TIME, "frame-time"),
+    (MOVE_GENERATION, "move-generation-time"),
+    (HIGHLIGHTING, "highlighting-time"),
+    (ENGINE, "engine-time"),
+];
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct PerformanceOverlay {
+    enabled: bool,
+}
+
+/// Returns the lines of the frame rate and of each timing which has been measured, with its
+/// average and its slowest recent measurement.
+fn lines(store: &DiagnosticsStore, locale: &Localisation) -> Vec<String> {
+    let fps = store
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .map(|fps| locale.format("fps", &[("fps", &format!("{:.0}", fps))]));
+    let timings = TIMINGS.iter().filter_map(|(id, key)| {
+        let diagnostic = store.get(*id)?;
+        let average = diagnostic.average()?;
+        let slowest = diagnostic.values().copied().fold(0., f64::max);
+        Some(locale.format(
+            "timing",
+            &[
+                ("name", &locale.get(key)),
+                ("average", &format!("{:.2}", average)),
+                ("slowest", &format!("{:.2}", slowest)),
+            ],
+        ))
+    });
+    fps.into_iter().chain(timings).collect()
+}
+
+/// Shows the performance window, collapsed until it is opened, and the overlay if it is on.
+pub(super) fn performance_window(
+    mut contexts: EguiContexts,
+    mut overlay: ResMut<PerformanceOverlay>,
+    store: Res<DiagnosticsStore>,
+    locale: Res<Localisation>,
+) {
+    let ctx = contexts.ctx_mut();
+    let lines = lines(&store, &locale);
+    if overlay.enabled {
+        egui::Area::new("performance-overlay")
+            .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8., -8.))
+            .interactable(false)
+            .show(ctx, |ui| {
+                for line in &lines {
+                    ui.label(egui::RichText::new(line).monospace());
+                }
+            });
+    }
+    egui::Window::new(locale.get("performance"))
+        .default_open(false)
+        .show(ctx, |ui| {
+            ui.checkbox(&mut overlay.enabled, locale.get("performance-overlay"));
+            ui.label(locale.get("performance-hint"));
+            for line in &lines {
+                ui.label(line);
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::diagnostic::{Diagnostic, DiagnosticMeasurement};
+
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let locale = Localisation::default();
+        let mut store = DiagnosticsStore::default();
+        assert!(lines(&store, &locale).is_empty());
+
+        let mut diagnostic = Diagnostic::new(HIGHLIGHTING, "highlighting", 10);
+        for value in [1., 3.] {
+            diagnostic.add_measurement(DiagnosticMeasurement {
+                time: Instant::now(),
+                value,
+            });
+        }
+        store.add(diagnostic);
+        // Timings which have not been measured yet are left out
+        store.add(Diagnostic::new(ENGINE, "engine", 10));
+        let lines = lines(&store, &locale);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].contains("2.00") && lines[0].contains("3.00"));
+    }
+}
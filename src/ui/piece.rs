@@ -1,20 +1,112 @@
+use std::collections::HashMap;
+use std::fs;
+use std::time::Duration;
+
+use bevy::audio::{SpatialAudioBundle, Volume};
+use bevy::ecs::system::{EntityCommands, SystemParam};
 use bevy::input::ButtonState;
+use bevy::log::warn;
 use bevy::prelude::{
     default, AssetServer, Assets, AudioBundle, Bundle, Camera, Changed, Commands, Component,
-    Entity, EventReader, EventWriter, FromWorld, GlobalTransform, Handle, MouseButton,
-    PlaybackSettings, Query, Res, Resource, Transform, Vec2, Vec3, With,
+    DetectChanges, Entity, EventReader, EventWriter, FromWorld, GlobalTransform, Handle, Image,
+    Local, MouseButton, PlaybackSettings, Query, Res, ResMut, Resource, Transform, Vec2, Vec3,
+    With,
 };
-use bevy::sprite::{SpriteSheetBundle, TextureAtlas, TextureAtlasSprite};
-use bevy::window::Window;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::sprite::{Sprite, SpriteBundle, SpriteSheetBundle, TextureAtlas, TextureAtlasSprite};
+use bevy::time::Time;
+use bevy::window::{RequestRedraw, Window};
 
+use crate::analysis::Analysis;
+use crate::chess_board::fairy::{fairy_piece, fairy_pieces};
 use crate::chess_board::r#move::Move;
+use crate::chess_board::snapshot::BoardSnapshot;
 use crate::chess_board::{
-    BoardPosition, ChessBoard, PieceColor, PieceCreateEvent, PieceMoveEvent, RequestMoveEvent,
-    ResetBoardEvent,
+    BoardPosition, ChessBoard, ChessBoardConfig, GameCommand, GameCommands, PieceColor,
+    PieceCreateEvent, PieceMoveEvent, PieceType, ResetBoardEvent,
 };
+use crate::engine::Engine;
+use crate::notification::Notification;
+use crate::spectating::Spectating;
 
+use super::audio::{spatial_settings, AudioSettings, SoundEvent, SoundPacks};
+use super::blunder::BlunderCheck;
 use super::board::BoardProperties;
-use super::{BoardClickEvent, MainCamera};
+use super::orientation::BoardOrientation;
+use super::premove::Premoves;
+use super::promotion::PromotionChoice;
+use super::theme::{PieceStyle, Theme};
+use super::{cursor_world_position, BoardClickEvent, MainCamera};
+
+use self::svg::Svg;
+
+mod svg;
+
+/// The vector piece set, in the order of the sprite sheet.
+const PIECE_SVGS: [&str; 12] = [
+    include_str!("../../assets/pieces/white_king.svg"),
+    include_str!("../../assets/pieces/white_queen.svg"),
+    include_str!("../../assets/pieces/white_bishop.svg"),
+    include_str!("../../assets/pieces/white_knight.svg"),
+    include_str!("../../assets/pieces/white_rook.svg"),
+    include_str!("../../assets/pieces/white_pawn.svg"),
+    include_str!("../../assets/pieces/black_king.svg"),
+    include_str!("../../assets/pieces/black_queen.svg"),
+    include_str!("../../assets/pieces/black_bishop.svg"),
+    include_str!("../../assets/pieces/black_knight.svg"),
+    include_str!("../../assets/pieces/black_rook.svg"),
+    include_str!("../../assets/pieces/black_pawn.svg"),
+];
+
+/// The duck of duck chess, which is drawn with the vector piece set in both piece styles.
+const DUCK_SVG: &str = include_str!("../../assets/pieces/duck.svg");
+
+/// The index of the duck in the vector piece set, after the pieces.
+const DUCK_INDEX: usize = PIECE_SVGS.len();
+
+/// The names of the files of the pieces and the duck in a piece set, in the order of the vector
+/// piece set.
+pub(super) const PIECE_FILES: [&str; DUCK_INDEX + 1] = [
+    "white_king.svg",
+    "white_queen.svg",
+    "white_bishop.svg",
+    "white_knight.svg",
+    "white_rook.svg",
+    "white_pawn.svg",
+    "black_king.svg",
+    "black_queen.svg",
+    "black_bishop.svg",
+    "black_knight.svg",
+    "black_rook.svg",
+    "black_pawn.svg",
+    "duck.svg",
+];
+
+/// Returns the index of the piece in the sprite sheet and the vector piece set, where a fairy
+/// piece is the standard piece it appears as.
+fn piece_index(color: &PieceColor, piece_type: &PieceType) -> usize {
+    let piece_type = match piece_type {
+        PieceType::Fairy(index) => {
+            fairy_piece(*index).map_or(PieceType::Pawn, |piece| piece.appearance)
+        }
+        piece_type => *piece_type,
+    };
+    piece_type.index().unwrap_or_default() + 6 * *color as usize
+}
+
+/// Returns the index of the given fairy piece in the vector piece set, after the duck.
+fn fairy_index(color: &PieceColor, index: u8) -> usize {
+    DUCK_INDEX + 1 + 2 * index as usize + *color as usize
+}
+
+/// Returns the given piece of the vector piece set rasterised at the given size in pixels, as
+/// RGBA rows.
+#[cfg(feature = "recognition")]
+pub(super) fn rasterise_piece(color: &PieceColor, piece_type: &PieceType, size: u32) -> Vec<u8> {
+    Svg::parse(PIECE_SVGS[piece_index(color, piece_type)])
+        .expect("Invalid piece SVG.")
+        .rasterise(size)
+}
 
 #[derive(Resource, Debug)]
 pub(super) struct PieceProperties {
@@ -46,6 +138,235 @@ impl FromWorld for PieceProperties {
     }
 }
 
+/// Returns the built-in vector piece set, with the duck after the pieces and the registered
+/// fairy pieces after the duck. Fairy pieces whose sprites cannot be loaded are drawn as the
+/// standard piece they appear as.
+fn built_in_svgs() -> Vec<Svg> {
+    let mut svgs: Vec<Svg> = PIECE_SVGS
+        .iter()
+        .chain([&DUCK_SVG])
+        .map(|svg| Svg::parse(svg).expect("Invalid piece SVG."))
+        .collect();
+    for (index, piece) in fairy_pieces() {
+        for color in [PieceColor::White, PieceColor::Black] {
+            let sprite = piece.sprites.as_ref().map(|(white, black)| match color {
+                PieceColor::White => white,
+                PieceColor::Black => black,
+            });
+            let svg = sprite.and_then(|path| match fs::read_to_string(path) {
+                Ok(text) => Svg::parse(&text),
+                Err(error) => {
+                    warn!(path, %error, "Could not load fairy piece sprite");
+                    None
+                }
+            });
+            debug_assert_eq!(svgs.len(), fairy_index(&color, index));
+            svgs.push(
+                svg.unwrap_or_else(|| svgs[piece_index(&color, &PieceType::Fairy(index))].clone()),
+            );
+        }
+    }
+    svgs
+}
+
+/// The vector piece set, rasterised at each size it has been shown at so the pieces stay crisp
+/// at any scale without being rasterised again when returning to an earlier scale.
+#[derive(Resource)]
+pub(super) struct VectorPieces {
+    svgs: Vec<Svg>,
+    images: HashMap<(usize, u32), Handle<Image>>,
+    /// The number of times the piece set has been replaced, so the pieces are redrawn each time.
+    version: u32,
+}
+
+impl Default for VectorPieces {
+    fn default() -> Self {
+        VectorPieces {
+            svgs: built_in_svgs(),
+            images: HashMap::new(),
+            version: 0,
+        }
+    }
+}
+
+impl VectorPieces {
+    /// Draws the pieces with the given SVG documents, in the order of [PIECE_FILES], keeping the
+    /// built-in piece for each which is missing or invalid, or with the built-in set for None.
+    pub(super) fn set_pieces(&mut self, pieces: Option<&[Option<String>]>) {
+        let mut svgs = built_in_svgs();
+        for (index, text) in pieces.unwrap_or_default().iter().enumerate() {
+            let Some(text) = text else {
+                continue;
+            };
+            match Svg::parse(text) {
+                Some(svg) if index < svgs.len() => svgs[index] = svg,
+                _ => warn!(file = PIECE_FILES.get(index), "Invalid piece SVG"),
+            }
+        }
+        self.svgs = svgs;
+        self.images.clear();
+        self.version += 1;
+    }
+
+    /// Returns the index of the given piece in the piece set, which is that of the standard piece
+    /// it appears as for a fairy piece registered after the set was loaded.
+    fn index(&self, color: &PieceColor, piece_type: &PieceType) -> usize {
+        match piece_type {
+            PieceType::Fairy(index) if fairy_index(color, *index) < self.svgs.len() => {
+                fairy_index(color, *index)
+            }
+            _ => piece_index(color, piece_type),
+        }
+    }
+
+    /// Returns the image of the piece with the given index rasterised at the given size in pixels.
+    fn image(&mut self, index: usize, size: u32, images: &mut Assets<Image>) -> Handle<Image> {
+        let svg = &self.svgs[index];
+        self.images
+            .entry((index, size))
+            .or_insert_with(|| {
+                images.add(Image::new(
+                    Extent3d {
+                        width: size,
+                        height: size,
+                        depth_or_array_layers: 1,
+                    },
+                    TextureDimension::D2,
+                    svg.rasterise(size),
+                    TextureFormat::Rgba8UnormSrgb,
+                ))
+            })
+            .clone()
+    }
+
+    /// Returns the image of the duck rasterised at the given size in pixels.
+    pub(super) fn duck_image(&mut self, size: u32, images: &mut Assets<Image>) -> Handle<Image> {
+        self.image(DUCK_INDEX, size, images)
+    }
+}
+
+/// The piece style and size the pieces are drawn with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct PieceAppearance {
+    style: PieceStyle,
+    /// The size of vector pieces in physical pixels.
+    size: u32,
+    /// The version of the vector piece set.
+    version: u32,
+}
+
+impl PieceAppearance {
+    pub(super) fn new(
+        theme: &Theme,
+        board_properties: &BoardProperties,
+        vector_pieces: &VectorPieces,
+        windows: &Query<&Window>,
+    ) -> Self {
+        let scale_factor = windows
+            .get_single()
+            .map_or(1., |window| window.scale_factor());
+        PieceAppearance {
+            style: theme.piece_style(),
+            size: (board_properties.square_size() as f64 * scale_factor).round() as u32,
+            version: vector_pieces.version,
+        }
+    }
+
+    pub(super) fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Adds the sprite of the given piece in this appearance to the entity.
+    fn insert_sprite(
+        &self,
+        entity: &mut EntityCommands,
+        color: &PieceColor,
+        piece_type: &PieceType,
+        transform: Transform,
+        assets: &mut PieceAssets,
+    ) {
+        let index = piece_index(color, piece_type);
+        match self.style {
+            PieceStyle::Sprites => {
+                entity
+                    .remove::<(Sprite, Handle<Image>)>()
+                    .insert(SpriteSheetBundle {
+                        sprite: TextureAtlasSprite::new(index),
+                        texture_atlas: assets.properties.texture_atlas_handle.clone(),
+                        transform: transform
+                            .with_scale(Vec3::splat(assets.properties.sprite_scale)),
+                        ..default()
+                    });
+            }
+            PieceStyle::Vector => {
+                let square_size = assets.board_properties.square_size();
+                let index = assets.vector_pieces.index(color, piece_type);
+                entity
+                    .remove::<(TextureAtlasSprite, Handle<TextureAtlas>)>()
+                    .insert(SpriteBundle {
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::splat(square_size)),
+                            ..default()
+                        },
+                        texture: assets
+                            .vector_pieces
+                            .image(index, self.size, &mut assets.images),
+                        transform: transform.with_scale(Vec3::ONE),
+                        ..default()
+                    });
+            }
+        }
+    }
+}
+
+/// The resources needed to draw the pieces.
+#[derive(SystemParam)]
+pub(super) struct PieceAssets<'w> {
+    properties: Res<'w, PieceProperties>,
+    board_properties: Res<'w, BoardProperties>,
+    theme: Res<'w, Theme>,
+    vector_pieces: ResMut<'w, VectorPieces>,
+    images: ResMut<'w, Assets<Image>>,
+}
+
+/// The animation of the engine's moves.
+#[derive(Resource, Debug)]
+pub(super) struct EngineMoveAnimation {
+    /// The speed pieces move at in squares per second, where zero moves them instantly.
+    pub(super) speed: f32,
+}
+
+impl Default for EngineMoveAnimation {
+    fn default() -> Self {
+        EngineMoveAnimation { speed: 8. }
+    }
+}
+
+/// A piece sliding from one point to another.
+#[derive(Component, Debug)]
+pub(super) struct MoveAnimation {
+    start: Vec2,
+    end: Vec2,
+    elapsed: Duration,
+    duration: Duration,
+}
+
+impl MoveAnimation {
+    /// Returns the position of the piece at the current point of the animation.
+    fn position(&self) -> Vec2 {
+        let progress = if self.duration.is_zero() {
+            1.
+        } else {
+            (self.elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.)
+        };
+        self.start.lerp(self.end, progress)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
 #[derive(Component, Clone)]
 pub(super) struct Dragging(bool);
 
@@ -55,33 +376,25 @@ impl Dragging {
     }
 }
 
-#[derive(Component)]
-pub(super) struct StartingPosition(BoardPosition);
-
 #[derive(Component)]
 pub(super) struct PieceTag;
 
-#[derive(Component)]
-pub(super) struct PieceMoveAudio;
-
 #[derive(Bundle)]
 struct PieceBundle {
     dragging: Dragging,
     position: BoardPosition,
-    sprite: SpriteSheetBundle,
     color: PieceColor,
-    starting_position: StartingPosition,
+    piece_type: PieceType,
     tag: PieceTag,
 }
 
 impl PieceBundle {
-    fn new(position: BoardPosition, sprite: SpriteSheetBundle, color: PieceColor) -> Self {
+    fn new(position: BoardPosition, color: PieceColor, piece_type: PieceType) -> Self {
         PieceBundle {
             dragging: Dragging(false),
             position,
-            sprite,
             color,
-            starting_position: StartingPosition(position),
+            piece_type,
             tag: PieceTag,
         }
     }
@@ -90,60 +403,210 @@ impl PieceBundle {
 pub(super) fn piece_creator(
     mut events: EventReader<PieceCreateEvent>,
     mut commands: Commands,
-    board_properties: Res<BoardProperties>,
-    piece_properties: Res<PieceProperties>,
+    mut assets: PieceAssets,
+    windows: Query<&Window>,
 ) {
+    let appearance = PieceAppearance::new(
+        &assets.theme,
+        &assets.board_properties,
+        &assets.vector_pieces,
+        &windows,
+    );
     for event in events.iter() {
-        let sprite_sheet_index = (*event.piece_type() as u8) + 6 * (*event.color() as u8);
-        let (x, y) = board_properties.position_to_transform(event.position());
-        let sprite = SpriteSheetBundle {
-            sprite: TextureAtlasSprite::new(sprite_sheet_index.into()),
-            texture_atlas: piece_properties.texture_atlas_handle.clone(),
-            transform: Transform::from_xyz(x, y, 1.)
-                .with_scale(Vec3::splat(piece_properties.sprite_scale)),
-            ..default()
-        };
-        commands.spawn(PieceBundle::new(*event.position(), sprite, *event.color()));
+        let (x, y) = assets
+            .board_properties
+            .position_to_transform(event.position());
+        let mut entity = commands.spawn(PieceBundle::new(
+            *event.position(),
+            *event.color(),
+            *event.piece_type(),
+        ));
+        appearance.insert_sprite(
+            &mut entity,
+            event.color(),
+            event.piece_type(),
+            Transform::from_xyz(x, y, 1.),
+            &mut assets,
+        );
+    }
+}
+
+/// Redraws the pieces when the piece style, the vector piece set or the window's scale factor
+/// changes.
+pub(super) fn piece_restyler(
+    query: Query<(Entity, &PieceColor, &PieceType, &Transform), With<PieceTag>>,
+    mut commands: Commands,
+    mut assets: PieceAssets,
+    windows: Query<&Window>,
+    mut current: Local<Option<PieceAppearance>>,
+) {
+    let appearance = PieceAppearance::new(
+        &assets.theme,
+        &assets.board_properties,
+        &assets.vector_pieces,
+        &windows,
+    );
+    if current.replace(appearance) == Some(appearance) {
+        return;
     }
+    for (entity, color, piece_type, transform) in query.iter() {
+        appearance.insert_sprite(
+            &mut commands.entity(entity),
+            color,
+            piece_type,
+            *transform,
+            &mut assets,
+        );
+    }
+}
+
+/// Returns the square the king on the given square lands on when castling with the rook on the
+/// other, if the other square holds a rook of the king's color on the same rank, so a king dropped
+/// onto its rook castles with it. Castling is two squares towards the rook, which the board then
+/// checks like any other castle.
+fn castle_onto_rook(
+    king: BoardPosition,
+    rook: BoardPosition,
+    board: &ChessBoard,
+) -> Option<BoardPosition> {
+    let color = board.get_piece_color(&king)?;
+    if board.get_piece_type(&king) != Some(PieceType::King)
+        || board.get_piece_type(&rook) != Some(PieceType::Rook)
+        || board.get_piece_color(&rook) != Some(color)
+        || king.rank() != rook.rank()
+    {
+        return None;
+    }
+    let file = if rook.file() > king.file() {
+        king.file() + 2
+    } else {
+        king.file().checked_sub(2)?
+    };
+    (file < board.size().files()).then(|| BoardPosition::new(*king.rank(), file))
+}
+
+/// The checks which hold a dropped piece's move back until the player confirms it or chooses the
+/// piece it promotes to.
+#[derive(SystemParam)]
+pub(super) struct MoveConfirmation<'w> {
+    pub(super) blunder_check: ResMut<'w, BlunderCheck>,
+    pub(super) promotion_choice: ResMut<'w, PromotionChoice>,
+}
+
+/// The board's options a dropped piece's move must be allowed by, the analysis and engine which
+/// explain a refused castle, and the notifications a refusal is sent with.
+#[derive(SystemParam)]
+pub(super) struct MoveRefusal<'w> {
+    config: Res<'w, ChessBoardConfig>,
+    analysis: Res<'w, Analysis>,
+    engine: Res<'w, Engine>,
+    notifications: EventWriter<'w, Notification>,
+}
+
+/// The board orientation, as pieces cannot be picked up while the board turns, and the premoves
+/// pieces are dropped into while the opponent is to move.
+#[derive(SystemParam)]
+pub(super) struct DragState<'w> {
+    orientation: Res<'w, BoardOrientation>,
+    premoves: ResMut<'w, Premoves>,
 }
 
 pub(super) fn piece_click_handler(
     mut board_click_events: EventReader<BoardClickEvent>,
     mut query: Query<(&mut Dragging, &BoardPosition), With<PieceTag>>,
-    mut piece_move_event: EventWriter<RequestMoveEvent>,
+    mut commands: ResMut<GameCommands>,
     board: Res<ChessBoard>,
+    confirmation: MoveConfirmation,
+    refusal: MoveRefusal,
+    drag_state: DragState,
 ) {
+    let MoveConfirmation {
+        mut blunder_check,
+        mut promotion_choice,
+    } = confirmation;
+    let MoveRefusal {
+        config,
+        analysis,
+        engine,
+        mut notifications,
+    } = refusal;
+    let DragState {
+        orientation,
+        mut premoves,
+    } = drag_state;
     for click in board_click_events.iter() {
+        // A right click cancels the premoves along with any drag
+        if click.input.button == MouseButton::Right
+            && click.input.state == ButtonState::Pressed
+            && !premoves.is_empty()
+        {
+            premoves.cancel();
+        }
         for (mut dragging, piece_position) in query.iter_mut() {
+            // Premoved pieces are picked up from where the premoves leave them
+            let shown_position = premoves.square_of(*piece_position);
             match click.input.button {
                 MouseButton::Left => {
                     if click.input.state == ButtonState::Pressed {
-                        if (click.position.is_some())
-                            && (click.position.unwrap() == *piece_position)
+                        // Pieces move under the cursor while the board turns
+                        if click.position.is_some()
+                            && click.position == shown_position
+                            && !orientation.is_rotating()
                         {
                             // Start dragging the piece
                             dragging.0 = true;
                         }
                     } else if click.input.state == ButtonState::Released && dragging.0 {
-                        if click.position.is_some() {
+                        let premover = board
+                            .get_piece_color(piece_position)
+                            .is_some_and(|color| premoves.can_premove(color));
+                        if let (true, Some(from), Some(to)) =
+                            (premover, shown_position, click.position)
+                        {
+                            // An illegal premove returns the piece, as dropping it off the board does
+                            premoves.queue(from, to, &board);
+                        } else if let Some((position, past_edge)) =
+                            click.position.map(|position| (position, false)).or(click
+                                .past_edge
+                                .filter(|_| !premover)
+                                .map(|position| (position, true)))
+                        {
+                            let position = castle_onto_rook(*piece_position, position, &board)
+                                .unwrap_or(position);
                             let potential_move =
-                                Move::from_board(*piece_position, click.position.unwrap(), &board);
-                            // When the button is released move the piece to that square if it is a valid move
-                            if board.valid_move(&potential_move, board.active_color(), &true) {
-                                let event = RequestMoveEvent::new(potential_move);
-                                piece_move_event.send(event);
+                                Move::from_board(*piece_position, position, &board);
+                            // When the button is released move the piece to that square if it is a valid move,
+                            // unless the player asks to choose what it promotes to or it loses material and
+                            // must be confirmed first
+                            if position == *piece_position
+                                || (past_edge && potential_move.promotion().is_none())
+                            {
+                                // Dropping the piece where it was picked up is not a move, and only a
+                                // promoting pawn can be dropped just beyond the last rank
+                            } else if !config.allows(&board, &potential_move) {
+                                // Analysis explains which condition a castle fails
+                                let castle_refusal = analysis
+                                    .is_active(&engine)
+                                    .then(|| config.castle_refusal(&board, &potential_move))
+                                    .flatten();
+                                notifications
+                                    .send(castle_refusal.unwrap_or_else(|| {
+                                        config.refusal(&board, &potential_move)
+                                    }));
+                            } else if promotion_choice.check(potential_move, past_edge || click.alt)
+                                && blunder_check.check(potential_move, &board)
+                            {
+                                commands.push(GameCommand::MakeMove(potential_move));
                             }
                         }
                         // Stop dragging the piece
                         dragging.0 = false;
                     }
                 }
-                MouseButton::Right => {
-                    // If the right button was clicked, stop dragging and return the piece to its original position
-                    if click.input.state == ButtonState::Pressed && dragging.0 {
-                        // Stop dragging the piece
-                        dragging.0 = false;
-                    }
+                // If the right button was clicked, stop dragging and return the piece to its original position
+                MouseButton::Right if click.input.state == ButtonState::Pressed && dragging.0 => {
+                    // Stop dragging the piece
+                    dragging.0 = false;
                 }
                 _ => {}
             }
@@ -151,26 +614,68 @@ pub(super) fn piece_click_handler(
     }
 }
 
+/// The engine, whose moves slide into place, and the animation they slide with.
+#[derive(SystemParam)]
+pub(super) struct EngineMoves<'w> {
+    engine: Res<'w, Engine>,
+    animation: Res<'w, EngineMoveAnimation>,
+}
+
 pub(super) fn piece_mover(
     mut piece_move_events: EventReader<PieceMoveEvent>,
-    mut query: Query<(Entity, &mut BoardPosition, &mut Transform), With<PieceTag>>,
+    mut query: Query<(Entity, &mut BoardPosition, &mut Transform, &PieceType), With<PieceTag>>,
     board_properties: Res<BoardProperties>,
+    board: Res<ChessBoard>,
+    engine_moves: EngineMoves,
+    windows: Query<&Window>,
     mut commands: Commands,
 ) {
+    let EngineMoves { engine, animation } = engine_moves;
+    // The engine's moves slide into place rather than moving instantly, unless the window is
+    // hidden, as the slide would be missed and only keep the app redrawing
+    let animate = animation.speed > 0.
+        && windows.get_single().map_or(true, |window| window.focused)
+        && engine.color().is_some()
+        && board
+            .past_moves()
+            .last()
+            .is_some_and(|piece_move| Some(*piece_move.piece_color()) == *engine.color());
     for event in piece_move_events.iter() {
-        // Remove any piece that is already there
-        for (entity, position, _transform) in query.iter() {
-            if *event.to() == *position {
+        // Remove any piece that is already there, or that was captured en passant
+        for (entity, position, _transform, _piece_type) in query.iter() {
+            if *event.to() == *position || *event.captured() == Some(*position) {
                 commands.entity(entity).despawn();
             }
         }
         // Move the piece
-        for (_entity, mut position, mut transform) in query.iter_mut() {
+        for (entity, mut position, mut transform, piece_type) in query.iter_mut() {
             if *position == *event.from() {
+                // A promoting pawn is replaced by the piece it promotes to
+                if board.get_piece_type(event.to()) != Some(*piece_type) {
+                    commands.entity(entity).despawn();
+                    continue;
+                }
                 // Change its transform
                 let new_transform = board_properties.position_to_transform(event.to());
-                *transform =
-                    transform.with_translation(Vec3::new(new_transform.0, new_transform.1, 1.0));
+                if animate {
+                    let squares = Vec2::new(
+                        event.from().file().abs_diff(*event.to().file()) as f32,
+                        event.from().rank().abs_diff(*event.to().rank()) as f32,
+                    )
+                    .length();
+                    commands.entity(entity).insert(MoveAnimation {
+                        start: transform.translation.truncate(),
+                        end: Vec2::new(new_transform.0, new_transform.1),
+                        elapsed: Duration::ZERO,
+                        duration: Duration::from_secs_f32(squares / animation.speed),
+                    });
+                } else {
+                    *transform = transform.with_translation(Vec3::new(
+                        new_transform.0,
+                        new_transform.1,
+                        1.0,
+                    ));
+                }
                 // Change its position
                 *position = *event.to();
             }
@@ -178,35 +683,103 @@ pub(super) fn piece_mover(
     }
 }
 
+/// Slides animated pieces towards their squares, above the other pieces.
+pub(super) fn piece_animator(
+    mut query: Query<(Entity, &mut MoveAnimation, &mut Transform), With<PieceTag>>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+    mut commands: Commands,
+    time: Res<Time>,
+) {
+    for (entity, mut animation, mut transform) in query.iter_mut() {
+        animation.elapsed += time.delta();
+        let position = animation.position();
+        let z = if animation.is_finished() {
+            commands.entity(entity).remove::<MoveAnimation>();
+            1.
+        } else {
+            redraw_events.send(RequestRedraw);
+            2.
+        };
+        *transform = transform.with_translation(position.extend(z));
+    }
+}
+
+/// Brings the pieces up to date with the board when it is reset, redrawing only the squares whose
+/// pieces changed, so stepping through a game does not redraw the whole board.
 pub(super) fn piece_resetter(
     mut board_reset_events: EventReader<ResetBoardEvent>,
-    mut query: Query<Entity, With<PieceTag>>,
+    query: Query<(Entity, &BoardPosition, &PieceColor, &PieceType), With<PieceTag>>,
+    board: Res<ChessBoard>,
+    mut create_events: EventWriter<PieceCreateEvent>,
     mut commands: Commands,
 ) {
-    for _event in board_reset_events.iter() {
-        for entity in query.iter_mut() {
-            // Despawn the piece
-            commands.entity(entity).despawn();
+    if board_reset_events.iter().last().is_none() {
+        return;
+    }
+    let drawn = BoardSnapshot::from_pieces(
+        query
+            .iter()
+            .map(|(_, position, color, piece_type)| (*position, *color, *piece_type)),
+    );
+    for change in drawn.diff(&BoardSnapshot::of(&board)) {
+        for (entity, position, _, _) in query.iter() {
+            if *position == change.position {
+                commands.entity(entity).despawn();
+            }
+        }
+        if let Some((color, piece_type)) = change.after {
+            create_events.send(PieceCreateEvent::new(change.position, piece_type, color));
         }
     }
 }
 
 pub(super) fn piece_move_audio(
     mut events: EventReader<PieceMoveEvent>,
+    board: Res<ChessBoard>,
+    orientation: Res<BoardOrientation>,
+    audio_settings: Res<AudioSettings>,
+    sound_packs: Res<SoundPacks>,
     asset_server: Res<AssetServer>,
     mut commands: Commands,
 ) {
-    for _event in events.iter() {
-        commands.spawn((AudioBundle {
-            source: asset_server.load("sounds/chess_move_on_alabaster.wav"),
-            settings: PlaybackSettings::DESPAWN,
-        },));
+    // A castle moves two pieces, so only play one sound for all the moves this frame
+    if events.is_empty() {
+        return;
+    }
+    events.clear();
+    let sound_event = match board.past_moves().last() {
+        Some(piece_move) if piece_move.is_castle() => SoundEvent::Castle,
+        Some(piece_move) if piece_move.is_capture() => SoundEvent::Capture,
+        _ => SoundEvent::Move,
+    };
+    let Some((path, volume)) = audio_settings.sound(sound_event, &sound_packs) else {
+        return;
+    };
+    let placement = board.past_moves().last().and_then(|piece_move| {
+        audio_settings.placement(piece_move.to(), board.size(), orientation.is_flipped())
+    });
+    match placement {
+        Some((pan, share)) => {
+            commands.spawn(SpatialAudioBundle {
+                source: asset_server.load(path),
+                settings: PlaybackSettings::DESPAWN
+                    .with_volume(Volume::new_relative(volume * share)),
+                spatial: spatial_settings(pan),
+            });
+        }
+        None => {
+            commands.spawn((AudioBundle {
+                source: asset_server.load(path),
+                settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+            },));
+        }
     }
 }
 
 pub(super) fn piece_dragger(
     mut query: Query<(&Dragging, &mut Transform, &PieceColor), With<PieceTag>>,
     board: Res<ChessBoard>,
+    premoves: Res<Premoves>,
     windows: Query<&Window>,
     camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
 ) {
@@ -214,16 +787,12 @@ pub(super) fn piece_dragger(
     let window = windows.single();
     let (camera, camera_transform) = camera.single();
     // Check if the cursor is in the window
-    if let Some(world_position) = window
-        .cursor_position()
-        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
-        .map(|ray| ray.origin.truncate())
-    {
+    if let Some(world_position) = cursor_world_position(window, camera, camera_transform) {
         for (dragging, mut transform, piece_color) in query.iter_mut() {
-            // If this piece is being dragged and is the current active color
+            // If this piece is being dragged and is the current active color, or is being premoved
             if dragging.0
-                && board.active_color().is_some()
-                && board.active_color().unwrap() == *piece_color
+                && (*board.active_color() == Some(*piece_color)
+                    || premoves.can_premove(*piece_color))
             {
                 // Move this piece to follow the mouse
                 *transform = transform.with_translation(Vec3::new(
@@ -236,6 +805,22 @@ pub(super) fn piece_dragger(
     }
 }
 
+/// Returns any piece being dragged once the board starts being [spectated](crate::spectating),
+/// as it can no longer be dropped.
+pub(super) fn drop_spectated_pieces(
+    spectating: Res<Spectating>,
+    mut query: Query<&mut Dragging, With<PieceTag>>,
+) {
+    if !spectating.is_changed() || spectating.get().is_none() {
+        return;
+    }
+    for mut dragging in query.iter_mut() {
+        if dragging.0 {
+            dragging.0 = false;
+        }
+    }
+}
+
 pub(super) fn piece_undragger(
     mut query: Query<
         (&Dragging, &mut Transform, &BoardPosition),
@@ -253,3 +838,78 @@ pub(super) fn piece_undragger(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_animation_position() {
+        let mut animation = MoveAnimation {
+            start: Vec2::new(0., 0.),
+            end: Vec2::new(80., 160.),
+            elapsed: Duration::ZERO,
+            duration: Duration::from_millis(200),
+        };
+        assert_eq!(animation.position(), Vec2::new(0., 0.));
+
+        animation.elapsed = Duration::from_millis(50);
+        assert_eq!(animation.position(), Vec2::new(20., 40.));
+        assert!(!animation.is_finished());
+
+        animation.elapsed = Duration::from_millis(300);
+        assert_eq!(animation.position(), Vec2::new(80., 160.));
+        assert!(animation.is_finished());
+    }
+
+    #[test]
+    fn test_castle_onto_rook() {
+        let board = ChessBoard::with_position(&crate::fen::Fen::from_string(
+            "r3k2r/8/8/8/8/8/8/R3K1NR w KQkq - 0 1",
+        ));
+        let square = |square| BoardPosition::from_algebraic(square).unwrap();
+        assert_eq!(
+            castle_onto_rook(square("e1"), square("h1"), &board),
+            Some(square("g1"))
+        );
+        assert_eq!(
+            castle_onto_rook(square("e1"), square("a1"), &board),
+            Some(square("c1"))
+        );
+        // Only a king dropped onto a rook of its own color castles
+        assert_eq!(castle_onto_rook(square("e1"), square("h8"), &board), None);
+        assert_eq!(castle_onto_rook(square("e1"), square("g1"), &board), None);
+        assert_eq!(castle_onto_rook(square("a1"), square("e1"), &board), None);
+    }
+
+    #[test]
+    fn test_vector_pieces() {
+        // Every piece and the duck parse, with the duck after the pieces, and no fairy pieces are
+        // registered
+        let pieces = VectorPieces::default();
+        assert_eq!(pieces.svgs.len(), DUCK_INDEX + 1);
+        let duck = pieces.svgs[DUCK_INDEX].rasterise(16);
+        // The centre of the duck is opaque and its corners are transparent
+        assert_eq!(duck[(8 * 16 + 8) * 4 + 3], 255);
+        assert_eq!(duck[3], 0);
+    }
+
+    #[test]
+    fn test_vector_pieces_set_pieces() {
+        let mut pieces = VectorPieces::default();
+        let built_in = pieces.svgs.clone();
+        let dot = r#"<svg viewBox="0 0 10 10"><circle cx="5" cy="5" r="4"/></svg>"#;
+        let mut files = vec![None; PIECE_FILES.len()];
+        files[0] = Some(dot.to_string());
+        files[1] = Some("not an svg".to_string());
+        pieces.set_pieces(Some(&files));
+        // Invalid and missing pieces keep the built-in ones
+        assert_ne!(pieces.svgs[0], built_in[0]);
+        assert_eq!(pieces.svgs[1..], built_in[1..]);
+        assert_eq!(pieces.version, 1);
+
+        pieces.set_pieces(None);
+        assert_eq!(pieces.svgs, built_in);
+        assert_eq!(pieces.version, 2);
+    }
+}
@@ -0,0 +1,386 @@
+//! A rasteriser for the subset of SVG used by the vector piece sets.
+//!
+//! Only `path` and `circle` elements are drawn, with `fill`, `stroke` and `stroke-width`
+//! attributes holding `#rrggbb` colors or `none`. Paths support the move, line, horizontal,
+//! vertical, cubic and quadratic curve and close commands in absolute and relative form.
+
+use bevy::prelude::Vec2;
+
+/// The number of samples along each side of a pixel, for anti-aliasing.
+const SAMPLES_PER_SIDE: usize = 4;
+
+/// The number of line segments each curve is flattened into.
+const CURVE_SEGMENTS: usize = 16;
+
+/// An RGBA color.
+type Rgba = [u8; 4];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Outline {
+    /// The polylines of each subpath, and whether each is closed.
+    Path(Vec<(Vec<Vec2>, bool)>),
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Shape {
+    outline: Outline,
+    fill: Option<Rgba>,
+    stroke: Option<(Rgba, f32)>,
+}
+
+/// A parsed SVG document.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Svg {
+    /// The top left corner and size of the view box.
+    view_box: (Vec2, Vec2),
+    shapes: Vec<Shape>,
+}
+
+/// Returns the value of the given attribute of an element.
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let start = element.find(&format!(" {}=\"", name))? + name.len() + 3;
+    let length = element[start..].find('"')?;
+    Some(&element[start..start + length])
+}
+
+fn numbers(text: &str) -> Vec<f32> {
+    text.split(|char: char| char.is_whitespace() || char == ',')
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+fn color(text: &str) -> Option<Rgba> {
+    let hex = text.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?, 255])
+}
+
+/// Splits path data into its commands and their arguments.
+fn path_commands(data: &str) -> Vec<(char, Vec<f32>)> {
+    let mut commands = Vec::new();
+    let mut start = None;
+    for (index, char) in data.char_indices() {
+        if char.is_ascii_alphabetic() {
+            if let Some(start) = start {
+                commands.push((
+                    data[start..].chars().next().unwrap(),
+                    &data[start + 1..index],
+                ));
+            }
+            start = Some(index);
+        }
+    }
+    if let Some(start) = start {
+        commands.push((data[start..].chars().next().unwrap(), &data[start + 1..]));
+    }
+    commands
+        .into_iter()
+        .map(|(command, arguments)| (command, numbers(arguments)))
+        .collect()
+}
+
+/// Flattens path data into polylines.
+fn path_outline(data: &str) -> Outline {
+    let mut subpaths: Vec<(Vec<Vec2>, bool)> = Vec::new();
+    let mut current = Vec2::ZERO;
+    for (command, arguments) in path_commands(data) {
+        let relative = command.is_ascii_lowercase();
+        let arity = match command.to_ascii_uppercase() {
+            'M' | 'L' => 2,
+            'H' | 'V' => 1,
+            'C' => 6,
+            'Q' => 4,
+            _ => 0,
+        };
+        if command.eq_ignore_ascii_case(&'Z') {
+            if let Some((points, closed)) = subpaths.last_mut() {
+                *closed = true;
+                current = points[0];
+            }
+            continue;
+        }
+        for chunk in 0..arguments.len() / arity.max(1) {
+            let index = chunk * arity;
+            let offset = if relative { current } else { Vec2::ZERO };
+            let point = |index: usize| Vec2::new(arguments[index], arguments[index + 1]) + offset;
+            let mut points = Vec::new();
+            match command.to_ascii_uppercase() {
+                // Further coordinates after a move are lines
+                'M' if chunk == 0 => {
+                    current = point(index);
+                    subpaths.push((vec![current], false));
+                    continue;
+                }
+                'M' | 'L' => points.push(point(index)),
+                'H' => points.push(Vec2::new(arguments[index] + offset.x, current.y)),
+                'V' => points.push(Vec2::new(current.x, arguments[index] + offset.y)),
+                'C' => {
+                    let (control1, control2, end) =
+                        (point(index), point(index + 2), point(index + 4));
+                    points.extend((1..=CURVE_SEGMENTS).map(|step| {
+                        let t = step as f32 / CURVE_SEGMENTS as f32;
+                        let u = 1. - t;
+                        current * u * u * u
+                            + control1 * 3. * u * u * t
+                            + control2 * 3. * u * t * t
+                            + end * t * t * t
+                    }));
+                }
+                'Q' => {
+                    let (control, end) = (point(index), point(index + 2));
+                    points.extend((1..=CURVE_SEGMENTS).map(|step| {
+                        let t = step as f32 / CURVE_SEGMENTS as f32;
+                        let u = 1. - t;
+                        current * u * u + control * 2. * u * t + end * t * t
+                    }));
+                }
+                _ => {}
+            }
+            if subpaths.is_empty() {
+                subpaths.push((vec![current], false));
+            }
+            current = *points.last().unwrap_or(&current);
+            subpaths.last_mut().unwrap().0.extend(points);
+        }
+    }
+    Outline::Path(subpaths)
+}
+
+impl Svg {
+    /// Parses the drawable elements of an SVG document.
+    pub(super) fn parse(text: &str) -> Option<Self> {
+        let mut view_box = None;
+        let mut shapes = Vec::new();
+        for element in text.split('<').skip(1) {
+            let element = element.split('>').next()?;
+            let name = element.split_whitespace().next()?;
+            let outline = match name {
+                "svg" => {
+                    let values = numbers(attribute(element, "viewBox")?);
+                    view_box = Some((
+                        Vec2::new(*values.first()?, *values.get(1)?),
+                        Vec2::new(*values.get(2)?, *values.get(3)?),
+                    ));
+                    continue;
+                }
+                "path" => path_outline(attribute(element, "d")?),
+                "circle" => {
+                    let value =
+                        |name| attribute(element, name).and_then(|value| value.parse().ok());
+                    Outline::Circle {
+                        center: Vec2::new(value("cx")?, value("cy")?),
+                        radius: value("r")?,
+                    }
+                }
+                _ => continue,
+            };
+            // Shapes are filled black and not stroked unless they say otherwise
+            let fill = match attribute(element, "fill") {
+                Some(fill) => color(fill),
+                None => Some([0, 0, 0, 255]),
+            };
+            let stroke = attribute(element, "stroke").and_then(color).map(|stroke| {
+                let width = attribute(element, "stroke-width")
+                    .and_then(|width| width.parse().ok())
+                    .unwrap_or(1.);
+                (stroke, width)
+            });
+            shapes.push(Shape {
+                outline,
+                fill,
+                stroke,
+            });
+        }
+        Some(Svg {
+            view_box: view_box?,
+            shapes,
+        })
+    }
+
+    /// Renders the document into a square image with the given side in pixels, returning its
+    /// RGBA pixels row by row.
+    pub(super) fn rasterise(&self, size: u32) -> Vec<u8> {
+        let (origin, extent) = self.view_box;
+        let scale = extent.x.max(extent.y) / size as f32;
+        let mut pixels = vec![0; (size * size * 4) as usize];
+        let sample_count = (SAMPLES_PER_SIDE * SAMPLES_PER_SIDE) as f32;
+        for shape in &self.shapes {
+            let layers = [
+                shape.fill.map(|fill| (fill, None)),
+                shape.stroke.map(|(stroke, width)| (stroke, Some(width))),
+            ];
+            for (color, stroke_width) in layers.into_iter().flatten() {
+                // Only the pixels around the shape can be covered by it
+                let (min, max) = shape.outline.bounds();
+                let margin = stroke_width.unwrap_or(0.) / 2.;
+                let pixel_range = |min: f32, max: f32, origin: f32| {
+                    let first = ((min - margin - origin) / scale).floor().max(0.) as u32;
+                    let last = ((max + margin - origin) / scale)
+                        .ceil()
+                        .clamp(0., size as f32);
+                    first..last as u32
+                };
+                for y in pixel_range(min.y, max.y, origin.y) {
+                    for x in pixel_range(min.x, max.x, origin.x) {
+                        let mut covered = 0;
+                        for sample in 0..SAMPLES_PER_SIDE * SAMPLES_PER_SIDE {
+                            let offset = Vec2::new(
+                                (sample % SAMPLES_PER_SIDE) as f32 + 0.5,
+                                (sample / SAMPLES_PER_SIDE) as f32 + 0.5,
+                            ) / SAMPLES_PER_SIDE as f32;
+                            let point = origin + (Vec2::new(x as f32, y as f32) + offset) * scale;
+                            let inside = match stroke_width {
+                                None => shape.outline.contains(point),
+                                Some(width) => shape.outline.distance(point) <= width / 2.,
+                            };
+                            covered += inside as u32;
+                        }
+                        if covered > 0 {
+                            let index = ((y * size + x) * 4) as usize;
+                            blend(
+                                &mut pixels[index..index + 4],
+                                color,
+                                covered as f32 / sample_count,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        pixels
+    }
+}
+
+impl Outline {
+    /// Returns the corners of the box bounding the outline.
+    fn bounds(&self) -> (Vec2, Vec2) {
+        match self {
+            Outline::Circle { center, radius } => (
+                *center - Vec2::splat(*radius),
+                *center + Vec2::splat(*radius),
+            ),
+            Outline::Path(subpaths) => subpaths.iter().flat_map(|(points, _)| points).fold(
+                (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+                |(min, max), point| (min.min(*point), max.max(*point)),
+            ),
+        }
+    }
+
+    /// Returns whether the point is inside the outline, using the nonzero rule for paths.
+    fn contains(&self, point: Vec2) -> bool {
+        match self {
+            Outline::Circle { center, radius } => point.distance(*center) <= *radius,
+            Outline::Path(subpaths) => {
+                let mut winding = 0;
+                for (points, _) in subpaths {
+                    // Subpaths are closed for filling
+                    for (start, end) in points.iter().zip(points.iter().cycle().skip(1)) {
+                        if (start.y <= point.y) != (end.y <= point.y) {
+                            let x = start.x
+                                + (point.y - start.y) / (end.y - start.y) * (end.x - start.x);
+                            if x > point.x {
+                                winding += if end.y > start.y { 1 } else { -1 };
+                            }
+                        }
+                    }
+                }
+                winding != 0
+            }
+        }
+    }
+
+    /// Returns the distance from the point to the outline.
+    fn distance(&self, point: Vec2) -> f32 {
+        match self {
+            Outline::Circle { center, radius } => (point.distance(*center) - radius).abs(),
+            Outline::Path(subpaths) => subpaths
+                .iter()
+                .flat_map(|(points, closed)| {
+                    let closing = closed.then(|| (*points.last().unwrap(), points[0]));
+                    points
+                        .windows(2)
+                        .map(|pair| (pair[0], pair[1]))
+                        .chain(closing)
+                })
+                .map(|(start, end)| {
+                    let segment = end - start;
+                    let t = if segment.length_squared() > 0. {
+                        ((point - start).dot(segment) / segment.length_squared()).clamp(0., 1.)
+                    } else {
+                        0.
+                    };
+                    point.distance(start + segment * t)
+                })
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+}
+
+/// Draws the color over the pixel with the given coverage.
+fn blend(pixel: &mut [u8], color: Rgba, coverage: f32) {
+    let alpha = coverage * color[3] as f32 / 255.;
+    let below = pixel[3] as f32 / 255.;
+    let out = alpha + below * (1. - alpha);
+    for channel in 0..3 {
+        let mixed = color[channel] as f32 * alpha + pixel[channel] as f32 * below * (1. - alpha);
+        pixel[channel] = if out > 0. {
+            (mixed / out).round() as u8
+        } else {
+            0
+        };
+    }
+    pixel[3] = (out * 255.).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_svg_parse() {
+        let svg = Svg::parse(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">
+            <path d="M 1 1 h 8 V 9 l -8 0 z" fill="#ff0000" stroke="#000000" stroke-width="2"/>
+            <circle cx="5" cy="5" r="2" fill="none"/>
+            </svg>"##,
+        )
+        .unwrap();
+        assert_eq!(svg.view_box, (Vec2::ZERO, Vec2::new(10., 10.)));
+        assert_eq!(svg.shapes.len(), 2);
+        assert_eq!(
+            svg.shapes[0].outline,
+            Outline::Path(vec![(
+                vec![
+                    Vec2::new(1., 1.),
+                    Vec2::new(9., 1.),
+                    Vec2::new(9., 9.),
+                    Vec2::new(1., 9.)
+                ],
+                true
+            )])
+        );
+        assert_eq!(svg.shapes[0].fill, Some([255, 0, 0, 255]));
+        assert_eq!(svg.shapes[0].stroke, Some(([0, 0, 0, 255], 2.)));
+        assert_eq!(svg.shapes[1].fill, None);
+    }
+
+    #[test]
+    fn test_svg_rasterise() {
+        let svg = Svg::parse(
+            r##"<svg viewBox="0 0 10 10"><path d="M 0 0 L 5 0 L 5 10 L 0 10 Z" fill="#ffffff"/></svg>"##,
+        )
+        .unwrap();
+        let pixels = svg.rasterise(10);
+        let pixel = |x: usize, y: usize| &pixels[(y * 10 + x) * 4..(y * 10 + x) * 4 + 4];
+        assert_eq!(pixel(2, 5), [255, 255, 255, 255]);
+        assert_eq!(pixel(7, 5), [0, 0, 0, 0]);
+
+        // Edges are anti-aliased
+        let pixels = svg.rasterise(5);
+        assert_eq!(pixels[(2 * 5 + 2) * 4 + 3], 128);
+    }
+}
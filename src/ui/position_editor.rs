@@ -0,0 +1,357 @@
+//! The position editor, which sets up a position square by square to load onto the board, with
+//! tools to clear the board, mirror the position and swap the colors of its pieces.
+//!
+//! Every change is an [Edit] kept on a stack, so the changes can be undone and redone in order.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use chesscomputer_core::castling_rights::CastlingRights;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::snapshot::SquareContent;
+use crate::chess_board::{
+    BoardPosition, BoardSize, ChessBoard, GameCommand, GameCommands, PieceColor, PieceType,
+    ResetBoardEvent, Variant, BOARD_SIZE, MAX_FILES,
+};
+use crate::fen::{Fen, PiecePlacement};
+use crate::report::piece_symbol;
+
+use super::locale::Localisation;
+
+/// The pieces which can be placed, in the order of the palette.
+const PIECES: [PieceType; 6] = [
+    PieceType::King,
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+    PieceType::Pawn,
+];
+
+/// A change made in the editor.
+#[derive(Debug, Clone, PartialEq)]
+enum Edit {
+    /// The piece on a square was replaced, which is also how pieces are placed and removed.
+    Square {
+        position: BoardPosition,
+        before: SquareContent,
+        after: SquareContent,
+    },
+    /// Every piece was replaced, as by clearing the board or copying the game's position.
+    Board {
+        before: Box<PiecePlacement>,
+        after: Box<PiecePlacement>,
+    },
+    /// The position was mirrored from the a-file to the h-file.
+    Mirror,
+    /// The colors of the pieces were swapped and the ranks flipped, so each pawn still moves
+    /// forwards, with the other player to move.
+    SwapColors,
+    /// The other player was made the one to move.
+    SwitchTurn,
+}
+
+impl Edit {
+    /// Makes the edit to the given position, or undoes it.
+    fn apply(&self, placement: &mut PiecePlacement, active_color: &mut PieceColor, undo: bool) {
+        let size = BoardSize::default();
+        match self {
+            Edit::Square {
+                position,
+                before,
+                after,
+            } => {
+                placement[*position.rank()][*position.file()] = if undo { *before } else { *after }
+            }
+            Edit::Board { before, after } => *placement = if undo { **before } else { **after },
+            // The other edits undo themselves
+            Edit::Mirror => {
+                for rank in placement.iter_mut() {
+                    rank[..size.files()].reverse();
+                }
+            }
+            Edit::SwapColors => {
+                placement[..size.ranks()].reverse();
+                for (color, _) in placement.iter_mut().flatten().flatten() {
+                    *color = color.opposite();
+                }
+                *active_color = active_color.opposite();
+            }
+            Edit::SwitchTurn => *active_color = active_color.opposite(),
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub(super) struct PositionEditor {
+    placement: PiecePlacement,
+    active_color: PieceColor,
+    /// The piece placed on the squares clicked, or None to remove their pieces.
+    brush: SquareContent,
+    /// The edits made, the last of which is undone first.
+    done: Vec<Edit>,
+    /// The edits undone, the last of which is redone first.
+    undone: Vec<Edit>,
+    /// The locale key of why the position could not be loaded.
+    error: Option<&'static str>,
+}
+
+impl Default for PositionEditor {
+    fn default() -> Self {
+        PositionEditor {
+            placement: *Fen::default().piece_placement(),
+            active_color: PieceColor::White,
+            brush: Some((PieceColor::White, PieceType::Queen)),
+            done: Vec::new(),
+            undone: Vec::new(),
+            error: None,
+        }
+    }
+}
+
+impl PositionEditor {
+    /// Makes the given edit, which can then be undone.
+    fn edit(&mut self, edit: Edit) {
+        edit.apply(&mut self.placement, &mut self.active_color, false);
+        self.done.push(edit);
+        self.undone.clear();
+        self.error = None;
+    }
+
+    fn undo(&mut self) {
+        if let Some(edit) = self.done.pop() {
+            edit.apply(&mut self.placement, &mut self.active_color, true);
+            self.undone.push(edit);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(edit) = self.undone.pop() {
+            edit.apply(&mut self.placement, &mut self.active_color, false);
+            self.done.push(edit);
+        }
+    }
+
+    /// Places the brush's piece on the given square, unless it is already there.
+    fn paint(&mut self, position: BoardPosition) {
+        let before = self.placement[*position.rank()][*position.file()];
+        if before != self.brush {
+            self.edit(Edit::Square {
+                position,
+                before,
+                after: self.brush,
+            });
+        }
+    }
+
+    /// Replaces every piece with the given ones.
+    fn replace(&mut self, placement: PiecePlacement) {
+        if placement != self.placement {
+            self.edit(Edit::Board {
+                before: Box::new(self.placement),
+                after: Box::new(placement),
+            });
+        }
+    }
+
+    /// Returns the position set up, which can castle with each king and rook still on their
+    /// starting squares.
+    fn fen(&self) -> Fen {
+        let has = |rank: usize, file: usize, piece: (PieceColor, PieceType)| {
+            self.placement[rank][file] == Some(piece)
+        };
+        let rights = |rank, color| {
+            let king = has(rank, 4, (color, PieceType::King));
+            [
+                king && has(rank, 7, (color, PieceType::Rook)),
+                king && has(rank, 0, (color, PieceType::Rook)),
+            ]
+        };
+        let castling_rights = CastlingRights {
+            white: rights(7, PieceColor::White),
+            black: rights(0, PieceColor::Black),
+        };
+        Fen::new(
+            self.placement,
+            self.active_color,
+            castling_rights,
+            None,
+            0,
+            1,
+        )
+    }
+
+    /// Loads the position set up onto the board, if it is a valid one.
+    fn load(&mut self, commands: &mut GameCommands) {
+        match Fen::read(&self.fen().to_string(), Variant::Standard) {
+            Ok(fen) => {
+                commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+                self.error = None;
+            }
+            Err(_) => self.error = Some("position-editor-invalid"),
+        }
+    }
+}
+
+/// Returns the text of a square or palette button showing the given piece.
+fn piece_text(content: SquareContent) -> String {
+    content.map_or(" ".to_string(), |(color, piece_type)| {
+        piece_symbol(&color, &piece_type).to_string()
+    })
+}
+
+/// Shows the position editor, collapsed until it is opened.
+pub(super) fn position_editor_window(
+    mut contexts: EguiContexts,
+    mut editor: ResMut<PositionEditor>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+) {
+    egui::Window::new(locale.get("position-editor"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for color in PieceColor::iter() {
+                ui.horizontal(|ui| {
+                    for piece_type in PIECES {
+                        let piece = Some((color, piece_type));
+                        ui.selectable_value(&mut editor.brush, piece, piece_text(piece))
+                            .on_hover_text(locale.piece(&piece_type));
+                    }
+                });
+            }
+            ui.selectable_value(&mut editor.brush, None, locale.get("position-editor-erase"));
+            egui::Grid::new("position_editor_squares")
+                .spacing(egui::Vec2::ZERO)
+                .show(ui, |ui| {
+                    let size = BoardSize::default();
+                    for rank in 0..size.ranks() {
+                        for file in 0..size.files() {
+                            let position = BoardPosition::new(rank, file);
+                            let square =
+                                egui::Button::new(piece_text(editor.placement[rank][file]))
+                                    .min_size(egui::Vec2::splat(24.0));
+                            if ui
+                                .add(square)
+                                .on_hover_text(position.to_algebraic())
+                                .clicked()
+                            {
+                                editor.paint(position);
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.label(locale.get("to-move"));
+                for color in PieceColor::iter() {
+                    let selected = editor.active_color == color;
+                    if ui
+                        .selectable_label(selected, locale.color(&color))
+                        .clicked()
+                        && !selected
+                    {
+                        editor.edit(Edit::SwitchTurn);
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        !editor.done.is_empty(),
+                        egui::Button::new(locale.get("position-editor-undo")),
+                    )
+                    .clicked()
+                {
+                    editor.undo();
+                }
+                if ui
+                    .add_enabled(
+                        !editor.undone.is_empty(),
+                        egui::Button::new(locale.get("position-editor-redo")),
+                    )
+                    .clicked()
+                {
+                    editor.redo();
+                }
+            });
+            ui.horizontal(|ui| {
+                if ui.button(locale.get("clear")).clicked() {
+                    editor.replace([[None; MAX_FILES]; BOARD_SIZE]);
+                }
+                if ui.button(locale.get("position-editor-mirror")).clicked() {
+                    editor.edit(Edit::Mirror);
+                }
+                if ui
+                    .button(locale.get("position-editor-swap-colors"))
+                    .clicked()
+                {
+                    editor.edit(Edit::SwapColors);
+                }
+                if ui
+                    .button(locale.get("position-editor-from-board"))
+                    .clicked()
+                {
+                    editor.replace(*board.to_fen().piece_placement());
+                }
+            });
+            if ui.button(locale.get("load")).clicked() {
+                editor.load(&mut commands);
+            }
+            if let Some(error) = editor.error {
+                ui.label(locale.get(error));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_history() {
+        let mut editor = PositionEditor::default();
+        let e4 = BoardPosition::from_algebraic("e4").unwrap();
+        editor.paint(e4);
+        // Painting a square with the piece already on it is not an edit
+        editor.paint(e4);
+        assert_eq!(editor.done.len(), 1);
+        editor.replace([[None; MAX_FILES]; BOARD_SIZE]);
+        editor.edit(Edit::SwitchTurn);
+        assert_eq!(editor.fen().to_string(), "8/8/8/8/8/8/8/8 b - - 0 1");
+
+        editor.undo();
+        editor.undo();
+        assert_eq!(
+            editor.fen().to_string(),
+            "rnbqkbnr/pppppppp/8/8/4Q3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+        editor.redo();
+        assert_eq!(editor.fen().to_string(), "8/8/8/8/8/8/8/8 w - - 0 1");
+        // A new edit drops the edits undone
+        editor.edit(Edit::Mirror);
+        editor.redo();
+        assert!(editor.undone.is_empty());
+        assert_eq!(editor.fen().to_string(), "8/8/8/8/8/8/8/8 w - - 0 1");
+    }
+
+    #[test]
+    fn test_mirror_and_swap_colors() {
+        let mut editor = PositionEditor::default();
+        editor.replace(*Fen::from_string("4k3/8/8/8/8/8/1P6/K6R w - - 0 1").piece_placement());
+        editor.edit(Edit::Mirror);
+        assert_eq!(editor.fen().to_string(), "3k4/8/8/8/8/8/6P1/R6K w - - 0 1");
+        editor.undo();
+        editor.edit(Edit::SwapColors);
+        assert_eq!(editor.fen().to_string(), "k6r/1p6/8/8/8/8/8/4K3 b - - 0 1");
+
+        // A position without a king of each color is not loaded
+        let mut commands = GameCommands::default();
+        editor.paint(BoardPosition::from_algebraic("a8").unwrap());
+        editor.load(&mut commands);
+        assert_eq!(editor.error, Some("position-editor-invalid"));
+        editor.undo();
+        editor.load(&mut commands);
+        assert_eq!(editor.error, None);
+    }
+}
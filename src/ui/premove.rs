@@ -0,0 +1,224 @@
+//! Premoves, moves the player queues while the engine is thinking, which are made as soon as it is
+//! their turn again.
+//!
+//! Several premoves can be queued as a chain, each moving a piece from where the moves before it
+//! leave the pieces, so one piece can be premoved several times. Each is made on one of the
+//! player's turns while it is still legal, and the first which is not cancels the rest. The
+//! pieces are drawn where the chain leaves them, on tinted squares, and a right click cancels the
+//! chain. Pawns premoved to the last rank promote to a queen.
+
+use bevy::prelude::{
+    Changed, DetectChanges, EventReader, Or, Query, Res, ResMut, Resource, Transform, Visibility,
+    With,
+};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{
+    BoardPosition, ChessBoard, ChessBoardConfig, GameCommand, GameCommands, PieceColor,
+    ResetBoardEvent, Variant,
+};
+use crate::engine::Engine;
+
+use super::board::BoardProperties;
+use super::piece::{Dragging, PieceTag};
+
+/// The queued premoves, from the first to be made.
+#[derive(Resource, Debug, Default)]
+pub(super) struct Premoves {
+    chain: Vec<(BoardPosition, BoardPosition)>,
+    /// The color of the player who can premove, while it is the engine's turn against them.
+    premover: Option<PieceColor>,
+}
+
+impl Premoves {
+    /// Returns whether the player of the given color can queue premoves.
+    pub(super) fn can_premove(&self, color: PieceColor) -> bool {
+        self.premover == Some(color)
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.chain.is_empty()
+    }
+
+    /// Returns whether the given square is the start or end of a premove.
+    pub(super) fn is_queued(&self, position: &BoardPosition) -> bool {
+        self.chain
+            .iter()
+            .any(|(from, to)| from == position || to == position)
+    }
+
+    /// Returns the given board with the premoves made, without the opponent moving in between.
+    fn board_after(&self, board: &ChessBoard) -> ChessBoard {
+        let mut after = board.clone();
+        for (from, to) in &self.chain {
+            after.apply_move(&Move::from_board(*from, *to, &after));
+        }
+        after
+    }
+
+    /// Returns the square the chain leaves the piece on the given square on, or None if a premove
+    /// captures it.
+    pub(super) fn square_of(&self, position: BoardPosition) -> Option<BoardPosition> {
+        let mut square = position;
+        for (from, to) in &self.chain {
+            if *from == square {
+                square = *to;
+            } else if *to == square {
+                return None;
+            }
+        }
+        Some(square)
+    }
+
+    /// Queues the premove from one square to the other on the given board, returning whether the
+    /// premover's piece there after the chain could make it if the opponent passed.
+    pub(super) fn queue(
+        &mut self,
+        from: BoardPosition,
+        to: BoardPosition,
+        board: &ChessBoard,
+    ) -> bool {
+        let Some(color) = self.premover else {
+            return false;
+        };
+        let after = self.board_after(board);
+        if from == to || after.get_piece_color(&from) != Some(color) {
+            return false;
+        }
+        let premove = Move::from_board(from, to, &after);
+        if !after.valid_move(&premove, &Some(color), &false) {
+            return false;
+        }
+        self.chain.push((from, to));
+        true
+    }
+
+    pub(super) fn cancel(&mut self) {
+        self.chain.clear();
+    }
+
+    /// Takes the first premove off the chain, returning it if it can be made on the given board
+    /// and otherwise cancelling the chain.
+    fn next(&mut self, board: &ChessBoard, config: &ChessBoardConfig) -> Option<Move> {
+        if self.chain.is_empty() {
+            return None;
+        }
+        let (from, to) = self.chain.remove(0);
+        let premove = Move::from_board(from, to, board);
+        if board.get_piece_type(&from).is_some() && config.allows(board, &premove) {
+            Some(premove)
+        } else {
+            self.chain.clear();
+            None
+        }
+    }
+}
+
+/// Returns the color of the player who can premove on the given board, which is the engine's
+/// opponent while the engine is to move. The duck cannot be premoved, so neither can duck chess.
+fn premover(board: &ChessBoard, engine: &Engine) -> Option<PieceColor> {
+    let engine_color = (*engine.color())?;
+    (*board.active_color() == Some(engine_color) && *board.variant() != Variant::Duck)
+        .then(|| engine_color.opposite())
+}
+
+/// Makes the next premove once the engine has moved, and cancels the chain when the board is
+/// reset or the engine stops playing.
+pub(super) fn play_premoves(
+    mut premoves: ResMut<Premoves>,
+    mut reset_events: EventReader<ResetBoardEvent>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+    config: Res<ChessBoardConfig>,
+    mut commands: ResMut<GameCommands>,
+) {
+    let premover = premover(&board, &engine);
+    if premover != premoves.premover {
+        premoves.premover = premover;
+    }
+    if reset_events.iter().last().is_some() || engine.color().is_none() {
+        if !premoves.is_empty() {
+            premoves.cancel();
+        }
+        return;
+    }
+    // The engine's move has just been made once the board changes to the player's turn
+    let players_turn = engine
+        .color()
+        .is_some_and(|color| *board.active_color() == Some(color.opposite()));
+    if !board.is_changed() || !players_turn || premoves.is_empty() {
+        return;
+    }
+    if let Some(premove) = premoves.next(&board, &config) {
+        commands.push(GameCommand::MakeMove(premove));
+    }
+}
+
+/// Draws the pieces where the premoves leave them, hiding those they capture.
+pub(super) fn show_premoves(
+    premoves: Res<Premoves>,
+    changed_pieces: Query<(), Or<(Changed<Dragging>, Changed<BoardPosition>)>>,
+    mut pieces: Query<(&BoardPosition, &Dragging, &mut Transform, &mut Visibility), With<PieceTag>>,
+    properties: Res<BoardProperties>,
+) {
+    if !premoves.is_changed() && changed_pieces.is_empty() && !properties.is_changed() {
+        return;
+    }
+    for (position, dragging, mut transform, mut visibility) in pieces.iter_mut() {
+        if dragging.get() {
+            continue;
+        }
+        match premoves.square_of(*position) {
+            Some(square) => {
+                let (x, y) = properties.position_to_transform(&square);
+                transform.translation.x = x;
+                transform.translation.y = y;
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_premoves() {
+        let square = |name| BoardPosition::from_algebraic(name).unwrap();
+        let mut board = ChessBoard::with_position(&Fen::default());
+        board.apply_move(&Move::from_algebraic("e4", &board).unwrap());
+        let mut engine = Engine::default();
+        engine.set_color(Some(PieceColor::Black));
+        let mut premoves = Premoves {
+            premover: premover(&board, &engine),
+            ..Default::default()
+        };
+        assert!(premoves.can_premove(PieceColor::White));
+
+        // The knight is premoved twice, and cannot move from where it was
+        assert!(premoves.queue(square("g1"), square("f3"), &board));
+        assert!(premoves.queue(square("f3"), square("g5"), &board));
+        assert!(!premoves.queue(square("g1"), square("h3"), &board));
+        assert!(!premoves.queue(square("e7"), square("e5"), &board));
+        assert_eq!(premoves.square_of(square("g1")), Some(square("g5")));
+        assert!(premoves.is_queued(&square("f3")));
+
+        let config = ChessBoardConfig::default();
+        board.apply_move(&Move::from_algebraic("e5", &board).unwrap());
+        let knight = premoves.next(&board, &config).unwrap();
+        assert_eq!(knight.as_algebraic(), "Nf3");
+        board.apply_move(&knight);
+
+        // A premove which is no longer legal cancels the chain
+        premoves.cancel();
+        assert!(premoves.queue(square("d2"), square("d3"), &board));
+        assert!(premoves.queue(square("b1"), square("c3"), &board));
+        board.apply_move(&Move::from_algebraic("Bb4", &board).unwrap());
+        assert!(premoves.next(&board, &config).is_none());
+        assert!(premoves.is_empty());
+    }
+}
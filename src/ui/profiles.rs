@@ -0,0 +1,111 @@
+//! The players window, where the [profile](crate::profile) of whoever plays next is chosen, with
+//! their rating and record, and where players are added and removed.
+
+use bevy::prelude::{EventWriter, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::GameCommand;
+use crate::database::{GamesDatabase, PlayerResult};
+use crate::notification::{Argument, Notification};
+use crate::profile::{Profiles, MAX_NAME_LENGTH};
+
+use super::engine::GameSetup;
+use super::locale::Localisation;
+
+/// The state of the players window.
+#[derive(Resource, Debug, Default)]
+pub(super) struct ProfilesWindowState {
+    /// The name typed for a new player.
+    new_name: String,
+    /// The locale key of why the last player could not be added.
+    error: Option<&'static str>,
+}
+
+/// Shows the players window, collapsed until it is opened. Choosing a player sets the engine and
+/// clock to the settings they last used.
+pub(super) fn profiles_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<ProfilesWindowState>,
+    mut profiles: ResMut<Profiles>,
+    database: Res<GamesDatabase>,
+    locale: Res<Localisation>,
+    setup: GameSetup,
+    mut notifications: EventWriter<Notification>,
+) {
+    let GameSetup {
+        mut commands,
+        mut engine,
+        mut clock,
+    } = setup;
+    let mut changed = false;
+    egui::Window::new(locale.get("players"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let mut selected = profiles.active_index();
+            egui::ComboBox::from_label(locale.get("player"))
+                .selected_text(profiles.active().name())
+                .show_ui(ui, |ui| {
+                    for (index, player) in profiles.players().iter().enumerate() {
+                        ui.selectable_value(&mut selected, index, player.name());
+                    }
+                });
+            if selected != profiles.active_index() {
+                profiles.select(selected);
+                changed = true;
+            }
+            let player = profiles.active();
+            ui.label(locale.format("profile-rating", &[("rating", &player.rating())]));
+            let count = |result| database.count(player.name(), result);
+            ui.label(locale.format(
+                "profile-record",
+                &[
+                    ("won", &count(PlayerResult::Won)),
+                    ("lost", &count(PlayerResult::Lost)),
+                    ("drawn", &count(PlayerResult::Drawn)),
+                ],
+            ));
+            if ui
+                .button(locale.format("new-game-as", &[("player", &player.name())]))
+                .clicked()
+            {
+                commands.push(GameCommand::Reset);
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut state.new_name)
+                        .char_limit(MAX_NAME_LENGTH)
+                        .hint_text(locale.get("player-name")),
+                );
+                if ui.button(locale.get("add-player")).clicked() {
+                    match profiles.add(&state.new_name) {
+                        Ok(()) => {
+                            state.new_name.clear();
+                            state.error = None;
+                            changed = true;
+                        }
+                        Err(error) => state.error = Some(error),
+                    }
+                }
+            });
+            if let Some(error) = state.error {
+                ui.label(locale.get(error));
+            }
+            let removable = profiles.players().len() > 1;
+            if ui
+                .add_enabled(removable, egui::Button::new(locale.get("remove-player")))
+                .clicked()
+            {
+                profiles.remove_active();
+                changed = true;
+            }
+        });
+    if changed {
+        profiles.active().apply_preferences(&mut engine, &mut clock);
+        if let Err(error) = profiles.save() {
+            notifications.send(
+                Notification::error("profile-save-failed").with_arg("error", Argument::Text(error)),
+            );
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! The choice of piece when a pawn is moved to the last rank.
+//!
+//! A pawn dropped on the last rank promotes to a queen straight away. Dropping it just beyond the
+//! edge of the board past that square, or holding Alt as it is dropped, asks which piece it
+//! promotes to instead.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, ChessBoardConfig, GameCommand, GameCommands, PieceType};
+
+use super::locale::Localisation;
+use super::piece::MoveConfirmation;
+use super::recording::InputRecorder;
+
+/// The promotion awaiting the player's choice of piece.
+#[derive(Resource, Debug, Default)]
+pub(super) struct PromotionChoice {
+    pending: Option<Move>,
+}
+
+impl PromotionChoice {
+    /// Returns whether the given move can be made straight away, as a promotion to a queen can be
+    /// unless the player asks to choose, or otherwise holds it back until the player chooses the
+    /// piece the pawn promotes to.
+    pub(super) fn check(&mut self, piece_move: Move, choose: bool) -> bool {
+        if piece_move.promotion().is_none() || !choose {
+            return true;
+        }
+        self.pending = Some(piece_move);
+        false
+    }
+
+    /// Returns the promotion awaiting the choice as a promotion to the given piece, or cancels it
+    /// if the piece is None.
+    pub(super) fn choose(&mut self, piece_type: Option<PieceType>) -> Option<Move> {
+        let piece_move = self.pending.take()?;
+        piece_type.map(|piece_type| piece_move.with_promotion(piece_type))
+    }
+}
+
+/// Asks the player which piece to promote to, making the move once they choose. The move is
+/// dropped if the position changes before they do.
+pub(super) fn promotion_window(
+    mut contexts: EguiContexts,
+    confirmation: MoveConfirmation,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+    config: Res<ChessBoardConfig>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    let MoveConfirmation {
+        mut blunder_check,
+        promotion_choice: mut choice,
+    } = confirmation;
+    let Some(piece_move) = choice.pending else {
+        return;
+    };
+    if !config.allows(&board, &piece_move) {
+        choice.pending = None;
+        return;
+    }
+    egui::Window::new(locale.get("promote-to"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                let mut chosen = None;
+                for piece_type in board.promotions() {
+                    let promotion = piece_move.with_promotion(*piece_type);
+                    if ui
+                        .button(locale.san(&promotion.as_algebraic()))
+                        .on_hover_text(locale.piece(piece_type))
+                        .clicked()
+                    {
+                        chosen = Some(Some(*piece_type));
+                    }
+                }
+                if ui.button(locale.get("cancel")).clicked() {
+                    chosen = Some(None);
+                }
+                let Some(piece_type) = chosen else {
+                    return;
+                };
+                recorder.record_promotion(piece_type);
+                if let Some(promotion) = choice.choose(piece_type) {
+                    if blunder_check.check(promotion, &board) {
+                        commands.push(GameCommand::MakeMove(promotion));
+                    }
+                }
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_promotion_choice() {
+        let board = ChessBoard::with_position(&Fen::from_string("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"));
+        let mut choice = PromotionChoice::default();
+        assert!(choice.check(Move::from_algebraic("Kd2", &board).unwrap(), true));
+        assert_eq!(choice.pending, None);
+
+        // A promotion is made as a queen unless the player asks to choose
+        let promotion = Move::from_algebraic("b8=Q", &board).unwrap();
+        assert!(choice.check(promotion, false));
+        assert_eq!(choice.pending, None);
+        assert!(!choice.check(promotion, true));
+        assert_eq!(choice.pending, Some(promotion));
+        let knight = choice.choose(Some(PieceType::Knight)).unwrap();
+        assert_eq!(knight.promotion(), Some(PieceType::Knight));
+        assert_eq!(choice.choose(Some(PieceType::Queen)), None);
+    }
+}
@@ -0,0 +1,542 @@
+//! Puzzle rush, in which the player solves as many [puzzles](crate::puzzle) as they can before
+//! the time runs out. The puzzles get harder as the rush goes on, and a wrong answer ends the
+//! streak of puzzles solved in a row and moves on to the next puzzle.
+//!
+//! The same window runs untimed [practice](crate::practice), which works through generated
+//! checkmate and stalemate puzzles without counting towards the record, and serves the puzzles of
+//! the player's [own blunders](crate::blunder_puzzles) which are due, rescheduling each once it is
+//! answered. The best score and streak
+//! of timed rushes are saved to [RECORD_PATH].
+//!
+//! The puzzles of the rush or practice can be exported as a [puzzle set](crate::puzzle_set), and
+//! an imported set is worked through like practice.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{EventWriter, Res, ResMut, Resource};
+use bevy::time::Time;
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::blunder_puzzles::BlunderPuzzles;
+use crate::chess_board::{ChessBoard, ResetBoardEvent};
+use crate::database::days_today;
+use crate::notification::{Argument, Notification};
+use crate::practice::generate;
+use crate::profile::Profiles;
+use crate::puzzle::{puzzles, Puzzle};
+use crate::puzzle_set::{Format, PuzzleSet, PUZZLE_SET_PATH};
+use crate::storage;
+
+use super::clock::format_duration;
+use super::engine::GameSetup;
+use super::locale::Localisation;
+
+/// The file the best results are saved to.
+const RECORD_PATH: &str = "puzzle_rush.ron";
+
+/// The length of a rush.
+const RUSH_TIME: Duration = Duration::from_secs(5 * 60);
+
+/// The best results of any rush.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Record {
+    score: usize,
+    streak: usize,
+}
+
+/// A rush in progress or finished.
+#[derive(Debug, Clone)]
+struct Rush {
+    /// The index of the current puzzle.
+    puzzle: usize,
+    /// Whether the current puzzle has been loaded onto the board.
+    started: bool,
+    remaining: Duration,
+    solved: usize,
+    attempted: usize,
+    streak: usize,
+    best_streak: usize,
+    /// Whether the time runs down, which it does not during practice.
+    timed: bool,
+    /// The solution the player missed in the last puzzle, if they answered it wrongly.
+    missed: Option<String>,
+    finished: bool,
+}
+
+impl Default for Rush {
+    fn default() -> Self {
+        Rush {
+            puzzle: 0,
+            started: false,
+            remaining: RUSH_TIME,
+            solved: 0,
+            attempted: 0,
+            streak: 0,
+            best_streak: 0,
+            timed: true,
+            missed: None,
+            finished: false,
+        }
+    }
+}
+
+impl Rush {
+    /// Returns an untimed rush through the practice puzzles.
+    fn practice() -> Self {
+        Rush {
+            timed: false,
+            ..Default::default()
+        }
+    }
+
+    fn puzzle<'a>(&self, puzzles: &'a [Puzzle]) -> Option<&'a Puzzle> {
+        puzzles.get(self.puzzle).filter(|_| !self.finished)
+    }
+
+    /// Runs down the time and checks the player's answer to the current puzzle, returning
+    /// whether the next puzzle should be loaded.
+    fn update(&mut self, board: &ChessBoard, puzzles: &[Puzzle], delta: Duration) -> bool {
+        let Some(puzzle) = self.puzzle(puzzles) else {
+            return false;
+        };
+        if self.timed {
+            self.remaining = self.remaining.saturating_sub(delta);
+        }
+        if self.remaining.is_zero() {
+            self.finished = true;
+            return false;
+        }
+        if !self.started {
+            self.started = board.past_moves().is_empty()
+                && board.to_fen().to_string() == puzzle.fen().to_string();
+            return false;
+        }
+        let [piece_move] = &board.past_moves()[..] else {
+            return false;
+        };
+        self.attempted += 1;
+        if puzzle.is_solved_by(piece_move) {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+            self.missed = None;
+        } else {
+            self.streak = 0;
+            self.missed = puzzle.solution_line().map(|line| line.join(" "));
+        }
+        self.puzzle += 1;
+        self.started = false;
+        self.finished = self.puzzle >= puzzles.len();
+        !self.finished
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct PuzzleRush {
+    rush: Option<Rush>,
+    /// The puzzles of the practice in progress.
+    practice: Vec<Puzzle>,
+    /// The index of the blunder of each puzzle of the practice, if it is of the player's blunders.
+    blunders: Vec<usize>,
+    record: Record,
+    /// Whether the last rush beat the record, which is then shown on the results.
+    new_record: bool,
+    /// The path of the puzzle set to import or export, [PUZZLE_SET_PATH] if it is left empty.
+    set_path: String,
+    /// The name of the puzzle set being worked through.
+    set_name: Option<String>,
+}
+
+impl PuzzleRush {
+    /// Returns the puzzles of the current rush.
+    fn puzzles(&self) -> &[Puzzle] {
+        match &self.rush {
+            Some(rush) if !rush.timed => &self.practice,
+            _ => puzzles(),
+        }
+    }
+
+    /// Brings the record up to date with the results of the finished timed rush, returning
+    /// whether it changed.
+    fn update_record(&mut self) -> bool {
+        let Some(rush) = self
+            .rush
+            .as_ref()
+            .filter(|rush| rush.finished && rush.timed)
+        else {
+            return false;
+        };
+        let record = Record {
+            score: self.record.score.max(rush.solved),
+            streak: self.record.streak.max(rush.best_streak),
+        };
+        self.new_record = rush.solved > self.record.score;
+        let changed = record != self.record;
+        self.record = record;
+        changed
+    }
+}
+
+/// Loads the best results of earlier rushes.
+pub(super) fn setup(mut puzzle_rush: ResMut<PuzzleRush>) {
+    if let Some(record) = storage::read(RECORD_PATH).and_then(|text| ron::from_str(&text).ok()) {
+        puzzle_rush.record = record;
+    }
+}
+
+/// Reads the puzzle set at the given path.
+fn import_set(path: &str) -> Result<PuzzleSet, String> {
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    PuzzleSet::read(&text, Format::of_path(path)).map_err(|error| error.to_string())
+}
+
+/// Writes the given puzzles to the given path as a set named after the file.
+fn export_set(path: &str, puzzles: &[Puzzle]) -> Result<(), String> {
+    let name = std::path::Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let set = PuzzleSet {
+        name,
+        puzzles: puzzles.to_vec(),
+    };
+    std::fs::write(path, set.write(Format::of_path(path))).map_err(|error| error.to_string())
+}
+
+/// Resets the board to the given puzzle, for the player alone.
+fn load_puzzle(puzzle: &Puzzle, setup: &mut GameSetup) {
+    setup.load_alone(ResetBoardEvent::new(puzzle.fen().clone()));
+}
+
+/// The puzzle rush, the time it is counted down by and the redraws which keep it counting down.
+#[derive(SystemParam)]
+pub(super) struct TimedRush<'w> {
+    puzzle_rush: ResMut<'w, PuzzleRush>,
+    time: Res<'w, Time>,
+    redraw_events: EventWriter<'w, RequestRedraw>,
+}
+
+/// The puzzles made from the players' blunders and the profiles of the players they are due for.
+#[derive(SystemParam)]
+pub(super) struct BlunderReview<'w> {
+    blunder_puzzles: ResMut<'w, BlunderPuzzles>,
+    profiles: Res<'w, Profiles>,
+}
+
+/// Shows the puzzle rush, collapsed until it is opened, with the time left and the streak during
+/// a rush and the results once it is over.
+pub(super) fn puzzle_rush_window(
+    mut contexts: EguiContexts,
+    timed_rush: TimedRush,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut setup: GameSetup,
+    blunder_review: BlunderReview,
+    mut notifications: EventWriter<Notification>,
+) {
+    let TimedRush {
+        mut puzzle_rush,
+        time,
+        mut redraw_events,
+    } = timed_rush;
+    let BlunderReview {
+        mut blunder_puzzles,
+        profiles,
+    } = blunder_review;
+    let mut finished_now = false;
+    let PuzzleRush {
+        rush,
+        practice,
+        blunders,
+        ..
+    } = &mut *puzzle_rush;
+    let rush_puzzles = match rush {
+        Some(rush) if !rush.timed => practice,
+        _ => puzzles(),
+    };
+    if let Some(rush) = rush.as_mut() {
+        let was_finished = rush.finished;
+        let (attempted, solved) = (rush.attempted, rush.solved);
+        let next = rush.update(&board, rush_puzzles, time.delta());
+        if rush.attempted > attempted {
+            if let Some(index) = blunders.get(rush.puzzle - 1) {
+                blunder_puzzles.answer(*index, rush.solved > solved, days_today());
+            }
+        }
+        if next {
+            load_puzzle(&rush_puzzles[rush.puzzle], &mut setup);
+        }
+        if !rush.finished {
+            // The time is only counted down while the app updates
+            redraw_events.send(RequestRedraw);
+        }
+        finished_now = rush.finished && !was_finished;
+    }
+    let current = puzzle_rush.rush.as_ref().and_then(|rush| {
+        let puzzle = rush.puzzle(puzzle_rush.puzzles())?;
+        let played = puzzle_rush
+            .blunders
+            .get(rush.puzzle)
+            .and_then(|index| blunder_puzzles.get(*index))
+            .map(|blunder| blunder.played().to_string());
+        Some((puzzle.rating(), puzzle.goal(), played))
+    });
+    let due = blunder_puzzles.due(profiles.active().name(), days_today());
+    egui::Window::new(locale.get("puzzle-rush"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let running = puzzle_rush.rush.as_ref().is_some_and(|rush| !rush.finished);
+            if running {
+                if ui.button(locale.get("stop-rush")).clicked() {
+                    if let Some(rush) = puzzle_rush.rush.as_mut() {
+                        rush.finished = true;
+                        finished_now = true;
+                    }
+                }
+            } else {
+                ui.horizontal(|ui| {
+                    if ui.button(locale.get("start-rush")).clicked() {
+                        let rush = Rush::default();
+                        if let Some(puzzle) = rush.puzzle(puzzles()) {
+                            load_puzzle(puzzle, &mut setup);
+                        }
+                        puzzle_rush.rush = Some(rush);
+                        puzzle_rush.new_record = false;
+                        puzzle_rush.set_name = None;
+                    }
+                    if ui.button(locale.get("start-practice")).clicked() {
+                        let seed = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |time| time.as_nanos() as u64);
+                        puzzle_rush.practice = generate(seed);
+                        puzzle_rush.blunders.clear();
+                        let rush = Rush::practice();
+                        if let Some(puzzle) = rush.puzzle(&puzzle_rush.practice) {
+                            load_puzzle(puzzle, &mut setup);
+                        }
+                        puzzle_rush.rush = Some(rush);
+                        puzzle_rush.new_record = false;
+                        puzzle_rush.set_name = None;
+                    }
+                    if ui
+                        .add_enabled(
+                            !due.is_empty(),
+                            egui::Button::new(locale.get("start-blunders")),
+                        )
+                        .on_hover_text(locale.format("start-blunders-hint", &[("due", &due.len())]))
+                        .on_disabled_hover_text(locale.get("no-blunders-due"))
+                        .clicked()
+                    {
+                        puzzle_rush.blunders.clear();
+                        puzzle_rush.practice.clear();
+                        for index in due {
+                            if let Some(puzzle) = blunder_puzzles
+                                .get(index)
+                                .and_then(|blunder| blunder.puzzle())
+                            {
+                                puzzle_rush.blunders.push(index);
+                                puzzle_rush.practice.push(puzzle);
+                            }
+                        }
+                        let rush = Rush::practice();
+                        if let Some(puzzle) = rush.puzzle(&puzzle_rush.practice) {
+                            load_puzzle(puzzle, &mut setup);
+                        }
+                        puzzle_rush.rush = Some(rush);
+                        puzzle_rush.new_record = false;
+                        puzzle_rush.set_name = None;
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label(locale.get("puzzle-set-path"));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut puzzle_rush.set_path)
+                            .hint_text(PUZZLE_SET_PATH),
+                    );
+                });
+                let path = match puzzle_rush.set_path.trim() {
+                    "" => PUZZLE_SET_PATH.to_string(),
+                    path => path.to_string(),
+                };
+                ui.horizontal(|ui| {
+                    if ui.button(locale.get("import-puzzle-set")).clicked() {
+                        match import_set(&path) {
+                            Ok(set) => {
+                                notifications.send(
+                                    Notification::info("puzzle-set-imported")
+                                        .with_arg("name", Argument::Text(set.name.clone()))
+                                        .with_arg(
+                                            "count",
+                                            Argument::Text(set.puzzles.len().to_string()),
+                                        ),
+                                );
+                                puzzle_rush.practice = set.puzzles;
+                                puzzle_rush.blunders.clear();
+                                let rush = Rush::practice();
+                                if let Some(puzzle) = rush.puzzle(&puzzle_rush.practice) {
+                                    load_puzzle(puzzle, &mut setup);
+                                }
+                                puzzle_rush.rush = Some(rush);
+                                puzzle_rush.new_record = false;
+                                puzzle_rush.set_name = Some(set.name);
+                            }
+                            Err(error) => notifications.send(
+                                Notification::error("puzzle-set-import-failed")
+                                    .with_arg("error", Argument::Text(error)),
+                            ),
+                        }
+                    }
+                    if ui
+                        .button(locale.get("export-puzzle-set"))
+                        .on_hover_text(locale.get("export-puzzle-set-hint"))
+                        .clicked()
+                    {
+                        let notification = match export_set(&path, puzzle_rush.puzzles()) {
+                            Ok(()) => Notification::info("puzzle-set-exported")
+                                .with_arg("path", Argument::Text(path.clone())),
+                            Err(error) => Notification::error("puzzle-set-export-failed")
+                                .with_arg("error", Argument::Text(error)),
+                        };
+                        notifications.send(notification);
+                    }
+                });
+            }
+            if let Some(name) = &puzzle_rush.set_name {
+                ui.label(locale.format("playing-puzzle-set", &[("name", name)]));
+            }
+            if let Some(rush) = &puzzle_rush.rush {
+                if rush.finished {
+                    ui.heading(locale.get("rush-over"));
+                } else {
+                    if rush.timed {
+                        ui.heading(format_duration(&rush.remaining));
+                    }
+                    match current {
+                        Some((_, goal, Some(played))) => {
+                            ui.label(locale.format(
+                                "blunder-puzzle",
+                                &[
+                                    ("number", &(rush.puzzle + 1)),
+                                    ("move", &locale.san(&played)),
+                                    ("goal", &locale.get(goal.key())),
+                                ],
+                            ));
+                        }
+                        Some((rating, goal, None)) => {
+                            ui.label(locale.format(
+                                "rush-puzzle",
+                                &[
+                                    ("number", &(rush.puzzle + 1)),
+                                    ("rating", &rating),
+                                    ("goal", &locale.get(goal.key())),
+                                ],
+                            ));
+                        }
+                        None => {}
+                    }
+                    ui.label(locale.format("rush-streak", &[("streak", &rush.streak)]));
+                }
+                if let Some(missed) = &rush.missed {
+                    ui.label(locale.format("rush-missed", &[("move", &locale.san(missed))]));
+                }
+                ui.label(locale.format(
+                    "rush-score",
+                    &[("solved", &rush.solved), ("attempted", &rush.attempted)],
+                ));
+                if rush.finished {
+                    ui.label(locale.format("rush-best-streak", &[("streak", &rush.best_streak)]));
+                    if puzzle_rush.new_record {
+                        ui.label(locale.get("rush-new-record"));
+                    }
+                }
+            }
+            ui.label(locale.format(
+                "rush-record",
+                &[
+                    ("score", &puzzle_rush.record.score),
+                    ("streak", &puzzle_rush.record.streak),
+                ],
+            ));
+        });
+    if finished_now && puzzle_rush.update_record() {
+        if let Ok(text) = ron::to_string(&puzzle_rush.record) {
+            // A failed save only loses the record, so it is not reported
+            storage::write(RECORD_PATH, &text).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+
+    use super::*;
+
+    #[test]
+    fn test_rush() {
+        let puzzles = puzzles();
+        let mut rush = Rush::default();
+        let second = Duration::from_secs(1);
+        let mut board = ChessBoard::with_position(puzzles[0].fen());
+        assert!(!rush.update(&board, puzzles, second));
+        assert!(rush.started);
+
+        // Solving the puzzle moves on to the next one
+        let solution = puzzles[0].solution().unwrap();
+        board.apply_move(&solution);
+        assert!(rush.update(&board, puzzles, second));
+        assert_eq!((rush.puzzle, rush.solved, rush.streak), (1, 1, 1));
+
+        // A wrong answer ends the streak
+        let mut board = ChessBoard::with_position(puzzles[1].fen());
+        rush.update(&board, puzzles, second);
+        let wrong = board
+            .legal_moves_iter()
+            .find(|piece_move: &Move| !puzzles[1].is_solved_by(piece_move))
+            .unwrap();
+        board.apply_move(&wrong);
+        assert!(rush.update(&board, puzzles, second));
+        assert_eq!((rush.solved, rush.attempted, rush.streak), (1, 2, 0));
+        assert_eq!(rush.best_streak, 1);
+        assert_eq!(
+            rush.missed,
+            puzzles[1].solution().map(|mate| mate.as_algebraic())
+        );
+
+        // The rush ends when the time runs out
+        assert!(!rush.update(&board, puzzles, RUSH_TIME));
+        assert!(rush.finished);
+        let mut puzzle_rush = PuzzleRush {
+            rush: Some(rush),
+            practice: Vec::new(),
+            blunders: Vec::new(),
+            record: Record {
+                score: 3,
+                streak: 0,
+            },
+            new_record: false,
+            set_path: String::new(),
+            set_name: None,
+        };
+        assert!(puzzle_rush.update_record());
+        assert_eq!(
+            puzzle_rush.record,
+            Record {
+                score: 3,
+                streak: 1
+            }
+        );
+        assert!(!puzzle_rush.new_record);
+
+        // Practice has no time limit and leaves the record alone
+        let mut practice = Rush::practice();
+        practice.update(&board, puzzles, RUSH_TIME);
+        assert!(!practice.finished);
+        practice.finished = true;
+        puzzle_rush.rush = Some(practice);
+        assert!(!puzzle_rush.update_record());
+    }
+}
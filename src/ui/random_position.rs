@@ -0,0 +1,80 @@
+//! The random position window, which loads a balanced [random position](crate::start_position)
+//! to practise from, either from a new seed or from one shared by another player.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::{GameCommand, GameCommands, ResetBoardEvent};
+use crate::start_position::generate;
+
+use super::locale::Localisation;
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct RandomPositionState {
+    /// The seed typed to load a shared position.
+    seed_text: String,
+    /// The seed of the position last loaded.
+    seed: Option<u64>,
+    /// The locale key of why the last position could not be loaded.
+    error: Option<&'static str>,
+}
+
+impl RandomPositionState {
+    /// Loads the position of the given seed onto the board, keeping the engine and clock as they
+    /// are so the position can be played against the engine.
+    fn load(&mut self, seed: u64, commands: &mut GameCommands) {
+        match generate(seed) {
+            Some(fen) => {
+                commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+                self.seed = Some(seed);
+                self.seed_text = seed.to_string();
+                self.error = None;
+            }
+            None => self.error = Some("random-position-not-found"),
+        }
+    }
+}
+
+/// Shows the random position window, collapsed until it is opened.
+pub(super) fn random_position_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<RandomPositionState>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+) {
+    egui::Window::new(locale.get("random-position"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if ui.button(locale.get("new-random-position")).clicked() {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_nanos() as u64);
+                state.load(seed, &mut commands);
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut state.seed_text)
+                        .hint_text(locale.get("random-position-seed-hint")),
+                );
+                if ui.button(locale.get("load-random-position")).clicked() {
+                    match state.seed_text.trim().parse() {
+                        Ok(seed) => state.load(seed, &mut commands),
+                        Err(_) => state.error = Some("random-position-invalid-seed"),
+                    }
+                }
+            });
+            if let Some(seed) = state.seed {
+                ui.horizontal(|ui| {
+                    ui.label(locale.format("random-position-seed", &[("seed", &seed)]));
+                    if ui.button(locale.get("copy-seed")).clicked() {
+                        ui.output_mut(|output| output.copied_text = seed.to_string());
+                    }
+                });
+            }
+            if let Some(error) = state.error {
+                ui.label(locale.get(error));
+            }
+        });
+}
@@ -0,0 +1,351 @@
+//! Recognising a position from a screenshot of a 2D board diagram, to load it into a new analysis
+//! board. This is experimental and behind the `recognition` feature.
+//!
+//! The image must be a PNG of the board alone, although a uniform margin around it is trimmed.
+//! Each square is shrunk to a small grayscale cell and compared with each piece of the vector
+//! piece set drawn over the background of the square, so diagrams with similar piece shapes are
+//! recognised best. As a diagram does not show whose turn it is, the player to move is chosen by
+//! the player, and castling rights are given wherever a king and rook are on their starting
+//! squares.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use chesscomputer_core::castling_rights::CastlingRights;
+use image::RgbaImage;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::{
+    GameCommand, GameCommands, PieceColor, PieceType, ResetBoardEvent, BOARD_SIZE, MAX_FILES,
+};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::fen::{Fen, PiecePlacement};
+
+use super::locale::Localisation;
+use super::piece::rasterise_piece;
+
+/// The size in pixels each square is shrunk to before it is compared with the pieces.
+const CELL_SIZE: u32 = 24;
+
+/// How far a pixel may be from the color of the margin, on any channel, to be part of it.
+const MARGIN_TOLERANCE: u8 = 24;
+
+/// Returns the brightness of the given pixel, from 0 to 1.
+fn brightness(pixel: &[u8]) -> f32 {
+    (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32) / 255.
+}
+
+/// A piece of the vector piece set at the size of a cell.
+struct Template {
+    piece: (PieceColor, PieceType),
+    /// The coverage and brightness of each pixel of the piece.
+    pixels: Vec<(f32, f32)>,
+}
+
+impl Template {
+    fn new(color: PieceColor, piece_type: PieceType) -> Self {
+        let pixels = rasterise_piece(&color, &piece_type, CELL_SIZE)
+            .chunks(4)
+            .map(|pixel| (pixel[3] as f32 / 255., brightness(pixel)))
+            .collect();
+        Template {
+            piece: (color, piece_type),
+            pixels,
+        }
+    }
+
+    /// Returns how far the given cell is from this piece drawn over the given background.
+    fn distance(&self, cell: &[f32], background: f32) -> f32 {
+        cell.iter()
+            .zip(&self.pixels)
+            .map(|(value, (coverage, brightness))| {
+                let expected = coverage * brightness + (1. - coverage) * background;
+                (value - expected).powi(2)
+            })
+            .sum()
+    }
+}
+
+/// Returns the left, top, width and height of the board in the image, without any margin of the
+/// color of its top left corner, or None if the image is all margin.
+fn board_bounds(image: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let corner = image.get_pixel(0, 0).0;
+    let in_board = |x: u32, y: u32| {
+        let pixel = image.get_pixel(x, y).0;
+        pixel
+            .iter()
+            .zip(corner)
+            .any(|(channel, margin)| channel.abs_diff(margin) > MARGIN_TOLERANCE)
+    };
+    let columns: Vec<u32> = (0..image.width())
+        .filter(|&x| (0..image.height()).any(|y| in_board(x, y)))
+        .collect();
+    let rows: Vec<u32> = (0..image.height())
+        .filter(|&y| (0..image.width()).any(|x| in_board(x, y)))
+        .collect();
+    let (left, right) = (*columns.first()?, *columns.last()?);
+    let (top, bottom) = (*rows.first()?, *rows.last()?);
+    // A board whose corner square matches the margin is left untrimmed
+    if right - left + 1 < image.width() / 2 || bottom - top + 1 < image.height() / 2 {
+        return Some((0, 0, image.width(), image.height()));
+    }
+    Some((left, top, right - left + 1, bottom - top + 1))
+}
+
+/// Shrinks the square of the given size with the given top left corner to a cell, averaging the
+/// brightness of the pixels under each pixel of the cell.
+fn sample_cell(image: &RgbaImage, left: f32, top: f32, size: f32) -> Vec<f32> {
+    let step = size / CELL_SIZE as f32;
+    let span = |start: f32, limit: u32| {
+        let first = (start as u32).min(limit - 1);
+        let last = ((start + step).ceil() as u32).clamp(first + 1, limit);
+        first..last
+    };
+    let mut cell = Vec::with_capacity((CELL_SIZE * CELL_SIZE) as usize);
+    for cell_y in 0..CELL_SIZE {
+        for cell_x in 0..CELL_SIZE {
+            let xs = span(left + cell_x as f32 * step, image.width());
+            let ys = span(top + cell_y as f32 * step, image.height());
+            let count = xs.len() * ys.len();
+            let total: f32 = ys
+                .flat_map(|y| xs.clone().map(move |x| (x, y)))
+                .map(|(x, y)| brightness(&image.get_pixel(x, y).0))
+                .sum();
+            cell.push(total / count as f32);
+        }
+    }
+    cell
+}
+
+/// Returns the brightness of the background of the given cell, the median of its outer pixels,
+/// which pieces rarely cover.
+fn background(cell: &[f32]) -> f32 {
+    let size = CELL_SIZE as usize;
+    let mut outer: Vec<f32> = (0..size * size)
+        .filter(|index| {
+            let (x, y) = (index % size, index / size);
+            x == 0 || y == 0 || x == size - 1 || y == size - 1
+        })
+        .map(|index| cell[index])
+        .collect();
+    outer.sort_by(f32::total_cmp);
+    outer[outer.len() / 2]
+}
+
+/// Returns the piece on the given cell, or None if the square is empty.
+fn classify(cell: &[f32], templates: &[Template]) -> Option<(PieceColor, PieceType)> {
+    let background = background(cell);
+    let empty: f32 = cell.iter().map(|value| (value - background).powi(2)).sum();
+    templates
+        .iter()
+        .map(|template| (template.distance(cell, background), template.piece))
+        .filter(|(distance, _)| *distance < empty)
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, piece)| piece)
+}
+
+/// Returns the castling rights of each king and rook on their starting squares.
+fn castling_rights(placement: &PiecePlacement) -> CastlingRights {
+    let can_castle = |rank: usize, color: PieceColor, rook_file: usize| {
+        placement[rank][4] == Some((color, PieceType::King))
+            && placement[rank][rook_file] == Some((color, PieceType::Rook))
+    };
+    CastlingRights {
+        white: [
+            can_castle(7, PieceColor::White, 7),
+            can_castle(7, PieceColor::White, 0),
+        ],
+        black: [
+            can_castle(0, PieceColor::Black, 7),
+            can_castle(0, PieceColor::Black, 0),
+        ],
+    }
+}
+
+/// Recognises the position in the given image of a board, which has black at the bottom if it is
+/// flipped. Returns None if the image holds no board.
+fn recognise(image: &RgbaImage, active_color: PieceColor, flipped: bool) -> Option<Fen> {
+    let (left, top, width, height) = board_bounds(image)?;
+    let square_width = width as f32 / BOARD_SIZE as f32;
+    let square_height = height as f32 / BOARD_SIZE as f32;
+    let templates: Vec<Template> = PieceColor::iter()
+        .flat_map(|color| PieceType::iter().map(move |piece_type| Template::new(color, piece_type)))
+        .collect();
+    let mut placement: PiecePlacement = [[None; MAX_FILES]; BOARD_SIZE];
+    for (rank, pieces) in placement.iter_mut().enumerate() {
+        for (file, piece) in pieces.iter_mut().take(BOARD_SIZE).enumerate() {
+            let (row, column) = if flipped {
+                (BOARD_SIZE - 1 - rank, BOARD_SIZE - 1 - file)
+            } else {
+                (rank, file)
+            };
+            let cell = sample_cell(
+                image,
+                left as f32 + column as f32 * square_width,
+                top as f32 + row as f32 * square_height,
+                square_width.min(square_height),
+            );
+            *piece = classify(&cell, &templates);
+        }
+    }
+    let castling_rights = castling_rights(&placement);
+    Some(Fen::new(
+        placement,
+        active_color,
+        castling_rights,
+        None,
+        0,
+        1,
+    ))
+}
+
+/// The state of the window recognising positions from images.
+#[derive(Resource, Debug)]
+pub(super) struct RecognitionState {
+    path: String,
+    active_color: PieceColor,
+    flipped: bool,
+    /// The position recognised from the last image, or the reason it could not be.
+    result: Option<Result<Fen, String>>,
+}
+
+impl Default for RecognitionState {
+    fn default() -> Self {
+        RecognitionState {
+            path: String::new(),
+            active_color: PieceColor::White,
+            flipped: false,
+            result: None,
+        }
+    }
+}
+
+impl RecognitionState {
+    /// Recognises the position in the image at the chosen path.
+    fn recognise(&mut self, locale: &Localisation) {
+        self.result = Some(match image::open(self.path.trim()) {
+            Ok(image) => recognise(&image.to_rgba8(), self.active_color, self.flipped)
+                .ok_or_else(|| locale.get("no-board-found").to_string()),
+            Err(error) => Err(locale.format("image-not-opened", &[("error", &error)])),
+        });
+    }
+}
+
+/// Shows the window recognising positions from images, collapsed until it is opened, from which
+/// a recognised position can be loaded into a new analysis board.
+pub(super) fn recognition_window(
+    mut contexts: EguiContexts,
+    mut state: ResMut<RecognitionState>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+) {
+    egui::Window::new(locale.get("position-from-image"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label(locale.get("image-path"));
+                ui.text_edit_singleline(&mut state.path);
+            });
+            let mut active_color = state.active_color;
+            egui::ComboBox::from_label(locale.get("to-move"))
+                .selected_text(locale.color(&active_color))
+                .show_ui(ui, |ui| {
+                    for color in PieceColor::iter() {
+                        ui.selectable_value(&mut active_color, color, locale.color(&color));
+                    }
+                });
+            state.active_color = active_color;
+            ui.checkbox(&mut state.flipped, locale.get("black-at-bottom"));
+            if ui.button(locale.get("recognise")).clicked() {
+                state.recognise(&locale);
+            }
+
+            let mut load = None;
+            match &state.result {
+                Some(Ok(fen)) => {
+                    ui.label(fen.to_string());
+                    if ui.button(locale.get("load")).clicked() {
+                        load = Some(fen.clone());
+                    }
+                }
+                Some(Err(message)) => {
+                    ui.label(message);
+                }
+                None => {}
+            }
+            if let Some(fen) = load {
+                // An analysis board is for the player alone
+                engine.set_color(None);
+                clock.set_time_control(None);
+                commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+                state.result = None;
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    /// Draws the given position as a diagram with the vector piece set and a margin.
+    fn diagram(fen: &Fen, square_size: u32, margin: u32, flipped: bool) -> RgbaImage {
+        let pieces: Vec<Vec<u8>> = PieceColor::iter()
+            .flat_map(|color| PieceType::iter().map(move |piece_type| (color, piece_type)))
+            .map(|(color, piece_type)| rasterise_piece(&color, &piece_type, square_size))
+            .collect();
+        let size = square_size * BOARD_SIZE as u32 + 2 * margin;
+        RgbaImage::from_fn(size, size, |x, y| {
+            let board_size = square_size * BOARD_SIZE as u32;
+            if x < margin || y < margin || x >= margin + board_size || y >= margin + board_size {
+                return Rgba([40, 40, 40, 255]);
+            }
+            let (x, y) = (x - margin, y - margin);
+            let (mut rank, mut file) = ((y / square_size) as usize, (x / square_size) as usize);
+            let background = if (rank + file) % 2 == 0 {
+                [240., 217., 181.]
+            } else {
+                [181., 136., 99.]
+            };
+            if flipped {
+                (rank, file) = (BOARD_SIZE - 1 - rank, BOARD_SIZE - 1 - file);
+            }
+            let index = (((y % square_size) * square_size + x % square_size) * 4) as usize;
+            let (piece, coverage) = match fen.piece_placement()[rank][file] {
+                Some((color, piece_type)) => {
+                    let piece = &pieces[piece_type.index().unwrap() + 6 * color as usize][index..];
+                    (piece, piece[3] as f32 / 255.)
+                }
+                None => (&[0; 4][..], 0.),
+            };
+            let mut pixel = [255; 4];
+            for channel in 0..3 {
+                pixel[channel] = (piece[channel] as f32 * coverage
+                    + background[channel] * (1. - coverage))
+                    .round() as u8;
+            }
+            Rgba(pixel)
+        })
+    }
+
+    #[test]
+    fn test_recognise() {
+        let fen =
+            Fen::from_string("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 0 1");
+        for (square_size, margin, flipped) in [(40, 12, false), (31, 0, true)] {
+            let image = diagram(&fen, square_size, margin, flipped);
+            let recognised = recognise(&image, PieceColor::Black, flipped).unwrap();
+            assert_eq!(recognised.to_string(), fen.to_string());
+        }
+    }
+
+    #[test]
+    fn test_castling_rights() {
+        let fen = Fen::from_string("r3k3/8/8/8/8/8/8/4K2R w - - 0 1");
+        let rights = castling_rights(fen.piece_placement());
+        assert_eq!(rights.to_fen_string(), "Kq");
+    }
+}
@@ -0,0 +1,518 @@
+//! The input recorder, a developer tool which records the player's input on the board, the
+//! [actions](super::actions) they run and the pieces they choose when a pawn promotes into a
+//! script, and replays a script as though the player gave the same input, so dragging, the
+//! promotion dialog and the panels can be tested end to end.
+//!
+//! The script is saved to [RECORDING_PATH], one step a line:
+//!
+//! - `press <button> <square>` and `release <button> <square>` press and release the `left` or
+//!   `right` mouse button over a square, `beyond <square>` just past the edge of the board by a
+//!   square of its first or last rank, or `off` the board, followed by `alt` if Alt is held
+//! - `action <name>` runs an action, e.g. `action flip-board`
+//! - `promote <letter>` promotes the pawn awaiting the choice of piece, e.g. `promote N`, and
+//!   `promote cancel` cancels the promotion
+//! - `wait <frames>` waits that many frames before the next step
+//!
+//! Blank lines and lines starting with `#` are skipped, so scripts can also be written by hand.
+//! A script does not reset the board, so one which should start from the standard position
+//! starts with `action new-game`. Clicks on the rest of the interface are not recorded, only the
+//! actions and promotions they lead to.
+
+use std::fmt;
+
+use bevy::input::mouse::MouseButtonInput;
+use bevy::input::ButtonState;
+use bevy::prelude::{Entity, EventReader, EventWriter, MouseButton, Res, ResMut, Resource};
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::{BoardPosition, ChessBoard, GameCommand, GameCommands, PieceType};
+use crate::notification::{Argument, Notification};
+use crate::storage;
+
+use super::actions::{Action, ActionEvent};
+use super::locale::Localisation;
+use super::piece::MoveConfirmation;
+use super::BoardClickEvent;
+
+/// The file the script is saved to and replayed from.
+const RECORDING_PATH: &str = "recording.txt";
+
+/// A press or release of a mouse button on or around the board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Click {
+    button: MouseButton,
+    state: ButtonState,
+    position: Option<BoardPosition>,
+    past_edge: Option<BoardPosition>,
+    alt: bool,
+}
+
+impl Click {
+    fn new(event: &BoardClickEvent) -> Self {
+        Click {
+            button: event.input.button,
+            state: event.input.state,
+            position: event.position,
+            past_edge: event.past_edge,
+            alt: event.alt,
+        }
+    }
+
+    fn event(&self) -> BoardClickEvent {
+        BoardClickEvent {
+            position: self.position,
+            past_edge: self.past_edge,
+            alt: self.alt,
+            input: MouseButtonInput {
+                button: self.button,
+                state: self.state,
+                window: Entity::PLACEHOLDER,
+            },
+        }
+    }
+}
+
+/// A step of a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Click(Click),
+    Action(Action),
+    /// Promotes the pawn awaiting the choice of piece to the given piece, or cancels the promotion
+    /// if it is None.
+    Promote(Option<PieceType>),
+    Wait(u32),
+}
+
+/// Returns the name of the given action in scripts, which is its locale key without the prefix.
+fn action_name(action: Action) -> &'static str {
+    action.key().trim_start_matches("action-")
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Click(click) => {
+                let state = match click.state {
+                    ButtonState::Pressed => "press",
+                    ButtonState::Released => "release",
+                };
+                let button = match click.button {
+                    MouseButton::Right => "right",
+                    _ => "left",
+                };
+                let square = match (click.position, click.past_edge) {
+                    (Some(position), _) => position.to_algebraic(),
+                    (None, Some(past_edge)) => format!("beyond {}", past_edge.to_algebraic()),
+                    (None, None) => "off".to_string(),
+                };
+                write!(f, "{} {} {}", state, button, square)?;
+                if click.alt {
+                    write!(f, " alt")?;
+                }
+                Ok(())
+            }
+            Step::Action(action) => write!(f, "action {}", action_name(*action)),
+            Step::Promote(Some(piece_type)) => write!(f, "promote {}", piece_type.letter()),
+            Step::Promote(None) => write!(f, "promote cancel"),
+            Step::Wait(frames) => write!(f, "wait {}", frames),
+        }
+    }
+}
+
+/// Reads the square of a click from the given words, returning its position on the board and
+/// the square it is just beyond.
+fn parse_square<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+) -> Result<(Option<BoardPosition>, Option<BoardPosition>), String> {
+    let square = |word: Option<&str>| {
+        let word = word.ok_or("Missing the square.")?;
+        BoardPosition::from_algebraic(word).ok_or(format!("Invalid square: {}.", word))
+    };
+    match words.next() {
+        Some("off") => Ok((None, None)),
+        Some("beyond") => Ok((None, Some(square(words.next())?))),
+        word => Ok((Some(square(word)?), None)),
+    }
+}
+
+/// Reads a step from a line of a script, or returns None if the line is blank or a comment.
+fn parse_step(line: &str) -> Result<Option<Step>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut words = line.split_whitespace();
+    let step = match words.next() {
+        Some(verb @ ("press" | "release")) => {
+            let button = match words.next() {
+                Some("left") => MouseButton::Left,
+                Some("right") => MouseButton::Right,
+                _ => return Err("The button must be left or right.".to_string()),
+            };
+            let (position, past_edge) = parse_square(&mut words)?;
+            let alt = match words.next() {
+                Some("alt") => true,
+                Some(word) => return Err(format!("Unexpected word: {}.", word)),
+                None => false,
+            };
+            Step::Click(Click {
+                button,
+                state: match verb {
+                    "press" => ButtonState::Pressed,
+                    _ => ButtonState::Released,
+                },
+                position,
+                past_edge,
+                alt,
+            })
+        }
+        Some("action") => {
+            let name = words.next().ok_or("Missing the action.")?;
+            let action = Action::iter()
+                .find(|action| action_name(*action) == name)
+                .ok_or(format!("Unknown action: {}.", name))?;
+            Step::Action(action)
+        }
+        Some("promote") => match words.next() {
+            Some("cancel") => Step::Promote(None),
+            Some(letter) => {
+                let mut letters = letter.chars();
+                let piece_type = letters
+                    .next()
+                    .filter(|_| letters.next().is_none())
+                    .and_then(PieceType::from_letter)
+                    .ok_or(format!("Unknown piece: {}.", letter))?;
+                Step::Promote(Some(piece_type))
+            }
+            None => return Err("Missing the piece.".to_string()),
+        },
+        Some("wait") => {
+            let frames = words.next().ok_or("Missing the number of frames.")?;
+            Step::Wait(
+                frames
+                    .parse()
+                    .map_err(|_| format!("Invalid number of frames: {}.", frames))?,
+            )
+        }
+        Some(word) => return Err(format!("Unknown step: {}.", word)),
+        None => unreachable!("the line is not blank"),
+    };
+    Ok(Some(step))
+}
+
+/// Reads the steps of the given script, or returns the first error with its line number.
+fn parse_script(text: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        match parse_step(line) {
+            Ok(step) => steps.extend(step),
+            Err(error) => return Err(format!("Line {}: {}", number + 1, error)),
+        }
+    }
+    Ok(steps)
+}
+
+/// Returns the script of the given steps, one a line.
+fn script(steps: &[Step]) -> String {
+    steps.iter().map(|step| format!("{}\n", step)).collect()
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum RecorderState {
+    #[default]
+    Idle,
+    Recording {
+        /// The number of frames since the last step was recorded.
+        idle_frames: u32,
+    },
+    Replaying {
+        /// The index of the next step.
+        next: usize,
+        /// The number of frames left to wait before it.
+        wait: u32,
+    },
+}
+
+/// The state of the input recorder, and the steps it has recorded or is replaying.
+#[derive(Resource, Debug, Default)]
+pub(super) struct InputRecorder {
+    state: RecorderState,
+    steps: Vec<Step>,
+}
+
+impl InputRecorder {
+    fn start_recording(&mut self) {
+        self.steps.clear();
+        self.state = RecorderState::Recording { idle_frames: 0 };
+    }
+
+    fn start_replay(&mut self, steps: Vec<Step>) {
+        self.steps = steps;
+        self.state = RecorderState::Replaying { next: 0, wait: 0 };
+    }
+
+    fn stop(&mut self) {
+        self.state = RecorderState::Idle;
+    }
+
+    /// Records the given step, after a wait for the frames since the step before it, if the
+    /// recorder is recording.
+    fn record(&mut self, step: Step) {
+        let RecorderState::Recording { idle_frames } = &mut self.state else {
+            return;
+        };
+        if *idle_frames > 0 && !self.steps.is_empty() {
+            self.steps.push(Step::Wait(*idle_frames));
+        }
+        *idle_frames = 0;
+        self.steps.push(step);
+    }
+
+    /// Records the piece chosen for a promotion, or that the promotion was cancelled.
+    pub(super) fn record_promotion(&mut self, piece_type: Option<PieceType>) {
+        self.record(Step::Promote(piece_type));
+    }
+
+    /// Returns the steps to take this frame, moving the replay on to the next frame.
+    fn replay_frame(&mut self) -> Vec<Step> {
+        let RecorderState::Replaying { next, wait } = &mut self.state else {
+            return Vec::new();
+        };
+        if *wait > 0 {
+            *wait -= 1;
+            return Vec::new();
+        }
+        let mut steps = Vec::new();
+        while let Some(step) = self.steps.get(*next) {
+            *next += 1;
+            match step {
+                // A wait of one frame takes the next step on the next frame
+                Step::Wait(frames) if *frames > 0 => {
+                    *wait = frames - 1;
+                    return steps;
+                }
+                Step::Wait(_) => {}
+                step => steps.push(*step),
+            }
+        }
+        self.state = RecorderState::Idle;
+        steps
+    }
+}
+
+/// Records the clicks on the board which are not on the interface, and the actions run, counting
+/// the frames between them.
+pub(super) fn record_input(
+    mut contexts: EguiContexts,
+    mut click_events: EventReader<BoardClickEvent>,
+    mut action_events: EventReader<ActionEvent>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    if !matches!(recorder.state, RecorderState::Recording { .. }) {
+        click_events.clear();
+        action_events.clear();
+        return;
+    }
+    let over_interface = contexts.ctx_mut().is_pointer_over_area();
+    for event in click_events.iter() {
+        let button = matches!(event.input.button, MouseButton::Left | MouseButton::Right);
+        if button && !over_interface {
+            recorder.record(Step::Click(Click::new(event)));
+        }
+    }
+    for ActionEvent(action) in action_events.iter() {
+        recorder.record(Step::Action(*action));
+    }
+    if let RecorderState::Recording { idle_frames } = &mut recorder.state {
+        *idle_frames += 1;
+    }
+}
+
+/// Takes the steps of the script being replayed which fall on this frame, keeping the app
+/// redrawing until the script ends.
+pub(super) fn replay_input(
+    mut recorder: ResMut<InputRecorder>,
+    mut click_events: EventWriter<BoardClickEvent>,
+    mut action_events: EventWriter<ActionEvent>,
+    confirmation: MoveConfirmation,
+    board: Res<ChessBoard>,
+    mut commands: ResMut<GameCommands>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    let MoveConfirmation {
+        mut blunder_check,
+        mut promotion_choice,
+    } = confirmation;
+    if !matches!(recorder.state, RecorderState::Replaying { .. }) {
+        return;
+    }
+    for step in recorder.replay_frame() {
+        match step {
+            Step::Click(click) => click_events.send(click.event()),
+            Step::Action(action) => action_events.send(ActionEvent(action)),
+            Step::Promote(piece_type) => {
+                if let Some(promotion) = promotion_choice.choose(piece_type) {
+                    if blunder_check.check(promotion, &board) {
+                        commands.push(GameCommand::MakeMove(promotion));
+                    }
+                }
+            }
+            Step::Wait(_) => {}
+        }
+    }
+    redraw_events.send(RequestRedraw);
+}
+
+/// Shows the input recorder, collapsed until it is opened.
+pub(super) fn recorder_window(
+    mut contexts: EguiContexts,
+    mut recorder: ResMut<InputRecorder>,
+    locale: Res<Localisation>,
+    mut notifications: EventWriter<Notification>,
+) {
+    egui::Window::new(locale.get("input-recorder"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let steps = recorder.steps.len().to_string();
+            match recorder.state {
+                RecorderState::Idle => {
+                    ui.label(locale.format("recorded-steps", &[("steps", &steps)]));
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button(locale.get("record-input"))
+                            .on_hover_text(locale.get("record-input-hint"))
+                            .clicked()
+                        {
+                            recorder.start_recording();
+                        }
+                        if ui
+                            .button(locale.get("replay-input"))
+                            .on_hover_text(locale.format(
+                                "replay-input-hint",
+                                &[("path", &RECORDING_PATH.to_string())],
+                            ))
+                            .clicked()
+                        {
+                            match storage::read(RECORDING_PATH)
+                                .ok_or(format!("{} not found.", RECORDING_PATH))
+                                .and_then(|text| parse_script(&text))
+                            {
+                                Ok(steps) => recorder.start_replay(steps),
+                                Err(error) => notifications.send(
+                                    Notification::error("recording-load-failed")
+                                        .with_arg("error", Argument::Text(error)),
+                                ),
+                            }
+                        }
+                    });
+                }
+                RecorderState::Recording { .. } => {
+                    ui.label(locale.format("recording-input", &[("steps", &steps)]));
+                    if ui.button(locale.get("stop-recording")).clicked() {
+                        recorder.stop();
+                        let notification =
+                            match storage::write(RECORDING_PATH, &script(&recorder.steps)) {
+                                Ok(()) => Notification::info("recording-saved")
+                                    .with_arg("path", Argument::Text(RECORDING_PATH.to_string())),
+                                Err(error) => Notification::error("recording-save-failed")
+                                    .with_arg("error", Argument::Text(error)),
+                            };
+                        notifications.send(notification);
+                    }
+                }
+                RecorderState::Replaying { next, .. } => {
+                    ui.label(locale.format(
+                        "replaying-input",
+                        &[("step", &next.to_string()), ("steps", &steps)],
+                    ));
+                    if ui.button(locale.get("stop-replay")).clicked() {
+                        recorder.stop();
+                    }
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{App, Events, Update};
+
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::super::blunder::BlunderCheck;
+    use super::super::promotion::PromotionChoice;
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let text = "# Promote to a knight\n\
+            action new-game\n\
+            press left b7 alt\nwait 2\n\
+            release left beyond b8\n\
+            promote N\n\
+            press right off\n";
+        let steps = parse_script(text).unwrap();
+        assert_eq!(steps.len(), 6);
+        assert_eq!(steps[0], Step::Action(Action::NewGame));
+        assert_eq!(steps[5].to_string(), "press right off");
+        assert_eq!(parse_script(&script(&steps)), Ok(steps));
+        assert_eq!(
+            parse_script("wait 1\npress middle e4"),
+            Err("Line 2: The button must be left or right.".to_string())
+        );
+
+        let mut recorder = InputRecorder::default();
+        recorder.start_recording();
+        recorder.record(Step::Action(Action::FlipBoard));
+        recorder.state = RecorderState::Recording { idle_frames: 3 };
+        recorder.record_promotion(None);
+        assert_eq!(
+            recorder.steps,
+            [
+                Step::Action(Action::FlipBoard),
+                Step::Wait(3),
+                Step::Promote(None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let board = ChessBoard::with_position(&Fen::from_string("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1"));
+        let promotion = Move::from_algebraic("b8=Q", &board).unwrap();
+        let mut choice = PromotionChoice::default();
+        assert!(!choice.check(promotion, true));
+
+        let mut app = App::new();
+        app.add_event::<BoardClickEvent>()
+            .add_event::<ActionEvent>()
+            .add_event::<RequestRedraw>()
+            .insert_resource(board)
+            .insert_resource(choice)
+            .init_resource::<BlunderCheck>()
+            .init_resource::<GameCommands>()
+            .init_resource::<InputRecorder>()
+            .add_systems(Update, replay_input);
+        let steps = parse_script("press left b7\nrelease left b8\nwait 2\npromote Q").unwrap();
+        app.world
+            .resource_mut::<InputRecorder>()
+            .start_replay(steps);
+
+        // Both clicks are sent on the first frame and the promotion made two frames later
+        let clicks = |app: &App| app.world.resource::<Events<BoardClickEvent>>().len();
+        app.update();
+        assert_eq!(clicks(&app), 2);
+        app.update();
+        let commands = |app: &App| format!("{:?}", app.world.resource::<GameCommands>());
+        assert!(!commands(&app).contains("MakeMove"));
+        app.update();
+        assert!(commands(&app).contains("MakeMove"));
+        assert_eq!(
+            app.world.resource::<InputRecorder>().state,
+            RecorderState::Idle
+        );
+    }
+}
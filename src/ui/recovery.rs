@@ -0,0 +1,73 @@
+//! The window offering to restore a game which was interrupted by an unexpected exit, and the
+//! window asking which game to keep when another window's game went a different way.
+
+use bevy::prelude::{EventWriter, Res};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::autosave::{Autosave, RestoreGameEvent};
+use crate::history::PositionHistory;
+use crate::tab_sync::{ResolveConflictEvent, TabSync};
+
+use super::locale::Localisation;
+
+/// Shows the restore window while there is an interrupted game.
+pub(super) fn recovery_window(
+    mut contexts: EguiContexts,
+    autosave: Res<Autosave>,
+    locale: Res<Localisation>,
+    mut restore_events: EventWriter<RestoreGameEvent>,
+) {
+    let Some(game) = autosave.interrupted() else {
+        return;
+    };
+    egui::Window::new(locale.get("restore-game"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(locale.format("restore-prompt", &[("moves", &game.move_count())]));
+            ui.horizontal(|ui| {
+                if ui.button(locale.get("restore")).clicked() {
+                    restore_events.send(RestoreGameEvent::new(true));
+                }
+                if ui.button(locale.get("discard")).clicked() {
+                    restore_events.send(RestoreGameEvent::new(false));
+                }
+            });
+        });
+}
+
+/// Shows the conflict window while another window's game conflicts with this one.
+pub(super) fn conflict_window(
+    mut contexts: EguiContexts,
+    sync: Res<TabSync>,
+    history: Res<PositionHistory>,
+    locale: Res<Localisation>,
+    mut resolve_events: EventWriter<ResolveConflictEvent>,
+) {
+    let Some(conflict) = sync.conflict() else {
+        return;
+    };
+    egui::Window::new(locale.get("game-conflict"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(locale.format(
+                "game-conflict-prompt",
+                &[
+                    ("moves", &history.moves().len()),
+                    ("other", &conflict.saved_moves()),
+                    ("squares", &conflict.squares()),
+                ],
+            ));
+            ui.horizontal(|ui| {
+                if ui.button(locale.get("keep-this-game")).clicked() {
+                    resolve_events.send(ResolveConflictEvent::new(true));
+                }
+                if ui.button(locale.get("keep-other-game")).clicked() {
+                    resolve_events.send(ResolveConflictEvent::new(false));
+                }
+            });
+        });
+}
@@ -0,0 +1,95 @@
+//! Controls for exporting a [game report](crate::report) of the current game.
+
+use bevy::prelude::Resource;
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::ChessBoard;
+use crate::report::{game_report, KeySwing, ReportFormat, ReportReview};
+
+use super::analysis::evaluation_text;
+use super::annotation::MoveAnnotations;
+use super::locale::Localisation;
+use super::move_list::PastMoves;
+use super::review::GameReview;
+
+/// The name of the exported report file, without its extension.
+const REPORT_FILE_NAME: &str = "game_report";
+
+/// The state of the report export controls.
+#[derive(Resource, Debug, Default)]
+pub(super) struct ReportExportState {
+    /// The result of the last export.
+    message: Option<String>,
+}
+
+/// Returns what the review of the game on the given board and the annotations of its moves add
+/// to its report. A review of another game adds nothing.
+fn report_review(
+    board: &ChessBoard,
+    review: &GameReview,
+    annotations: &MoveAnnotations,
+) -> ReportReview {
+    let past_moves = board.past_moves();
+    ReportReview {
+        glyphs: (0..past_moves.len())
+            .filter_map(|index| Some((index, annotations.get(index)?.glyph())))
+            .collect(),
+        evaluations: past_moves
+            .iter()
+            .enumerate()
+            .filter_map(|(index, played)| {
+                Some((
+                    index,
+                    evaluation_text(review.evaluation_after(index, played)?),
+                ))
+            })
+            .collect(),
+        swings: review
+            .swings()
+            .filter(|(index, played, _, _)| {
+                past_moves
+                    .get(*index)
+                    .is_some_and(|past_move| past_move.as_algebraic() == *played)
+            })
+            .map(|(index, _, fen, swing)| KeySwing {
+                index,
+                fen: fen.clone(),
+                swing,
+            })
+            .collect(),
+    }
+}
+
+/// Draws the report export controls.
+pub(super) fn export_ui(
+    ui: &mut egui::Ui,
+    board: &ChessBoard,
+    past_moves: &PastMoves,
+    state: &mut ReportExportState,
+    locale: &Localisation,
+) {
+    ui.collapsing(locale.get("export-report"), |ui| {
+        for format in ReportFormat::iter() {
+            let format_name = format!("{:?}", format);
+            if ui
+                .button(locale.format("export", &[("format", &format_name)]))
+                .clicked()
+            {
+                let path = format!("{}.{}", REPORT_FILE_NAME, format.extension());
+                let review = report_review(board, past_moves.review(), past_moves.annotations());
+                state.message = Some(
+                    match std::fs::write(&path, game_report(board, &review, format)) {
+                        Ok(()) => locale.format("saved-to", &[("path", &path)]),
+                        Err(error) => {
+                            locale.format("unable-to-save", &[("path", &path), ("error", &error)])
+                        }
+                    },
+                );
+            }
+        }
+        if let Some(message) = &state.message {
+            ui.label(message);
+        }
+    });
+}
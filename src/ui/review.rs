@@ -0,0 +1,452 @@
+//! The game review window, which finds the [biggest mistake](crate::review) of each player in the
+//! game, jumps the board to the position before it and lets the player try to find a better move
+//! there, once, as a puzzle. Below them it lists the critical moments of the game, where only one
+//! move held, with whether it was found. The [evaluation graph](super::eval_graph) of the game
+//! reviewed is drawn under the move list, and hovering over one of its moves in the list shows the
+//! line the engine expected instead, from the searches of the review. The review annotates each
+//! move it classifies as an inaccuracy, a mistake or a blunder with its glyph.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, PieceColor, ResetBoardEvent};
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::puzzle::Puzzle;
+use crate::review::{
+    biggest_evaluated_mistakes, evaluated_lines, evaluated_mistakes, only_moves, Mistake, OnlyMove,
+};
+
+use super::analysis::{evaluation_text, line_labels};
+use super::annotation::{MoveAnnotations, MoveQuality};
+use super::engine::GameSetup;
+use super::eval_graph::EvalGraph;
+use super::locale::Localisation;
+
+/// A mistake of the game reviewed, with the position it was made in.
+#[derive(Debug, Clone)]
+struct ReviewedMistake {
+    mistake: Mistake,
+    /// The move played, in standard algebraic notation.
+    played: String,
+    fen: Fen,
+    /// The event which resets the board to the position before the mistake.
+    reset_event: ResetBoardEvent,
+}
+
+/// A position of the game reviewed in which only one move held.
+#[derive(Debug, Clone)]
+struct CriticalMoment {
+    only_move: OnlyMove,
+    /// The color of the player to move.
+    color: PieceColor,
+    /// The move which held and the move played, in standard algebraic notation.
+    best: String,
+    played: String,
+    /// Whether the move which held was played.
+    found: bool,
+    /// The event which resets the board to the position.
+    reset_event: ResetBoardEvent,
+}
+
+/// What the engine would have played instead of a move of the game reviewed, from the review's
+/// evaluations, so hovering over the move needs no search.
+#[derive(Debug, Clone)]
+pub(super) struct WhatIf {
+    played: Move,
+    /// The evaluation after the move played and after the engine's move instead, from white's
+    /// point of view.
+    played_evaluation: i32,
+    best_evaluation: i32,
+    /// The line the engine expected from the position, starting with its move, with move numbers.
+    line: Vec<String>,
+    /// Whether the move played was the engine's.
+    best_played: bool,
+}
+
+impl WhatIf {
+    /// Returns what the engine would have played instead of the given move, from the position it
+    /// was played in and the one it reached, with the evaluation of each from the point of view
+    /// of the player to move in it and the line the engine expected from the first.
+    fn new(played: &Move, before: (&Fen, i32, &[Move]), after: (&Fen, i32)) -> Self {
+        let from_white = |fen: &Fen, evaluation: i32| match fen.active_color() {
+            PieceColor::White => evaluation,
+            PieceColor::Black => -evaluation,
+        };
+        let (fen, evaluation, line) = before;
+        WhatIf {
+            played: *played,
+            played_evaluation: from_white(after.0, after.1),
+            best_evaluation: from_white(fen, evaluation),
+            line: line_labels(line, *fen.fullmove_number()),
+            best_played: line.first().is_some_and(|best| {
+                best.from() == played.from()
+                    && best.to() == played.to()
+                    && best.promotion() == played.promotion()
+            }),
+        }
+    }
+
+    /// Draws the evaluation of the engine's line instead of the move, and of the move played.
+    pub(super) fn hover_ui(&self, ui: &mut egui::Ui, locale: &Localisation) {
+        if self.best_played {
+            ui.label(locale.format(
+                "what-if-best",
+                &[("evaluation", &evaluation_text(self.best_evaluation))],
+            ));
+            return;
+        }
+        let line: Vec<String> = self.line.iter().map(|label| locale.san(label)).collect();
+        ui.label(locale.format(
+            "what-if",
+            &[
+                ("line", &line.join(" ")),
+                ("evaluation", &evaluation_text(self.best_evaluation)),
+            ],
+        ));
+        ui.label(locale.format(
+            "what-if-played",
+            &[("evaluation", &evaluation_text(self.played_evaluation))],
+        ));
+    }
+}
+
+/// A try at finding a better move than a mistake.
+#[derive(Debug, Clone)]
+struct Attempt {
+    puzzle: Puzzle,
+    /// The number of moves of the game before the mistake.
+    ply: usize,
+    /// Whether the position has been loaded onto the board.
+    started: bool,
+    /// Whether the move tried solved the puzzle, and the solution if it did not, once it is made.
+    result: Option<(bool, Option<String>)>,
+}
+
+impl Attempt {
+    /// Checks for the player's move, judging it once they have made it.
+    fn update(&mut self, board: &ChessBoard) {
+        if self.result.is_some() {
+            return;
+        }
+        let past_moves = board.past_moves();
+        if !self.started {
+            self.started = past_moves.len() == self.ply;
+            return;
+        }
+        if past_moves.len() != self.ply + 1 {
+            return;
+        }
+        let solved = self.puzzle.is_solved_by(&past_moves[self.ply]);
+        let solution = (!solved)
+            .then(|| self.puzzle.solution())
+            .flatten()
+            .map(|solution| solution.as_algebraic());
+        self.result = Some((solved, solution));
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct GameReview {
+    /// The biggest mistake of white and of black in the game reviewed.
+    mistakes: [Option<ReviewedMistake>; 2],
+    /// The moves which lost at least an inaccuracy, the biggest first.
+    swings: Vec<ReviewedMistake>,
+    moments: Vec<CriticalMoment>,
+    graph: Option<EvalGraph>,
+    /// What the engine would have played instead of each move, from the first.
+    what_ifs: Vec<WhatIf>,
+    /// The number of moves made before the game reviewed started.
+    offset: usize,
+    /// The event which resets the board to the end of the game reviewed.
+    end: Option<ResetBoardEvent>,
+    attempt: Option<Attempt>,
+}
+
+impl GameReview {
+    /// Reviews the game of the given history, annotating the moves it classifies.
+    pub(super) fn review(&mut self, history: &PositionHistory, annotations: &mut MoveAnnotations) {
+        let positions = history.positions();
+        let moves = history.moves();
+        let lines = evaluated_lines(positions);
+        let evaluations: Vec<i32> = lines.iter().map(|(evaluation, _)| *evaluation).collect();
+        let reviewed = |mistake: Mistake| ReviewedMistake {
+            mistake,
+            played: moves[mistake.ply].as_algebraic(),
+            fen: positions[mistake.ply].clone(),
+            reset_event: history.reset_event(mistake.ply),
+        };
+        let classifications: Vec<_> = evaluated_mistakes(evaluations.clone(), moves.len())
+            .map(|mistake| mistake.classification())
+            .collect();
+        self.mistakes = biggest_evaluated_mistakes(evaluations.clone(), moves)
+            .map(|mistake| mistake.map(reviewed));
+        self.swings = evaluated_mistakes(evaluations.clone(), moves.len())
+            .filter(|mistake| mistake.classification().is_some())
+            .map(reviewed)
+            .collect();
+        self.swings
+            .sort_by_key(|reviewed| -reviewed.mistake.swing());
+        self.moments = only_moves(positions, moves)
+            .into_iter()
+            .map(|only_move| {
+                let played = &moves[only_move.ply];
+                CriticalMoment {
+                    only_move,
+                    color: *played.piece_color(),
+                    best: only_move.best.as_algebraic(),
+                    played: played.as_algebraic(),
+                    found: only_move.is_found_by(played),
+                    reset_event: history.reset_event(only_move.ply),
+                }
+            })
+            .collect();
+        self.what_ifs = moves
+            .iter()
+            .enumerate()
+            .map(|(ply, played)| {
+                let (evaluation, line) = &lines[ply];
+                WhatIf::new(
+                    played,
+                    (&positions[ply], *evaluation, line),
+                    (&positions[ply + 1], evaluations[ply + 1]),
+                )
+            })
+            .collect();
+        self.offset = history.moves_before_start();
+        // Moves the review does not classify keep the annotations the player gave them
+        for (ply, classification) in classifications.iter().enumerate() {
+            if let Some(classification) = classification {
+                annotations.set(self.offset + ply, Some(MoveQuality::from(*classification)));
+            }
+        }
+        self.graph = Some(EvalGraph::new(history, evaluations, &classifications));
+        self.end = history
+            .len()
+            .checked_sub(1)
+            .map(|last| history.reset_event(last));
+        self.attempt = None;
+    }
+
+    /// Returns what the engine would have played instead of the given past move of the given
+    /// index, if it was reviewed.
+    pub(super) fn what_if(&self, index: usize, played: &Move) -> Option<&WhatIf> {
+        index
+            .checked_sub(self.offset)
+            .and_then(|ply| self.what_ifs.get(ply))
+            .filter(|what_if| what_if.played == *played)
+    }
+
+    /// Returns the evaluation after the given past move of the given index, from white's point of
+    /// view, if it was reviewed.
+    pub(super) fn evaluation_after(&self, index: usize, played: &Move) -> Option<i32> {
+        self.what_if(index, played)
+            .map(|what_if| what_if.played_evaluation)
+    }
+
+    /// Returns the moves of the game reviewed which lost at least an inaccuracy, the biggest
+    /// first, each with its index in the past moves, the move played in standard algebraic
+    /// notation, the position it was played in and the centipawns it lost.
+    pub(super) fn swings(&self) -> impl Iterator<Item = (usize, &str, &Fen, i32)> {
+        self.swings.iter().map(|reviewed| {
+            (
+                self.offset + reviewed.mistake.ply,
+                reviewed.played.as_str(),
+                &reviewed.fen,
+                reviewed.mistake.swing(),
+            )
+        })
+    }
+
+    /// Returns the evaluation graph of the game reviewed, once it has been.
+    pub(super) fn graph(&self) -> Option<&EvalGraph> {
+        self.graph.as_ref()
+    }
+}
+
+/// Shows the game review, collapsed until it is opened, with the biggest mistake of each player
+/// and the result of the last try at finding a better move.
+pub(super) fn review_window(
+    mut contexts: EguiContexts,
+    mut review: ResMut<GameReview>,
+    mut annotations: ResMut<MoveAnnotations>,
+    history: Res<PositionHistory>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut setup: GameSetup,
+) {
+    if let Some(attempt) = review.attempt.as_mut() {
+        attempt.update(&board);
+    }
+    egui::Window::new(locale.get("game-review"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let review = &mut *review;
+            if ui
+                .add_enabled(
+                    !history.moves().is_empty(),
+                    egui::Button::new(locale.get("review-find-mistakes")),
+                )
+                .on_hover_text(locale.get("review-find-mistakes-hint"))
+                .clicked()
+            {
+                review.review(&history, &mut annotations);
+            }
+            let Some(end) = &review.end else {
+                return;
+            };
+            for color in PieceColor::iter() {
+                let color_name = locale.color(&color);
+                let Some(reviewed) = &review.mistakes[color as usize] else {
+                    ui.label(locale.format("review-no-mistake", &[("color", &color_name)]));
+                    continue;
+                };
+                ui.label(locale.format(
+                    "review-mistake",
+                    &[
+                        ("color", &color_name),
+                        ("move", &locale.san(&reviewed.played)),
+                        ("swing", &reviewed.mistake.swing()),
+                    ],
+                ));
+                ui.horizontal(|ui| {
+                    if ui.button(locale.get("review-jump")).clicked() {
+                        setup.load_alone(reviewed.reset_event.clone());
+                        review.attempt = None;
+                    }
+                    if ui.button(locale.get("review-try")).clicked() {
+                        setup.load_alone(reviewed.reset_event.clone());
+                        review.attempt = Some(Attempt {
+                            puzzle: reviewed.mistake.puzzle(&reviewed.fen),
+                            ply: reviewed.reset_event.past_moves().len(),
+                            started: false,
+                            result: None,
+                        });
+                    }
+                });
+            }
+            if let Some(attempt) = &review.attempt {
+                ui.label(match &attempt.result {
+                    None => locale.get("review-find-better").to_string(),
+                    Some((true, _)) => locale.get("review-found").to_string(),
+                    Some((false, solution)) => locale.format(
+                        "review-not-found",
+                        &[(
+                            "move",
+                            &solution
+                                .as_deref()
+                                .map_or(String::new(), |solution| locale.san(solution)),
+                        )],
+                    ),
+                });
+            }
+            ui.separator();
+            ui.strong(locale.get("critical-moments"))
+                .on_hover_text(locale.get("critical-moments-hint"));
+            if review.moments.is_empty() {
+                ui.label(locale.get("no-critical-moments"));
+            }
+            let mut jump = None;
+            for moment in &review.moments {
+                ui.horizontal(|ui| {
+                    let number = moment.reset_event.past_moves().len() / 2 + 1;
+                    ui.label(locale.format(
+                        "critical-moment",
+                        &[
+                            ("number", &number),
+                            ("color", &locale.color(&moment.color)),
+                            ("move", &locale.san(&moment.best)),
+                        ],
+                    ))
+                    .on_hover_text(
+                        locale.format("critical-moment-hint", &[("gap", &moment.only_move.gap())]),
+                    );
+                    if moment.found {
+                        ui.label(locale.get("critical-found"));
+                    } else {
+                        ui.label(
+                            locale.format(
+                                "critical-missed",
+                                &[("move", &locale.san(&moment.played))],
+                            ),
+                        );
+                    }
+                    if ui.button(locale.get("review-jump")).clicked() {
+                        jump = Some(moment.reset_event.clone());
+                    }
+                });
+            }
+            if let Some(reset_event) = jump {
+                setup.load_alone(reset_event);
+                review.attempt = None;
+            }
+            if ui.button(locale.get("review-back")).clicked() {
+                setup.load_alone(end.clone());
+                review.attempt = None;
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attempt() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        for text in ["e4", "e5", "Nf3", "Qh4", "Nxh4"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        let mut history = PositionHistory::default();
+        history.set_game(&Fen::default(), board.past_moves());
+        let mut review = GameReview::default();
+        let mut annotations = MoveAnnotations::default();
+        annotations.set(0, Some(MoveQuality::Good));
+        annotations.set(3, Some(MoveQuality::Good));
+        review.review(&history, &mut annotations);
+        // The hung queen is annotated as a blunder, and the opening keeps the player's annotation
+        assert_eq!(annotations.get(3), Some(MoveQuality::Blunder));
+        assert_eq!(annotations.get(0), Some(MoveQuality::Good));
+        let reviewed = review.mistakes[PieceColor::Black as usize].clone().unwrap();
+        assert_eq!(reviewed.played, "Qh4");
+        // It is also the biggest swing of the game
+        let (index, played, _, swing) = review.swings().next().unwrap();
+        assert_eq!((index, played), (3, "Qh4"));
+        assert_eq!(swing, reviewed.mistake.swing());
+        assert_eq!(reviewed.reset_event.past_moves().len(), 3);
+        assert_eq!(
+            review.end.as_ref().map(|end| end.past_moves().len()),
+            Some(5)
+        );
+        // The queen was hung, and taking it was the engine's move
+        let moves = board.past_moves();
+        assert!(!review.what_if(3, &moves[3]).unwrap().best_played);
+        assert!(review.what_if(4, &moves[4]).unwrap().best_played);
+        assert!(review.what_if(4, &moves[3]).is_none());
+        assert_eq!(
+            review.evaluation_after(3, &moves[3]),
+            Some(review.what_if(3, &moves[3]).unwrap().played_evaluation)
+        );
+
+        let mut attempt = Attempt {
+            puzzle: reviewed.mistake.puzzle(&reviewed.fen),
+            ply: 3,
+            started: false,
+            result: None,
+        };
+        // The move is only judged once the position has been loaded
+        attempt.update(&board);
+        assert!(!attempt.started);
+        let mut board = ChessBoard::with_position(&Fen::default());
+        for text in ["e4", "e5", "Nf3"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        }
+        attempt.update(&board);
+        assert!(attempt.started);
+        board.apply_move(&Move::from_algebraic("Nc6", &board).unwrap());
+        attempt.update(&board);
+        assert_eq!(attempt.result, Some((true, None)));
+    }
+}
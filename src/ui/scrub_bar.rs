@@ -0,0 +1,312 @@
+//! The scrub bar under the board, which stands for the whole game: dragging its handle steps the
+//! board through the positions of the game, snapping to each ply, and clicking it jumps to one.
+//!
+//! The game scrubbed through is kept while the board is at one of its positions, so going back
+//! does not lose the moves after it, and the annotations of those moves are put back as the board
+//! comes forward to them again. A move which leaves the game starts a new one. Each annotated move
+//! is marked on the bar in the colour of its quality, the move selected in the move list is ringed,
+//! and the move the board is scrubbed to is selected there.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::{GameCommand, GameCommands};
+use crate::history::PositionHistory;
+
+use super::annotation::{MoveAnnotations, MoveQuality};
+use super::locale::Localisation;
+use super::move_list::MoveListState;
+
+/// The height of the bar.
+const BAR_HEIGHT: f32 = 24.0;
+
+/// The radius of the handle.
+const HANDLE_RADIUS: f32 = 7.0;
+
+/// The radius of the marks of annotated moves.
+const MARK_RADIUS: f32 = 3.5;
+
+/// The narrowest gap between plies at which each ply has a tick.
+const MIN_TICK_GAP: f32 = 4.0;
+
+/// Returns the ply nearest the given fraction of the bar, for a game of the given number of plies.
+fn ply_at(fraction: f32, plies: usize) -> usize {
+    (fraction.clamp(0.0, 1.0) * plies as f32).round() as usize
+}
+
+/// Returns the fraction of the bar at which the given ply is, for a game of the given number of
+/// plies.
+fn fraction_of(ply: usize, plies: usize) -> f32 {
+    match plies {
+        0 => 0.0,
+        _ => ply as f32 / plies as f32,
+    }
+}
+
+/// The game scrubbed through.
+#[derive(Debug)]
+struct ScrubbedGame {
+    history: PositionHistory,
+    /// The annotation of each move from the starting position, kept for the moves after the
+    /// board's position.
+    qualities: Vec<Option<MoveQuality>>,
+}
+
+impl ScrubbedGame {
+    fn new(history: &PositionHistory) -> Self {
+        ScrubbedGame {
+            history: history.clone(),
+            qualities: vec![None; history.moves().len()],
+        }
+    }
+
+    /// Returns whether the given history is at one of the positions of this game.
+    fn contains(&self, history: &PositionHistory) -> bool {
+        let same_start = match (self.history.start(), history.start()) {
+            (Some(own), Some(start)) => own.to_string() == start.to_string(),
+            _ => false,
+        };
+        same_start
+            && self.history.moves_before_start() == history.moves_before_start()
+            && self.history.moves().starts_with(history.moves())
+    }
+
+    /// Returns the index in the past moves of the move which reached the given ply.
+    fn move_index(&self, ply: usize) -> Option<usize> {
+        (self.history.moves_before_start() + ply).checked_sub(1)
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct ScrubBar {
+    game: Option<ScrubbedGame>,
+    /// Where the handle is while it is dragged, as a fraction of the bar.
+    dragged: Option<f32>,
+    /// The ply jumped from and the ply last jumped to, until the history leaves the ply jumped
+    /// from.
+    jumped: Option<(usize, usize)>,
+}
+
+impl ScrubBar {
+    /// Keeps the game in step with the given history and annotations, starting a new game once
+    /// the history leaves it.
+    fn update(&mut self, history: &PositionHistory, annotations: &MoveAnnotations) {
+        let ply = history.len().saturating_sub(1);
+        if self.jumped.is_some_and(|(from, _)| from != ply) {
+            self.jumped = None;
+        }
+        // Until the board leaves the ply jumped from, the history and annotations are of the
+        // position before the jump
+        if self.jumped.is_some() {
+            return;
+        }
+        if !self
+            .game
+            .as_ref()
+            .is_some_and(|game| game.contains(history))
+        {
+            self.game = Some(ScrubbedGame::new(history));
+        }
+        if let Some(game) = &mut self.game {
+            for move_ply in 1..=ply {
+                if let Some(index) = game.move_index(move_ply) {
+                    game.qualities[move_ply - 1] = annotations.get(index);
+                }
+            }
+        }
+    }
+}
+
+/// Resets the board to the given ply of the game, putting back the annotations of the moves up
+/// to it and selecting the move which reached it.
+fn jump(
+    game: &ScrubbedGame,
+    ply: usize,
+    annotations: &mut MoveAnnotations,
+    move_list: &mut MoveListState,
+    commands: &mut GameCommands,
+) {
+    commands.push(GameCommand::LoadGame(Box::new(
+        game.history.reset_event(ply),
+    )));
+    for (move_ply, quality) in (1..=ply).zip(&game.qualities) {
+        if let Some(index) = game.move_index(move_ply) {
+            annotations.set(index, *quality);
+        }
+    }
+    move_list.select(game.move_index(ply));
+}
+
+/// Shows the scrub bar once the game has a move.
+pub(super) fn scrub_bar(
+    mut contexts: EguiContexts,
+    mut bar: ResMut<ScrubBar>,
+    history: Res<PositionHistory>,
+    mut annotations: ResMut<MoveAnnotations>,
+    mut move_list: ResMut<MoveListState>,
+    mut commands: ResMut<GameCommands>,
+    locale: Res<Localisation>,
+) {
+    bar.update(&history, &annotations);
+    let ScrubBar {
+        game,
+        dragged,
+        jumped,
+    } = &mut *bar;
+    let Some(game) = game.as_ref() else {
+        return;
+    };
+    let plies = game.history.len().saturating_sub(1);
+    if plies == 0 {
+        return;
+    }
+    let board_ply = history.len().saturating_sub(1);
+    let current = jumped.map_or(board_ply, |(_, to)| to);
+    let label = |ply: usize| match ply {
+        0 => locale.get("position-start").to_string(),
+        _ => {
+            let glyph = game.qualities[ply - 1].map_or("", |quality| quality.glyph());
+            format!("{}{}", locale.san(&game.history.label(ply)), glyph)
+        }
+    };
+    egui::TopBottomPanel::bottom("scrub_bar").show(contexts.ctx_mut(), |ui| {
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), BAR_HEIGHT),
+            egui::Sense::click_and_drag(),
+        );
+        let track = rect.shrink2(egui::vec2(HANDLE_RADIUS, 0.0));
+        let fraction_at = |x: f32| ((x - track.left()) / track.width().max(1.0)).clamp(0.0, 1.0);
+        let point_at =
+            |fraction: f32| egui::pos2(track.left() + fraction * track.width(), rect.center().y);
+
+        *dragged = None;
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let fraction = fraction_at(pointer.x);
+            if response.dragged() {
+                *dragged = Some(fraction);
+            }
+            let target = ply_at(fraction, plies);
+            if target != current {
+                jump(
+                    game,
+                    target,
+                    &mut annotations,
+                    &mut move_list,
+                    &mut commands,
+                );
+                let from = jumped.map_or(board_ply, |(from, _)| from);
+                // Jumping back to where the board was leaves the history as it is
+                *jumped = (target != from).then_some((from, target));
+            }
+        }
+        let shown = jumped.map_or(board_ply, |(_, to)| to);
+
+        let visuals = ui.visuals();
+        let painter = ui.painter();
+        painter.line_segment(
+            [point_at(0.0), point_at(1.0)],
+            egui::Stroke::new(4.0, visuals.widgets.inactive.bg_fill),
+        );
+        painter.line_segment(
+            [point_at(0.0), point_at(fraction_of(shown, plies))],
+            egui::Stroke::new(4.0, visuals.selection.bg_fill),
+        );
+        if track.width() / plies as f32 >= MIN_TICK_GAP {
+            for ply in 0..=plies {
+                let point = point_at(fraction_of(ply, plies));
+                painter.line_segment(
+                    [point - egui::vec2(0.0, 4.0), point + egui::vec2(0.0, 4.0)],
+                    egui::Stroke::new(1.0, visuals.weak_text_color()),
+                );
+            }
+        }
+        for (ply, quality) in (1..=plies).zip(&game.qualities) {
+            if let Some(quality) = quality {
+                painter.circle_filled(
+                    point_at(fraction_of(ply, plies)),
+                    MARK_RADIUS,
+                    quality.egui_color(),
+                );
+            }
+        }
+        let selected = move_list.selected().and_then(|index| {
+            (index + 1)
+                .checked_sub(game.history.moves_before_start())
+                .filter(|ply| *ply <= plies)
+        });
+        if let Some(ply) = selected {
+            painter.circle_stroke(
+                point_at(fraction_of(ply, plies)),
+                HANDLE_RADIUS + 2.0,
+                visuals.selection.stroke,
+            );
+        }
+        let handle = dragged.unwrap_or(fraction_of(shown, plies));
+        painter.circle_filled(
+            point_at(handle),
+            HANDLE_RADIUS,
+            ui.style().interact(&response).fg_stroke.color,
+        );
+
+        response.widget_info(|| {
+            egui::WidgetInfo::slider(
+                shown as f64,
+                format!("{}: {}", locale.get("scrub-bar"), label(shown)),
+            )
+        });
+        if let Some(pointer) = response.hover_pos() {
+            let hovered = ply_at(fraction_at(pointer.x), plies);
+            response.on_hover_text(label(hovered));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::chess_board::ChessBoard;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    fn history(moves: &[&str]) -> PositionHistory {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut history = PositionHistory::default();
+        history.update(&board);
+        for text in moves {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            history.update(&board);
+        }
+        history
+    }
+
+    #[test]
+    fn test_ply_at() {
+        assert_eq!(ply_at(0.0, 10), 0);
+        assert_eq!(ply_at(0.34, 10), 3);
+        assert_eq!(ply_at(0.36, 10), 4);
+        // Dragging past either end stays on the first or last ply
+        assert_eq!(ply_at(-0.5, 10), 0);
+        assert_eq!(ply_at(1.5, 10), 10);
+        assert_eq!(fraction_of(4, 8), 0.5);
+        assert_eq!(fraction_of(0, 0), 0.0);
+    }
+
+    #[test]
+    fn test_scrubbed_game() {
+        let mut bar = ScrubBar::default();
+        let mut annotations = MoveAnnotations::default();
+        annotations.set(2, Some(MoveQuality::Good));
+        bar.update(&history(&["e4", "e5", "Nf3"]), &annotations);
+
+        // Going back keeps the whole game and the annotations of the moves after
+        bar.update(&history(&["e4"]), &MoveAnnotations::default());
+        let game = bar.game.as_ref().unwrap();
+        assert_eq!(game.history.len(), 4);
+        assert_eq!(game.qualities, [None, None, Some(MoveQuality::Good)]);
+
+        // Leaving the game starts a new one
+        bar.update(&history(&["e4", "c5"]), &MoveAnnotations::default());
+        assert_eq!(bar.game.as_ref().unwrap().history.len(), 3);
+    }
+}
@@ -0,0 +1,170 @@
+//! The simultaneous exhibition window, where an [exhibition](crate::simul) is started and its
+//! games are shown as a grid of miniature boards, which are clicked to put that game on the board.
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::{ChessBoard, GameCommand, PieceColor, BOARD_SIZE};
+use crate::report::piece_symbol;
+use crate::simul::{outcome, Simul, MAX_BOARDS};
+
+use super::engine::GameSetup;
+use super::locale::Localisation;
+
+/// The width of each miniature board.
+const MINIATURE_SIZE: f32 = 96.0;
+
+/// The number of miniature boards in each row of the grid.
+const GRID_COLUMNS: usize = 4;
+
+const LIGHT_SQUARE: egui::Color32 = egui::Color32::from_rgb(240, 217, 181);
+const DARK_SQUARE: egui::Color32 = egui::Color32::from_rgb(181, 136, 99);
+
+#[derive(Resource, Debug)]
+pub(super) struct SimulSetup {
+    /// The number of games to play.
+    count: usize,
+    /// The color the player plays.
+    color: PieceColor,
+}
+
+impl Default for SimulSetup {
+    fn default() -> Self {
+        SimulSetup {
+            count: 4,
+            color: PieceColor::White,
+        }
+    }
+}
+
+/// Draws the position on the given board from the given side as a miniature, outlined when
+/// it is the game on the board, and returns whether it was clicked.
+fn miniature(ui: &mut egui::Ui, board: &ChessBoard, side: PieceColor, focused: bool) -> bool {
+    let (rect, response) = ui.allocate_exact_size(
+        egui::vec2(MINIATURE_SIZE, MINIATURE_SIZE),
+        egui::Sense::click(),
+    );
+    let painter = ui.painter_at(rect);
+    let square = MINIATURE_SIZE / BOARD_SIZE as f32;
+    let placement = *board.to_fen().piece_placement();
+    for (row, squares) in placement.iter().enumerate() {
+        for (column, piece) in squares.iter().enumerate() {
+            let (x, y) = match side {
+                PieceColor::White => (column, row),
+                PieceColor::Black => (BOARD_SIZE - 1 - column, BOARD_SIZE - 1 - row),
+            };
+            let square_rect = egui::Rect::from_min_size(
+                rect.min + egui::vec2(x as f32 * square, y as f32 * square),
+                egui::vec2(square, square),
+            );
+            let color = match (row + column).is_multiple_of(2) {
+                true => LIGHT_SQUARE,
+                false => DARK_SQUARE,
+            };
+            painter.rect_filled(square_rect, 0.0, color);
+            if let Some((piece_color, piece_type)) = piece {
+                painter.text(
+                    square_rect.center(),
+                    egui::Align2::CENTER_CENTER,
+                    piece_symbol(piece_color, piece_type),
+                    egui::FontId::proportional(square),
+                    egui::Color32::BLACK,
+                );
+            }
+        }
+    }
+    if focused {
+        painter.rect_stroke(
+            rect.shrink(1.0),
+            0.0,
+            egui::Stroke::new(2.0, ui.visuals().selection.bg_fill),
+        );
+    }
+    response.clicked()
+}
+
+/// Returns the line shown under the miniature of the given game.
+fn status(board: &ChessBoard, player: PieceColor, locale: &Localisation) -> String {
+    match outcome(board) {
+        Some(Some(winner)) if winner == player => locale.get("simul-won").to_string(),
+        Some(Some(_)) => locale.get("simul-lost").to_string(),
+        Some(None) => locale.get("simul-drawn").to_string(),
+        None if *board.active_color() == Some(player) => locale.get("simul-your-move").to_string(),
+        None => locale.get("simul-engine-thinking").to_string(),
+    }
+}
+
+/// Shows the simultaneous exhibition window, collapsed until it is opened.
+pub(super) fn simul_window(
+    mut contexts: EguiContexts,
+    mut simul: ResMut<Simul>,
+    mut setup: ResMut<SimulSetup>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    game_setup: GameSetup,
+) {
+    let GameSetup {
+        mut commands,
+        mut engine,
+        mut clock,
+    } = game_setup;
+    egui::Window::new(locale.get("simul"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let Some(player) = simul.player() else {
+                ui.add(
+                    egui::Slider::new(&mut setup.count, 2..=MAX_BOARDS).text(locale.get("boards")),
+                );
+                egui::ComboBox::from_label(locale.get("simul-plays"))
+                    .selected_text(locale.color(&setup.color))
+                    .show_ui(ui, |ui| {
+                        for color in PieceColor::iter() {
+                            ui.selectable_value(&mut setup.color, color, locale.color(&color));
+                        }
+                    });
+                if ui.button(locale.get("start-simul")).clicked() {
+                    // Clocks would run out on the games kept aside, so the games are untimed
+                    clock.set_time_control(None);
+                    engine.set_color(Some(setup.color.opposite()));
+                    let reset_event = simul.start(setup.count, setup.color);
+                    commands.push(GameCommand::LoadGame(Box::new(reset_event)));
+                }
+                return;
+            };
+            let (wins, draws, losses) = simul.score(&board);
+            ui.label(locale.format(
+                "simul-score",
+                &[("wins", &wins), ("draws", &draws), ("losses", &losses)],
+            ));
+            let mut chosen = None;
+            egui::Grid::new("simul_boards").show(ui, |ui| {
+                for (index, game) in simul.boards(&board).into_iter().enumerate() {
+                    ui.vertical(|ui| {
+                        if miniature(ui, game, player, index == simul.focused()) {
+                            chosen = Some(index);
+                        }
+                        ui.label(status(game, player, &locale));
+                    });
+                    if (index + 1) % GRID_COLUMNS == 0 {
+                        ui.end_row();
+                    }
+                }
+            });
+            ui.horizontal(|ui| {
+                let next = simul.next_board(&board);
+                if ui
+                    .add_enabled(next.is_some(), egui::Button::new(locale.get("next-board")))
+                    .clicked()
+                {
+                    chosen = next;
+                }
+                if ui.button(locale.get("stop-simul")).clicked() {
+                    simul.stop();
+                }
+            });
+            if let Some(reset_event) = chosen.and_then(|index| simul.focus(index, &board)) {
+                commands.push(GameCommand::LoadGame(Box::new(reset_event)));
+            }
+        });
+}
@@ -0,0 +1,31 @@
+//! The banner shown over the board while it is [spectated](crate::spectating), and so read only.
+
+use bevy::prelude::Res;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::spectating::{Spectated, Spectating};
+
+use super::locale::Localisation;
+
+/// Shows what the board is spectating at the top of the window.
+pub(super) fn spectating_banner(
+    mut contexts: EguiContexts,
+    spectating: Res<Spectating>,
+    locale: Res<Localisation>,
+) {
+    let Some(spectated) = spectating.get() else {
+        return;
+    };
+    let key = match spectated {
+        Spectated::Broadcast => "spectating-broadcast",
+        Spectated::Network => "spectating-network",
+    };
+    egui::Area::new("spectating-banner")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., 8.))
+        .interactable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(egui::RichText::new(locale.get(key)).strong());
+            });
+        });
+}
@@ -0,0 +1,198 @@
+//! The square info overlay, a tooltip over the square under the cursor giving its name, the
+//! piece on it and the pieces attacking and defending it, for teaching and debugging. While a
+//! piece is dragged over a square it can move to, the tooltip also says what the move would do.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Camera, GlobalTransform, Query, Res, Resource, With};
+use bevy::window::Window;
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::{BoardPosition, ChessBoard, LegalMoves, MoveClassification, PieceColor};
+use crate::exchange::{value, Target};
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+use super::piece::{Dragging, PieceTag};
+use super::{cursor_world_position, MainCamera};
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct SquareInfo {
+    enabled: bool,
+}
+
+/// Returns the pieces of the given color attacking the given square, least valuable first.
+fn attackers(board: &ChessBoard, position: &BoardPosition, color: &PieceColor) -> Vec<Target> {
+    let mut attackers: Vec<Target> = board
+        .attackers(position, color)
+        .into_iter()
+        .filter_map(|attacker| Some((board.get_piece_type(&attacker)?, attacker)))
+        .collect();
+    attackers.sort_by_key(|(piece_type, _)| value(piece_type));
+    attackers
+}
+
+/// Returns the line saying what a move would do, or None if it is a plain move.
+fn move_line(classification: &MoveClassification, locale: &Localisation) -> Option<String> {
+    let kinds: Vec<String> = [
+        (classification.is_castle, "move-castle"),
+        (classification.is_en_passant, "move-en-passant"),
+        (
+            classification.is_capture && !classification.is_en_passant,
+            "move-capture",
+        ),
+        (classification.is_promotion, "move-promotion"),
+        (classification.gives_check, "move-check"),
+    ]
+    .into_iter()
+    .filter(|(applies, _)| *applies)
+    .map(|(_, key)| locale.get(key).to_string())
+    .collect();
+    (!kinds.is_empty()).then(|| locale.format("square-move", &[("kinds", &kinds.join(", "))]))
+}
+
+/// Returns the lines of the tooltip of the given square.
+fn tooltip_lines(
+    board: &ChessBoard,
+    position: &BoardPosition,
+    locale: &Localisation,
+) -> Vec<String> {
+    let mut lines = vec![position.to_algebraic()];
+    let piece = board
+        .get_piece_color(position)
+        .zip(board.get_piece_type(position));
+    if let Some((color, piece_type)) = piece {
+        lines.push(locale.format(
+            "square-piece",
+            &[
+                ("color", &locale.color(&color)),
+                ("piece", &locale.piece(&piece_type)),
+            ],
+        ));
+    }
+    for color in PieceColor::iter() {
+        let attackers = attackers(board, position, &color);
+        if attackers.is_empty() {
+            continue;
+        }
+        let pieces = attackers
+            .iter()
+            .map(|(piece_type, attacker)| {
+                locale.format(
+                    "piece-on",
+                    &[
+                        ("piece", &locale.piece(piece_type)),
+                        ("square", &attacker.to_algebraic()),
+                    ],
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        lines.push(match piece {
+            Some((piece_color, _)) if piece_color == color => {
+                locale.format("square-defended-by", &[("pieces", &pieces)])
+            }
+            Some(_) => locale.format("square-attacked-by", &[("pieces", &pieces)]),
+            None => locale.format(
+                "square-covered-by",
+                &[("color", &locale.color(&color)), ("pieces", &pieces)],
+            ),
+        });
+    }
+    lines
+}
+
+/// The window, camera and board properties which find the square under the cursor.
+#[derive(SystemParam)]
+pub(super) struct CursorSquare<'w, 's> {
+    windows: Query<'w, 's, &'static Window>,
+    camera: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<MainCamera>>,
+    properties: Res<'w, BoardProperties>,
+}
+
+/// Shows the tooltip of the square under the cursor, if the overlay is enabled and the cursor is
+/// not over a window.
+pub(super) fn square_tooltip(
+    mut contexts: EguiContexts,
+    info: Res<SquareInfo>,
+    cursor: CursorSquare,
+    piece_query: Query<(&BoardPosition, &Dragging), With<PieceTag>>,
+    board: Res<ChessBoard>,
+    legal_moves: Res<LegalMoves>,
+    locale: Res<Localisation>,
+) {
+    if !info.enabled {
+        return;
+    }
+    let CursorSquare {
+        windows,
+        camera,
+        properties,
+    } = cursor;
+    let ctx = contexts.ctx_mut();
+    if ctx.is_pointer_over_area() {
+        return;
+    }
+    let (camera, camera_transform) = camera.single();
+    let Some(position) = windows
+        .get_single()
+        .ok()
+        .and_then(|window| cursor_world_position(window, camera, camera_transform))
+        .and_then(|world_position| properties.transform_to_position(&world_position))
+    else {
+        return;
+    };
+    let dragged_move = piece_query
+        .iter()
+        .find(|(_, dragging)| dragging.get())
+        .and_then(|(from, _)| {
+            legal_moves
+                .moves_from(from)
+                .find(|piece_move| *piece_move.to() == position)
+        });
+    let move_line =
+        dragged_move.and_then(|piece_move| move_line(&board.classify_move(piece_move), &locale));
+    egui::show_tooltip_at_pointer(ctx, egui::Id::new("square-info"), |ui| {
+        for line in tooltip_lines(&board, &position, &locale)
+            .into_iter()
+            .chain(move_line)
+        {
+            ui.label(line);
+        }
+    });
+}
+
+/// Shows the setting enabling the overlay.
+pub(super) fn settings_ui(ui: &mut egui::Ui, info: &mut SquareInfo, locale: &Localisation) {
+    ui.checkbox(&mut info.enabled, locale.get("square-info"))
+        .on_hover_text(locale.get("square-info-hint"));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_tooltip_lines() {
+        let locale = Localisation::default();
+        let board =
+            ChessBoard::with_position(&Fen::from_string("4k3/8/8/3p4/8/2N5/8/3RK3 w - - 0 1"));
+        let d5 = BoardPosition::from_algebraic("d5").unwrap();
+        assert_eq!(
+            tooltip_lines(&board, &d5, &locale),
+            vec!["d5", "Black pawn", "Attacked by knight on c3, rook on d1"]
+        );
+
+        let e4 = BoardPosition::from_algebraic("e4").unwrap();
+        assert_eq!(
+            tooltip_lines(&board, &e4, &locale),
+            vec![
+                "e4",
+                "Covered by White: knight on c3",
+                "Covered by Black: pawn on d5",
+            ]
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! The status bar along the bottom of the window, which shows whether the game is still in the
+//! engine's [opening book](crate::book).
+//!
+//! Unlike the opening named under the move list, which is found from the positions the
+//! [ECO](crate::eco) classification knows, the game is in book while every move played so far is
+//! one the book has and the position reached still has book moves. Once either fails the game
+//! has left the book, and the tooltip names the last book move played.
+
+use bevy::prelude::{DetectChanges, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, PieceColor, Variant};
+use crate::engine::Engine;
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+
+use super::locale::Localisation;
+
+/// Where the game stands against the opening book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum BookStatus {
+    /// There is no book, or the game did not start from a position in it.
+    #[default]
+    Unknown,
+    /// Every move played was a book move, and there are book moves to play.
+    InBook,
+    /// The game left the book after the given number of book moves.
+    Left { book_moves: usize },
+}
+
+/// Returns whether two moves are the same, ignoring whether they were found to give check.
+fn same_move(first: &Move, second: &Move) -> bool {
+    first.from() == second.from()
+        && first.to() == second.to()
+        && first.promotion() == second.promotion()
+}
+
+/// Returns where the game of the given positions and moves stands against the book whose moves
+/// in a position are given.
+fn book_status(
+    positions: &[Fen],
+    moves: &[Move],
+    book_moves: impl Fn(&ChessBoard) -> Vec<Move>,
+) -> BookStatus {
+    for (ply, fen) in positions.iter().enumerate() {
+        let book = book_moves(&ChessBoard::with_position(fen));
+        if book.is_empty() {
+            return match ply {
+                0 => BookStatus::Unknown,
+                _ => BookStatus::Left { book_moves: ply },
+            };
+        }
+        match moves.get(ply) {
+            None => return BookStatus::InBook,
+            Some(played) if !book.iter().any(|book| same_move(book, played)) => {
+                return BookStatus::Left { book_moves: ply }
+            }
+            Some(_) => {}
+        }
+    }
+    BookStatus::InBook
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct StatusBar {
+    book: BookStatus,
+}
+
+/// Finds where the game stands against the book whenever a move is made or taken back.
+pub(super) fn update_book_status(
+    mut status_bar: ResMut<StatusBar>,
+    history: Res<PositionHistory>,
+    board: Res<ChessBoard>,
+    engine: Res<Engine>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+    // The book is only for standard chess, and the positions are of the variant being played
+    if *board.variant() != Variant::Standard {
+        status_bar.book = BookStatus::Unknown;
+        return;
+    }
+    status_bar.book = book_status(history.positions(), history.moves(), |board| {
+        engine.book_moves(board)
+    });
+}
+
+/// Returns the text of the last of the given number of moves of the game, numbered as in the
+/// position it was played in, e.g. "4. Bxc6".
+fn move_label(positions: &[Fen], moves: &[Move], count: usize) -> Option<String> {
+    let ply = count.checked_sub(1)?;
+    let (fen, piece_move) = (positions.get(ply)?, moves.get(ply)?);
+    let dots = match fen.active_color() {
+        PieceColor::White => ".",
+        PieceColor::Black => "...",
+    };
+    Some(format!(
+        "{}{} {}",
+        fen.fullmove_number(),
+        dots,
+        piece_move.as_algebraic()
+    ))
+}
+
+/// Shows the status bar.
+pub(super) fn status_bar(
+    mut contexts: EguiContexts,
+    status_bar: Res<StatusBar>,
+    history: Res<PositionHistory>,
+    locale: Res<Localisation>,
+) {
+    if status_bar.book == BookStatus::Unknown {
+        return;
+    }
+    egui::TopBottomPanel::bottom("status_bar").show(contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| match status_bar.book {
+            BookStatus::InBook => {
+                ui.label(locale.get("in-book"))
+                    .on_hover_text(locale.get("in-book-hint"));
+            }
+            BookStatus::Left { book_moves } => {
+                let hint = match move_label(history.positions(), history.moves(), book_moves) {
+                    Some(last) => locale.format("left-book-hint", &[("move", &last)]),
+                    None => locale.get("left-book-at-start").to_string(),
+                };
+                ui.colored_label(
+                    egui::Color32::from_rgb(230, 140, 20),
+                    locale.get("left-book"),
+                )
+                .on_hover_text(hint);
+            }
+            BookStatus::Unknown => {}
+        });
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_book_status() {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut positions = vec![board.to_fen()];
+        let mut moves = Vec::new();
+        for text in ["e4", "e5", "Qh5"] {
+            board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+            moves.push(*board.past_moves().last().unwrap());
+            positions.push(board.to_fen());
+        }
+        // The book only knows 1. e4 e5 2. Nf3
+        let book = |board: &ChessBoard| {
+            let fen = board.to_fen().to_string();
+            positions
+                .iter()
+                .position(|position| position.to_string() == fen)
+                .and_then(|ply| ["e4", "e5", "Nf3"].get(ply))
+                .and_then(|text| Move::from_algebraic(text, board))
+                .into_iter()
+                .collect()
+        };
+        assert_eq!(
+            book_status(&positions[..3], &moves[..2], book),
+            BookStatus::InBook
+        );
+        assert_eq!(
+            book_status(&positions, &moves, book),
+            BookStatus::Left { book_moves: 2 }
+        );
+        assert_eq!(
+            move_label(&positions, &moves, 2).as_deref(),
+            Some("1... e5")
+        );
+        assert_eq!(move_label(&positions, &moves, 0), None);
+        assert_eq!(
+            book_status(&positions, &moves, |_| Vec::new()),
+            BookStatus::Unknown
+        );
+    }
+}
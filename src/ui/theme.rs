@@ -0,0 +1,244 @@
+//! The theme, which chooses the colors of the square highlights from a [HighlightPalette], how
+//! the pieces are drawn and the [user theme](super::user_theme) in use, if any.
+//!
+//! Besides the standard palette there are palettes for each common form of colour blindness,
+//! built from colors which stay distinct from each other and from the squares for that form.
+
+use bevy::prelude::{Color, Resource};
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::chess_board::PieceColor;
+
+use super::locale::Localisation;
+use super::user_theme::{UserThemes, THEMES_DIRECTORY};
+
+/// How much the highlights are darkened on dark squares, for palettes without their own dark colors.
+const DARK_SQUARE_SHADE: f32 = 0.75;
+
+/// The sets of highlight colors which can be selected.
+#[derive(Clone, Copy, Debug, Default, EnumIter, PartialEq, Eq)]
+pub(super) enum HighlightPalette {
+    #[default]
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+impl HighlightPalette {
+    /// Returns the key of the name of the palette.
+    fn label(&self) -> &'static str {
+        match self {
+            HighlightPalette::Standard => "palette-standard",
+            HighlightPalette::Deuteranopia => "palette-deuteranopia",
+            HighlightPalette::Protanopia => "palette-protanopia",
+            HighlightPalette::Tritanopia => "palette-tritanopia",
+        }
+    }
+}
+
+/// The ways the pieces can be drawn.
+#[derive(Clone, Copy, Debug, Default, EnumIter, PartialEq, Eq)]
+pub(super) enum PieceStyle {
+    /// The pieces of the sprite sheet.
+    #[default]
+    Sprites,
+    /// The vector piece set, rasterised at the current scale.
+    Vector,
+}
+
+impl PieceStyle {
+    /// Returns the key of the name of the style.
+    fn label(&self) -> &'static str {
+        match self {
+            PieceStyle::Sprites => "piece-style-sprites",
+            PieceStyle::Vector => "piece-style-vector",
+        }
+    }
+}
+
+/// The kinds of square highlight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Highlight {
+    /// A square of the last move.
+    LastMove,
+    /// The square of a king in check.
+    Check,
+    /// A legal move of the piece the cursor has rested over.
+    Preview,
+    /// A legal move of the dragged piece.
+    ValidMove,
+    /// A square of a queued premove.
+    Premove,
+}
+
+/// The selected theme.
+#[derive(Resource, Debug, Default)]
+pub(super) struct Theme {
+    palette: HighlightPalette,
+    piece_style: PieceStyle,
+    /// The index of the user theme in use, if any.
+    user_theme: Option<usize>,
+}
+
+impl Theme {
+    pub(super) fn user_theme(&self) -> Option<usize> {
+        self.user_theme
+    }
+
+    pub(super) fn piece_style(&self) -> PieceStyle {
+        self.piece_style
+    }
+
+    /// Returns the color of the given highlight on a square of the given color.
+    pub(super) fn highlight_color(&self, highlight: Highlight, square: &PieceColor) -> Color {
+        let shade = |color: Color| match square {
+            PieceColor::White => color,
+            PieceColor::Black => Color::rgb(
+                color.r() * DARK_SQUARE_SHADE,
+                color.g() * DARK_SQUARE_SHADE,
+                color.b() * DARK_SQUARE_SHADE,
+            ),
+        };
+        match (self.palette, highlight) {
+            (HighlightPalette::Standard, Highlight::ValidMove) => match square {
+                PieceColor::White => Color::AQUAMARINE,
+                PieceColor::Black => Color::TEAL,
+            },
+            (HighlightPalette::Standard, Highlight::Preview) => match square {
+                PieceColor::White => Color::rgb(0.85, 1., 0.95),
+                PieceColor::Black => Color::rgb(0.45, 0.6, 0.6),
+            },
+            (HighlightPalette::Standard, Highlight::LastMove) => shade(Color::rgb(0.95, 0.9, 0.5)),
+            (HighlightPalette::Standard, Highlight::Check) => shade(Color::rgb(0.95, 0.3, 0.3)),
+            (HighlightPalette::Standard, Highlight::Premove) => shade(Color::rgb(0.95, 0.65, 0.45)),
+            // Blue and yellow stay distinct without red-green vision
+            (
+                HighlightPalette::Deuteranopia | HighlightPalette::Protanopia,
+                Highlight::ValidMove,
+            ) => shade(Color::rgb(0.34, 0.71, 0.91)),
+            (HighlightPalette::Deuteranopia | HighlightPalette::Protanopia, Highlight::Preview) => {
+                shade(Color::rgb(0.75, 0.88, 0.96))
+            }
+            (
+                HighlightPalette::Deuteranopia | HighlightPalette::Protanopia,
+                Highlight::LastMove,
+            ) => shade(Color::rgb(0.94, 0.89, 0.26)),
+            (HighlightPalette::Deuteranopia | HighlightPalette::Protanopia, Highlight::Premove) => {
+                shade(Color::rgb(0.6, 0.55, 0.85))
+            }
+            (HighlightPalette::Deuteranopia, Highlight::Check) => shade(Color::rgb(0.84, 0.37, 0.)),
+            // Red appears dark without red cones, so check is shown in a lighter reddish purple
+            (HighlightPalette::Protanopia, Highlight::Check) => shade(Color::rgb(0.8, 0.47, 0.65)),
+            // Red and cyan stay distinct without blue-yellow vision
+            (HighlightPalette::Tritanopia, Highlight::ValidMove) => {
+                shade(Color::rgb(0.2, 0.75, 0.75))
+            }
+            (HighlightPalette::Tritanopia, Highlight::Preview) => shade(Color::rgb(0.7, 0.9, 0.9)),
+            (HighlightPalette::Tritanopia, Highlight::LastMove) => {
+                shade(Color::rgb(0.8, 0.47, 0.65))
+            }
+            (HighlightPalette::Tritanopia, Highlight::Check) => shade(Color::rgb(0.85, 0.1, 0.1)),
+            (HighlightPalette::Tritanopia, Highlight::Premove) => {
+                shade(Color::rgb(0.95, 0.7, 0.75))
+            }
+        }
+    }
+}
+
+/// Draws the theme settings controls.
+pub(super) fn settings_ui(
+    ui: &mut egui::Ui,
+    theme: &mut Theme,
+    user_themes: &UserThemes,
+    locale: &Localisation,
+) {
+    ui.collapsing(locale.get("theme"), |ui| {
+        egui::ComboBox::from_label(locale.get("highlight-palette"))
+            .selected_text(locale.get(theme.palette.label()))
+            .show_ui(ui, |ui| {
+                for palette in HighlightPalette::iter() {
+                    ui.selectable_value(&mut theme.palette, palette, locale.get(palette.label()));
+                }
+            });
+        egui::ComboBox::from_label(locale.get("piece-style"))
+            .selected_text(locale.get(theme.piece_style.label()))
+            .show_ui(ui, |ui| {
+                for style in PieceStyle::iter() {
+                    ui.selectable_value(&mut theme.piece_style, style, locale.get(style.label()));
+                }
+            });
+        let themes = user_themes.themes();
+        let selected = theme
+            .user_theme
+            .and_then(|index| themes.get(index))
+            .map_or(locale.get("built-in-theme"), |user_theme| user_theme.name());
+        let mut user_theme = theme.user_theme;
+        egui::ComboBox::from_label(locale.get("user-theme"))
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut user_theme, None, locale.get("built-in-theme"));
+                for (index, option) in themes.iter().enumerate() {
+                    ui.selectable_value(&mut user_theme, Some(index), option.name());
+                }
+            })
+            .response
+            .on_hover_text(locale.format("user-theme-hint", &[("directory", &THEMES_DIRECTORY)]));
+        if user_theme != theme.user_theme {
+            theme.user_theme = user_theme;
+            // A theme's piece set is drawn as vector pieces
+            if user_theme
+                .and_then(|index| themes.get(index))
+                .is_some_and(|user_theme| user_theme.has_pieces())
+            {
+                theme.piece_style = PieceStyle::Vector;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_colors_are_distinct() {
+        let highlights = [
+            Highlight::LastMove,
+            Highlight::Check,
+            Highlight::Preview,
+            Highlight::ValidMove,
+            Highlight::Premove,
+        ];
+        for palette in HighlightPalette::iter() {
+            let theme = Theme {
+                palette,
+                ..Default::default()
+            };
+            for square in [PieceColor::White, PieceColor::Black] {
+                let colors: Vec<Color> = highlights
+                    .iter()
+                    .map(|highlight| theme.highlight_color(*highlight, &square))
+                    .collect();
+                for (index, color) in colors.iter().enumerate() {
+                    assert!(!colors[index + 1..].contains(color), "{:?}", palette);
+                }
+            }
+        }
+        // Dark squares get darker highlights
+        let theme = Theme {
+            palette: HighlightPalette::Tritanopia,
+            ..Default::default()
+        };
+        assert_eq!(
+            theme.highlight_color(Highlight::Check, &PieceColor::Black),
+            Color::rgb(
+                0.85 * DARK_SQUARE_SHADE,
+                0.1 * DARK_SQUARE_SHADE,
+                0.1 * DARK_SQUARE_SHADE
+            )
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! The time scramble practice, which trains fast play: each scramble loads a random [sharp
+//! position](crate::start_position::generate_sharp) in which the player has only [PLAYER_TIME] on
+//! their clock, against a modest engine which moves as soon as it has searched.
+//!
+//! The engine plays the side to move, so its instant first move starts the clock and the player is
+//! under time pressure from their first move. The engine and clock settings the practice replaces
+//! are put back when it is stopped.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::{DetectChanges, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, PieceColor, ResetBoardEvent};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::start_position::generate_sharp;
+
+use super::locale::Localisation;
+
+/// The time the player has for each scramble.
+const PLAYER_TIME: Duration = Duration::from_secs(30);
+
+/// The time the engine has for each scramble, which it hardly uses.
+const ENGINE_TIME: Duration = Duration::from_secs(60);
+
+/// The depth the engine searches to.
+const ENGINE_DEPTH: u32 = 2;
+
+/// The engine and clock settings in place before the practice started.
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    color: Option<PieceColor>,
+    depth: u32,
+    min_think_time: Duration,
+    adaptive: bool,
+    time_controls: Option<[Duration; 2]>,
+}
+
+impl Settings {
+    fn take(engine: &Engine, clock: &ChessClock) -> Self {
+        Settings {
+            color: *engine.color(),
+            depth: engine.depth(),
+            min_think_time: engine.min_think_time(),
+            adaptive: engine.adaptive().enabled,
+            time_controls: *clock.time_controls(),
+        }
+    }
+
+    fn restore(&self, engine: &mut Engine, clock: &mut ChessClock) {
+        engine.set_color(self.color);
+        engine.set_depth(self.depth);
+        engine.set_min_think_time(self.min_think_time);
+        engine.adaptive_mut().enabled = self.adaptive;
+        clock.set_time_controls(self.time_controls);
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct TimeScramble {
+    /// The settings to put back, which are kept while the practice is running.
+    settings: Option<Settings>,
+    /// The color the player has in the current scramble, until it is over.
+    player: Option<PieceColor>,
+    /// The scrambles won, drawn and lost since the practice started.
+    results: [u32; 3],
+    /// The locale key of why the last scramble could not be set up.
+    error: Option<&'static str>,
+}
+
+impl TimeScramble {
+    /// Sets up the scramble of the given seed, starting the practice if it is not running.
+    fn start(
+        &mut self,
+        seed: u64,
+        engine: &mut Engine,
+        clock: &mut ChessClock,
+        commands: &mut GameCommands,
+    ) {
+        let Some(fen) = generate_sharp(seed) else {
+            self.error = Some("time-scramble-not-found");
+            return;
+        };
+        if self.settings.is_none() {
+            self.settings = Some(Settings::take(engine, clock));
+            self.results = [0; 3];
+        }
+        let engine_color = *fen.active_color();
+        let player = engine_color.opposite();
+        engine.set_color(Some(engine_color));
+        engine.set_depth(ENGINE_DEPTH);
+        engine.set_min_think_time(Duration::ZERO);
+        // The engine plays at one strength, so the scrambles can be compared
+        engine.adaptive_mut().enabled = false;
+        let mut time_controls = [ENGINE_TIME; 2];
+        time_controls[player as usize] = PLAYER_TIME;
+        clock.set_time_controls(Some(time_controls));
+        commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(fen))));
+        self.player = Some(player);
+        self.error = None;
+    }
+
+    /// Stops the practice, putting back the settings it replaced.
+    fn stop(&mut self, engine: &mut Engine, clock: &mut ChessClock) {
+        if let Some(settings) = self.settings.take() {
+            settings.restore(engine, clock);
+        }
+        self.player = None;
+    }
+
+    /// Counts the result of the current scramble if the game on the given board is over.
+    fn score(&mut self, board: &ChessBoard) {
+        let Some(player) = self.player else {
+            return;
+        };
+        if board.game_end_status().is_none() {
+            return;
+        }
+        let result = match board.winner() {
+            Some(winner) if *winner == player => 0,
+            None => 1,
+            Some(_) => 2,
+        };
+        self.results[result] += 1;
+        self.player = None;
+    }
+}
+
+/// Shows the time scramble window, collapsed until it is opened, and counts the result of each
+/// scramble as it ends.
+pub(super) fn time_scramble_window(
+    mut contexts: EguiContexts,
+    mut scramble: ResMut<TimeScramble>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+    mut commands: ResMut<GameCommands>,
+) {
+    if board.is_changed() {
+        scramble.score(&board);
+    }
+    egui::Window::new(locale.get("time-scramble"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            ui.label(locale.format("time-scramble-hint", &[("seconds", &PLAYER_TIME.as_secs())]));
+            ui.horizontal(|ui| {
+                let running = scramble.settings.is_some();
+                let start = if running {
+                    "next-time-scramble"
+                } else {
+                    "start-time-scramble"
+                };
+                if ui.button(locale.get(start)).clicked() {
+                    let seed = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |time| time.as_nanos() as u64);
+                    scramble.start(seed, &mut engine, &mut clock, &mut commands);
+                }
+                if ui
+                    .add_enabled(running, egui::Button::new(locale.get("stop-time-scramble")))
+                    .clicked()
+                {
+                    scramble.stop(&mut engine, &mut clock);
+                }
+            });
+            if scramble.settings.is_some() {
+                let [won, drawn, lost] = scramble.results;
+                ui.label(locale.format(
+                    "time-scramble-results",
+                    &[("won", &won), ("drawn", &drawn), ("lost", &lost)],
+                ));
+            }
+            if let Some(error) = scramble.error {
+                ui.label(locale.get(error));
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_scramble() {
+        let mut scramble = TimeScramble::default();
+        let mut engine = Engine::default();
+        let mut clock = ChessClock::new(Some(Duration::from_secs(300)));
+        let mut commands = GameCommands::default();
+        let before = Settings::take(&engine, &clock);
+
+        scramble.start(7, &mut engine, &mut clock, &mut commands);
+        let player = scramble.player.unwrap();
+        assert_eq!(*engine.color(), Some(player.opposite()));
+        assert_eq!(engine.min_think_time(), Duration::ZERO);
+        assert_eq!(clock.remaining(&player), Some(PLAYER_TIME));
+        assert_eq!(clock.remaining(&player.opposite()), Some(ENGINE_TIME));
+
+        // A second scramble keeps the settings from before the first
+        scramble.start(8, &mut engine, &mut clock, &mut commands);
+        scramble.stop(&mut engine, &mut clock);
+        assert_eq!(*engine.color(), before.color);
+        assert_eq!(engine.depth(), before.depth);
+        assert_eq!(engine.min_think_time(), before.min_think_time);
+        assert_eq!(*clock.time_controls(), before.time_controls);
+        assert!(scramble.player.is_none());
+    }
+}
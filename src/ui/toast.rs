@@ -0,0 +1,158 @@
+//! Toasts, which show each [Notification] in the corner of the window for a few seconds, with a
+//! color for how serious it is. A toast is dismissed early by clicking it.
+
+use std::time::Duration;
+
+use bevy::prelude::{EventReader, EventWriter, Res, ResMut, Resource};
+use bevy::time::Time;
+use bevy::window::RequestRedraw;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::notification::{Argument, Notification, Severity};
+
+use super::locale::Localisation;
+
+/// The most toasts shown at once, where the oldest are dismissed to make room for new ones.
+const MAX_TOASTS: usize = 4;
+
+/// Returns how long a toast of the given severity is shown for.
+fn duration(severity: Severity) -> Duration {
+    match severity {
+        Severity::Info => Duration::from_secs(3),
+        Severity::Warning => Duration::from_secs(5),
+        Severity::Error => Duration::from_secs(8),
+    }
+}
+
+/// Returns the color of the text of a toast of the given severity.
+fn color(severity: Severity) -> Option<egui::Color32> {
+    match severity {
+        Severity::Info => None,
+        Severity::Warning => Some(egui::Color32::from_rgb(255, 165, 0)),
+        Severity::Error => Some(egui::Color32::RED),
+    }
+}
+
+/// Returns the message of the given notification in the player's language.
+fn message(notification: &Notification, locale: &Localisation) -> String {
+    let args: Vec<(&str, String)> = notification
+        .args()
+        .iter()
+        .map(|(name, arg)| {
+            let value = match arg {
+                Argument::Text(text) => text.clone(),
+                Argument::Color(color) => locale.color(color).to_string(),
+                Argument::Move(text) => locale.san(text),
+            };
+            (*name, value)
+        })
+        .collect();
+    let args: Vec<(&str, &dyn ToString)> = args
+        .iter()
+        .map(|(name, value)| (*name, value as &dyn ToString))
+        .collect();
+    locale.format(notification.key(), &args)
+}
+
+#[derive(Debug, Clone)]
+struct Toast {
+    text: String,
+    severity: Severity,
+    remaining: Duration,
+}
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct Toasts(Vec<Toast>);
+
+impl Toasts {
+    fn push(&mut self, text: String, severity: Severity) {
+        // A message repeated while it is still shown only restarts its toast
+        self.0.retain(|toast| toast.text != text);
+        self.0.push(Toast {
+            text,
+            severity,
+            remaining: duration(severity),
+        });
+        let excess = self.0.len().saturating_sub(MAX_TOASTS);
+        self.0.drain(..excess);
+    }
+
+    /// Runs down the time left on each toast, dismissing those which have run out.
+    fn tick(&mut self, delta: Duration) {
+        for toast in &mut self.0 {
+            toast.remaining = toast.remaining.saturating_sub(delta);
+        }
+        self.0.retain(|toast| !toast.remaining.is_zero());
+    }
+}
+
+/// Shows a toast for each notification sent, newest at the bottom right.
+pub(super) fn show_toasts(
+    mut contexts: EguiContexts,
+    mut notifications: EventReader<Notification>,
+    mut toasts: ResMut<Toasts>,
+    time: Res<Time>,
+    locale: Res<Localisation>,
+    mut redraw_events: EventWriter<RequestRedraw>,
+) {
+    toasts.tick(time.delta());
+    for notification in notifications.iter() {
+        toasts.push(message(notification, &locale), notification.severity());
+    }
+    if toasts.0.is_empty() {
+        return;
+    }
+    // The toasts are only dismissed while the app updates
+    redraw_events.send(RequestRedraw);
+    let mut dismissed = None;
+    egui::Area::new("toasts")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .show(contexts.ctx_mut(), |ui| {
+            for (index, toast) in toasts.0.iter().enumerate() {
+                let mut text = egui::RichText::new(&toast.text);
+                if let Some(color) = color(toast.severity) {
+                    text = text.color(color).strong();
+                }
+                let response = egui::Frame::popup(ui.style())
+                    .show(ui, |ui| {
+                        ui.add(egui::Label::new(text).sense(egui::Sense::click()))
+                    })
+                    .inner;
+                if response.clicked() {
+                    dismissed = Some(index);
+                }
+            }
+        });
+    if let Some(index) = dismissed {
+        toasts.0.remove(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::PieceColor;
+
+    use super::*;
+
+    #[test]
+    fn test_toasts() {
+        let locale = Localisation::default();
+        let notification = Notification::info("offers-draw")
+            .with_arg("player", Argument::Color(PieceColor::Black));
+        assert_eq!(message(&notification, &locale), "Black offers a draw");
+
+        let mut toasts = Toasts::default();
+        toasts.push("Connection lost".to_string(), Severity::Error);
+        toasts.push("Draw declined".to_string(), Severity::Info);
+        toasts.tick(duration(Severity::Info));
+        let texts: Vec<&str> = toasts.0.iter().map(|toast| toast.text.as_str()).collect();
+        assert_eq!(texts, vec!["Connection lost"]);
+
+        for number in 0..MAX_TOASTS {
+            toasts.push(number.to_string(), Severity::Warning);
+        }
+        toasts.push("0".to_string(), Severity::Warning);
+        let texts: Vec<&str> = toasts.0.iter().map(|toast| toast.text.as_str()).collect();
+        assert_eq!(texts, vec!["1", "2", "3", "0"]);
+    }
+}
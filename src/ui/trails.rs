@@ -0,0 +1,257 @@
+//! The movement trails, turned on in the settings, which are drawn while a [replay](crate::replay)
+//! is watched or a game is [spectated](crate::spectating). Each piece on the board trails a line
+//! back along its last moves, in the colour of its side, fading the older the move. A castling
+//! rook trails its move too, and a captured piece's trail goes with it.
+
+use std::collections::HashMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{
+    default, Color, Commands, Component, DetectChanges, Entity, Local, Quat, Query, Res, Resource,
+    Transform, Vec2, With,
+};
+use bevy::sprite::{Sprite, SpriteBundle};
+use bevy_egui::egui;
+use strum::IntoEnumIterator;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, Variant};
+use crate::fen::Fen;
+use crate::history::PositionHistory;
+use crate::replay::Playback;
+use crate::spectating::Spectating;
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+
+/// The height of the trails above the squares, below the pieces.
+const TRAIL_Z: f32 = 0.65;
+
+/// The width of the trails, as a fraction of the square.
+const TRAIL_WIDTH: f32 = 0.12;
+
+/// The opacity of the newest part of a trail.
+const TRAIL_ALPHA: f32 = 0.7;
+
+/// The most moves a trail can be set to follow.
+const MAX_TRAIL_MOVES: usize = 8;
+
+/// A move followed by a trail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Segment {
+    from: BoardPosition,
+    to: BoardPosition,
+    color: PieceColor,
+    /// The number of moves the piece has made since, so the newest move's is 0.
+    age: usize,
+}
+
+/// Returns the last moves, up to the given number, of each piece left on the board after the
+/// given moves are played from the given position.
+fn trails(start: &Fen, variant: Variant, moves: &[Move], length: usize) -> Vec<Segment> {
+    let mut board = ChessBoard::with_position(start).with_variant(variant);
+    // The moves each piece made to reach its square, by the square it is on
+    let mut paths: HashMap<BoardPosition, Vec<(BoardPosition, BoardPosition)>> = HashMap::new();
+    let mut follow = |from: BoardPosition, to: BoardPosition| {
+        let mut path = paths.remove(&from).unwrap_or_default();
+        path.push((from, to));
+        // A captured piece's path is replaced by its captor's
+        paths.insert(to, path);
+    };
+    for piece_move in moves {
+        let rook_move = board.apply_move(piece_move);
+        follow(*piece_move.from(), *piece_move.to());
+        if let Some((from, to)) = rook_move {
+            follow(from, to);
+        }
+    }
+    paths
+        .into_iter()
+        .filter_map(|(position, path)| Some((board.get_piece_color(&position)?, path)))
+        .flat_map(|(color, path)| {
+            let shown = path.len().min(length);
+            path.into_iter()
+                .rev()
+                .take(shown)
+                .enumerate()
+                .map(move |(age, (from, to))| Segment {
+                    from,
+                    to,
+                    color,
+                    age,
+                })
+        })
+        .collect()
+}
+
+#[derive(Resource, Debug)]
+pub(super) struct PieceTrails {
+    enabled: bool,
+    /// The number of each piece's moves its trail follows.
+    moves: usize,
+    /// The colour of the trails of white's and black's pieces.
+    colors: [[u8; 3]; 2],
+}
+
+impl Default for PieceTrails {
+    fn default() -> Self {
+        PieceTrails {
+            enabled: false,
+            moves: 3,
+            colors: [[240, 200, 60], [60, 140, 230]],
+        }
+    }
+}
+
+impl PieceTrails {
+    /// Returns the colour of the given segment of a trail of the given length.
+    fn color(&self, segment: &Segment, length: usize) -> Color {
+        let [r, g, b] = self.colors[segment.color as usize];
+        let fade = 1.0 - segment.age as f32 / length.max(1) as f32;
+        Color::rgba_u8(r, g, b, (255.0 * TRAIL_ALPHA * fade) as u8)
+    }
+}
+
+#[derive(Component)]
+pub(super) struct TrailTag;
+
+/// The game the trails are drawn from, and the replay or spectated game it is watched through.
+#[derive(SystemParam)]
+pub(super) struct WatchedGame<'w> {
+    history: Res<'w, PositionHistory>,
+    board: Res<'w, ChessBoard>,
+    playback: Option<Res<'w, Playback>>,
+    spectating: Res<'w, Spectating>,
+}
+
+/// Redraws the trails whenever a move is made or the trails or board change, while a replay is
+/// watched or a game is spectated.
+pub(super) fn trail_overlay(
+    query: Query<Entity, With<TrailTag>>,
+    mut commands: Commands,
+    trails_settings: Res<PieceTrails>,
+    watched: WatchedGame,
+    board_properties: Res<BoardProperties>,
+    mut shown: Local<bool>,
+) {
+    let WatchedGame {
+        history,
+        board,
+        playback,
+        spectating,
+    } = watched;
+    let show = trails_settings.enabled && (playback.is_some() || spectating.get().is_some());
+    let changed =
+        history.is_changed() || trails_settings.is_changed() || board_properties.is_changed();
+    if show == *shown && !(show && changed) {
+        return;
+    }
+    *shown = show;
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Some(start) = history.start().filter(|_| show) else {
+        return;
+    };
+    let square_size = board_properties.square_size();
+    let length = trails_settings.moves;
+    for segment in trails(start, *board.variant(), history.moves(), length) {
+        let (from_x, from_y) = board_properties.position_to_transform(&segment.from);
+        let (to_x, to_y) = board_properties.position_to_transform(&segment.to);
+        let line = Vec2::new(to_x - from_x, to_y - from_y);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: trails_settings.color(&segment, length),
+                    custom_size: Some(Vec2::new(line.length(), square_size * TRAIL_WIDTH)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(
+                    (from_x + to_x) / 2.0,
+                    (from_y + to_y) / 2.0,
+                    TRAIL_Z,
+                )
+                .with_rotation(Quat::from_rotation_z(line.y.atan2(line.x))),
+                ..default()
+            },
+            TrailTag,
+        ));
+    }
+}
+
+/// Draws the trail settings.
+pub(super) fn settings_ui(ui: &mut egui::Ui, trails: &mut PieceTrails, locale: &Localisation) {
+    ui.collapsing(locale.get("piece-trails"), |ui| {
+        ui.checkbox(&mut trails.enabled, locale.get("show-piece-trails"))
+            .on_hover_text(locale.get("piece-trails-hint"));
+        ui.add(
+            egui::Slider::new(&mut trails.moves, 1..=MAX_TRAIL_MOVES)
+                .text(locale.get("piece-trail-moves")),
+        );
+        for color in PieceColor::iter() {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgb(&mut trails.colors[color as usize]);
+                ui.label(locale.color(&color));
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(texts: &[&str]) -> Vec<Move> {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        texts
+            .iter()
+            .map(|text| {
+                let piece_move = Move::from_algebraic(text, &board).unwrap();
+                board.apply_move(&piece_move);
+                piece_move
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trails() {
+        let square = |square| BoardPosition::from_algebraic(square).unwrap();
+        let segment = |from, to, color, age| Segment {
+            from: square(from),
+            to: square(to),
+            color,
+            age,
+        };
+        let trails_of = |texts: &[&str], length| {
+            let mut segments = trails(&Fen::default(), Variant::Standard, &moves(texts), length);
+            segments.sort_by_key(|segment| (String::from(segment.from), String::from(segment.to)));
+            segments
+        };
+
+        // The knight's trail follows only its last two moves
+        let raid = ["Nc3", "h5", "Nd5", "Rh6", "Nf4", "Rg6"];
+        assert_eq!(
+            trails_of(&raid, 2),
+            [
+                segment("c3", "d5", PieceColor::White, 1),
+                segment("d5", "f4", PieceColor::White, 0),
+                segment("h6", "g6", PieceColor::Black, 0),
+                segment("h7", "h5", PieceColor::Black, 0),
+                segment("h8", "h6", PieceColor::Black, 1),
+            ]
+        );
+        // Captured pieces take their trails with them
+        let captures = [raid.as_slice(), &["Nxg6", "fxg6"]].concat();
+        assert_eq!(
+            trails_of(&captures, 2),
+            [
+                segment("f7", "g6", PieceColor::Black, 0),
+                segment("h7", "h5", PieceColor::Black, 0),
+            ]
+        );
+        // Castling moves the rook as well as the king
+        let castled = trails_of(&["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "O-O"], 1);
+        assert!(castled.contains(&segment("h1", "f1", PieceColor::White, 0)));
+        assert!(castled.contains(&segment("e1", "g1", PieceColor::White, 0)));
+    }
+}
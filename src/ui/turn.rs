@@ -0,0 +1,161 @@
+//! The turn indicator, a bar in the color of the player to move along their edge of the board,
+//! which is optionally also named in the window title.
+//!
+//! The bar is drawn below the board for white and above it for black, so it follows the board
+//! when it is flipped.
+
+use bevy::prelude::{
+    default, Color, Commands, Component, DetectChanges, Query, Res, Resource, Transform, Vec2,
+    Visibility, With,
+};
+use bevy::sprite::{Sprite, SpriteBundle};
+use bevy_egui::egui;
+
+use crate::chess_board::{ChessBoard, PieceColor, BOARD_SIZE};
+
+use super::board::BoardProperties;
+use super::locale::Localisation;
+
+/// The thickness of the bar as a fraction of a square.
+const BAR_THICKNESS: f32 = 0.125;
+
+/// The settings of the turn indicator.
+#[derive(Resource, Debug)]
+pub(super) struct TurnIndicator {
+    /// Whether the bar is shown beside the board.
+    show_bar: bool,
+    /// Whether the player to move is added to the window title.
+    in_title: bool,
+}
+
+impl Default for TurnIndicator {
+    fn default() -> Self {
+        TurnIndicator {
+            show_bar: true,
+            in_title: false,
+        }
+    }
+}
+
+impl TurnIndicator {
+    /// Returns the given window title with the player to move added, if they are shown there.
+    pub(super) fn title(&self, title: &str, board: &ChessBoard, locale: &Localisation) -> String {
+        match board.active_color() {
+            Some(color) if self.in_title => format!(
+                "{} - {}",
+                title,
+                locale.format("title-to-move", &[("player", &locale.color(color))])
+            ),
+            _ => title.to_string(),
+        }
+    }
+}
+
+#[derive(Component)]
+pub(super) struct TurnBarTag;
+
+/// Returns the centre of the bar on the given player's edge of the board.
+fn bar_position(properties: &BoardProperties, color: &PieceColor) -> Vec2 {
+    let ranks = properties.size().ranks() as f32;
+    let offset = properties.square_size() * (ranks + BAR_THICKNESS * 3.) / 2.;
+    match color {
+        PieceColor::White => properties.center() - Vec2::new(0., offset),
+        PieceColor::Black => properties.center() + Vec2::new(0., offset),
+    }
+}
+
+pub(super) fn setup(mut commands: Commands, properties: Res<BoardProperties>) {
+    let square_size = properties.square_size();
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(
+                    square_size * BOARD_SIZE as f32,
+                    square_size * BAR_THICKNESS,
+                )),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        TurnBarTag,
+    ));
+}
+
+/// Moves the bar to the edge of the player to move, hiding it once the game has ended.
+pub(super) fn turn_bar(
+    mut query: Query<(&mut Transform, &mut Sprite, &mut Visibility), With<TurnBarTag>>,
+    board: Res<ChessBoard>,
+    indicator: Res<TurnIndicator>,
+    properties: Res<BoardProperties>,
+) {
+    if !board.is_changed() && !indicator.is_changed() && !properties.is_changed() {
+        return;
+    }
+    for (mut transform, mut sprite, mut visibility) in query.iter_mut() {
+        match board.active_color() {
+            Some(color) if indicator.show_bar => {
+                *transform =
+                    Transform::from_translation(bar_position(&properties, color).extend(0.));
+                sprite.custom_size = Some(Vec2::new(
+                    properties.square_size() * properties.size().files() as f32,
+                    properties.square_size() * BAR_THICKNESS,
+                ));
+                sprite.color = match color {
+                    PieceColor::White => Color::WHITE,
+                    PieceColor::Black => Color::BLACK,
+                };
+                *visibility = Visibility::Visible;
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// Draws the turn indicator settings.
+pub(super) fn settings_ui(ui: &mut egui::Ui, indicator: &mut TurnIndicator, locale: &Localisation) {
+    ui.checkbox(&mut indicator.show_bar, locale.get("turn-bar"));
+    ui.checkbox(&mut indicator.in_title, locale.get("turn-in-title"));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    #[test]
+    fn test_turn_indicator_title() {
+        let locale = Localisation::default();
+        let mut board = ChessBoard::with_position(&Fen::default());
+        let mut indicator = TurnIndicator::default();
+        assert_eq!(indicator.title("Chess", &board, &locale), "Chess");
+
+        indicator.in_title = true;
+        assert_eq!(
+            indicator.title("Chess", &board, &locale),
+            "Chess - White to move"
+        );
+        board = ChessBoard::with_position(&Fen::from_string(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+        ));
+        assert_eq!(
+            indicator.title("Chess", &board, &locale),
+            "Chess - Black to move"
+        );
+    }
+
+    #[test]
+    fn test_bar_position() {
+        let properties = BoardProperties::default();
+        // The bar sits just outside the board, on each player's side
+        assert_eq!(
+            bar_position(&properties, &PieceColor::White),
+            Vec2::new(0., -335.)
+        );
+        assert_eq!(
+            bar_position(&properties, &PieceColor::Black),
+            Vec2::new(0., 335.)
+        );
+    }
+}
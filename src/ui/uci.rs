@@ -0,0 +1,129 @@
+//! The external engine window, where an [external engine](ExternalEngine) is attached from the
+//! path of its executable and its options are set with a form made from those it declares.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::{EventWriter, Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+
+use crate::engine::{Engine, ExternalEngine, UciOption, UciOptionKind};
+use crate::notification::{Argument, Notification};
+
+use super::locale::Localisation;
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct UciWindowState {
+    /// The path of the engine to attach.
+    path: String,
+    /// The text being typed into each string option, which is sent once the field loses focus.
+    editing: BTreeMap<String, String>,
+}
+
+/// Shows the widget for the given option, returning the value it was set to if it changed, which
+/// is None for a pressed button.
+fn option_widget(
+    ui: &mut egui::Ui,
+    option: &UciOption,
+    value: Option<String>,
+    editing: &mut BTreeMap<String, String>,
+) -> Option<Option<String>> {
+    let value = value.unwrap_or_default();
+    match option.kind() {
+        UciOptionKind::Check { .. } => {
+            let mut checked = value == "true";
+            ui.checkbox(&mut checked, "")
+                .changed()
+                .then(|| Some(checked.to_string()))
+        }
+        UciOptionKind::Spin { min, max, .. } => {
+            let mut number: i64 = value.parse().unwrap_or(*min);
+            ui.add(egui::DragValue::new(&mut number).clamp_range(*min..=*max))
+                .changed()
+                .then(|| Some(number.to_string()))
+        }
+        UciOptionKind::Combo { choices, .. } => {
+            let mut selected = value.clone();
+            egui::ComboBox::from_id_source(option.name())
+                .selected_text(&selected)
+                .show_ui(ui, |ui| {
+                    for choice in choices {
+                        ui.selectable_value(&mut selected, choice.clone(), choice);
+                    }
+                });
+            (selected != value).then_some(Some(selected))
+        }
+        UciOptionKind::Button => ui.button(option.name()).clicked().then_some(None),
+        UciOptionKind::String { .. } => {
+            let text = editing
+                .entry(option.name().to_string())
+                .or_insert_with(|| value.clone());
+            let response = ui.text_edit_singleline(text);
+            if !response.lost_focus() {
+                return None;
+            }
+            let text = editing.remove(option.name()).unwrap_or_default();
+            (text != value).then_some(Some(text))
+        }
+    }
+}
+
+/// Shows the form of the given engine's options, sending each value as it is changed.
+fn options_form(ui: &mut egui::Ui, external: &mut ExternalEngine, state: &mut UciWindowState) {
+    let mut changed = None;
+    egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .show(ui, |ui| {
+            egui::Grid::new("uci_options").striped(true).show(ui, |ui| {
+                for option in external.options() {
+                    ui.label(option.name());
+                    let value = external.value(option);
+                    if let Some(value) = option_widget(ui, option, value, &mut state.editing) {
+                        changed = Some((option.name().to_string(), value));
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    if let Some((name, value)) = changed {
+        external.set_option(&name, value);
+    }
+}
+
+/// Shows the external engine window, collapsed until it is opened.
+pub(super) fn uci_window(
+    mut contexts: EguiContexts,
+    mut engine: ResMut<Engine>,
+    mut state: ResMut<UciWindowState>,
+    mut notifications: EventWriter<Notification>,
+    locale: Res<Localisation>,
+) {
+    egui::Window::new(locale.get("external-engine"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            let Some(external) = engine.external_mut() else {
+                ui.horizontal(|ui| {
+                    ui.label(locale.get("engine-path"));
+                    ui.text_edit_singleline(&mut state.path);
+                });
+                if ui.button(locale.get("attach-engine")).clicked() {
+                    if let Err(error) = engine.attach_external(state.path.trim()) {
+                        notifications.send(
+                            Notification::error("attach-engine-failed")
+                                .with_arg("error", Argument::Text(error)),
+                        );
+                    }
+                }
+                return;
+            };
+            if external.is_ready() {
+                ui.label(external.name().unwrap_or_default());
+                options_form(ui, external, &mut state);
+            } else {
+                ui.label(locale.get("engine-starting"));
+            }
+            if ui.button(locale.get("detach-engine")).clicked() {
+                state.editing.clear();
+                engine.detach_external();
+            }
+        });
+}
@@ -0,0 +1,172 @@
+//! The underpromotion trainer, a window giving [drills](crate::underpromotion) in which the
+//! player must promote a pawn to the one piece that wins, and keeping count of the drills solved
+//! on each theme.
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::{Res, ResMut, Resource};
+use bevy_egui::{egui, EguiContexts};
+use strum::IntoEnumIterator;
+
+use crate::chess_board::{ChessBoard, GameCommand, GameCommands, ResetBoardEvent};
+use crate::clock::ChessClock;
+use crate::engine::Engine;
+use crate::underpromotion::{generate, Drill, Theme};
+
+use super::locale::Localisation;
+
+/// The number of seeds tried for a new drill before telling the player none was found.
+const SEEDS: u64 = 8;
+
+#[derive(Resource, Debug, Default)]
+pub(super) struct UnderpromotionTrainer {
+    drill: Option<Drill>,
+    /// Whether the drill's position has been loaded onto the board.
+    started: bool,
+    /// Whether the drill was solved, once the player has promoted.
+    solved: Option<bool>,
+    /// Whether no drill could be found when one was last asked for.
+    not_found: bool,
+    /// The number of drills solved and attempted on each theme.
+    results: HashMap<Theme, (usize, usize)>,
+}
+
+impl UnderpromotionTrainer {
+    /// Checks the first move made in the drill's position, recording the result.
+    fn update(&mut self, board: &ChessBoard) {
+        let Some(drill) = &self.drill else {
+            return;
+        };
+        if self.solved.is_some() {
+            return;
+        }
+        if !self.started {
+            self.started = board.past_moves().is_empty()
+                && board.to_fen().to_string() == drill.fen().to_string();
+            return;
+        }
+        if let [piece_move] = &board.past_moves()[..] {
+            let solved = drill.is_solved_by(piece_move);
+            let (solved_count, attempted) = self.results.entry(*drill.theme()).or_default();
+            *solved_count += solved as usize;
+            *attempted += 1;
+            self.solved = Some(solved);
+        }
+    }
+}
+
+/// Returns the name of the given theme.
+fn theme_name<'a>(theme: &Theme, locale: &'a Localisation) -> &'a str {
+    match theme {
+        Theme::Knight => locale.get("drill-theme-knight"),
+        Theme::Rook => locale.get("drill-theme-rook"),
+    }
+}
+
+/// Shows the underpromotion trainer, collapsed until it is opened. A new drill is loaded into an
+/// analysis board, and the player's first move in it is taken as their answer.
+pub(super) fn trainer_window(
+    mut contexts: EguiContexts,
+    mut trainer: ResMut<UnderpromotionTrainer>,
+    board: Res<ChessBoard>,
+    locale: Res<Localisation>,
+    mut commands: ResMut<GameCommands>,
+    mut engine: ResMut<Engine>,
+    mut clock: ResMut<ChessClock>,
+) {
+    trainer.update(&board);
+    egui::Window::new(locale.get("underpromotion-trainer"))
+        .default_open(false)
+        .show(contexts.ctx_mut(), |ui| {
+            if ui.button(locale.get("new-drill")).clicked() {
+                let seed = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |time| time.as_nanos() as u64);
+                let themes: Vec<Theme> = Theme::iter().collect();
+                let theme = themes[seed as usize % themes.len()];
+                let drill =
+                    (0..SEEDS).find_map(|offset| generate(theme, seed.wrapping_add(offset)));
+                trainer.not_found = drill.is_none();
+                if let Some(drill) = &drill {
+                    // The drill is for the player alone
+                    engine.set_color(None);
+                    clock.set_time_control(None);
+                    commands.push(GameCommand::LoadGame(Box::new(ResetBoardEvent::new(
+                        drill.fen().clone(),
+                    ))));
+                }
+                trainer.drill = drill;
+                trainer.started = false;
+                trainer.solved = None;
+            }
+            if trainer.not_found {
+                ui.label(locale.get("drill-not-found"));
+            }
+            if let Some(drill) = &trainer.drill {
+                let solution = locale.san(&drill.solution().as_algebraic());
+                match trainer.solved {
+                    None => {
+                        ui.label(locale.format(
+                            "drill-task",
+                            &[("square", &drill.solution().from().to_algebraic())],
+                        ));
+                    }
+                    Some(solved) => {
+                        let key = if solved {
+                            "drill-solved"
+                        } else {
+                            "drill-failed"
+                        };
+                        ui.label(locale.format(key, &[("move", &solution)]));
+                        ui.label(locale.get(match drill.theme() {
+                            Theme::Knight => "drill-knight",
+                            Theme::Rook => "drill-rook",
+                        }));
+                    }
+                }
+            }
+            for theme in Theme::iter() {
+                if let Some((solved, attempted)) = trainer.results.get(&theme) {
+                    ui.label(locale.format(
+                        "drill-results",
+                        &[
+                            ("theme", &theme_name(&theme, &locale)),
+                            ("solved", solved),
+                            ("attempted", attempted),
+                        ],
+                    ));
+                }
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::PieceType;
+
+    use super::*;
+
+    #[test]
+    fn test_trainer_results() {
+        let drill = generate(Theme::Rook, 1).unwrap();
+        let mut trainer = UnderpromotionTrainer {
+            drill: Some(drill.clone()),
+            ..Default::default()
+        };
+        let mut board = ChessBoard::with_position(drill.fen());
+        trainer.update(&board);
+        assert!(trainer.started);
+        assert_eq!(trainer.solved, None);
+
+        let queen = drill.solution().with_promotion(PieceType::Queen);
+        board.apply_move(&queen);
+        trainer.update(&board);
+        assert_eq!(trainer.solved, Some(false));
+        assert_eq!(trainer.results.get(&Theme::Rook), Some(&(0, 1)));
+
+        // A result is only recorded once for each drill
+        trainer.update(&board);
+        assert_eq!(trainer.results.get(&Theme::Rook), Some(&(0, 1)));
+    }
+}
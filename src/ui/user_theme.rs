@@ -0,0 +1,267 @@
+//! Themes added by the user, loaded from the subdirectories of [THEMES_DIRECTORY] when the app
+//! starts, so custom board colors and piece sets can be used without rebuilding.
+//!
+//! Each theme is a directory with a `theme.ron` manifest such as
+//!
+//! ```ron
+//! (
+//!     name: "Walnut",
+//!     light_squares: Some("#f0d9b5"),
+//!     dark_squares: Some("#b58863"),
+//!     pieces: Some("pieces"),
+//! )
+//! ```
+//!
+//! where `pieces` names a directory, relative to the theme's, holding a vector piece set with the
+//! file names of the built-in one, such as `white_king.svg`. Any colors or pieces left out are
+//! the built-in ones. The theme in use is reloaded whenever its files change.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use bevy::log::{info, warn};
+use bevy::prelude::{Color, Local, Res, ResMut, Resource};
+use bevy::time::Time;
+use serde::Deserialize;
+
+use super::board::BoardProperties;
+use super::piece::{VectorPieces, PIECE_FILES};
+use super::theme::Theme;
+
+/// The directory themes are loaded from.
+pub(super) const THEMES_DIRECTORY: &str = "themes";
+
+/// The name of the manifest file of a theme.
+const MANIFEST_FILE: &str = "theme.ron";
+
+/// The time between checks of whether the files of the theme in use have changed.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The manifest of a theme.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    name: String,
+    #[serde(default)]
+    light_squares: Option<String>,
+    #[serde(default)]
+    dark_squares: Option<String>,
+    /// The directory of the piece set, relative to the theme's.
+    #[serde(default)]
+    pieces: Option<String>,
+}
+
+/// Parses the given color, written as hexadecimal RGB with or without a leading `#`.
+fn parse_color(text: &str) -> Result<Color, String> {
+    Color::hex(text.trim()).map_err(|_| format!("invalid color {}", text))
+}
+
+/// Returns the time the most recently changed file in the given directories was changed.
+fn last_modified(directories: &[&Path]) -> Option<SystemTime> {
+    directories
+        .iter()
+        .filter_map(|directory| fs::read_dir(directory).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()
+}
+
+/// A theme loaded from its directory.
+#[derive(Debug, Clone)]
+pub(super) struct UserTheme {
+    name: String,
+    directory: PathBuf,
+    light: Option<Color>,
+    dark: Option<Color>,
+    pieces_directory: Option<PathBuf>,
+    /// The SVG documents of the pieces, in the order of [PIECE_FILES], if the theme has a piece
+    /// set.
+    pieces: Option<Vec<Option<String>>>,
+    /// The time its files were last changed when the theme was loaded.
+    modified: Option<SystemTime>,
+}
+
+impl UserTheme {
+    /// Loads the theme in the given directory.
+    fn load(directory: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(directory.join(MANIFEST_FILE))
+            .map_err(|error| format!("{}: {}", MANIFEST_FILE, error))?;
+        let manifest: Manifest =
+            ron::from_str(&text).map_err(|error| format!("{}: {}", MANIFEST_FILE, error))?;
+        let pieces_directory = manifest.pieces.map(|pieces| directory.join(pieces));
+        let pieces = pieces_directory.as_ref().map(|pieces_directory| {
+            PIECE_FILES
+                .iter()
+                .map(|file| fs::read_to_string(pieces_directory.join(file)).ok())
+                .collect()
+        });
+        let mut theme = UserTheme {
+            name: manifest.name,
+            directory: directory.to_path_buf(),
+            light: manifest
+                .light_squares
+                .as_deref()
+                .map(parse_color)
+                .transpose()?,
+            dark: manifest
+                .dark_squares
+                .as_deref()
+                .map(parse_color)
+                .transpose()?,
+            pieces_directory,
+            pieces,
+            modified: None,
+        };
+        theme.modified = theme.last_modified();
+        Ok(theme)
+    }
+
+    pub(super) fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn has_pieces(&self) -> bool {
+        self.pieces.is_some()
+    }
+
+    /// Returns the time the theme's files were last changed.
+    fn last_modified(&self) -> Option<SystemTime> {
+        let mut directories = vec![self.directory.as_path()];
+        directories.extend(self.pieces_directory.as_deref());
+        last_modified(&directories)
+    }
+}
+
+/// The themes loaded from [THEMES_DIRECTORY].
+#[derive(Resource, Debug)]
+pub(super) struct UserThemes {
+    themes: Vec<UserTheme>,
+    /// The number of times a theme has been reloaded, so the theme in use is applied again.
+    reloads: u32,
+}
+
+impl Default for UserThemes {
+    fn default() -> Self {
+        UserThemes::scan(Path::new(THEMES_DIRECTORY))
+    }
+}
+
+impl UserThemes {
+    /// Loads the themes in the subdirectories of the given directory, in the order of their
+    /// names, skipping any which cannot be loaded.
+    fn scan(directory: &Path) -> Self {
+        let mut directories: Vec<PathBuf> = fs::read_dir(directory)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.is_dir())
+            .collect();
+        directories.sort();
+        let themes = directories
+            .iter()
+            .filter_map(|directory| match UserTheme::load(directory) {
+                Ok(theme) => {
+                    info!(name = theme.name, "Loaded theme");
+                    Some(theme)
+                }
+                Err(error) => {
+                    warn!(directory = %directory.display(), error, "Could not load theme");
+                    None
+                }
+            })
+            .collect();
+        UserThemes { themes, reloads: 0 }
+    }
+
+    pub(super) fn themes(&self) -> &[UserTheme] {
+        &self.themes
+    }
+}
+
+/// Applies the board colors and pieces of the theme in use whenever it is chosen or reloaded.
+pub(super) fn apply_user_theme(
+    theme: Res<Theme>,
+    themes: Res<UserThemes>,
+    mut board_properties: ResMut<BoardProperties>,
+    mut vector_pieces: ResMut<VectorPieces>,
+    mut applied: Local<Option<(Option<usize>, u32)>>,
+) {
+    let selected = theme.user_theme();
+    if applied.replace((selected, themes.reloads)) == Some((selected, themes.reloads)) {
+        return;
+    }
+    let user_theme = selected.and_then(|index| themes.themes.get(index));
+    board_properties.set_square_colors(
+        user_theme.and_then(|user_theme| user_theme.light),
+        user_theme.and_then(|user_theme| user_theme.dark),
+    );
+    vector_pieces.set_pieces(user_theme.and_then(|user_theme| user_theme.pieces.as_deref()));
+}
+
+/// Reloads the theme in use when its files have changed since it was loaded.
+pub(super) fn reload_user_theme(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    mut themes: ResMut<UserThemes>,
+    mut elapsed: Local<Duration>,
+) {
+    *elapsed += time.delta();
+    if *elapsed < RELOAD_INTERVAL {
+        return;
+    }
+    *elapsed = Duration::ZERO;
+    let UserThemes { themes, reloads } = &mut *themes;
+    let Some(user_theme) = theme.user_theme().and_then(|index| themes.get_mut(index)) else {
+        return;
+    };
+    let modified = user_theme.last_modified();
+    if modified <= user_theme.modified {
+        return;
+    }
+    match UserTheme::load(&user_theme.directory) {
+        Ok(loaded) => {
+            info!(name = loaded.name, "Reloaded theme");
+            *user_theme = loaded;
+            *reloads += 1;
+        }
+        Err(error) => {
+            // The theme is kept as it was until its files are fixed
+            warn!(name = user_theme.name, error, "Could not reload theme");
+            user_theme.modified = modified;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_themes() {
+        let root = std::env::temp_dir().join(format!("chess_themes_{}", std::process::id()));
+        let walnut = root.join("walnut");
+        fs::create_dir_all(walnut.join("pieces")).unwrap();
+        fs::write(
+            walnut.join(MANIFEST_FILE),
+            r##"(name: "Walnut", light_squares: Some("#f0d9b5"), pieces: Some("pieces"))"##,
+        )
+        .unwrap();
+        fs::write(walnut.join("pieces").join("white_king.svg"), "<svg/>").unwrap();
+        // Directories without a valid manifest are skipped
+        fs::create_dir_all(root.join("broken")).unwrap();
+        fs::write(root.join("broken").join(MANIFEST_FILE), "(name: 3)").unwrap();
+
+        let themes = UserThemes::scan(&root);
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(themes.themes().len(), 1);
+        let theme = &themes.themes()[0];
+        assert_eq!(theme.name(), "Walnut");
+        assert_eq!(theme.light, Some(Color::rgb_u8(0xf0, 0xd9, 0xb5)));
+        assert_eq!(theme.dark, None);
+        let pieces = theme.pieces.as_ref().unwrap();
+        assert_eq!(pieces[0].as_deref(), Some("<svg/>"));
+        assert_eq!(pieces[1], None);
+
+        assert!(parse_color("#12345").is_err());
+    }
+}
@@ -0,0 +1,245 @@
+//! The voice announcements, turned on in the audio settings, which speak each move as it is made
+//! in the selected language, e.g. "knight, f, three", for playing blindfold.
+//!
+//! A move is spoken as a run of words, each a recording in the voice pack for the language: the
+//! piece, its file for a pawn capture, "takes", the destination square's file letter and rank
+//! number, the piece a pawn promotes to after "promotes", and "check" or "checkmate". Castling is
+//! spoken as "castles short" or "castles long". The voice packs are listed in the voice pack
+//! manifest, and a move made while the last is still spoken cuts it short.
+
+use std::collections::VecDeque;
+
+use bevy::audio::Volume;
+use bevy::prelude::{
+    AssetServer, AudioBundle, Commands, DetectChanges, Entity, Local, PlaybackSettings, Query, Res,
+    ResMut, Resource,
+};
+use serde::Deserialize;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{ChessBoard, PieceType};
+
+use super::audio::AudioSettings;
+use super::locale::Localisation;
+
+/// The manifest listing the available voice packs.
+const VOICE_PACK_MANIFEST: &str = include_str!("../../assets/voice/packs.ron");
+
+/// The words spoken for the ranks, from the first.
+const RANK_WORDS: [&str; 8] = [
+    "one", "two", "three", "four", "five", "six", "seven", "eight",
+];
+
+/// A named set of recordings of the words of moves, spoken in one language.
+#[derive(Debug, Clone, Deserialize)]
+struct VoicePack {
+    name: String,
+    /// The code of the language of the pack, as its locale gives it.
+    language: String,
+    /// The folder in the assets folder holding a recording of each word, named after the word.
+    folder: String,
+}
+
+impl VoicePack {
+    /// Returns the asset path of the recording of the given word.
+    fn recording(&self, word: &str) -> String {
+        format!("{}/{}.ogg", self.folder, word)
+    }
+}
+
+/// The voice packs loaded from the voice pack manifest.
+#[derive(Resource, Debug)]
+pub(super) struct VoicePacks(Vec<VoicePack>);
+
+impl VoicePacks {
+    fn from_manifest(manifest: &str) -> Self {
+        VoicePacks(ron::from_str(manifest).expect("Invalid voice pack manifest."))
+    }
+
+    /// Returns the name of the voice pack for the language with the given code, if there is one.
+    pub(super) fn pack_name(&self, language: &str) -> Option<&str> {
+        self.pack(language).map(|pack| pack.name.as_str())
+    }
+
+    fn pack(&self, language: &str) -> Option<&VoicePack> {
+        self.0.iter().find(|pack| pack.language == language)
+    }
+}
+
+impl Default for VoicePacks {
+    fn default() -> Self {
+        VoicePacks::from_manifest(VOICE_PACK_MANIFEST)
+    }
+}
+
+/// Returns the word spoken for the given piece.
+fn piece_word(piece_type: &PieceType) -> &'static str {
+    match piece_type {
+        PieceType::King => "king",
+        PieceType::Queen => "queen",
+        PieceType::Rook => "rook",
+        PieceType::Bishop => "bishop",
+        PieceType::Knight => "knight",
+        PieceType::Pawn => "pawn",
+        PieceType::Fairy(_) => "piece",
+    }
+}
+
+/// Returns the words spoken for the given move.
+fn move_words(piece_move: &Move) -> Vec<String> {
+    let mut words = Vec::new();
+    if piece_move.is_castle() {
+        words.push("castles".to_string());
+        let short = piece_move.to().file() > piece_move.from().file();
+        words.push(if short { "short" } else { "long" }.to_string());
+    } else {
+        let square = |position| {
+            let text = String::from(position);
+            let (file, rank) = text.split_at(1);
+            let rank = rank
+                .parse::<usize>()
+                .ok()
+                .and_then(|rank| RANK_WORDS.get(rank - 1));
+            [Some(file.to_string()), rank.map(|rank| rank.to_string())]
+        };
+        match piece_move.piece_type() {
+            PieceType::Pawn if piece_move.is_capture() => {
+                words.extend(square(*piece_move.from()).into_iter().take(1).flatten())
+            }
+            PieceType::Pawn => {}
+            piece_type => words.push(piece_word(piece_type).to_string()),
+        }
+        if piece_move.is_capture() {
+            words.push("takes".to_string());
+        }
+        words.extend(square(*piece_move.to()).into_iter().flatten());
+        if let Some(promotion) = piece_move.promotion() {
+            words.push("promotes".to_string());
+            words.push(piece_word(&promotion).to_string());
+        }
+    }
+    if piece_move.is_checkmate() {
+        words.push("checkmate".to_string());
+    } else if piece_move.is_check() {
+        words.push("check".to_string());
+    }
+    words
+}
+
+/// The words of the last move, being spoken one after another.
+#[derive(Resource, Debug, Default)]
+pub(super) struct Announcer {
+    /// The asset paths of the recordings still to play.
+    queue: VecDeque<String>,
+    /// The recording playing, which is despawned once it finishes.
+    playing: Option<Entity>,
+}
+
+/// Queues the words of each move made, in the voice pack for the selected language.
+pub(super) fn announce_moves(
+    mut announcer: ResMut<Announcer>,
+    mut commands: Commands,
+    board: Res<ChessBoard>,
+    settings: Res<AudioSettings>,
+    packs: Res<VoicePacks>,
+    locale: Res<Localisation>,
+    mut moves: Local<usize>,
+) {
+    if !board.is_changed() {
+        return;
+    }
+    let past_moves = board.past_moves();
+    // Only a move made is spoken, not the moves of a game loaded or taken back
+    let made = past_moves.len() == *moves + 1;
+    *moves = past_moves.len();
+    let pack = packs.pack(locale.code());
+    let (Some(piece_move), Some(pack), true, Some(_)) =
+        (past_moves.last(), pack, made, settings.voice_volume())
+    else {
+        return;
+    };
+    if let Some(entity) = announcer.playing.take() {
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.despawn();
+        }
+    }
+    announcer.queue = move_words(piece_move)
+        .iter()
+        .map(|word| pack.recording(word))
+        .collect();
+}
+
+/// Plays the next word of the move being announced once the last has finished.
+pub(super) fn play_announcements(
+    mut announcer: ResMut<Announcer>,
+    mut commands: Commands,
+    recordings: Query<Entity>,
+    settings: Res<AudioSettings>,
+    asset_server: Res<AssetServer>,
+) {
+    if announcer
+        .playing
+        .is_some_and(|entity| recordings.contains(entity))
+    {
+        return;
+    }
+    let Some(path) = announcer.queue.pop_front() else {
+        announcer.playing = None;
+        return;
+    };
+    let Some(volume) = settings.voice_volume() else {
+        announcer.queue.clear();
+        return;
+    };
+    let entity = commands
+        .spawn(AudioBundle {
+            source: asset_server.load(path),
+            settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume)),
+        })
+        .id();
+    announcer.playing = Some(entity);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fen::Fen;
+
+    use super::*;
+
+    fn words(fen: &str, text: &str) -> Vec<String> {
+        let mut board = ChessBoard::with_position(&Fen::parse(fen).unwrap());
+        board.apply_move(&Move::from_algebraic(text, &board).unwrap());
+        move_words(board.past_moves().last().unwrap())
+    }
+
+    #[test]
+    fn test_move_words() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(words(start, "Nf3"), ["knight", "f", "three"]);
+        assert_eq!(words(start, "e4"), ["e", "four"]);
+        let scandinavian = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        assert_eq!(words(scandinavian, "exd5"), ["e", "takes", "d", "five"]);
+        assert_eq!(
+            words("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8=Q+"),
+            ["b", "eight", "promotes", "queen", "check"]
+        );
+        assert_eq!(
+            words("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1", "O-O-O"),
+            ["castles", "long"]
+        );
+    }
+
+    #[test]
+    fn test_voice_packs() {
+        let packs =
+            VoicePacks::from_manifest(r#"[(name: "Anna", language: "de", folder: "voice/de")]"#);
+        assert_eq!(packs.pack_name("de"), Some("Anna"));
+        assert_eq!(packs.pack_name("en"), None);
+        assert_eq!(
+            packs.pack("de").unwrap().recording("knight"),
+            "voice/de/knight.ogg"
+        );
+        // The manifest shipped with the app can be read
+        VoicePacks::default();
+    }
+}
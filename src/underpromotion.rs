@@ -0,0 +1,285 @@
+//! Underpromotion drills, positions in which promoting a pawn to a knight or a rook is the only
+//! move that wins.
+//!
+//! Positions are generated at random from a pattern for each theme and kept only when a short
+//! search confirms the intended promotion is the single best one and promoting to a queen is
+//! worse.
+
+use strum_macros::EnumIter;
+
+use crate::chess_board::r#move::Move;
+use crate::chess_board::{BoardPosition, ChessBoard, PieceColor, PieceType, BOARD_SIZE};
+use crate::engine::evaluate_position;
+use crate::fen::Fen;
+use crate::random::Random;
+
+/// The depth each promotion is searched to, after the promotion itself.
+const DEPTH: u32 = 2;
+/// The evaluation in centipawns beyond which a player is taken to be winning.
+const WINNING: i32 = 200;
+/// The number of positions tried before giving up on a drill.
+const ATTEMPTS: usize = 2000;
+
+/// The idea behind a drill.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+pub enum Theme {
+    /// Promoting to a knight forks the king and queen with check, where promoting to anything
+    /// else lets the queen mate on the back rank.
+    Knight,
+    /// Promoting to a queen stalemates, where promoting to a rook wins.
+    Rook,
+}
+
+impl Theme {
+    /// Returns the piece the pawn should promote to.
+    pub fn piece_type(&self) -> PieceType {
+        match self {
+            Theme::Knight => PieceType::Knight,
+            Theme::Rook => PieceType::Rook,
+        }
+    }
+}
+
+/// A position with white to move in which a single underpromotion wins.
+#[derive(Debug, Clone)]
+pub struct Drill {
+    fen: Fen,
+    solution: Move,
+    theme: Theme,
+}
+
+impl Drill {
+    /// Returns the position of the drill.
+    pub fn fen(&self) -> &Fen {
+        &self.fen
+    }
+
+    /// Returns the winning promotion.
+    pub fn solution(&self) -> &Move {
+        &self.solution
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// Returns whether the given move solves the drill, whether or not it is known to give check.
+    pub fn is_solved_by(&self, piece_move: &Move) -> bool {
+        piece_move.from() == self.solution.from()
+            && piece_move.to() == self.solution.to()
+            && piece_move.promotion() == self.solution.promotion()
+    }
+}
+
+/// The result of a move for the player making it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
+
+/// Returns the outcome of the given move according to a short search of the position after it.
+fn outcome(board: &ChessBoard, piece_move: &Move) -> Outcome {
+    let mut after = board.clone();
+    after.apply_move(piece_move);
+    match -evaluate_position(&after, DEPTH) {
+        evaluation if evaluation > WINNING => Outcome::Win,
+        evaluation if evaluation < -WINNING => Outcome::Loss,
+        _ => Outcome::Draw,
+    }
+}
+
+/// Returns the promotion of the pawn on the given square which is the only one to win, if
+/// promoting to a queen does not. The pawn must have a single square to promote on, so that only
+/// the choice of piece matters.
+fn winning_underpromotion(board: &ChessBoard, pawn: &BoardPosition) -> Option<Move> {
+    let promotions: Vec<Move> = board
+        .legal_moves_iter()
+        .filter(|piece_move| piece_move.from() == pawn)
+        .collect();
+    if promotions.is_empty()
+        || promotions.iter().any(|piece_move| {
+            piece_move.promotion().is_none() || piece_move.to() != promotions[0].to()
+        })
+    {
+        return None;
+    }
+    let outcomes: Vec<(Move, Outcome)> = promotions
+        .into_iter()
+        .map(|piece_move| (piece_move, outcome(board, &piece_move)))
+        .collect();
+    let mut winners = outcomes
+        .iter()
+        .filter(|(_, outcome)| *outcome == Outcome::Win);
+    match (winners.next(), winners.next()) {
+        (Some((piece_move, _)), None) if piece_move.promotion() != Some(PieceType::Queen) => {
+            Some(*piece_move)
+        }
+        _ => None,
+    }
+}
+
+/// Returns a random square not among the given ones, on one of the given ranks.
+pub fn random_square(
+    random: &mut Random,
+    ranks: std::ops::Range<usize>,
+    taken: &[BoardPosition],
+) -> BoardPosition {
+    loop {
+        let position = BoardPosition::new(
+            ranks.start + random.below(ranks.len()),
+            random.below(BOARD_SIZE),
+        );
+        if !taken.contains(&position) {
+            return position;
+        }
+    }
+}
+
+/// Returns the squares a knight on the given square attacks.
+fn knight_squares(position: &BoardPosition) -> Vec<BoardPosition> {
+    let (rank, file) = (*position.rank() as i32, *position.file() as i32);
+    [
+        (1, 2),
+        (2, 1),
+        (2, -1),
+        (1, -2),
+        (-1, -2),
+        (-2, -1),
+        (-2, 1),
+        (-1, 2),
+    ]
+    .into_iter()
+    .map(|(ranks, files)| (rank + ranks, file + files))
+    .filter(|(rank, file)| {
+        (0..BOARD_SIZE as i32).contains(rank) && (0..BOARD_SIZE as i32).contains(file)
+    })
+    .map(|(rank, file)| BoardPosition::new(rank as usize, file as usize))
+    .collect()
+}
+
+/// Places pieces at random following the pattern of the given theme, returning them together
+/// with the square of the pawn to promote.
+fn candidate(
+    theme: &Theme,
+    random: &mut Random,
+) -> (Vec<(PieceColor, PieceType, BoardPosition)>, BoardPosition) {
+    let pawn = BoardPosition::new(1, random.below(BOARD_SIZE));
+    let promotion = BoardPosition::new(0, *pawn.file());
+    let mut pieces = vec![(PieceColor::White, PieceType::Pawn, pawn)];
+    match theme {
+        Theme::Knight => {
+            // The white king is shut in on the back rank behind its pawns, on either wing
+            let files = match random.below(2) {
+                0 => [5, 6, 7],
+                _ => [2, 1, 0],
+            };
+            pieces.push((
+                PieceColor::White,
+                PieceType::King,
+                BoardPosition::new(BOARD_SIZE - 1, files[1]),
+            ));
+            for file in files {
+                pieces.push((
+                    PieceColor::White,
+                    PieceType::Pawn,
+                    BoardPosition::new(BOARD_SIZE - 2, file),
+                ));
+            }
+            // The black king and queen stand where a knight on the promotion square forks them
+            let mut forked = knight_squares(&promotion);
+            let king = forked.swap_remove(random.below(forked.len()));
+            let queen = forked.swap_remove(random.below(forked.len()));
+            pieces.push((PieceColor::Black, PieceType::King, king));
+            pieces.push((PieceColor::Black, PieceType::Queen, queen));
+        }
+        Theme::Rook => {
+            // The black king is cornered near the promotion square
+            let black_king = random_square(random, 0..3, &[pawn, promotion]);
+            let white_king = random_square(random, 0..4, &[pawn, promotion, black_king]);
+            pieces.push((PieceColor::Black, PieceType::King, black_king));
+            pieces.push((PieceColor::White, PieceType::King, white_king));
+        }
+    }
+    (pieces, pawn)
+}
+
+/// Returns whether promoting to a queen on the given move stalemates.
+fn queen_stalemates(board: &ChessBoard, piece_move: &Move) -> bool {
+    let mut after = board.clone();
+    after.apply_move(&piece_move.with_promotion(PieceType::Queen));
+    !after.in_check(&PieceColor::Black) && after.legal_moves_iter().next().is_none()
+}
+
+/// Generates a drill on the given theme from the given seed, or None if no position was found.
+pub fn generate(theme: Theme, seed: u64) -> Option<Drill> {
+    let mut random = Random::new(seed);
+    for _ in 0..ATTEMPTS {
+        let (pieces, pawn) = candidate(&theme, &mut random);
+        let fen = Fen::with_pieces(&pieces, PieceColor::White);
+        let board = ChessBoard::with_position(&fen);
+        // The player not to move cannot be in check
+        if board.in_check(&PieceColor::Black) {
+            continue;
+        }
+        // Searching is slow, so positions which cannot fit the theme are dropped beforehand
+        let destination = BoardPosition::new(0, *pawn.file());
+        let push = Move::from_board(pawn, destination, &board);
+        let fits = match theme {
+            Theme::Knight => board.valid_move(&push, &Some(PieceColor::White), &true),
+            Theme::Rook => {
+                board.valid_move(&push, &Some(PieceColor::White), &true)
+                    && queen_stalemates(&board, &push)
+            }
+        };
+        if !fits {
+            continue;
+        }
+        match winning_underpromotion(&board, &pawn) {
+            Some(solution) if solution.promotion() == Some(theme.piece_type()) => {
+                return Some(Drill {
+                    fen,
+                    solution,
+                    theme,
+                });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::*;
+
+    #[test]
+    fn test_winning_underpromotion() {
+        // Promoting to a queen stalemates the king in the corner
+        let board = ChessBoard::with_position(&Fen::from_string("8/k1P5/8/K7/8/8/8/8 w - - 0 1"));
+        let solution = winning_underpromotion(&board, &BoardPosition::new(1, 2)).unwrap();
+        assert_eq!(solution.as_algebraic(), "c8=R");
+
+        // Any promotion wins here
+        let board = ChessBoard::with_position(&Fen::from_string("8/2P5/8/8/8/8/8/k1K5 w - - 0 1"));
+        assert_eq!(
+            winning_underpromotion(&board, &BoardPosition::new(1, 2)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_generate() {
+        for theme in Theme::iter() {
+            let drill = generate(theme, 1).unwrap();
+            assert_eq!(drill.solution().promotion(), Some(theme.piece_type()));
+            let board = ChessBoard::with_position(drill.fen());
+            assert!(board.valid_move(drill.solution(), &Some(PieceColor::White), &true));
+            assert!(drill.is_solved_by(drill.solution()));
+            assert!(!drill.is_solved_by(&drill.solution().with_promotion(PieceType::Queen)));
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Zobrist hashing, which gives each position a 64-bit key for looking it up in tables.
+//!
+//! A key is the exclusive or of a random number for each piece on its square, for the player to
+//! move, for each castling right, for the en passant file, for the variant, for the duck and for
+//! the size of boards other than 8x8, so positions reached by different move orders have the same
+//! key. The numbers are generated from a fixed seed, so keys are the same each time the app runs.
+
+use std::sync::OnceLock;
+
+use crate::chess_board::fairy::MAX_FAIRY_PIECES;
+use crate::chess_board::{ChessBoard, PieceColor, PieceType, Variant, BOARD_SIZE, MAX_FILES};
+use crate::random::Random;
+
+/// The seed the random numbers are generated from.
+const SEED: u64 = 0x5eed_c4e5_5b0a_4d00;
+
+/// The number of squares on the largest board.
+const SQUARES: usize = BOARD_SIZE * MAX_FILES;
+
+/// The random numbers each part of a position contributes to its key.
+struct Keys {
+    /// Indexed by color, piece type and square.
+    pieces: [[[u64; SQUARES]; 6]; 2],
+    black_to_move: u64,
+    /// Indexed by color, then kingside and queenside.
+    castling: [[u64; 2]; 2],
+    en_passant: [u64; MAX_FILES],
+    variants: [u64; 3],
+    duck: [u64; SQUARES],
+    /// Indexed by color, fairy piece and square.
+    fairy: [[[u64; SQUARES]; MAX_FAIRY_PIECES]; 2],
+    /// Indexed by the number of ranks and files less one, for boards other than 8x8.
+    sizes: [[u64; MAX_FILES]; BOARD_SIZE],
+}
+
+impl Keys {
+    fn generate() -> Self {
+        let mut random = Random::new(SEED);
+        let mut keys = Keys {
+            pieces: [[[0; SQUARES]; 6]; 2],
+            black_to_move: random.next(),
+            castling: [[0; 2]; 2],
+            en_passant: [0; MAX_FILES],
+            variants: [0; 3],
+            duck: [0; SQUARES],
+            fairy: [[[0; SQUARES]; MAX_FAIRY_PIECES]; 2],
+            sizes: [[0; MAX_FILES]; BOARD_SIZE],
+        };
+        for key in keys
+            .pieces
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .chain(keys.castling.iter_mut().flatten())
+            .chain(keys.en_passant.iter_mut())
+            .chain(keys.variants.iter_mut())
+            .chain(keys.duck.iter_mut())
+            .chain(keys.fairy.iter_mut().flatten().flatten())
+            .chain(keys.sizes.iter_mut().flatten())
+        {
+            *key = random.next();
+        }
+        keys
+    }
+}
+
+fn keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(Keys::generate)
+}
+
+/// Returns the Zobrist key of the position on the given board.
+pub fn zobrist_hash(board: &ChessBoard) -> u64 {
+    let keys = keys();
+    let fen = board.to_fen();
+    let mut hash = 0;
+    for (rank, squares) in fen.piece_placement().iter().enumerate() {
+        for (file, square) in squares.iter().enumerate() {
+            let position = rank * MAX_FILES + file;
+            hash ^= match square {
+                Some((color, PieceType::Fairy(index))) => {
+                    keys.fairy[*color as usize][*index as usize][position]
+                }
+                Some((color, piece_type)) => {
+                    keys.pieces[*color as usize][piece_type.index().unwrap()][position]
+                }
+                None => 0,
+            };
+        }
+    }
+    if *fen.active_color() == PieceColor::Black {
+        hash ^= keys.black_to_move;
+    }
+    let rights = fen.castling_rights();
+    for (color, rights) in [rights.white, rights.black].iter().enumerate() {
+        for (side, right) in rights.iter().enumerate() {
+            if *right {
+                hash ^= keys.castling[color][side];
+            }
+        }
+    }
+    if let Some(square) = fen.ep_target_square() {
+        hash ^= keys.en_passant[*square.file()];
+    }
+    hash ^= keys.variants[match board.variant() {
+        Variant::Standard => 0,
+        Variant::Duck => 1,
+        Variant::Antichess => 2,
+    }];
+    if let Some(duck) = board.duck() {
+        hash ^= keys.duck[duck.rank() * MAX_FILES + duck.file()];
+    }
+    let size = board.size();
+    if !size.is_standard() {
+        hash ^= keys.sizes[size.ranks() - 1][size.files() - 1];
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chess_board::r#move::Move;
+    use crate::fen::Fen;
+
+    use super::*;
+
+    fn play(moves: &[&str]) -> ChessBoard {
+        let mut board = ChessBoard::with_position(&Fen::default());
+        for text in moves {
+            let piece_move = Move::from_algebraic(text, &board).unwrap();
+            board.apply_move(&piece_move);
+        }
+        board
+    }
+
+    #[test]
+    fn test_zobrist_hash() {
+        // The same position reached by a different move order has the same key
+        assert_eq!(
+            zobrist_hash(&play(&["Nf3", "Nf6", "d4"])),
+            zobrist_hash(&play(&["d4", "Nf6", "Nf3"]))
+        );
+        assert_ne!(
+            zobrist_hash(&play(&["e4"])),
+            zobrist_hash(&play(&["e3", "e6", "e4"]))
+        );
+        let start = ChessBoard::with_position(&Fen::default());
+        assert_ne!(
+            zobrist_hash(&start),
+            zobrist_hash(&start.clone().with_variant(Variant::Antichess))
+        );
+        // Losing the right to castle changes the key
+        assert_ne!(
+            zobrist_hash(&play(&["Nf3", "Nf6", "Rg1", "Ng8", "Rh1", "Nf6"])),
+            zobrist_hash(&play(&["Nf3", "Nf6"]))
+        );
+    }
+}